@@ -0,0 +1,118 @@
+//! Compares serial and parallel codegen on a generated program with 2,000
+//! small functions spread over 16 modules.
+//!
+//! Run with `cargo bench --bench parallel_codegen`.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+use inkwell::targets::TargetMachine;
+
+const MODULES: usize = 16;
+const FNS_PER_MODULE: usize = 125;
+const RUNS: usize = 3;
+
+/// Writes the program to `dir`, returning the path of the root module.
+fn write_program(dir: &Path) -> PathBuf {
+    std::fs::create_dir_all(dir).unwrap();
+
+    let mut main = String::from("use std::int::int_to_string\nuse std::io::println\n\n");
+
+    for module in 0..MODULES {
+        writeln!(main, "mod m{module}").unwrap();
+
+        let mut source = String::new();
+
+        for index in 0..FNS_PER_MODULE {
+            writeln!(
+                source,
+                "pub fn f{index}(x: Uint64) -> Uint64 {{\n    let y = x * 3 + {}\n    y % 1000\n}}\n",
+                module + index
+            )
+            .unwrap();
+        }
+
+        source.push_str("pub fn chain(x: Uint64) -> Uint64 {\n    let x0 = x\n");
+        for index in 0..FNS_PER_MODULE {
+            writeln!(
+                source,
+                "    let x{} = m{module}::f{index}(x{index})",
+                index + 1
+            )
+            .unwrap();
+        }
+        writeln!(source, "    x{FNS_PER_MODULE}\n}}").unwrap();
+
+        std::fs::write(dir.join(format!("m{module}.crane")), source).unwrap();
+    }
+
+    main.push_str("\nfn main() {\n");
+    for module in 0..MODULES {
+        writeln!(main, "    println(int_to_string(m{module}::chain(1)))").unwrap();
+    }
+    main.push_str("}\n");
+
+    let main_path = dir.join("main.crane");
+    std::fs::write(&main_path, main).unwrap();
+
+    main_path
+}
+
+/// Returns the fastest of several builds of the program with the given
+/// number of jobs.
+fn time_build(input: &Path, output_dir: &Path, jobs: usize) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let params = CompileParams {
+                input: Input::File(input.to_path_buf()),
+                options: CompileOptions {
+                    target_triple: TargetMachine::get_default_triple()
+                        .as_str()
+                        .to_string_lossy()
+                        .into_owned(),
+                    output_dir: output_dir.to_path_buf(),
+                    jobs,
+                    ..CompileOptions::default()
+                },
+            };
+
+            let mut stderr = Vec::new();
+
+            let start = Instant::now();
+
+            Compiler::new()
+                .compile(&mut stderr, params)
+                .unwrap_or_else(|_| panic!("{}", String::from_utf8_lossy(&stderr)));
+
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let dir = std::env::temp_dir()
+        .join("crane-bench")
+        .join("parallel_codegen");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let input = write_program(&dir.join("src"));
+
+    let jobs = std::thread::available_parallelism().map_or(1, |jobs| jobs.get());
+
+    let serial = time_build(&input, &dir.join("serial"), 1);
+    let parallel = time_build(&input, &dir.join("parallel"), jobs);
+
+    println!(
+        "{} functions in {MODULES} modules",
+        MODULES * FNS_PER_MODULE
+    );
+    println!("serial:   {serial:?}");
+    println!("parallel: {parallel:?} ({jobs} jobs)");
+    println!(
+        "speedup:  {:.2}x",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -0,0 +1,70 @@
+//! Compares serial and parallel parsing of 16 generated files with 2,000
+//! small functions each.
+//!
+//! Run with `cargo bench --bench parallel_parse`.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crane::ast::{AstArena, SourceFile};
+use crane::compiler::{default_threads, parse_parallel};
+
+const FILES: usize = 16;
+const FNS_PER_FILE: usize = 2_000;
+const RUNS: usize = 5;
+
+/// Returns the generated files.
+fn generate_files() -> Vec<SourceFile> {
+    (0..FILES)
+        .map(|file| {
+            let mut source = String::new();
+
+            for index in 0..FNS_PER_FILE {
+                writeln!(
+                    source,
+                    "pub fn f{index}(x: Uint64) -> Uint64 {{\n    let y = x * 3 + {}\n    if y > 100 {{\n        y % 1000\n    }} else {{\n        f{index}(y + 1)\n    }}\n}}\n",
+                    file + index
+                )
+                .unwrap();
+            }
+
+            SourceFile::new(format!("m{file}.crane"), source)
+        })
+        .collect()
+}
+
+/// Returns the fastest of several parses of the files on the given number of
+/// threads.
+fn time_parse(files: &[SourceFile], threads: usize) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let arena = AstArena::new();
+
+            let start = Instant::now();
+
+            for result in parse_parallel(files, &arena, threads) {
+                result.expect("the generated files parse");
+            }
+
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let files = generate_files();
+
+    let threads = default_threads();
+
+    let serial = time_parse(&files, 1);
+    let parallel = time_parse(&files, threads);
+
+    println!("{} functions in {FILES} files", FILES * FNS_PER_FILE);
+    println!("serial:   {serial:?}");
+    println!("parallel: {parallel:?} ({threads} threads)");
+    println!(
+        "speedup:  {:.2}x",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
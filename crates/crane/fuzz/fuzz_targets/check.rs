@@ -0,0 +1,25 @@
+//! Lexes, parses, and type checks arbitrary input, which must be reported as
+//! diagnostics rather than crash the compiler.
+//!
+//! Run with `cargo +nightly fuzz run check` from `crates/crane`.
+
+#![no_main]
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let params = CompileParams {
+        input: Input::String {
+            filename: "fuzz.crane".into(),
+            input: String::from_utf8_lossy(data).into_owned(),
+        },
+        options: CompileOptions {
+            threads: 1,
+            ..CompileOptions::default()
+        },
+    };
+
+    // Errors in the input are expected, so only a panic is a failure.
+    let _ = Compiler::new().check(&mut std::io::sink(), params);
+});
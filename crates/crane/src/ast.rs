@@ -1,14 +1,21 @@
+mod arena;
 mod ident;
+mod source_map;
 mod source_span;
 mod span;
+mod symbol;
 mod typed;
 mod untyped;
 
 pub mod keywords;
+pub mod printer;
 pub mod visitor;
 
+pub use arena::*;
 pub use ident::*;
+pub use source_map::*;
 pub use source_span::*;
 pub use span::*;
+pub use symbol::*;
 pub use typed::*;
 pub use untyped::*;
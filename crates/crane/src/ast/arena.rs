@@ -0,0 +1,279 @@
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::sync::Mutex;
+
+use crate::ast::*;
+
+/// The number of values in the first chunk of a [`TypedArena`].
+const FIRST_CHUNK_LEN: usize = 256;
+
+/// A bump allocator for values of a single type.
+///
+/// The values are allocated in chunks, each twice as long as the last, so
+/// allocating a value is usually just a write into the current chunk. They're
+/// dropped along with the arena.
+///
+/// The values may borrow from each other, and from the arena itself. The
+/// borrow checker only allows that if dropping the arena doesn't need their
+/// lifetimes to still be alive, so the chunks are dropped by [`Chunks`], whose
+/// type doesn't name the type of the values.
+///
+/// The chunks are behind a [`Mutex`], so that files can be parsed into the
+/// same arena on several threads at once.
+struct TypedArena<T> {
+    chunks: Mutex<Chunks>,
+
+    /// The values are owned by `chunks`, so as far as the drop checker is
+    /// concerned, the arena doesn't own any `T`.
+    values: PhantomData<fn(T) -> T>,
+}
+
+// SAFETY: The arena owns its values and hands each of them out once, like a
+// `Mutex<Vec<T>>` would.
+unsafe impl<T: Send> Send for TypedArena<T> {}
+unsafe impl<T: Send> Sync for TypedArena<T> {}
+
+impl<T> TypedArena<T> {
+    fn new() -> Self {
+        Self {
+            chunks: Mutex::new(Chunks {
+                chunks: Vec::new(),
+                drop_chunk: drop_chunk::<T>,
+            }),
+            values: PhantomData,
+        }
+    }
+
+    // Each call hands out a `&mut` to a value that was just moved into the
+    // arena, which nothing else refers to, so the `&mut`s never alias even
+    // though they come from a shared reference to the arena.
+    #[allow(clippy::mut_from_ref)]
+    fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.lock().unwrap();
+
+        let is_full = chunks
+            .chunks
+            .last()
+            .is_none_or(|chunk| chunk.len == chunk.capacity);
+
+        if is_full {
+            let capacity = chunks
+                .chunks
+                .last()
+                .map_or(FIRST_CHUNK_LEN, |chunk| chunk.capacity * 2);
+
+            let mut values = ManuallyDrop::new(Vec::<T>::with_capacity(capacity));
+
+            chunks.chunks.push(Chunk {
+                ptr: values.as_mut_ptr().cast(),
+                len: 0,
+                capacity: values.capacity(),
+            });
+        }
+
+        let chunk = chunks.chunks.last_mut().unwrap();
+
+        // SAFETY: The chunk was allocated for `T`s and isn't full, so the slot
+        // after its last value is in bounds and unused. A chunk never
+        // reallocates, so the value stays where it is until the arena is
+        // dropped.
+        unsafe {
+            let slot = chunk.ptr.cast::<T>().add(chunk.len);
+            slot.write(value);
+            chunk.len += 1;
+
+            &mut *slot
+        }
+    }
+}
+
+/// The chunks of a [`TypedArena`].
+struct Chunks {
+    chunks: Vec<Chunk>,
+
+    /// Drops the values in a chunk, and frees it.
+    drop_chunk: unsafe fn(&Chunk),
+}
+
+impl Drop for Chunks {
+    fn drop(&mut self) {
+        for chunk in &self.chunks {
+            // SAFETY: The chunks hold the type of values that `drop_chunk` is
+            // for, and nothing refers to them once the arena is dropped.
+            // Dropping a value doesn't read the values it borrows, which may
+            // have been dropped before it, since none of the types in the AST
+            // implement `Drop` themselves, as `assert_no_drop_impls!` checks.
+            unsafe { (self.drop_chunk)(chunk) }
+        }
+    }
+}
+
+/// A chunk of values, which are the parts of a `Vec` that never reallocates.
+struct Chunk {
+    ptr: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+/// Drops the values in the given chunk of `T`s, and frees it.
+///
+/// # Safety
+///
+/// The chunk must have been allocated for `T`s, and nothing may refer to its
+/// values anymore.
+unsafe fn drop_chunk<T>(chunk: &Chunk) {
+    drop(unsafe { Vec::from_raw_parts(chunk.ptr.cast::<T>(), chunk.len, chunk.capacity) });
+}
+/// The arena that the expressions of the AST are allocated in, rather than
+/// each in a [`Box`] of its own.
+///
+/// The AST borrows from the arena, so the arena must outlive it:
+///
+/// ```ignore
+/// let arena = AstArena::new();
+/// let items = Parser::new(Lexer::new(source), &arena).parse()?;
+/// ```
+pub struct AstArena<'a> {
+    exprs: TypedArena<Expr<'a>>,
+    ty_exprs: TypedArena<TyExpr<'a>>,
+}
+
+impl Default for AstArena<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> AstArena<'a> {
+    pub fn new() -> Self {
+        Self {
+            exprs: TypedArena::new(),
+            ty_exprs: TypedArena::new(),
+        }
+    }
+
+    /// Allocates the given expression in the arena.
+    pub fn alloc_expr(&'a self, expr: Expr<'a>) -> &'a mut Expr<'a> {
+        self.exprs.alloc(expr)
+    }
+
+    /// Allocates the given typed expression in the arena.
+    pub fn alloc_ty_expr(&'a self, expr: TyExpr<'a>) -> &'a TyExpr<'a> {
+        self.ty_exprs.alloc(expr)
+    }
+}
+
+/// Fails to compile if any of the given types implements `Drop`.
+///
+/// The blanket implementation of `NoDropImpl` conflicts with the one for a
+/// type that implements `Drop`, so the assertion costs nothing at runtime.
+macro_rules! assert_no_drop_impls {
+    ($($ty:ty),* $(,)?) => {
+        #[allow(dead_code)]
+        trait NoDropImpl {}
+
+        #[allow(drop_bounds)]
+        impl<T: Drop> NoDropImpl for T {}
+
+        $(impl NoDropImpl for $ty {})*
+    };
+}
+
+// The types that the arena's values are made up of, which borrow from the
+// arena, so their `Drop` could read values that are already dropped.
+assert_no_drop_impls!(
+    Expr<'_>,
+    ExprKind<'_>,
+    MatchArm<'_>,
+    StructExpr<'_>,
+    StructExprField<'_>,
+    ForExpr<'_>,
+    FieldExpr<'_>,
+    MethodCallExpr<'_>,
+    MacroCall<'_>,
+    FnTy<'_>,
+    TyKind<'_>,
+    Ty<'_>,
+    Stmt<'_>,
+    StmtKind<'_>,
+    Local<'_>,
+    LocalKind<'_>,
+    Item<'_>,
+    ItemKind<'_>,
+    Fn<'_>,
+    FnDecl<'_>,
+    FnParam<'_>,
+    FnReturnTy<'_>,
+    FieldDecl<'_>,
+    VariantData<'_>,
+    Variant<'_>,
+    StructDecl<'_>,
+    UnionDecl<'_>,
+    ImplDecl<'_>,
+    ConstDecl<'_>,
+    ModuleDecl<'_>,
+    Module<'_>,
+    Package<'_>,
+    TyExpr<'_>,
+    TyExprKind<'_>,
+    TyMatchArm<'_>,
+    TyForExpr<'_>,
+    TyStmt<'_>,
+    TyStmtKind<'_>,
+    TyLocal<'_>,
+    TyLocalKind<'_>,
+    TyItem<'_>,
+    TyItemKind<'_>,
+    TyFn<'_>,
+    TyImplDecl<'_>,
+    TyConst<'_>,
+    TyModule<'_>,
+    TyPackage<'_>,
+);
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Counts how many times it's been dropped in the [`Cell`] it borrows.
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_values_outlive_their_chunk_filling_up() {
+        let arena = TypedArena::new();
+
+        let values = (0..FIRST_CHUNK_LEN * 3)
+            .map(|value| &*arena.alloc(value.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(arena.chunks.lock().unwrap().chunks.len(), 2);
+
+        for (value, allocated) in values.iter().enumerate() {
+            assert_eq!(**allocated, value.to_string());
+        }
+    }
+
+    #[test]
+    fn test_values_are_dropped_with_the_arena() {
+        let drops = Cell::new(0);
+        let arena = TypedArena::new();
+
+        for _ in 0..FIRST_CHUNK_LEN * 3 {
+            arena.alloc(DropCounter(&drops));
+        }
+
+        assert_eq!(drops.get(), 0);
+
+        drop(arena);
+
+        assert_eq!(drops.get(), FIRST_CHUNK_LEN * 3);
+    }
+}
@@ -1,14 +1,12 @@
 use std::hash::{Hash, Hasher};
 
+use crate::ast::{Span, Symbol};
 use serde::{Deserialize, Serialize};
-use smol_str::SmolStr;
-
-use crate::ast::Span;
 
 /// An identifier.
 #[derive(Debug, Eq, Clone, Serialize, Deserialize)]
 pub struct Ident {
-    pub name: SmolStr,
+    pub name: Symbol,
     pub span: Span,
 }
 
@@ -37,10 +35,10 @@ mod tests {
 
     #[test]
     fn partial_eq_impl_ignores_spans() {
-        let name = SmolStr::new_inline("foo");
+        let name = Symbol::intern("foo");
 
         let ident_a = Ident {
-            name: name.clone(),
+            name,
             span: Span::new(0, 3),
         };
 
@@ -66,10 +64,10 @@ mod tests {
             hasher.finish()
         }
 
-        let name = SmolStr::new_inline("foo");
+        let name = Symbol::intern("foo");
 
         let ident_a = Ident {
-            name: name.clone(),
+            name,
             span: Span::new(0, 3),
         };
 
@@ -1,38 +1,113 @@
-use smol_str::SmolStr;
+use crate::ast::{Ident, Symbol, DUMMY_SPAN};
 
-use crate::ast::{Ident, DUMMY_SPAN};
+/// The text of every keyword, which is interned before any other string so
+/// that the keywords' [`Symbol`]s are constants.
+pub(crate) const KEYWORDS: [&str; 21] = [
+    "as", "asm", "comptime", "const", "else", "extern", "fn", "for", "if", "impl", "in", "let",
+    "match", "mod", "pub", "struct", "typeof", "union", "unsafe", "use", "while",
+];
+
+pub const AS: Ident = Ident {
+    name: Symbol::keyword(0),
+    span: DUMMY_SPAN,
+};
+
+pub const ASM: Ident = Ident {
+    name: Symbol::keyword(1),
+    span: DUMMY_SPAN,
+};
+
+pub const COMPTIME: Ident = Ident {
+    name: Symbol::keyword(2),
+    span: DUMMY_SPAN,
+};
+
+pub const CONST: Ident = Ident {
+    name: Symbol::keyword(3),
+    span: DUMMY_SPAN,
+};
+
+pub const ELSE: Ident = Ident {
+    name: Symbol::keyword(4),
+    span: DUMMY_SPAN,
+};
+
+pub const EXTERN: Ident = Ident {
+    name: Symbol::keyword(5),
+    span: DUMMY_SPAN,
+};
 
 pub const FN: Ident = Ident {
-    name: SmolStr::new_inline("fn"),
+    name: Symbol::keyword(6),
+    span: DUMMY_SPAN,
+};
+
+pub const FOR: Ident = Ident {
+    name: Symbol::keyword(7),
+    span: DUMMY_SPAN,
+};
+
+pub const IF: Ident = Ident {
+    name: Symbol::keyword(8),
+    span: DUMMY_SPAN,
+};
+
+pub const IMPL: Ident = Ident {
+    name: Symbol::keyword(9),
+    span: DUMMY_SPAN,
+};
+
+pub const IN: Ident = Ident {
+    name: Symbol::keyword(10),
     span: DUMMY_SPAN,
 };
 
 pub const LET: Ident = Ident {
-    name: SmolStr::new_inline("let"),
+    name: Symbol::keyword(11),
+    span: DUMMY_SPAN,
+};
+
+pub const MATCH: Ident = Ident {
+    name: Symbol::keyword(12),
     span: DUMMY_SPAN,
 };
 
 pub const MOD: Ident = Ident {
-    name: SmolStr::new_inline("mod"),
+    name: Symbol::keyword(13),
     span: DUMMY_SPAN,
 };
 
 pub const PUB: Ident = Ident {
-    name: SmolStr::new_inline("pub"),
+    name: Symbol::keyword(14),
     span: DUMMY_SPAN,
 };
 
 pub const STRUCT: Ident = Ident {
-    name: SmolStr::new_inline("struct"),
+    name: Symbol::keyword(15),
+    span: DUMMY_SPAN,
+};
+
+pub const TYPEOF: Ident = Ident {
+    name: Symbol::keyword(16),
     span: DUMMY_SPAN,
 };
 
 pub const UNION: Ident = Ident {
-    name: SmolStr::new_inline("union"),
+    name: Symbol::keyword(17),
+    span: DUMMY_SPAN,
+};
+
+pub const UNSAFE: Ident = Ident {
+    name: Symbol::keyword(18),
     span: DUMMY_SPAN,
 };
 
 pub const USE: Ident = Ident {
-    name: SmolStr::new_inline("use"),
+    name: Symbol::keyword(19),
+    span: DUMMY_SPAN,
+};
+
+pub const WHILE: Ident = Ident {
+    name: Symbol::keyword(20),
     span: DUMMY_SPAN,
 };
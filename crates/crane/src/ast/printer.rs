@@ -0,0 +1,703 @@
+//! Printing of the AST as an indented tree, for debugging the parser and
+//! typer with `--emit=ast` and `--emit=typed-ast`.
+//!
+//! Each node is printed on its own line, followed by its span, with its
+//! children indented beneath it:
+//!
+//! ```text
+//! Fn main @ 3..7
+//!   Body
+//!     Call @ 16..36
+//!       Variable println @ 16..23
+//!       Literal String "Hello, world!" @ 24..35
+//! ```
+//!
+//! In the typed AST, every expression is also followed by its type.
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::ast::{
+    Expr, ExprKind, FieldDecl, FnDecl, FnReturnTy, Item, ItemKind, LiteralKind, LocalKind,
+    ModuleDecl, PatKind, Path, Stmt, StmtKind, Ty, TyExpr, TyExprKind, TyFieldDecl, TyFn, TyItem,
+    TyItemKind, TyKind, TyLiteral, TyLiteralKind, TyLocalKind, TyPackage, TyPatKind, TyStmt,
+    TyStmtKind, TyVariantData, VariantData, Visibility,
+};
+use crate::typer::ty_to_string;
+
+/// The indentation of each level of the tree.
+const INDENT: &str = "  ";
+
+/// Prints the given items as a tree.
+pub fn print_items(items: &[Item]) -> String {
+    let mut printer = AstPrinter::default();
+
+    for item in items {
+        printer.item(item);
+    }
+
+    printer.out
+}
+
+/// Prints the items in the modules of the given typed package as a tree.
+pub fn print_ty_package(package: &TyPackage) -> String {
+    let mut printer = AstPrinter::default();
+
+    for item in package.modules.iter().flat_map(|module| &module.items) {
+        printer.ty_item(item);
+    }
+
+    printer.out
+}
+
+#[derive(Default)]
+struct AstPrinter {
+    out: String,
+    depth: usize,
+}
+
+impl AstPrinter {
+    /// Writes a line at the current depth.
+    fn line(&mut self, text: impl Display) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+
+        self.out.push_str(&text.to_string());
+        self.out.push('\n');
+    }
+
+    /// Writes the lines written by `f` one level deeper.
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+
+    fn item(&mut self, item: &Item) {
+        let vis = match item.vis {
+            Visibility::Public => " (pub)",
+            Visibility::Private => "",
+        };
+
+        let kind = match &item.kind {
+            ItemKind::Use(use_tree) => {
+                let prefix = &use_tree.prefix;
+
+                // The parser doesn't record the span of a `use` path, so it is
+                // taken from its segments instead.
+                let span = match (prefix.segments.first(), prefix.segments.last()) {
+                    (Some(first), Some(last)) => first.ident.span.to(last.ident.span),
+                    _ => prefix.span,
+                };
+
+                return self.line(format!("Use {} @ {span}", path_to_string(prefix)));
+            }
+            ItemKind::Fn(_) => "Fn",
+            ItemKind::ExternFn(_) => "ExternFn",
+            ItemKind::Struct(_) => "Struct",
+            ItemKind::Union(_) => "Union",
+            ItemKind::Module(_) => "Module",
+            ItemKind::Impl(_) => "Impl",
+            ItemKind::Const(_) => "Const",
+        };
+
+        self.line(format!("{kind} {}{vis} @ {}", item.name, item.name.span));
+
+        self.nested(|printer| {
+            for attr in &item.attrs {
+                printer.line(format!("Attribute {} @ {}", attr.name, attr.span));
+            }
+
+            match &item.kind {
+                ItemKind::Use(_) => {}
+                ItemKind::Fn(fun) => {
+                    printer.fn_decl(&fun.decl);
+                    printer.block("Body", &fun.body);
+                }
+                ItemKind::ExternFn(decl) => printer.fn_decl(decl),
+                ItemKind::Struct(struct_decl) => printer.variant_data(&struct_decl.0),
+                ItemKind::Union(union_decl) => {
+                    for variant in &union_decl.variants {
+                        printer.line(format!("Variant {} @ {}", variant.name, variant.span));
+                        printer.nested(|printer| printer.variant_data(&variant.data));
+                    }
+                }
+                ItemKind::Module(module_decl) => match &**module_decl {
+                    ModuleDecl::Loaded(module, _) => {
+                        for item in &module.items {
+                            printer.item(item);
+                        }
+                    }
+                    ModuleDecl::Unloaded => printer.line("Unloaded"),
+                },
+                ItemKind::Impl(impl_decl) => {
+                    if let Some(trait_name) = &impl_decl.trait_name {
+                        printer.line(format!("Trait {trait_name} @ {}", trait_name.span));
+                    }
+
+                    for item in &impl_decl.items {
+                        printer.item(item);
+                    }
+                }
+                ItemKind::Const(const_decl) => {
+                    printer.ty(&const_decl.ty);
+                    printer.expr(const_decl.expr);
+                }
+            }
+        });
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl) {
+        for param in &decl.params {
+            // The parameters of a function type are unnamed.
+            if param.name.name.is_empty() {
+                self.line(format!("Param @ {}", param.span));
+            } else {
+                self.line(format!("Param {} @ {}", param.name, param.span));
+            }
+
+            self.nested(|printer| printer.ty(&param.ty));
+        }
+
+        if let FnReturnTy::Ty(ty) = &decl.return_ty {
+            self.line("Return");
+            self.nested(|printer| printer.ty(ty));
+        }
+    }
+
+    fn variant_data(&mut self, data: &VariantData) {
+        for (index, field) in data.fields().iter().enumerate() {
+            self.field_decl(index, field);
+        }
+    }
+
+    fn field_decl(&mut self, index: usize, field: &FieldDecl) {
+        let name = field
+            .name
+            .as_ref()
+            .map_or(index.to_string(), |name| name.to_string());
+
+        self.line(format!("Field {name} @ {}", field.span));
+
+        self.nested(|printer| {
+            for attr in &field.attrs {
+                printer.line(format!("Attribute {} @ {}", attr.name, attr.span));
+            }
+
+            printer.ty(&field.ty);
+        });
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match &ty.kind {
+            TyKind::Path(path) => {
+                self.line(format!("Ty {} @ {}", path_to_string(path), ty.span));
+            }
+            TyKind::Fn(fn_ty) => {
+                self.line(format!("FnTy @ {}", ty.span));
+                self.nested(|printer| printer.fn_decl(&fn_ty.decl));
+            }
+            TyKind::Array { elem, len } => {
+                self.line(format!("ArrayTy @ {}", ty.span));
+                self.nested(|printer| {
+                    printer.ty(elem);
+                    printer.expr(len);
+                });
+            }
+        }
+    }
+
+    /// Writes a labelled block of statements.
+    fn block(&mut self, label: &str, stmts: &[Stmt]) {
+        self.line(label);
+        self.nested(|printer| printer.stmts(stmts));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Local(local) => {
+                self.line(format!("Let {} @ {}", local.name, local.span));
+                self.nested(|printer| {
+                    if let Some(destructure) = &local.destructure {
+                        printer.line(format!(
+                            "Destructure {} @ {}",
+                            path_to_string(destructure),
+                            destructure.span
+                        ));
+                    }
+
+                    if let Some(ty) = &local.ty {
+                        printer.ty(ty);
+                    }
+
+                    if let LocalKind::Init(init) = &local.kind {
+                        printer.expr(init);
+                    }
+                });
+            }
+            StmtKind::Item(item) => self.item(item),
+            StmtKind::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let span = expr.span;
+
+        match &expr.kind {
+            ExprKind::Literal(literal) => {
+                let kind = match literal.kind {
+                    LiteralKind::String => "String",
+                    LiteralKind::Integer => "Integer",
+                    LiteralKind::Float => "Float",
+                };
+
+                self.line(format!("Literal {kind} {} @ {span}", literal.value));
+            }
+            ExprKind::Variable(path) => {
+                self.line(format!("Variable {} @ {span}", path_to_string(path)));
+            }
+            ExprKind::Call { fun, args } => {
+                self.line(format!("Call @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(fun);
+                    printer.exprs(args);
+                });
+            }
+            ExprKind::Struct(struct_expr) => {
+                self.line(format!(
+                    "Struct {} @ {span}",
+                    path_to_string(&struct_expr.path)
+                ));
+                self.nested(|printer| {
+                    for field in &struct_expr.fields {
+                        printer.line(format!("Field {} @ {}", field.name, field.span));
+                        printer.nested(|printer| printer.expr(field.expr));
+                    }
+                });
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.line(format!("Binary {op} @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(lhs);
+                    printer.expr(rhs);
+                });
+            }
+            ExprKind::Cast { expr, ty } => {
+                self.line(format!("Cast @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(expr);
+                    printer.ty(ty);
+                });
+            }
+            ExprKind::Array(elems) => {
+                self.line(format!("Array @ {span}"));
+                self.nested(|printer| printer.exprs(elems));
+            }
+            ExprKind::Index { expr, index } => {
+                self.line(format!("Index @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(expr);
+                    printer.expr(index);
+                });
+            }
+            ExprKind::Assign { target, value } => {
+                self.line(format!("Assign @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(target);
+                    printer.expr(value);
+                });
+            }
+            ExprKind::While { cond, body } => {
+                self.line(format!("While @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(cond);
+                    printer.block("Body", body);
+                });
+            }
+            ExprKind::TypeOf(expr) => {
+                self.line(format!("TypeOf @ {span}"));
+                self.nested(|printer| printer.expr(expr));
+            }
+            ExprKind::Asm(lines) => {
+                self.line(format!("Asm @ {span}"));
+                self.nested(|printer| {
+                    for line in lines {
+                        printer.line(format!("{line:?}"));
+                    }
+                });
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.line(format!("If @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(cond);
+                    printer.block("Then", then_branch);
+
+                    if let Some(else_branch) = else_branch {
+                        printer.block("Else", else_branch);
+                    }
+                });
+            }
+            ExprKind::For(for_expr) => {
+                self.line(format!("For {} @ {span}", for_expr.binding));
+                self.nested(|printer| {
+                    printer.expr(for_expr.start);
+                    printer.expr(for_expr.end);
+                    printer.block("Body", &for_expr.body);
+                });
+            }
+            ExprKind::Comptime(stmts) => {
+                self.line(format!("Comptime @ {span}"));
+                self.nested(|printer| printer.stmts(stmts));
+            }
+            ExprKind::Unsafe(stmts) => {
+                self.line(format!("Unsafe @ {span}"));
+                self.nested(|printer| printer.stmts(stmts));
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.line(format!("Match @ {span}"));
+                self.nested(|printer| {
+                    printer.expr(scrutinee);
+
+                    for arm in arms {
+                        let pat = match &arm.pat.kind {
+                            PatKind::Wild => "_".to_string(),
+                            PatKind::Literal(literal) => literal.value.to_string(),
+                        };
+
+                        printer.line(format!("Arm {pat} @ {}", arm.span));
+                        printer.nested(|printer| printer.stmts(&arm.body));
+                    }
+                });
+            }
+            ExprKind::Field(field_expr) => {
+                self.line(format!("Field {} @ {span}", field_expr.name));
+                self.nested(|printer| printer.expr(field_expr.expr));
+            }
+            ExprKind::MethodCall(method_call) => {
+                self.line(format!("MethodCall {} @ {span}", method_call.name));
+                self.nested(|printer| {
+                    printer.expr(method_call.receiver);
+                    printer.exprs(&method_call.args);
+                });
+            }
+            ExprKind::Macro(macro_call) => {
+                self.line(format!("Macro {}! @ {span}", macro_call.name));
+                self.nested(|printer| printer.exprs(&macro_call.args));
+            }
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[&mut Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn ty_item(&mut self, item: &TyItem) {
+        let vis = match item.vis {
+            Visibility::Public => " (pub)",
+            Visibility::Private => "",
+        };
+
+        let kind = match &item.kind {
+            // The typer drops `use` declarations once their paths are resolved.
+            TyItemKind::Use => return self.line("Use"),
+            TyItemKind::Fn(_) => "Fn",
+            TyItemKind::ExternFn(_) => "ExternFn",
+            TyItemKind::Struct(_) => "Struct",
+            TyItemKind::Union(_) => "Union",
+            TyItemKind::Module(..) => "Module",
+            TyItemKind::Impl(_) => "Impl",
+            TyItemKind::Const(_) => "Const",
+        };
+
+        self.line(format!("{kind} {}{vis} @ {}", item.name, item.name.span));
+
+        self.nested(|printer| {
+            for attr in &item.attrs {
+                printer.line(format!("Attribute {} @ {}", attr.name, attr.span));
+            }
+
+            match &item.kind {
+                TyItemKind::Use => {}
+                TyItemKind::Fn(fun) => {
+                    printer.ty_fn_signature(fun);
+                    printer.ty_block("Body", &fun.body);
+                }
+                TyItemKind::ExternFn(fun) => printer.ty_fn_signature(fun),
+                TyItemKind::Struct(struct_decl) => printer.ty_variant_data(&struct_decl.0),
+                TyItemKind::Union(union_decl) => {
+                    for variant in &union_decl.variants {
+                        printer.line(format!("Variant {} @ {}", variant.name, variant.span));
+                        printer.nested(|printer| printer.ty_variant_data(&variant.data));
+                    }
+                }
+                TyItemKind::Module(module, _) => {
+                    for item in &module.items {
+                        printer.ty_item(item);
+                    }
+                }
+                TyItemKind::Impl(impl_decl) => {
+                    for item in &impl_decl.items {
+                        printer.ty_item(item);
+                    }
+                }
+                TyItemKind::Const(const_decl) => printer.ty_expr(&const_decl.value),
+            }
+        });
+    }
+
+    fn ty_fn_signature(&mut self, fun: &TyFn) {
+        for param in &fun.params {
+            self.line(format!(
+                "Param {}: {} @ {}",
+                param.name,
+                ty_to_string(param.ty),
+                param.span
+            ));
+        }
+
+        self.line(format!("Return {}", ty_to_string(fun.return_ty)));
+    }
+
+    fn ty_variant_data(&mut self, data: &TyVariantData) {
+        for (index, field) in data.fields().iter().enumerate() {
+            self.ty_field_decl(index, field);
+        }
+    }
+
+    fn ty_field_decl(&mut self, index: usize, field: &TyFieldDecl) {
+        let name = field
+            .name
+            .as_ref()
+            .map_or(index.to_string(), |name| name.to_string());
+
+        let bits = field
+            .bits
+            .map_or(String::new(), |bits| format!(" ({bits} bits)"));
+
+        self.line(format!(
+            "Field {name}: {}{bits} @ {}",
+            ty_to_string(field.ty),
+            field.span
+        ));
+    }
+
+    /// Writes a labelled block of typed statements.
+    fn ty_block(&mut self, label: &str, stmts: &[TyStmt]) {
+        self.line(label);
+        self.nested(|printer| printer.ty_stmts(stmts));
+    }
+
+    fn ty_stmt(&mut self, stmt: &TyStmt) {
+        match &stmt.kind {
+            TyStmtKind::Local(local) => {
+                let ty = local
+                    .ty
+                    .as_ref()
+                    .map_or(String::new(), |ty| format!(": {}", ty_to_string(*ty)));
+
+                self.line(format!("Let {}{ty} @ {}", local.name, local.span));
+
+                if let TyLocalKind::Init(init) = &local.kind {
+                    self.nested(|printer| printer.ty_expr(init));
+                }
+            }
+            TyStmtKind::Item(item) => self.ty_item(item),
+            TyStmtKind::Expr(expr) => self.ty_expr(expr),
+        }
+    }
+
+    fn ty_expr(&mut self, expr: &TyExpr) {
+        // Every line for an expression ends with its type and span.
+        let suffix = format!(": {} @ {}", ty_to_string(expr.ty), expr.span);
+
+        match &expr.kind {
+            TyExprKind::Literal(literal) => {
+                self.line(format!(
+                    "Literal {} {suffix}",
+                    ty_literal_to_string(literal)
+                ));
+            }
+            TyExprKind::Variable(path) => self.line(format!("Variable {path} {suffix}")),
+            TyExprKind::Call { fun, args } => {
+                self.line(format!("Call {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(fun);
+                    printer.ty_exprs(args);
+                });
+            }
+            TyExprKind::Binary { op, lhs, rhs } => {
+                self.line(format!("Binary {op} {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(lhs);
+                    printer.ty_expr(rhs);
+                });
+            }
+            TyExprKind::Cast(inner) => {
+                self.line(format!("Cast {suffix}"));
+                self.nested(|printer| printer.ty_expr(inner));
+            }
+            TyExprKind::Array(elems) => {
+                self.line(format!("Array {suffix}"));
+                self.nested(|printer| printer.ty_exprs(elems));
+            }
+            TyExprKind::Index { expr, index } => {
+                self.line(format!("Index {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(expr);
+                    printer.ty_expr(index);
+                });
+            }
+            TyExprKind::Assign { target, value } => {
+                self.line(format!("Assign {target} {suffix}"));
+                self.nested(|printer| printer.ty_expr(value));
+            }
+            TyExprKind::While { cond, body } => {
+                self.line(format!("While {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(cond);
+                    printer.ty_block("Body", body);
+                });
+            }
+            TyExprKind::TypeOf(name) => self.line(format!("TypeOf {name} {suffix}")),
+            TyExprKind::Asm(lines) => {
+                self.line(format!("Asm {suffix}"));
+                self.nested(|printer| {
+                    for line in lines {
+                        printer.line(format!("{line:?}"));
+                    }
+                });
+            }
+            TyExprKind::Block(stmts) => {
+                self.line(format!("Block {suffix}"));
+                self.nested(|printer| printer.ty_stmts(stmts));
+            }
+            TyExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.line(format!("If {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(cond);
+                    printer.ty_block("Then", then_branch);
+
+                    if let Some(else_branch) = else_branch {
+                        printer.ty_block("Else", else_branch);
+                    }
+                });
+            }
+            TyExprKind::For(for_expr) => {
+                self.line(format!("For {} {suffix}", for_expr.binding));
+                self.nested(|printer| {
+                    printer.ty_expr(&for_expr.start);
+                    printer.ty_expr(&for_expr.end);
+                    printer.ty_block("Body", &for_expr.body);
+                });
+            }
+            TyExprKind::Match { scrutinee, arms } => {
+                self.line(format!("Match {suffix}"));
+                self.nested(|printer| {
+                    printer.ty_expr(scrutinee);
+
+                    for arm in arms {
+                        let pat = match &arm.pat.kind {
+                            TyPatKind::Wild => "_".to_string(),
+                            TyPatKind::Literal(literal) => ty_literal_to_string(literal),
+                        };
+
+                        printer.line(format!("Arm {pat} @ {}", arm.span));
+                        printer.nested(|printer| printer.ty_stmts(&arm.body));
+                    }
+                });
+            }
+            TyExprKind::Struct(fields) => {
+                self.line(format!("Struct {suffix}"));
+                self.nested(|printer| printer.ty_exprs(fields));
+            }
+            TyExprKind::Field { expr, index } => {
+                self.line(format!("Field {index} {suffix}"));
+                self.nested(|printer| printer.ty_expr(expr));
+            }
+        }
+    }
+
+    fn ty_stmts(&mut self, stmts: &[TyStmt]) {
+        for stmt in stmts {
+            self.ty_stmt(stmt);
+        }
+    }
+
+    fn ty_exprs(&mut self, exprs: &[&TyExpr]) {
+        for expr in exprs {
+            self.ty_expr(expr);
+        }
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.segments
+        .iter()
+        .map(|segment| &segment.ident.name)
+        .join("::")
+}
+
+fn ty_literal_to_string(literal: &TyLiteral) -> String {
+    match &literal.kind {
+        TyLiteralKind::String(value) => value.to_string(),
+        TyLiteralKind::Integer(integer) => integer.value().to_string(),
+        TyLiteralKind::Float(value) => format!("{value:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ast::AstArena;
+    use crate::compiler::{CompileOptions, CompileParams, Compiler, Input};
+
+    fn params(path: &std::path::Path) -> CompileParams {
+        CompileParams {
+            input: Input::File(path.to_path_buf()),
+            options: CompileOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_print_ast() {
+        insta::glob!("../../../../examples", "*.crane", |path| {
+            let arena = AstArena::new();
+            let items = Compiler::new()
+                .parse_input(&mut std::io::stderr(), params(path), &arena)
+                .unwrap();
+
+            insta::assert_snapshot!(print_items(&items));
+        });
+    }
+
+    #[test]
+    fn test_print_typed_ast() {
+        insta::glob!("../../../../examples", "*.crane", |path| {
+            let arena = AstArena::new();
+            let package = Compiler::new()
+                .type_check_input(&mut std::io::stderr(), params(path), &arena)
+                .unwrap();
+
+            insta::assert_snapshot!(print_ty_package(&package));
+        });
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use smol_str::SmolStr;
+
+use crate::ast::Span;
+
+/// A source file.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// The path of the file, as shown in diagnostics.
+    pub path: String,
+
+    /// The source code in the file.
+    pub source: String,
+
+    /// The offsets at which each line starts.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(path: String, source: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        Self {
+            path,
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the line and column of the given offset, both starting at `1`.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= offset);
+        let line_start = self.line_starts[line - 1];
+
+        let column = self
+            .source
+            .get(line_start..offset)
+            .map_or(1, |prefix| prefix.chars().count() + 1);
+
+        (line, column)
+    }
+}
+
+/// The source files that the modules of a package were loaded from.
+///
+/// The spans in a module are offsets into the file it was loaded from, so
+/// this is what turns them back into locations in a file.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    /// The files, keyed by the path of the module loaded from each of them.
+    /// The root module has an empty path.
+    files: HashMap<Vec<SmolStr>, SourceFile>,
+}
+
+impl SourceMap {
+    /// Adds the file that the module at the given path was loaded from.
+    pub fn add_file(&mut self, module_path: Vec<SmolStr>, file: SourceFile) {
+        self.files.insert(module_path, file);
+    }
+
+    /// Returns the file that the item at the given path is written in.
+    ///
+    /// Items in an inline module are written in the file of the closest
+    /// enclosing module that was loaded from a file.
+    pub fn file_of(&self, item_path: &[SmolStr]) -> Option<&SourceFile> {
+        (0..=item_path.len())
+            .rev()
+            .find_map(|len| self.files.get(&item_path[..len]))
+    }
+
+    /// Returns the location of the given span in the item at the given path,
+    /// as `path:line:column`.
+    pub fn location(&self, item_path: &[SmolStr], span: Span) -> Option<String> {
+        let file = self.file_of(item_path)?;
+        let (line, column) = file.line_col(span.start);
+
+        Some(format!("{}:{line}:{column}", file.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_col_starts_at_one() {
+        let file = SourceFile::new("main.crane".into(), "fn main() {\n    exit(1)\n}\n".into());
+
+        assert_eq!(file.line_col(0), (1, 1));
+        assert_eq!(file.line_col(16), (2, 5));
+        assert_eq!(file.line_col(24), (3, 1));
+    }
+
+    #[test]
+    fn items_in_inline_modules_use_the_enclosing_file() {
+        let mut source_map = SourceMap::default();
+        source_map.add_file(
+            vec![],
+            SourceFile::new("main.crane".into(), "mod util\n".into()),
+        );
+        source_map.add_file(
+            vec!["util".into()],
+            SourceFile::new("util.crane".into(), "\n\npub fn value()".into()),
+        );
+
+        let location = |path: &[&str]| {
+            let path = path
+                .iter()
+                .map(|&segment| segment.into())
+                .collect::<Vec<_>>();
+
+            source_map.location(&path, Span::new(2, 5))
+        };
+
+        assert_eq!(location(&["main"]), Some("main.crane:1:3".into()));
+        assert_eq!(
+            location(&["util", "inner", "value"]),
+            Some("util.crane:3:1".into())
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// A dummy [`Span`].
 ///
@@ -72,12 +72,82 @@ impl ariadne::Span for Span {
     }
 }
 
+/// The [`SpanId`] of the [`DUMMY_SPAN`].
+pub const DUMMY_SPAN_ID: SpanId = SpanId { start: 0, end: 0 };
+
+/// A [`Span`] that the tokens and AST nodes hold, in 8 bytes rather than 16.
+///
+/// The offsets are stored inline as `u32`s, rather than interned, so that
+/// threads parsing files in parallel don't share anything to create them.
+/// Most spans are unique, so interning them would cost more than it saves, as
+/// each would take an entry in the pool on top of the handle in the node.
+///
+/// It is shown and serialized as the span it refers to.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SpanId {
+    start: u32,
+    end: u32,
+}
+
+impl SpanId {
+    /// Returns the span this refers to.
+    pub fn get(self) -> Span {
+        Span::new(self.start as usize, self.end as usize)
+    }
+
+    /// Returns a new [`SpanId`] for the span that encloses `self` and `end`.
+    pub fn to(self, end: impl Into<Span>) -> Self {
+        self.get().to(end.into()).into()
+    }
+}
+
+impl From<Span> for SpanId {
+    fn from(span: Span) -> Self {
+        let offset = |offset: usize| u32::try_from(offset).expect("source files are under 4 GiB");
+
+        Self {
+            start: offset(span.start),
+            end: offset(span.end),
+        }
+    }
+}
+
+impl From<SpanId> for Span {
+    fn from(id: SpanId) -> Self {
+        id.get()
+    }
+}
+
+impl std::fmt::Debug for SpanId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.get(), f)
+    }
+}
+
+impl std::fmt::Display for SpanId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.get(), f)
+    }
+}
+
+impl Serialize for SpanId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Span::deserialize(deserializer).map(Self::from)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn span_to_works_forwards() {
+    fn test_span_to_works_forwards() {
         let start = Span::new(0, 3);
         let end = Span::new(4, 6);
 
@@ -85,10 +155,20 @@ mod tests {
     }
 
     #[test]
-    fn span_to_works_backwards() {
+    fn test_span_to_works_backwards() {
         let start = Span::new(3, 7);
         let end = Span::new(0, 2);
 
         assert_eq!(start.to(end), Span::new(0, 7))
     }
+
+    #[test]
+    fn test_span_ids_round_trip() {
+        let id = SpanId::from(Span::new(12, 15));
+
+        assert_eq!(id.get(), Span::new(12, 15));
+        assert_eq!(SpanId::from(Span::new(12, 15)), id);
+        assert_ne!(SpanId::from(Span::new(12, 16)), id);
+        assert_eq!(DUMMY_SPAN_ID.get(), DUMMY_SPAN);
+    }
 }
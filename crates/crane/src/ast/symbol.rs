@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smol_str::SmolStr;
+
+use crate::ast::keywords::KEYWORDS;
+
+/// The strings interned so far, which are shared by every thread.
+static INTERNER: LazyLock<RwLock<SymbolInterner>> = LazyLock::new(|| {
+    let mut interner = SymbolInterner {
+        strings: Vec::new(),
+        symbols: HashMap::new(),
+    };
+
+    // The keywords are interned first, in order, so that their symbols can be
+    // constants.
+    for keyword in KEYWORDS {
+        interner.insert(keyword);
+    }
+
+    RwLock::new(interner)
+});
+
+/// A pool of interned strings, which identifiers refer to by [`Symbol`],
+/// rather than each holding a string of their own.
+///
+/// Equal strings are interned once, so they share a [`Symbol`], and comparing
+/// two symbols doesn't compare their strings.
+///
+/// Interned strings live for the rest of the process, like the ASTs that refer
+/// to them. As each string is only interned once, the language server and the
+/// REPL only grow by the identifiers they haven't seen before, rather than by
+/// every request.
+pub struct SymbolInterner {
+    strings: Vec<&'static str>,
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+impl SymbolInterner {
+    /// Interns the given string, returning its [`Symbol`].
+    pub fn intern(string: &str) -> Symbol {
+        if let Some(symbol) = INTERNER.read().unwrap().symbols.get(string) {
+            return *symbol;
+        }
+
+        INTERNER.write().unwrap().insert(string)
+    }
+
+    /// Returns the string with the given [`Symbol`].
+    pub fn get(symbol: Symbol) -> &'static str {
+        INTERNER.read().unwrap().strings[symbol.0 as usize]
+    }
+
+    fn insert(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(u32::try_from(self.strings.len()).expect("too many strings to intern"));
+        let string: &'static str = Box::leak(string.into());
+
+        self.strings.push(string);
+        self.symbols.insert(string, symbol);
+
+        symbol
+    }
+}
+
+/// A string interned in the [`SymbolInterner`].
+///
+/// It is shown, ordered and serialized as the string it refers to.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the [`Symbol`] of the keyword at the given index of
+    /// [`KEYWORDS`].
+    pub(crate) const fn keyword(index: usize) -> Self {
+        assert!(index < KEYWORDS.len());
+
+        Self(index as u32)
+    }
+
+    /// Interns the given string, returning its [`Symbol`].
+    pub fn intern(string: &str) -> Self {
+        SymbolInterner::intern(string)
+    }
+
+    /// Returns the string this refers to.
+    pub fn as_str(self) -> &'static str {
+        SymbolInterner::get(self)
+    }
+}
+
+impl std::ops::Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(string: &str) -> Self {
+        Self::intern(string)
+    }
+}
+
+impl From<&SmolStr> for Symbol {
+    fn from(string: &SmolStr) -> Self {
+        Self::intern(string)
+    }
+}
+
+impl From<SmolStr> for Symbol {
+    fn from(string: SmolStr) -> Self {
+        Self::intern(&string)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(string: String) -> Self {
+        Self::intern(&string)
+    }
+}
+
+impl From<Symbol> for SmolStr {
+    fn from(symbol: Symbol) -> Self {
+        SmolStr::new(symbol.as_str())
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SmolStr::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::keywords;
+
+    #[test]
+    fn test_equal_strings_share_a_symbol() {
+        let symbol = Symbol::intern("interned");
+
+        assert_eq!(Symbol::intern("interned"), symbol);
+        assert_ne!(Symbol::intern("interned_too"), symbol);
+        assert_eq!(symbol.as_str(), "interned");
+    }
+
+    #[test]
+    fn test_keywords_are_preinterned() {
+        for (index, keyword) in KEYWORDS.iter().enumerate() {
+            assert_eq!(Symbol::intern(keyword), Symbol::keyword(index));
+        }
+
+        assert_eq!(keywords::AS.name, "as");
+        assert_eq!(keywords::FN.name, "fn");
+        assert_eq!(keywords::WHILE.name, "while");
+    }
+
+    #[test]
+    fn test_symbols_are_ordered_by_their_strings() {
+        let mut symbols = ["zebra", "apple", "mango"].map(Symbol::intern);
+
+        symbols.sort();
+
+        assert_eq!(symbols.map(Symbol::as_str), ["apple", "mango", "zebra"]);
+    }
+}
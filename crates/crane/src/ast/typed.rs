@@ -4,8 +4,8 @@ use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 use thin_vec::ThinVec;
 
-use crate::ast::{Ident, Span};
-use crate::typer::Ty;
+use crate::ast::{Attribute, BinaryOp, Ident, InlineModuleDecl, Span, Visibility};
+use crate::typer::{CallingConvention, TyKind, TypeId};
 
 /// A path.
 #[derive(Debug, Eq, Clone, Serialize, Deserialize)]
@@ -48,23 +48,47 @@ pub struct TyPathSegment {
     pub ident: Ident,
 }
 
+/// The type of a signed integer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TyInt {
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+}
+
 /// The type of an unsigned integer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TyUint {
+    Uint8,
+    Uint16,
+    Uint32,
     Uint64,
 }
 
 /// The type of an integer literal.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TyIntegerLiteral {
+    Signed(i128, TyInt),
     Unsigned(u128, TyUint),
 }
 
+impl TyIntegerLiteral {
+    /// Returns the value of this literal.
+    pub fn value(&self) -> i128 {
+        match self {
+            TyIntegerLiteral::Signed(value, _) => *value,
+            TyIntegerLiteral::Unsigned(value, _) => *value as i128,
+        }
+    }
+}
+
 /// The kind of a [`TyLiteral`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TyLiteralKind {
     String(SmolStr),
     Integer(TyIntegerLiteral),
+    Float(f64),
 }
 
 /// A typed literal.
@@ -75,8 +99,8 @@ pub struct TyLiteral {
 }
 
 /// The kind of a [`TyExpr`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TyExprKind {
+#[derive(Debug, Clone, Serialize)]
+pub enum TyExprKind<'a> {
     /// A literal.
     Literal(TyLiteral),
 
@@ -85,57 +109,178 @@ pub enum TyExprKind {
 
     /// A function call.
     Call {
-        fun: Box<TyExpr>,
-        args: ThinVec<Box<TyExpr>>,
+        fun: &'a TyExpr<'a>,
+        args: ThinVec<&'a TyExpr<'a>>,
+    },
+
+    /// A binary operation.
+    Binary {
+        op: BinaryOp,
+        lhs: &'a TyExpr<'a>,
+        rhs: &'a TyExpr<'a>,
+    },
+
+    /// A cast to the type of the enclosing [`TyExpr`].
+    Cast(&'a TyExpr<'a>),
+
+    /// An array literal.
+    Array(ThinVec<&'a TyExpr<'a>>),
+
+    /// An index into an array.
+    Index {
+        expr: &'a TyExpr<'a>,
+        index: &'a TyExpr<'a>,
+    },
+
+    /// An assignment to a local variable.
+    Assign {
+        target: TyPath,
+        value: &'a TyExpr<'a>,
+    },
+
+    /// A `while` loop.
+    While {
+        cond: &'a TyExpr<'a>,
+        body: ThinVec<TyStmt<'a>>,
     },
+
+    /// The name of the type queried by `typeof`.
+    ///
+    /// The queried expression is only type checked, never evaluated.
+    TypeOf(SmolStr),
+
+    /// The lines of an inline assembly block.
+    Asm(ThinVec<SmolStr>),
+
+    /// A block of statements, whose value is that of its last statement.
+    ///
+    /// This is what an `unsafe` block becomes once it has been checked.
+    Block(ThinVec<TyStmt<'a>>),
+
+    /// An `if` expression, with an optional `else` branch.
+    If {
+        cond: &'a TyExpr<'a>,
+        then_branch: ThinVec<TyStmt<'a>>,
+        else_branch: Option<ThinVec<TyStmt<'a>>>,
+    },
+
+    /// A `for` loop over a range.
+    For(Box<TyForExpr<'a>>),
+
+    /// A `match` expression.
+    Match {
+        scrutinee: &'a TyExpr<'a>,
+        arms: ThinVec<TyMatchArm<'a>>,
+    },
+
+    /// A struct literal, with its fields in declaration order.
+    Struct(ThinVec<&'a TyExpr<'a>>),
+
+    /// An access of the field at `index` in the declaration of a struct.
+    Field { expr: &'a TyExpr<'a>, index: usize },
 }
 
-/// A typed expression.
+/// The kind of a [`TyPat`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyExpr {
-    pub kind: TyExprKind,
+pub enum TyPatKind {
+    /// A wildcard pattern (`_`).
+    Wild,
+
+    /// A literal pattern.
+    Literal(TyLiteral),
+}
+
+/// A typed pattern in a [`TyMatchArm`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TyPat {
+    pub kind: TyPatKind,
+    pub span: Span,
+}
+
+/// A typed arm in a `match` expression.
+#[derive(Debug, Clone, Serialize)]
+pub struct TyMatchArm<'a> {
+    pub pat: TyPat,
+    pub body: ThinVec<TyStmt<'a>>,
     pub span: Span,
-    pub ty: Ty,
+}
+
+/// A `for` loop over the half-open range `start..end`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TyForExpr<'a> {
+    pub binding: Ident,
+    pub start: TyExpr<'a>,
+    pub end: TyExpr<'a>,
+    pub body: ThinVec<TyStmt<'a>>,
+}
+
+/// A typed expression.
+// SAFETY: Typed expressions are allocated in the `AstArena`, which drops them without
+// the drop checker knowing that they borrow from each other and from the
+// arena. So neither this nor any other type of the AST may implement `Drop`,
+// which could read a value that's already dropped through its `'a`
+// references. The arena asserts that for each of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct TyExpr<'a> {
+    pub kind: TyExprKind<'a>,
+    pub span: Span,
+    pub ty: TypeId,
+}
+
+impl TyExpr<'_> {
+    /// Returns whether evaluating this expression never finishes, because it
+    /// calls a function that returns `Never`.
+    ///
+    /// This looks at the type of the callee rather than that of the call,
+    /// since a diverging call takes on the type expected where it is used.
+    pub fn diverges(&self) -> bool {
+        let TyExprKind::Call { fun, .. } = &self.kind else {
+            return false;
+        };
+
+        matches!(&*fun.ty, TyKind::Fn { return_ty, .. } if **return_ty == TyKind::Never)
+    }
 }
 
 /// The kind of a [`TyStmt`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TyStmtKind {
+#[derive(Debug, Clone, Serialize)]
+pub enum TyStmtKind<'a> {
     /// A local `let` binding.
-    Local(Box<TyLocal>),
+    Local(Box<TyLocal<'a>>),
 
     /// An item.
-    Item(Box<TyItem>),
+    Item(Box<TyItem<'a>>),
 
     /// An expression.
-    Expr(Box<TyExpr>),
+    Expr(&'a TyExpr<'a>),
 }
 
 /// The kind of a [`TyLocal`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TyLocalKind {
+#[derive(Debug, Clone, Serialize)]
+pub enum TyLocalKind<'a> {
     /// A local declaration.
     Decl,
 
     /// A local declaration with an initializer.
-    Init(Box<TyExpr>),
+    Init(&'a TyExpr<'a>),
 }
 
 /// A local `let` binding.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyLocal {
-    pub kind: TyLocalKind,
+#[derive(Debug, Clone, Serialize)]
+pub struct TyLocal<'a> {
+    pub kind: TyLocalKind<'a>,
     pub name: Ident,
-    pub ty: Option<Ty>,
+    pub ty: Option<TypeId>,
     pub span: Span,
 }
 
 /// A typed function definition.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyFn {
+#[derive(Debug, Clone, Serialize)]
+pub struct TyFn<'a> {
     pub params: ThinVec<TyFnParam>,
-    pub return_ty: Ty,
-    pub body: ThinVec<TyStmt>,
+    pub return_ty: TypeId,
+    pub body: ThinVec<TyStmt<'a>>,
+    pub calling_convention: CallingConvention,
 
     // HACK: Adding this to the node so we don't have to recompute the path in
     // the backend. Should find a better way of doing this.
@@ -146,14 +291,14 @@ pub struct TyFn {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TyFnParam {
     pub name: Ident,
-    pub ty: Ty,
+    pub ty: TypeId,
     pub span: Span,
 }
 
 /// A typed statement.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyStmt {
-    pub kind: TyStmtKind,
+#[derive(Debug, Clone, Serialize)]
+pub struct TyStmt<'a> {
+    pub kind: TyStmtKind<'a>,
     pub span: Span,
 }
 
@@ -161,7 +306,10 @@ pub struct TyStmt {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TyFieldDecl {
     pub name: Option<Ident>,
-    pub ty: Ident,
+    pub ty: TypeId,
+
+    /// The width of this field in bits, in a `#[bitfield]` struct.
+    pub bits: Option<u32>,
     pub span: Span,
 }
 
@@ -200,6 +348,17 @@ pub struct TyVariant {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TyStructDecl(pub TyVariantData);
 
+impl TyStructDecl {
+    /// Returns the wrapped type, if this is a tuple struct like
+    /// `struct Meters(Float64)`.
+    pub fn newtype(&self) -> Option<&TypeId> {
+        match &self.0 {
+            TyVariantData::Tuple(fields) => fields.first().map(|field| &field.ty),
+            _ => None,
+        }
+    }
+}
+
 /// A `union` declaration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TyUnionDecl {
@@ -207,15 +366,35 @@ pub struct TyUnionDecl {
     pub variants: ThinVec<TyVariant>,
 }
 
+/// An `impl` block.
+#[derive(Debug, Clone, Serialize)]
+pub struct TyImplDecl<'a> {
+    pub items: ThinVec<TyItem<'a>>,
+}
+
+/// A typed `const` declaration.
+#[derive(Debug, Clone, Serialize)]
+pub struct TyConst<'a> {
+    /// The value of the constant, folded into a literal.
+    pub value: TyExpr<'a>,
+
+    pub path: TyPath,
+}
+
 /// The kind of a [`TyItem`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TyItemKind {
+#[derive(Debug, Clone, Serialize)]
+pub enum TyItemKind<'a> {
     /// A use declaration (`use`).
     Use,
 
     // TODO: Remove `Box`?
     /// A function declaration (`fn`).
-    Fn(Box<TyFn>),
+    Fn(Box<TyFn<'a>>),
+
+    /// A function defined outside of Crane (`extern fn`).
+    ///
+    /// It has no body, and its path is the symbol it is linked against.
+    ExternFn(Box<TyFn<'a>>),
 
     /// A struct declaration (`struct`).
     Struct(TyStructDecl),
@@ -224,26 +403,41 @@ pub enum TyItemKind {
     Union(TyUnionDecl),
 
     /// A module declaration (`mod`).
-    Module(TyModule),
+    Module(TyModule<'a>, InlineModuleDecl),
+
+    /// An `impl` block (`impl`).
+    Impl(TyImplDecl<'a>),
+
+    /// A constant (`const`).
+    Const(Box<TyConst<'a>>),
 }
 
 /// An item in a [`TyModule`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyItem {
-    pub kind: TyItemKind,
+#[derive(Debug, Clone, Serialize)]
+pub struct TyItem<'a> {
+    pub kind: TyItemKind<'a>,
     pub name: Ident,
+    pub vis: Visibility,
+    pub attrs: ThinVec<Attribute>,
+}
+
+impl TyItem<'_> {
+    /// Returns the attribute with the given name, if this item has it.
+    pub fn attr(&self, name: &str) -> Option<&Attribute> {
+        self.attrs.iter().find(|attr| attr.name.name == name)
+    }
 }
 
 /// A module.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyModule {
-    pub items: ThinVec<TyItem>,
+#[derive(Debug, Clone, Serialize)]
+pub struct TyModule<'a> {
+    pub items: ThinVec<TyItem<'a>>,
 }
 
 /// A package.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TyPackage {
-    pub modules: ThinVec<TyModule>,
+#[derive(Debug, Clone, Serialize)]
+pub struct TyPackage<'a> {
+    pub modules: ThinVec<TyModule<'a>>,
 }
 
 #[cfg(test)]
@@ -258,8 +452,8 @@ mod tests {
 
         #[cfg(target_arch = "x86_64")]
         {
-            insta::assert_snapshot!(size_of::<TyExpr>().to_string(), @"64");
-            insta::assert_snapshot!(size_of::<TyExprKind>().to_string(), @"40");
+            insta::assert_snapshot!(size_of::<TyExpr>().to_string(), @"80");
+            insta::assert_snapshot!(size_of::<TyExprKind>().to_string(), @"48");
         }
 
         #[cfg(target_arch = "aarch64")]
@@ -269,8 +463,8 @@ mod tests {
         }
 
         insta::assert_snapshot!(size_of::<TyFn>().to_string(), @"48");
-        insta::assert_snapshot!(size_of::<TyItem>().to_string(), @"56");
-        insta::assert_snapshot!(size_of::<TyItemKind>().to_string(), @"16");
+        insta::assert_snapshot!(size_of::<TyItem>().to_string(), @"64");
+        insta::assert_snapshot!(size_of::<TyItemKind>().to_string(), @"24");
         insta::assert_snapshot!(size_of::<TyStmt>().to_string(), @"32");
         insta::assert_snapshot!(size_of::<TyStmtKind>().to_string(), @"16");
     }
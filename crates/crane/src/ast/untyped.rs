@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 use thin_vec::ThinVec;
 
-use crate::ast::{Ident, Span};
+use crate::ast::{Ident, Span, SpanId};
 
 /// A path.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,31 +20,39 @@ pub struct PathSegment {
 }
 
 /// A function type.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FnTy {
-    pub decl: Box<FnDecl>,
+#[derive(Debug, Serialize)]
+pub struct FnTy<'a> {
+    pub decl: Box<FnDecl<'a>>,
 }
 
 /// The kind of a [`Ty`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TyKind {
+#[derive(Debug, Serialize)]
+pub enum TyKind<'a> {
     /// A type referenced by its path.
     Path(Path),
 
     /// A function type.
-    Fn(Box<FnTy>),
+    Fn(Box<FnTy<'a>>),
+
+    /// A fixed-size array type (`[T; N]`).
+    ///
+    /// The length is a constant expression, evaluated by the typer.
+    Array {
+        elem: Box<Ty<'a>>,
+        len: &'a mut Expr<'a>,
+    },
 }
 
 /// A type.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Ty {
-    pub kind: TyKind,
+#[derive(Debug, Serialize)]
+pub struct Ty<'a> {
+    pub kind: TyKind<'a>,
     pub span: Span,
 }
 
 /// The kind of an [`Expr`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ExprKind {
+#[derive(Debug, Serialize)]
+pub enum ExprKind<'a> {
     /// A literal.
     Literal(Literal),
 
@@ -53,12 +61,165 @@ pub enum ExprKind {
 
     /// A function call.
     Call {
-        fun: Box<Expr>,
-        args: ThinVec<Box<Expr>>,
+        fun: &'a mut Expr<'a>,
+        args: ThinVec<&'a mut Expr<'a>>,
     },
 
     /// A struct literal.
-    Struct(Box<StructExpr>),
+    Struct(Box<StructExpr<'a>>),
+
+    /// A binary operation.
+    Binary {
+        op: BinaryOp,
+        lhs: &'a mut Expr<'a>,
+        rhs: &'a mut Expr<'a>,
+    },
+
+    /// A cast (`expr as Ty`).
+    Cast {
+        expr: &'a mut Expr<'a>,
+        ty: Box<Ty<'a>>,
+    },
+
+    /// An array literal (`[a, b, c]`).
+    Array(ThinVec<&'a mut Expr<'a>>),
+
+    /// An index into an array (`expr[index]`).
+    Index {
+        expr: &'a mut Expr<'a>,
+        index: &'a mut Expr<'a>,
+    },
+
+    /// An assignment to a local variable (`name = value`).
+    Assign {
+        target: &'a mut Expr<'a>,
+        value: &'a mut Expr<'a>,
+    },
+
+    /// A `while` loop.
+    While {
+        cond: &'a mut Expr<'a>,
+        body: ThinVec<Stmt<'a>>,
+    },
+
+    /// A query for the type of an expression (`typeof(expr)`).
+    TypeOf(&'a mut Expr<'a>),
+
+    /// An inline assembly block (`asm("nop", "ret")`), with one string per
+    /// line of assembly.
+    Asm(ThinVec<SmolStr>),
+
+    /// An `if` expression, with an optional `else` branch.
+    If {
+        cond: &'a mut Expr<'a>,
+        then_branch: ThinVec<Stmt<'a>>,
+        else_branch: Option<ThinVec<Stmt<'a>>>,
+    },
+
+    /// A `for` loop over a range (`for i in start..end`).
+    For(Box<ForExpr<'a>>),
+
+    /// A block evaluated at compile time (`comptime { ... }`).
+    Comptime(ThinVec<Stmt<'a>>),
+
+    /// An `unsafe` block, in which operations that require `unsafe` are allowed.
+    Unsafe(ThinVec<Stmt<'a>>),
+
+    /// A `match` expression.
+    Match {
+        scrutinee: &'a mut Expr<'a>,
+        arms: ThinVec<MatchArm<'a>>,
+    },
+
+    /// An access of a field of a struct (`expr.name`).
+    Field(Box<FieldExpr<'a>>),
+
+    /// A method call (`receiver.name(args)`).
+    MethodCall(Box<MethodCallExpr<'a>>),
+
+    /// A call of a built-in macro (`name!(args)`), which is expanded before
+    /// type checking.
+    Macro(Box<MacroCall<'a>>),
+}
+
+/// A binary operator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+
+    /// `-`
+    Sub,
+
+    /// `*`
+    Mul,
+
+    /// `/`
+    Div,
+
+    /// `%`
+    Rem,
+
+    /// `==`
+    Eq,
+
+    /// `!=`
+    Ne,
+
+    /// `<`
+    Lt,
+
+    /// `<=`
+    Le,
+
+    /// `>`
+    Gt,
+
+    /// `>=`
+    Ge,
+
+    /// `&&`
+    And,
+}
+
+impl BinaryOp {
+    /// Returns how tightly this operator binds. Higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::And => 1,
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge => 2,
+            Self::Add | Self::Sub => 3,
+            Self::Mul | Self::Div | Self::Rem => 4,
+        }
+    }
+
+    /// Returns whether this operator compares its operands.
+    pub fn is_comparison(&self) -> bool {
+        self.precedence() == 2
+    }
+}
+
+impl std::fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Add => "+",
+                Self::Sub => "-",
+                Self::Mul => "*",
+                Self::Div => "/",
+                Self::Rem => "%",
+                Self::Eq => "==",
+                Self::Ne => "!=",
+                Self::Lt => "<",
+                Self::Le => "<=",
+                Self::Gt => ">",
+                Self::Ge => ">=",
+                Self::And => "&&",
+            }
+        )
+    }
 }
 
 /// The kind of a [`Literal`].
@@ -66,6 +227,7 @@ pub enum ExprKind {
 pub enum LiteralKind {
     String,
     Integer,
+    Float,
 }
 
 /// A literal.
@@ -75,54 +237,115 @@ pub struct Literal {
     pub value: SmolStr,
 }
 
-/// A struct literal expression.
+/// The kind of a [`Pat`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StructExpr {
+pub enum PatKind {
+    /// A wildcard pattern (`_`).
+    Wild,
+
+    /// A literal pattern.
+    Literal(Literal),
+}
+
+/// A pattern in a [`MatchArm`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pat {
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+/// An arm in a `match` expression (`pat => body`).
+#[derive(Debug, Serialize)]
+pub struct MatchArm<'a> {
+    pub pat: Pat,
+    pub body: ThinVec<Stmt<'a>>,
+    pub span: Span,
+}
+
+/// A struct literal expression.
+#[derive(Debug, Serialize)]
+pub struct StructExpr<'a> {
     pub path: Path,
-    pub fields: ThinVec<StructExprField>,
+    pub fields: ThinVec<StructExprField<'a>>,
 }
 
 /// A field in a [`StructExpr`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StructExprField {
+#[derive(Debug, Serialize)]
+pub struct StructExprField<'a> {
     pub name: Ident,
-    pub expr: Box<Expr>,
+    pub expr: &'a mut Expr<'a>,
     pub span: Span,
 }
 
+/// A `for` loop over the half-open range `start..end`.
+#[derive(Debug, Serialize)]
+pub struct ForExpr<'a> {
+    pub binding: Ident,
+    pub start: &'a mut Expr<'a>,
+    pub end: &'a mut Expr<'a>,
+    pub body: ThinVec<Stmt<'a>>,
+}
+
+/// An access of the field `name` of the struct `expr`.
+#[derive(Debug, Serialize)]
+pub struct FieldExpr<'a> {
+    pub expr: &'a mut Expr<'a>,
+    pub name: Ident,
+}
+
+/// A call of the method `name` on `receiver`.
+#[derive(Debug, Serialize)]
+pub struct MethodCallExpr<'a> {
+    pub receiver: &'a mut Expr<'a>,
+    pub name: Ident,
+    pub args: ThinVec<&'a mut Expr<'a>>,
+}
+
+/// A call of the built-in macro `name` with `args`.
+#[derive(Debug, Serialize)]
+pub struct MacroCall<'a> {
+    pub name: Ident,
+    pub args: ThinVec<&'a mut Expr<'a>>,
+}
+
 /// An expression.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Expr {
-    pub kind: ExprKind,
-    pub span: Span,
+// SAFETY: Expressions are allocated in the `AstArena`, which drops them without
+// the drop checker knowing that they borrow from each other and from the
+// arena. So neither this nor any other type of the AST may implement `Drop`,
+// which could read a value that's already dropped through its `'a`
+// references. The arena asserts that for each of them.
+#[derive(Debug, Serialize)]
+pub struct Expr<'a> {
+    pub kind: ExprKind<'a>,
+    pub span: SpanId,
 }
 
 /// The kind of a [`Stmt`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum StmtKind {
+#[derive(Debug, Serialize)]
+pub enum StmtKind<'a> {
     /// A local `let` binding.
-    Local(Box<Local>),
+    Local(Box<Local<'a>>),
 
     /// An item.
-    Item(Box<Item>),
+    Item(Box<Item<'a>>),
 
     /// An expression.
-    Expr(Box<Expr>),
+    Expr(&'a mut Expr<'a>),
 }
 
 /// The kind of a [`Local`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum LocalKind {
+#[derive(Debug, Serialize)]
+pub enum LocalKind<'a> {
     /// A local declaration.
     Decl,
 
     /// A local declaration with an initializer.
-    Init(Box<Expr>),
+    Init(&'a mut Expr<'a>),
 }
 
-impl LocalKind {
+impl<'a> LocalKind<'a> {
     /// Returns the initializer for this local.
-    pub fn init(&self) -> Option<&Expr> {
+    pub fn init(&self) -> Option<&Expr<'a>> {
         match self {
             Self::Decl => None,
             Self::Init(init) => Some(init),
@@ -131,11 +354,15 @@ impl LocalKind {
 }
 
 /// A local `let` binding.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Local {
-    pub kind: LocalKind,
+#[derive(Debug, Serialize)]
+pub struct Local<'a> {
+    pub kind: LocalKind<'a>,
     pub name: Ident,
-    pub ty: Option<Box<Ty>>,
+    pub ty: Option<Box<Ty<'a>>>,
+
+    /// The tuple struct that the initializer is destructured as, binding
+    /// `name` to its field (`let Meters(x) = m`).
+    pub destructure: Option<Path>,
     pub span: Span,
 }
 
@@ -153,69 +380,81 @@ pub struct UseTree {
 }
 
 /// A function definition.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Fn {
-    pub decl: Box<FnDecl>,
-    pub body: ThinVec<Stmt>,
+#[derive(Debug, Serialize)]
+pub struct Fn<'a> {
+    pub decl: Box<FnDecl<'a>>,
+    pub body: ThinVec<Stmt<'a>>,
+    /// Whether this is an `unsafe fn`, which can only be called from an
+    /// `unsafe` context.
+    pub is_unsafe: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FnDecl {
-    pub params: ThinVec<FnParam>,
-    pub return_ty: FnReturnTy,
+#[derive(Debug, Serialize)]
+pub struct FnDecl<'a> {
+    pub params: ThinVec<FnParam<'a>>,
+    pub return_ty: FnReturnTy<'a>,
 }
 
 /// A parameter to a [`Fn`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FnParam {
+#[derive(Debug, Serialize)]
+pub struct FnParam<'a> {
     pub name: Ident,
-    pub ty: Box<Ty>,
+    pub ty: Box<Ty<'a>>,
     pub span: Span,
 }
 
 /// The return type of a [`Fn`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum FnReturnTy {
+#[derive(Debug, Serialize)]
+pub enum FnReturnTy<'a> {
     /// The unit type `()`.
     ///
     /// This is the default return type if one is not specified.
     Unit,
 
     /// Any other type.
-    Ty(Box<Ty>),
+    Ty(Box<Ty<'a>>),
 }
 
 /// A statement.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Stmt {
-    pub kind: StmtKind,
-    pub span: Span,
+#[derive(Debug, Serialize)]
+pub struct Stmt<'a> {
+    pub kind: StmtKind<'a>,
+    pub span: SpanId,
 }
 
 /// A field declaration in a `struct` or [`Variant`] of a `union`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FieldDecl {
+#[derive(Debug, Serialize)]
+pub struct FieldDecl<'a> {
     pub name: Option<Ident>,
-    pub ty: Box<Ty>,
+    pub ty: Box<Ty<'a>>,
+    pub attrs: ThinVec<Attribute>,
+    pub docs: ThinVec<DocComment>,
     pub span: Span,
 }
 
+impl FieldDecl<'_> {
+    /// Returns the text of this field's doc comments, one line per comment.
+    pub fn doc_text(&self) -> String {
+        doc_text(&self.docs)
+    }
+}
+
 /// The data for a `struct` or [`Variant`] of a `union`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum VariantData {
+#[derive(Debug, Serialize)]
+pub enum VariantData<'a> {
     /// A struct variant.
-    Struct(ThinVec<FieldDecl>),
+    Struct(ThinVec<FieldDecl<'a>>),
 
     /// A tuple variant.
-    Tuple(ThinVec<FieldDecl>),
+    Tuple(ThinVec<FieldDecl<'a>>),
 
     /// A unit variant.
     Unit,
 }
 
-impl VariantData {
+impl<'a> VariantData<'a> {
     /// Return the fields in this [`VariantData`].
-    pub fn fields(&self) -> &[FieldDecl] {
+    pub fn fields(&self) -> &[FieldDecl<'a>] {
         match self {
             VariantData::Struct(fields) | VariantData::Tuple(fields) => fields,
             VariantData::Unit => &[],
@@ -224,22 +463,48 @@ impl VariantData {
 }
 
 /// A variant in a `union`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Variant {
+#[derive(Debug, Serialize)]
+pub struct Variant<'a> {
     pub name: Ident,
-    pub data: VariantData,
+    pub data: VariantData<'a>,
+    pub docs: ThinVec<DocComment>,
     pub span: Span,
 }
 
+impl Variant<'_> {
+    /// Returns the text of this variant's doc comments, one line per comment.
+    pub fn doc_text(&self) -> String {
+        doc_text(&self.docs)
+    }
+}
+
 /// A `struct` declaration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StructDecl(pub VariantData);
+#[derive(Debug, Serialize)]
+pub struct StructDecl<'a>(pub VariantData<'a>);
 
 /// A `union` declaration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UnionDecl {
+#[derive(Debug, Serialize)]
+pub struct UnionDecl<'a> {
     /// The variants of the `union`.
-    pub variants: ThinVec<Variant>,
+    pub variants: ThinVec<Variant<'a>>,
+}
+
+/// An `impl` block, declaring the associated functions of the type named by
+/// its [`Item`].
+#[derive(Debug, Serialize)]
+pub struct ImplDecl<'a> {
+    /// The trait implemented by the block (`impl ToString for Point`), if any.
+    pub trait_name: Option<Ident>,
+    pub items: ThinVec<Item<'a>>,
+}
+
+/// A `const` declaration (`const NAME: Ty = expr`).
+///
+/// The value is evaluated at compile time.
+#[derive(Debug, Serialize)]
+pub struct ConstDecl<'a> {
+    pub ty: Box<Ty<'a>>,
+    pub expr: &'a mut Expr<'a>,
 }
 
 /// Denotes whether a [`ModuleDecl`] is inlined.
@@ -250,53 +515,124 @@ pub enum InlineModuleDecl {
 }
 
 /// A module (`mod`) declaration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ModuleDecl {
+#[derive(Debug, Serialize)]
+pub enum ModuleDecl<'a> {
     /// A module declaration that either:
     ///   1. has an inlined declaration body (`mod foo { ... }`) or
     ///   2. refers to a module in a separate file that has already been loaded.
-    Loaded(Module, InlineModuleDecl),
+    Loaded(Module<'a>, InlineModuleDecl),
 
     /// A module declaration that refers to a separate file that has not yet been loaded.
     Unloaded,
 }
 
 /// The kind of an [`Item`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ItemKind {
+#[derive(Debug, Serialize)]
+pub enum ItemKind<'a> {
     /// A use declaration (`use`).
     Use(UseTree),
 
     /// A function declaration (`fn`).
-    Fn(Box<Fn>),
+    Fn(Box<Fn<'a>>),
+
+    /// A function defined outside of Crane, like in a C library (`extern fn`).
+    ExternFn(Box<FnDecl<'a>>),
 
     /// A struct declaration (`struct`).
-    Struct(StructDecl),
+    Struct(StructDecl<'a>),
 
     /// A union declaration (`union`).
-    Union(UnionDecl),
+    Union(UnionDecl<'a>),
 
     /// A module declaration (`mod`).
-    Module(Box<ModuleDecl>),
+    Module(Box<ModuleDecl<'a>>),
+
+    /// An `impl` block (`impl`).
+    Impl(Box<ImplDecl<'a>>),
+
+    /// A constant (`const`).
+    Const(ConstDecl<'a>),
 }
 
-/// An item in a [`Module`].
+/// The visibility of an [`Item`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Visibility {
+    /// The item is visible outside of its module (`pub`).
+    Public,
+
+    /// The item is only visible within its module.
+    Private,
+}
+
+/// An argument to an [`Attribute`], either `name`, `name = "value"`, or an
+/// integer like `3`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Item {
-    pub kind: ItemKind,
+pub struct AttributeArg {
     pub name: Ident,
+    pub value: Option<SmolStr>,
+    pub span: Span,
 }
 
-/// A module.
+/// An attribute on an item or field (`#[name]` or `#[name(args)]`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Module {
-    pub items: ThinVec<Item>,
+pub struct Attribute {
+    pub name: Ident,
+    pub args: ThinVec<AttributeArg>,
+    /// The value of an attribute written as `#[name = "value"]`.
+    pub value: Option<SmolStr>,
+    pub span: Span,
 }
 
-/// A package.
+/// A documentation comment (`/// text`) preceding an [`Item`], a field, or a
+/// variant of a `union`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Package {
-    pub modules: ThinVec<Module>,
+pub struct DocComment {
+    /// The text of the comment, after the `///`.
+    pub text: SmolStr,
+    pub span: Span,
+}
+
+/// An item in a [`Module`].
+#[derive(Debug, Serialize)]
+pub struct Item<'a> {
+    pub kind: ItemKind<'a>,
+    pub name: Ident,
+    pub vis: Visibility,
+    pub attrs: ThinVec<Attribute>,
+    pub docs: ThinVec<DocComment>,
+}
+
+impl Item<'_> {
+    /// Returns the text of this item's doc comments, one line per comment.
+    pub fn doc_text(&self) -> String {
+        doc_text(&self.docs)
+    }
+
+    /// Returns the attribute with the given name, if this item has it.
+    pub fn attr(&self, name: &str) -> Option<&Attribute> {
+        self.attrs.iter().find(|attr| attr.name.name == name)
+    }
+}
+
+/// Returns the text of the given doc comments, one line per comment, without
+/// the space that usually follows `///`.
+fn doc_text(docs: &[DocComment]) -> String {
+    docs.iter()
+        .map(|doc| doc.text.strip_prefix(' ').unwrap_or(&doc.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A module.
+#[derive(Debug, Serialize)]
+pub struct Module<'a> {
+    pub items: ThinVec<Item<'a>>,
+}
+
+/// A package.
+#[derive(Debug, Serialize)]
+pub struct Package<'a> {
+    pub modules: ThinVec<Module<'a>>,
 }
 
 #[cfg(test)]
@@ -308,14 +644,14 @@ mod tests {
     fn test_ast_node_sizes() {
         use std::mem::size_of;
 
-        insta::assert_snapshot!(size_of::<Expr>().to_string(), @"48");
+        insta::assert_snapshot!(size_of::<Expr>().to_string(), @"40");
         insta::assert_snapshot!(size_of::<ExprKind>().to_string(), @"32");
-        insta::assert_snapshot!(size_of::<Fn>().to_string(), @"16");
-        insta::assert_snapshot!(size_of::<Item>().to_string(), @"72");
+        insta::assert_snapshot!(size_of::<Fn>().to_string(), @"24");
+        insta::assert_snapshot!(size_of::<Item>().to_string(), @"80");
         insta::assert_snapshot!(size_of::<ItemKind>().to_string(), @"32");
-        insta::assert_snapshot!(size_of::<Stmt>().to_string(), @"32");
+        insta::assert_snapshot!(size_of::<Stmt>().to_string(), @"24");
         insta::assert_snapshot!(size_of::<StmtKind>().to_string(), @"16");
-        insta::assert_snapshot!(size_of::<Ty>().to_string(), @"40");
-        insta::assert_snapshot!(size_of::<TyKind>().to_string(), @"24");
+        insta::assert_snapshot!(size_of::<Ty>().to_string(), @"48");
+        insta::assert_snapshot!(size_of::<TyKind>().to_string(), @"32");
     }
 }
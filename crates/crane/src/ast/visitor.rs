@@ -92,6 +92,9 @@ pub fn walk_item<V: Visitor>(visitor: &mut V, item: &Item) {
         ItemKind::Fn(fun) => {
             visitor.visit_fn(fun);
         }
+        ItemKind::ExternFn(fun_decl) => {
+            visitor.visit_fn_decl(fun_decl);
+        }
         ItemKind::Struct(struct_decl) => {
             visitor.visit_struct_decl(struct_decl);
         }
@@ -101,6 +104,15 @@ pub fn walk_item<V: Visitor>(visitor: &mut V, item: &Item) {
         ItemKind::Module(module_decl) => {
             visitor.visit_module_decl(module_decl);
         }
+        ItemKind::Impl(impl_decl) => {
+            for item in &impl_decl.items {
+                visitor.visit_item(item);
+            }
+        }
+        ItemKind::Const(const_decl) => {
+            visitor.visit_ty(&const_decl.ty);
+            visitor.visit_expr(const_decl.expr);
+        }
     }
 }
 
@@ -206,6 +218,14 @@ pub fn walk_local<V: Visitor>(visitor: &mut V, local: &Local) {
     if let Some(ty) = &local.ty {
         visitor.visit_ty(ty);
     }
+
+    if let Some(path) = &local.destructure {
+        visitor.visit_path(path);
+    }
+
+    if let Some(init) = local.kind.init() {
+        visitor.visit_expr(init);
+    }
 }
 
 pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
@@ -223,13 +243,99 @@ pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
             visitor.visit_path(&struct_expr.path);
 
             for field in &struct_expr.fields {
-                visitor.visit_struct_expr_field(&field);
+                visitor.visit_struct_expr_field(field);
+            }
+        }
+        ExprKind::Binary { op: _, lhs, rhs } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Cast { expr, ty } => {
+            visitor.visit_expr(expr);
+            visitor.visit_ty(ty);
+        }
+        ExprKind::Array(elems) => {
+            for elem in elems {
+                visitor.visit_expr(elem);
+            }
+        }
+        ExprKind::Index { expr, index } => {
+            visitor.visit_expr(expr);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Assign { target, value } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        ExprKind::While { cond, body } => {
+            visitor.visit_expr(cond);
+
+            for stmt in body {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::TypeOf(expr) => visitor.visit_expr(expr),
+        ExprKind::Asm(_) => {}
+        ExprKind::Unsafe(body) => {
+            for stmt in body {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::If {
+            cond,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(cond);
+
+            for stmt in then_branch.iter().chain(else_branch.iter().flatten()) {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::For(for_expr) => {
+            visitor.visit_ident(&for_expr.binding);
+            visitor.visit_expr(for_expr.start);
+            visitor.visit_expr(for_expr.end);
+
+            for stmt in &for_expr.body {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::Comptime(body) => {
+            for stmt in body {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            visitor.visit_expr(scrutinee);
+
+            for stmt in arms.iter().flat_map(|arm| &arm.body) {
+                visitor.visit_stmt(stmt);
+            }
+        }
+        ExprKind::Field(field_expr) => {
+            visitor.visit_expr(field_expr.expr);
+            visitor.visit_ident(&field_expr.name);
+        }
+        ExprKind::MethodCall(method_call) => {
+            visitor.visit_expr(method_call.receiver);
+            visitor.visit_ident(&method_call.name);
+
+            for arg in &method_call.args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Macro(macro_call) => {
+            visitor.visit_ident(&macro_call.name);
+
+            for arg in &macro_call.args {
+                visitor.visit_expr(arg);
             }
         }
     }
 }
 
 pub fn walk_struct_expr_field<V: Visitor>(visitor: &mut V, field: &StructExprField) {
-    visitor.visit_expr(&field.expr);
+    visitor.visit_expr(field.expr);
     visitor.visit_ident(&field.name);
 }
@@ -1 +1,6 @@
+mod error;
+
+pub mod cache;
 pub mod native;
+
+pub use error::*;
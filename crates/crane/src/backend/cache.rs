@@ -0,0 +1,176 @@
+//! A cache of compiled units, for incremental rebuilds.
+//!
+//! Each unit is stored under a key that hashes everything its object file
+//! depends on, so a unit whose key is unchanged can be reused as is. Since
+//! the key covers everything, one cache can be shared between the builds of
+//! different projects.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::backend::native::Artifacts;
+use crate::backend::BackendError;
+
+/// The name of the directory in the output directory that holds the cache,
+/// unless another one is given.
+pub const CACHE_DIR: &str = "cache";
+
+/// The name of the file in the output directory that lists the keys of the
+/// units that builds into it used from a shared cache, so that `crane clean
+/// --cache` can remove the project's entries from it.
+pub const CACHE_ENTRIES: &str = "cache-entries";
+
+/// The key of a unit in a [`CompilationCache`], which is a BLAKE3 hash of
+/// everything its object file depends on.
+pub type CacheKey = blake3::Hash;
+
+/// A directory of compiled units, keyed by [`unit_key`].
+pub struct CompilationCache {
+    dir: PathBuf,
+
+    /// The keys of the units loaded from or stored in the cache so far.
+    used_keys: Mutex<Vec<CacheKey>>,
+}
+
+impl CompilationCache {
+    /// Returns the cache in the given directory.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            used_keys: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the cache in the given output directory.
+    pub fn in_output_dir(output_dir: &Path) -> Self {
+        Self::new(output_dir.join(CACHE_DIR))
+    }
+
+    /// Returns the directory of the cache shared by every project of the
+    /// current user, `~/.crane/cache`, if their home directory is known.
+    pub fn user_cache_dir() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+
+        Some(PathBuf::from(home).join(".crane").join(CACHE_DIR))
+    }
+
+    /// Returns the cached artifacts for the unit with the given key, if there
+    /// are any.
+    pub fn load(&self, key: &CacheKey, name: &str) -> Option<Artifacts> {
+        let object = std::fs::read(self.path(key, "o")).ok()?;
+        let ir = std::fs::read_to_string(self.path(key, "ll")).ok()?;
+        let bitcode = std::fs::read(self.path(key, "bc")).ok();
+
+        self.used_keys.lock().unwrap().push(*key);
+
+        Some(Artifacts {
+            name: name.to_string(),
+            ir,
+            object,
+            bitcode,
+            cached: true,
+        })
+    }
+
+    /// Stores the artifacts of a freshly compiled unit under the given key.
+    pub fn store(&self, key: &CacheKey, artifacts: &Artifacts) -> Result<(), BackendError> {
+        std::fs::create_dir_all(&self.dir).map_err(|source| BackendError::Io {
+            path: self.dir.clone(),
+            source,
+        })?;
+
+        let mut files = vec![("ll", artifacts.ir.as_bytes())];
+
+        if let Some(bitcode) = &artifacts.bitcode {
+            files.push(("bc", bitcode));
+        }
+
+        // The object file is written last, since its presence marks the entry
+        // as complete.
+        files.push(("o", &artifacts.object));
+
+        for (extension, contents) in files {
+            let path = self.path(key, extension);
+
+            // Each file is written next to its final path and then renamed
+            // into place, so that another build sharing the cache never reads
+            // it half-written.
+            let partial_path = path.with_extension(format!("{extension}.{}", std::process::id()));
+
+            std::fs::write(&partial_path, contents)
+                .and_then(|()| std::fs::rename(&partial_path, &path))
+                .map_err(|source| BackendError::Io { path, source })?;
+        }
+
+        self.used_keys.lock().unwrap().push(*key);
+
+        Ok(())
+    }
+
+    /// Adds the keys of the units used from the cache so far to the list of
+    /// entries in the given file, which is created if it doesn't exist.
+    pub fn record_entries(&self, entries: &Path) -> Result<(), BackendError> {
+        let mut keys = read_entries(entries);
+        keys.extend(
+            self.used_keys
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|key| key.to_hex().to_string()),
+        );
+
+        let contents = keys.into_iter().map(|key| key + "\n").collect::<String>();
+
+        std::fs::write(entries, contents).map_err(|source| BackendError::Io {
+            path: entries.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Returns the files in the cache that make up the entries listed in the
+    /// given file, which a build recorded with [`Self::record_entries`].
+    ///
+    /// An entry may also be used by another project that compiled the same
+    /// unit, which then compiles it again.
+    pub fn entry_files(&self, entries: &Path) -> Vec<PathBuf> {
+        read_entries(entries)
+            .iter()
+            .filter_map(|key| CacheKey::from_hex(key).ok())
+            .flat_map(|key| ["o", "ll", "bc"].map(|extension| self.path(&key, extension)))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    fn path(&self, key: &CacheKey, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{extension}", key.to_hex()))
+    }
+}
+
+/// Returns the keys listed in the given file of entries, which has none if it
+/// doesn't exist.
+fn read_entries(entries: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(entries)
+        .map(|contents| contents.lines().map(ToString::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the cache key of a unit from the parts that its object file
+/// depends on.
+///
+/// The key also covers the version of the compiler, since a different
+/// compiler may generate different code for the same input.
+pub fn unit_key(parts: &[&str]) -> CacheKey {
+    let mut hasher = blake3::Hasher::new();
+
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+
+    for part in parts {
+        // Each part is prefixed with its length, so that moving text from one
+        // part to the next changes the key.
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+
+    hasher.finalize()
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BackendError {
+    /// The generated LLVM IR failed verification.
+    #[error("{}", verification_message(.function, .message))]
+    Verification {
+        /// The name of the function that failed verification, if known.
+        function: Option<String>,
+
+        /// The error message reported by LLVM.
+        message: String,
+    },
+
+    /// LLVM could not create a target machine for the requested target.
+    #[error("{0}")]
+    Target(String),
+
+    /// LLVM could not emit the object file of a compilation unit.
+    #[error("Failed to emit the object file: {0}")]
+    Emit(String),
+
+    /// LLVM refused to build an instruction, or the backend found code that
+    /// type checking should have rejected. Either is a bug in the compiler.
+    #[error("Internal code generation error: {0}")]
+    Internal(String),
+
+    /// The linker or archiver failed, with the given output.
+    #[error("`{program}` failed to link:\n{stderr}")]
+    LinkFailed { program: String, stderr: String },
+
+    /// An output file or directory could not be written.
+    #[error("Failed to write `{}`: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl BackendError {
+    /// Returns a [`BackendError::Internal`] error with the given message.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal(message.into())
+    }
+}
+
+fn verification_message(function: &Option<String>, message: &str) -> String {
+    match function {
+        Some(function) => format!("LLVM verification failed in `{function}`:\n{message}"),
+        None => format!("LLVM verification failed:\n{message}"),
+    }
+}
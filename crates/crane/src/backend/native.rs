@@ -1,37 +1,149 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
 
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::{Linkage, Module};
 use inkwell::passes::PassManager;
 use inkwell::targets::{
-    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetTriple,
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetMachine,
+    TargetTriple,
+};
+use inkwell::types::{
+    AnyType, AnyTypeEnum, ArrayType, BasicMetadataTypeEnum, BasicType, BasicTypeEnum, IntType,
+    StructType,
 };
-use inkwell::types::{AnyType, AnyTypeEnum, BasicMetadataTypeEnum, BasicType};
 use inkwell::values::{
-    BasicValue, BasicValueEnum, CallSiteValue, FunctionValue, GlobalValue, IntValue, PointerValue,
+    AnyValue, ArrayValue, AsValueRef, BasicMetadataValueEnum, BasicValue, BasicValueEnum,
+    CallSiteValue, FloatValue, FunctionValue, GlobalValue, InstructionOpcode, InstructionValue,
+    IntValue, PhiValue, PointerValue,
+};
+use inkwell::{
+    AddressSpace, AtomicOrdering, AtomicRMWBinOp, FloatPredicate, IntPredicate, OptimizationLevel,
 };
-use inkwell::{AddressSpace, OptimizationLevel};
 use smol_str::SmolStr;
 use thin_vec::{thin_vec, ThinVec};
 
 use crate::ast::{
-    TyExpr, TyExprKind, TyFnParam, TyIntegerLiteral, TyItem, TyItemKind, TyLiteralKind,
-    TyLocalKind, TyModule, TyPackage, TyPath, TyPathSegment, TyStmtKind, TyUint,
+    BinaryOp, Ident, InlineModuleDecl, TyConst, TyExpr, TyExprKind, TyFn, TyFnParam, TyForExpr,
+    TyIntegerLiteral, TyItem, TyItemKind, TyLiteralKind, TyLocalKind, TyModule, TyPackage,
+    TyPatKind, TyPath, TyPathSegment, TyStmt, TyStmtKind, TyStructDecl, TyUnionDecl, TyVariantData,
+    Visibility,
 };
-use crate::typer::{Ty, TyKind, UintTy};
+use crate::backend::cache::{unit_key, CompilationCache, CACHE_ENTRIES};
+use crate::backend::BackendError;
+use crate::compiler::{CompileOptions, OutputKind};
+use crate::timings;
+use crate::typer::{self, CallingConvention, FloatTy, TyKind, TypeId};
+
+/// The LLVM orderings for the variants of `std::sync::MemoryOrdering`, in the
+/// same order as [`typer::MEMORY_ORDERINGS`].
+const MEMORY_ORDERINGS: [AtomicOrdering; 5] = [
+    AtomicOrdering::Monotonic,
+    AtomicOrdering::Release,
+    AtomicOrdering::Acquire,
+    AtomicOrdering::AcquireRelease,
+    AtomicOrdering::SequentiallyConsistent,
+];
+
+/// The offset basis of the 64-bit FNV-1a hash, which `std::hash` starts from.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The prime of the 64-bit FNV-1a hash, which `std::hash` mixes values with.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The artifacts produced by the [`NativeBackend`] for a single compilation unit.
+#[derive(Debug)]
+pub struct Artifacts {
+    /// The name of the compilation unit.
+    pub name: String,
+
+    /// The textual LLVM IR.
+    pub ir: String,
+
+    /// The object file.
+    pub object: Vec<u8>,
+
+    /// The LLVM bitcode, if it was requested.
+    pub bitcode: Option<Vec<u8>>,
+
+    /// Whether the artifacts were reused from the cache of a previous build.
+    pub cached: bool,
+}
+
+/// The declarations in a package that are shared by all of its compilation units.
+struct PackageDecls<'a> {
+    /// Every function in the package.
+    fns: Vec<&'a TyFn<'a>>,
+
+    /// Every `struct` and `union` in the package.
+    types: Vec<&'a TyItem<'a>>,
+
+    /// Every `const` in the package.
+    consts: Vec<&'a TyConst<'a>>,
+
+    /// The paths of the functions that are visible outside of their compilation unit.
+    exported_fns: HashSet<String>,
+
+    /// The layout-relevant parts of every `struct` and `union`, which every
+    /// compilation unit depends on.
+    types_signature: String,
+}
 
 pub struct NativeBackend<'ctx> {
     context: &'ctx Context,
     builder: Builder<'ctx>,
     module: Module<'ctx>,
     fpm: PassManager<FunctionValue<'ctx>>,
+
+    /// The declaration indices of the fields of each lowered `struct`, in the
+    /// order they are laid out in memory.
+    struct_fields: RefCell<HashMap<String, Vec<usize>>>,
+
+    /// The wrapped type of each tuple struct, which is represented as the
+    /// wrapped type itself.
+    newtypes: RefCell<HashMap<String, TypeId>>,
+
+    /// The bit offset and width of every field of each `#[bitfield]` struct,
+    /// in declaration order.
+    bitfields: RefCell<HashMap<String, Vec<(u32, u32)>>>,
+
+    /// The calls built in the function being compiled.
+    call_sites: RefCell<Vec<CallSiteValue<'ctx>>>,
+
+    /// The functions that can be called from the body of each function being
+    /// compiled, innermost last, which includes the functions nested in it.
+    local_fns: RefCell<Vec<HashMap<TyPath, FunctionValue<'ctx>>>>,
+
+    /// The number of nested functions hoisted so far, which disambiguates
+    /// their names.
+    nested_fn_count: Cell<usize>,
+
+    /// The value of each `const`, which is used in place of a reference to it.
+    consts: RefCell<HashMap<TyPath, BasicValueEnum<'ctx>>>,
+
+    /// The global holding each string literal compiled so far, so that equal
+    /// literals share one global.
+    string_literals: RefCell<HashMap<SmolStr, GlobalValue<'ctx>>>,
+
+    /// The number of values and blocks given each name in each function, for
+    /// [`NativeBackend::value_name`].
+    value_names: RefCell<HashMap<(String, String), usize>>,
 }
 
 impl<'ctx> NativeBackend<'ctx> {
     pub fn new(context: &'ctx Context) -> Self {
-        let module = context.create_module("main");
+        Self::with_module_name(context, "main")
+    }
+
+    fn with_module_name(context: &'ctx Context, name: &str) -> Self {
+        let module = context.create_module(name);
         let builder = context.create_builder();
 
         let fpm = PassManager::create(&module);
@@ -41,33 +153,665 @@ impl<'ctx> NativeBackend<'ctx> {
             module,
             builder,
             fpm,
+            struct_fields: RefCell::new(HashMap::new()),
+            newtypes: RefCell::new(HashMap::new()),
+            bitfields: RefCell::new(HashMap::new()),
+            call_sites: RefCell::new(Vec::new()),
+            local_fns: RefCell::new(Vec::new()),
+            nested_fn_count: Cell::new(0),
+            consts: RefCell::new(HashMap::new()),
+            string_literals: RefCell::new(HashMap::new()),
+            value_names: RefCell::new(HashMap::new()),
         }
     }
 
-    pub fn compile(&self, package: TyPackage) {
-        Target::initialize_aarch64(&InitializationConfig::default());
+    /// Compiles the given package, writing the artifacts to the output
+    /// directory and linking them into the requested [`OutputKind`].
+    ///
+    /// The artifacts are named after `name`, which is usually the stem of the
+    /// input file. Returns the paths of the files that were written, ending
+    /// with the linked artifact, unless [`CompileOptions::link`] is disabled.
+    pub fn compile(
+        &self,
+        package: TyPackage,
+        name: &str,
+        options: &CompileOptions,
+    ) -> Result<Vec<PathBuf>, BackendError> {
+        let output_dir = &options.output_dir;
+
+        std::fs::create_dir_all(output_dir).map_err(|source| BackendError::Io {
+            path: output_dir.clone(),
+            source,
+        })?;
+
+        let cache = options.incremental.then(|| match &options.cache_dir {
+            Some(cache_dir) => CompilationCache::new(cache_dir.clone()),
+            None => CompilationCache::in_output_dir(output_dir),
+        });
+
+        let mut link_libraries = options.link_libraries.clone();
+
+        for module in &package.modules {
+            collect_link_libraries(&module.items, &mut link_libraries);
+        }
 
-        let opt = OptimizationLevel::Default;
-        let reloc = RelocMode::Default;
-        let model = CodeModel::Default;
+        let mut link_args: Vec<String> = options
+            .link_search_paths
+            .iter()
+            .map(|dir| format!("-L{}", dir.display()))
+            .collect();
 
-        let target = Target::from_name("aarch64").expect("Failed to parse target");
+        link_args.extend(link_libraries.iter().map(|library| format!("-l{library}")));
 
-        let target_machine = target
-            .create_target_machine(
-                &TargetTriple::create("aarch64-apple-darwin"),
-                "apple-m2",
-                "",
-                opt,
-                reloc,
-                model,
+        if options.lto {
+            link_args.extend(lto_link_args(&options.target_triple).map(String::from));
+        }
+
+        let all_artifacts = self.compile_units(package, options, cache.as_ref())?;
+
+        // The entries of a shared cache aren't removed with the output
+        // directory, so they're listed in it for `crane clean --cache`.
+        if let (Some(cache), Some(_)) = (&cache, &options.cache_dir) {
+            cache.record_entries(&output_dir.join(CACHE_ENTRIES))?;
+        }
+
+        let filenames = artifact_filenames(name, &all_artifacts);
+
+        for artifacts in all_artifacts.iter().filter(|artifacts| artifacts.cached) {
+            tracing::info!("Reusing `{}` from the cache", artifacts.name);
+        }
+
+        let mut outputs = Vec::new();
+        let mut objects = Vec::new();
+
+        let emit_span = tracing::info_span!(target: timings::TARGET, "emit").entered();
+
+        for (artifacts, filename) in all_artifacts.iter().zip(filenames) {
+            if options.emit_llvm_ir {
+                write_output(
+                    output_dir.join(format!("{filename}.ll")),
+                    artifacts.ir.as_bytes(),
+                    &mut outputs,
+                )?;
+            }
+
+            let object = output_dir.join(format!("{filename}.o"));
+
+            write_output(object.clone(), &artifacts.object, &mut outputs)?;
+
+            if let Some(bitcode) = &artifacts.bitcode {
+                write_output(
+                    output_dir.join(format!("{filename}.bc")),
+                    bitcode,
+                    &mut outputs,
+                )?;
+            }
+
+            objects.push(object);
+        }
+
+        drop(emit_span);
+
+        if !options.link {
+            return Ok(outputs);
+        }
+
+        let _span = tracing::info_span!(target: timings::TARGET, "link").entered();
+
+        let target_is_windows = options.target_triple.contains("windows");
+
+        let artifact = match options.output_kind {
+            OutputKind::Executable => {
+                let executable = if target_is_windows {
+                    output_dir.join(format!("{name}.exe"))
+                } else {
+                    output_dir.join(name)
+                };
+
+                run_linker(
+                    Command::new("clang")
+                        .arg("-o")
+                        .arg(&executable)
+                        .args(&objects)
+                        .args(&link_args),
+                )?;
+
+                executable
+            }
+            OutputKind::StaticLib => {
+                let library = output_dir.join(format!("lib{name}.a"));
+
+                let _ = std::fs::remove_file(&library);
+
+                run_linker(Command::new("ar").arg("rcs").arg(&library).args(&objects))?;
+
+                library
+            }
+            OutputKind::SharedLib => {
+                let library = if options.target_triple.contains("apple") {
+                    output_dir.join(format!("lib{name}.dylib"))
+                } else if target_is_windows {
+                    output_dir.join(format!("{name}.dll"))
+                } else {
+                    output_dir.join(format!("lib{name}.so"))
+                };
+
+                run_linker(
+                    Command::new("clang")
+                        .arg("-shared")
+                        .arg("-o")
+                        .arg(&library)
+                        .args(&objects)
+                        .args(&link_args),
+                )?;
+
+                library
+            }
+        };
+
+        outputs.push(artifact);
+
+        Ok(outputs)
+    }
+
+    /// Compiles the given package to in-memory [`Artifacts`].
+    ///
+    /// Each module that lives in its own file is compiled as a separate
+    /// compilation unit, after the root module. With more than one job, the
+    /// units are compiled in parallel, each in its own LLVM [`Context`]. The
+    /// artifacts are always returned in the same order, regardless of which
+    /// unit finishes first.
+    ///
+    /// This does not touch the filesystem or spawn any processes.
+    pub fn compile_to_buffers(
+        &self,
+        package: TyPackage,
+        options: &CompileOptions,
+    ) -> Result<Vec<Artifacts>, BackendError> {
+        self.compile_units(package, options, None)
+    }
+
+    /// Compiles every compilation unit in the given package, reusing the
+    /// units that haven't changed from the cache, if there is one.
+    fn compile_units(
+        &self,
+        package: TyPackage,
+        options: &CompileOptions,
+        cache: Option<&CompilationCache>,
+    ) -> Result<Vec<Artifacts>, BackendError> {
+        let target_machine = create_target_machine(options)?;
+
+        let mut root_items = ThinVec::new();
+        let mut file_modules = Vec::new();
+
+        for module in package.modules {
+            root_items.extend(split_file_modules(None, module.items, &mut file_modules));
+        }
+
+        let mut fns = Vec::new();
+        let mut types = Vec::new();
+        let mut consts = Vec::new();
+
+        collect_fns(&root_items, &mut fns);
+        collect_types(&root_items, &mut types);
+        collect_consts(&root_items, &mut consts);
+        for (_, items) in &file_modules {
+            collect_fns(items, &mut fns);
+            collect_types(items, &mut types);
+            collect_consts(items, &mut consts);
+        }
+
+        // Executables need every function to be visible across objects, whereas
+        // only the `pub` functions make up the public interface of a library.
+        let exported_fns = fns
+            .iter()
+            .filter(|(_, vis)| !options.output_kind.is_library() || *vis == Visibility::Public)
+            .map(|(fun, _)| fun.path.to_string())
+            .collect::<HashSet<_>>();
+
+        // Every unit can refer to any `const`, so their values are part of
+        // what every unit depends on.
+        let types_signature = types
+            .iter()
+            .map(|item| type_signature(item))
+            .chain(
+                consts
+                    .iter()
+                    .map(|konst| format!("const {} = {:?}", konst.path, konst.value.kind)),
             )
-            .unwrap();
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let decls = PackageDecls {
+            fns: fns.into_iter().map(|(fun, _)| fun).collect(),
+            types,
+            consts,
+            exported_fns,
+            types_signature,
+        };
+
+        // Every unit is compiled on a thread of its own, up to the number of
+        // threads allowed.
+        let jobs = options.jobs.min(options.threads);
+
+        if jobs <= 1 || file_modules.is_empty() {
+            let mut artifacts = vec![self.compile_or_reuse_unit(
+                "main",
+                &root_items,
+                &decls,
+                options,
+                &target_machine,
+                cache,
+            )?];
+
+            for (name, items) in &file_modules {
+                let backend = Self::with_module_name(self.context, name);
+
+                artifacts.push(backend.compile_or_reuse_unit(
+                    name,
+                    items,
+                    &decls,
+                    options,
+                    &target_machine,
+                    cache,
+                )?);
+            }
+
+            return Ok(artifacts);
+        }
+
+        // The root module is compiled on this thread, while the workers take
+        // the other units off of a shared queue.
+        let next_unit = AtomicUsize::new(0);
+        let workers = (jobs - 1).min(file_modules.len());
+
+        let (root_artifacts, mut unit_artifacts) = std::thread::scope(|scope| {
+            let handles = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let target_machine = create_target_machine(options)?;
+
+                        let mut artifacts = Vec::new();
+
+                        loop {
+                            let index = next_unit.fetch_add(1, Ordering::Relaxed);
+
+                            let Some((name, items)) = file_modules.get(index) else {
+                                break;
+                            };
+
+                            let context = Context::create();
+                            let backend = NativeBackend::with_module_name(&context, name);
+
+                            artifacts.push((
+                                index,
+                                backend.compile_or_reuse_unit(
+                                    name,
+                                    items,
+                                    &decls,
+                                    options,
+                                    &target_machine,
+                                    cache,
+                                )?,
+                            ));
+                        }
+
+                        Ok::<_, BackendError>(artifacts)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let root_artifacts = self.compile_or_reuse_unit(
+                "main",
+                &root_items,
+                &decls,
+                options,
+                &target_machine,
+                cache,
+            );
+
+            let mut unit_artifacts = Vec::new();
+
+            for handle in handles {
+                // A panic in a worker is a bug in the compiler, which is
+                // reported as one.
+                let artifacts = handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+
+                unit_artifacts.extend(artifacts);
+            }
+
+            Ok::<_, BackendError>((root_artifacts?, unit_artifacts))
+        })?;
+
+        unit_artifacts.sort_by_key(|(index, _)| *index);
+
+        Ok(std::iter::once(root_artifacts)
+            .chain(unit_artifacts.into_iter().map(|(_, artifacts)| artifacts))
+            .collect())
+    }
+
+    /// Describes the memory layout of every `struct` and `union` in the
+    /// package on the target, with one type per line.
+    pub fn describe_layouts(
+        &self,
+        package: &TyPackage,
+        options: &CompileOptions,
+    ) -> Result<String, BackendError> {
+        let target_machine = create_target_machine(options)?;
+        let target_data = target_machine.get_target_data();
+
+        self.module.set_data_layout(&target_data.get_data_layout());
+
+        let mut types = Vec::new();
+
+        for module in &package.modules {
+            collect_types(&module.items, &mut types);
+        }
+
+        self.register_newtypes(&types);
+
+        let mut layouts = String::new();
+
+        for item in types {
+            let (llvm_type, field_names) = match &item.kind {
+                TyItemKind::Struct(struct_decl) if item.attr("bitfield").is_some() => {
+                    let llvm_type = self.lower_struct(item, struct_decl, &target_data)?;
+
+                    let fields = struct_decl
+                        .0
+                        .fields()
+                        .iter()
+                        .zip(bitfield_layout(struct_decl))
+                        .map(|(field, (offset, width))| {
+                            let name = field.name.as_ref().map(ToString::to_string);
+
+                            format!(
+                                "{} @ bits {offset}..{}",
+                                name.unwrap_or_default(),
+                                offset + width
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    layouts.push_str(&format!(
+                        "{}: size={} bytes, align={} bytes, fields=[{}]\n",
+                        item.name,
+                        target_data.get_abi_size(&llvm_type),
+                        target_data.get_abi_alignment(&llvm_type),
+                        fields
+                    ));
+
+                    continue;
+                }
+                TyItemKind::Struct(struct_decl) if struct_decl.newtype().is_some() => {
+                    let inner = match struct_decl.newtype() {
+                        Some(ty) => self.lower_type(ty)?,
+                        None => None,
+                    };
+
+                    (
+                        self.context
+                            .struct_type(&inner.into_iter().collect::<Vec<_>>(), false),
+                        vec!["0".to_string()],
+                    )
+                }
+                TyItemKind::Struct(struct_decl) => {
+                    let field_names = self
+                        .struct_field_order(item, struct_decl, &target_data)?
+                        .into_iter()
+                        .map(|index| match &struct_decl.0.fields()[index].name {
+                            Some(name) => name.to_string(),
+                            None => index.to_string(),
+                        })
+                        .collect::<Vec<_>>();
+
+                    (
+                        self.lower_struct(item, struct_decl, &target_data)?,
+                        field_names,
+                    )
+                }
+                TyItemKind::Union(union_decl) => (
+                    self.lower_union(item, union_decl, &target_data)?,
+                    vec!["tag".to_string(), "payload".to_string()],
+                ),
+                _ => continue,
+            };
+
+            let fields = field_names
+                .iter()
+                .enumerate()
+                .map(|(index, name)| {
+                    let offset = target_data
+                        .offset_of_element(&llvm_type, index as u32)
+                        .ok_or_else(|| {
+                            BackendError::internal(format!("`{}` has no field {index}.", item.name))
+                        })?;
+
+                    Ok(format!("{name} @ {offset}"))
+                })
+                .collect::<Result<Vec<_>, BackendError>>()?
+                .join(", ");
+
+            layouts.push_str(&format!(
+                "{}: size={} bytes, align={} bytes, fields=[{}]\n",
+                item.name,
+                target_data.get_abi_size(&llvm_type),
+                target_data.get_abi_alignment(&llvm_type),
+                fields
+            ));
+        }
+
+        Ok(layouts)
+    }
+
+    /// Compiles a single compilation unit, or reuses its artifacts from the
+    /// cache if nothing that its object file depends on has changed.
+    fn compile_or_reuse_unit(
+        &self,
+        name: &str,
+        items: &ThinVec<TyItem>,
+        decls: &PackageDecls,
+        options: &CompileOptions,
+        target_machine: &TargetMachine,
+        cache: Option<&CompilationCache>,
+    ) -> Result<Artifacts, BackendError> {
+        let Some(cache) = cache else {
+            return self.compile_unit(name, items, decls, options, target_machine);
+        };
+
+        let (cpu, features) = target_cpu_and_features(options);
+
+        // The typed items include the type of every function they call, so
+        // changing the signature of a function invalidates its callers, while
+        // changing its body only invalidates its own unit.
+        let key = unit_key(&[
+            name,
+            &format!("{items:?}"),
+            &decls.types_signature,
+            &options.target_triple,
+            &format!(
+                "{:?} bitcode={} tailcallelim={} lto={} opt={} debug={} test={}",
+                options.output_kind,
+                options.emit_bitcode,
+                options.tail_call_elim,
+                options.lto,
+                options.opt_level,
+                options.debug,
+                options.test
+            ),
+            &cpu,
+            &features,
+        ]);
+
+        if let Some(artifacts) = cache.load(&key, name) {
+            return Ok(artifacts);
+        }
+
+        let artifacts = self.compile_unit(name, items, decls, options, target_machine)?;
+
+        cache.store(&key, &artifacts)?;
+
+        Ok(artifacts)
+    }
+
+    /// Compiles a single compilation unit into its own LLVM module.
+    fn compile_unit(
+        &self,
+        name: &str,
+        items: &ThinVec<TyItem>,
+        decls: &PackageDecls,
+        options: &CompileOptions,
+        target_machine: &TargetMachine,
+    ) -> Result<Artifacts, BackendError> {
+        tracing::info!("Generating code for the `{name}` unit");
+
+        let codegen_span = tracing::info_span!(target: timings::TARGET, "codegen").entered();
+
+        let target_data = target_machine.get_target_data();
+
+        self.module.set_triple(&target_machine.get_triple());
+        self.module.set_data_layout(&target_data.get_data_layout());
 
         self.fpm.add_instruction_combining_pass();
 
+        if options.opt_level >= 1 {
+            self.fpm.add_promote_memory_to_register_pass();
+            self.fpm.add_cfg_simplification_pass();
+        }
+
+        if options.opt_level >= 2 {
+            self.fpm.add_reassociate_pass();
+            self.fpm.add_gvn_pass();
+            self.fpm.add_cfg_simplification_pass();
+        }
+
+        if options.opt_level >= 3 {
+            self.fpm.add_aggressive_dce_pass();
+        }
+
+        if options.tail_call_elim {
+            self.fpm.add_tail_call_elimination_pass();
+        }
+
         self.fpm.initialize();
 
+        self.compile_preamble(options)?;
+
+        self.register_newtypes(&decls.types);
+
+        // Lower every type in the package before any function signatures
+        // can refer to them.
+        for item in &decls.types {
+            match &item.kind {
+                TyItemKind::Struct(struct_decl) if struct_decl.newtype().is_some() => {}
+                TyItemKind::Struct(struct_decl) => {
+                    self.lower_struct(item, struct_decl, &target_data)?;
+                }
+                TyItemKind::Union(union_decl) => {
+                    self.lower_union(item, union_decl, &target_data)?;
+                }
+                _ => {}
+            }
+        }
+
+        // Define every `const` in the package in each unit, as they are
+        // internal to it.
+        for konst in &decls.consts {
+            self.define_const(konst)?;
+        }
+
+        // Declare every function in the package up front, so that calls across
+        // compilation units resolve to external declarations.
+        for fun in &decls.fns {
+            self.declare_fn(fun)?;
+        }
+
+        // HACK: Reverse the items so we define the helper functions before `main`.
+        // This should be replaced with a call graph.
+        for item in items.iter().rev() {
+            self.compile_item(item)?;
+        }
+
+        let package_fns = decls
+            .fns
+            .iter()
+            .map(|fun| fun.path.to_string())
+            .collect::<HashSet<_>>();
+
+        for function in self.module.get_functions().collect::<Vec<_>>() {
+            let is_definition = function.count_basic_blocks() > 0;
+
+            // Only keep the declarations of the functions from other units
+            // that this unit calls, so that it doesn't depend on the rest.
+            let is_unused_package_fn = !is_definition
+                && function
+                    .as_global_value()
+                    .as_pointer_value()
+                    .get_first_use()
+                    .is_none()
+                && function
+                    .get_name()
+                    .to_str()
+                    .is_ok_and(|name| package_fns.contains(name));
+
+            if is_unused_package_fn {
+                // SAFETY: The function is a declaration without any uses.
+                unsafe { function.delete() };
+                continue;
+            }
+
+            let is_exported = function
+                .get_name()
+                .to_str()
+                .is_ok_and(|name| decls.exported_fns.contains(name));
+
+            if is_definition && !is_exported {
+                function.set_linkage(Linkage::Internal);
+            }
+
+            if is_definition {
+                self.add_target_attributes(function, target_machine);
+            }
+        }
+
+        drop(codegen_span);
+
+        {
+            let _span = tracing::info_span!(target: timings::TARGET, "optimize").entered();
+
+            self.optimize_and_verify()?;
+        }
+
+        let _span = tracing::info_span!(target: timings::TARGET, "emit").entered();
+
+        // With LTO, the object files hold bitcode that the linker compiles
+        // to machine code once it can see the whole program.
+        let object = if options.lto {
+            self.module.write_bitcode_to_memory()
+        } else {
+            target_machine
+                .write_to_memory_buffer(&self.module, FileType::Object)
+                .map_err(|err| BackendError::Emit(err.to_string()))?
+        };
+
+        let bitcode = options
+            .emit_bitcode
+            .then(|| self.module.write_bitcode_to_memory().as_slice().to_vec());
+
+        Ok(Artifacts {
+            name: name.to_string(),
+            ir: self.module.print_to_string().to_string(),
+            object: object.as_slice().to_vec(),
+            bitcode,
+            cached: false,
+        })
+    }
+
+    /// Defines the functions from `std` that are implemented in the backend.
+    fn compile_preamble(&self, options: &CompileOptions) -> Result<(), BackendError> {
         // Define `puts`.
         let puts = {
             let fn_name = "puts";
@@ -82,12 +826,9 @@ impl<'ctx> NativeBackend<'ctx> {
                 false,
             );
 
-            let puts = self
-                .module
+            self.module
                 .add_function(fn_name, fn_type, Some(Linkage::External));
 
-            self.verify_fn(fn_name, &puts).unwrap();
-
             fn_name
         };
 
@@ -112,12 +853,9 @@ impl<'ctx> NativeBackend<'ctx> {
                 true,
             );
 
-            let sprintf = self
-                .module
+            self.module
                 .add_function(fn_name, fn_type, Some(Linkage::External));
 
-            self.verify_fn(fn_name, &sprintf).unwrap();
-
             fn_name
         };
 
@@ -136,11 +874,41 @@ impl<'ctx> NativeBackend<'ctx> {
                 true,
             );
 
-            let printf = self
+            self.module
+                .add_function(fn_name, fn_type, Some(Linkage::External));
+
+            fn_name
+        };
+
+        // Define `exit`.
+        let exit = {
+            let fn_name = "exit";
+
+            let i32_type = self.context.i32_type();
+
+            let fn_type = self
+                .context
+                .void_type()
+                .fn_type(&[i32_type.as_basic_type_enum().into()], false);
+
+            let exit = self
                 .module
                 .add_function(fn_name, fn_type, Some(Linkage::External));
+            exit.add_attribute(AttributeLoc::Function, self.noreturn_attribute());
 
-            self.verify_fn(fn_name, &printf).unwrap();
+            fn_name
+        };
+
+        // Define `abort`.
+        let abort = {
+            let fn_name = "abort";
+
+            let fn_type = self.context.void_type().fn_type(&[], false);
+
+            let abort = self
+                .module
+                .add_function(fn_name, fn_type, Some(Linkage::External));
+            abort.add_attribute(AttributeLoc::Function, self.noreturn_attribute());
 
             fn_name
         };
@@ -161,7 +929,7 @@ impl<'ctx> NativeBackend<'ctx> {
 
             let fn_value = self.module.add_function(fn_name, fn_type, None);
 
-            let value_param = fn_value.get_first_param().unwrap();
+            let value_param = nth_param(fn_value, 0)?;
 
             let entry = self.context.append_basic_block(fn_value, "entry");
 
@@ -192,8 +960,6 @@ impl<'ctx> NativeBackend<'ctx> {
             }
 
             self.builder.build_return(None);
-
-            self.verify_fn(fn_name, &fn_value).unwrap();
         }
 
         // Define `std::io::println`.
@@ -212,7 +978,7 @@ impl<'ctx> NativeBackend<'ctx> {
 
             let fn_value = self.module.add_function(fn_name, fn_type, None);
 
-            let value_param = fn_value.get_first_param().unwrap();
+            let value_param = nth_param(fn_value, 0)?;
 
             let entry = self.context.append_basic_block(fn_value, "entry");
 
@@ -226,8 +992,126 @@ impl<'ctx> NativeBackend<'ctx> {
             }
 
             self.builder.build_return(None);
+        }
+
+        // Define `std::io::read_line`.
+        //
+        // Reads a line from stdin with `getline`, without its trailing newline.
+        // At the end of the input, this returns an empty string.
+        {
+            let fn_name = "std::io::read_line";
+
+            let i8_type = self.context.i8_type();
+            let i64_type = self.context.i64_type();
+            let ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let getline = self.module.add_function(
+                "getline",
+                i64_type.fn_type(&[ptr_type.into(), ptr_type.into(), ptr_type.into()], false),
+                Some(Linkage::External),
+            );
+
+            // The C library's `stdin`, which is called `__stdinp` on Apple platforms.
+            let stdin_name = if options.target_triple.contains("apple") {
+                "__stdinp"
+            } else {
+                "stdin"
+            };
+
+            let stdin = self.module.add_global(ptr_type, None, stdin_name);
+            stdin.set_linkage(Linkage::External);
+
+            let fn_value = self
+                .module
+                .add_function(fn_name, ptr_type.fn_type(&[], false), None);
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+            let eof_block = self.context.append_basic_block(fn_value, "eof");
+            let read_block = self.context.append_basic_block(fn_value, "read");
+            let strip_block = self.context.append_basic_block(fn_value, "strip_newline");
+            let done_block = self.context.append_basic_block(fn_value, "done");
+
+            self.builder.position_at_end(entry);
+
+            let line = self.builder.build_alloca(ptr_type, "line");
+            self.builder.build_store(line, ptr_type.const_null());
+
+            let capacity = self.builder.build_alloca(i64_type, "capacity");
+            self.builder.build_store(capacity, i64_type.const_zero());
+
+            let stream = self
+                .builder
+                .build_load(ptr_type, stdin.as_pointer_value(), "stdin");
+
+            let len = self
+                .builder
+                .build_call(
+                    getline,
+                    &[line.into(), capacity.into(), stream.into()],
+                    "len",
+                )
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+
+            // `getline` returns -1 at the end of the input.
+            let is_eof = self.builder.build_int_compare(
+                IntPredicate::SLT,
+                len,
+                i64_type.const_int(1, false),
+                "is_eof",
+            );
+
+            self.builder
+                .build_conditional_branch(is_eof, eof_block, read_block);
+
+            self.builder.position_at_end(eof_block);
+
+            let empty = self.compile_string_literal("\"\"".into());
+
+            self.builder
+                .build_return(Some(&empty.as_basic_value_enum()));
+
+            self.builder.position_at_end(read_block);
+
+            let buffer = self
+                .builder
+                .build_load(ptr_type, line, "buffer")
+                .into_pointer_value();
+
+            let last_index =
+                self.builder
+                    .build_int_sub(len, i64_type.const_int(1, false), "last_index");
+
+            let last = unsafe {
+                self.builder
+                    .build_gep(i8_type, buffer, &[last_index], "last")
+            };
+
+            let last_char = self
+                .builder
+                .build_load(i8_type, last, "last_char")
+                .into_int_value();
+
+            let is_newline = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                last_char,
+                i8_type.const_int(b'\n' as u64, false),
+                "is_newline",
+            );
+
+            self.builder
+                .build_conditional_branch(is_newline, strip_block, done_block);
+
+            self.builder.position_at_end(strip_block);
+
+            self.builder.build_store(last, i8_type.const_zero());
+            self.builder.build_unconditional_branch(done_block);
 
-            self.verify_fn(fn_name, &fn_value).unwrap();
+            self.builder.position_at_end(done_block);
+
+            self.builder.build_return(Some(&buffer));
         }
 
         // Define `std::int::int_add`.
@@ -246,8 +1130,8 @@ impl<'ctx> NativeBackend<'ctx> {
 
             let fn_value = self.module.add_function(fn_name, fn_type, None);
 
-            let lhs_param = fn_value.get_first_param().unwrap().into_int_value();
-            let rhs_param = fn_value.get_nth_param(1).unwrap().into_int_value();
+            let lhs_param = nth_param(fn_value, 0)?.into_int_value();
+            let rhs_param = nth_param(fn_value, 1)?.into_int_value();
 
             let entry = self.context.append_basic_block(fn_value, "entry");
 
@@ -256,8 +1140,6 @@ impl<'ctx> NativeBackend<'ctx> {
             let sum = self.builder.build_int_add(lhs_param, rhs_param, "sum");
 
             self.builder.build_return(Some(&sum));
-
-            self.verify_fn(fn_name, &fn_value).unwrap();
         }
 
         // Define `std::int::int_to_string`.
@@ -272,7 +1154,7 @@ impl<'ctx> NativeBackend<'ctx> {
 
             let fn_value = self.module.add_function(fn_name, fn_type, None);
 
-            let int_value = fn_value.get_first_param().unwrap().into_int_value();
+            let int_value = nth_param(fn_value, 0)?.into_int_value();
 
             let entry = self.context.append_basic_block(fn_value, "entry");
 
@@ -281,7 +1163,7 @@ impl<'ctx> NativeBackend<'ctx> {
             let buffer = self
                 .builder
                 .build_malloc(i8_ptr_type, "buffer")
-                .expect("Failed to allocate `int_to_string` buffer.");
+                .map_err(BackendError::internal)?;
 
             let template = b"%1$d";
 
@@ -297,462 +1179,5335 @@ impl<'ctx> NativeBackend<'ctx> {
             global.set_constant(true);
             global.set_initializer(&template);
 
-            if let Some(callee) = self.module.get_function(sprintf) {
-                self.builder.build_call(
-                    callee,
-                    &[
-                        buffer.into(),
-                        global.as_basic_value_enum().into(),
-                        int_value.into(),
-                    ],
-                    "tmp",
-                );
-            } else {
-                panic!("Function '{}' not found.", sprintf);
-            }
-
+            let callee = self.function(sprintf)?;
+            self.builder.build_call(
+                callee,
+                &[
+                    buffer.into(),
+                    global.as_basic_value_enum().into(),
+                    int_value.into(),
+                ],
+                "tmp",
+            );
+
+            self.builder.build_return(Some(&buffer));
+        }
+
+        // Define `std::float::float_to_string`.
+        {
+            let fn_name = "std::float::float_to_string";
+
+            let f64_type = self.context.f64_type();
+            let i8_type = self.context.i8_type();
+            let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let fn_type = i8_ptr_type.fn_type(&[f64_type.as_basic_type_enum().into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let float_value = nth_param(fn_value, 0)?.into_float_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            // Large enough for any `double` formatted with `%g`.
+            let buffer = self
+                .builder
+                .build_malloc(i8_type.array_type(32), "buffer")
+                .map_err(BackendError::internal)?;
+
+            let template = b"%g";
+
+            let i8_array_type = i8_type.array_type(template.len() as u32 + 1);
+
+            let template = self.context.const_string(template, true);
+
+            let global = self
+                .module
+                .add_global(i8_array_type, None, "float_to_string_template");
+            global.set_linkage(Linkage::Internal);
+            global.set_constant(true);
+            global.set_initializer(&template);
+
+            let callee = self.function(sprintf)?;
+            self.builder.build_call(
+                callee,
+                &[
+                    buffer.into(),
+                    global.as_basic_value_enum().into(),
+                    float_value.into(),
+                ],
+                "tmp",
+            );
+
             self.builder.build_return(Some(&buffer));
+        }
+
+        // Define `strcmp`.
+        let strcmp = {
+            let fn_name = "strcmp";
+
+            let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+            let i32_type = self.context.i32_type();
+
+            let fn_type = i32_type.fn_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false);
+
+            self.module
+                .add_function(fn_name, fn_type, Some(Linkage::External));
+
+            fn_name
+        };
 
-            self.verify_fn(fn_name, &fn_value).unwrap();
+        // Define `std::string::string_eq`.
+        {
+            let fn_name = "std::string::string_eq";
+
+            let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+            let bool_type = self.context.bool_type();
+
+            let fn_type = bool_type.fn_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let a = nth_param(fn_value, 0)?;
+            a.set_name("a");
+            let b = nth_param(fn_value, 1)?;
+            b.set_name("b");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let ordering = self
+                .builder
+                .build_call(self.function(strcmp)?, &[a.into(), b.into()], "ordering")
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+
+            let is_eq = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                ordering,
+                ordering.get_type().const_zero(),
+                "is_eq",
+            );
+
+            self.builder.build_return(Some(&is_eq));
         }
 
-        for item in package
-            .modules
-            .into_iter()
-            .flat_map(|module| module.items)
-            // HACK: Reverse the items so we define the helper functions before `main`.
-            // This should be replaced with a call graph.
-            .rev()
+        // Define `strlen`.
+        let strlen = {
+            let fn_name = "strlen";
+
+            let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+            let i64_type = self.context.i64_type();
+
+            let fn_type = i64_type.fn_type(&[i8_ptr_type.into()], false);
+
+            self.module
+                .add_function(fn_name, fn_type, Some(Linkage::External));
+
+            fn_name
+        };
+
+        // Define `std::string::concat`.
+        {
+            let fn_name = "std::string::concat";
+
+            let i8_type = self.context.i8_type();
+            let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let fn_type = i8_ptr_type.fn_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let a = nth_param(fn_value, 0)?;
+            a.set_name("a");
+            let b = nth_param(fn_value, 1)?;
+            b.set_name("b");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let strlen = self.function(strlen)?;
+
+            let a_len = self
+                .builder
+                .build_call(strlen, &[a.into()], "a_len")
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+            let b_len = self
+                .builder
+                .build_call(strlen, &[b.into()], "b_len")
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+
+            // The lengths of both strings, and their null terminator.
+            let len = self.builder.build_int_add(a_len, b_len, "len");
+            let len =
+                self.builder
+                    .build_int_add(len, self.context.i64_type().const_int(1, false), "len");
+
+            let buffer = self
+                .builder
+                .build_array_malloc(i8_type, len, "buffer")
+                .map_err(BackendError::internal)?;
+
+            let template = self
+                .builder
+                .build_global_string_ptr("%s%s", "concat_template");
+
+            let callee = self.function(sprintf)?;
+            self.builder.build_call(
+                callee,
+                &[
+                    buffer.into(),
+                    template.as_pointer_value().into(),
+                    a.into(),
+                    b.into(),
+                ],
+                "tmp",
+            );
+
+            self.builder.build_return(Some(&buffer));
+        }
+
+        // Define `std::string::bool_to_string`.
+        {
+            let fn_name = "std::string::bool_to_string";
+
+            let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+            let bool_type = self.context.bool_type();
+
+            let fn_type = i8_ptr_type.fn_type(&[bool_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?.into_int_value();
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let true_string = self.builder.build_global_string_ptr("true", "true_string");
+            let false_string = self
+                .builder
+                .build_global_string_ptr("false", "false_string");
+
+            let string = self.builder.build_select(
+                value,
+                true_string.as_pointer_value(),
+                false_string.as_pointer_value(),
+                "string",
+            );
+
+            self.builder.build_return(Some(&string));
+        }
+
+        // Define `std::string::to_string`, which `String` implements `ToString`
+        // with.
+        {
+            let fn_name = "std::string::to_string";
+
+            let i8_ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+
+            let fn_type = i8_ptr_type.fn_type(&[i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?;
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+            self.builder.build_return(Some(&value));
+        }
+
+        // Define `std::string::debug_repr`, which writes a string in quotes.
+        {
+            let fn_name = "std::string::debug_repr";
+
+            let i8_type = self.context.i8_type();
+            let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let fn_type = i8_ptr_type.fn_type(&[i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?;
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let strlen = self.function(strlen)?;
+
+            let value_len = self
+                .builder
+                .build_call(strlen, &[value.into()], "value_len")
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+
+            // The string, its quotes, and the null terminator.
+            let len = self.builder.build_int_add(
+                value_len,
+                self.context.i64_type().const_int(3, false),
+                "len",
+            );
+
+            let buffer = self
+                .builder
+                .build_array_malloc(i8_type, len, "buffer")
+                .map_err(BackendError::internal)?;
+
+            let template = self
+                .builder
+                .build_global_string_ptr("\"%s\"", "debug_repr_template");
+
+            let callee = self.function(sprintf)?;
+            self.builder.build_call(
+                callee,
+                &[
+                    buffer.into(),
+                    template.as_pointer_value().into(),
+                    value.into(),
+                ],
+                "tmp",
+            );
+
+            self.builder.build_return(Some(&buffer));
+        }
+
+        // Define `std::string::clone`, which copies a string into a new buffer.
+        {
+            let fn_name = "std::string::clone";
+
+            let i8_type = self.context.i8_type();
+            let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let fn_type = i8_ptr_type.fn_type(&[i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?;
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let strlen = self.function(strlen)?;
+
+            let value_len = self
+                .builder
+                .build_call(strlen, &[value.into()], "value_len")
+                .try_as_basic_value()
+                .left()
+                .ok_or_else(returns_no_value)?
+                .into_int_value();
+
+            // The string and its null terminator.
+            let len = self.builder.build_int_add(
+                value_len,
+                self.context.i64_type().const_int(1, false),
+                "len",
+            );
+
+            let buffer = self
+                .builder
+                .build_array_malloc(i8_type, len, "buffer")
+                .map_err(BackendError::internal)?;
+
+            let template = self.builder.build_global_string_ptr("%s", "clone_template");
+
+            let callee = self.function(sprintf)?;
+            self.builder.build_call(
+                callee,
+                &[
+                    buffer.into(),
+                    template.as_pointer_value().into(),
+                    value.into(),
+                ],
+                "tmp",
+            );
+
+            self.builder.build_return(Some(&buffer));
+        }
+
+        // Define `std::hash::hash_int`, `std::hash::hash_float` and
+        // `std::hash::hash_bool`, which hash the bits of the value XORed with
+        // the FNV offset basis.
+        for (fn_name, param_type) in [
+            (
+                "std::hash::hash_int",
+                self.context.i64_type().as_basic_type_enum(),
+            ),
+            (
+                "std::hash::hash_float",
+                self.context.f64_type().as_basic_type_enum(),
+            ),
+            (
+                "std::hash::hash_bool",
+                self.context.bool_type().as_basic_type_enum(),
+            ),
+        ] {
+            let i64_type = self.context.i64_type();
+
+            let fn_type = i64_type.fn_type(&[param_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?;
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let bits = match value {
+                BasicValueEnum::FloatValue(value) => self
+                    .builder
+                    .build_bitcast(value, i64_type, "bits")
+                    .into_int_value(),
+                value => self.builder.build_int_z_extend_or_bit_cast(
+                    value.into_int_value(),
+                    i64_type,
+                    "bits",
+                ),
+            };
+
+            let hash =
+                self.builder
+                    .build_xor(bits, i64_type.const_int(FNV_OFFSET_BASIS, false), "hash");
+
+            self.builder.build_return(Some(&hash));
+        }
+
+        // Define `std::hash::hash_string`, which hashes the bytes of a string
+        // with FNV-1a.
+        {
+            let fn_name = "std::hash::hash_string";
+
+            let i64_type = self.context.i64_type();
+            let i8_type = self.context.i8_type();
+            let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+            let fn_type = i64_type.fn_type(&[i8_ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?.into_pointer_value();
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+            let loop_block = self.context.append_basic_block(fn_value, "loop");
+            let body_block = self.context.append_basic_block(fn_value, "body");
+            let end_block = self.context.append_basic_block(fn_value, "end");
+
+            self.builder.position_at_end(entry);
+            self.builder.build_unconditional_branch(loop_block);
+
+            self.builder.position_at_end(loop_block);
+
+            let hash = self.builder.build_phi(i64_type, "hash");
+            let index = self.builder.build_phi(i64_type, "index");
+
+            let byte_ptr = unsafe {
+                self.builder.build_in_bounds_gep(
+                    i8_type,
+                    value,
+                    &[index.as_basic_value().into_int_value()],
+                    "byte_ptr",
+                )
+            };
+            let byte = self
+                .builder
+                .build_load(i8_type, byte_ptr, "byte")
+                .into_int_value();
+
+            // The null terminator ends the string.
+            let is_end = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                byte,
+                i8_type.const_zero(),
+                "is_end",
+            );
+            self.builder
+                .build_conditional_branch(is_end, end_block, body_block);
+
+            self.builder.position_at_end(body_block);
+
+            let byte = self.builder.build_int_z_extend(byte, i64_type, "byte");
+            let mixed =
+                self.builder
+                    .build_xor(hash.as_basic_value().into_int_value(), byte, "mixed");
+            let next_hash = self.builder.build_int_mul(
+                mixed,
+                i64_type.const_int(FNV_PRIME, false),
+                "next_hash",
+            );
+            let next_index = self.builder.build_int_add(
+                index.as_basic_value().into_int_value(),
+                i64_type.const_int(1, false),
+                "next_index",
+            );
+            self.builder.build_unconditional_branch(loop_block);
+
+            hash.add_incoming(&[
+                (&i64_type.const_int(FNV_OFFSET_BASIS, false), entry),
+                (&next_hash, body_block),
+            ]);
+            index.add_incoming(&[(&i64_type.const_zero(), entry), (&next_index, body_block)]);
+
+            self.builder.position_at_end(end_block);
+            self.builder
+                .build_return(Some(&hash.as_basic_value().into_int_value()));
+        }
+
+        // Define `std::hash::combine`, which mixes a value into a hash the way
+        // FNV-1a mixes in a byte.
         {
-            self.compile_item(&item);
+            let fn_name = "std::hash::combine";
+
+            let i64_type = self.context.i64_type();
+
+            let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let hash = nth_param(fn_value, 0)?.into_int_value();
+            hash.set_name("hash");
+            let value = nth_param(fn_value, 1)?.into_int_value();
+            value.set_name("value");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let mixed = self.builder.build_xor(hash, value, "mixed");
+            let combined =
+                self.builder
+                    .build_int_mul(mixed, i64_type.const_int(FNV_PRIME, false), "combined");
+
+            self.builder.build_return(Some(&combined));
         }
 
-        self.module
-            .print_to_file("build/main.ll")
-            .expect("Failed to emit main.ll");
+        // Define `std::process::exit`.
+        //
+        // The exit code is truncated to the `int` that the C library's `exit`
+        // takes.
+        {
+            let fn_name = "std::process::exit";
+
+            let i32_type = self.context.i32_type();
+            let i64_type = self.context.i64_type();
+
+            let fn_type = self
+                .context
+                .void_type()
+                .fn_type(&[i64_type.as_basic_type_enum().into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+            fn_value.add_attribute(AttributeLoc::Function, self.noreturn_attribute());
+
+            let code_param = nth_param(fn_value, 0)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let code = self
+                .builder
+                .build_int_truncate(code_param, i32_type, "code");
+
+            let callee = self.function(exit)?;
+            self.builder.build_call(callee, &[code.into()], "");
+
+            self.builder.build_unreachable();
+        }
+
+        // The C library's `stderr`, which is called `__stderrp` on Apple platforms.
+        let stderr = {
+            let stderr_name = if options.target_triple.contains("apple") {
+                "__stderrp"
+            } else {
+                "stderr"
+            };
+
+            let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+
+            let stderr = self.module.add_global(ptr_type, None, stderr_name);
+            stderr.set_linkage(Linkage::External);
+
+            stderr
+        };
+
+        // Define `std::process::abort`.
+        self.define_abort_fn("std::process::abort", b"%s\n", 1, abort, stderr)?;
+
+        // Define `std::process::assert_failed`, which an `assert` calls with
+        // its location and message when it fails.
+        {
+            let fn_value = self.define_abort_fn(
+                "std::process::assert_failed",
+                b"Assertion failed at %s: %s\n",
+                2,
+                abort,
+                stderr,
+            )?;
+
+            fn_value.add_attribute(
+                AttributeLoc::Function,
+                self.context
+                    .create_enum_attribute(Attribute::get_named_enum_kind_id("cold"), 0),
+            );
+        }
+
+        // Define `std::process::todo` and `std::process::unimplemented`, which
+        // abort with a message in debug builds. In release builds they are
+        // assumed to be unreachable, like the code they stand in for.
+        for (fn_name, template) in [
+            ("std::process::todo", b"not yet implemented\n".as_slice()),
+            (
+                "std::process::unimplemented",
+                b"not implemented\n".as_slice(),
+            ),
+        ] {
+            if options.debug {
+                self.define_abort_fn(fn_name, template, 0, abort, stderr)?;
+            } else {
+                let fn_value = self.module.add_function(
+                    fn_name,
+                    self.context.void_type().fn_type(&[], false),
+                    None,
+                );
+                fn_value.add_attribute(AttributeLoc::Function, self.noreturn_attribute());
+
+                let entry = self.context.append_basic_block(fn_value, "entry");
+
+                self.builder.position_at_end(entry);
+                self.builder.build_unreachable();
+            }
+        }
+
+        let target_is_windows = options.target_triple.contains("windows");
+
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+
+        // Define `std::time::now`.
+        {
+            let fn_name = "std::time::now";
+
+            let fn_type = i64_type.fn_type(&[], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let nanos = if target_is_windows {
+                // `FILETIME` holds the number of 100ns intervals since 1601-01-01.
+                const UNIX_EPOCH_AS_FILETIME: u64 = 116_444_736_000_000_000;
+
+                let get_system_time = self.module.add_function(
+                    "GetSystemTimeAsFileTime",
+                    self.context
+                        .void_type()
+                        .fn_type(&[ptr_type.as_basic_type_enum().into()], false),
+                    Some(Linkage::External),
+                );
+
+                let filetime = self.builder.build_alloca(i64_type, "filetime");
+
+                self.builder
+                    .build_call(get_system_time, &[filetime.into()], "");
+
+                let intervals = self
+                    .builder
+                    .build_load(i64_type, filetime, "intervals")
+                    .into_int_value();
+
+                let intervals = self.builder.build_int_sub(
+                    intervals,
+                    i64_type.const_int(UNIX_EPOCH_AS_FILETIME, false),
+                    "intervals",
+                );
+
+                self.builder
+                    .build_int_mul(intervals, i64_type.const_int(100, false), "nanos")
+            } else {
+                const CLOCK_REALTIME: u64 = 0;
+
+                let clock_gettime = self.module.add_function(
+                    "clock_gettime",
+                    i32_type.fn_type(
+                        &[
+                            i32_type.as_basic_type_enum().into(),
+                            ptr_type.as_basic_type_enum().into(),
+                        ],
+                        false,
+                    ),
+                    Some(Linkage::External),
+                );
+
+                // `struct timespec { time_t tv_sec; long tv_nsec; }`
+                let timespec_type = self
+                    .context
+                    .struct_type(&[i64_type.into(), i64_type.into()], false);
+
+                let timespec = self.builder.build_alloca(timespec_type, "timespec");
+
+                self.builder.build_call(
+                    clock_gettime,
+                    &[
+                        i32_type.const_int(CLOCK_REALTIME, false).into(),
+                        timespec.into(),
+                    ],
+                    "",
+                );
+
+                let secs_ptr = self
+                    .builder
+                    .build_struct_gep(timespec_type, timespec, 0, "secs_ptr")
+                    .map_err(invalid_field_index)?;
+                let secs = self
+                    .builder
+                    .build_load(i64_type, secs_ptr, "secs")
+                    .into_int_value();
+
+                let nsecs_ptr = self
+                    .builder
+                    .build_struct_gep(timespec_type, timespec, 1, "nsecs_ptr")
+                    .map_err(invalid_field_index)?;
+                let nsecs = self
+                    .builder
+                    .build_load(i64_type, nsecs_ptr, "nsecs")
+                    .into_int_value();
+
+                let secs_as_nanos = self.builder.build_int_mul(
+                    secs,
+                    i64_type.const_int(1_000_000_000, false),
+                    "secs_as_nanos",
+                );
+
+                self.builder.build_int_add(secs_as_nanos, nsecs, "nanos")
+            };
+
+            self.builder.build_return(Some(&nanos));
+        }
+
+        // The program arguments are stashed in these globals by the C `main`
+        // that wraps the user's `main`. Every compilation unit defines them, and
+        // the linker merges the definitions together.
+        let argc_global = self.module.add_global(i32_type, None, "std::env::argc");
+        argc_global.set_linkage(Linkage::LinkOnceAny);
+        argc_global.set_initializer(&i32_type.const_zero());
+
+        let argv_global = self.module.add_global(ptr_type, None, "std::env::argv");
+        argv_global.set_linkage(Linkage::LinkOnceAny);
+        argv_global.set_initializer(&ptr_type.const_null());
+
+        // Define `std::env::arg_count`.
+        {
+            let fn_name = "std::env::arg_count";
+
+            let fn_type = i64_type.fn_type(&[], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let argc = self
+                .builder
+                .build_load(i32_type, argc_global.as_pointer_value(), "argc")
+                .into_int_value();
+
+            let arg_count = self.builder.build_int_z_extend(argc, i64_type, "arg_count");
+
+            self.builder.build_return(Some(&arg_count));
+        }
+
+        // Define `std::env::arg`.
+        {
+            let fn_name = "std::env::arg";
+
+            let fn_type = ptr_type.fn_type(&[i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let index_param = nth_param(fn_value, 0)?.into_int_value();
+            index_param.set_name("index");
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+            let in_bounds = self.context.append_basic_block(fn_value, "in_bounds");
+            let out_of_bounds = self.context.append_basic_block(fn_value, "out_of_bounds");
+
+            self.builder.position_at_end(entry);
+
+            let argc = self
+                .builder
+                .build_load(i32_type, argc_global.as_pointer_value(), "argc")
+                .into_int_value();
+            let arg_count = self.builder.build_int_z_extend(argc, i64_type, "arg_count");
+
+            let is_in_bounds = self.builder.build_int_compare(
+                IntPredicate::ULT,
+                index_param,
+                arg_count,
+                "is_in_bounds",
+            );
+
+            self.builder
+                .build_conditional_branch(is_in_bounds, in_bounds, out_of_bounds);
+
+            self.builder.position_at_end(in_bounds);
+
+            let argv = self
+                .builder
+                .build_load(ptr_type, argv_global.as_pointer_value(), "argv")
+                .into_pointer_value();
+
+            let arg_ptr = unsafe {
+                self.builder
+                    .build_in_bounds_gep(ptr_type, argv, &[index_param], "arg_ptr")
+            };
+
+            let arg = self.builder.build_load(ptr_type, arg_ptr, "arg");
+
+            self.builder.build_return(Some(&arg));
+
+            self.builder.position_at_end(out_of_bounds);
+
+            let callee = self.function(abort)?;
+            self.builder.build_call(callee, &[], "");
+
+            self.builder.build_unreachable();
+        }
+
+        // Define `std::thread::trampoline`.
+        //
+        // Threads are started with a pointer to the Crane function as their argument,
+        // which the trampoline then calls.
+        let thread_trampoline = {
+            let fn_name = "std::thread::trampoline";
+
+            let fn_type = if target_is_windows {
+                i32_type.fn_type(&[ptr_type.into()], false)
+            } else {
+                ptr_type.fn_type(&[ptr_type.into()], false)
+            };
+
+            let fn_value = self
+                .module
+                .add_function(fn_name, fn_type, Some(Linkage::Internal));
+
+            let fun = nth_param(fn_value, 0)?.into_pointer_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            self.builder.build_indirect_call(
+                self.context.void_type().fn_type(&[], false),
+                fun,
+                &[],
+                "",
+            );
+
+            if target_is_windows {
+                self.builder.build_return(Some(&i32_type.const_zero()));
+            } else {
+                self.builder.build_return(Some(&ptr_type.const_null()));
+            }
+
+            fn_value
+        };
+
+        // Define `std::thread::spawn`.
+        {
+            let fn_name = "std::thread::spawn";
+
+            let fn_type = ptr_type.fn_type(&[ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let fun = nth_param(fn_value, 0)?;
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let trampoline = thread_trampoline.as_global_value().as_pointer_value();
+
+            let handle = if target_is_windows {
+                let create_thread = self.module.add_function(
+                    "CreateThread",
+                    ptr_type.fn_type(
+                        &[
+                            ptr_type.into(),
+                            i64_type.into(),
+                            ptr_type.into(),
+                            ptr_type.into(),
+                            i32_type.into(),
+                            ptr_type.into(),
+                        ],
+                        false,
+                    ),
+                    Some(Linkage::External),
+                );
+
+                self.builder
+                    .build_call(
+                        create_thread,
+                        &[
+                            ptr_type.const_null().into(),
+                            i64_type.const_zero().into(),
+                            trampoline.into(),
+                            fun.into(),
+                            i32_type.const_zero().into(),
+                            ptr_type.const_null().into(),
+                        ],
+                        "handle",
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .ok_or_else(returns_no_value)?
+                    .into_pointer_value()
+            } else {
+                let pthread_create = self.module.add_function(
+                    "pthread_create",
+                    i32_type.fn_type(
+                        &[
+                            ptr_type.into(),
+                            ptr_type.into(),
+                            ptr_type.into(),
+                            ptr_type.into(),
+                        ],
+                        false,
+                    ),
+                    Some(Linkage::External),
+                );
+
+                // `pthread_t` is pointer-sized on all of the platforms we support.
+                let thread = self.builder.build_alloca(i64_type, "thread");
+
+                self.builder.build_call(
+                    pthread_create,
+                    &[
+                        thread.into(),
+                        ptr_type.const_null().into(),
+                        trampoline.into(),
+                        fun.into(),
+                    ],
+                    "",
+                );
+
+                let thread = self
+                    .builder
+                    .build_load(i64_type, thread, "thread")
+                    .into_int_value();
+
+                self.builder.build_int_to_ptr(thread, ptr_type, "handle")
+            };
+
+            self.builder.build_return(Some(&handle));
+        }
+
+        // Define `std::thread::join`.
+        {
+            let fn_name = "std::thread::join";
+
+            let fn_type = self.context.void_type().fn_type(&[ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let handle = nth_param(fn_value, 0)?.into_pointer_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            if target_is_windows {
+                const INFINITE: u64 = 0xFFFF_FFFF;
+
+                let wait_for_single_object = self.module.add_function(
+                    "WaitForSingleObject",
+                    i32_type.fn_type(&[ptr_type.into(), i32_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                let close_handle = self.module.add_function(
+                    "CloseHandle",
+                    i32_type.fn_type(&[ptr_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                self.builder.build_call(
+                    wait_for_single_object,
+                    &[handle.into(), i32_type.const_int(INFINITE, false).into()],
+                    "",
+                );
+
+                self.builder.build_call(close_handle, &[handle.into()], "");
+            } else {
+                let pthread_join = self.module.add_function(
+                    "pthread_join",
+                    i32_type.fn_type(&[i64_type.into(), ptr_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                let thread = self.builder.build_ptr_to_int(handle, i64_type, "thread");
+
+                self.builder.build_call(
+                    pthread_join,
+                    &[thread.into(), ptr_type.const_null().into()],
+                    "",
+                );
+            }
+
+            self.builder.build_return(None);
+        }
+
+        // Define `std::thread::sleep`.
+        {
+            let fn_name = "std::thread::sleep";
+
+            let fn_type = self.context.void_type().fn_type(&[i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let nanos = nth_param(fn_value, 0)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            if target_is_windows {
+                let sleep = self.module.add_function(
+                    "Sleep",
+                    self.context.void_type().fn_type(&[i32_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                let millis = self.builder.build_int_unsigned_div(
+                    nanos,
+                    i64_type.const_int(1_000_000, false),
+                    "millis",
+                );
+
+                let millis = self.builder.build_int_truncate(millis, i32_type, "millis");
+
+                self.builder.build_call(sleep, &[millis.into()], "");
+            } else {
+                let nanosleep = self.module.add_function(
+                    "nanosleep",
+                    i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                let timespec_type = self
+                    .context
+                    .struct_type(&[i64_type.into(), i64_type.into()], false);
+
+                let timespec = self.builder.build_alloca(timespec_type, "timespec");
+
+                let nanos_per_sec = i64_type.const_int(1_000_000_000, false);
+
+                let secs = self
+                    .builder
+                    .build_int_unsigned_div(nanos, nanos_per_sec, "secs");
+                let nsecs = self
+                    .builder
+                    .build_int_unsigned_rem(nanos, nanos_per_sec, "nsecs");
+
+                let secs_ptr = self
+                    .builder
+                    .build_struct_gep(timespec_type, timespec, 0, "secs_ptr")
+                    .map_err(invalid_field_index)?;
+                self.builder.build_store(secs_ptr, secs);
+
+                let nsecs_ptr = self
+                    .builder
+                    .build_struct_gep(timespec_type, timespec, 1, "nsecs_ptr")
+                    .map_err(invalid_field_index)?;
+                self.builder.build_store(nsecs_ptr, nsecs);
+
+                self.builder.build_call(
+                    nanosleep,
+                    &[timespec.into(), ptr_type.const_null().into()],
+                    "",
+                );
+            }
+
+            self.builder.build_return(None);
+        }
+
+        // A `Mutex` is a pointer to a heap-allocated `{ lock, value }` pair.
+        //
+        // The lock storage is sized to fit a `pthread_mutex_t` or a `CRITICAL_SECTION`
+        // on all of the platforms we support.
+        let mutex_type = self
+            .context
+            .struct_type(&[i64_type.array_type(8).into(), i64_type.into()], false);
+
+        let (lock_fn_name, unlock_fn_name) = if target_is_windows {
+            ("EnterCriticalSection", "LeaveCriticalSection")
+        } else {
+            ("pthread_mutex_lock", "pthread_mutex_unlock")
+        };
+
+        let lock_fn_type = if target_is_windows {
+            self.context.void_type().fn_type(&[ptr_type.into()], false)
+        } else {
+            i32_type.fn_type(&[ptr_type.into()], false)
+        };
+
+        let lock_fn = self
+            .module
+            .add_function(lock_fn_name, lock_fn_type, Some(Linkage::External));
+        let unlock_fn =
+            self.module
+                .add_function(unlock_fn_name, lock_fn_type, Some(Linkage::External));
+
+        // Define `std::sync::mutex_new`.
+        {
+            let fn_name = "std::sync::mutex_new";
+
+            let fn_type = ptr_type.fn_type(&[i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let mutex = self
+                .builder
+                .build_malloc(mutex_type, "mutex")
+                .map_err(BackendError::internal)?;
+
+            let lock_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 0, "lock_ptr")
+                .map_err(invalid_field_index)?;
+
+            if target_is_windows {
+                let initialize_critical_section = self.module.add_function(
+                    "InitializeCriticalSection",
+                    self.context.void_type().fn_type(&[ptr_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                self.builder
+                    .build_call(initialize_critical_section, &[lock_ptr.into()], "");
+            } else {
+                let pthread_mutex_init = self.module.add_function(
+                    "pthread_mutex_init",
+                    i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], false),
+                    Some(Linkage::External),
+                );
+
+                self.builder.build_call(
+                    pthread_mutex_init,
+                    &[lock_ptr.into(), ptr_type.const_null().into()],
+                    "",
+                );
+            }
+
+            let value_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 1, "value_ptr")
+                .map_err(invalid_field_index)?;
+            self.builder.build_store(value_ptr, value);
+
+            self.builder.build_return(Some(&mutex));
+        }
+
+        // Define `std::sync::mutex_lock`.
+        //
+        // Blocks until the lock is acquired and then returns the guarded value.
+        {
+            let fn_name = "std::sync::mutex_lock";
+
+            let fn_type = i64_type.fn_type(&[ptr_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let mutex = nth_param(fn_value, 0)?.into_pointer_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let lock_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 0, "lock_ptr")
+                .map_err(invalid_field_index)?;
+            self.builder.build_call(lock_fn, &[lock_ptr.into()], "");
+
+            let value_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 1, "value_ptr")
+                .map_err(invalid_field_index)?;
+            let value = self.builder.build_load(i64_type, value_ptr, "value");
+
+            self.builder.build_return(Some(&value));
+        }
+
+        // Define `std::sync::mutex_unlock`.
+        //
+        // Stores the new guarded value and then releases the lock.
+        {
+            let fn_name = "std::sync::mutex_unlock";
+
+            let fn_type = self
+                .context
+                .void_type()
+                .fn_type(&[ptr_type.into(), i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let mutex = nth_param(fn_value, 0)?.into_pointer_value();
+            let value = nth_param(fn_value, 1)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let value_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 1, "value_ptr")
+                .map_err(invalid_field_index)?;
+            self.builder.build_store(value_ptr, value);
+
+            let lock_ptr = self
+                .builder
+                .build_struct_gep(mutex_type, mutex, 0, "lock_ptr")
+                .map_err(invalid_field_index)?;
+            self.builder.build_call(unlock_fn, &[lock_ptr.into()], "");
+
+            self.builder.build_return(None);
+        }
+
+        // Define `std::sync::atomic_new`.
+        //
+        // An `AtomicUint64` is a pointer to a heap-allocated `Uint64`.
+        {
+            let fn_name = "std::sync::atomic_new";
+
+            let fn_type = ptr_type.fn_type(&[i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let value = nth_param(fn_value, 0)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let atomic = self
+                .builder
+                .build_malloc(i64_type, "atomic")
+                .map_err(BackendError::internal)?;
+            self.builder.build_store(atomic, value);
+
+            self.builder.build_return(Some(&atomic));
+        }
+
+        // Define `std::sync::atomic_load`.
+        {
+            let fn_name = "std::sync::atomic_load";
+
+            let fn_type = i64_type.fn_type(&[ptr_type.into(), i32_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let atomic = nth_param(fn_value, 0)?.into_pointer_value();
+            let ordering = nth_param(fn_value, 1)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            self.build_memory_ordering_switch(
+                fn_value,
+                ordering,
+                &[
+                    AtomicOrdering::Monotonic,
+                    AtomicOrdering::Acquire,
+                    AtomicOrdering::SequentiallyConsistent,
+                ],
+                |ordering| {
+                    let value = self.builder.build_load(i64_type, atomic, "value");
+
+                    let load = instruction(value)?;
+                    load.set_alignment(8).map_err(BackendError::internal)?;
+                    load.set_atomic_ordering(ordering)
+                        .map_err(BackendError::internal)?;
+
+                    self.builder.build_return(Some(&value));
+
+                    Ok(())
+                },
+            )?;
+        }
+
+        // Define `std::sync::atomic_store`.
+        {
+            let fn_name = "std::sync::atomic_store";
+
+            let fn_type = self
+                .context
+                .void_type()
+                .fn_type(&[ptr_type.into(), i64_type.into(), i32_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let atomic = nth_param(fn_value, 0)?.into_pointer_value();
+            let value = nth_param(fn_value, 1)?.into_int_value();
+            let ordering = nth_param(fn_value, 2)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            self.build_memory_ordering_switch(
+                fn_value,
+                ordering,
+                &[
+                    AtomicOrdering::Monotonic,
+                    AtomicOrdering::Release,
+                    AtomicOrdering::SequentiallyConsistent,
+                ],
+                |ordering| {
+                    let store = self.builder.build_store(atomic, value);
+                    store.set_alignment(8).map_err(BackendError::internal)?;
+                    store
+                        .set_atomic_ordering(ordering)
+                        .map_err(BackendError::internal)?;
+
+                    self.builder.build_return(None);
+
+                    Ok(())
+                },
+            )?;
+        }
+
+        // Define `std::sync::atomic_fetch_add`.
+        //
+        // Returns the previous value.
+        {
+            let fn_name = "std::sync::atomic_fetch_add";
+
+            let fn_type =
+                i64_type.fn_type(&[ptr_type.into(), i64_type.into(), i32_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let atomic = nth_param(fn_value, 0)?.into_pointer_value();
+            let value = nth_param(fn_value, 1)?.into_int_value();
+            let ordering = nth_param(fn_value, 2)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            self.build_memory_ordering_switch(fn_value, ordering, &MEMORY_ORDERINGS, |ordering| {
+                let previous = self
+                    .builder
+                    .build_atomicrmw(AtomicRMWBinOp::Add, atomic, value, ordering)
+                    .map_err(BackendError::internal)?;
+
+                self.builder.build_return(Some(&previous));
+
+                Ok(())
+            })?;
+        }
+
+        // Define `std::sync::atomic_compare_exchange`.
+        //
+        // Returns the previous value, which is equal to `current` if the exchange succeeded.
+        {
+            let fn_name = "std::sync::atomic_compare_exchange";
+
+            let fn_type = i64_type.fn_type(
+                &[
+                    ptr_type.into(),
+                    i64_type.into(),
+                    i64_type.into(),
+                    i32_type.into(),
+                ],
+                false,
+            );
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let atomic = nth_param(fn_value, 0)?.into_pointer_value();
+            let current = nth_param(fn_value, 1)?.into_int_value();
+            let new = nth_param(fn_value, 2)?.into_int_value();
+            let ordering = nth_param(fn_value, 3)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            self.build_memory_ordering_switch(fn_value, ordering, &MEMORY_ORDERINGS, |ordering| {
+                // The failure ordering can't release, as nothing gets stored.
+                let failure_ordering = match ordering {
+                    AtomicOrdering::Release => AtomicOrdering::Monotonic,
+                    AtomicOrdering::AcquireRelease => AtomicOrdering::Acquire,
+                    ordering => ordering,
+                };
+
+                let result = self
+                    .builder
+                    .build_cmpxchg(atomic, current, new, ordering, failure_ordering)
+                    .map_err(BackendError::internal)?;
+
+                let previous = self
+                    .builder
+                    .build_extract_value(result, 0, "previous")
+                    .ok_or_else(|| BackendError::internal("`cmpxchg` returned no value."))?;
+
+                self.builder.build_return(Some(&previous));
+
+                Ok(())
+            })?;
+        }
+
+        // Define `std::ptr::volatile_read`.
+        //
+        // Volatile accesses are never removed, merged or reordered with other
+        // volatile accesses by the optimizer.
+        {
+            let fn_name = "std::ptr::volatile_read";
+
+            let fn_type = i64_type.fn_type(&[i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let address = nth_param(fn_value, 0)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let ptr = self.builder.build_int_to_ptr(address, ptr_type, "ptr");
+
+            let value = self.builder.build_load(i64_type, ptr, "value");
+
+            let load = instruction(value)?;
+            load.set_alignment(8).map_err(BackendError::internal)?;
+            load.set_volatile(true).map_err(BackendError::internal)?;
+
+            self.builder.build_return(Some(&value));
+        }
+
+        // Define `std::ptr::volatile_write`.
+        {
+            let fn_name = "std::ptr::volatile_write";
+
+            let fn_type = self
+                .context
+                .void_type()
+                .fn_type(&[i64_type.into(), i64_type.into()], false);
+
+            let fn_value = self.module.add_function(fn_name, fn_type, None);
+
+            let address = nth_param(fn_value, 0)?.into_int_value();
+            let value = nth_param(fn_value, 1)?.into_int_value();
+
+            let entry = self.context.append_basic_block(fn_value, "entry");
+
+            self.builder.position_at_end(entry);
+
+            let ptr = self.builder.build_int_to_ptr(address, ptr_type, "ptr");
+
+            let store = self.builder.build_store(ptr, value);
+            store.set_alignment(8).map_err(BackendError::internal)?;
+            store.set_volatile(true).map_err(BackendError::internal)?;
+
+            self.builder.build_return(None);
+        }
+
+        Ok(())
+    }
+
+    /// Branches on a `std::sync::MemoryOrdering` passed at runtime, calling
+    /// `build_case` to build a block for each of the `supported` orderings.
+    ///
+    /// Any other ordering aborts the program.
+    fn build_memory_ordering_switch(
+        &self,
+        fn_value: FunctionValue<'ctx>,
+        ordering: IntValue<'ctx>,
+        supported: &[AtomicOrdering],
+        mut build_case: impl FnMut(AtomicOrdering) -> Result<(), BackendError>,
+    ) -> Result<(), BackendError> {
+        let unsupported = self.context.append_basic_block(fn_value, "unsupported");
+
+        let cases = MEMORY_ORDERINGS
+            .into_iter()
+            .enumerate()
+            .filter(|(_, ordering)| supported.contains(ordering))
+            .map(|(index, ordering)| {
+                let block = self
+                    .context
+                    .append_basic_block(fn_value, &format!("{ordering:?}"));
+
+                (index, ordering, block)
+            })
+            .collect::<Vec<_>>();
+
+        self.builder.build_switch(
+            ordering,
+            unsupported,
+            &cases
+                .iter()
+                .map(|(index, _, block)| {
+                    (
+                        self.context.i32_type().const_int(*index as u64, false),
+                        *block,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        for (_, ordering, block) in cases {
+            self.builder.position_at_end(block);
+
+            build_case(ordering)?;
+        }
+
+        self.builder.position_at_end(unsupported);
+
+        let callee = self.function("abort")?;
+        self.builder.build_call(callee, &[], "");
+
+        self.builder.build_unreachable();
+
+        Ok(())
+    }
+
+    /// Compiles a path to a `std::sync::MemoryOrdering` variant, if it is one.
+    fn compile_memory_ordering(&self, path: &TyPath) -> Option<BasicValueEnum<'ctx>> {
+        let (variant, enum_path) = path.segments.split_last()?;
+
+        let enum_path = enum_path
+            .iter()
+            .map(|segment| segment.ident.name.as_str())
+            .collect::<Vec<_>>();
+
+        if enum_path != ["std", "sync", "MemoryOrdering"] {
+            return None;
+        }
+
+        let index = typer::MEMORY_ORDERINGS
+            .iter()
+            .position(|name| variant.ident.name == *name)?;
+
+        Some(
+            self.context
+                .i32_type()
+                .const_int(index as u64, false)
+                .as_basic_value_enum(),
+        )
+    }
+
+    /// Runs the function pass manager over every function in the module and
+    /// then verifies the module as a whole.
+    ///
+    /// Functions that fail verification on their own are skipped by the pass
+    /// manager, so that a single broken function doesn't prevent the rest of
+    /// the module from being optimized.
+    fn optimize_and_verify(&self) -> Result<(), BackendError> {
+        let mut broken_fn = None;
+
+        for fn_value in self.module.get_functions() {
+            if fn_value.verify(false) {
+                self.fpm.run_on(&fn_value);
+            } else if broken_fn.is_none() {
+                broken_fn = Some(fn_value);
+            }
+        }
+
+        self.module.verify().map_err(|llvm_message| {
+            let mut message = llvm_message.to_string().trim_end().to_string();
+
+            // In debug builds we include the IR of the offending function to
+            // make it easier to track down what went wrong.
+            if cfg!(debug_assertions) {
+                if let Some(fn_value) = broken_fn {
+                    message.push_str("\n\n");
+                    message.push_str(fn_value.print_to_string().to_string().trim_end());
+                }
+            }
+
+            BackendError::Verification {
+                function: broken_fn.map(|fn_value| fn_value.get_name().to_string_lossy().into()),
+                message,
+            }
+        })
+    }
+
+    /// Defines a function that writes its string parameters to stderr with
+    /// the given `fprintf` template and then aborts.
+    ///
+    /// The output written so far is flushed first, as `abort` would discard it.
+    fn define_abort_fn(
+        &self,
+        fn_name: &str,
+        template: &[u8],
+        param_count: usize,
+        abort: &str,
+        stderr: GlobalValue<'ctx>,
+    ) -> Result<FunctionValue<'ctx>, BackendError> {
+        let i8_type = self.context.i8_type();
+        let i32_type = self.context.i32_type();
+        let ptr_type = i8_type.ptr_type(AddressSpace::default());
+
+        let fflush = self.module.get_function("fflush").unwrap_or_else(|| {
+            self.module.add_function(
+                "fflush",
+                i32_type.fn_type(&[ptr_type.into()], false),
+                Some(Linkage::External),
+            )
+        });
+
+        let fprintf = self.module.get_function("fprintf").unwrap_or_else(|| {
+            self.module.add_function(
+                "fprintf",
+                i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], true),
+                Some(Linkage::External),
+            )
+        });
+
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&vec![ptr_type.into(); param_count], false);
+
+        let fn_value = self.module.add_function(fn_name, fn_type, None);
+        fn_value.add_attribute(AttributeLoc::Function, self.noreturn_attribute());
+
+        let entry = self.context.append_basic_block(fn_value, "entry");
+
+        self.builder.position_at_end(entry);
+
+        let i8_array_type = i8_type.array_type(template.len() as u32 + 1);
+
+        let template = self.context.const_string(template, true);
+
+        let global = self
+            .module
+            .add_global(i8_array_type, None, "abort_template");
+        global.set_linkage(Linkage::Internal);
+        global.set_constant(true);
+        global.set_initializer(&template);
+
+        // Flushing a null stream flushes every stream.
+        self.builder
+            .build_call(fflush, &[ptr_type.const_null().into()], "");
+
+        let stream = self
+            .builder
+            .build_load(ptr_type, stderr.as_pointer_value(), "stderr");
+
+        let args = [stream.into(), global.as_pointer_value().into()]
+            .into_iter()
+            .chain(fn_value.get_param_iter().map(Into::into))
+            .collect::<Vec<_>>();
+
+        self.builder.build_call(fprintf, &args, "");
+
+        let callee = self.function(abort)?;
+        self.builder.build_call(callee, &[], "");
+
+        self.builder.build_unreachable();
+
+        Ok(fn_value)
+    }
+
+    /// Returns the `noreturn` function attribute.
+    fn noreturn_attribute(&self) -> Attribute {
+        self.context
+            .create_enum_attribute(Attribute::get_named_enum_kind_id("noreturn"), 0)
+    }
+
+    /// Declares the given function in the module, if it hasn't been declared already.
+    fn declare_fn(&self, fun: &TyFn) -> Result<FunctionValue<'ctx>, BackendError> {
+        let fn_name = fun.path.to_string();
+
+        if let Some(fn_value) = self.module.get_function(&fn_name) {
+            return Ok(fn_value);
+        }
+
+        let params = fun
+            .params
+            .iter()
+            .map(|param| {
+                let ty = self.lower_param_type(&param.ty)?.ok_or_else(|| {
+                    BackendError::internal(format!("Parameter `{}` has no value.", param.name))
+                })?;
+
+                Ok(ty.into())
+            })
+            .collect::<Result<Vec<BasicMetadataTypeEnum>, BackendError>>()?;
+
+        let fn_type = match self.lower_type(&fun.return_ty)? {
+            Some(return_type) => return_type.fn_type(&params, false),
+            None => self.context.void_type().fn_type(&params, false),
+        };
+
+        // `main` is given the real C signature, so that it can receive the program arguments.
+        let fn_type = if fn_name == "main" {
+            let i32_type = self.context.i32_type();
+            let ptr_type = self.context.i8_type().ptr_type(AddressSpace::default());
+
+            i32_type.fn_type(&[i32_type.into(), ptr_type.into()], false)
+        } else {
+            fn_type
+        };
+
+        let fn_value = self.module.add_function(&fn_name, fn_type, None);
+
+        fn_value.set_call_conventions(llvm_calling_convention(fun.calling_convention));
+
+        Ok(fn_value)
+    }
+
+    /// Stores the `argc` and `argv` passed to the C `main` for use by `std::env`.
+    fn store_program_args(&self, main_fn: FunctionValue<'ctx>) -> Result<(), BackendError> {
+        let argc = nth_param(main_fn, 0)?;
+        argc.set_name("argc");
+
+        let argv = nth_param(main_fn, 1)?;
+        argv.set_name("argv");
+
+        for (name, value) in [("std::env::argc", argc), ("std::env::argv", argv)] {
+            let global = self
+                .module
+                .get_global(name)
+                .ok_or_else(|| BackendError::internal(format!("Global `{name}` not found.")))?;
+
+            self.builder.build_store(global.as_pointer_value(), value);
+        }
+
+        Ok(())
+    }
+
+    fn compile_module(&self, ty_module: &TyModule) -> Result<(), BackendError> {
+        for item in &ty_module.items {
+            self.compile_item(item)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the LLVM integer type used for the given type and whether it
+    /// is signed, if it is represented as an integer.
+    ///
+    /// This is the single table that both type lowering and instruction
+    /// selection consult for integer widths and signedness.
+    fn lower_int_type(&self, ty: &TypeId) -> Option<(IntType<'ctx>, bool)> {
+        match &**ty {
+            TyKind::Bool => Some((self.context.bool_type(), false)),
+            TyKind::Int(int_ty) => {
+                Some((self.context.custom_width_int_type(int_ty.bit_width()), true))
+            }
+            TyKind::Uint(uint_ty) => Some((
+                self.context.custom_width_int_type(uint_ty.bit_width()),
+                false,
+            )),
+            TyKind::UserDefined { module, name }
+                if (module.as_str(), name.as_str()) == ("std::sync", "MemoryOrdering") =>
+            {
+                Some((self.context.i32_type(), false))
+            }
+            _ => None,
+        }
+    }
+
+    /// Lowers the given `struct` to a named LLVM struct type.
+    ///
+    /// Fields of a `#[repr(C)]` struct are laid out in declaration order, with
+    /// each field padded to its ABI alignment. `#[repr(packed)]` also keeps the
+    /// declaration order, but removes all padding. Otherwise the fields are
+    /// sorted by descending alignment to minimize the padding between them.
+    ///
+    /// The fields of a `#[bitfield]` struct are packed into a single integer,
+    /// starting from the least significant bit.
+    fn lower_struct(
+        &self,
+        item: &TyItem,
+        struct_decl: &TyStructDecl,
+        target_data: &TargetData,
+    ) -> Result<StructType<'ctx>, BackendError> {
+        let name = item.name.to_string();
+
+        if let Some(struct_type) = self.module.get_struct_type(&name) {
+            return Ok(struct_type);
+        }
+
+        if item.attr("bitfield").is_some() {
+            let layout = bitfield_layout(struct_decl);
+
+            let total_bits = layout.iter().map(|(_, width)| width).sum::<u32>();
+
+            let storage_type = self
+                .context
+                .custom_width_int_type(total_bits.next_power_of_two().max(8));
+
+            let struct_type = self.context.opaque_struct_type(&name);
+            struct_type.set_body(&[storage_type.into()], false);
+
+            // The fields don't have elements of their own, but the struct is
+            // still passed around like any other.
+            self.struct_fields
+                .borrow_mut()
+                .insert(name.clone(), Vec::new());
+            self.bitfields.borrow_mut().insert(name, layout);
+
+            return Ok(struct_type);
+        }
+
+        let repr = item
+            .attr("repr")
+            .and_then(|attr| attr.args.first())
+            .map(|arg| arg.name.name.as_str());
+
+        let field_order = self.struct_field_order(item, struct_decl, target_data)?;
+
+        let mut field_types = Vec::new();
+
+        for index in &field_order {
+            field_types.extend(self.lower_type(&struct_decl.0.fields()[*index].ty)?);
+        }
+
+        let struct_type = self.context.opaque_struct_type(&name);
+        struct_type.set_body(&field_types, repr == Some("packed"));
+
+        self.struct_fields.borrow_mut().insert(name, field_order);
+
+        Ok(struct_type)
+    }
+
+    /// Returns the indices of the fields of the given `struct` that have
+    /// values, in the order they are laid out in memory.
+    fn struct_field_order(
+        &self,
+        item: &TyItem,
+        struct_decl: &TyStructDecl,
+        target_data: &TargetData,
+    ) -> Result<Vec<usize>, BackendError> {
+        let mut fields = Vec::new();
+
+        for (index, field) in struct_decl.0.fields().iter().enumerate() {
+            if let Some(field_type) = self.lower_type(&field.ty)? {
+                fields.push((index, field_type));
+            }
+        }
+
+        if item.attr("repr").is_none() {
+            // This is a stable sort, so fields with equal alignment keep their
+            // declaration order.
+            fields.sort_by_key(|(_, field_type)| {
+                std::cmp::Reverse(target_data.get_abi_alignment(field_type))
+            });
+        }
+
+        Ok(fields.into_iter().map(|(index, _)| index).collect())
+    }
+
+    /// Lowers the given `union` to a named `{ tag, payload }` LLVM struct type.
+    ///
+    /// The tag is an `i64` unless the `union` selects a narrower discriminant
+    /// with `#[repr(u8)]`, `#[repr(u16)]` or `#[repr(u32)]`. The payload is
+    /// sized and aligned to fit the largest variant.
+    fn lower_union(
+        &self,
+        item: &TyItem,
+        union_decl: &TyUnionDecl,
+        target_data: &TargetData,
+    ) -> Result<StructType<'ctx>, BackendError> {
+        let name = item.name.to_string();
+
+        if let Some(union_type) = self.module.get_struct_type(&name) {
+            return Ok(union_type);
+        }
+
+        let tag_bits = match item.attr("repr").and_then(|attr| attr.args.first()) {
+            Some(arg) => arg.name.name[1..].parse::<u32>().map_err(|_| {
+                BackendError::internal(format!(
+                    "Unsupported `#[repr({})]` on `{name}`.",
+                    arg.name.name
+                ))
+            })?,
+            None => 64,
+        };
+
+        let tag_type = self.context.custom_width_int_type(tag_bits);
+
+        let (payload_size, payload_alignment) = union_decl
+            .variants
+            .iter()
+            .map(|variant| {
+                let mut field_types = Vec::new();
+
+                for field in variant.data.fields() {
+                    field_types.extend(self.lower_type(&field.ty)?);
+                }
+
+                let variant_type = self.context.struct_type(&field_types, false);
+
+                Ok((
+                    target_data.get_abi_size(&variant_type),
+                    target_data.get_abi_alignment(&variant_type),
+                ))
+            })
+            .collect::<Result<Vec<_>, BackendError>>()?
+            .into_iter()
+            .fold(
+                (0, 1),
+                |(size, alignment), (variant_size, variant_alignment)| {
+                    (size.max(variant_size), alignment.max(variant_alignment))
+                },
+            );
+
+        let payload_type = self
+            .context
+            .custom_width_int_type(payload_alignment * 8)
+            .array_type(payload_size.div_ceil(payload_alignment as u64) as u32);
+
+        let union_type = self.context.opaque_struct_type(&name);
+        union_type.set_body(&[tag_type.into(), payload_type.into()], false);
+
+        Ok(union_type)
+    }
+
+    /// Records the wrapped type of every tuple struct among the given types.
+    fn register_newtypes(&self, types: &[&TyItem]) {
+        for item in types {
+            if let TyItemKind::Struct(struct_decl) = &item.kind {
+                if let Some(inner) = struct_decl.newtype() {
+                    self.newtypes
+                        .borrow_mut()
+                        .insert(item.name.to_string(), *inner);
+                }
+            }
+        }
+    }
+
+    /// Returns the bit offset and width of every field, if the given type is
+    /// a `#[bitfield]` struct.
+    fn bitfield_fields(&self, ty: &TypeId) -> Option<Vec<(u32, u32)>> {
+        match &**ty {
+            TyKind::UserDefined { module, name } if module == "std::prelude" => {
+                self.bitfields.borrow().get(name.as_str()).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads the field at the given bit offset and width out of the storage
+    /// of a `#[bitfield]` struct.
+    fn bitfield_get(
+        &self,
+        storage: IntValue<'ctx>,
+        offset: u32,
+        width: u32,
+        ty: &TypeId,
+    ) -> Result<IntValue<'ctx>, BackendError> {
+        let (int_type, is_signed) = self.lower_int_type(ty).ok_or_else(|| {
+            BackendError::internal(format!("Bitfield of non-integer type {ty:?}."))
+        })?;
+
+        let shifted = self.builder.build_right_shift(
+            storage,
+            storage.get_type().const_int(offset as u64, false),
+            false,
+            "bits",
+        );
+
+        let bits = self.builder.build_int_truncate(
+            shifted,
+            self.context.custom_width_int_type(width),
+            "bits",
+        );
+
+        Ok(self
+            .builder
+            .build_int_cast_sign_flag(bits, int_type, is_signed, "field"))
+    }
+
+    /// Writes `value` into the field at the given bit offset and width of the
+    /// storage of a `#[bitfield]` struct, returning the new storage.
+    fn bitfield_set(
+        &self,
+        storage: IntValue<'ctx>,
+        value: IntValue<'ctx>,
+        offset: u32,
+        width: u32,
+    ) -> IntValue<'ctx> {
+        let storage_type = storage.get_type();
+
+        let bits = self.builder.build_int_truncate(
+            value,
+            self.context.custom_width_int_type(width),
+            "bits",
+        );
+
+        let bits = self.builder.build_int_z_extend(bits, storage_type, "bits");
+
+        let bits = self.builder.build_left_shift(
+            bits,
+            storage_type.const_int(offset as u64, false),
+            "bits",
+        );
+
+        let mask = (u64::MAX >> (64 - width)) << offset;
+
+        let cleared =
+            self.builder
+                .build_and(storage, storage_type.const_int(!mask, false), "cleared");
+
+        self.builder.build_or(cleared, bits, "bitfield")
+    }
+
+    /// Returns the wrapped type, if the given type is a tuple struct.
+    fn newtype_inner(&self, ty: &TypeId) -> Option<TypeId> {
+        match &**ty {
+            TyKind::UserDefined { module, name } if module == "std::prelude" => {
+                self.newtypes.borrow().get(name.as_str()).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// Lowers the given type to the LLVM type of its values.
+    ///
+    /// Returns `None` for the unit type, which has no values at runtime.
+    fn lower_type(&self, ty: &TypeId) -> Result<Option<BasicTypeEnum<'ctx>>, BackendError> {
+        if let Some(inner) = self.newtype_inner(ty) {
+            return self.lower_type(&inner);
+        }
+
+        if let Some((int_type, _)) = self.lower_int_type(ty) {
+            return Ok(Some(int_type.as_basic_type_enum()));
+        }
+
+        let ptr_type = self
+            .context
+            .i8_type()
+            .ptr_type(AddressSpace::default())
+            .as_basic_type_enum();
+
+        Ok(match &**ty {
+            TyKind::Unit | TyKind::Never => None,
+            TyKind::Float(FloatTy::F64) => Some(self.context.f64_type().as_basic_type_enum()),
+            // Functions are passed around as function pointers.
+            TyKind::Fn { .. } => Some(ptr_type),
+            TyKind::UserDefined { module, name } => match (module.as_str(), name.as_str()) {
+                ("std::prelude", "String")
+                | ("std::thread", "ThreadHandle")
+                | ("std::sync", "Mutex")
+                | ("std::sync", "AtomicUint64") => Some(ptr_type),
+                // The result of `typeof` is the name of the type, as a string.
+                ("std::meta", _) => Some(ptr_type),
+                ("std::prelude", name) if self.module.get_struct_type(name).is_some() => self
+                    .module
+                    .get_struct_type(name)
+                    .map(|struct_type| struct_type.as_basic_type_enum()),
+                (module, name) => {
+                    return Err(BackendError::internal(format!(
+                        "Unknown type `{module}::{name}`."
+                    )))
+                }
+            },
+            TyKind::Array { elem, len } => self
+                .lower_type(elem)?
+                .map(|elem_type| elem_type.array_type(*len as u32).into()),
+            TyKind::Bool | TyKind::Int(_) | TyKind::Uint(_) => {
+                unreachable!("Integer types are lowered by `lower_int_type`.")
+            }
+        })
+    }
+
+    /// Lowers the type of a function parameter.
+    ///
+    /// This is the same as [`Self::lower_type`], except that arrays and
+    /// `struct`s are passed by pointer. The length of an array is known
+    /// statically from its type.
+    fn lower_param_type(&self, ty: &TypeId) -> Result<Option<BasicTypeEnum<'ctx>>, BackendError> {
+        if self.is_aggregate(ty) {
+            return Ok(Some(
+                self.context
+                    .i8_type()
+                    .ptr_type(AddressSpace::default())
+                    .as_basic_type_enum(),
+            ));
+        }
+
+        self.lower_type(ty)
+    }
+
+    /// Returns whether the given type is an array or `struct`, whose values
+    /// are passed to functions by pointer.
+    fn is_aggregate(&self, ty: &TypeId) -> bool {
+        if let Some(inner) = self.newtype_inner(ty) {
+            return self.is_aggregate(&inner);
+        }
+
+        match &**ty {
+            TyKind::Array { .. } => true,
+            TyKind::UserDefined { module, name } => {
+                module == "std::prelude" && self.struct_fields.borrow().contains_key(name.as_str())
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the index of the LLVM struct element holding the field at
+    /// `index` in the declaration of the given `struct` type, if the field
+    /// has a value.
+    fn struct_field_index(&self, ty: &TypeId, index: usize) -> Result<Option<u32>, BackendError> {
+        let TyKind::UserDefined { name, .. } = &**ty else {
+            return Err(BackendError::internal(format!("Not a struct: {ty:?}")));
+        };
+
+        let struct_fields = self.struct_fields.borrow();
+
+        let field_order = struct_fields.get(name.as_str()).ok_or_else(|| {
+            BackendError::internal(format!("Struct `{name}` has not been lowered."))
+        })?;
+
+        Ok(field_order
+            .iter()
+            .position(|field_index| *field_index == index)
+            .map(|position| position as u32))
+    }
+
+    /// Returns the LLVM type for the given type, lowering function types to
+    /// function types rather than function pointers.
+    fn to_llvm_type(&self, ty: TypeId) -> Result<AnyTypeEnum<'ctx>, BackendError> {
+        Ok(match &*ty {
+            TyKind::Fn {
+                args: params,
+                return_ty,
+                ..
+            } => {
+                let params = params
+                    .iter()
+                    .map(|param| {
+                        let ty = self.lower_param_type(param)?.ok_or_else(|| {
+                            BackendError::internal(format!("Unsupported parameter type: {param:?}"))
+                        })?;
+
+                        Ok(ty.into())
+                    })
+                    .collect::<Result<Vec<BasicMetadataTypeEnum>, BackendError>>()?;
+
+                AnyTypeEnum::FunctionType(match self.lower_type(return_ty)? {
+                    Some(return_type) => return_type.fn_type(&params, false),
+                    None => self.context.void_type().fn_type(&params, false),
+                })
+            }
+            _ => match self.lower_type(&ty)? {
+                Some(ty) => ty.as_any_type_enum(),
+                None => self.context.void_type().as_any_type_enum(),
+            },
+        })
+    }
+
+    fn compile_item(&self, item: &TyItem) -> Result<(), BackendError> {
+        match &item.kind {
+            TyItemKind::Use => {}
+            TyItemKind::Fn(fun) => {
+                let fn_value = self.declare_fn(fun)?;
+
+                if item.attr("naked").is_some() {
+                    self.compile_naked_fn(fun, fn_value);
+                    return Ok(());
+                }
+
+                self.add_inline_attribute(item, fn_value);
+
+                self.compile_fn_body(fun, fn_value)?;
+            }
+            // An `extern fn` is declared wherever it is called, and defined
+            // by the library that it's linked against.
+            TyItemKind::ExternFn(_) => {}
+            TyItemKind::Struct(_) => {}
+            TyItemKind::Union(_) => {}
+            // Every `const` is defined before the items of the unit are compiled.
+            TyItemKind::Const(_) => {}
+            TyItemKind::Impl(impl_decl) => {
+                for item in &impl_decl.items {
+                    self.compile_item(item)?;
+                }
+            }
+            TyItemKind::Module(ty_module, InlineModuleDecl::Yes) => {
+                self.compile_module(ty_module)?;
+            }
+            // Modules in separate files are compiled as their own compilation units.
+            TyItemKind::Module(_, InlineModuleDecl::No) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the body of the given function into its entry block.
+    fn compile_fn_body(
+        &self,
+        fun: &TyFn,
+        fn_value: FunctionValue<'ctx>,
+    ) -> Result<(), BackendError> {
+        let is_main_fn = fun.path.to_string() == "main";
+
+        self.call_sites.borrow_mut().clear();
+
+        self.local_fns
+            .borrow_mut()
+            .push(HashMap::from([(fun.path.clone(), fn_value)]));
+
+        for (index, param_value) in fn_value.get_param_iter().enumerate() {
+            if let Some(param) = fun.params.get(index) {
+                param_value.set_name(&param.name.to_string());
+            }
+        }
+
+        let entry = self.context.append_basic_block(fn_value, "entry");
+
+        self.builder.position_at_end(entry);
+
+        if is_main_fn {
+            self.store_program_args(fn_value)?;
+        }
+
+        let mut locals = HashMap::new();
+
+        let last_stmt = self.compile_block(&fun.params, &fn_value, &mut locals, &fun.body)?;
+
+        if is_main_fn {
+            self.builder
+                .build_return(Some(&self.context.i32_type().const_int(0, false)));
+        } else if let Some(last_stmt) = last_stmt {
+            self.builder.build_return(Some(&last_stmt));
+        } else {
+            self.builder.build_return(None);
+        }
+
+        self.mark_tail_calls(fn_value)?;
+
+        self.local_fns.borrow_mut().pop();
+
+        Ok(())
+    }
+
+    /// Compiles a function declared in the body of `parent`.
+    ///
+    /// The function is hoisted to the module under a name made of the names
+    /// of its parent and itself, along with a number to tell apart nested
+    /// functions with the same name. Calls to it from the parent's body are
+    /// resolved through [`Self::local_fns`].
+    fn compile_nested_fn(
+        &self,
+        parent: FunctionValue<'ctx>,
+        item: &TyItem,
+    ) -> Result<(), BackendError> {
+        let TyItemKind::Fn(fun) = &item.kind else {
+            unreachable!("Only functions can be nested in a function.");
+        };
+
+        let index = self.nested_fn_count.get();
+        self.nested_fn_count.set(index + 1);
+
+        let mangled_name = format!(
+            "{}::{}.{index}",
+            parent.get_name().to_string_lossy(),
+            fun.path
+        );
+
+        let hoisted_fn = TyFn {
+            path: TyPath {
+                segments: thin_vec![TyPathSegment {
+                    ident: Ident {
+                        name: mangled_name.into(),
+                        span: fun.path.span,
+                    }
+                }],
+                span: fun.path.span,
+            },
+            ..(**fun).clone()
+        };
+
+        let fn_value = self.declare_fn(&hoisted_fn)?;
+        fn_value.set_linkage(Linkage::Internal);
+
+        self.add_inline_attribute(item, fn_value);
+
+        if let Some(local_fns) = self.local_fns.borrow_mut().last_mut() {
+            local_fns.insert(fun.path.clone(), fn_value);
+        }
+
+        let insert_block = self.builder.get_insert_block();
+        let call_sites = self.call_sites.take();
+
+        // The body is compiled under its original path, so that it can call
+        // itself by name.
+        self.compile_fn_body(fun, fn_value)?;
+
+        *self.call_sites.borrow_mut() = call_sites;
+
+        if let Some(insert_block) = insert_block {
+            self.builder.position_at_end(insert_block);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the function in the module with the given name, which has been
+    /// declared already.
+    fn function(&self, name: &str) -> Result<FunctionValue<'ctx>, BackendError> {
+        self.module
+            .get_function(name)
+            .ok_or_else(|| BackendError::internal(format!("Function `{name}` not found.")))
+    }
+
+    /// Returns the block the builder is inserting instructions into.
+    fn insert_block(&self) -> Result<BasicBlock<'ctx>, BackendError> {
+        self.builder
+            .get_insert_block()
+            .ok_or_else(|| BackendError::internal("The builder isn't positioned in a block."))
+    }
+
+    /// Returns the function nested in the body being compiled that is called
+    /// by the given path, if there is one.
+    fn local_fn(&self, path: &TyPath) -> Option<FunctionValue<'ctx>> {
+        self.local_fns
+            .borrow()
+            .last()
+            .and_then(|local_fns| local_fns.get(path).copied())
+    }
+
+    /// Compiles a `#[naked]` function, whose body is only its inline assembly,
+    /// without a prologue or epilogue.
+    fn compile_naked_fn(&self, fun: &TyFn, fn_value: FunctionValue<'ctx>) {
+        for kind in ["naked", "noinline"] {
+            fn_value.add_attribute(
+                AttributeLoc::Function,
+                self.context
+                    .create_enum_attribute(Attribute::get_named_enum_kind_id(kind), 0),
+            );
+        }
+
+        let entry = self.context.append_basic_block(fn_value, "entry");
+
+        self.builder.position_at_end(entry);
+
+        for stmt in &fun.body {
+            if let TyStmtKind::Expr(expr) = &stmt.kind {
+                if let TyExprKind::Asm(lines) = &expr.kind {
+                    self.compile_asm(lines);
+                }
+            }
+        }
+
+        // The assembly is responsible for returning.
+        self.builder.build_unreachable();
+    }
+
+    /// Compiles an inline assembly block with the given lines.
+    fn compile_asm(&self, lines: &[SmolStr]) {
+        let asm_fn_type = self.context.void_type().fn_type(&[], false);
+
+        let asm = self.context.create_inline_asm(
+            asm_fn_type,
+            lines.join("\n"),
+            String::new(),
+            true,
+            false,
+            None,
+            false,
+        );
+
+        self.builder
+            .build_indirect_call(asm_fn_type, asm, &[], "asm");
+    }
+
+    /// Records the target CPU and features on a function definition, like
+    /// Clang does, so that they survive into bitcode and LTO.
+    fn add_target_attributes(&self, fn_value: FunctionValue<'ctx>, target_machine: &TargetMachine) {
+        let cpu = target_machine.get_cpu().to_string();
+        let features = target_machine.get_feature_string().to_string_lossy();
+
+        for (key, value) in [("target-cpu", cpu.as_str()), ("target-features", &features)] {
+            if !value.is_empty() {
+                fn_value.add_attribute(
+                    AttributeLoc::Function,
+                    self.context.create_string_attribute(key, value),
+                );
+            }
+        }
+    }
+
+    /// Adds the LLVM function attribute for the `#[inline]` attribute on the
+    /// given item, if it has one.
+    fn add_inline_attribute(&self, item: &TyItem, fn_value: FunctionValue<'ctx>) {
+        let Some(attr) = item.attr("inline") else {
+            return;
+        };
+
+        let kind = match attr.args.first().map(|arg| arg.name.name.as_str()) {
+            None => "inlinehint",
+            Some("always") => "alwaysinline",
+            Some("never") => "noinline",
+            Some(arg) => unreachable!("Unknown `#[inline]` argument `{arg}`."),
+        };
+
+        fn_value.add_attribute(
+            AttributeLoc::Function,
+            self.context
+                .create_enum_attribute(Attribute::get_named_enum_kind_id(kind), 0),
+        );
+    }
+
+    /// Marks the calls in tail position in the given function as `tail` calls,
+    /// so that LLVM can reuse the caller's stack frame for them.
+    ///
+    /// A call is in tail position when its value is returned immediately,
+    /// either by the `ret` that follows it or through the `phi`s joining the
+    /// branches of an `if` or `match`.
+    fn mark_tail_calls(&self, fn_value: FunctionValue<'ctx>) -> Result<(), BackendError> {
+        let mut tail_calls = Vec::new();
+        let mut visited = HashSet::new();
+
+        for block in fn_value.get_basic_blocks() {
+            let Some(terminator) = block.get_terminator() else {
+                continue;
+            };
+
+            if terminator.get_opcode() == InstructionOpcode::Return {
+                let value = terminator.get_operand(0).and_then(|operand| operand.left());
+
+                find_tail_calls(fn_value, block, value, &mut visited, &mut tail_calls)?;
+            }
+        }
+
+        for call_site in self.call_sites.borrow().iter() {
+            let Some(call) = tail_calls
+                .iter()
+                .find(|call| call.as_value_ref() == call_site.as_value_ref())
+            else {
+                continue;
+            };
+
+            // A `tail` call may not access the caller's stack, which arrays
+            // and structs are passed by pointer into.
+            let passes_stack_ptr = (0..call.get_num_operands()).any(|index| {
+                call.get_operand(index)
+                    .and_then(|operand| operand.left())
+                    .and_then(|operand| operand.as_instruction_value())
+                    .is_some_and(|operand| operand.get_opcode() == InstructionOpcode::Alloca)
+            });
+
+            if !passes_stack_ptr {
+                call_site.set_tail_call(true);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the given statements, returning the value of the last
+    /// expression statement.
+    fn compile_block(
+        &self,
+        fn_params: &ThinVec<TyFnParam>,
+        fn_value: &FunctionValue<'ctx>,
+        locals: &mut HashMap<TyPath, PointerValue<'ctx>>,
+        stmts: &[TyStmt],
+    ) -> Result<Option<BasicValueEnum<'ctx>>, BackendError> {
+        let mut last_stmt = None;
+
+        for stmt in stmts {
+            match &stmt.kind {
+                TyStmtKind::Local(local) => {
+                    let ty = local.ty.as_ref().ok_or_else(|| {
+                        BackendError::internal(format!(
+                            "No type for `let` binding `{}`.",
+                            local.name
+                        ))
+                    })?;
+
+                    let ty = self.lower_type(ty)?.ok_or_else(|| {
+                        BackendError::internal(format!(
+                            "`let` binding `{}` has no value.",
+                            local.name
+                        ))
+                    })?;
+
+                    let local_ptr = self
+                        .builder
+                        .build_alloca(ty, &self.value_name(fn_value, &local.name.name));
+
+                    let value = match &local.kind {
+                        TyLocalKind::Decl => None,
+                        TyLocalKind::Init(init) => {
+                            self.compile_expr(fn_params, fn_value, locals, init)?
+                        }
+                    }
+                    .ok_or_else(|| {
+                        BackendError::internal(format!(
+                            "`let` binding `{}` does not have an initializer.",
+                            local.name
+                        ))
+                    })?;
+
+                    self.builder.build_store(local_ptr, value);
+
+                    let local_path = TyPath {
+                        segments: thin_vec![TyPathSegment {
+                            ident: local.name.clone()
+                        }],
+                        span: local.name.span,
+                    };
+
+                    locals.insert(local_path, local_ptr);
+                }
+                TyStmtKind::Expr(expr) => {
+                    last_stmt = self.compile_expr(fn_params, fn_value, locals, expr)?;
+                }
+                TyStmtKind::Item(item) => {
+                    self.compile_nested_fn(*fn_value, item)?;
+
+                    last_stmt = None;
+                }
+            }
+        }
+
+        Ok(last_stmt)
+    }
+
+    fn compile_expr(
+        &self,
+        fn_params: &ThinVec<TyFnParam>,
+        fn_value: &FunctionValue<'ctx>,
+        locals: &HashMap<TyPath, PointerValue<'ctx>>,
+        expr: &TyExpr,
+    ) -> Result<Option<BasicValueEnum<'ctx>>, BackendError> {
+        Ok(match &expr.kind {
+            TyExprKind::Literal(literal) => match &literal.kind {
+                TyLiteralKind::String(literal) => Some(
+                    self.compile_string_literal(literal.clone())
+                        .as_basic_value_enum(),
+                ),
+                TyLiteralKind::Integer(literal) => Some(
+                    self.compile_integer_literal(literal.clone(), &expr.ty)?
+                        .as_basic_value_enum(),
+                ),
+                TyLiteralKind::Float(value) => Some(
+                    self.context
+                        .f64_type()
+                        .const_float(*value)
+                        .as_basic_value_enum(),
+                ),
+            },
+            TyExprKind::Variable(path) => {
+                if let Some(value) = self.consts.borrow().get(path) {
+                    return Ok(Some(*value));
+                }
+
+                if let Some((param_index, param)) = fn_params
+                    .iter()
+                    .enumerate()
+                    .find(|(_, param)| *param.name.name == path.to_string())
+                {
+                    let param_value = nth_param(*fn_value, param_index as u32)?;
+
+                    // Arrays and structs are passed by pointer, so load the value it points to.
+                    if self.is_aggregate(&param.ty) {
+                        let Some(param_type) = self.lower_type(&param.ty)? else {
+                            return Ok(None);
+                        };
+
+                        return Ok(Some(self.builder.build_load(
+                            param_type,
+                            param_value.into_pointer_value(),
+                            &self.value_name(fn_value, &format!("{}.load", param.name)),
+                        )));
+                    }
+
+                    return Ok(Some(param_value));
+                }
+
+                if let Some(local) = locals.get(path) {
+                    let ty = match self.to_llvm_type(expr.ty)? {
+                        AnyTypeEnum::FunctionType(_) => self
+                            .context
+                            .i8_type()
+                            .ptr_type(AddressSpace::default())
+                            .as_basic_type_enum(),
+                        ty => BasicTypeEnum::try_from(ty).map_err(|_| {
+                            BackendError::internal(format!("`{path}` is not a value."))
+                        })?,
+                    };
+
+                    return Ok(Some(self.builder.build_load(
+                        ty,
+                        *local,
+                        &self.value_name(fn_value, &format!("{path}.load")),
+                    )));
+                }
+
+                if let Some(ordering) = self.compile_memory_ordering(path) {
+                    return Ok(Some(ordering));
+                }
+
+                // Functions can be used as values, in which case they become function pointers.
+                self.module.get_function(&path.to_string()).map(|function| {
+                    function
+                        .as_global_value()
+                        .as_pointer_value()
+                        .as_basic_value_enum()
+                })
+            }
+            TyExprKind::Call { fun, args } if is_assert(fun) => {
+                self.compile_assert(fn_params, fn_value, locals, args)?;
+
+                None
+            }
+            // A call that diverges ends its block, so the code after it goes
+            // in a new block that is never reached. Its value is never used,
+            // so any value of the type expected where it is used will do.
+            TyExprKind::Call { fun, args } if expr.diverges() => {
+                self.compile_fn_call(fn_value, fn_params, fun, args, locals)?;
+
+                self.builder.build_unreachable();
+
+                let unreachable_block = self.append_block(fn_value, "unreachable");
+                self.builder.position_at_end(unreachable_block);
+
+                self.lower_type(&expr.ty)?.map(|ty| ty.const_zero())
+            }
+            TyExprKind::Call { fun, args } => self
+                .compile_fn_call(fn_value, fn_params, fun, args, locals)?
+                .try_as_basic_value()
+                .either(Some, |_| None),
+            TyExprKind::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } => {
+                let rhs_block = self.append_block(fn_value, "and.rhs");
+                let end_block = self.append_block(fn_value, "and.end");
+
+                let Some(lhs) = self
+                    .compile_expr(fn_params, fn_value, locals, lhs)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+                let lhs_block = self.insert_block()?;
+
+                // The right-hand side is only evaluated when the left-hand
+                // side holds.
+                self.builder
+                    .build_conditional_branch(lhs, rhs_block, end_block);
+
+                self.builder.position_at_end(rhs_block);
+
+                let Some(rhs) = self
+                    .compile_expr(fn_params, fn_value, locals, rhs)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+                let rhs_block = self.insert_block()?;
+
+                self.builder.build_unconditional_branch(end_block);
+
+                self.builder.position_at_end(end_block);
+
+                let phi = self
+                    .builder
+                    .build_phi(self.context.bool_type(), &self.value_name(fn_value, "and"));
+                phi.add_incoming(&[
+                    (&self.context.bool_type().const_zero(), lhs_block),
+                    (&rhs, rhs_block),
+                ]);
+
+                Some(phi.as_basic_value())
+            }
+            TyExprKind::Binary { op, lhs, rhs } if is_string(&lhs.ty) => {
+                let Some(lhs) = self.compile_expr(fn_params, fn_value, locals, lhs)? else {
+                    return Ok(None);
+                };
+                let Some(rhs) = self.compile_expr(fn_params, fn_value, locals, rhs)? else {
+                    return Ok(None);
+                };
+
+                let string_eq = self.function("std::string::string_eq")?;
+
+                let is_eq = self
+                    .builder
+                    .build_call(string_eq, &[lhs.into(), rhs.into()], "is_eq")
+                    .try_as_basic_value()
+                    .left()
+                    .ok_or_else(returns_no_value)?
+                    .into_int_value();
+
+                Some(match op {
+                    BinaryOp::Eq => is_eq.as_basic_value_enum(),
+                    BinaryOp::Ne => self.builder.build_not(is_eq, "is_ne").into(),
+                    _ => unreachable!("Strings only support `==` and `!=`."),
+                })
+            }
+            TyExprKind::Binary { op, lhs, rhs } if matches!(&*lhs.ty, TyKind::Float(_)) => {
+                let Some(lhs) = self
+                    .compile_expr(fn_params, fn_value, locals, lhs)?
+                    .map(BasicValueEnum::into_float_value)
+                else {
+                    return Ok(None);
+                };
+                let Some(rhs) = self
+                    .compile_expr(fn_params, fn_value, locals, rhs)?
+                    .map(BasicValueEnum::into_float_value)
+                else {
+                    return Ok(None);
+                };
+
+                Some(self.compile_float_binary_op(*op, lhs, rhs))
+            }
+            TyExprKind::Binary { op, lhs, rhs } => {
+                let (_, is_signed) = self.lower_int_type(&lhs.ty).ok_or_else(|| {
+                    BackendError::internal(format!("Cannot apply `{op}` to {:?}", lhs.ty))
+                })?;
+
+                let Some(lhs) = self
+                    .compile_expr(fn_params, fn_value, locals, lhs)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+                let Some(rhs) = self
+                    .compile_expr(fn_params, fn_value, locals, rhs)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+
+                Some(
+                    self.compile_binary_op(*op, lhs, rhs, is_signed)
+                        .as_basic_value_enum(),
+                )
+            }
+            TyExprKind::Cast(inner) => {
+                let from_ty = inner.ty;
+                let Some(value) = self.compile_expr(fn_params, fn_value, locals, inner)? else {
+                    return Ok(None);
+                };
+
+                Some(self.compile_cast(value, &from_ty, &expr.ty)?)
+            }
+            TyExprKind::Array(elems) => {
+                let Some(array_type) = self
+                    .lower_type(&expr.ty)?
+                    .map(BasicTypeEnum::into_array_type)
+                else {
+                    return Ok(None);
+                };
+
+                let mut values = Vec::new();
+
+                for elem in elems {
+                    let Some(value) = self.compile_expr(fn_params, fn_value, locals, elem)? else {
+                        return Ok(None);
+                    };
+
+                    values.push(value);
+                }
+
+                // Arrays of constants are emitted as constant globals, rather
+                // than being built up element by element.
+                if let Some(const_array) = const_array(array_type.get_element_type(), &values) {
+                    let global = self.module.add_global(array_type, None, "array_lit");
+                    global.set_linkage(Linkage::Internal);
+                    global.set_constant(true);
+                    global.set_initializer(&const_array);
+
+                    return Ok(Some(self.builder.build_load(
+                        array_type,
+                        global.as_pointer_value(),
+                        "array",
+                    )));
+                }
+
+                let array_ptr = self.builder.build_alloca(array_type, "array");
+
+                for (index, value) in values.into_iter().enumerate() {
+                    let elem_ptr = self.build_array_elem_ptr(
+                        array_type,
+                        array_ptr,
+                        self.context.i64_type().const_int(index as u64, false),
+                    );
+
+                    self.builder.build_store(elem_ptr, value);
+                }
+
+                Some(self.builder.build_load(array_type, array_ptr, "array"))
+            }
+            TyExprKind::Index { expr: array, index } => {
+                let Some(array_type) = self
+                    .lower_type(&array.ty)?
+                    .map(BasicTypeEnum::into_array_type)
+                else {
+                    return Ok(None);
+                };
+
+                let array_ptr = self.compile_aggregate_ptr(fn_params, fn_value, locals, array)?;
+
+                let Some(index) = self
+                    .compile_expr(fn_params, fn_value, locals, index)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+                let index = self.builder.build_int_z_extend_or_bit_cast(
+                    index,
+                    self.context.i64_type(),
+                    "index",
+                );
+
+                self.build_bounds_check(fn_value, index, array_type.len())?;
+
+                let elem_ptr = self.build_array_elem_ptr(array_type, array_ptr, index);
+
+                Some(
+                    self.builder
+                        .build_load(array_type.get_element_type(), elem_ptr, "elem"),
+                )
+            }
+            TyExprKind::Struct(fields) if self.bitfield_fields(&expr.ty).is_some() => {
+                let layout = self.bitfield_fields(&expr.ty).unwrap_or_default();
+
+                let Some(struct_type) = self
+                    .lower_type(&expr.ty)?
+                    .map(BasicTypeEnum::into_struct_type)
+                else {
+                    return Ok(None);
+                };
+
+                let mut storage = struct_type
+                    .get_field_type_at_index(0)
+                    .ok_or_else(|| BackendError::internal("A bitfield struct has no storage."))?
+                    .into_int_type()
+                    .const_zero();
+
+                for (field, (offset, width)) in fields.into_iter().zip(layout) {
+                    let Some(value) = self
+                        .compile_expr(fn_params, fn_value, locals, field)?
+                        .map(BasicValueEnum::into_int_value)
+                    else {
+                        return Ok(None);
+                    };
+
+                    storage = self.bitfield_set(storage, value, offset, width);
+                }
+
+                let struct_value = self
+                    .builder
+                    .build_insert_value(struct_type.get_undef(), storage, 0, "bitfield")
+                    .ok_or_else(|| BackendError::internal("Failed to store a bitfield."))?
+                    .into_struct_value();
+
+                Some(struct_value.as_basic_value_enum())
+            }
+            // A tuple struct is represented as the value it wraps.
+            TyExprKind::Struct(fields) if self.newtype_inner(&expr.ty).is_some() => {
+                self.compile_expr(fn_params, fn_value, locals, fields[0])?
+            }
+            TyExprKind::Struct(fields) => {
+                let Some(struct_type) = self
+                    .lower_type(&expr.ty)?
+                    .map(BasicTypeEnum::into_struct_type)
+                else {
+                    return Ok(None);
+                };
+
+                let mut struct_value = struct_type.get_undef();
+
+                for (index, field) in fields.into_iter().enumerate() {
+                    let value = self.compile_expr(fn_params, fn_value, locals, field)?;
+
+                    // Fields without a value aren't part of the LLVM struct.
+                    let (Some(value), Some(field_index)) =
+                        (value, self.struct_field_index(&expr.ty, index)?)
+                    else {
+                        continue;
+                    };
+
+                    struct_value = self
+                        .builder
+                        .build_insert_value(struct_value, value, field_index, "field")
+                        .ok_or_else(|| {
+                            BackendError::internal(format!("Failed to store field {index}."))
+                        })?
+                        .into_struct_value();
+                }
+
+                Some(struct_value.as_basic_value_enum())
+            }
+            TyExprKind::Field {
+                expr: struct_expr,
+                index,
+            } if self.bitfield_fields(&struct_expr.ty).is_some() => {
+                let (offset, width) = self
+                    .bitfield_fields(&struct_expr.ty)
+                    .and_then(|layout| layout.get(*index).copied())
+                    .ok_or_else(|| {
+                        BackendError::internal(format!("No bitfield at index {index}."))
+                    })?;
+
+                let Some(struct_value) = self
+                    .compile_expr(fn_params, fn_value, locals, struct_expr)?
+                    .map(BasicValueEnum::into_struct_value)
+                else {
+                    return Ok(None);
+                };
+
+                let storage = self
+                    .builder
+                    .build_extract_value(struct_value, 0, "bitfield")
+                    .ok_or_else(|| BackendError::internal("Failed to load a bitfield."))?
+                    .into_int_value();
+
+                Some(
+                    self.bitfield_get(storage, offset, width, &expr.ty)?
+                        .as_basic_value_enum(),
+                )
+            }
+            TyExprKind::Field {
+                expr: struct_expr, ..
+            } if self.newtype_inner(&struct_expr.ty).is_some() => {
+                self.compile_expr(fn_params, fn_value, locals, struct_expr)?
+            }
+            TyExprKind::Field {
+                expr: struct_expr,
+                index,
+            } => {
+                let Some(field_index) = self.struct_field_index(&struct_expr.ty, *index)? else {
+                    return Ok(None);
+                };
+
+                let Some(struct_value) = self
+                    .compile_expr(fn_params, fn_value, locals, struct_expr)?
+                    .map(BasicValueEnum::into_struct_value)
+                else {
+                    return Ok(None);
+                };
+
+                self.builder
+                    .build_extract_value(struct_value, field_index, "field")
+            }
+            TyExprKind::Assign { target, value } => {
+                let local = *locals.get(target).ok_or_else(|| {
+                    BackendError::internal(format!("`{target}` is not a local variable."))
+                })?;
+
+                if let Some(value) = self.compile_expr(fn_params, fn_value, locals, value)? {
+                    self.builder.build_store(local, value);
+                }
+
+                None
+            }
+            TyExprKind::While { cond, body } => {
+                let cond_block = self.append_block(fn_value, "while.cond");
+                let body_block = self.append_block(fn_value, "while.body");
+                let end_block = self.append_block(fn_value, "while.end");
+
+                self.builder.build_unconditional_branch(cond_block);
+
+                self.builder.position_at_end(cond_block);
+
+                let Some(cond) = self
+                    .compile_expr(fn_params, fn_value, locals, cond)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+
+                self.builder
+                    .build_conditional_branch(cond, body_block, end_block);
+
+                self.builder.position_at_end(body_block);
+
+                // Bindings in the body go out of scope at the end of each iteration.
+                let mut body_locals = locals.clone();
+                self.compile_block(fn_params, fn_value, &mut body_locals, body)?;
+
+                self.builder.build_unconditional_branch(cond_block);
+
+                self.builder.position_at_end(end_block);
+
+                None
+            }
+            TyExprKind::TypeOf(name) => Some(
+                self.compile_string_literal(format!("\"{name}\"").into())
+                    .as_basic_value_enum(),
+            ),
+            TyExprKind::Asm(lines) => {
+                self.compile_asm(lines);
+
+                None
+            }
+            TyExprKind::Block(body) => {
+                let mut block_locals = locals.clone();
+
+                self.compile_block(fn_params, fn_value, &mut block_locals, body)?
+            }
+            TyExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let then_block = self.append_block(fn_value, "if.then");
+                let else_block = self.append_block(fn_value, "if.else");
+                let end_block = self.append_block(fn_value, "if.end");
+
+                let Some(cond) = self
+                    .compile_expr(fn_params, fn_value, locals, cond)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+
+                self.builder
+                    .build_conditional_branch(cond, then_block, else_block);
+
+                self.builder.position_at_end(then_block);
+
+                let then_value =
+                    self.compile_block(fn_params, fn_value, &mut locals.clone(), then_branch)?;
+                // The branch may have ended in a different block than it started in.
+                let then_block = self.insert_block()?;
+
+                self.builder.build_unconditional_branch(end_block);
+
+                self.builder.position_at_end(else_block);
+
+                let else_value = match else_branch {
+                    Some(else_branch) => {
+                        self.compile_block(fn_params, fn_value, &mut locals.clone(), else_branch)?
+                    }
+                    None => None,
+                };
+                let else_block = self.insert_block()?;
+
+                self.builder.build_unconditional_branch(end_block);
+
+                self.builder.position_at_end(end_block);
+
+                // An `if` with an `else` produces the value of the branch taken.
+                let Some(ty) = self.lower_type(&expr.ty)? else {
+                    return Ok(None);
+                };
+
+                let (Some(then_value), Some(else_value)) = (then_value, else_value) else {
+                    return Ok(None);
+                };
+
+                let phi = self
+                    .builder
+                    .build_phi(ty, &self.value_name(fn_value, "if.value"));
+                phi.add_incoming(&[(&then_value, then_block), (&else_value, else_block)]);
+
+                Some(phi.as_basic_value())
+            }
+            TyExprKind::Match { scrutinee, arms } => {
+                let scrutinee_ty = scrutinee.ty;
+
+                let Some(scrutinee) = self
+                    .compile_expr(fn_params, fn_value, locals, scrutinee)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+
+                let end_block = self.append_block(fn_value, "match.end");
+
+                // The typer ensures there is exactly one `_` arm, which becomes
+                // the default case, and that the literal arms are distinct.
+                let mut default_block = end_block;
+                let mut cases = Vec::new();
+                let mut arm_blocks = Vec::new();
+
+                for arm in arms {
+                    let arm_block = self.append_block(fn_value, "match.arm");
+
+                    match &arm.pat.kind {
+                        TyPatKind::Wild => default_block = arm_block,
+                        TyPatKind::Literal(literal) => {
+                            let TyLiteralKind::Integer(literal) = &literal.kind else {
+                                unreachable!("Only integer literals can be matched on.");
+                            };
+
+                            cases.push((
+                                self.compile_integer_literal(literal.clone(), &scrutinee_ty)?,
+                                arm_block,
+                            ));
+                        }
+                    }
+
+                    arm_blocks.push(arm_block);
+                }
+
+                end_block
+                    .move_after(*arm_blocks.last().unwrap_or(&default_block))
+                    .map_err(|()| BackendError::internal("Failed to move the end of a `match`."))?;
+
+                self.builder.build_switch(scrutinee, default_block, &cases);
+
+                let mut incoming = Vec::new();
+                let mut reaches_end = false;
+
+                for (arm, arm_block) in arms.into_iter().zip(arm_blocks) {
+                    self.builder.position_at_end(arm_block);
+
+                    let value =
+                        self.compile_block(fn_params, fn_value, &mut locals.clone(), &arm.body)?;
+
+                    // Arms that diverge don't reach the end of the `match`.
+                    if matches!(
+                        arm.body.last().map(|stmt| &stmt.kind),
+                        Some(TyStmtKind::Expr(expr)) if expr.diverges()
+                    ) {
+                        self.builder.build_unreachable();
+                        continue;
+                    }
+
+                    if let Some(value) = value {
+                        incoming.push((value, self.insert_block()?));
+                    }
+
+                    self.builder.build_unconditional_branch(end_block);
+                    reaches_end = true;
+                }
+
+                self.builder.position_at_end(end_block);
+
+                if !reaches_end {
+                    self.builder.build_unreachable();
+                    return Ok(None);
+                }
+
+                // A `match` used as an expression produces the value of the arm taken.
+                let Some(ty) = self.lower_type(&expr.ty)? else {
+                    return Ok(None);
+                };
+
+                let phi = self
+                    .builder
+                    .build_phi(ty, &self.value_name(fn_value, "match.value"));
+                for (value, block) in &incoming {
+                    phi.add_incoming(&[(value, *block)]);
+                }
+
+                Some(phi.as_basic_value())
+            }
+            TyExprKind::For(for_expr) => {
+                let TyForExpr {
+                    binding,
+                    start,
+                    end,
+                    body,
+                } = &**for_expr;
+
+                let (int_type, is_signed) = self.lower_int_type(&start.ty).ok_or_else(|| {
+                    BackendError::internal(format!("Cannot iterate over {:?}", start.ty))
+                })?;
+
+                let Some(start) = self
+                    .compile_expr(fn_params, fn_value, locals, start)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+                let Some(end) = self
+                    .compile_expr(fn_params, fn_value, locals, end)?
+                    .map(BasicValueEnum::into_int_value)
+                else {
+                    return Ok(None);
+                };
+
+                let binding_ptr = self
+                    .builder
+                    .build_alloca(int_type, &self.value_name(fn_value, &binding.name));
+                self.builder.build_store(binding_ptr, start);
+
+                let cond_block = self.append_block(fn_value, "for.cond");
+                let body_block = self.append_block(fn_value, "for.body");
+                let end_block = self.append_block(fn_value, "for.end");
+
+                self.builder.build_unconditional_branch(cond_block);
+
+                self.builder.position_at_end(cond_block);
+
+                let current = self
+                    .builder
+                    .build_load(int_type, binding_ptr, "current")
+                    .into_int_value();
+
+                let predicate = if is_signed {
+                    IntPredicate::SLT
+                } else {
+                    IntPredicate::ULT
+                };
+
+                let in_range = self
+                    .builder
+                    .build_int_compare(predicate, current, end, "in_range");
+
+                self.builder
+                    .build_conditional_branch(in_range, body_block, end_block);
+
+                self.builder.position_at_end(body_block);
+
+                // Bindings in the body go out of scope at the end of each iteration.
+                let mut body_locals = locals.clone();
+                body_locals.insert(
+                    TyPath {
+                        segments: thin_vec![TyPathSegment {
+                            ident: binding.clone()
+                        }],
+                        span: binding.span,
+                    },
+                    binding_ptr,
+                );
+                self.compile_block(fn_params, fn_value, &mut body_locals, body)?;
+
+                let current = self
+                    .builder
+                    .build_load(int_type, binding_ptr, "current")
+                    .into_int_value();
+                let next =
+                    self.builder
+                        .build_int_add(current, int_type.const_int(1, false), "next");
+                self.builder.build_store(binding_ptr, next);
+
+                self.builder.build_unconditional_branch(cond_block);
+
+                self.builder.position_at_end(end_block);
+
+                None
+            }
+        })
+    }
+
+    /// Returns a pointer to the given array- or `struct`-typed expression.
+    ///
+    /// Locals and parameters are used in place, whereas any other expression
+    /// is first spilled to the stack.
+    fn compile_aggregate_ptr(
+        &self,
+        fn_params: &ThinVec<TyFnParam>,
+        fn_value: &FunctionValue<'ctx>,
+        locals: &HashMap<TyPath, PointerValue<'ctx>>,
+        expr: &TyExpr,
+    ) -> Result<PointerValue<'ctx>, BackendError> {
+        if let TyExprKind::Variable(path) = &expr.kind {
+            if let Some(local) = locals.get(path) {
+                return Ok(*local);
+            }
+
+            // Arrays and structs are passed to functions by pointer.
+            if let Some((param_index, _)) = fn_params
+                .iter()
+                .enumerate()
+                .find(|(_, param)| *param.name.name == path.to_string())
+            {
+                return Ok(nth_param(*fn_value, param_index as u32)?.into_pointer_value());
+            }
+        }
+
+        let aggregate_type = self.lower_type(&expr.ty)?.ok_or_else(|| {
+            BackendError::internal(format!("Not an array or struct: {:?}", expr.ty))
+        })?;
+
+        let value = self
+            .compile_expr(fn_params, fn_value, locals, expr)?
+            .ok_or_else(|| BackendError::internal("An array or struct always has a value."))?;
+
+        let aggregate_ptr = self.builder.build_alloca(aggregate_type, "aggregate");
+        self.builder.build_store(aggregate_ptr, value);
+
+        Ok(aggregate_ptr)
+    }
+
+    /// Returns a name for a new value or block in the given function, derived
+    /// from `name`.
+    ///
+    /// The first use of a name in a function gets it as is, and later ones get
+    /// a numeric suffix, like `if.then.1`, so the names in the emitted IR
+    /// follow the source instead of LLVM's renaming.
+    fn value_name(&self, fn_value: &FunctionValue<'ctx>, name: &str) -> String {
+        let fn_name = fn_value.get_name().to_string_lossy().into_owned();
+
+        let mut value_names = self.value_names.borrow_mut();
+        let count = value_names.entry((fn_name, name.to_string())).or_insert(0);
+
+        let unique_name = match *count {
+            0 => name.to_string(),
+            count => format!("{name}.{count}"),
+        };
+
+        *count += 1;
+
+        unique_name
+    }
+
+    /// Appends a basic block named after `name` to the given function.
+    fn append_block(&self, fn_value: &FunctionValue<'ctx>, name: &str) -> BasicBlock<'ctx> {
+        self.context
+            .append_basic_block(*fn_value, &self.value_name(fn_value, name))
+    }
+
+    /// Returns the name for the result of a call to `callee`, which is empty
+    /// for calls that don't return a value, as those can't be named.
+    fn call_result_name(
+        &self,
+        caller: &FunctionValue<'ctx>,
+        callee: &str,
+        returns_void: bool,
+    ) -> String {
+        if returns_void {
+            return String::new();
+        }
+
+        self.value_name(caller, &format!("{callee}_ret"))
+    }
+
+    /// Returns a pointer to the element at `index` in the array at `array_ptr`.
+    fn build_array_elem_ptr(
+        &self,
+        array_type: ArrayType<'ctx>,
+        array_ptr: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let zero = self.context.i64_type().const_zero();
+
+        // SAFETY: The index is bounds checked or known to be in bounds.
+        unsafe {
+            self.builder
+                .build_in_bounds_gep(array_type, array_ptr, &[zero, index], "elem_ptr")
+        }
+    }
+
+    /// Aborts the program if `index` is not less than `len`.
+    ///
+    /// Array indexing is always bounds checked.
+    fn build_bounds_check(
+        &self,
+        fn_value: &FunctionValue<'ctx>,
+        index: IntValue<'ctx>,
+        len: u32,
+    ) -> Result<(), BackendError> {
+        let in_bounds = self.append_block(fn_value, "in_bounds");
+        let out_of_bounds = self.append_block(fn_value, "out_of_bounds");
+
+        let is_in_bounds = self.builder.build_int_compare(
+            IntPredicate::ULT,
+            index,
+            self.context.i64_type().const_int(len as u64, false),
+            "is_in_bounds",
+        );
+
+        self.builder
+            .build_conditional_branch(is_in_bounds, in_bounds, out_of_bounds);
+
+        self.builder.position_at_end(out_of_bounds);
+
+        let callee = self.function("abort")?;
+        self.builder.build_call(callee, &[], "");
+
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(in_bounds);
+
+        Ok(())
+    }
+
+    /// Compiles a call to `std::process::assert` inline, as a branch to a
+    /// block that reports the failure and aborts.
+    ///
+    /// The typer passes the location of the call after the condition and the
+    /// message, which are only evaluated when the assertion fails.
+    fn compile_assert(
+        &self,
+        fn_params: &ThinVec<TyFnParam>,
+        fn_value: &FunctionValue<'ctx>,
+        locals: &HashMap<TyPath, PointerValue<'ctx>>,
+        args: &[&TyExpr],
+    ) -> Result<(), BackendError> {
+        let [cond, message, location] = args else {
+            unreachable!("`assert` is called with a condition, a message and a location.");
+        };
+
+        let cond = self
+            .compile_expr(fn_params, fn_value, locals, cond)?
+            .ok_or_else(|| BackendError::internal("An `assert` has no condition."))?
+            .into_int_value();
+
+        let failed_block = self.append_block(fn_value, "assert.failed");
+        let passed_block = self.append_block(fn_value, "assert.passed");
+
+        self.builder
+            .build_conditional_branch(cond, passed_block, failed_block);
+
+        self.builder.position_at_end(failed_block);
+
+        let message = self
+            .compile_expr(fn_params, fn_value, locals, message)?
+            .ok_or_else(|| BackendError::internal("An `assert` has no message."))?;
+        let location = self
+            .compile_expr(fn_params, fn_value, locals, location)?
+            .ok_or_else(|| BackendError::internal("An `assert` has no location."))?;
+
+        let assert_failed = self.function("std::process::assert_failed")?;
+
+        self.builder
+            .build_call(assert_failed, &[location.into(), message.into()], "");
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(passed_block);
+
+        Ok(())
+    }
+
+    /// Defines a `const` as an internal constant global, recording the value
+    /// that references to it compile to.
+    ///
+    /// Integers are used directly, rather than loaded from the global, while
+    /// strings refer to the global of the string literal.
+    fn define_const(&self, konst: &TyConst) -> Result<(), BackendError> {
+        let value = match &konst.value.kind {
+            TyExprKind::Literal(literal) => match &literal.kind {
+                TyLiteralKind::String(literal) => self
+                    .compile_string_literal(literal.clone())
+                    .as_pointer_value()
+                    .as_basic_value_enum(),
+                TyLiteralKind::Integer(literal) => {
+                    let value = self.compile_integer_literal(literal.clone(), &konst.value.ty)?;
+
+                    let global =
+                        self.module
+                            .add_global(value.get_type(), None, &konst.path.to_string());
+                    global.set_linkage(Linkage::Internal);
+                    global.set_constant(true);
+                    global.set_initializer(&value);
+
+                    value.as_basic_value_enum()
+                }
+                TyLiteralKind::Float(value) => {
+                    let value = self.context.f64_type().const_float(*value);
+
+                    let global =
+                        self.module
+                            .add_global(value.get_type(), None, &konst.path.to_string());
+                    global.set_linkage(Linkage::Internal);
+                    global.set_constant(true);
+                    global.set_initializer(&value);
+
+                    value.as_basic_value_enum()
+                }
+            },
+            _ => unreachable!("The value of a `const` is folded into a literal."),
+        };
+
+        self.consts.borrow_mut().insert(konst.path.clone(), value);
+
+        Ok(())
+    }
+
+    fn compile_string_literal(&self, literal: SmolStr) -> GlobalValue<'ctx> {
+        if let Some(global) = self.string_literals.borrow().get(&literal) {
+            return *global;
+        }
+
+        // Unquote the string literal.
+        let value = {
+            let mut chars = literal.chars();
+            chars.next();
+            chars.next_back();
+            chars.as_str()
+        };
+
+        let value = value.as_bytes();
+
+        let i8_type = self.context.i8_type();
+        let i8_array_type = i8_type.array_type(value.len() as u32 + 1);
+
+        let string = self.context.const_string(value, true);
+
+        let global = self.module.add_global(i8_array_type, None, "string_lit");
+        global.set_linkage(Linkage::Internal);
+        global.set_constant(true);
+        global.set_initializer(&string);
+
+        self.string_literals.borrow_mut().insert(literal, global);
+
+        global
+    }
+
+    fn compile_integer_literal(
+        &self,
+        literal: TyIntegerLiteral,
+        ty: &TypeId,
+    ) -> Result<IntValue<'ctx>, BackendError> {
+        let (int_type, _) = self.lower_int_type(ty).ok_or_else(|| {
+            BackendError::internal(format!("Integer literal has a non-integer type: {ty:?}"))
+        })?;
+
+        Ok(match literal {
+            TyIntegerLiteral::Signed(value, _) => int_type.const_int(value as u64, true),
+            TyIntegerLiteral::Unsigned(value, _) => int_type.const_int(value as u64, false),
+        })
+    }
+
+    fn compile_float_binary_op(
+        &self,
+        op: BinaryOp,
+        lhs: FloatValue<'ctx>,
+        rhs: FloatValue<'ctx>,
+    ) -> BasicValueEnum<'ctx> {
+        let compare = |predicate, name| {
+            self.builder
+                .build_float_compare(predicate, lhs, rhs, name)
+                .as_basic_value_enum()
+        };
+
+        match op {
+            BinaryOp::Add => self.builder.build_float_add(lhs, rhs, "add").into(),
+            BinaryOp::Sub => self.builder.build_float_sub(lhs, rhs, "sub").into(),
+            BinaryOp::Mul => self.builder.build_float_mul(lhs, rhs, "mul").into(),
+            BinaryOp::Div => self.builder.build_float_div(lhs, rhs, "div").into(),
+            BinaryOp::Rem => self.builder.build_float_rem(lhs, rhs, "rem").into(),
+            BinaryOp::Eq => compare(FloatPredicate::OEQ, "eq"),
+            BinaryOp::Ne => compare(FloatPredicate::UNE, "ne"),
+            BinaryOp::Lt => compare(FloatPredicate::OLT, "lt"),
+            BinaryOp::Le => compare(FloatPredicate::OLE, "le"),
+            BinaryOp::Gt => compare(FloatPredicate::OGT, "gt"),
+            BinaryOp::Ge => compare(FloatPredicate::OGE, "ge"),
+            BinaryOp::And => unreachable!("`&&` is compiled on `Bool`s."),
+        }
+    }
+
+    /// Converts a value between numeric types.
+    fn compile_cast(
+        &self,
+        value: BasicValueEnum<'ctx>,
+        from_ty: &TypeId,
+        to_ty: &TypeId,
+    ) -> Result<BasicValueEnum<'ctx>, BackendError> {
+        let to_type = self
+            .lower_type(to_ty)?
+            .ok_or_else(|| BackendError::internal(format!("Cannot cast to {to_ty:?}")))?;
+
+        Ok(
+            match (self.lower_int_type(from_ty), self.lower_int_type(to_ty)) {
+                (Some((_, is_signed)), Some((int_type, _))) => self
+                    .builder
+                    .build_int_cast_sign_flag(value.into_int_value(), int_type, is_signed, "cast")
+                    .into(),
+                (Some((_, true)), None) => self
+                    .builder
+                    .build_signed_int_to_float(
+                        value.into_int_value(),
+                        to_type.into_float_type(),
+                        "cast",
+                    )
+                    .into(),
+                (Some((_, false)), None) => self
+                    .builder
+                    .build_unsigned_int_to_float(
+                        value.into_int_value(),
+                        to_type.into_float_type(),
+                        "cast",
+                    )
+                    .into(),
+                (None, Some((int_type, true))) => self
+                    .builder
+                    .build_float_to_signed_int(value.into_float_value(), int_type, "cast")
+                    .into(),
+                (None, Some((int_type, false))) => self
+                    .builder
+                    .build_float_to_unsigned_int(value.into_float_value(), int_type, "cast")
+                    .into(),
+                (None, None) => value,
+            },
+        )
+    }
+
+    fn compile_binary_op(
+        &self,
+        op: BinaryOp,
+        lhs: IntValue<'ctx>,
+        rhs: IntValue<'ctx>,
+        is_signed: bool,
+    ) -> IntValue<'ctx> {
+        let predicate = |signed, unsigned| if is_signed { signed } else { unsigned };
+
+        match op {
+            BinaryOp::Add => self.builder.build_int_add(lhs, rhs, "add"),
+            BinaryOp::Sub => self.builder.build_int_sub(lhs, rhs, "sub"),
+            BinaryOp::Mul => self.builder.build_int_mul(lhs, rhs, "mul"),
+            BinaryOp::Div if is_signed => self.builder.build_int_signed_div(lhs, rhs, "div"),
+            BinaryOp::Div => self.builder.build_int_unsigned_div(lhs, rhs, "div"),
+            BinaryOp::Rem if is_signed => self.builder.build_int_signed_rem(lhs, rhs, "rem"),
+            BinaryOp::Rem => self.builder.build_int_unsigned_rem(lhs, rhs, "rem"),
+            BinaryOp::Eq => self
+                .builder
+                .build_int_compare(IntPredicate::EQ, lhs, rhs, "eq"),
+            BinaryOp::Ne => self
+                .builder
+                .build_int_compare(IntPredicate::NE, lhs, rhs, "ne"),
+            BinaryOp::Lt => self.builder.build_int_compare(
+                predicate(IntPredicate::SLT, IntPredicate::ULT),
+                lhs,
+                rhs,
+                "lt",
+            ),
+            BinaryOp::Le => self.builder.build_int_compare(
+                predicate(IntPredicate::SLE, IntPredicate::ULE),
+                lhs,
+                rhs,
+                "le",
+            ),
+            BinaryOp::Gt => self.builder.build_int_compare(
+                predicate(IntPredicate::SGT, IntPredicate::UGT),
+                lhs,
+                rhs,
+                "gt",
+            ),
+            BinaryOp::Ge => self.builder.build_int_compare(
+                predicate(IntPredicate::SGE, IntPredicate::UGE),
+                lhs,
+                rhs,
+                "ge",
+            ),
+            BinaryOp::And => unreachable!("`&&` is compiled by short-circuiting."),
+        }
+    }
+
+    fn compile_fn_call(
+        &self,
+        caller: &FunctionValue<'ctx>,
+        caller_params: &ThinVec<TyFnParam>,
+        fun: &TyExpr,
+        args: &[&TyExpr],
+        locals: &HashMap<TyPath, PointerValue<'ctx>>,
+    ) -> Result<CallSiteValue<'ctx>, BackendError> {
+        let callee_name = match &fun.kind {
+            TyExprKind::Variable(path) => path.clone(),
+            _ => return Err(BackendError::internal(format!("Cannot call {fun:?}"))),
+        };
+
+        if let Some((param_index, callee)) = caller_params
+            .iter()
+            .enumerate()
+            .find(|(_, param)| *param.name.name == callee_name.to_string())
+        {
+            let function_type = self.to_llvm_type(callee.ty)?.into_function_type();
+
+            let function_ptr = nth_param(*caller, param_index as u32)?.into_pointer_value();
+
+            let args = args
+                .iter()
+                .map(|arg| {
+                    Ok(match &arg.kind {
+                        _ if self.is_aggregate(&arg.ty) => self
+                            .compile_aggregate_ptr(caller_params, caller, locals, arg)?
+                            .into(),
+                        _ if arg.diverges() => self
+                            .compile_expr(caller_params, caller, locals, arg)?
+                            .ok_or_else(|| BackendError::internal("An argument has no value."))?
+                            .into(),
+                        TyExprKind::Literal(literal) => match &literal.kind {
+                            TyLiteralKind::String(literal) => self
+                                .compile_string_literal(literal.clone())
+                                .as_basic_value_enum()
+                                .into(),
+                            TyLiteralKind::Integer(literal) => self
+                                .compile_integer_literal(literal.clone(), &arg.ty)?
+                                .as_basic_value_enum()
+                                .into(),
+                            TyLiteralKind::Float(value) => {
+                                self.context.f64_type().const_float(*value).into()
+                            }
+                        },
+                        TyExprKind::Variable(path) => {
+                            let param = caller_params
+                                .into_iter()
+                                .enumerate()
+                                .find(|(_, param)| {
+                                    Some(param.name.clone())
+                                        == path.segments.last().map(|segment| segment.ident.clone())
+                                })
+                                .and_then(|(param_index, _)| {
+                                    caller.get_nth_param(param_index as u32)
+                                });
+
+                            param
+                                .ok_or_else(|| {
+                                    BackendError::internal(format!("Variable `{path}` not found."))
+                                })?
+                                .into()
+                        }
+                        TyExprKind::Call { fun, args } => self
+                            .compile_fn_call(caller, caller_params, fun, args, locals)?
+                            .try_as_basic_value()
+                            .left()
+                            .ok_or_else(returns_no_value)?
+                            .into(),
+                        TyExprKind::Binary { .. }
+                        | TyExprKind::Cast(_)
+                        | TyExprKind::Array(_)
+                        | TyExprKind::Index { .. }
+                        | TyExprKind::Assign { .. }
+                        | TyExprKind::While { .. }
+                        | TyExprKind::TypeOf(_)
+                        | TyExprKind::Asm(_)
+                        | TyExprKind::Block(_)
+                        | TyExprKind::If { .. }
+                        | TyExprKind::For(_)
+                        | TyExprKind::Match { .. }
+                        | TyExprKind::Struct(_)
+                        | TyExprKind::Field { .. } => self
+                            .compile_expr(caller_params, caller, locals, arg)?
+                            .ok_or_else(|| BackendError::internal("An argument has no value."))?
+                            .into(),
+                    })
+                })
+                .collect::<Result<Vec<BasicMetadataValueEnum>, BackendError>>()?;
+
+            let call_site = self.builder.build_indirect_call(
+                function_type,
+                function_ptr,
+                &args,
+                &self.call_result_name(
+                    caller,
+                    &callee.name.name,
+                    function_type.get_return_type().is_none(),
+                ),
+            );
+
+            if let TyKind::Fn {
+                calling_convention, ..
+            } = &*callee.ty
+            {
+                call_site.set_call_convention(llvm_calling_convention(*calling_convention));
+            }
+
+            self.call_sites.borrow_mut().push(call_site);
+
+            return Ok(call_site);
+        }
+
+        if let Some(callee) = self
+            .local_fn(&callee_name)
+            .or_else(|| self.module.get_function(&callee_name.to_string()))
+        {
+            let args = args
+                .iter()
+                .enumerate()
+                .map(|(arg_index, arg)| {
+                    Ok(match &arg.kind {
+                        _ if self.is_aggregate(&arg.ty) => self
+                            .compile_aggregate_ptr(caller_params, caller, locals, arg)?
+                            .into(),
+                        _ if arg.diverges() => self
+                            .compile_expr(caller_params, caller, locals, arg)?
+                            .ok_or_else(|| BackendError::internal("An argument has no value."))?
+                            .into(),
+                        TyExprKind::Literal(literal) => match &literal.kind {
+                            TyLiteralKind::String(literal) => self
+                                .compile_string_literal(literal.clone())
+                                .as_basic_value_enum()
+                                .into(),
+                            TyLiteralKind::Integer(literal) => self
+                                .compile_integer_literal(literal.clone(), &arg.ty)?
+                                .as_basic_value_enum()
+                                .into(),
+                            TyLiteralKind::Float(value) => {
+                                self.context.f64_type().const_float(*value).into()
+                            }
+                        },
+                        TyExprKind::Variable(path) => {
+                            let param = caller_params
+                                .into_iter()
+                                .enumerate()
+                                .find(|(_, param)| {
+                                    Some(param.name.clone())
+                                        == path.segments.last().map(|segment| segment.ident.clone())
+                                })
+                                .and_then(|(param_index, _)| {
+                                    caller.get_nth_param(param_index as u32)
+                                });
+
+                            let callee_param =
+                                callee.get_nth_param(arg_index as u32).ok_or_else(|| {
+                                    BackendError::internal(format!(
+                                        "No param for `{callee_name}` found at index {arg_index}"
+                                    ))
+                                })?;
+
+                            let variable = param
+                                .or_else(|| {
+                                    locals.get(path).map(|local| {
+                                        self.builder.build_load(
+                                            callee_param.get_type(),
+                                            *local,
+                                            &self.value_name(caller, &format!("{path}.load")),
+                                        )
+                                    })
+                                })
+                                .or_else(|| {
+                                    self.module.get_function(&path.to_string()).map(|function| {
+                                        function
+                                            .as_global_value()
+                                            .as_pointer_value()
+                                            .as_basic_value_enum()
+                                    })
+                                })
+                                .or_else(|| self.compile_memory_ordering(path))
+                                .ok_or_else(|| {
+                                    BackendError::internal(format!("Variable `{path}` not found."))
+                                })?;
+
+                            variable.into()
+                        }
+                        TyExprKind::Call { fun, args } => self
+                            .compile_fn_call(caller, caller_params, fun, args, locals)?
+                            .try_as_basic_value()
+                            .left()
+                            .ok_or_else(returns_no_value)?
+                            .into(),
+                        TyExprKind::Binary { .. }
+                        | TyExprKind::Cast(_)
+                        | TyExprKind::Array(_)
+                        | TyExprKind::Index { .. }
+                        | TyExprKind::Assign { .. }
+                        | TyExprKind::While { .. }
+                        | TyExprKind::TypeOf(_)
+                        | TyExprKind::Asm(_)
+                        | TyExprKind::Block(_)
+                        | TyExprKind::If { .. }
+                        | TyExprKind::For(_)
+                        | TyExprKind::Match { .. }
+                        | TyExprKind::Struct(_)
+                        | TyExprKind::Field { .. } => self
+                            .compile_expr(caller_params, caller, locals, arg)?
+                            .ok_or_else(|| BackendError::internal("An argument has no value."))?
+                            .into(),
+                    })
+                })
+                .collect::<Result<Vec<BasicMetadataValueEnum>, BackendError>>()?;
+
+            let callee_ident = callee_name
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default();
+
+            let call_site = self.builder.build_call(
+                callee,
+                args.as_slice(),
+                &self.call_result_name(
+                    caller,
+                    &callee_ident,
+                    callee.get_type().get_return_type().is_none(),
+                ),
+            );
+
+            call_site.set_call_convention(callee.get_call_conventions());
+
+            self.call_sites.borrow_mut().push(call_site);
+
+            Ok(call_site)
+        } else {
+            Err(BackendError::internal(format!(
+                "Function `{callee_name}` not found."
+            )))
+        }
+    }
+}
+
+/// Returns the base filename of each compilation unit's artifacts.
+///
+/// The root unit is named `root_name`, and the others after their module path.
+/// Names that collide get a numeric suffix, in compilation unit order, so the
+/// result is deterministic.
+fn artifact_filenames(root_name: &str, all_artifacts: &[Artifacts]) -> Vec<String> {
+    let mut taken = HashSet::new();
+
+    all_artifacts
+        .iter()
+        .enumerate()
+        .map(|(index, artifacts)| {
+            let base = if index == 0 {
+                root_name.to_string()
+            } else {
+                artifacts.name.replace("::", "-")
+            };
+
+            let mut filename = base.clone();
+            let mut suffix = 2;
+
+            while !taken.insert(filename.clone()) {
+                filename = format!("{base}-{suffix}");
+                suffix += 1;
+            }
+
+            filename
+        })
+        .collect()
+}
+
+/// Writes an output file, recording its path in `outputs`.
+fn write_output(
+    path: PathBuf,
+    contents: &[u8],
+    outputs: &mut Vec<PathBuf>,
+) -> Result<(), BackendError> {
+    std::fs::write(&path, contents).map_err(|source| BackendError::Io {
+        path: path.clone(),
+        source,
+    })?;
+
+    outputs.push(path);
+
+    Ok(())
+}
+
+/// Creates the [`TargetMachine`] for the target in the given options.
+fn create_target_machine(options: &CompileOptions) -> Result<TargetMachine, BackendError> {
+    // Target machines are created on every codegen thread, but the targets
+    // must only be registered once.
+    static INITIALIZE_TARGETS: Once = Once::new();
+
+    INITIALIZE_TARGETS.call_once(|| Target::initialize_all(&InitializationConfig::default()));
+
+    let opt = match options.opt_level {
+        0 => OptimizationLevel::None,
+        1 => OptimizationLevel::Less,
+        2 => OptimizationLevel::Default,
+        _ => OptimizationLevel::Aggressive,
+    };
+    // Position-independent code works for both executables (as PIE) and libraries.
+    let reloc = RelocMode::PIC;
+    let model = CodeModel::Default;
+
+    let triple = TargetTriple::create(&options.target_triple);
+
+    let target = Target::from_triple(&triple).map_err(|err| {
+        BackendError::Target(format!(
+            "Unsupported target `{}`: {}",
+            options.target_triple,
+            err.to_string_lossy()
+        ))
+    })?;
+
+    let (cpu, features) = target_cpu_and_features(options);
+
+    target
+        .create_target_machine(&triple, &cpu, &features, opt, reloc, model)
+        .ok_or_else(|| {
+            BackendError::Target(format!(
+                "LLVM could not create a target machine for `{}` with CPU `{cpu}` and features `{features}`.",
+                options.target_triple
+            ))
+        })
+}
+
+/// Returns the CPU and features to generate code for.
+///
+/// Builds for the host use the host CPU and its features, whereas cross builds
+/// use a `generic` CPU, unless overridden in the options.
+fn target_cpu_and_features(options: &CompileOptions) -> (String, String) {
+    let host_triple = TargetMachine::get_default_triple();
+    let is_native = TargetMachine::normalize_triple(&TargetTriple::create(&options.target_triple))
+        == TargetMachine::normalize_triple(&host_triple);
+
+    let use_host_cpu = match options.target_cpu.as_deref() {
+        None => is_native,
+        Some(cpu) => cpu == "native",
+    };
+
+    let cpu = if use_host_cpu {
+        TargetMachine::get_host_cpu_name().to_string()
+    } else {
+        options
+            .target_cpu
+            .clone()
+            .unwrap_or_else(|| "generic".to_string())
+    };
+
+    let features = match &options.target_features {
+        Some(features) => features.clone(),
+        None if use_host_cpu => TargetMachine::get_host_cpu_features().to_string(),
+        None => String::new(),
+    };
+
+    (cpu, features)
+}
+
+/// Returns whether the given type is `String`.
+fn is_string(ty: &TypeId) -> bool {
+    matches!(
+        &**ty,
+        TyKind::UserDefined { module, name } if module == "std::prelude" && name == "String"
+    )
+}
+
+/// Returns whether the given callee is `std::process::assert`, which is
+/// compiled inline rather than called.
+fn is_assert(fun: &TyExpr) -> bool {
+    matches!(&fun.kind, TyExprKind::Variable(path) if path.to_string() == "std::process::assert")
+}
+
+/// Returns the parameter of the given function at `index`.
+fn nth_param(fn_value: FunctionValue, index: u32) -> Result<BasicValueEnum, BackendError> {
+    fn_value.get_nth_param(index).ok_or_else(|| {
+        BackendError::internal(format!(
+            "`{}` has no parameter {index}.",
+            fn_value.get_name().to_string_lossy()
+        ))
+    })
+}
+
+/// Returns the instruction that produced the given value.
+fn instruction(value: BasicValueEnum) -> Result<InstructionValue, BackendError> {
+    value
+        .as_instruction_value()
+        .ok_or_else(|| BackendError::internal("The value isn't produced by an instruction."))
+}
+
+/// Converts the error of `build_struct_gep`, which is only returned for an
+/// index past the last field of the struct.
+fn invalid_field_index(_: ()) -> BackendError {
+    BackendError::internal("Struct field index out of bounds.")
+}
+
+/// The error for a call whose value is used, but whose callee returns `void`.
+fn returns_no_value() -> BackendError {
+    BackendError::internal("The call returns no value.")
+}
+
+/// Returns a constant array of the given values, if they are all constants.
+fn const_array<'ctx>(
+    elem_type: BasicTypeEnum<'ctx>,
+    values: &[BasicValueEnum<'ctx>],
+) -> Option<ArrayValue<'ctx>> {
+    match elem_type {
+        BasicTypeEnum::IntType(int_type) => {
+            let values = values
+                .iter()
+                .map(|value| Some(value.into_int_value()).filter(|value| value.is_const()))
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(int_type.const_array(&values))
+        }
+        BasicTypeEnum::FloatType(float_type) => {
+            let values = values
+                .iter()
+                .map(|value| Some(value.into_float_value()).filter(|value| value.is_const()))
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(float_type.const_array(&values))
+        }
+        _ => None,
+    }
+}
+
+/// Collects the calls whose value is returned by the end of `block`, where
+/// `value` is the value returned, or `None` for a `ret void`.
+///
+/// This follows the unconditional branches into `block` from the blocks ending
+/// the branches of an `if` or `match`, whose values are joined by a `phi`.
+fn find_tail_calls<'ctx>(
+    fn_value: FunctionValue<'ctx>,
+    block: BasicBlock<'ctx>,
+    value: Option<BasicValueEnum<'ctx>>,
+    visited: &mut HashSet<BasicBlock<'ctx>>,
+    tail_calls: &mut Vec<InstructionValue<'ctx>>,
+) -> Result<(), BackendError> {
+    if !visited.insert(block) {
+        return Ok(());
+    }
+
+    let Some(terminator) = block.get_terminator() else {
+        return Ok(());
+    };
+
+    let is_returned = |instruction: InstructionValue<'ctx>| {
+        value.is_none_or(|value| value.as_value_ref() == instruction.as_value_ref())
+    };
+
+    match terminator.get_previous_instruction() {
+        Some(instruction)
+            if instruction.get_opcode() == InstructionOpcode::Call && is_returned(instruction) =>
+        {
+            tail_calls.push(instruction);
+        }
+        Some(instruction)
+            if instruction.get_opcode() == InstructionOpcode::Phi
+                && instruction.get_previous_instruction().is_none()
+                && value.is_some()
+                && is_returned(instruction) =>
+        {
+            let phi = PhiValue::try_from(instruction)
+                .map_err(|()| BackendError::internal("Expected a `phi` instruction."))?;
+
+            for index in 0..phi.count_incoming() {
+                let (incoming_value, incoming_block) =
+                    phi.get_incoming(index).ok_or_else(|| {
+                        BackendError::internal(format!("The `phi` has no incoming value {index}."))
+                    })?;
+
+                if branches_only_to(incoming_block, block) {
+                    find_tail_calls(
+                        fn_value,
+                        incoming_block,
+                        Some(incoming_value),
+                        visited,
+                        tail_calls,
+                    )?;
+                }
+            }
+        }
+        None if value.is_none() => {
+            for predecessor in fn_value.get_basic_blocks() {
+                if branches_only_to(predecessor, block) {
+                    find_tail_calls(fn_value, predecessor, None, visited, tail_calls)?;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Returns whether `block` ends with an unconditional branch to `target`.
+fn branches_only_to<'ctx>(block: BasicBlock<'ctx>, target: BasicBlock<'ctx>) -> bool {
+    block.get_terminator().is_some_and(|terminator| {
+        terminator.get_opcode() == InstructionOpcode::Br
+            && terminator.get_num_operands() == 1
+            && terminator
+                .get_operand(0)
+                .and_then(|operand| operand.right())
+                == Some(target)
+    })
+}
+
+/// Splits the modules that live in separate files out of the given items.
+///
+/// Returns the remaining items, while the split-out modules are added to
+/// `file_modules` along with their paths.
+fn split_file_modules<'a>(
+    prefix: Option<&str>,
+    items: ThinVec<TyItem<'a>>,
+    file_modules: &mut Vec<(String, ThinVec<TyItem<'a>>)>,
+) -> ThinVec<TyItem<'a>> {
+    let mut remaining_items = ThinVec::new();
+
+    for item in items {
+        match item.kind {
+            TyItemKind::Module(module, InlineModuleDecl::No) => {
+                let path = match prefix {
+                    Some(prefix) => format!("{prefix}::{}", item.name),
+                    None => item.name.to_string(),
+                };
+
+                let items = split_file_modules(Some(&path), module.items, file_modules);
+
+                file_modules.push((path, items));
+            }
+            _ => remaining_items.push(item),
+        }
+    }
+
+    remaining_items
+}
+
+/// Returns the parts of the given `struct` or `union` that affect how it is
+/// laid out, leaving out spans so that moving it around in its file doesn't
+/// change the result.
+fn type_signature(item: &TyItem) -> String {
+    let attrs = item
+        .attrs
+        .iter()
+        .map(|attr| {
+            let args = attr
+                .args
+                .iter()
+                .map(|arg| match &arg.value {
+                    Some(value) => format!("{} = {value:?}", arg.name),
+                    None => arg.name.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!("#[{}({args})] ", attr.name)
+        })
+        .collect::<String>();
+
+    let variants: Vec<(String, &TyVariantData)> = match &item.kind {
+        TyItemKind::Struct(struct_decl) => vec![(String::new(), &struct_decl.0)],
+        TyItemKind::Union(union_decl) => union_decl
+            .variants
+            .iter()
+            .map(|variant| (variant.name.to_string(), &variant.data))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let variants = variants
+        .into_iter()
+        .map(|(name, data)| {
+            let fields = data
+                .fields()
+                .iter()
+                .map(|field| {
+                    let name = field.name.as_ref().map(ToString::to_string);
+
+                    format!(
+                        "{}: {:?} {:?}",
+                        name.unwrap_or_default(),
+                        field.ty,
+                        field.bits
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!("{name} {{ {fields} }}")
+        })
+        .collect::<Vec<_>>()
+        .join(" | ");
+
+    format!("{attrs}{} {variants}", item.name)
+}
+
+/// Returns the bit offset and width of every field of the given `#[bitfield]`
+/// struct, packed in declaration order from the least significant bit.
+fn bitfield_layout(struct_decl: &TyStructDecl) -> Vec<(u32, u32)> {
+    let mut offset = 0;
+
+    struct_decl
+        .0
+        .fields()
+        .iter()
+        .map(|field| {
+            let width = field.bits.unwrap_or_default();
+            let field_offset = offset;
+
+            offset += width;
+
+            (field_offset, width)
+        })
+        .collect()
+}
+
+/// Collects all of the `struct`s and `union`s in the given items, including
+/// those in nested modules.
+fn collect_types<'a>(items: &'a ThinVec<TyItem<'a>>, types: &mut Vec<&'a TyItem<'a>>) {
+    for item in items {
+        match &item.kind {
+            TyItemKind::Struct(_) | TyItemKind::Union(_) => types.push(item),
+            TyItemKind::Module(module, _) => collect_types(&module.items, types),
+            TyItemKind::Use
+            | TyItemKind::Fn(_)
+            | TyItemKind::ExternFn(_)
+            | TyItemKind::Impl(_)
+            | TyItemKind::Const(_) => {}
+        }
+    }
+}
+
+/// Collects all of the functions in the given items, including those in nested modules.
+fn collect_fns<'a>(items: &'a ThinVec<TyItem<'a>>, fns: &mut Vec<(&'a TyFn<'a>, Visibility)>) {
+    for item in items {
+        match &item.kind {
+            TyItemKind::Fn(fun) | TyItemKind::ExternFn(fun) => fns.push((fun, item.vis)),
+            TyItemKind::Module(module, _) => collect_fns(&module.items, fns),
+            TyItemKind::Impl(impl_decl) => collect_fns(&impl_decl.items, fns),
+            TyItemKind::Use
+            | TyItemKind::Struct(_)
+            | TyItemKind::Union(_)
+            | TyItemKind::Const(_) => {}
+        }
+    }
+}
+
+/// Collects all of the `const`s in the given items, including those in nested modules.
+fn collect_consts<'a>(items: &'a ThinVec<TyItem<'a>>, consts: &mut Vec<&'a TyConst<'a>>) {
+    for item in items {
+        match &item.kind {
+            TyItemKind::Const(konst) => consts.push(konst),
+            TyItemKind::Module(module, _) => collect_consts(&module.items, consts),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the LLVM ID of the given calling convention.
+fn llvm_calling_convention(calling_convention: CallingConvention) -> u32 {
+    match calling_convention {
+        CallingConvention::C => 0,
+        CallingConvention::Fast => 8,
+        CallingConvention::Cold => 9,
+        CallingConvention::X86Stdcall => 64,
+        CallingConvention::X86Fastcall => 65,
+        CallingConvention::ArmAapcs => 67,
+    }
+}
+
+/// Collects the libraries named by the `#[link]` attributes in the given
+/// items, including those in nested modules.
+fn collect_link_libraries(items: &ThinVec<TyItem>, libraries: &mut Vec<String>) {
+    for item in items {
+        match &item.kind {
+            TyItemKind::ExternFn(_) => {
+                let library = item
+                    .attr("link")
+                    .and_then(|attr| attr.args.first())
+                    .and_then(|arg| arg.value.as_ref());
+
+                if let Some(library) = library {
+                    if !libraries.iter().any(|existing| existing == library) {
+                        libraries.push(library.to_string());
+                    }
+                }
+            }
+            TyItemKind::Module(module, _) => collect_link_libraries(&module.items, libraries),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the arguments that make `clang` link bitcode objects with LTO.
+///
+/// Apple's linker supports LTO out of the box, while elsewhere the system
+/// linker may not be able to read bitcode, so `lld` is used instead.
+fn lto_link_args(target_triple: &str) -> impl Iterator<Item = &'static str> {
+    let linker = (!target_triple.contains("apple")).then_some("-fuse-ld=lld");
+
+    std::iter::once("-flto").chain(linker)
+}
+
+/// Runs the given linker or archiver, returning its output as a
+/// [`BackendError::LinkFailed`] if it fails.
+///
+/// Nothing is printed when it succeeds, so that `crane run` only shows the
+/// output of the program. Whatever it prints is only logged then.
+fn run_linker(command: &mut Command) -> Result<(), BackendError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    tracing::info!("Linking with `{program}`");
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(err) => {
+            return Err(BackendError::LinkFailed {
+                program,
+                stderr: format!("Failed to run it: {err}"),
+            })
+        }
+    };
+
+    tracing::debug!("`{program}` exited with {}", output.status);
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    if !output.status.success() {
+        return Err(BackendError::LinkFailed { program, stderr });
+    }
+
+    if !stderr.is_empty() {
+        tracing::debug!("`{program}` printed:\n{stderr}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{AstArena, Ident, Module, Package, TyFn, DUMMY_SPAN};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::typer::{Typer, UintTy};
+
+    use super::*;
+
+    #[test]
+    fn test_verification_error() {
+        let name = Ident {
+            name: "broken".into(),
+            span: DUMMY_SPAN,
+        };
+
+        // A function that claims to return a `Uint64` but has an empty body,
+        // which lowers to a `ret void` inside of a function returning `i64`.
+        let broken_fn = TyFn {
+            params: ThinVec::new(),
+            return_ty: TypeId::intern(TyKind::Uint(UintTy::U64)),
+            body: ThinVec::new(),
+            calling_convention: CallingConvention::C,
+            path: TyPath {
+                segments: thin_vec![TyPathSegment {
+                    ident: name.clone()
+                }],
+                span: DUMMY_SPAN,
+            },
+        };
+
+        let package = TyPackage {
+            modules: thin_vec![TyModule {
+                items: thin_vec![TyItem {
+                    kind: TyItemKind::Fn(Box::new(broken_fn)),
+                    name,
+                    vis: Visibility::Private,
+                    attrs: ThinVec::new(),
+                }],
+            }],
+        };
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        match backend.compile_to_buffers(package, &CompileOptions::default()) {
+            Err(BackendError::Verification { function, message }) => {
+                assert_eq!(function.as_deref(), Some("broken"));
+                assert!(
+                    message.contains("Function return type does not match operand type"),
+                    "unexpected verification message: {message}"
+                );
+            }
+            result => panic!("expected a verification error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unknown_type_is_an_internal_error() {
+        let name = Ident {
+            name: "make_widget".into(),
+            span: DUMMY_SPAN,
+        };
+
+        // A function returning a type that was never declared, which the typer
+        // would have rejected.
+        let widget_fn = TyFn {
+            params: ThinVec::new(),
+            return_ty: TypeId::intern(TyKind::UserDefined {
+                module: "std::prelude".into(),
+                name: "Widget".into(),
+            }),
+            body: ThinVec::new(),
+            calling_convention: CallingConvention::C,
+            path: TyPath {
+                segments: thin_vec![TyPathSegment {
+                    ident: name.clone()
+                }],
+                span: DUMMY_SPAN,
+            },
+        };
+
+        let package = TyPackage {
+            modules: thin_vec![TyModule {
+                items: thin_vec![TyItem {
+                    kind: TyItemKind::Fn(Box::new(widget_fn)),
+                    name,
+                    vis: Visibility::Private,
+                    attrs: ThinVec::new(),
+                }],
+            }],
+        };
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        match backend.compile_to_buffers(package, &CompileOptions::default()) {
+            Err(BackendError::Internal(message)) => {
+                assert_eq!(message, "Unknown type `std::prelude::Widget`.");
+            }
+            result => panic!("expected an internal error, got {result:?}"),
+        }
+    }
+    fn type_check(source: &str) -> TyPackage<'static> {
+        // The arena is leaked, so that the package can outlive this function.
+        let arena = Box::leak(Box::new(AstArena::new()));
+        let parser = Parser::new(Lexer::new(source), arena);
+
+        let package = Package {
+            modules: thin_vec![Module {
+                items: parser.parse().unwrap(),
+            }],
+        };
+
+        Typer::new(arena).type_check_package(&package).unwrap()
+    }
+
+    #[test]
+    fn test_compile_to_buffers() {
+        let package = type_check(
+            r#"
+use std::io::println
+
+fn main() {
+    println("Hello, world!")
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("define i32 @main(i32 %argc, ptr %argv)"));
+        assert_eq!(artifacts.object[..4], [0xcf, 0xfa, 0xed, 0xfe]);
+        assert!(artifacts.bitcode.is_none());
+    }
+
+    #[test]
+    fn test_compile_to_buffers_with_bitcode() {
+        let package = type_check(
+            r#"
+use std::io::println
+
+fn main() {
+    println("Hello, world!")
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let options = CompileOptions {
+            emit_bitcode: true,
+            ..CompileOptions::default()
+        };
+
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
 
-        let buffer = target_machine
-            .write_to_memory_buffer(&self.module, FileType::Object)
-            .expect("Failed to write to buffer");
+        let bitcode = artifacts.bitcode.unwrap();
 
-        use std::io::Write;
+        // Bitcode for Darwin targets is wrapped in a 20 byte header.
+        assert_eq!(bitcode[..4], [0xde, 0xc0, 0x17, 0x0b]);
+        assert_eq!(bitcode[20..24], *b"BC\xc0\xde");
+    }
 
-        let mut outfile = std::fs::File::create("build/main.o").unwrap();
+    #[test]
+    fn test_lto_writes_bitcode_objects() {
+        let package = type_check(include_str!("../snapshot_inputs/hello_world.crane"));
 
-        outfile.write_all(buffer.as_slice()).unwrap();
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-        let bitcode = self.module.write_bitcode_to_memory();
+        let options = CompileOptions {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            lto: true,
+            ..CompileOptions::default()
+        };
 
-        outfile.write_all(bitcode.as_slice()).unwrap();
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
 
-        let exit_status = Command::new("clang")
-            .args(["-o", "build/main", "build/main.o"])
-            .status()
-            .expect("Failed to build with clang");
+        assert_eq!(artifacts.object[..4], *b"BC\xc0\xde");
+        assert!(artifacts
+            .ir
+            .contains("target triple = \"x86_64-unknown-linux-gnu\""));
+    }
 
-        println!("clang exited with {}", exit_status);
+    #[test]
+    fn test_lto_link_args() {
+        assert_eq!(
+            lto_link_args("aarch64-apple-darwin").collect::<Vec<_>>(),
+            ["-flto"]
+        );
+        assert_eq!(
+            lto_link_args("x86_64-unknown-linux-gnu").collect::<Vec<_>>(),
+            ["-flto", "-fuse-ld=lld"]
+        );
     }
 
-    fn verify_fn(&self, fn_name: &str, fn_value: &FunctionValue) -> Result<(), String> {
-        if fn_value.verify(true) {
-            self.fpm.run_on(fn_value);
+    #[test]
+    fn test_target_cpu_and_features_are_recorded() {
+        let package = type_check(include_str!("../snapshot_inputs/hello_world.crane"));
 
-            Ok(())
-        } else {
-            Err(format!("`{}` not verified", fn_name))
-        }
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let options = CompileOptions {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            target_cpu: Some("skylake".to_string()),
+            target_features: Some("+avx2".to_string()),
+            ..CompileOptions::default()
+        };
+
+        let ir = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0)
+            .ir;
+
+        assert!(ir.contains(r#"target triple = "x86_64-unknown-linux-gnu""#));
+        assert!(ir.contains(r#""target-cpu"="skylake""#));
+        assert!(ir.contains(r#""target-features"="+avx2""#));
     }
 
-    fn compile_module(&self, ty_module: &TyModule) {
-        for item in &ty_module.items {
-            self.compile_item(item);
-        }
+    #[test]
+    fn test_colliding_artifact_filenames_get_a_suffix() {
+        let artifacts = ["main", "util", "net::util", "net-util"].map(|name| Artifacts {
+            name: name.to_string(),
+            ir: String::new(),
+            object: Vec::new(),
+            bitcode: None,
+            cached: false,
+        });
+
+        assert_eq!(
+            artifact_filenames("util", &artifacts),
+            ["util", "util-2", "net-util", "net-util-2"]
+        );
     }
 
-    fn to_llvm_type(&self, ty: Ty) -> AnyTypeEnum<'ctx> {
-        match &*ty {
-            TyKind::Unit => self.context.void_type().as_any_type_enum(),
-            TyKind::Uint(UintTy::U64) => self.context.i64_type().as_any_type_enum(),
-            TyKind::Fn {
-                args: params,
-                return_ty,
-            } => {
-                let params = params
-                    .iter()
-                    .map(|param| self.to_llvm_type(param.clone()))
-                    .map(any_type_to_basic_metadata_type)
-                    .collect::<Vec<_>>();
+    #[test]
+    fn test_cross_builds_use_a_generic_cpu() {
+        let options = CompileOptions {
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            ..CompileOptions::default()
+        };
+
+        assert_eq!(
+            target_cpu_and_features(&options),
+            ("generic".to_string(), String::new())
+        );
+    }
 
-                let return_ty = self.to_llvm_type(return_ty.clone());
+    #[test]
+    fn test_unknown_target_is_an_error() {
+        let options = CompileOptions {
+            target_triple: "crane-unknown-nowhere".to_string(),
+            ..CompileOptions::default()
+        };
 
-                AnyTypeEnum::FunctionType(match return_ty {
-                    AnyTypeEnum::IntType(int_type) => int_type.fn_type(&params, false),
-                    _ => panic!(""),
-                })
+        match create_target_machine(&options) {
+            Err(BackendError::Target(message)) => {
+                assert!(message.starts_with("Unsupported target `crane-unknown-nowhere`: "));
             }
-            TyKind::UserDefined { module, name } => match (module.as_ref(), name.as_ref()) {
-                ("std::prelude", "String") => self
-                    .context
-                    .i8_type()
-                    .ptr_type(AddressSpace::default())
-                    .as_any_type_enum(),
-                (module, name) => {
-                    panic!("Unknown function parameter type: {}::{}", module, name)
-                }
-            },
+            result => panic!("expected a target error, got {:?}", result.map(|_| ())),
         }
     }
 
-    fn compile_item(&self, item: &TyItem) {
-        match &item.kind {
-            TyItemKind::Use => {}
-            TyItemKind::Fn(fun) => {
-                let params = fun
-                    .params
-                    .iter()
-                    .map(|param| {
-                        let param_type = self.to_llvm_type(param.ty.clone());
+    #[test]
+    fn test_threads_use_pthreads_on_posix() {
+        let package = type_check(include_str!("../snapshot_inputs/threads.crane"));
 
-                        any_type_to_basic_metadata_type(param_type)
-                    })
-                    .collect::<Vec<_>>();
-
-                let fn_type = match &*fun.return_ty {
-                    TyKind::Unit => self.context.void_type().fn_type(&params, false),
-                    TyKind::Uint(UintTy::U64) => self.context.i64_type().fn_type(&params, false),
-                    TyKind::UserDefined { module, name } => {
-                        match (module.as_str(), name.as_str()) {
-                            ("std::prelude", "String") => self
-                                .context
-                                .i8_type()
-                                .ptr_type(AddressSpace::default())
-                                .fn_type(&params, false),
-                            (module, name) => panic!("Unknown type {}::{}", module, name),
-                        }
-                    }
-                    TyKind::Fn {
-                        args: _,
-                        return_ty: _,
-                    } => todo!(),
-                };
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-                let is_main_fn = item.name.name == "main";
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
 
-                let fn_type = if is_main_fn {
-                    self.context.i32_type().fn_type(&params, false)
-                } else {
-                    fn_type
-                };
+        assert!(artifacts.ir.contains("declare i32 @pthread_create("));
+        assert!(artifacts.ir.contains("declare i32 @pthread_join("));
+        assert!(artifacts.ir.contains("declare i32 @nanosleep("));
+        assert!(artifacts
+            .ir
+            .contains(r#"call ptr @"std::thread::spawn"(ptr nonnull @worker)"#));
+    }
 
-                let fn_value = self
-                    .module
-                    .add_function(&fun.path.to_string(), fn_type, None);
+    #[test]
+    fn test_threads_use_win32_on_windows() {
+        let package = type_check(include_str!("../snapshot_inputs/threads.crane"));
 
-                for (index, param_value) in fn_value.get_param_iter().enumerate() {
-                    if let Some(param) = fun.params.get(index) {
-                        param_value.set_name(&param.name.to_string());
-                    }
-                }
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-                let entry = self.context.append_basic_block(fn_value, "entry");
+        let options = CompileOptions {
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            ..CompileOptions::default()
+        };
 
-                self.builder.position_at_end(entry);
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
 
-                let mut locals = HashMap::new();
-
-                let mut last_stmt: Option<BasicValueEnum> = None;
-
-                for stmt in &fun.body {
-                    match &stmt.kind {
-                        TyStmtKind::Local(local) => {
-                            let ty = local.ty.as_ref().unwrap_or_else(|| {
-                                panic!("No type for `let` binding `{}`.", local.name)
-                            });
-
-                            let ty = match &*ty.clone() {
-                                TyKind::Unit => todo!(),
-                                TyKind::Uint(UintTy::U64) => {
-                                    self.context.i64_type().as_basic_type_enum()
-                                }
-                                TyKind::UserDefined { module, name } => {
-                                    match (module.as_str(), name.as_str()) {
-                                        ("std::prelude", "String") => self
-                                            .context
-                                            .i8_type()
-                                            .ptr_type(AddressSpace::default())
-                                            .as_basic_type_enum(),
-                                        (module, name) => {
-                                            panic!("Unknown type {}::{}", module, name)
-                                        }
-                                    }
-                                }
-                                TyKind::Fn {
-                                    args: _,
-                                    return_ty: _,
-                                } => todo!(),
-                            };
+        assert!(artifacts.ir.contains("declare ptr @CreateThread("));
+        assert!(artifacts.ir.contains("declare i32 @WaitForSingleObject("));
+        assert!(artifacts.ir.contains("declare void @Sleep("));
+        assert!(!artifacts.ir.contains("pthread"));
+    }
 
-                            let local_ptr = self.builder.build_alloca(ty, &local.name.to_string());
+    #[test]
+    fn test_time_now_uses_clock_gettime_on_posix() {
+        let package = type_check(
+            r#"
+use std::int::int_to_string
+use std::io::println
+use std::time::now
 
-                            let value = match &local.kind {
-                                TyLocalKind::Decl => None,
-                                TyLocalKind::Init(init) => self.compile_expr(
-                                    &fun.params,
-                                    &fn_value,
-                                    &locals,
-                                    *init.clone(),
-                                ),
-                            }
-                            .unwrap_or_else(|| {
-                                panic!(
-                                    "`let` binding `{}` does not have an initializer.",
-                                    local.name
-                                )
-                            });
-
-                            self.builder.build_store(local_ptr, value);
-
-                            let local_path = TyPath {
-                                segments: thin_vec![TyPathSegment {
-                                    ident: local.name.clone()
-                                }],
-                                span: local.name.span,
-                            };
+fn main() {
+    println(int_to_string(now()))
+}
+            "#,
+        );
 
-                            locals.insert(local_path, local_ptr);
-                        }
-                        TyStmtKind::Expr(expr) => {
-                            last_stmt =
-                                self.compile_expr(&fun.params, &fn_value, &locals, *expr.clone());
-                        }
-                        TyStmtKind::Item(_item) => todo!(),
-                    }
-                }
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-                if is_main_fn {
-                    self.builder
-                        .build_return(Some(&self.context.i32_type().const_int(0, false)));
-                } else if let Some(last_stmt) = last_stmt {
-                    self.builder.build_return(Some(&last_stmt));
-                } else {
-                    self.builder.build_return(None);
-                }
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
 
-                self.verify_fn(&item.name.to_string(), &fn_value).unwrap();
-            }
-            TyItemKind::Struct(_) => {}
-            TyItemKind::Union(_) => {}
-            TyItemKind::Module(ty_module) => {
-                self.compile_module(&ty_module);
-            }
-        }
+        assert!(artifacts.ir.contains("declare i32 @clock_gettime("));
+        assert!(!artifacts.ir.contains("GetSystemTimeAsFileTime"));
     }
 
-    fn compile_expr(
-        &self,
-        fn_params: &ThinVec<TyFnParam>,
-        fn_value: &FunctionValue<'ctx>,
-        locals: &HashMap<TyPath, PointerValue<'ctx>>,
-        expr: TyExpr,
-    ) -> Option<BasicValueEnum<'ctx>> {
-        match expr.kind {
-            TyExprKind::Literal(literal) => match literal.kind {
-                TyLiteralKind::String(literal) => {
-                    Some(self.compile_string_literal(literal).as_basic_value_enum())
-                }
-                TyLiteralKind::Integer(literal) => {
-                    Some(self.compile_integer_literal(literal).as_basic_value_enum())
-                }
-            },
-            TyExprKind::Variable(_) => todo!(),
-            TyExprKind::Call { fun, args } => self
-                .compile_fn_call(fn_value, fn_params, fun.clone(), args, locals)
-                .unwrap_or_else(|_| panic!("Failed to compile function call: {:?}", fun))
-                .try_as_basic_value()
-                .either(Some, |_| None),
-        }
-    }
+    #[test]
+    fn test_time_now_uses_get_system_time_on_windows() {
+        let package = type_check(
+            r#"
+use std::int::int_to_string
+use std::io::println
+use std::time::now
 
-    fn compile_string_literal(&self, literal: SmolStr) -> GlobalValue<'ctx> {
-        // Unquote the string literal.
-        let value = {
-            let mut chars = literal.chars();
-            chars.next();
-            chars.next_back();
-            chars.as_str()
+fn main() {
+    println(int_to_string(now()))
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let options = CompileOptions {
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            ..CompileOptions::default()
         };
 
-        let value = value.as_bytes();
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
 
-        let i8_type = self.context.i8_type();
-        let i8_array_type = i8_type.array_type(value.len() as u32 + 1);
+        assert!(artifacts
+            .ir
+            .contains("declare void @GetSystemTimeAsFileTime("));
+        assert!(!artifacts.ir.contains("clock_gettime"));
+    }
 
-        let string = self.context.const_string(value, true);
+    #[test]
+    fn test_mutex_uses_pthreads_on_posix() {
+        let package = type_check(include_str!("../snapshot_inputs/mutex.crane"));
 
-        let global = self.module.add_global(i8_array_type, None, "string_lit");
-        global.set_linkage(Linkage::Internal);
-        global.set_constant(true);
-        global.set_initializer(&string);
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-        global
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("declare i32 @pthread_mutex_init("));
+        assert!(artifacts.ir.contains("declare i32 @pthread_mutex_lock("));
+        assert!(artifacts.ir.contains("declare i32 @pthread_mutex_unlock("));
+        assert!(!artifacts.ir.contains("CriticalSection"));
     }
 
-    fn compile_integer_literal(&self, literal: TyIntegerLiteral) -> IntValue<'ctx> {
-        let (int_value, int_type) = match literal {
-            TyIntegerLiteral::Unsigned(value, TyUint::Uint64) => {
-                (value as u64, self.context.i64_type())
-            }
+    #[test]
+    fn test_mutex_uses_critical_sections_on_windows() {
+        let package = type_check(include_str!("../snapshot_inputs/mutex.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let options = CompileOptions {
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            ..CompileOptions::default()
         };
 
-        int_type.const_int(int_value, false)
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("declare void @InitializeCriticalSection("));
+        assert!(artifacts.ir.contains("declare void @EnterCriticalSection("));
+        assert!(artifacts.ir.contains("declare void @LeaveCriticalSection("));
+        assert!(!artifacts.ir.contains("pthread"));
     }
 
-    fn compile_fn_call(
-        &self,
-        caller: &FunctionValue<'ctx>,
-        caller_params: &ThinVec<TyFnParam>,
-        fun: Box<TyExpr>,
-        args: ThinVec<Box<TyExpr>>,
-        locals: &HashMap<TyPath, PointerValue<'ctx>>,
-    ) -> Result<CallSiteValue<'ctx>, String> {
-        let callee_name = match fun.kind {
-            TyExprKind::Variable(path) => path,
-            _ => todo!(),
-        };
+    #[test]
+    fn test_struct_reprs_control_field_layout() {
+        let package = type_check(include_str!("../snapshot_inputs/struct_reprs.crane"));
 
-        if let Some((param_index, callee)) = caller_params
-            .iter()
-            .enumerate()
-            .find(|(_, param)| param.name.name == callee_name.to_string())
-        {
-            let function_type = self.to_llvm_type(callee.ty.clone()).into_function_type();
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
 
-            let function_ptr = caller
-                .get_nth_param(param_index as u32)
-                .unwrap()
-                .into_pointer_value();
+        backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap();
 
-            let args = args
-                .into_iter()
-                .map(|arg| match arg.kind {
-                    TyExprKind::Literal(literal) => match literal.kind {
-                        TyLiteralKind::String(literal) => self
-                            .compile_string_literal(literal)
-                            .as_basic_value_enum()
-                            .into(),
-                        TyLiteralKind::Integer(literal) => self
-                            .compile_integer_literal(literal)
-                            .as_basic_value_enum()
-                            .into(),
-                    },
-                    TyExprKind::Variable(path) => {
-                        let param = caller_params
-                            .into_iter()
-                            .enumerate()
-                            .find(|(_, param)| {
-                                Some(param.name.clone())
-                                    == path.segments.last().map(|segment| segment.ident.clone())
-                            })
-                            .and_then(|(param_index, _)| caller.get_nth_param(param_index as u32));
-
-                        param.unwrap().into()
-                    }
-                    TyExprKind::Call { fun, args } => self
-                        .compile_fn_call(caller, caller_params, fun, args, locals)
-                        .unwrap()
-                        .try_as_basic_value()
-                        .unwrap_left()
-                        .into(),
-                })
+        let data_layout = backend.module.get_data_layout();
+        let target_data = TargetData::create(data_layout.as_str().to_str().unwrap());
+
+        let layout_of = |name: &str| {
+            let struct_type = backend.module.get_struct_type(name).unwrap();
+
+            let offsets = (0..struct_type.count_fields())
+                .map(|index| target_data.offset_of_element(&struct_type, index).unwrap())
                 .collect::<Vec<_>>();
 
-            return Ok(self.builder.build_indirect_call(
-                function_type,
-                function_ptr,
-                &args,
-                &callee.name.name,
-            ));
+            (offsets, target_data.get_abi_size(&struct_type))
+        };
+
+        // `tag`, `length` and `flags`, in declaration order with padding.
+        assert_eq!(layout_of("CHeader"), (vec![0, 8, 16], 24));
+
+        // `tag`, `length` and `flags`, in declaration order without padding.
+        assert_eq!(layout_of("PackedHeader"), (vec![0, 1, 9], 11));
+
+        // `length`, `flags` and `tag`, sorted by alignment.
+        assert_eq!(layout_of("Header"), (vec![0, 8, 10], 16));
+    }
+
+    #[test]
+    fn test_describe_layouts() {
+        let package = type_check(include_str!("../snapshot_inputs/struct_reprs.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        assert_eq!(
+            backend
+                .describe_layouts(&package, &CompileOptions::default())
+                .unwrap(),
+            [
+                "CHeader: size=24 bytes, align=8 bytes, fields=[tag @ 0, length @ 8, flags @ 16]",
+                "PackedHeader: size=11 bytes, align=1 bytes, fields=[tag @ 0, length @ 1, flags @ 9]",
+                "Header: size=16 bytes, align=8 bytes, fields=[length @ 0, flags @ 8, tag @ 10]",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_union_reprs_control_tag_width() {
+        let package = type_check(include_str!("../snapshot_inputs/union_reprs.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap();
+
+        let shape = backend.module.get_struct_type("Shape").unwrap();
+        assert_eq!(
+            shape.print_to_string().to_string(),
+            "%Shape = type { i8, [1 x i64] }"
+        );
+
+        let token = backend.module.get_struct_type("Token").unwrap();
+        assert_eq!(
+            token.print_to_string().to_string(),
+            "%Token = type { i64, [1 x i64] }"
+        );
+    }
+
+    #[test]
+    fn test_todo_aborts_only_in_debug_builds() {
+        let source = "use std::process::todo\n\nfn parse() -> Uint64 {\n    todo()\n}\n\nfn main() {\n    parse()\n}\n";
+
+        for debug in [true, false] {
+            let context = Context::create();
+            let backend = NativeBackend::new(&context);
+
+            let options = CompileOptions {
+                debug,
+                ..CompileOptions::default()
+            };
+
+            backend
+                .compile_to_buffers(type_check(source), &options)
+                .unwrap();
+
+            let todo = backend
+                .module
+                .get_function("std::process::todo")
+                .unwrap()
+                .print_to_string()
+                .to_string();
+
+            assert_eq!(todo.contains("call void @abort()"), debug, "{todo}");
+            assert!(todo.contains("unreachable"), "{todo}");
         }
+    }
 
-        if let Some(callee) = self.module.get_function(&callee_name.to_string()) {
-            let args = args
-                .into_iter()
-                .enumerate()
-                .map(|(arg_index, arg)| match arg.kind {
-                    TyExprKind::Literal(literal) => match literal.kind {
-                        TyLiteralKind::String(literal) => self
-                            .compile_string_literal(literal)
-                            .as_basic_value_enum()
-                            .into(),
-                        TyLiteralKind::Integer(literal) => self
-                            .compile_integer_literal(literal)
-                            .as_basic_value_enum()
-                            .into(),
-                    },
-                    TyExprKind::Variable(path) => {
-                        let param = caller_params
-                            .into_iter()
-                            .enumerate()
-                            .find(|(_, param)| {
-                                Some(param.name.clone())
-                                    == path.segments.last().map(|segment| segment.ident.clone())
-                            })
-                            .and_then(|(param_index, _)| caller.get_nth_param(param_index as u32));
-
-                        let callee_param =
-                            callee.get_nth_param(arg_index as u32).unwrap_or_else(|| {
-                                panic!("No param for `{callee_name}` found at index {arg_index}");
-                            });
-
-                        let variable = param
-                            .or_else(|| {
-                                locals.get(&path).map(|local| {
-                                    self.builder
-                                        .build_load(callee_param.get_type(), *local, "load")
-                                })
-                            })
-                            .or_else(|| {
-                                self.module.get_function(&path.to_string()).map(|function| {
-                                    function
-                                        .as_global_value()
-                                        .as_pointer_value()
-                                        .as_basic_value_enum()
-                                })
-                            })
-                            .unwrap_or_else(|| panic!("Variable `{}` not found.", path));
+    #[test]
+    fn test_arrays_are_passed_by_pointer_and_bounds_checked() {
+        let package = type_check(include_str!("../snapshot_inputs/arrays.crane"));
 
-                        variable.into()
-                    }
-                    TyExprKind::Call { fun, args } => self
-                        .compile_fn_call(caller, caller_params, fun, args, locals)
-                        .unwrap()
-                        .try_as_basic_value()
-                        .unwrap_left()
-                        .into(),
-                })
-                .collect::<Vec<_>>();
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("define i8 @first(ptr %values)"));
+        assert!(artifacts
+            .ir
+            .contains("@array_lit = internal constant [3 x i8] c\"\\04\\05\\06\""));
+        assert!(artifacts.ir.contains("call void @abort()"));
+    }
+
+    #[test]
+    fn test_methods_take_structs_by_pointer() {
+        let package = type_check(include_str!("../snapshot_inputs/methods.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("define i64 @\"Point::sum\"(ptr %self)"));
+        assert!(artifacts
+            .ir
+            .contains("define %Point @\"Point::new\"(i64 %x, i64 %y)"));
+    }
+
+    #[test]
+    fn test_tuple_structs_are_represented_as_the_wrapped_type() {
+        let package = type_check(include_str!("../snapshot_inputs/tuple_structs.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("define double @total(double %a, double %b)"));
+        assert!(artifacts
+            .ir
+            .contains("define double @Meters(double %value)"));
+        assert!(!artifacts.ir.contains("%Meters = type"));
+    }
+
+    #[test]
+    fn test_bitfields_are_packed_into_a_single_integer() {
+        let package = type_check(include_str!("../snapshot_inputs/bitfields.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("%ControlRegister = type { i8 }"));
+        // `mode` is 3 bits wide, after the 1 bit of `enabled`.
+        assert!(artifacts.ir.contains("lshr i8 %register.load.unpack, 1"));
+        assert!(artifacts.ir.contains("and i8 %bits, 7"));
+        // `offset` is signed, so it is sign extended when it is read.
+        assert!(artifacts.ir.contains("ashr i8"));
+    }
+
+    #[test]
+    fn test_calls_in_tail_position_are_marked_tail() {
+        let package = type_check(include_str!("../snapshot_inputs/tail_calls.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("tail call i64 @countdown("));
+        assert!(!artifacts.ir.contains("tail call i64 @double("));
+        assert!(artifacts.ir.contains("noinline"));
+        assert!(artifacts.ir.contains("inlinehint"));
+    }
+
+    #[test]
+    fn test_tail_call_elim_turns_recursion_into_a_loop() {
+        let package = type_check(include_str!("../snapshot_inputs/tail_calls.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let options = CompileOptions {
+            tail_call_elim: true,
+            ..CompileOptions::default()
+        };
+
+        let artifacts = backend
+            .compile_to_buffers(package, &options)
+            .unwrap()
+            .remove(0);
+
+        let countdown_start = artifacts.ir.find("define i64 @countdown(").unwrap();
+        let countdown_len = artifacts.ir[countdown_start..].find("\n}\n").unwrap();
+        let countdown = &artifacts.ir[countdown_start..countdown_start + countdown_len];
+
+        assert!(!countdown.contains("call i64 @countdown("));
+    }
+
+    #[test]
+    fn test_integer_match_compiles_to_switch() {
+        let package = type_check(include_str!("../snapshot_inputs/match.crane"));
 
-            Ok(self.builder.build_call(callee, args.as_slice(), "tmp"))
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        let start = artifacts.ir.find("define i64 @classify(").unwrap();
+        let end = start + artifacts.ir[start..].find("\n}\n").unwrap() + 2;
+        let classify = &artifacts.ir[start..end];
+
+        assert_eq!(classify.matches("switch ").count(), 1);
+        assert!(!classify.contains("icmp"));
+
+        insta::assert_snapshot!(classify);
+    }
+
+    #[test]
+    fn test_values_are_named_after_the_source() {
+        let package = type_check(
+            r#"
+use std::int::int_to_string
+use std::io::println
+
+fn sum_evens(limit: Uint64) -> Uint64 {
+    let total = 0
+    let count = 0
+
+    while count < limit {
+        count = count + 1
+
+        if count % 2 == 0 {
+            total = total + count
         } else {
-            eprintln!("Function '{}' not found.", callee_name);
-            Err(format!("Function '{}' not found.", callee_name))
+            println(int_to_string(count))
         }
     }
+
+    total
 }
 
-fn any_type_to_basic_metadata_type<'ctx>(
-    any_type: AnyTypeEnum<'ctx>,
-) -> BasicMetadataTypeEnum<'ctx> {
-    match any_type {
-        AnyTypeEnum::ArrayType(array_type) => BasicMetadataTypeEnum::ArrayType(array_type),
-        AnyTypeEnum::FloatType(float_type) => BasicMetadataTypeEnum::FloatType(float_type),
-        AnyTypeEnum::IntType(int_type) => BasicMetadataTypeEnum::IntType(int_type),
-        AnyTypeEnum::PointerType(ptr_type) => BasicMetadataTypeEnum::PointerType(ptr_type),
-        AnyTypeEnum::StructType(struct_type) => BasicMetadataTypeEnum::StructType(struct_type),
-        AnyTypeEnum::VectorType(vector_type) => BasicMetadataTypeEnum::VectorType(vector_type),
-        AnyTypeEnum::VoidType(_) => todo!(),
-        AnyTypeEnum::FunctionType(function_type) => {
-            BasicMetadataTypeEnum::PointerType(function_type.ptr_type(AddressSpace::default()))
-        }
+fn main() {
+    let total = sum_evens(10)
+    println(int_to_string(total))
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        let start = artifacts.ir.find("define i64 @sum_evens(").unwrap();
+        let end = start + artifacts.ir[start..].find("\n}\n").unwrap() + 2;
+        let sum_evens = &artifacts.ir[start..end];
+
+        assert!(!artifacts.ir.contains("%tmp"));
+        assert!(artifacts
+            .ir
+            .contains("%int_to_string_ret = call ptr @\"std::int::int_to_string\"("));
+
+        insta::assert_snapshot!(sum_evens);
+    }
+
+    #[test]
+    fn test_typeof_compiles_to_type_name() {
+        let package = type_check(include_str!("../snapshot_inputs/typeof.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("c\"Uint8\\00\""));
+        assert!(artifacts.ir.contains("c\"[Float64; 2]\\00\""));
+    }
+
+    #[test]
+    fn test_atomics_emit_atomic_instructions() {
+        let package = type_check(include_str!("../snapshot_inputs/atomics.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("load atomic i64, ptr %0 acquire, align 8"));
+        assert!(artifacts
+            .ir
+            .contains("store atomic i64 %1, ptr %0 release, align 8"));
+        assert!(artifacts
+            .ir
+            .contains("atomicrmw add ptr %0, i64 %1 acq_rel"));
+        assert!(artifacts
+            .ir
+            .contains("cmpxchg ptr %0, i64 %1, i64 %2 seq_cst seq_cst"));
+    }
+
+    #[test]
+    fn test_volatile_accesses_are_marked_volatile() {
+        let package = type_check(include_str!("../snapshot_inputs/volatile.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("load volatile i64, ptr %ptr, align 8"));
+        assert!(artifacts
+            .ir
+            .contains("store volatile i64 %1, ptr %ptr, align 8"));
+    }
+
+    #[test]
+    fn test_naked_fns_have_only_their_assembly() {
+        let package = type_check(include_str!("../snapshot_inputs/naked_fns.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("; Function Attrs: naked noinline\ndefine void @trampoline()"));
+        assert!(artifacts
+            .ir
+            .contains("entry:\n  call void asm sideeffect \"nop\\0Aret\", \"\"()"));
+    }
+
+    #[test]
+    fn test_nested_fns_are_hoisted_to_the_module() {
+        let package = type_check(include_str!("../snapshot_inputs/nested_fns.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("define internal void @\"main::shout.0\"(ptr %msg)"));
+        assert_eq!(
+            artifacts
+                .ir
+                .matches("call void @\"main::shout.0\"(ptr")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_calling_conventions_are_set_on_fns_and_calls() {
+        let package = type_check(include_str!("../snapshot_inputs/calling_conventions.crane"));
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts
+            .ir
+            .contains("declare x86_fastcallcc i8 @read_port(i16)"));
+        assert!(artifacts.ir.contains("define fastcc i64 @add("));
+        assert!(artifacts.ir.contains("define coldcc void @report_failure("));
+        assert!(artifacts.ir.contains("call x86_fastcallcc i8 @read_port("));
+        assert!(artifacts.ir.contains("call fastcc i64 @add("));
+        assert!(artifacts.ir.contains("call coldcc void @report_failure("));
+    }
+
+    #[test]
+    fn test_lower_uint8_param() {
+        let package = type_check(
+            r#"
+use std::io::println
+
+fn first_byte(value: Uint8) -> Uint8 {
+    value
+}
+
+fn main() {
+    first_byte(1)
+    println("")
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("define i8 @first_byte(i8 %value)"));
+    }
+
+    #[test]
+    fn test_signedness_selects_division() {
+        let package = type_check(
+            r#"
+use std::io::println
+
+fn signed_div(a: Int32, b: Int32) -> Int32 {
+    a / b
+}
+
+fn unsigned_div(a: Uint32, b: Uint32) -> Uint32 {
+    a / b
+}
+
+fn main() {
+    signed_div(1, 2)
+    unsigned_div(1, 2)
+    println("")
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("sdiv i32 %a, %b"));
+        assert!(artifacts.ir.contains("udiv i32 %a, %b"));
+    }
+
+    #[test]
+    fn test_integer_consts_are_used_inline() {
+        let package = type_check(
+            r#"
+const SCALE: Uint64 = 21
+const OFFSET: Uint64 = SCALE * 2
+
+fn scale(value: Uint64) -> Uint64 {
+    value * SCALE + OFFSET
+}
+
+fn main() {
+    scale(1)
+}
+            "#,
+        );
+
+        let context = Context::create();
+        let backend = NativeBackend::new(&context);
+
+        let artifacts = backend
+            .compile_to_buffers(package, &CompileOptions::default())
+            .unwrap()
+            .remove(0);
+
+        assert!(artifacts.ir.contains("@SCALE = internal constant i64 21"));
+        assert!(artifacts.ir.contains("@OFFSET = internal constant i64 42"));
+        assert!(artifacts.ir.contains("mul i64 %value, 21"));
+        assert!(artifacts.ir.contains(", 42"));
+        assert!(!artifacts.ir.contains("load i64, ptr @"));
     }
 }
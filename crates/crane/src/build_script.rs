@@ -0,0 +1,187 @@
+//! Build scripts, which run before a package is compiled.
+//!
+//! A `build.crane` file at the root of a package is compiled and run before
+//! the package itself, like Cargo's `build.rs`. It configures the compilation
+//! by printing directives to stdout, one per line:
+//!
+//! - `crane:link-lib=<name>` links against a native library.
+//! - `crane:link-search=<dir>` adds a directory to search for native libraries
+//!   in.
+//! - `crane:env=<key>=<value>` sets an environment variable for the
+//!   compilation.
+//! - `crane:warning=<message>` prints a warning.
+//!
+//! Any other line is ignored. The script is run with `OUT_DIR` set to a
+//! directory it can generate files in.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use inkwell::targets::TargetMachine;
+use thiserror::Error;
+
+use crate::compiler::{CompileOptions, CompileParams, Compiler, Input, OutputKind};
+
+/// The name of the build script at the root of a package.
+pub const BUILD_SCRIPT: &str = "build.crane";
+
+/// The name of the directory in the output directory that the build script is
+/// compiled to.
+pub const BUILD_SCRIPT_DIR: &str = "build-script";
+
+/// The prefix of the lines printed by a build script that are directives.
+const DIRECTIVE_PREFIX: &str = "crane:";
+
+#[derive(Error, Debug)]
+pub enum BuildScriptError {
+    /// The build script failed to compile, and its errors have been reported.
+    #[error("The build script `{}` failed to compile.", .0.display())]
+    CompileFailed(PathBuf),
+
+    /// The build script could not be run.
+    #[error("Failed to run the build script `{}`: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The build script exited unsuccessfully, with the given output.
+    #[error("The build script failed ({status}):\n{stderr}")]
+    Failed { status: ExitStatus, stderr: String },
+
+    /// The build script printed a directive that isn't known.
+    #[error("The build script printed an unknown directive `{0}`.")]
+    UnknownDirective(String),
+}
+
+/// The configuration printed by a build script.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BuildScriptOutput {
+    /// The native libraries to link against.
+    pub link_libraries: Vec<String>,
+
+    /// The directories to search for native libraries in.
+    pub link_search_paths: Vec<PathBuf>,
+
+    /// The environment variables to set for the compilation.
+    pub env: Vec<(String, String)>,
+
+    /// The warnings to print.
+    pub warnings: Vec<String>,
+}
+
+impl BuildScriptOutput {
+    /// Parses the directives in the stdout of a build script.
+    pub fn parse(stdout: &str) -> Result<Self, BuildScriptError> {
+        let mut output = Self::default();
+
+        for line in stdout.lines() {
+            let Some(directive) = line.strip_prefix(DIRECTIVE_PREFIX) else {
+                continue;
+            };
+
+            let unknown_directive = || BuildScriptError::UnknownDirective(line.to_string());
+
+            let (key, value) = directive.split_once('=').ok_or_else(unknown_directive)?;
+
+            match key {
+                "link-lib" => output.link_libraries.push(value.to_string()),
+                "link-search" => output.link_search_paths.push(PathBuf::from(value)),
+                "env" => {
+                    let (name, value) = value.split_once('=').ok_or_else(unknown_directive)?;
+
+                    output.env.push((name.to_string(), value.to_string()));
+                }
+                "warning" => output.warnings.push(value.to_string()),
+                _ => return Err(unknown_directive()),
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Adds the configuration to the given [`CompileOptions`].
+    pub fn apply(self, options: &mut CompileOptions) {
+        options.link_libraries.extend(self.link_libraries);
+        options.link_search_paths.extend(self.link_search_paths);
+        options.env.extend(self.env);
+    }
+}
+
+/// Compiles and runs the given build script, returning the configuration it
+/// printed.
+///
+/// The errors from compiling the script and the warnings it prints are
+/// written to `stderr`.
+///
+/// The script is compiled for the host, since it runs as part of the build,
+/// into the [`BUILD_SCRIPT_DIR`] of the output directory.
+pub fn run_build_script(
+    stderr: &mut impl Write,
+    script: &Path,
+    options: &CompileOptions,
+) -> Result<BuildScriptOutput, BuildScriptError> {
+    let script_dir = options.output_dir.join(BUILD_SCRIPT_DIR);
+
+    let params = CompileParams {
+        input: Input::File(script.to_path_buf()),
+        options: CompileOptions {
+            target_triple: TargetMachine::get_default_triple()
+                .as_str()
+                .to_string_lossy()
+                .into_owned(),
+            output_kind: OutputKind::Executable,
+            debug: options.debug,
+            output_dir: script_dir.clone(),
+            jobs: options.jobs,
+            threads: options.threads,
+            incremental: options.incremental,
+            cache_dir: options.cache_dir.clone(),
+            color: options.color,
+            ..CompileOptions::default()
+        },
+    };
+
+    // The linked executable is the last output.
+    let executable = Compiler::new()
+        .compile(stderr, params)
+        .ok()
+        .and_then(|mut outputs| outputs.pop())
+        .ok_or_else(|| BuildScriptError::CompileFailed(script.to_path_buf()))?;
+
+    let stdout = execute(&executable, &script_dir.join("out"))?;
+    let output = BuildScriptOutput::parse(&stdout)?;
+
+    for warning in &output.warnings {
+        writeln!(stderr, "Warning: {warning}").unwrap();
+    }
+
+    Ok(output)
+}
+
+/// Runs the compiled build script, returning its stdout.
+fn execute(executable: &Path, out_dir: &Path) -> Result<String, BuildScriptError> {
+    let io_error = |source| BuildScriptError::Io {
+        path: executable.to_path_buf(),
+        source,
+    };
+
+    std::fs::create_dir_all(out_dir).map_err(io_error)?;
+
+    let output = Command::new(executable)
+        .env("OUT_DIR", out_dir)
+        .output()
+        .map_err(io_error)?;
+
+    if !output.status.success() {
+        return Err(BuildScriptError::Failed {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
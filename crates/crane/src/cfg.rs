@@ -0,0 +1,328 @@
+//! Conditional compilation with `#[cfg]`.
+//!
+//! Items and fields whose `#[cfg]` conditions don't hold are removed from the
+//! AST before type checking, so they never have to type check.
+
+use thin_vec::ThinVec;
+use thiserror::Error;
+
+use crate::ast::{Attribute, FieldDecl, Item, ItemKind, ModuleDecl, Span, VariantData};
+use crate::compiler::CompileOptions;
+
+/// The predicates that can be used in a `#[cfg]` attribute.
+pub const CFG_PREDICATES: [&str; 3] = ["target_os", "target_arch", "debug"];
+
+/// An error in a `#[cfg]` attribute.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct CfgError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub type CfgResult<T> = Result<T, CfgError>;
+
+/// The configuration that `#[cfg]` conditions are evaluated against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub target_os: String,
+    pub target_arch: String,
+    pub debug: bool,
+}
+
+impl Cfg {
+    /// Returns the configuration for compiling with the given options.
+    pub fn from_options(options: &CompileOptions) -> Self {
+        let mut components = options.target_triple.split('-');
+
+        let target_arch = components.next().unwrap_or_default().to_string();
+
+        let target_os = match components.nth(1).unwrap_or_default() {
+            "darwin" => "macos",
+            os => os,
+        }
+        .to_string();
+
+        Self {
+            target_os,
+            target_arch,
+            debug: options.debug,
+        }
+    }
+
+    /// Returns whether the conditions of the given `#[cfg]` attribute hold.
+    ///
+    /// An attribute with multiple conditions, like
+    /// `#[cfg(target_os = "linux", debug)]`, holds when all of them do.
+    pub fn eval(&self, attr: &Attribute) -> CfgResult<bool> {
+        if attr.args.is_empty() {
+            return Err(CfgError {
+                message: "`#[cfg]` expects a condition, like `#[cfg(target_os = \"linux\")]`."
+                    .to_string(),
+                span: attr.span,
+            });
+        }
+
+        let mut holds = true;
+
+        for arg in &attr.args {
+            holds &= match (arg.name.name.as_str(), &arg.value) {
+                ("target_os", Some(value)) => self.target_os == *value,
+                ("target_arch", Some(value)) => self.target_arch == *value,
+                ("debug", None) => self.debug,
+                ("target_os" | "target_arch", None) => {
+                    return Err(CfgError {
+                        message: format!(
+                            "`{0}` expects a value, like `#[cfg({0} = \"...\")]`.",
+                            arg.name
+                        ),
+                        span: arg.span,
+                    });
+                }
+                ("debug", Some(_)) => {
+                    return Err(CfgError {
+                        message: "`debug` does not take a value.".to_string(),
+                        span: arg.span,
+                    });
+                }
+                _ => {
+                    return Err(CfgError {
+                        message: format!(
+                            "Unknown `#[cfg]` condition `{}`. Expected one of: {}.",
+                            arg.name,
+                            CFG_PREDICATES.join(", ")
+                        ),
+                        span: arg.span,
+                    });
+                }
+            };
+        }
+
+        Ok(holds)
+    }
+
+    /// Returns whether all of the `#[cfg]` attributes in `attrs` hold.
+    fn is_enabled(&self, attrs: &[Attribute]) -> CfgResult<bool> {
+        for attr in attrs.iter().filter(|attr| attr.name.name == "cfg") {
+            if !self.eval(attr)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Removes the items and fields that are disabled by `#[cfg]`.
+    ///
+    /// This descends into inline modules, `impl` blocks, and the fields of
+    /// `struct`s and `union`s. Modules that haven't been loaded yet must be
+    /// stripped once they are.
+    pub fn strip_items(&self, items: &mut ThinVec<Item>) -> CfgResult<()> {
+        let mut result = Ok(());
+
+        items.retain(|item| match self.is_enabled(&item.attrs) {
+            Ok(enabled) => enabled,
+            Err(err) => {
+                result = Err(err);
+                true
+            }
+        });
+
+        result?;
+
+        for item in items.iter_mut() {
+            match &mut item.kind {
+                ItemKind::Module(module_decl) => {
+                    if let ModuleDecl::Loaded(module, _) = module_decl.as_mut() {
+                        self.strip_items(&mut module.items)?;
+                    }
+                }
+                ItemKind::Impl(impl_decl) => self.strip_items(&mut impl_decl.items)?,
+                ItemKind::Struct(struct_decl) => self.strip_fields(&mut struct_decl.0)?,
+                ItemKind::Union(union_decl) => {
+                    for variant in union_decl.variants.iter_mut() {
+                        self.strip_fields(&mut variant.data)?;
+                    }
+                }
+                ItemKind::Use(_) | ItemKind::Fn(_) | ItemKind::ExternFn(_) | ItemKind::Const(_) => {
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn strip_fields(&self, variant_data: &mut VariantData) -> CfgResult<()> {
+        let fields: &mut ThinVec<FieldDecl> = match variant_data {
+            VariantData::Struct(fields) | VariantData::Tuple(fields) => fields,
+            VariantData::Unit => return Ok(()),
+        };
+
+        let mut result = Ok(());
+
+        fields.retain(|field| match self.is_enabled(&field.attrs) {
+            Ok(enabled) => enabled,
+            Err(err) => {
+                result = Err(err);
+                true
+            }
+        });
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::AstArena;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    use super::*;
+
+    fn strip<'a>(cfg: &Cfg, source: &str, arena: &'a AstArena<'a>) -> CfgResult<ThinVec<Item<'a>>> {
+        let mut items = Parser::new(Lexer::new(source), arena).parse().unwrap();
+
+        cfg.strip_items(&mut items)?;
+
+        Ok(items)
+    }
+
+    fn linux() -> Cfg {
+        Cfg::from_options(&CompileOptions {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            ..CompileOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_cfg_from_target_triple() {
+        assert_eq!(
+            linux(),
+            Cfg {
+                target_os: "linux".to_string(),
+                target_arch: "x86_64".to_string(),
+                debug: false,
+            }
+        );
+
+        let macos = Cfg::from_options(&CompileOptions {
+            target_triple: "aarch64-apple-darwin".to_string(),
+            debug: true,
+            ..CompileOptions::default()
+        });
+
+        assert_eq!(macos.target_os, "macos");
+        assert_eq!(macos.target_arch, "aarch64");
+        assert!(macos.debug);
+    }
+
+    #[test]
+    fn test_strip_disabled_items_and_fields() {
+        let source = r#"
+#[cfg(target_os = "linux")]
+fn platform() -> String {
+    "linux"
+}
+
+#[cfg(target_os = "macos")]
+fn platform() -> String {
+    "macos"
+}
+
+struct Config {
+    name: String,
+    #[cfg(debug)]
+    verbose: Bool,
+    #[cfg(target_os = "linux", target_arch = "x86_64")]
+    epoll: Bool,
+}
+        "#;
+
+        let arena = AstArena::new();
+        let items = strip(&linux(), source, &arena).unwrap();
+
+        let [platform, config] = items.as_slice() else {
+            panic!("expected two items, got {}", items.len());
+        };
+
+        assert_eq!(platform.name.name, "platform");
+        assert_eq!(
+            platform.attr("cfg").unwrap().args[0].value.as_deref(),
+            Some("linux")
+        );
+
+        let ItemKind::Struct(config) = &config.kind else {
+            panic!("expected a struct");
+        };
+
+        let field_names = config
+            .0
+            .fields()
+            .iter()
+            .map(|field| field.name.as_ref().unwrap().name.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(field_names, ["name", "epoll"]);
+    }
+
+    #[test]
+    fn test_strip_inside_modules_and_impls() {
+        let source = r#"
+mod sys {
+    #[cfg(target_os = "windows")]
+    use std::windows
+
+    #[cfg(target_os = "linux")]
+    pub fn page_size() -> Uint64 {
+        4096
+    }
+}
+
+struct Point {
+    x: Uint64,
+}
+
+impl Point {
+    #[cfg(debug)]
+    fn dump(self: Point) {}
+}
+        "#;
+
+        let arena = AstArena::new();
+        let items = strip(&linux(), source, &arena).unwrap();
+
+        let ItemKind::Module(module_decl) = &items[0].kind else {
+            panic!("expected a module");
+        };
+
+        let ModuleDecl::Loaded(module, _) = module_decl.as_ref() else {
+            panic!("expected an inline module");
+        };
+
+        assert_eq!(module.items.len(), 1);
+        assert_eq!(module.items[0].name.name, "page_size");
+
+        let ItemKind::Impl(impl_decl) = &items[2].kind else {
+            panic!("expected an impl block");
+        };
+
+        assert!(impl_decl.items.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_cfg_condition() {
+        let source = r#"
+#[cfg(feature = "fast")]
+fn fast() {}
+        "#;
+
+        let arena = AstArena::new();
+        let err = strip(&linux(), source, &arena).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "Unknown `#[cfg]` condition `feature`. Expected one of: target_os, target_arch, debug."
+        );
+    }
+}
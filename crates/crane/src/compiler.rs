@@ -1,17 +1,36 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use ariadne::{Color, Label, Report, ReportKind, Source};
-use itertools::Itertools;
-use thin_vec::thin_vec;
+use smol_str::SmolStr;
+use thin_vec::{thin_vec, ThinVec};
+use thiserror::Error;
 
-use crate::ast::{Module, Package, SourceSpan};
+use crate::ast::{
+    AstArena, InlineModuleDecl, Item, ItemKind, Module, ModuleDecl, Package, SourceFile, SourceMap,
+    Span, TyPackage,
+};
 use crate::backend::native::NativeBackend;
+use crate::backend::BackendError;
+use crate::cfg::Cfg;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::CraneError;
+use crate::error_codes::ErrorCode;
+use crate::ice;
 use crate::lexer::Lexer;
-use crate::parser::{ParseErrorKind, Parser};
-use crate::typer::{TypeErrorKind, Typer};
+use crate::lint::pass::{check_items, AST_LINTS};
+use crate::lint::{LintLevel, LintLevels};
+use crate::macro_expander::MacroExpander;
+use crate::parser::{ParseError, ParseResult, Parser};
+use crate::test_harness::add_test_harness;
+use crate::timings;
+use crate::typer::{Typer, Warning};
+
+/// The name that source code read from stdin is shown under in diagnostics.
+pub const STDIN_FILENAME: &str = "<stdin>";
 
 /// The input to the compiler.
+#[derive(Debug, Clone)]
 pub enum Input {
     /// Load the source code from a file.
     File(PathBuf),
@@ -24,249 +43,977 @@ pub enum Input {
         /// The source code.
         input: String,
     },
+
+    /// Load the source code from a string, as the contents of the file at the
+    /// given path, like an unsaved file open in an editor.
+    ///
+    /// Unlike [`Input::String`], the modules it declares are loaded from the
+    /// directory of the file.
+    Buffer {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The source code.
+        input: String,
+    },
+}
+
+/// The stage at which the [`Compiler`] failed, once it has reported why.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Failure {
+    /// The source failed to lex or parse, or its `#[cfg]` attributes, macros,
+    /// or module declarations were invalid.
+    Syntax,
+
+    /// The source failed to type check.
+    Type,
+
+    /// Generating code for the source failed.
+    Codegen,
+
+    /// Linking the generated code failed.
+    Link,
+
+    /// Anything else, like failing to read the input or to write an artifact.
+    Other,
+}
+
+/// An error loading a module declared without a body, like `mod foo`, from
+/// its file.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct ModuleError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+}
+
+/// The kind of artifact the compiler produces.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OutputKind {
+    /// An executable with a `main` function.
+    Executable,
+
+    /// A static library (`.a`).
+    StaticLib,
+
+    /// A shared library (`.so` or `.dylib`).
+    SharedLib,
+}
+
+impl OutputKind {
+    /// Returns whether this output kind is a library.
+    pub fn is_library(&self) -> bool {
+        match self {
+            Self::Executable => false,
+            Self::StaticLib | Self::SharedLib => true,
+        }
+    }
+}
+
+/// The options that control code generation.
+#[derive(Debug, Clone)]
+pub struct CompileOptions {
+    /// The target triple to generate code for.
+    pub target_triple: String,
+
+    /// The kind of artifact to produce.
+    pub output_kind: OutputKind,
+
+    /// Whether to emit the textual LLVM IR alongside the object file.
+    pub emit_llvm_ir: bool,
+
+    /// Whether to emit LLVM bitcode alongside the object file.
+    pub emit_bitcode: bool,
+
+    /// Whether to link the object files into the [`OutputKind`].
+    ///
+    /// Otherwise, only the artifacts of each compilation unit are written.
+    pub link: bool,
+
+    /// How much to optimize the generated code, from `0` for no optimization
+    /// to `3` for aggressive optimization.
+    pub opt_level: u8,
+
+    /// Whether to print the memory layout of every `struct` and `union`.
+    pub print_layout: bool,
+
+    /// Whether to run the `tailcallelim` pass, which turns self-recursive
+    /// tail calls into loops.
+    pub tail_call_elim: bool,
+
+    /// Whether to perform link-time optimization.
+    ///
+    /// The object files contain LLVM bitcode instead of machine code, which
+    /// the linker optimizes as a whole, allowing functions to be inlined and
+    /// removed across compilation units.
+    pub lto: bool,
+
+    /// The CPU to generate code for, or `native` for the host CPU.
+    ///
+    /// Defaults to the host CPU when compiling for the host, and to
+    /// `generic` when cross compiling.
+    pub target_cpu: Option<String>,
+
+    /// The comma-separated target features to enable or disable, like
+    /// `+avx2,-sse4.1`.
+    ///
+    /// Defaults to the features of the host CPU when its CPU is used.
+    pub target_features: Option<String>,
+
+    /// Whether this is a debug build, which enables `#[cfg(debug)]` items.
+    pub debug: bool,
+
+    /// The directory to write the artifacts to, which is created if needed.
+    pub output_dir: PathBuf,
+
+    /// The name of the linked artifact.
+    ///
+    /// Defaults to the stem of the input file.
+    pub output_name: Option<String>,
+
+    /// The number of compilation units to generate code for in parallel.
+    pub jobs: usize,
+
+    /// The number of threads to parse files and generate code on, which
+    /// limits `jobs`. `1` does everything on the current thread, in order.
+    ///
+    /// Defaults to the number of logical CPUs.
+    pub threads: usize,
+
+    /// Whether to reuse the compilation units that haven't changed since the
+    /// previous build, from the cache in the output directory.
+    pub incremental: bool,
+
+    /// The directory to cache the compilation units in, which may be shared
+    /// between projects.
+    ///
+    /// Defaults to the `cache` directory in the output directory.
+    pub cache_dir: Option<PathBuf>,
+
+    /// The native libraries to link against, in addition to those named by
+    /// `#[link]` attributes.
+    pub link_libraries: Vec<String>,
+
+    /// The directories to search for native libraries in.
+    pub link_search_paths: Vec<PathBuf>,
+
+    /// The environment variables set for the compilation by the build script.
+    pub env: Vec<(String, String)>,
+
+    /// Whether to build the test harness, whose `main` function runs the
+    /// `#[test]` function named by its first argument, instead of the `main`
+    /// function of the input.
+    pub test: bool,
+
+    /// The levels to report the lints at, as set by the `-A`, `-W` and `-D`
+    /// flags.
+    pub lint_levels: LintLevels,
+
+    /// Whether to color the diagnostics with ANSI escape codes.
+    pub color: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            target_triple: "aarch64-apple-darwin".to_string(),
+            output_kind: OutputKind::Executable,
+            emit_llvm_ir: true,
+            emit_bitcode: false,
+            link: true,
+            opt_level: 0,
+            print_layout: false,
+            tail_call_elim: false,
+            lto: false,
+            target_cpu: None,
+            target_features: None,
+            debug: false,
+            output_dir: PathBuf::from("build"),
+            output_name: None,
+            jobs: 1,
+            threads: default_threads(),
+            incremental: true,
+            cache_dir: None,
+            link_libraries: Vec::new(),
+            link_search_paths: Vec::new(),
+            env: Vec::new(),
+            test: false,
+            lint_levels: LintLevels::default(),
+            color: false,
+        }
+    }
+}
+
+/// A diagnostic reported by the [`Compiler`], along with the file it is in.
+#[derive(Debug, Clone)]
+pub struct ReportedDiagnostic {
+    /// The path of the file, as shown in the diagnostic.
+    pub path: String,
+
+    pub diagnostic: Diagnostic,
 }
 
 pub struct CompileParams {
     pub input: Input,
+    pub options: CompileOptions,
 }
 
 /// The interface to the Crane compiler.
-pub struct Compiler {}
+pub struct Compiler {
+    /// The files loaded for the package being compiled.
+    source_map: SourceMap,
+
+    /// The diagnostics reported so far.
+    diagnostics: Vec<ReportedDiagnostic>,
+
+    /// The canonical paths of the files of the modules being loaded, from the
+    /// root module down, which a module declaration that loads one of them
+    /// again would never finish loading.
+    module_files: Vec<PathBuf>,
+
+    /// Whether to color the diagnostics, as set by the [`CompileOptions`] of
+    /// the input being compiled.
+    color: bool,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Compiler {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            source_map: SourceMap::default(),
+            diagnostics: Vec::new(),
+            module_files: Vec::new(),
+            color: false,
+        }
+    }
+
+    /// Returns the diagnostics reported since the compiler was created, in the
+    /// order they were written.
+    pub fn diagnostics(&self) -> &[ReportedDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the files loaded for the package parsed most recently.
+    ///
+    /// Type checking hands the files over to the typer, so this is only
+    /// populated after [`Compiler::parse_input`] or [`Compiler::parse_file`].
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Writes the given diagnostic in the source at `filepath`, and records it
+    /// for [`Compiler::diagnostics`].
+    fn report(
+        &mut self,
+        stderr: &mut impl Write,
+        filepath: &str,
+        source: &str,
+        diagnostic: Diagnostic,
+    ) {
+        diagnostic.write(stderr, filepath, source, self.color);
+
+        self.diagnostics.push(ReportedDiagnostic {
+            path: filepath.to_string(),
+            diagnostic,
+        });
     }
 
+    /// Compiles the input, returning the paths of the files that were written,
+    /// ending with the linked artifact.
+    ///
+    /// Unless [`CompileOptions::output_name`] is set, the artifacts are named
+    /// after the stem of the input file, so `examples/hello.crane` produces
+    /// `hello`, `hello.o`, and so on.
     pub fn compile<'io>(
         &mut self,
         stderr: &'io mut impl Write,
         params: CompileParams,
-    ) -> Result<(), ()> {
-        let (filepath, source) = match params.input {
-            Input::File(path) => (
-                path.display().to_string(),
-                std::fs::read_to_string(&path).unwrap(),
-            ),
-            Input::String { filename, input } => (filename, input),
+    ) -> Result<Vec<PathBuf>, CraneError> {
+        let arena = AstArena::new();
+
+        let (filepath, source, typed_package) =
+            self.type_check(stderr, params.input, &params.options, &arena)?;
+
+        let output_name = params.options.output_name.clone().unwrap_or_else(|| {
+            Path::new(&filepath)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "main".to_string())
+        });
+
+        let context = inkwell::context::Context::create();
+
+        let backend = NativeBackend::new(&context);
+
+        let layouts = if params.options.print_layout {
+            NativeBackend::new(&context)
+                .describe_layouts(&typed_package, &params.options)
+                .map(|layouts| print!("{layouts}"))
+        } else {
+            Ok(())
         };
 
-        let lexer = Lexer::new(&source);
-        let parser = Parser::new(lexer);
+        let result =
+            layouts.and_then(|()| backend.compile(typed_package, &output_name, &params.options));
 
-        match parser.parse() {
-            Ok(items) => {
-                let mut typer = Typer::new();
+        result.map_err(|backend_error| {
+            let code = match backend_error {
+                BackendError::LinkFailed { .. } => ErrorCode::LinkFailed,
+                _ => ErrorCode::CodegenFailed,
+            };
 
-                let module = Module { items };
+            Diagnostic::error(code, "An error occurred during code generation.")
+                .write(stderr, &filepath, &source, self.color);
 
-                let package = Package {
-                    modules: thin_vec![module],
-                };
+            // Notes are only shown alongside a label, so the error is written
+            // after the report.
+            writeln!(stderr, "{backend_error}").unwrap();
 
-                match typer.type_check_package(package) {
-                    Ok(typed_package) => {
-                        std::fs::create_dir_all("build").unwrap();
+            backend_error.into()
+        })
+    }
 
-                        let context = inkwell::context::Context::create();
+    /// Checks the input for errors, without generating any code.
+    ///
+    /// The diagnostics are the same as those reported by [`Compiler::compile`]
+    /// before code generation.
+    pub fn check(
+        &mut self,
+        stderr: &mut impl Write,
+        params: CompileParams,
+    ) -> Result<(), CraneError> {
+        let arena = AstArena::new();
 
-                        let backend = NativeBackend::new(&context);
+        self.type_check(stderr, params.input, &params.options, &arena)
+            .map(|_| ())
+    }
 
-                        backend.compile(typed_package);
+    /// Parses the input into the given arena, returning its items once the
+    /// modules it declares are loaded and its macros are expanded.
+    pub fn parse_input<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        params: CompileParams,
+        arena: &'a AstArena<'a>,
+    ) -> Result<ThinVec<Item<'a>>, CraneError> {
+        let (filepath, source, dir) = read_input(stderr, params.input)?;
+
+        self.parse(
+            stderr,
+            &params.options,
+            &filepath,
+            &source,
+            dir.as_deref(),
+            arena,
+        )
+    }
 
-                        println!("Compiled!");
+    /// Parses and type checks the input into the given arena, returning the
+    /// typed package.
+    pub fn type_check_input<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        params: CompileParams,
+        arena: &'a AstArena<'a>,
+    ) -> Result<TyPackage<'a>, CraneError> {
+        self.type_check(stderr, params.input, &params.options, arena)
+            .map(|(_, _, typed_package)| typed_package)
+    }
 
-                        Ok(())
-                    }
-                    Err(type_error) => {
-                        let span = type_error.span;
-
-                        let error_report = match type_error.kind {
-                            TypeErrorKind::InvalidFunctionName { reason, suggestion } => {
-                                Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(reason)
-                                            .with_color(Color::Red),
-                                    )
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(format!(
-                                                "Try writing it as `{suggestion}` instead."
-                                            ))
-                                            .with_color(Color::Cyan),
-                                    )
-                                    .finish()
-                            }
-                            TypeErrorKind::InvalidTypeName { reason, suggestion } => {
-                                Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(reason)
-                                            .with_color(Color::Red),
-                                    )
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(format!(
-                                                "Try writing it as `{suggestion}` instead."
-                                            ))
-                                            .with_color(Color::Cyan),
-                                    )
-                                    .finish()
-                            }
-                            TypeErrorKind::UnknownModule { path, options } => {
-                                let report = Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(format!(
-                                                "Module `{path}` does not exist.",
-                                            ))
-                                            .with_color(Color::Red),
-                                    );
-
-                                let suggestion = options
-                                    .iter()
-                                    .sorted_by_key(|option| option.to_string())
-                                    .min_by_key(|option| {
-                                        strsim::levenshtein(&option.to_string(), &path.to_string())
-                                    });
-
-                                let report = if let Some(suggestion) = suggestion {
-                                    report.with_label(
-                                        Label::new(SourceSpan::from((&filepath, suggestion.span)))
-                                            .with_message(format!(
-                                                "There is a module with a similar name: `{}`.",
-                                                suggestion.clone()
-                                            ))
-                                            .with_color(Color::Cyan),
-                                    )
-                                } else {
-                                    report
-                                };
-
-                                report.finish()
-                            }
-                            TypeErrorKind::UnknownFunction { path, options } => {
-                                let report = Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(format!(
-                                                "Function `{path}` does not exist.",
-                                            ))
-                                            .with_color(Color::Red),
-                                    );
-
-                                let suggestion = options
-                                    .iter()
-                                    .sorted_by_key(|option| option.to_string())
-                                    .min_by_key(|option| {
-                                        strsim::levenshtein(&option.to_string(), &path.to_string())
-                                    });
-
-                                let report = if let Some(suggestion) = suggestion {
-                                    report.with_label(
-                                        Label::new(SourceSpan::from((&filepath, suggestion.span)))
-                                            .with_message(format!(
-                                                "There is a function with a similar name: `{}`.",
-                                                suggestion.clone()
-                                            ))
-                                            .with_color(Color::Cyan),
-                                    )
-                                } else {
-                                    report
-                                };
-
-                                report.finish()
-                            }
-                            TypeErrorKind::UnknownType { path, options } => {
-                                let report = Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(format!("Type `{path}` does not exist.",))
-                                            .with_color(Color::Red),
-                                    );
-
-                                let suggestion = options
-                                    .iter()
-                                    .sorted_by_key(|option| option.to_string())
-                                    .min_by_key(|option| {
-                                        strsim::levenshtein(&option.to_string(), &path.to_string())
-                                    });
-
-                                let report = if let Some(suggestion) = suggestion {
-                                    report.with_label(
-                                        Label::new(SourceSpan::from((&filepath, suggestion.span)))
-                                            .with_message(format!(
-                                                "There is a type with a similar name: `{}`.",
-                                                suggestion.clone()
-                                            ))
-                                            .with_color(Color::Cyan),
-                                    )
-                                } else {
-                                    report
-                                };
-
-                                report.finish()
-                            }
-                            TypeErrorKind::Error(message) => {
-                                Report::build(ReportKind::Error, &filepath, 1)
-                                    .with_message("A type error occurred.")
-                                    .with_label(
-                                        Label::new(SourceSpan::from((&filepath, span)))
-                                            .with_message(message)
-                                            .with_color(Color::Red),
-                                    )
-                                    .finish()
-                            }
+    /// Reads, parses, and type checks the input, returning the path and source
+    /// of the input file along with the typed package.
+    fn type_check<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        input: Input,
+        options: &CompileOptions,
+        arena: &'a AstArena<'a>,
+    ) -> Result<(String, String, TyPackage<'a>), CraneError> {
+        let (filepath, source, dir) = read_input(stderr, input)?;
+
+        let items = self.parse(stderr, options, &filepath, &source, dir.as_deref(), arena)?;
+
+        let mut typer = Typer::with_output_kind(arena, options.output_kind)
+            .with_source_map(std::mem::take(&mut self.source_map));
+
+        let module = Module { items };
+
+        let package = Package {
+            modules: thin_vec![module],
+        };
+
+        tracing::info!("Type checking `{filepath}`");
+
+        let result = {
+            let _span = tracing::info_span!(target: timings::TARGET, "type_check").entered();
+
+            typer.type_check_package(&package)
+        };
+
+        // Report each diagnostic in the file of the module it was found in.
+        let file_of = |module: &[SmolStr]| {
+            typer
+                .source_map()
+                .file_of(module)
+                .map_or((filepath.as_str(), source.as_str()), |file| {
+                    (file.path.as_str(), file.source.as_str())
+                })
+        };
+
+        let denied = self.report_warnings(stderr, typer.warnings(), &options.lint_levels, file_of);
+
+        match result {
+            // A denied lint fails compilation as a type error would.
+            Ok(_) if denied > 0 => Err(CraneError::DeniedLints { count: denied }),
+            Ok(typed_package) => Ok((filepath, source, typed_package)),
+            Err(type_error) => {
+                let (path, source) = file_of(typer.error_module());
+
+                self.report(stderr, path, source, Diagnostic::from(&type_error));
+
+                Err(type_error.into())
+            }
+        }
+    }
+
+    /// Parses the input and runs the lints that check the AST over it, without
+    /// type checking it.
+    ///
+    /// Fails if the input doesn't parse, or a lint that is denied finds
+    /// anything.
+    pub fn lint(
+        &mut self,
+        stderr: &mut impl Write,
+        params: CompileParams,
+    ) -> Result<(), CraneError> {
+        let arena = AstArena::new();
+        let (filepath, source, dir) = read_input(stderr, params.input)?;
+
+        let items = self.parse(
+            stderr,
+            &params.options,
+            &filepath,
+            &source,
+            dir.as_deref(),
+            &arena,
+        )?;
+
+        tracing::info!("Linting `{filepath}`");
+
+        let warnings = {
+            let _span = tracing::info_span!(target: timings::TARGET, "lint").entered();
+
+            check_items(&items, &AST_LINTS)
+        };
+
+        // The source map is only borrowed while reporting, and handed back
+        // after.
+        let source_map = std::mem::take(&mut self.source_map);
+        let file_of = |module: &[SmolStr]| {
+            source_map
+                .file_of(module)
+                .map_or((filepath.as_str(), source.as_str()), |file| {
+                    (file.path.as_str(), file.source.as_str())
+                })
+        };
+
+        let denied = self.report_warnings(stderr, &warnings, &params.options.lint_levels, file_of);
+
+        self.source_map = source_map;
+
+        // A denied lint fails as a type error would.
+        if denied > 0 {
+            Err(CraneError::DeniedLints { count: denied })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reports each of the warnings that isn't allowed in the file of the
+    /// module it was found in, returning how many of them were denied.
+    fn report_warnings<'s>(
+        &mut self,
+        stderr: &mut impl Write,
+        warnings: &[(Vec<SmolStr>, Warning)],
+        lint_levels: &LintLevels,
+        file_of: impl Fn(&[SmolStr]) -> (&'s str, &'s str),
+    ) -> usize {
+        let mut denied = 0;
+
+        for (module, warning) in warnings {
+            let (path, source) = file_of(module);
+
+            let diagnostic = match lint_levels.level(warning.lint_name()) {
+                LintLevel::Allow => continue,
+                LintLevel::Warn => warning.into(),
+                LintLevel::Deny => {
+                    denied += 1;
+
+                    denied_warning(warning, lint_levels)
+                }
+            };
+
+            self.report(stderr, path, source, diagnostic);
+        }
+
+        denied
+    }
+
+    /// Parses the file at the given path, along with the modules it declares,
+    /// leaving out the items disabled by `#[cfg]` and expanding macros.
+    pub fn parse_file<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        path: &Path,
+        options: &CompileOptions,
+        arena: &'a AstArena<'a>,
+    ) -> Result<ThinVec<Item<'a>>, CraneError> {
+        let source = std::fs::read_to_string(path).map_err(|source| {
+            let err = CraneError::Io {
+                path: path.to_path_buf(),
+                source,
+            };
+            writeln!(stderr, "Error: {err}").unwrap();
+
+            err
+        })?;
+
+        self.parse(
+            stderr,
+            options,
+            &path.display().to_string(),
+            &source,
+            path.parent(),
+            arena,
+        )
+    }
+
+    /// Parses the given source, written in the file at `filepath`, and loads
+    /// the modules it declares from `dir`.
+    fn parse<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        options: &CompileOptions,
+        filepath: &str,
+        source: &str,
+        dir: Option<&Path>,
+        arena: &'a AstArena<'a>,
+    ) -> Result<ThinVec<Item<'a>>, CraneError> {
+        tracing::info!("Parsing `{filepath}`");
+
+        self.color = options.color;
+
+        let mut items = match lex_and_parse(source, arena) {
+            Ok(items) => items,
+            Err(err) => {
+                self.report(stderr, filepath, source, Diagnostic::from(&err));
+
+                return Err(err.into());
+            }
+        };
+
+        let cfg = Cfg::from_options(options);
+
+        if let Err(err) = cfg.strip_items(&mut items) {
+            self.report(stderr, filepath, source, Diagnostic::from(&err));
+
+            return Err(err.into());
+        }
+
+        let file = SourceFile::new(filepath.to_string(), source.to_string());
+
+        if let Err(err) = MacroExpander::new(&file, arena)
+            .with_dir(dir)
+            .with_debug(cfg.debug)
+            .expand_items(&mut items)
+        {
+            self.report(stderr, filepath, source, Diagnostic::from(&err));
+
+            return Err(err.into());
+        }
+
+        self.source_map = SourceMap::default();
+        self.module_files = Path::new(filepath).canonicalize().into_iter().collect();
+
+        self.load_modules(
+            stderr,
+            &cfg,
+            options.threads,
+            dir,
+            &[],
+            &file,
+            &mut items,
+            arena,
+        )?;
+        self.source_map.add_file(Vec::new(), file);
+
+        if options.test {
+            add_test_harness(&mut items, arena);
+        }
+
+        Ok(items)
+    }
+
+    /// Loads the modules declared without a body (`mod foo`) from the files
+    /// in the given directory, removing their items disabled by `#[cfg]` and
+    /// expanding their macros.
+    ///
+    /// The given items are those of the module at the given path, written in
+    /// the given file. The files of the modules it declares don't depend on
+    /// each other, so they're parsed in parallel on up to `threads` threads.
+    /// The files that modules are loaded from are added to the source map.
+    #[allow(clippy::too_many_arguments)]
+    fn load_modules<'a>(
+        &mut self,
+        stderr: &mut impl Write,
+        cfg: &Cfg,
+        threads: usize,
+        dir: Option<&Path>,
+        module: &[SmolStr],
+        file: &SourceFile,
+        items: &mut ThinVec<Item<'a>>,
+        arena: &'a AstArena<'a>,
+    ) -> Result<(), CraneError> {
+        let (filepath, source) = (file.path.as_str(), file.source.as_str());
+
+        // The index of each module declared without a body, along with the
+        // directory of its submodules and the canonical path of the file it's
+        // loaded from.
+        let mut unloaded = Vec::new();
+        let mut module_files = Vec::new();
+
+        for (index, item) in items.iter_mut().enumerate() {
+            let ItemKind::Module(module_decl) = &mut item.kind else {
+                continue;
+            };
+
+            match module_decl.as_mut() {
+                ModuleDecl::Loaded(inline_module, InlineModuleDecl::Yes) => {
+                    let dir = dir.map(|dir| dir.join(item.name.to_string()));
+
+                    self.load_modules(
+                        stderr,
+                        cfg,
+                        threads,
+                        dir.as_deref(),
+                        &[module, &[item.name.name.into()]].concat(),
+                        file,
+                        &mut inline_module.items,
+                        arena,
+                    )?;
+                }
+                ModuleDecl::Loaded(_, InlineModuleDecl::No) => {}
+                ModuleDecl::Unloaded => {
+                    let Some(dir) = dir else {
+                        let err = ModuleError {
+                            code: ErrorCode::ModuleWithoutFile,
+                            message: format!(
+                                "Module `{}` can only be loaded when compiling a file.",
+                                item.name
+                            ),
+                            span: item.name.span,
+                        };
+
+                        self.report(stderr, filepath, source, Diagnostic::from(&err));
+
+                        return Err(err.into());
+                    };
+
+                    // `mod foo` is loaded from `foo.crane`, or else `foo/mod.crane`.
+                    let module_path = dir.join(format!("{}.crane", item.name));
+                    let module_path = if module_path.exists() {
+                        module_path
+                    } else {
+                        let nested_path = dir.join(item.name.to_string()).join("mod.crane");
+
+                        if nested_path.exists() {
+                            nested_path
+                        } else {
+                            module_path
+                        }
+                    };
+
+                    let Ok(module_source) = std::fs::read_to_string(&module_path) else {
+                        let err = ModuleError {
+                            code: ErrorCode::ModuleFileNotFound,
+                            message: format!(
+                                "Could not read `{}` or `{}`.",
+                                module_path.display(),
+                                dir.join(item.name.to_string()).join("mod.crane").display()
+                            ),
+                            span: item.name.span,
+                        };
+
+                        self.report(stderr, filepath, source, Diagnostic::from(&err));
+
+                        return Err(err.into());
+                    };
+
+                    let canonical_path = module_path.canonicalize().unwrap_or(module_path.clone());
+
+                    if let Some(start) = self
+                        .module_files
+                        .iter()
+                        .position(|file| *file == canonical_path)
+                    {
+                        let cycle = self.module_files[start..]
+                            .iter()
+                            .chain([&canonical_path])
+                            .map(|file| format!("`{}`", file.display()))
+                            .collect::<Vec<_>>()
+                            .join(" -> ");
+
+                        let err = ModuleError {
+                            code: ErrorCode::CyclicModule,
+                            message: format!(
+                                "Module `{}` is loaded from a file that declares it: {cycle}.",
+                                item.name
+                            ),
+                            span: item.name.span,
                         };
 
-                        error_report
-                            .write((filepath.into(), Source::from(source)), stderr)
-                            .unwrap();
+                        self.report(stderr, filepath, source, Diagnostic::from(&err));
 
-                        Err(())
+                        return Err(err.into());
                     }
+
+                    unloaded.push((index, dir.to_path_buf(), canonical_path));
+                    module_files.push(SourceFile::new(
+                        module_path.display().to_string(),
+                        module_source,
+                    ));
                 }
             }
-            Err(err) => {
-                let span = err.span;
-
-                let error_report = match err.kind {
-                    ParseErrorKind::LexError(lex_error) => {
-                        Report::build(ReportKind::Error, &filepath, 1)
-                            .with_message("An error occurred during lexing.")
-                            .with_label(
-                                Label::new(SourceSpan::from((&filepath, span)))
-                                    .with_message(lex_error)
-                                    .with_color(Color::Red),
-                            )
-                            .finish()
-                    }
-                    ParseErrorKind::Error(message) => {
-                        Report::build(ReportKind::Error, &filepath, 1)
-                            .with_message("An error occurred during parsing.")
-                            .with_label(
-                                Label::new(SourceSpan::from((&filepath, span)))
-                                    .with_message(message)
-                                    .with_color(Color::Red),
-                            )
-                            .finish()
-                    }
+        }
+
+        let results = parse_parallel(&module_files, arena, threads);
+
+        for (((index, dir, canonical_path), module_file), result) in
+            unloaded.into_iter().zip(module_files).zip(results)
+        {
+            let mut module_items = match result {
+                Ok(items) => items,
+                Err(err) => {
+                    let diagnostic = Diagnostic::from(&err);
+                    self.report(stderr, &module_file.path, &module_file.source, diagnostic);
+
+                    return Err(err.into());
+                }
+            };
+
+            if let Err(err) = cfg.strip_items(&mut module_items) {
+                let diagnostic = Diagnostic::from(&err);
+                self.report(stderr, &module_file.path, &module_file.source, diagnostic);
+
+                return Err(err.into());
+            }
+
+            let item = &mut items[index];
+
+            if let Err(err) = MacroExpander::new(&module_file, arena)
+                .with_dir(Some(dir.as_path()))
+                .with_debug(cfg.debug)
+                .expand_items(&mut module_items)
+            {
+                let diagnostic = Diagnostic::from(&err);
+                self.report(stderr, &module_file.path, &module_file.source, diagnostic);
+
+                return Err(err.into());
+            }
+
+            let submodule = [module, &[item.name.name.into()]].concat();
+
+            self.module_files.push(canonical_path);
+
+            self.load_modules(
+                stderr,
+                cfg,
+                threads,
+                Some(&dir.join(item.name.to_string())),
+                &submodule,
+                &module_file,
+                &mut module_items,
+                arena,
+            )?;
+
+            self.module_files.pop();
+
+            self.source_map.add_file(submodule, module_file);
+
+            item.kind = ItemKind::Module(Box::new(ModuleDecl::Loaded(
+                Module {
+                    items: module_items,
+                },
+                InlineModuleDecl::No,
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the input, returning its path, its source, and the directory to load
+/// the modules it declares from.
+fn read_input(
+    stderr: &mut impl Write,
+    input: Input,
+) -> Result<(String, String, Option<PathBuf>), CraneError> {
+    match input {
+        Input::File(path) => {
+            let source = std::fs::read_to_string(&path).map_err(|source| {
+                let err = CraneError::Io {
+                    path: path.clone(),
+                    source,
                 };
+                writeln!(stderr, "Error: {err}").unwrap();
 
-                error_report
-                    .write((filepath.into(), Source::from(source)), stderr)
-                    .unwrap();
+                err
+            })?;
 
-                Err(())
+            ice::set_input_file(&path.display().to_string());
+
+            Ok((
+                path.display().to_string(),
+                source,
+                path.parent().map(Path::to_path_buf),
+            ))
+        }
+        Input::String { filename, input } => {
+            ice::set_input_file(&filename);
+
+            Ok((filename, input, None))
+        }
+        Input::Buffer { path, input } => {
+            ice::set_input_file(&path.display().to_string());
+
+            Ok((
+                path.display().to_string(),
+                input,
+                path.parent().map(Path::to_path_buf),
+            ))
+        }
+    }
+}
+
+/// Lexes and then parses the given source.
+///
+/// The source is lexed up front, rather than as it is parsed, so that the two
+/// phases can be timed separately.
+fn lex_and_parse<'a>(source: &str, arena: &'a AstArena<'a>) -> ParseResult<ThinVec<Item<'a>>> {
+    let tokens = {
+        let _span = tracing::info_span!(target: timings::TARGET, "lex").entered();
+
+        Lexer::new(source).collect::<Vec<_>>()
+    };
+
+    let _span = tracing::info_span!(target: timings::TARGET, "parse").entered();
+
+    Parser::new(tokens.into_iter(), arena).parse()
+}
+
+/// Lexes and parses the given files on up to `threads` threads, into the
+/// given arena, returning the results in the order of the files.
+///
+/// The files are parsed independently, so the modules they declare are left
+/// unloaded.
+pub fn parse_parallel<'a>(
+    files: &[SourceFile],
+    arena: &'a AstArena<'a>,
+    threads: usize,
+) -> Vec<ParseResult<ThinVec<Item<'a>>>> {
+    par_map(files.iter().collect(), threads, |file| {
+        tracing::info!("Parsing `{}`", file.path);
+
+        lex_and_parse(&file.source, arena)
+    })
+}
+
+/// Returns the number of threads to compile on when it isn't given, which is
+/// the number of logical CPUs.
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Runs `f` on each of the items on up to `threads` threads, returning the
+/// results in the order of the items.
+///
+/// With a single thread, the items are mapped in order on the current thread.
+pub fn par_map<T: Send, R: Send>(
+    items: Vec<T>,
+    threads: usize,
+    f: impl Fn(T) -> R + Sync,
+) -> Vec<R> {
+    if threads <= 1 || items.len() <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let len = items.len();
+    let items = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new((0..len).map(|_| None).collect::<Vec<_>>());
+
+    std::thread::scope(|scope| {
+        let handles = (0..threads.min(len))
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let Some((index, item)) = items.lock().unwrap().next() else {
+                        break;
+                    };
+
+                    let result = f(item);
+
+                    results.lock().unwrap()[index] = Some(result);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Joined here, so that a panic is resumed with its own payload rather
+        // than the scope's.
+        for handle in handles {
+            if let Err(panic) = handle.join() {
+                std::panic::resume_unwind(panic);
             }
         }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item was mapped"))
+        .collect()
+}
+
+/// Returns the diagnostic for a warning whose lint is denied, which is an
+/// error.
+fn denied_warning(warning: &Warning, lint_levels: &LintLevels) -> Diagnostic {
+    let mut diagnostic = Diagnostic::from(warning);
+    diagnostic.severity = Severity::Error;
+
+    if let Some(flag) = lint_levels.flag(warning.lint_name()) {
+        diagnostic.notes.insert(
+            0,
+            format!("This warning is an error, as `{flag}` denies it."),
+        );
     }
+
+    diagnostic
+}
+
+/// Reports the given [`ParseError`] in the source at `filepath`.
+pub fn report_parse_error(
+    stderr: &mut impl Write,
+    filepath: &str,
+    source: &str,
+    err: &ParseError,
+    color: bool,
+) {
+    Diagnostic::from(err).write(stderr, filepath, source, color);
 }
 
 #[cfg(test)]
@@ -288,6 +1035,7 @@ fn inCamelCase() {}
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -315,6 +1063,7 @@ fn XMLHttpRequest() {}
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -343,6 +1092,7 @@ struct snake_cased_struct {
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -371,6 +1121,7 @@ struct XMLHttpRequest {
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -399,6 +1150,7 @@ union snake_cased_union {
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -427,6 +1179,7 @@ union XMLHttpRequest {
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -457,13 +1210,68 @@ fn main() {
     let user = User {
         first_name: "Elaine",
         last_name: "Benes",
-        age: 27,
     }
 }
                 "#
                 .trim()
                 .to_string(),
             },
+            options: CompileOptions::default(),
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_missing_main_fn() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "missing_main.crane".into(),
+                input: r#"
+pub fn add(a: Uint64, b: Uint64) -> Uint64 {
+    a
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions::default(),
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_main_fn_with_params() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "main_with_params.crane".into(),
+                input: r#"
+fn main(count: Uint64) {
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions::default(),
         };
 
         let mut stderr = Vec::new();
@@ -475,4 +1283,757 @@ fn main() {
 
         insta::assert_snapshot!(&stderr);
     }
+
+    #[test]
+    pub fn test_mixed_float_and_int() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "mixed_float_and_int.crane".into(),
+                input: r#"
+fn half(value: Float64) -> Float64 {
+    value / 2
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_unknown_repr() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "unknown_repr.crane".into(),
+                input: r#"
+#[repr(transparent)]
+struct Wrapper {
+    value: Uint64,
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_comptime_overflow() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "comptime_overflow.crane".into(),
+                input: r#"
+pub fn mask() -> Uint8 {
+    comptime {
+        let bits = 0 as Uint8
+        for i in 0..9 {
+            bits = bits * 2 + 1
+        }
+        bits
+    }
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_overlapping_match_arms() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "overlapping_match_arms.crane".into(),
+                input: r#"
+pub fn describe(code: Uint8) -> Uint64 {
+    match code {
+        0 => 10,
+        1 => 20,
+        0 => 30,
+        _ => 40,
+    }
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_deprecated_function_call() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "deprecated.crane".into(),
+                input: r#"
+#[deprecated(message = "use `area` instead")]
+fn old_area(width: Uint64, height: Uint64) -> Uint64 {
+    width * height
+}
+
+#[deprecated]
+fn older_area(width: Uint64) -> Uint64 {
+    width * width
+}
+
+pub fn area() -> Uint64 {
+    old_area(2, 3) + older_area(2)
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.check(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_allow_deprecated() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "allow_deprecated.crane".into(),
+                input: r#"
+#[deprecated(message = "use `area` instead")]
+fn old_area(width: Uint64, height: Uint64) -> Uint64 {
+    width * height
+}
+
+#[allow(deprecated)]
+pub fn area() -> Uint64 {
+    old_area(2, 3)
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        compiler.check(&mut stderr, params).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&stderr), "");
+    }
+
+    #[test]
+    pub fn test_std_types_do_not_shadow_user_types() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "std_types.crane".into(),
+                input: r#"
+use std::sync::mutex_lock
+use std::thread::ThreadHandle
+
+pub struct Mutex {
+    count: Uint64
+}
+
+pub fn count(mutex: Mutex) -> Uint64 {
+    mutex.count
+}
+
+pub fn lock(mutex: std::sync::Mutex) -> Uint64 {
+    mutex_lock(mutex)
+}
+
+pub fn handles(handle: ThreadHandle) -> ThreadHandle {
+    handle
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        compiler.check(&mut stderr, params).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&stderr), "");
+    }
+
+    #[test]
+    pub fn test_lint_levels() {
+        let check = |lint_levels: &[(&str, LintLevel)]| {
+            let mut options = CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            };
+
+            for (name, level) in lint_levels {
+                options.lint_levels.set(name, *level).unwrap();
+            }
+
+            let params = CompileParams {
+                input: Input::String {
+                    filename: "lint_levels.crane".into(),
+                    input: r#"
+#[deprecated]
+fn old_area(width: Uint64, height: Uint64) -> Uint64 {
+    width * height
+}
+
+#[allow(deprecated)]
+pub fn area() -> Uint64 {
+    old_area(2, 3)
+}
+
+pub fn exit_area() -> Uint64 {
+    std::process::exit(1)
+    old_area(2, 3)
+}
+                    "#
+                    .trim()
+                    .to_string(),
+                },
+                options,
+            };
+
+            let mut compiler = Compiler::new();
+            let mut stderr = Vec::new();
+
+            let result = compiler
+                .check(&mut stderr, params)
+                .map_err(|err| err.failure());
+
+            let severities = compiler
+                .diagnostics()
+                .iter()
+                .map(|reported| reported.diagnostic.severity)
+                .collect::<Vec<_>>();
+
+            (result, severities)
+        };
+
+        assert_eq!(
+            check(&[]),
+            (Ok(()), vec![Severity::Warning, Severity::Warning])
+        );
+        assert_eq!(check(&[("warnings", LintLevel::Allow)]), (Ok(()), vec![]));
+        assert_eq!(
+            check(&[
+                ("warnings", LintLevel::Deny),
+                ("deprecated", LintLevel::Warn)
+            ]),
+            (Err(Failure::Type), vec![Severity::Warning, Severity::Error])
+        );
+
+        // `#[allow]` silences a lint whatever its level.
+        assert_eq!(
+            check(&[
+                ("unreachable-code", LintLevel::Allow),
+                ("deprecated", LintLevel::Deny)
+            ]),
+            (Err(Failure::Type), vec![Severity::Error])
+        );
+    }
+
+    #[test]
+    pub fn test_unused_must_use_result() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "must_use.crane".into(),
+                input: r#"
+#[must_use(message = "the checked value is returned, not stored")]
+fn checked_double(value: Uint64) -> Uint64 {
+    value * 2
+}
+
+pub fn double_twice(value: Uint64) -> Uint64 {
+    checked_double(value)
+    checked_double(checked_double(value))
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.check(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_unreachable_code_after_exit() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "unreachable_code.crane".into(),
+                input: r#"
+use std::process::exit
+
+pub fn checked_double(value: Uint64) -> Uint64 {
+    if value > 100 {
+        exit(1)
+        value
+    } else {
+        value * 2
+    }
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.check(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_unknown_cfg_condition() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "unknown_cfg_condition.crane".into(),
+                input: r#"
+#[cfg(target_family = "unix")]
+pub fn page_size() -> Uint64 {
+    4096
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_bitfield_wider_than_its_type() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "bitfield_wider_than_its_type.crane".into(),
+                input: r#"
+#[bitfield]
+pub struct Flags {
+    #[bits(9)]
+    mode: Uint8,
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_naked_fn_with_a_body() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "naked_fn_with_a_body.crane".into(),
+                input: r#"
+#[naked]
+pub fn add(a: Int64, b: Int64) -> Int64 {
+    a + b
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_test_fn_with_parameters() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "test_fn_with_parameters.crane".into(),
+                input: r#"
+#[test]
+fn adds(a: Uint64, b: Uint64) -> Uint64 {
+    a + b
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_trait_impl_with_wrong_signature() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "trait_impl_with_wrong_signature.crane".into(),
+                input: r#"
+struct Celsius {
+    degrees: Float64,
+}
+
+impl ToString for Celsius {
+    fn to_string(self: Celsius) -> Float64 {
+        self.degrees
+    }
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_calling_an_extern_fn_requires_unsafe() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "calling_an_extern_fn_requires_unsafe.crane".into(),
+                input: r#"
+extern fn abs(x: Int32) -> Int32
+
+pub fn distance(a: Int32, b: Int32) -> Int32 {
+    abs(a - b)
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_asm_requires_unsafe() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "asm_requires_unsafe.crane".into(),
+                input: r#"
+pub fn pause() {
+    asm("nop")
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_unknown_calling_convention() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "unknown_calling_convention.crane".into(),
+                input: r#"
+#[calling_convention = "vectorcall"]
+pub fn add(a: Int64, b: Int64) -> Int64 {
+    a + b
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_calling_convention_is_part_of_the_fn_type() {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: "calling_convention_is_part_of_the_fn_type.crane".into(),
+                input: r#"
+#[calling_convention = "fast"]
+fn add(a: Int64, b: Int64) -> Int64 {
+    a + b
+}
+
+fn apply(f: Fn(Int64, Int64) -> Int64, a: Int64, b: Int64) -> Int64 {
+    f(a, b)
+}
+
+pub fn sum(a: Int64, b: Int64) -> Int64 {
+    apply(add, a, b)
+}
+                "#
+                .trim()
+                .to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            },
+        };
+
+        let mut stderr = Vec::new();
+
+        let _ = compiler.compile(&mut stderr, params);
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+        let stderr = std::str::from_utf8(&stderr).unwrap();
+
+        insta::assert_snapshot!(&stderr);
+    }
+
+    #[test]
+    pub fn test_parse_parallel() {
+        for threads in [1, 2, 4] {
+            let arena = AstArena::new();
+
+            let files = [
+                ("first.crane", "fn first() {}\nfn second() {}\n"),
+                ("broken.crane", "fn broken( {}\n"),
+                ("third.crane", "fn third() {}\n"),
+            ]
+            .map(|(path, source)| SourceFile::new(path.to_string(), source.to_string()));
+
+            let results = parse_parallel(&files, &arena, threads);
+
+            let names = |result: &ParseResult<ThinVec<Item>>| {
+                result
+                    .as_ref()
+                    .unwrap()
+                    .iter()
+                    .map(|item| item.name.to_string())
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(results.len(), 3);
+            assert_eq!(names(&results[0]), ["first", "second"]);
+            assert!(results[1].is_err());
+            assert_eq!(names(&results[2]), ["third"]);
+        }
+    }
+
+    #[test]
+    fn test_par_map_on_one_thread_runs_in_order_on_the_current_thread() {
+        let current = std::thread::current().id();
+        let order = Mutex::new(Vec::new());
+
+        let results = par_map((0..8).collect(), 1, |item: u32| {
+            assert_eq!(std::thread::current().id(), current);
+            order.lock().unwrap().push(item);
+
+            item * 2
+        });
+
+        assert_eq!(results, (0..8).map(|item| item * 2).collect::<Vec<_>>());
+        assert_eq!(order.into_inner().unwrap(), (0..8).collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,496 @@
+//! Diagnostics reported to the user, like errors and warnings.
+//!
+//! Every error and warning the compiler reports, from lexing through type
+//! checking, is turned into a [`Diagnostic`] and rendered the same way:
+//!
+//! ```text
+//! Error: A type error occurred.
+//!    ╭─[main.crane:4:5]
+//!    │
+//!  4 │     greet_al("world")
+//!    │     ────┬───
+//!    │         ╰───── Function `greet_al` does not exist.
+//! ───╯
+//! ```
+//!
+//! Diagnostics are colored only when asked to, which the CLI decides with
+//! [`ColorChoice::use_color`].
+
+use std::ffi::OsStr;
+use std::io::Write;
+
+use ariadne::{Color, Config, Report, ReportKind, Source};
+use itertools::Itertools;
+
+use crate::ast::{SourceSpan, Span};
+use crate::cfg::CfgError;
+use crate::compiler::ModuleError;
+use crate::error_codes::ErrorCode;
+use crate::macro_expander::MacroError;
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::typer::{TypeError, TypeErrorKind, Warning};
+
+/// How severe a [`Diagnostic`] is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    /// A problem that stops compilation.
+    Error,
+
+    /// A problem that doesn't stop compilation.
+    Warning,
+}
+
+/// When to color diagnostics, as chosen with `--color`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorChoice {
+    /// Color diagnostics written to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+
+    /// Always color diagnostics, even when they're captured by another tool.
+    Always,
+
+    /// Never color diagnostics.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns whether to color the diagnostics written to a stream, given
+    /// whether the stream is a terminal and the value of `NO_COLOR`, if set.
+    ///
+    /// As <https://no-color.org> asks, a `NO_COLOR` that is set to anything
+    /// but an empty string turns off colors, unless they're forced with
+    /// [`ColorChoice::Always`].
+    pub fn use_color(self, is_terminal: bool, no_color: Option<&OsStr>) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => is_terminal && no_color.is_none_or(OsStr::is_empty),
+        }
+    }
+}
+
+/// A span in the source with a message attached.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// An error or warning, pointing at the source that caused it.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// The code of the error, which `crane explain` explains.
+    ///
+    /// Only errors have codes. Warnings are named by their lint instead.
+    pub code: Option<ErrorCode>,
+
+    /// A summary of the problem.
+    pub message: String,
+
+    /// The spans in the source that the diagnostic is about.
+    ///
+    /// The first label is the primary one, which the diagnostic is located
+    /// at. Any others point at related code, like where a function is
+    /// defined.
+    pub labels: Vec<Label>,
+
+    /// Notes with additional context.
+    pub notes: Vec<String>,
+
+    /// A suggestion for how to fix the problem.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Returns a new error with the given code and message.
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, Some(code), message)
+    }
+
+    /// Returns a new warning with the given message.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, None, message)
+    }
+
+    fn new(severity: Severity, code: Option<ErrorCode>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    /// Adds a label to the diagnostic. The first label added is the primary
+    /// one.
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+
+        self
+    }
+
+    /// Adds a note to the diagnostic.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+
+        self
+    }
+
+    /// Sets the suggestion for how to fix the problem.
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+
+        self
+    }
+
+    /// Renders the diagnostic in the source at `filepath`, with ANSI colors if
+    /// `color` is set: red for errors, yellow for warnings and cyan for the
+    /// labels that point at related code.
+    pub fn write(&self, out: &mut impl Write, filepath: &str, source: &str, color: bool) {
+        let (kind, primary_color) = match self.severity {
+            Severity::Error => (ReportKind::Error, Color::Red),
+            Severity::Warning => (ReportKind::Warning, Color::Yellow),
+        };
+
+        // `ariadne` can't locate a span in a source without any lines, so an
+        // empty file is written as a single empty line.
+        let source = if source.is_empty() { "\n" } else { source };
+
+        let offset = self
+            .labels
+            .first()
+            .map_or(0, |label| clamp_span(label.span, source).start);
+
+        let mut report = Report::<SourceSpan>::build(kind, filepath, offset)
+            .with_config(Config::default().with_color(color))
+            .with_message(&self.message);
+
+        if let Some(code) = self.code {
+            report = report.with_code(code);
+        }
+
+        for (index, label) in self.labels.iter().enumerate() {
+            let label_color = if index == 0 {
+                primary_color
+            } else {
+                Color::Cyan
+            };
+
+            report = report.with_label(
+                ariadne::Label::new(SourceSpan::from((filepath, clamp_span(label.span, source))))
+                    .with_message(&label.message)
+                    .with_color(label_color)
+                    .with_order(index as i32),
+            );
+        }
+
+        // Only one note is shown by a report, so any others are joined onto
+        // the lines after it.
+        if !self.notes.is_empty() {
+            report = report.with_note(self.notes.join("\n"));
+        }
+
+        if let Some(help) = &self.help {
+            report = report.with_help(help);
+        }
+
+        report
+            .finish()
+            .write((filepath.to_string(), Source::from(source)), out)
+            .unwrap();
+    }
+}
+
+/// Fits the given span within the source, so that spans past the end, like
+/// that of the end of the file, point at the last character instead.
+fn clamp_span(span: Span, source: &str) -> Span {
+    let len = source.trim_end().len();
+
+    if span.start < len {
+        return Span::new(span.start, span.end.min(source.len()).max(span.start));
+    }
+
+    let last_char_start = source[..len]
+        .char_indices()
+        .last()
+        .map_or(0, |(offset, _)| offset);
+
+    Span::new(last_char_start, len)
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let code = err.kind.code();
+
+        match &err.kind {
+            ParseErrorKind::LexError(lex_error) => {
+                Diagnostic::error(code, "An error occurred during lexing.")
+                    .with_label(err.span, lex_error.to_string())
+            }
+            ParseErrorKind::Error(message) => {
+                Diagnostic::error(code, "An error occurred during parsing.")
+                    .with_label(err.span, message.clone())
+            }
+        }
+    }
+}
+
+impl From<&CfgError> for Diagnostic {
+    fn from(err: &CfgError) -> Self {
+        Diagnostic::error(
+            ErrorCode::InvalidCfg,
+            "An error occurred during conditional compilation.",
+        )
+        .with_label(err.span, err.message.clone())
+    }
+}
+
+impl From<&MacroError> for Diagnostic {
+    fn from(err: &MacroError) -> Self {
+        Diagnostic::error(
+            ErrorCode::MacroExpansion,
+            "An error occurred during macro expansion.",
+        )
+        .with_label(err.span, err.message.clone())
+    }
+}
+
+impl From<&ModuleError> for Diagnostic {
+    fn from(err: &ModuleError) -> Self {
+        let title = match err.code {
+            ErrorCode::CyclicModule => "Cyclic module declaration.",
+            _ => "Failed to load module.",
+        };
+
+        Diagnostic::error(err.code, title).with_label(err.span, err.message.clone())
+    }
+}
+
+impl From<&TypeError> for Diagnostic {
+    fn from(err: &TypeError) -> Self {
+        let diagnostic = Diagnostic::error(err.kind.code(), "A type error occurred.")
+            .with_label(err.span, err.kind.to_string());
+
+        match &err.kind {
+            TypeErrorKind::InvalidFunctionName { suggestion, .. }
+            | TypeErrorKind::InvalidTypeName { suggestion, .. } => {
+                diagnostic.with_help(format!("Try writing it as `{suggestion}` instead."))
+            }
+            TypeErrorKind::UnknownModule { path, options } => {
+                match closest(&path.to_string(), options) {
+                    Some(suggestion) => diagnostic.with_label(
+                        suggestion.span,
+                        format!("There is a module with a similar name: `{suggestion}`."),
+                    ),
+                    None => diagnostic,
+                }
+            }
+            TypeErrorKind::UnknownFunction { path, options } => {
+                match closest(&path.to_string(), options) {
+                    Some(suggestion) => diagnostic.with_label(
+                        suggestion.span,
+                        format!("There is a function with a similar name: `{suggestion}`."),
+                    ),
+                    None => diagnostic,
+                }
+            }
+            TypeErrorKind::UnknownType { path, options } => {
+                match closest(&path.to_string(), options) {
+                    Some(suggestion) => diagnostic.with_label(
+                        suggestion.span,
+                        format!("There is a type with a similar name: `{suggestion}`."),
+                    ),
+                    None => diagnostic,
+                }
+            }
+            TypeErrorKind::RequiresUnsafe { .. } => {
+                diagnostic.with_help("Wrap it in an `unsafe { ... }` block.")
+            }
+            TypeErrorKind::Error(_) => diagnostic,
+        }
+    }
+}
+
+/// Returns the option whose name is closest to `name`.
+fn closest<'a, T: ToString>(name: &str, options: &'a [T]) -> Option<&'a T> {
+    options
+        .iter()
+        .sorted_by_key(|option| option.to_string())
+        .min_by_key(|option| strsim::levenshtein(&option.to_string(), name))
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        let (title, message, span) = match warning {
+            Warning::Deprecated {
+                fn_name,
+                message,
+                span,
+            } => (
+                format!("Use of deprecated function `{fn_name}`."),
+                message
+                    .as_deref()
+                    .unwrap_or("This function is deprecated.")
+                    .into(),
+                span,
+            ),
+            Warning::UnusedResult {
+                fn_name,
+                message,
+                span,
+            } => (
+                format!("Unused result of `{fn_name}`."),
+                message
+                    .as_deref()
+                    .unwrap_or("The result of this call should be used.")
+                    .into(),
+                span,
+            ),
+            Warning::UnreachableCode { span } => (
+                "Unreachable code.".to_string(),
+                "This code is never run, as the code before it never finishes.".into(),
+                span,
+            ),
+            Warning::NonSnakeCase {
+                kind, name, span, ..
+            } => (
+                format!("{kind} `{name}` should have a snake_case name."),
+                format!("{kind} names are written in snake_case."),
+                span,
+            ),
+            Warning::NonPascalCaseType {
+                kind, name, span, ..
+            } => (
+                format!("{kind} `{name}` should have a PascalCase name."),
+                format!("{kind} names are written in PascalCase."),
+                span,
+            ),
+            Warning::TooManyStatements {
+                fn_name,
+                count,
+                max,
+                span,
+            } => (
+                format!("Function `{fn_name}` has {count} statements."),
+                format!(
+                    "Functions should have at most {max} statements. Try splitting this one up."
+                ),
+                span,
+            ),
+            Warning::EmptyFn { fn_name, span } => (
+                format!("Function `{fn_name}` has an empty body."),
+                "This function does nothing.".into(),
+                span,
+            ),
+        };
+
+        let diagnostic = Diagnostic::warning(title)
+            .with_label(*span, message)
+            .with_note(format!(
+                "Add `#[allow({})]` to the enclosing item to silence this warning.",
+                warning.lint_name()
+            ));
+
+        match warning {
+            Warning::NonSnakeCase { suggestion, .. }
+            | Warning::NonPascalCaseType { suggestion, .. } => {
+                diagnostic.with_help(format!("Try writing it as `{suggestion}` instead."))
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::{CompileOptions, CompileParams, Compiler, Input};
+
+    #[test]
+    fn test_diagnostics() {
+        insta::glob!("snapshot_inputs/diagnostics/*.crane", |path| {
+            let mut compiler = Compiler::new();
+
+            let params = CompileParams {
+                input: Input::String {
+                    filename: path.file_name().unwrap().to_string_lossy().into_owned(),
+                    input: std::fs::read_to_string(path).unwrap(),
+                },
+                options: CompileOptions::default(),
+            };
+
+            let mut stderr = Vec::new();
+
+            assert!(compiler.check(&mut stderr, params).is_err());
+
+            let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+
+            insta::assert_snapshot!(String::from_utf8_lossy(&stderr));
+        });
+    }
+
+    #[test]
+    fn test_use_color() {
+        let no_color = Some(OsStr::new("1"));
+        let empty = Some(OsStr::new(""));
+
+        assert!(ColorChoice::Auto.use_color(true, None));
+        assert!(ColorChoice::Auto.use_color(true, empty));
+        assert!(!ColorChoice::Auto.use_color(true, no_color));
+        assert!(!ColorChoice::Auto.use_color(false, None));
+
+        assert!(ColorChoice::Always.use_color(false, no_color));
+        assert!(!ColorChoice::Never.use_color(true, None));
+    }
+
+    #[test]
+    fn test_colored_and_plain_rendering() {
+        let source = "fn main() {\n    greet_al(\"world\")\n}\n\nfn greet_all() {}\n";
+
+        let diagnostic = Diagnostic::error(ErrorCode::UnknownFunction, "A type error occurred.")
+            .with_label(Span::new(16, 24), "Function `greet_al` does not exist.")
+            .with_label(
+                Span::new(40, 49),
+                "There is a function with a similar name: `greet_all`.",
+            )
+            .with_note("Functions are looked up in the current module.");
+
+        let render = |color| {
+            let mut out = Vec::new();
+            diagnostic.write(&mut out, "main.crane", source, color);
+
+            String::from_utf8(out).unwrap()
+        };
+
+        let plain = render(false);
+        let colored = render(true);
+
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(
+            String::from_utf8(strip_ansi_escapes::strip(&colored).unwrap()).unwrap(),
+            plain
+        );
+
+        insta::assert_snapshot!("plain_rendering", plain);
+        insta::assert_snapshot!("colored_rendering", colored.replace('\x1b', "\\x1b"));
+    }
+
+    #[test]
+    fn test_clamp_span_at_end_of_file() {
+        let source = "fn main() {\n    let x =\n";
+
+        assert_eq!(
+            clamp_span(Span::new(source.len(), source.len() + 1), source),
+            Span::new(22, 23)
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Documentation generated from the doc comments (`///`) on items.
+//!
+//! Each module of a package is documented on its own HTML page, which lists
+//! the module's items with their signatures and their doc comments rendered
+//! from Markdown, along with the fields of structs and the variants of unions.
+//! An index page links to every module.
+//!
+//! Only public items are documented, unless private items are asked for.
+
+use std::path::PathBuf;
+
+use pulldown_cmark::escape::escape_html;
+use smol_str::SmolStr;
+
+use crate::ast::{
+    keywords, FieldDecl, Ident, ImplDecl, Item, ItemKind, ModuleDecl, VariantData, Visibility,
+};
+use crate::formatter::{format_field, format_signature};
+use crate::lexer::token::TokenKind;
+use crate::lexer::Lexer;
+
+/// The keywords that are highlighted in signatures.
+const KEYWORDS: &[Ident] = &[
+    keywords::AS,
+    keywords::CONST,
+    keywords::EXTERN,
+    keywords::FN,
+    keywords::IMPL,
+    keywords::MOD,
+    keywords::PUB,
+    keywords::STRUCT,
+    keywords::UNION,
+    keywords::UNSAFE,
+    keywords::USE,
+];
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+pre.signature { background: #f5f5f5; padding: 0.75rem; border-radius: 4px; }
+.keyword { color: #8959a8; }
+.type { color: #4271ae; }
+.literal { color: #718c00; }
+.item { margin-bottom: 2rem; }
+dt { margin-top: 0.5rem; }
+";
+
+/// A section of a module's page, with its heading and whether an item of the
+/// given kind belongs in it.
+type Section = (&'static str, fn(&ItemKind) -> bool);
+
+/// A page of documentation.
+#[derive(Debug)]
+pub struct DocPage {
+    /// The path of the page, relative to the documentation's directory.
+    pub path: PathBuf,
+
+    /// The contents of the page.
+    pub html: String,
+}
+
+/// Documents the package with the given name and items, returning the index
+/// page followed by a page for each module.
+///
+/// Private items, and the modules within them, are left out unless
+/// `document_private_items` is set.
+pub fn document_package(name: &str, items: &[Item], document_private_items: bool) -> Vec<DocPage> {
+    let mut modules = Vec::new();
+
+    collect_modules(
+        vec![SmolStr::from(name)],
+        items,
+        document_private_items,
+        &mut modules,
+    );
+
+    let mut index = String::new();
+
+    index.push_str(&format!("<h1>{}</h1>\n<ul>\n", escape(name)));
+
+    for (path, _) in &modules {
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            module_file(path),
+            escape(&path.join("::"))
+        ));
+    }
+
+    index.push_str("</ul>\n");
+
+    let mut pages = vec![DocPage {
+        path: PathBuf::from("index.html"),
+        html: page(name, &index),
+    }];
+
+    for (path, items) in &modules {
+        let title = path.join("::");
+
+        pages.push(DocPage {
+            path: PathBuf::from(module_file(path)),
+            html: page(
+                &title,
+                &document_module(&title, path, items, document_private_items),
+            ),
+        });
+    }
+
+    pages
+}
+
+/// Collects the module at `path` along with all of its documented submodules.
+fn collect_modules<'i, 'a>(
+    path: Vec<SmolStr>,
+    items: &'i [Item<'a>],
+    document_private_items: bool,
+    modules: &mut Vec<(Vec<SmolStr>, &'i [Item<'a>])>,
+) {
+    modules.push((path.clone(), items));
+
+    for item in items {
+        if !is_documented(item, items, document_private_items) {
+            continue;
+        }
+
+        if let ItemKind::Module(module_decl) = &item.kind {
+            if let ModuleDecl::Loaded(module, _) = module_decl.as_ref() {
+                let mut submodule = path.clone();
+                submodule.push(item.name.name.into());
+
+                collect_modules(submodule, &module.items, document_private_items, modules);
+            }
+        }
+    }
+}
+
+/// Returns the name of the page documenting the module at `path`.
+fn module_file(path: &[SmolStr]) -> String {
+    format!("{}.html", path.join("."))
+}
+
+/// Returns whether the given item, one of `items`, is documented.
+///
+/// Public items always are, and private ones only are with
+/// `document_private_items`. An `impl` block is documented along with the
+/// type it implements, when that is one of `items`, as long as it has any
+/// documented methods or implements a trait.
+fn is_documented(item: &Item, items: &[Item], document_private_items: bool) -> bool {
+    let ItemKind::Impl(impl_decl) = &item.kind else {
+        return document_private_items || item.vis == Visibility::Public;
+    };
+
+    let is_ty_documented = items
+        .iter()
+        .find(|ty| {
+            matches!(ty.kind, ItemKind::Struct(_) | ItemKind::Union(_)) && ty.name == item.name
+        })
+        .is_none_or(|ty| is_documented(ty, items, document_private_items));
+
+    is_ty_documented
+        && (impl_decl.trait_name.is_some()
+            || !documented_methods(impl_decl, document_private_items).is_empty())
+}
+
+/// Returns the documented methods of an `impl` block, which are all of them
+/// for the implementation of a trait.
+fn documented_methods<'i, 'a>(
+    impl_decl: &'i ImplDecl<'a>,
+    document_private_items: bool,
+) -> Vec<&'i Item<'a>> {
+    impl_decl
+        .items
+        .iter()
+        .filter(|method| {
+            impl_decl.trait_name.is_some()
+                || document_private_items
+                || method.vis == Visibility::Public
+        })
+        .collect()
+}
+
+fn document_module(
+    title: &str,
+    path: &[SmolStr],
+    items: &[Item],
+    document_private_items: bool,
+) -> String {
+    let mut html = format!(
+        "<a href=\"index.html\">Index</a>\n<h1>Module <code>{}</code></h1>\n",
+        escape(title)
+    );
+
+    let sections: [Section; 6] = [
+        ("Modules", |kind| matches!(kind, ItemKind::Module(_))),
+        ("Constants", |kind| matches!(kind, ItemKind::Const(_))),
+        ("Structs", |kind| matches!(kind, ItemKind::Struct(_))),
+        ("Unions", |kind| matches!(kind, ItemKind::Union(_))),
+        ("Functions", |kind| {
+            matches!(kind, ItemKind::Fn(_) | ItemKind::ExternFn(_))
+        }),
+        ("Implementations", |kind| matches!(kind, ItemKind::Impl(_))),
+    ];
+
+    for (heading, is_in_section) in sections {
+        let section_items = items
+            .iter()
+            .filter(|item| {
+                is_in_section(&item.kind) && is_documented(item, items, document_private_items)
+            })
+            .collect::<Vec<_>>();
+
+        if section_items.is_empty() {
+            continue;
+        }
+
+        html.push_str(&format!("<h2>{heading}</h2>\n"));
+
+        for item in section_items {
+            match &item.kind {
+                ItemKind::Module(_) => {
+                    let mut submodule = path.to_vec();
+                    submodule.push(item.name.name.into());
+
+                    html.push_str(&format!(
+                        "<section class=\"item\" id=\"{}\"><a href=\"{}\"><code>{}</code></a>{}</section>\n",
+                        escape(&item_id(item)),
+                        module_file(&submodule),
+                        escape(&item.name.name),
+                        docs(&item.doc_text())
+                    ));
+                }
+                ItemKind::Impl(impl_decl) => {
+                    let id = match &impl_decl.trait_name {
+                        Some(trait_name) => format!("impl.{trait_name}.{}", item.name),
+                        None => format!("impl.{}", item.name),
+                    };
+
+                    html.push_str(&document_item(item, &id));
+
+                    for method in documented_methods(impl_decl, document_private_items) {
+                        html.push_str(&document_item(
+                            method,
+                            &format!("{}.{}", item.name, method.name),
+                        ));
+                    }
+                }
+                _ => html.push_str(&document_item(item, &item_id(item))),
+            }
+        }
+    }
+
+    html
+}
+
+/// Returns the anchor of an item on its module's page, like `fn.main`.
+fn item_id(item: &Item) -> String {
+    let kind = match item.kind {
+        ItemKind::Use(_) => "use",
+        ItemKind::Fn(_) | ItemKind::ExternFn(_) => "fn",
+        ItemKind::Struct(_) => "struct",
+        ItemKind::Union(_) => "union",
+        ItemKind::Module(_) => "mod",
+        ItemKind::Impl(_) => "impl",
+        ItemKind::Const(_) => "const",
+    };
+
+    format!("{kind}.{}", item.name)
+}
+
+/// Documents an item with its signature and doc comments, under the given
+/// anchor.
+fn document_item(item: &Item, id: &str) -> String {
+    format!(
+        "<section class=\"item\" id=\"{}\">\n<pre class=\"signature\">{}</pre>\n{}{}</section>\n",
+        escape(id),
+        highlight(&format_signature(item)),
+        docs(&item.doc_text()),
+        document_members(item, id)
+    )
+}
+
+/// Lists the fields of a struct, or the variants of a union along with their
+/// fields, with their doc comments.
+fn document_members(item: &Item, id: &str) -> String {
+    match &item.kind {
+        ItemKind::Struct(struct_decl) => match &struct_decl.0 {
+            VariantData::Struct(fields) if !fields.is_empty() => {
+                format!("<h3>Fields</h3>\n{}", document_fields(fields, id))
+            }
+            _ => String::new(),
+        },
+        ItemKind::Union(union_decl) if !union_decl.variants.is_empty() => {
+            let mut html = String::from("<h3>Variants</h3>\n<dl class=\"variants\">\n");
+
+            for variant in &union_decl.variants {
+                let variant_id = format!("{id}.{}", variant.name);
+
+                html.push_str(&format!(
+                    "<dt id=\"{}\"><code>{}</code></dt>\n",
+                    escape(&variant_id),
+                    escape(&variant.name.name)
+                ));
+
+                let fields = match &variant.data {
+                    VariantData::Struct(fields) if !fields.is_empty() => {
+                        document_fields(fields, &variant_id)
+                    }
+                    _ => String::new(),
+                };
+
+                let docs = docs(&variant.doc_text());
+
+                if !docs.is_empty() || !fields.is_empty() {
+                    html.push_str(&format!("<dd>\n{docs}{fields}</dd>\n"));
+                }
+            }
+
+            html.push_str("</dl>\n");
+
+            html
+        }
+        _ => String::new(),
+    }
+}
+
+/// Lists the named fields of a struct or a variant of a union, with their doc
+/// comments, under anchors starting with the given one.
+fn document_fields(fields: &[FieldDecl], id: &str) -> String {
+    let mut html = String::from("<dl class=\"fields\">\n");
+
+    for field in fields {
+        let Some(name) = &field.name else {
+            continue;
+        };
+
+        html.push_str(&format!(
+            "<dt id=\"{}\"><code>{}</code></dt>\n",
+            escape(&format!("{id}.{name}")),
+            highlight(&format_field(field))
+        ));
+
+        let docs = docs(&field.doc_text());
+
+        if !docs.is_empty() {
+            html.push_str(&format!("<dd>\n{docs}</dd>\n"));
+        }
+    }
+
+    html.push_str("</dl>\n");
+
+    html
+}
+
+/// Renders doc comments from Markdown.
+fn docs(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from("<div class=\"docs\">\n");
+
+    pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(text));
+
+    html.push_str("</div>\n");
+
+    html
+}
+
+/// Highlights the keywords, types, and literals in the given code.
+fn highlight(code: &str) -> String {
+    let mut html = String::new();
+    let mut end = 0;
+
+    for token in Lexer::new(code).flatten() {
+        html.push_str(&escape(&code[end..token.span.get().start]));
+
+        let class = match token.kind {
+            TokenKind::Ident
+                if KEYWORDS
+                    .iter()
+                    .any(|keyword| token.is_keyword(keyword.clone())) =>
+            {
+                Some("keyword")
+            }
+            TokenKind::Ident if token.lexeme.starts_with(char::is_uppercase) => Some("type"),
+            TokenKind::String | TokenKind::Integer | TokenKind::Float => Some("literal"),
+            _ => None,
+        };
+
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{class}\">{}</span>",
+                escape(&token.lexeme)
+            )),
+            None => html.push_str(&escape(&token.lexeme)),
+        }
+
+        end = token.span.get().end;
+    }
+
+    html.push_str(&escape(&code[end..]));
+
+    html
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+
+    escape_html(&mut escaped, text).expect("writing to a `String` can't fail");
+
+    escaped
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ast::AstArena;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_document_package() {
+        let source = r#"
+/// The answer to *everything*.
+pub const ANSWER: Uint64 = 42
+
+/// A point on a plane.
+struct Point {
+    x: Uint64,
+    y: Uint64,
+}
+
+impl Point {
+    /// Returns the sum of the coordinates.
+    fn sum(self: Point) -> Uint64 {
+        self.x + self.y
+    }
+}
+
+/// Geometry helpers.
+mod geometry {
+    /// Returns the area of a rectangle.
+    ///
+    /// ```
+    /// area(2, 3)
+    /// ```
+    pub fn area(width: Uint64, height: Uint64) -> Uint64 {
+        width * height
+    }
+}
+
+fn main() {}
+"#;
+
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let pages = document_package("shapes", &items, true);
+
+        let paths = pages
+            .iter()
+            .map(|page| page.path.display().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths, ["index.html", "shapes.html", "shapes.geometry.html"]);
+
+        insta::assert_snapshot!(pages[1].html);
+        insta::assert_snapshot!(pages[2].html);
+    }
+
+    #[test]
+    fn test_private_items_are_not_documented() {
+        let source = r#"
+pub fn public() {}
+
+fn private() {}
+
+mod internal {
+    pub fn helper() {}
+}
+"#;
+
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let pages = document_package("main", &items, false);
+
+        assert_eq!(pages.len(), 2);
+        assert!(pages[1].html.contains("id=\"fn.public\""));
+        assert!(!pages[1].html.contains("id=\"fn.private\""));
+        assert!(!pages[1].html.contains("internal"));
+
+        let pages = document_package("main", &items, true);
+
+        assert_eq!(pages.len(), 3);
+        assert!(pages[1].html.contains("id=\"fn.private\""));
+    }
+
+    #[test]
+    fn test_doc() {
+        insta::glob!("snapshot_inputs/doc/*.crane", |path| {
+            let source = std::fs::read_to_string(path).unwrap();
+
+            let arena = AstArena::new();
+            let items = Parser::new(Lexer::new(&source), &arena).parse().unwrap();
+
+            let pages = document_package("main", &items, false)
+                .into_iter()
+                .map(|page| format!("==> {} <==\n{}", page.path.display(), page.html))
+                .collect::<Vec<_>>();
+
+            insta::assert_snapshot!(pages.join("\n"));
+        })
+    }
+}
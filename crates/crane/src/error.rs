@@ -0,0 +1,226 @@
+//! The errors that stop compilation, for tools that use the compiler as a
+//! library.
+//!
+//! The [`Compiler`](crate::compiler::Compiler) reports these, as diagnostics
+//! or on stderr, before it returns them. The [`Failure`] they map to decides
+//! the exit code of `crane`.
+
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
+use thiserror::Error;
+
+use crate::backend::BackendError;
+use crate::build_script::BuildScriptError;
+use crate::cfg::CfgError;
+use crate::compiler::{Failure, ModuleError};
+use crate::macro_expander::MacroError;
+use crate::manifest::ManifestError;
+use crate::parser::ParseError;
+use crate::scaffold::ScaffoldError;
+use crate::typer::TypeError;
+
+#[derive(Error, Debug)]
+pub enum CraneError {
+    /// The source failed to lex or parse.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    /// A `#[cfg]` attribute in the source was invalid.
+    #[error(transparent)]
+    Cfg(#[from] CfgError),
+
+    /// A macro call in the source failed to expand.
+    #[error(transparent)]
+    Macro(#[from] MacroError),
+
+    /// A module declared without a body could not be loaded.
+    #[error(transparent)]
+    Module(#[from] ModuleError),
+
+    /// The source failed to type check.
+    #[error(transparent)]
+    Type(#[from] TypeError),
+
+    /// Lints that are denied found something in the source.
+    #[error("Found {count} warning(s) from denied lints.")]
+    DeniedLints { count: usize },
+
+    /// Generating, emitting, or linking code failed.
+    #[error(transparent)]
+    Codegen(#[from] BackendError),
+
+    /// The manifest of the package was invalid, or could not be read.
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+
+    /// The build script of the package failed.
+    #[error(transparent)]
+    BuildScript(#[from] BuildScriptError),
+
+    /// A new package could not be created.
+    #[error(transparent)]
+    Scaffold(#[from] ScaffoldError),
+
+    /// The language server or the REPL stopped, as its input or output
+    /// failed.
+    #[error(transparent)]
+    Session(Box<dyn std::error::Error + Send + Sync>),
+
+    /// An input file could not be read.
+    #[error("Failed to read `{}`: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The current directory could not be read.
+    #[error("Failed to read the current directory: {0}")]
+    CurrentDir(#[source] std::io::Error),
+
+    /// A file or directory could not be written.
+    #[error("Failed to write `{}`: {source}", .path.display())]
+    Write {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A directory could not be removed.
+    #[error("Failed to remove `{}`: {source}", .path.display())]
+    Remove {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A directory that `crane clean` would remove isn't inside the project.
+    #[error("Refusing to remove `{}`, which isn't inside the project in `{}`.", .path.display(), .root.display())]
+    OutsideProject { path: PathBuf, root: PathBuf },
+
+    /// Two inputs would be built into artifacts of the same name.
+    #[error("`{}` and `{}` would both be built as `{name}` in `{}`.", .first.display(), .second.display(), .output_dir.display())]
+    ConflictingOutputs {
+        first: PathBuf,
+        second: PathBuf,
+        name: String,
+        output_dir: PathBuf,
+    },
+
+    /// A compiled program could not be run.
+    #[error("Failed to run `{}`: {source}", .path.display())]
+    Run {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A compiled program exited unsuccessfully.
+    #[error("The program exited with {0}.")]
+    Exited(ExitStatus),
+
+    /// The value of a REPL input has a type that can't be printed.
+    #[error("Values of type `{0}` can't be printed.")]
+    Unprintable(String),
+
+    /// Some of the `#[test]` functions failed.
+    #[error("{0} test(s) failed.")]
+    TestsFailed(usize),
+
+    /// A file isn't formatted, with `crane fmt --check`.
+    #[error("`{}` is not formatted.", .0.display())]
+    Unformatted(PathBuf),
+}
+
+impl CraneError {
+    /// Returns the stage of compilation that failed.
+    pub fn failure(&self) -> Failure {
+        match self {
+            Self::Parse(_) | Self::Cfg(_) | Self::Macro(_) | Self::Module(_) => Failure::Syntax,
+            Self::Type(_) | Self::DeniedLints { .. } => Failure::Type,
+            Self::Codegen(BackendError::LinkFailed { .. }) => Failure::Link,
+            Self::Codegen(BackendError::Io { .. })
+            | Self::Manifest(_)
+            | Self::BuildScript(_)
+            | Self::Scaffold(_)
+            | Self::Session(_)
+            | Self::Io { .. }
+            | Self::CurrentDir(_)
+            | Self::Write { .. }
+            | Self::Remove { .. }
+            | Self::OutsideProject { .. }
+            | Self::ConflictingOutputs { .. }
+            | Self::Run { .. }
+            | Self::Exited(_)
+            | Self::Unprintable(_)
+            | Self::TestsFailed(_)
+            | Self::Unformatted(_) => Failure::Other,
+            Self::Codegen(
+                BackendError::Verification { .. }
+                | BackendError::Target(_)
+                | BackendError::Emit(_)
+                | BackendError::Internal(_),
+            ) => Failure::Codegen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::ast::Span;
+    use crate::parser::ParseErrorKind;
+    use crate::typer::TypeErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_failure() {
+        let parse_error = CraneError::from(ParseError {
+            kind: ParseErrorKind::Error("Expected an item.".to_string()),
+            span: Span::new(0, 1),
+        });
+        let link_error = CraneError::from(BackendError::LinkFailed {
+            program: "cc".to_string(),
+            stderr: String::new(),
+        });
+        let io_error = CraneError::Io {
+            path: PathBuf::from("main.crane"),
+            source: std::io::ErrorKind::NotFound.into(),
+        };
+
+        assert_eq!(parse_error.failure(), Failure::Syntax);
+        assert_eq!(link_error.failure(), Failure::Link);
+        assert_eq!(io_error.failure(), Failure::Other);
+    }
+
+    #[test]
+    fn test_display() {
+        let type_error = CraneError::from(TypeError {
+            kind: TypeErrorKind::RequiresUnsafe {
+                operation: "Calling an `extern` function".to_string(),
+            },
+            span: Span::new(0, 1),
+        });
+        let io_error = CraneError::Io {
+            path: PathBuf::from("main.crane"),
+            source: std::io::ErrorKind::NotFound.into(),
+        };
+
+        assert_eq!(
+            type_error.to_string(),
+            "Calling an `extern` function requires an `unsafe` block or function."
+        );
+        assert_eq!(
+            io_error.to_string(),
+            "Failed to read `main.crane`: entity not found"
+        );
+        assert!(io_error.source().is_some());
+    }
+}
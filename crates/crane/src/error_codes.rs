@@ -0,0 +1,235 @@
+//! The codes that identify each kind of error, like `E0204`, along with the
+//! longer explanations that `crane explain` prints.
+//!
+//! The codes are stable: a code is never reused for a different error, even
+//! once the error it named is no longer reported. They are grouped by the
+//! phase of the compiler that reports them:
+//!
+//! - `E00xx`: Lexing, parsing, and loading the files of a package.
+//! - `E02xx`: Type checking.
+//! - `E03xx`: Code generation and linking.
+//!
+//! The explanation of each code is written in `error_codes/<code>.md`, and
+//! embedded in the binary.
+
+use std::fmt;
+
+/// A kind of error, identified by a stable code.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ErrorCode {
+    /// `E0001`: A character that doesn't start any token.
+    UnrecognizedCharacter,
+
+    /// `E0002`: Tokens that don't make up valid syntax.
+    SyntaxError,
+
+    /// `E0003`: An invalid `#[cfg]` condition.
+    InvalidCfg,
+
+    /// `E0004`: A macro that could not be expanded.
+    MacroExpansion,
+
+    /// `E0005`: A module declared without a body, in source with no file.
+    ModuleWithoutFile,
+
+    /// `E0006`: A module declared without a body, whose file doesn't exist.
+    ModuleFileNotFound,
+
+    /// `E0007`: A module loaded from a file that declares it.
+    CyclicModule,
+
+    /// `E0008`: A `Crane.toml` that isn't valid.
+    InvalidManifest,
+
+    /// `E0200`: A type error without a code of its own, like a mismatched
+    /// type.
+    TypeError,
+
+    /// `E0201`: A function name that isn't in `snake_case`.
+    InvalidFunctionName,
+
+    /// `E0202`: A type name that isn't in `PascalCase`.
+    InvalidTypeName,
+
+    /// `E0203`: A path to a module that doesn't exist.
+    UnknownModule,
+
+    /// `E0204`: A call to a function that doesn't exist.
+    UnknownFunction,
+
+    /// `E0205`: A reference to a type that doesn't exist.
+    UnknownType,
+
+    /// `E0206`: An operation that requires `unsafe`, outside of an `unsafe`
+    /// block or function.
+    RequiresUnsafe,
+
+    /// `E0300`: Code that LLVM could not generate.
+    CodegenFailed,
+
+    /// `E0301`: Object files that the linker could not link.
+    LinkFailed,
+}
+
+impl ErrorCode {
+    /// Every error code, in order.
+    pub const ALL: [Self; 17] = [
+        Self::UnrecognizedCharacter,
+        Self::SyntaxError,
+        Self::InvalidCfg,
+        Self::MacroExpansion,
+        Self::ModuleWithoutFile,
+        Self::ModuleFileNotFound,
+        Self::CyclicModule,
+        Self::InvalidManifest,
+        Self::TypeError,
+        Self::InvalidFunctionName,
+        Self::InvalidTypeName,
+        Self::UnknownModule,
+        Self::UnknownFunction,
+        Self::UnknownType,
+        Self::RequiresUnsafe,
+        Self::CodegenFailed,
+        Self::LinkFailed,
+    ];
+
+    /// Returns the code, like `E0204`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::UnrecognizedCharacter => "E0001",
+            Self::SyntaxError => "E0002",
+            Self::InvalidCfg => "E0003",
+            Self::MacroExpansion => "E0004",
+            Self::ModuleWithoutFile => "E0005",
+            Self::ModuleFileNotFound => "E0006",
+            Self::CyclicModule => "E0007",
+            Self::InvalidManifest => "E0008",
+            Self::TypeError => "E0200",
+            Self::InvalidFunctionName => "E0201",
+            Self::InvalidTypeName => "E0202",
+            Self::UnknownModule => "E0203",
+            Self::UnknownFunction => "E0204",
+            Self::UnknownType => "E0205",
+            Self::RequiresUnsafe => "E0206",
+            Self::CodegenFailed => "E0300",
+            Self::LinkFailed => "E0301",
+        }
+    }
+
+    /// Returns the explanation of the error, with an example of code that
+    /// causes it and how to fix it.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Self::UnrecognizedCharacter => include_str!("error_codes/E0001.md"),
+            Self::SyntaxError => include_str!("error_codes/E0002.md"),
+            Self::InvalidCfg => include_str!("error_codes/E0003.md"),
+            Self::MacroExpansion => include_str!("error_codes/E0004.md"),
+            Self::ModuleWithoutFile => include_str!("error_codes/E0005.md"),
+            Self::ModuleFileNotFound => include_str!("error_codes/E0006.md"),
+            Self::CyclicModule => include_str!("error_codes/E0007.md"),
+            Self::InvalidManifest => include_str!("error_codes/E0008.md"),
+            Self::TypeError => include_str!("error_codes/E0200.md"),
+            Self::InvalidFunctionName => include_str!("error_codes/E0201.md"),
+            Self::InvalidTypeName => include_str!("error_codes/E0202.md"),
+            Self::UnknownModule => include_str!("error_codes/E0203.md"),
+            Self::UnknownFunction => include_str!("error_codes/E0204.md"),
+            Self::UnknownType => include_str!("error_codes/E0205.md"),
+            Self::RequiresUnsafe => include_str!("error_codes/E0206.md"),
+            Self::CodegenFailed => include_str!("error_codes/E0300.md"),
+            Self::LinkFailed => include_str!("error_codes/E0301.md"),
+        }
+    }
+
+    /// Returns the error with the given code, which may be written in either
+    /// case, like `E0204` or `e0204`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|error_code| error_code.code().eq_ignore_ascii_case(code))
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique_and_in_order() {
+        let codes = ErrorCode::ALL.map(ErrorCode::code);
+
+        assert!(
+            codes.windows(2).all(|pair| pair[0] < pair[1]),
+            "The codes must be unique and in order: {codes:?}"
+        );
+
+        for code in codes {
+            assert!(
+                code.len() == 5
+                    && code.starts_with('E')
+                    && code[1..].bytes().all(|b| b.is_ascii_digit()),
+                "`{code}` is not of the form `E0000`"
+            );
+        }
+    }
+
+    /// Every explanation file must belong to an error code, and every error
+    /// code must have an explanation with an example.
+    #[test]
+    fn test_explanations_are_registered() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/error_codes");
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<HashSet<_>>();
+
+        let registered = ErrorCode::ALL
+            .map(|error_code| format!("{error_code}.md"))
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut orphans = files.difference(&registered).collect::<Vec<_>>();
+        orphans.sort();
+
+        assert!(
+            orphans.is_empty(),
+            "Explanations without an error code: {orphans:?}"
+        );
+
+        for error_code in ErrorCode::ALL {
+            let explanation = error_code.explanation();
+
+            assert!(
+                explanation.starts_with(&format!("# {error_code}: ")),
+                "The explanation of `{error_code}` must start with its title"
+            );
+            assert!(
+                explanation.contains("```"),
+                "The explanation of `{error_code}` must have an example"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(
+            ErrorCode::from_code("E0204"),
+            Some(ErrorCode::UnknownFunction)
+        );
+        assert_eq!(
+            ErrorCode::from_code("e0204"),
+            Some(ErrorCode::UnknownFunction)
+        );
+        assert_eq!(ErrorCode::from_code("E9999"), None);
+        assert_eq!(ErrorCode::from_code("0204"), None);
+    }
+}
@@ -0,0 +1,1135 @@
+//! Formatting of Crane source code.
+//!
+//! The formatter parses a file and re-emits it from the AST in the canonical
+//! style, so formatting a file that is already formatted doesn't change it:
+//!
+//! - Blocks are indented by four spaces.
+//! - Binary operators are surrounded by spaces, as are `->` and the space
+//!   after `:`.
+//! - Parameter lists and struct literals that don't fit on one line are split
+//!   with one parameter or field per line, each followed by a comma.
+//! - Blank lines between statements are kept, but runs of them are collapsed
+//!   into one. Items are separated by a blank line, except for runs of `use`
+//!   items.
+//!
+//! Comments aren't part of the AST, so they are spliced back in by their
+//! position in the source: a comment is written before the first statement,
+//! item, field, or match arm that follows it, or at the end of the line of the
+//! one it trails. Comments anywhere else, like between the arguments of a
+//! call, are moved before the next statement rather than lost.
+
+use crate::ast::{
+    AstArena, Attribute, Expr, ExprKind, FieldDecl, FnDecl, FnReturnTy, InlineModuleDecl, Item,
+    ItemKind, Local, LocalKind, MatchArm, ModuleDecl, PatKind, Path, Span, Stmt, StmtKind, Ty,
+    TyKind, VariantData, Visibility,
+};
+use crate::lexer::token::TokenKind;
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+
+/// The indentation of each level of nesting.
+pub const INDENT: &str = "    ";
+
+/// The width that lines are kept within, where possible.
+pub const MAX_WIDTH: usize = 100;
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The source failed to parse.
+    Parse(ParseError),
+}
+
+/// Formats the given Crane source code.
+pub fn format_source(source: &str) -> Result<String, FormatError> {
+    let arena = AstArena::new();
+    let items = Parser::new(Lexer::new(source), &arena)
+        .parse()
+        .map_err(FormatError::Parse)?;
+
+    let mut formatter = Formatter::new(source);
+
+    for token in Lexer::new(source).flatten() {
+        match token.kind {
+            TokenKind::Comment | TokenKind::DocComment => formatter.comments.push(token.span.get()),
+            TokenKind::CloseBrace => formatter.close_braces.push(token.span.get().start),
+            _ => {}
+        }
+    }
+
+    formatter.items(&items);
+
+    // Any comments after the last item.
+    formatter.comments_at_end(usize::MAX, !items.is_empty());
+
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+
+    Ok(formatter.out)
+}
+
+/// Formats the declaration of the given item, without its attributes or the
+/// body of a function, module, or `impl` block.
+pub fn format_signature(item: &Item) -> String {
+    let mut formatter = Formatter::new("");
+
+    formatter.signature(item);
+
+    formatter.out
+}
+
+/// Formats the given type, like `[Uint8; 4]`.
+pub fn format_ty(ty: &Ty) -> String {
+    let mut formatter = Formatter::new("");
+
+    formatter.ty(ty);
+
+    formatter.out
+}
+
+/// Formats the declaration of the given field, like `x: Uint64`, without its
+/// attributes.
+pub fn format_field(field: &FieldDecl) -> String {
+    let mut formatter = Formatter::new("");
+
+    formatter.field_decl(field);
+
+    formatter.out
+}
+
+struct Formatter<'src> {
+    /// The source being formatted, which blank lines and comments are carried
+    /// over from.
+    source: &'src str,
+
+    /// The spans of the comments in the source, in order.
+    comments: Vec<Span>,
+
+    /// The index of the first comment that hasn't been written yet.
+    next_comment: usize,
+
+    /// The offsets of the `}`s in the source, in order.
+    close_braces: Vec<usize>,
+
+    /// The formatted output.
+    out: String,
+
+    /// The current level of indentation.
+    indent: usize,
+}
+
+impl<'src> Formatter<'src> {
+    fn new(source: &'src str) -> Self {
+        Self {
+            source,
+            comments: Vec::new(),
+            next_comment: 0,
+            close_braces: Vec::new(),
+            out: String::new(),
+            indent: 0,
+        }
+    }
+
+    /// Formats a node on its own, at the current indentation, without
+    /// touching the output.
+    ///
+    /// Comments aren't written, so this must only be used for nodes without
+    /// any.
+    fn render(&self, format: impl FnOnce(&mut Self)) -> String {
+        let mut formatter = Self {
+            indent: self.indent,
+            ..Self::new(self.source)
+        };
+
+        format(&mut formatter);
+
+        formatter.out
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    /// Starts a new line at the current indentation.
+    fn newline(&mut self) {
+        self.out.push('\n');
+
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Returns the column that the output is currently at.
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |index| index + 1);
+
+        self.out[line_start..].chars().count()
+    }
+
+    /// Returns whether the line before the one at `offset` in the source is
+    /// blank.
+    fn follows_blank_line(&self, offset: usize) -> bool {
+        let Some(line_start) = self.source[..offset].rfind('\n') else {
+            return false;
+        };
+
+        let before = &self.source[..line_start];
+        let prev_line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        before[prev_line_start..].trim().is_empty()
+    }
+
+    /// Returns the span of the first comment that hasn't been written yet.
+    fn pending_comment(&self) -> Option<Span> {
+        self.comments.get(self.next_comment).copied()
+    }
+
+    /// Returns whether there's a comment left to write before `offset`.
+    fn has_comment_before(&self, offset: usize) -> bool {
+        self.pending_comment()
+            .is_some_and(|comment| comment.start < offset)
+    }
+
+    /// Returns the offset at which a node starting at `start` starts, along
+    /// with the comments leading up to it.
+    fn leading_start(&self, start: usize) -> usize {
+        match self.pending_comment() {
+            Some(comment) if comment.start < start => comment.start,
+            _ => start,
+        }
+    }
+
+    /// Writes the comment with the given span, as it's written in the source.
+    fn write_comment(&mut self, comment: Span) {
+        self.out
+            .push_str(self.source[comment.start..comment.end].trim_end());
+
+        self.next_comment += 1;
+    }
+
+    /// Writes the comments before `offset`, each on its own line, leaving the
+    /// output at the start of a new line for the node at `offset`.
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.pending_comment() {
+            if comment.start >= offset {
+                break;
+            }
+
+            self.write_comment(comment);
+
+            let next = self.leading_start(offset);
+
+            if self.follows_blank_line(next) {
+                self.out.push('\n');
+            }
+
+            self.newline();
+        }
+    }
+
+    /// Writes the comments before `offset` on the lines after the output, like
+    /// those at the end of a block before its closing `}`.
+    ///
+    /// A blank line before a comment is kept, unless nothing precedes it.
+    fn comments_at_end(&mut self, offset: usize, mut after_node: bool) {
+        while let Some(comment) = self.pending_comment() {
+            if comment.start >= offset {
+                break;
+            }
+
+            if after_node && self.follows_blank_line(comment.start) {
+                self.out.push('\n');
+            }
+
+            if !self.out.is_empty() {
+                self.newline();
+            }
+
+            self.write_comment(comment);
+
+            after_node = true;
+        }
+    }
+
+    /// Writes the comment on the same line after the node ending at `end`, if
+    /// there is one.
+    fn trailing_comment(&mut self, end: usize) {
+        let Some(comment) = self.pending_comment() else {
+            return;
+        };
+
+        let between = self.source.get(end..comment.start).unwrap_or("\n");
+
+        if between
+            .chars()
+            .all(|char| matches!(char, ' ' | '\t' | ',' | ')'))
+        {
+            self.write(" ");
+            self.write_comment(comment);
+        }
+    }
+
+    /// Returns the offset of the first `}` in the source at or after `offset`,
+    /// which is the one closing a block whose contents end at `offset`.
+    fn close_brace(&self, offset: usize) -> usize {
+        let index = self
+            .close_braces
+            .partition_point(|close_brace| *close_brace < offset);
+
+        self.close_braces
+            .get(index)
+            .copied()
+            .unwrap_or(self.source.len())
+    }
+
+    /// Returns the offset in the source at which the given item starts.
+    fn item_start(item: &Item) -> usize {
+        if let Some(attr) = item.attrs.first() {
+            return attr.span.start;
+        }
+
+        match &item.kind {
+            ItemKind::Use(use_tree) => use_tree
+                .prefix
+                .segments
+                .first()
+                .map_or(0, |segment| segment.ident.span.start),
+            _ => item.name.span.start,
+        }
+    }
+
+    /// Formats the given items, returning the offset in the source at which
+    /// the last one ends.
+    fn items(&mut self, items: &[Item]) -> usize {
+        let mut end = 0;
+
+        for (index, item) in items.iter().enumerate() {
+            let start = Self::item_start(item);
+
+            if index > 0 {
+                let is_use = |item: &Item| matches!(item.kind, ItemKind::Use(_));
+
+                if !(is_use(&items[index - 1]) && is_use(item))
+                    || self.follows_blank_line(self.leading_start(start))
+                {
+                    self.out.push('\n');
+                }
+
+                self.newline();
+            }
+
+            self.comments_before(start);
+
+            end = self.item(item);
+
+            self.trailing_comment(end);
+        }
+
+        end
+    }
+
+    /// Formats the given items as the body of a braced block, whose `{` is
+    /// after `open` in the source. Returns the offset of its `}`.
+    fn item_block(&mut self, items: &[Item], open: usize) -> usize {
+        let close = self.close_brace(open);
+
+        if items.is_empty() && !self.has_comment_before(close) {
+            self.write("{}");
+            return close;
+        }
+
+        self.write("{");
+        self.indent += 1;
+        self.newline();
+
+        let end = self.items(items);
+        let close = self.close_brace(end.max(open));
+
+        self.comments_at_end(close, !items.is_empty());
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+
+        close
+    }
+
+    fn attrs(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            self.write("#[");
+            self.write(&attr.name.name);
+
+            if let Some(value) = &attr.value {
+                self.write(&format!(" = \"{value}\""));
+            } else if !attr.args.is_empty() {
+                let args = attr
+                    .args
+                    .iter()
+                    .map(|arg| match &arg.value {
+                        Some(value) => format!("{} = \"{value}\"", arg.name),
+                        None => arg.name.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+
+                self.write(&format!("({})", args.join(", ")));
+            }
+
+            self.write("]");
+            self.newline();
+        }
+    }
+
+    /// Formats an item, returning the offset in the source at which it ends.
+    fn item(&mut self, item: &Item) -> usize {
+        self.attrs(&item.attrs);
+
+        let end = self.signature(item);
+
+        match &item.kind {
+            ItemKind::Fn(fun) => {
+                self.write(" ");
+                self.block(&fun.body, end) + 1
+            }
+            ItemKind::Module(module_decl) => match module_decl.as_ref() {
+                ModuleDecl::Loaded(module, InlineModuleDecl::Yes) => {
+                    self.write(" ");
+                    self.item_block(&module.items, end) + 1
+                }
+                _ => end,
+            },
+            ItemKind::Impl(impl_decl) => {
+                self.write(" ");
+                self.item_block(&impl_decl.items, end) + 1
+            }
+            _ => end,
+        }
+    }
+
+    /// Formats the declaration of an item, leaving out its attributes and the
+    /// body of a function, module, or `impl` block. Returns the offset in the
+    /// source at which the declaration ends.
+    fn signature(&mut self, item: &Item) -> usize {
+        if item.vis == Visibility::Public {
+            self.write("pub ");
+        }
+
+        match &item.kind {
+            ItemKind::Use(use_tree) => {
+                self.write("use ");
+                self.path(&use_tree.prefix);
+
+                use_tree.prefix.span.end
+            }
+            ItemKind::Fn(fun) => {
+                if fun.is_unsafe {
+                    self.write("unsafe ");
+                }
+
+                self.write("fn ");
+                self.fn_decl(&item.name.name, &fun.decl);
+
+                Self::fn_decl_end(item, &fun.decl)
+            }
+            ItemKind::ExternFn(decl) => {
+                self.write("extern fn ");
+                self.fn_decl(&item.name.name, decl);
+
+                Self::fn_decl_end(item, decl)
+            }
+            ItemKind::Struct(struct_decl) => {
+                self.write(&format!("struct {}", item.name));
+
+                match &struct_decl.0 {
+                    VariantData::Tuple(fields) => {
+                        let tys = fields
+                            .iter()
+                            .map(|field| self.render(|formatter| formatter.ty(&field.ty)))
+                            .collect::<Vec<_>>();
+
+                        self.write(&format!("({})", tys.join(", ")));
+
+                        fields
+                            .last()
+                            .map_or(item.name.span.end, |field| field.ty.span.end)
+                    }
+                    VariantData::Struct(fields) => {
+                        self.write(" ");
+                        self.field_decls(fields, item.name.span.end) + 1
+                    }
+                    VariantData::Unit => item.name.span.end,
+                }
+            }
+            ItemKind::Union(union_decl) => {
+                self.write(&format!("union {} ", item.name));
+
+                let mut end = item.name.span.end;
+
+                if union_decl.variants.is_empty() && !self.has_comment_before(self.close_brace(end))
+                {
+                    self.write("{}");
+                    return self.close_brace(end) + 1;
+                }
+
+                self.write("{");
+                self.indent += 1;
+
+                for variant in &union_decl.variants {
+                    self.newline();
+                    self.comments_before(variant.span.start);
+                    self.write(&variant.name.name);
+
+                    end = match &variant.data {
+                        VariantData::Struct(fields) => {
+                            self.write(" ");
+                            self.field_decls(fields, variant.name.span.end) + 1
+                        }
+                        _ => variant.name.span.end,
+                    };
+
+                    self.write(",");
+                    self.trailing_comment(end);
+                }
+
+                let close = self.close_brace(end);
+
+                self.comments_at_end(close, !union_decl.variants.is_empty());
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+
+                close + 1
+            }
+            ItemKind::Module(_) => {
+                self.write(&format!("mod {}", item.name));
+
+                item.name.span.end
+            }
+            ItemKind::Impl(impl_decl) => {
+                match &impl_decl.trait_name {
+                    Some(trait_name) => self.write(&format!("impl {trait_name} for {}", item.name)),
+                    None => self.write(&format!("impl {}", item.name)),
+                }
+
+                item.name.span.end
+            }
+            ItemKind::Const(const_decl) => {
+                self.write(&format!("const {}: ", item.name));
+                self.ty(&const_decl.ty);
+                self.write(" = ");
+                self.expr(const_decl.expr);
+
+                const_decl.expr.span.get().end
+            }
+        }
+    }
+
+    /// Returns the offset in the source at which the declaration of the given
+    /// function ends.
+    fn fn_decl_end(item: &Item, decl: &FnDecl) -> usize {
+        match &decl.return_ty {
+            FnReturnTy::Ty(ty) => ty.span.end,
+            FnReturnTy::Unit => decl
+                .params
+                .last()
+                .map_or(item.name.span.end, |param| param.span.end),
+        }
+    }
+
+    /// Formats the fields of a `struct` or a variant of a `union`, whose `{` is
+    /// after `open` in the source. Returns the offset of the closing `}`.
+    fn field_decls(&mut self, fields: &[FieldDecl], open: usize) -> usize {
+        let close = self.close_brace(fields.last().map_or(open, |field| field.ty.span.end));
+
+        if fields.is_empty() && !self.has_comment_before(close) {
+            self.write("{}");
+            return close;
+        }
+
+        self.write("{");
+        self.indent += 1;
+
+        for field in fields {
+            self.newline();
+            self.comments_before(
+                field
+                    .attrs
+                    .first()
+                    .map_or(field.span.start, |attr| attr.span.start),
+            );
+            self.attrs(&field.attrs);
+            self.field_decl(field);
+            self.write(",");
+            self.trailing_comment(field.ty.span.end);
+        }
+
+        self.comments_at_end(close, !fields.is_empty());
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+
+        close
+    }
+
+    /// Formats the name and type of a field.
+    fn field_decl(&mut self, field: &FieldDecl) {
+        if let Some(name) = &field.name {
+            self.write(&format!("{name}: "));
+        }
+
+        self.ty(&field.ty);
+    }
+
+    /// Formats the name, parameters, and return type of a function.
+    ///
+    /// Parameters that don't fit on one line are written one per line.
+    fn fn_decl(&mut self, name: &str, decl: &FnDecl) {
+        let (params, return_ty) = self.fn_decl_parts(decl);
+
+        let single_line = format!("{name}({}){return_ty}", params.join(", "));
+
+        // The ` {` opening the body follows the declaration.
+        if params.is_empty() || self.column() + single_line.len() + 2 <= MAX_WIDTH {
+            self.write(&single_line);
+            return;
+        }
+
+        self.write(&format!("{name}("));
+        self.indent += 1;
+
+        for param in params {
+            self.newline();
+            self.write(&format!("{param},"));
+        }
+
+        self.indent -= 1;
+        self.newline();
+        self.write(&format!("){return_ty}"));
+    }
+
+    /// Returns the formatted parameters of a function, along with its return
+    /// type preceded by ` -> `, if it has one.
+    fn fn_decl_parts(&self, decl: &FnDecl) -> (Vec<String>, String) {
+        let params = decl
+            .params
+            .iter()
+            .map(|param| {
+                let ty = self.render(|formatter| formatter.ty(&param.ty));
+
+                if param.name.name.is_empty() {
+                    ty
+                } else {
+                    format!("{}: {ty}", param.name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let return_ty = match &decl.return_ty {
+            FnReturnTy::Ty(ty) => format!(" -> {}", self.render(|formatter| formatter.ty(ty))),
+            FnReturnTy::Unit => String::new(),
+        };
+
+        (params, return_ty)
+    }
+
+    fn path(&mut self, path: &Path) {
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.name.as_str())
+            .collect::<Vec<_>>();
+
+        self.write(&segments.join("::"));
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match &ty.kind {
+            TyKind::Path(path) => self.path(path),
+            TyKind::Fn(fn_ty) => {
+                let (params, return_ty) = self.fn_decl_parts(&fn_ty.decl);
+
+                self.write(&format!("Fn({}){return_ty}", params.join(", ")));
+            }
+            TyKind::Array { elem, len } => {
+                self.write("[");
+                self.ty(elem);
+                self.write("; ");
+                self.expr(len);
+                self.write("]");
+            }
+        }
+    }
+
+    /// Formats a brace-delimited block of statements, whose `{` is after
+    /// `open` in the source. Returns the offset of its `}`.
+    fn block(&mut self, stmts: &[Stmt], open: usize) -> usize {
+        let close = self.close_brace(stmts.last().map_or(open, |stmt| stmt.span.get().end));
+
+        if stmts.is_empty() && !self.has_comment_before(close) {
+            self.write("{}");
+            return close;
+        }
+
+        self.write("{");
+        self.indent += 1;
+
+        for (index, stmt) in stmts.iter().enumerate() {
+            if index > 0 && self.follows_blank_line(self.leading_start(stmt.span.get().start)) {
+                self.out.push('\n');
+            }
+
+            self.newline();
+            self.comments_before(stmt.span.get().start);
+            self.stmt(stmt);
+            self.trailing_comment(stmt.span.get().end);
+        }
+
+        self.comments_at_end(close, !stmts.is_empty());
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+
+        close
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Local(local) => self.local(local),
+            StmtKind::Item(item) => {
+                self.item(item);
+            }
+            StmtKind::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn local(&mut self, local: &Local) {
+        self.write("let ");
+
+        match &local.destructure {
+            Some(path) => {
+                self.path(path);
+                self.write(&format!("({})", local.name));
+            }
+            None => self.write(&local.name.name),
+        }
+
+        if let Some(ty) = &local.ty {
+            self.write(": ");
+            self.ty(ty);
+        }
+
+        if let LocalKind::Init(init) = &local.kind {
+            self.write(" = ");
+            self.expr(init);
+        }
+    }
+
+    /// Formats a comma-separated list of expressions.
+    fn exprs(&mut self, exprs: &[&mut Expr]) {
+        for (index, expr) in exprs.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(literal) => self.write(&literal.value),
+            ExprKind::Variable(path) => self.path(path),
+            ExprKind::Call { fun, args } => {
+                self.expr(fun);
+                self.write("(");
+                self.exprs(args);
+                self.write(")");
+            }
+            ExprKind::Struct(struct_expr) => {
+                self.path(&struct_expr.path);
+                self.write(" ");
+
+                let close = self.close_brace(
+                    struct_expr
+                        .fields
+                        .last()
+                        .map_or(struct_expr.path.span.end, |field| field.span.end),
+                );
+
+                if struct_expr.fields.is_empty() && !self.has_comment_before(close) {
+                    self.write("{}");
+                    return;
+                }
+
+                let fields = struct_expr
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let expr = self.render(|formatter| formatter.expr(field.expr));
+
+                        format!("{}: {expr}", field.name)
+                    })
+                    .collect::<Vec<_>>();
+
+                let single_line = format!("{{ {} }}", fields.join(", "));
+
+                if !single_line.contains('\n')
+                    && self.column() + single_line.len() <= MAX_WIDTH
+                    && !self.has_comment_before(close)
+                {
+                    self.write(&single_line);
+                    return;
+                }
+
+                self.write("{");
+                self.indent += 1;
+
+                for field in &struct_expr.fields {
+                    self.newline();
+                    self.comments_before(field.span.start);
+                    self.write(&format!("{}: ", field.name));
+                    self.expr(field.expr);
+                    self.write(",");
+                    self.trailing_comment(field.span.end);
+                }
+
+                self.comments_at_end(close, !struct_expr.fields.is_empty());
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.expr(lhs);
+                self.write(&format!(" {op} "));
+                self.expr(rhs);
+            }
+            ExprKind::Cast { expr, ty } => {
+                self.expr(expr);
+                self.write(" as ");
+                self.ty(ty);
+            }
+            ExprKind::Array(elems) => {
+                self.write("[");
+                self.exprs(elems);
+                self.write("]");
+            }
+            ExprKind::Index { expr, index } => {
+                self.expr(expr);
+                self.write("[");
+                self.expr(index);
+                self.write("]");
+            }
+            ExprKind::Assign { target, value } => {
+                self.expr(target);
+                self.write(" = ");
+                self.expr(value);
+            }
+            ExprKind::While { cond, body } => {
+                self.write("while ");
+                self.expr(cond);
+                self.write(" ");
+                self.block(body, cond.span.get().end);
+            }
+            ExprKind::TypeOf(expr) => {
+                self.write("typeof(");
+                self.expr(expr);
+                self.write(")");
+            }
+            ExprKind::Asm(lines) => {
+                let lines = lines
+                    .iter()
+                    .map(|line| format!("\"{line}\""))
+                    .collect::<Vec<_>>();
+
+                self.write(&format!("asm({})", lines.join(", ")));
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.write("if ");
+                self.expr(cond);
+                self.write(" ");
+
+                let then_close = self.block(then_branch, cond.span.get().end);
+
+                match else_branch.as_deref() {
+                    // An `else` branch holding only another `if` is an
+                    // `else if`.
+                    Some(
+                        [Stmt {
+                            kind: StmtKind::Expr(else_if),
+                            ..
+                        }],
+                    ) if matches!(else_if.kind, ExprKind::If { .. }) => {
+                        self.write(" else ");
+                        self.expr(else_if);
+                    }
+                    Some(else_branch) => {
+                        self.write(" else ");
+                        self.block(else_branch, then_close + 1);
+                    }
+                    None => {}
+                }
+            }
+            ExprKind::For(for_expr) => {
+                self.write(&format!("for {} in ", for_expr.binding));
+                self.expr(for_expr.start);
+                self.write("..");
+                self.expr(for_expr.end);
+                self.write(" ");
+                self.block(&for_expr.body, for_expr.end.span.get().end);
+            }
+            ExprKind::Comptime(body) => {
+                self.write("comptime ");
+                self.block(body, expr.span.get().start);
+            }
+            ExprKind::Unsafe(body) => {
+                self.write("unsafe ");
+                self.block(body, expr.span.get().start);
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.write("match ");
+                self.expr(scrutinee);
+                self.write(" {");
+                self.indent += 1;
+
+                let close = self.close_brace(
+                    arms.last()
+                        .map_or(scrutinee.span.get().end, |arm| arm.span.end),
+                );
+
+                for arm in arms {
+                    self.newline();
+                    self.comments_before(arm.span.start);
+                    self.match_arm(arm);
+                    self.write(",");
+                    self.trailing_comment(arm.span.end);
+                }
+
+                self.comments_at_end(close, !arms.is_empty());
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            ExprKind::Field(field_expr) => {
+                self.expr(field_expr.expr);
+                self.write(&format!(".{}", field_expr.name));
+            }
+            ExprKind::MethodCall(method_call) => {
+                self.expr(method_call.receiver);
+                self.write(&format!(".{}(", method_call.name));
+                self.exprs(&method_call.args);
+                self.write(")");
+            }
+            ExprKind::Macro(macro_call) => {
+                self.write(&format!("{}!(", macro_call.name));
+                self.exprs(&macro_call.args);
+                self.write(")");
+            }
+        }
+    }
+
+    fn match_arm(&mut self, arm: &MatchArm) {
+        match &arm.pat.kind {
+            PatKind::Wild => self.write("_"),
+            PatKind::Literal(literal) => self.write(&literal.value),
+        }
+
+        self.write(" => ");
+
+        // An arm whose body is a single expression is written without braces.
+        match arm.body.as_slice() {
+            [Stmt {
+                kind: StmtKind::Expr(expr),
+                ..
+            }] => self.expr(expr),
+            body => {
+                self.block(body, arm.pat.span.end);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the text of the comments in the given source, in order.
+    fn comments(source: &str) -> Vec<String> {
+        Lexer::new(source)
+            .flatten()
+            .filter(|token| matches!(token.kind, TokenKind::Comment | TokenKind::DocComment))
+            .map(|token| token.lexeme.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+
+        // The examples made up of multiple files are in directories of their own.
+        let mut paths = std::fs::read_dir(examples)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .flat_map(|path| match std::fs::read_dir(&path) {
+                Ok(entries) => entries.map(|entry| entry.unwrap().path()).collect(),
+                Err(_) => vec![path],
+            })
+            .collect::<Vec<_>>();
+
+        insta::glob!("snapshot_inputs/*.crane", |path| paths
+            .push(path.to_path_buf()));
+
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+
+            let formatted = format_source(&source)
+                .unwrap_or_else(|err| panic!("Failed to format `{}`: {err:?}", path.display()));
+
+            assert_eq!(
+                format_source(&formatted).unwrap(),
+                formatted,
+                "Formatting `{}` twice changed it",
+                path.display()
+            );
+            assert_eq!(
+                comments(&formatted),
+                comments(&source),
+                "Formatting `{}` lost a comment",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_source() {
+        let source = r#"
+use std::io::println
+use std::int::int_to_string
+struct Point { x: Uint64, y: Uint64 }
+union Shape { Empty, Square { side: Uint64 } }
+
+
+pub fn main() {
+        let point = Point { x: 1+2*3, y: 4 }
+  let label = Label { text: "a rather long piece of text to wrap", width: 100000, height: 200000, depth: 3 }
+
+
+    if point.x==7 { println("seven") } else if point.y>4 { println("big") } else {}
+    match point.y { 4 => println("four"), _ => { println("other") } }
+}
+"#;
+
+        insta::assert_snapshot!(format_source(source).unwrap(), @r###"
+        use std::io::println
+        use std::int::int_to_string
+
+        struct Point {
+            x: Uint64,
+            y: Uint64,
+        }
+
+        union Shape {
+            Empty,
+            Square {
+                side: Uint64,
+            },
+        }
+
+        pub fn main() {
+            let point = Point { x: 1 + 2 * 3, y: 4 }
+            let label = Label {
+                text: "a rather long piece of text to wrap",
+                width: 100000,
+                height: 200000,
+                depth: 3,
+            }
+
+            if point.x == 7 {
+                println("seven")
+            } else if point.y > 4 {
+                println("big")
+            } else {}
+            match point.y {
+                4 => println("four"),
+                _ => println("other"),
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_format_keeps_comments() {
+        let source = r#"
+// The entry point.
+use std::io::println
+
+
+/// A point.
+struct Point {
+  // The horizontal position.
+  x: Uint64, // In pixels.
+
+  y: Uint64,
+  // No more fields.
+}
+
+fn main() {
+  // Nothing to see here.
+}
+
+fn greet(first_name: String, last_name: String, greeting: String, punctuation: String, times: Uint64) {
+  let point = Point {
+    x: 1, // One.
+    y: 2,
+  }
+  match point.x {
+    // The origin.
+    0 => println("origin"),
+    _ => {} // Anywhere else.
+  }
+
+  // Done.
+}
+// The end.
+"#;
+
+        insta::assert_snapshot!(format_source(source).unwrap(), @r###"
+        // The entry point.
+        use std::io::println
+
+        /// A point.
+        struct Point {
+            // The horizontal position.
+            x: Uint64, // In pixels.
+            y: Uint64,
+            // No more fields.
+        }
+
+        fn main() {
+            // Nothing to see here.
+        }
+
+        fn greet(
+            first_name: String,
+            last_name: String,
+            greeting: String,
+            punctuation: String,
+            times: Uint64,
+        ) {
+            let point = Point {
+                x: 1, // One.
+                y: 2,
+            }
+            match point.x {
+                // The origin.
+                0 => println("origin"),
+                _ => {}, // Anywhere else.
+            }
+
+            // Done.
+        }
+        // The end.
+        "###);
+    }
+}
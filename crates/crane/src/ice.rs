@@ -0,0 +1,216 @@
+//! Reporting internal compiler errors (ICEs): panics in the compiler, which are
+//! always bugs in the compiler rather than in the code being compiled.
+//!
+//! Once [`install_hook`] has replaced the panic hook, a panic is recorded as an
+//! [`Ice`], along with the phase of the compiler it happened in and the file
+//! being compiled, instead of being printed. The caller catches the panic with
+//! [`std::panic::catch_unwind`] and then reports the [`Ice`] from [`take`].
+//!
+//! The phase is tracked by the [`PhaseLayer`], from the spans that the phases
+//! run in for [`timings`].
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::PanicHookInfo;
+use std::sync::Mutex;
+
+use tracing::span::Id;
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::timings;
+
+/// Where to report ICEs.
+pub const ISSUES_URL: &str = "https://github.com/crane-lang/crane/issues";
+
+/// The first panic since [`install_hook`], if any.
+static ICE: Mutex<Option<Ice>> = Mutex::new(None);
+
+/// The file being compiled, as set by [`set_input_file`].
+static INPUT_FILE: Mutex<Option<String>> = Mutex::new(None);
+
+thread_local! {
+    /// The phases of the compiler that the current thread is in, innermost
+    /// last.
+    static PHASES: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An internal compiler error.
+#[derive(Debug)]
+pub struct Ice {
+    /// The message that the compiler panicked with.
+    pub message: String,
+
+    /// Where in the compiler's source it panicked, like `src/typer.rs:10:5`.
+    pub location: Option<String>,
+
+    /// The phase of the compiler it panicked in, like `type_check`.
+    pub phase: Option<&'static str>,
+
+    /// The file being compiled.
+    pub input_file: Option<String>,
+
+    /// The backtrace of the panic, which is only captured when enabled with
+    /// `RUST_BACKTRACE`.
+    pub backtrace: Backtrace,
+}
+
+impl fmt::Display for Ice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Error: Internal compiler error: {}", self.message)?;
+
+        if let Some(location) = &self.location {
+            writeln!(f, "  at: {location}")?;
+        }
+
+        writeln!(f, "  phase: {}", self.phase.unwrap_or("unknown"))?;
+        writeln!(
+            f,
+            "  input: {}",
+            self.input_file.as_deref().unwrap_or("unknown")
+        )?;
+
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n{}", self.backtrace)?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "This is a bug in the Crane compiler, rather than in your code."
+        )?;
+        write!(
+            f,
+            "Please file an issue at {ISSUES_URL} with the input that caused it."
+        )
+    }
+}
+
+/// Replaces the panic hook with one that records panics as [`Ice`]s, rather
+/// than printing them.
+pub fn install_hook() {
+    std::panic::set_hook(Box::new(record));
+}
+
+/// Returns the [`Ice`] recorded since [`install_hook`], if the compiler has
+/// panicked.
+pub fn take() -> Option<Ice> {
+    ICE.lock().unwrap_or_else(|err| err.into_inner()).take()
+}
+
+/// Sets the file being compiled, for reporting an [`Ice`].
+pub fn set_input_file(path: &str) {
+    *INPUT_FILE.lock().unwrap_or_else(|err| err.into_inner()) = Some(path.to_string());
+}
+
+fn record(info: &PanicHookInfo<'_>) {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "Box<dyn Any>".to_string(),
+        },
+    };
+
+    let mut ice = ICE.lock().unwrap_or_else(|err| err.into_inner());
+
+    // A panic in another thread is resumed in the thread that joins it, which
+    // would record it a second time.
+    if ice.is_some() {
+        return;
+    }
+
+    *ice = Some(Ice {
+        message,
+        location: info.location().map(ToString::to_string),
+        phase: PHASES.with(|phases| phases.borrow().last().copied()),
+        input_file: INPUT_FILE
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone(),
+        backtrace: Backtrace::capture(),
+    });
+}
+
+/// A [`Layer`] that tracks the phase of the compiler that each thread is in,
+/// from the spans that the [`timings`] are measured with.
+pub struct PhaseLayer;
+
+impl<S> Layer<S> for PhaseLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if span.metadata().target() == timings::TARGET {
+                PHASES.with(|phases| phases.borrow_mut().push(span.metadata().name()));
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if span.metadata().target() == timings::TARGET {
+                PHASES.with(|phases| phases.borrow_mut().pop());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_phase_layer() {
+        let subscriber = tracing_subscriber::registry().with(PhaseLayer);
+
+        let current_phase = || PHASES.with(|phases| phases.borrow().last().copied());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let parse = tracing::info_span!(target: timings::TARGET, "parse").entered();
+
+            assert_eq!(current_phase(), Some("parse"));
+
+            {
+                // Spans from elsewhere aren't phases.
+                let _span = tracing::info_span!("lower").entered();
+
+                assert_eq!(current_phase(), Some("parse"));
+            }
+
+            drop(parse);
+
+            assert_eq!(current_phase(), None);
+        });
+    }
+
+    #[test]
+    fn test_display() {
+        let ice = Ice {
+            message: "not yet implemented".to_string(),
+            location: Some("src/typer.rs:10:5".to_string()),
+            phase: Some("type_check"),
+            input_file: Some("main.crane".to_string()),
+            backtrace: Backtrace::disabled(),
+        };
+
+        assert_eq!(
+            ice.to_string(),
+            format!(
+                "Error: Internal compiler error: not yet implemented
+  at: src/typer.rs:10:5
+  phase: type_check
+  input: main.crane
+
+This is a bug in the Crane compiler, rather than in your code.
+Please file an issue at {ISSUES_URL} with the input that caused it."
+            )
+        );
+    }
+}
@@ -6,6 +6,7 @@ pub use error::*;
 
 use logos::Logos;
 
+use crate::ast::Span;
 use crate::lexer::token::{Token, TokenKind};
 
 pub struct Lexer<'src> {
@@ -26,19 +27,55 @@ impl<'src> Iterator for Lexer<'src> {
     fn next(&mut self) -> Option<Self::Item> {
         let kind = self.lexer.next()?;
         let lexeme = self.lexer.slice();
-        let span = self.lexer.span().into();
+        let span = Span::from(self.lexer.span());
 
         Some(
             kind.map(|kind| Token {
                 kind,
                 lexeme: lexeme.into(),
-                span,
+                span: span.into(),
             })
             .map_err(|kind| LexError { kind, span }),
         )
     }
 }
 
+/// Lexes all of the input, returning every token, including comments, and
+/// every lex error, in source order.
+///
+/// Unlike the parser, which stops at the first lex error, this keeps going, so
+/// it shows everything the lexer sees.
+pub fn tokenize(input: &str) -> Vec<Result<Token, LexError>> {
+    Lexer::new(input).collect()
+}
+
+/// Prints every token in the input as `Kind lexeme @ start..end`, one per
+/// line, with lex errors in between, for `--emit=tokens`.
+///
+/// The output ends with the `Eof` token that the parser adds.
+pub fn print_tokens(input: &str) -> String {
+    let mut out = String::new();
+
+    for token in tokenize(input) {
+        let line = match token {
+            Ok(token) => format!("{:?} {} @ {}\n", token.kind, token.lexeme, token.span),
+            Err(err) => format!(
+                "Error {} @ {}: {err}\n",
+                &input[std::ops::Range::from(err.span)],
+                err.span
+            ),
+        };
+
+        out.push_str(&line);
+    }
+
+    let eof = Span::new(input.len(), input.len());
+
+    out.push_str(&format!("{:?} @ {eof}\n", TokenKind::Eof));
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,11 +111,33 @@ mod tests {
         check("}", TokenKind::CloseBrace)
     }
 
+    #[test]
+    fn lex_bang() {
+        check("!", TokenKind::Bang);
+        check("!=", TokenKind::BangEqual)
+    }
+
+    #[test]
+    fn lex_ampersand_ampersand() {
+        check("&&", TokenKind::AmpersandAmpersand)
+    }
+
     #[test]
     fn lex_comment() {
         check("// This is a comment.", TokenKind::Comment)
     }
 
+    #[test]
+    fn lex_doc_comment() {
+        check("/// This is a doc comment.", TokenKind::DocComment);
+        check("///", TokenKind::DocComment)
+    }
+
+    #[test]
+    fn lex_four_slashes_as_a_comment() {
+        check("//// This is a comment.", TokenKind::Comment)
+    }
+
     #[test]
     fn lex_fn_declaration() {
         let mut lex = lex("fn foo() {}");
@@ -118,4 +177,13 @@ mod tests {
             insta::assert_yaml_snapshot!(lexer.into_iter().collect::<Vec<_>>());
         })
     }
+
+    #[test]
+    fn test_print_tokens() {
+        insta::glob!("snapshot_inputs/tokens/*.crane", |path| {
+            let source = std::fs::read_to_string(path).unwrap();
+
+            insta::assert_snapshot!(print_tokens(&source));
+        })
+    }
 }
@@ -27,6 +27,6 @@ impl std::fmt::Display for LexError {
 #[derive(Debug, Error, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub enum LexErrorKind {
     #[default]
-    #[error("unknown")]
+    #[error("Unrecognized character.")]
     Unknown,
 }
@@ -2,7 +2,7 @@ use logos::Logos;
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 
-use crate::ast::{Ident, Span, DUMMY_SPAN};
+use crate::ast::{Ident, SpanId, Symbol, DUMMY_SPAN_ID};
 use crate::lexer::LexErrorKind;
 
 #[derive(Logos, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
@@ -16,6 +16,14 @@ pub enum TokenKind {
     #[token(")")]
     CloseParen,
 
+    /// `[`
+    #[token("[")]
+    OpenBracket,
+
+    /// `]`
+    #[token("]")]
+    CloseBracket,
+
     /// `{`
     #[token("{")]
     OpenBrace,
@@ -24,10 +32,18 @@ pub enum TokenKind {
     #[token("}")]
     CloseBrace,
 
+    /// `#`
+    #[token("#")]
+    Pound,
+
     /// `,`
     #[token(",")]
     Comma,
 
+    /// `;`
+    #[token(";")]
+    Semicolon,
+
     /// `:`
     #[token(":")]
     Colon,
@@ -36,6 +52,14 @@ pub enum TokenKind {
     #[token("::")]
     ColonColon,
 
+    /// `.`
+    #[token(".")]
+    Dot,
+
+    /// `..`
+    #[token("..")]
+    DotDot,
+
     /// `=`
     #[token("=")]
     Equal,
@@ -44,6 +68,62 @@ pub enum TokenKind {
     #[token("->")]
     RightArrow,
 
+    /// `=>`
+    #[token("=>")]
+    FatArrow,
+
+    /// `+`
+    #[token("+")]
+    Plus,
+
+    /// `-`
+    #[token("-")]
+    Minus,
+
+    /// `*`
+    #[token("*")]
+    Star,
+
+    /// `/`
+    #[token("/")]
+    Slash,
+
+    /// `%`
+    #[token("%")]
+    Percent,
+
+    /// `==`
+    #[token("==")]
+    EqualEqual,
+
+    /// `!`
+    #[token("!")]
+    Bang,
+
+    /// `!=`
+    #[token("!=")]
+    BangEqual,
+
+    /// `<`
+    #[token("<")]
+    Less,
+
+    /// `<=`
+    #[token("<=")]
+    LessEqual,
+
+    /// `>`
+    #[token(">")]
+    Greater,
+
+    /// `>=`
+    #[token(">=")]
+    GreaterEqual,
+
+    /// `&&`
+    #[token("&&")]
+    AmpersandAmpersand,
+
     /// An identifier.
     #[regex(r"[A-Za-z_][A-Za-z0-9_]*")]
     Ident,
@@ -56,12 +136,19 @@ pub enum TokenKind {
     #[regex(r#"[\d]+"#)]
     Integer,
 
+    /// A floating-point literal.
+    #[regex(r#"[\d]+\.[\d]+"#)]
+    Float,
+
     /// A comment.
     #[regex(r"//.*")]
     Comment,
 
     /// A documentation comment.
-    #[regex(r"///.*")]
+    ///
+    /// Like in Rust, a comment starting with four or more slashes is a plain
+    /// comment.
+    #[regex(r"///([^/\n].*)?")]
     DocComment,
 
     /// Any sequence of whitespace characters.
@@ -77,7 +164,7 @@ pub enum TokenKind {
 pub struct Token {
     pub kind: TokenKind,
     pub lexeme: SmolStr,
-    pub span: Span,
+    pub span: SpanId,
 }
 
 impl Token {
@@ -86,21 +173,21 @@ impl Token {
         Self {
             kind: TokenKind::Comment,
             lexeme: SmolStr::default(),
-            span: DUMMY_SPAN,
+            span: DUMMY_SPAN_ID,
         }
     }
 
     /// Returns whether this token is the given keyword.
     pub fn is_keyword(&self, keyword: Ident) -> bool {
-        self.kind == TokenKind::Ident && self.lexeme == keyword.name
+        self.kind == TokenKind::Ident && keyword.name == self.lexeme.as_str()
     }
 
     /// Returns the [`Ident`] for this token, if it is one.
     pub fn ident(&self) -> Option<Ident> {
         match self.kind {
             TokenKind::Ident => Some(Ident {
-                name: self.lexeme.clone(),
-                span: self.span,
+                name: Symbol::intern(&self.lexeme),
+                span: self.span.get(),
             }),
             _ => None,
         }
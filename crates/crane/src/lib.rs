@@ -0,0 +1,24 @@
+pub mod ast;
+pub mod backend;
+pub mod build_script;
+pub mod cfg;
+pub mod compiler;
+pub mod diagnostic;
+pub mod doc;
+pub mod error;
+pub mod error_codes;
+pub mod formatter;
+pub mod ice;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod macro_expander;
+pub mod manifest;
+pub mod parser;
+pub mod repl;
+pub mod scaffold;
+pub mod symbols;
+pub mod test_harness;
+pub mod timings;
+pub mod typer;
+pub mod watch;
@@ -0,0 +1,263 @@
+//! Lints, the checks that report [`Warning`]s, and the levels they're reported
+//! at.
+//!
+//! Most lints are found while type checking. The style lints in [`style`]
+//! check the AST instead, and are only run by `crane lint`, through the
+//! framework in [`pass`].
+//!
+//! Every lint has a default level, listed in [`LINTS`]. The `-A`, `-W` and
+//! `-D` flags set the level of a lint, by its name, or of every lint, with
+//! `warnings`:
+//!
+//! ```text
+//! crane build -D warnings -A unreachable-code main.crane
+//! ```
+//!
+//! Lint names are written with `-` in flags and with `_` in `#[allow]`
+//! attributes, and either is accepted in flags. The level of a lint is
+//! resolved as follows:
+//!
+//! 1. A lint that is allowed with `#[allow]` on an enclosing item is never
+//!    reported there, whatever its level.
+//! 2. Otherwise, a flag naming the lint sets its level.
+//! 3. Otherwise, a flag naming `warnings` sets its level.
+//! 4. Otherwise, it has its default level.
+//!
+//! When the same name is given more than one level, the most severe of them
+//! applies, whatever the order of the flags.
+//!
+//! [`Warning`]: crate::typer::Warning
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+pub mod pass;
+pub mod style;
+
+/// A lint, which flags and `#[allow]` name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// The name of the lint, as `#[allow]` writes it.
+    pub name: &'static str,
+
+    /// The level of the lint when no flag sets it.
+    pub default_level: LintLevel,
+}
+
+impl Lint {
+    const fn warn(name: &'static str) -> Self {
+        Self {
+            name,
+            default_level: LintLevel::Warn,
+        }
+    }
+}
+
+/// The lints, in order of their names.
+pub const LINTS: [Lint; 7] = [
+    Lint::warn("deprecated"),
+    Lint::warn("empty_fn"),
+    Lint::warn("non_pascal_case_types"),
+    Lint::warn("non_snake_case"),
+    Lint::warn("too_many_statements"),
+    Lint::warn("unreachable_code"),
+    Lint::warn("unused_must_use"),
+];
+
+/// The name of the group of every lint.
+pub const WARNINGS: &str = "warnings";
+
+/// The level a lint is reported at.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum LintLevel {
+    /// The lint isn't reported.
+    Allow,
+
+    /// The lint is reported as a warning.
+    Warn,
+
+    /// The lint is reported as an error, which fails compilation.
+    Deny,
+}
+
+impl LintLevel {
+    /// Returns the flag that sets this level, like `-D`.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Self::Allow => "-A",
+            Self::Warn => "-W",
+            Self::Deny => "-D",
+        }
+    }
+}
+
+/// A flag named a lint that doesn't exist.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Unknown lint `{name}` in `{} {name}`. Expected `{WARNINGS}` or one of: {}.", .level.flag(), lint_flag_names().join(", "))]
+pub struct UnknownLint {
+    pub name: String,
+    pub level: LintLevel,
+}
+
+/// The levels of the lints, as set by the `-A`, `-W` and `-D` flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintLevels {
+    /// The level set for `warnings`, if any.
+    group: Option<LintLevel>,
+
+    /// The levels set for individual lints.
+    lints: HashMap<&'static str, LintLevel>,
+}
+
+impl LintLevels {
+    /// Sets the level of the lint with the given name, or of every lint for
+    /// `warnings`.
+    ///
+    /// A level that is less severe than one already set is ignored.
+    pub fn set(&mut self, name: &str, level: LintLevel) -> Result<(), UnknownLint> {
+        let lint_name = name.replace('-', "_");
+
+        if lint_name == WARNINGS {
+            self.group = self.group.max(Some(level));
+
+            return Ok(());
+        }
+
+        let Some(lint) = find_lint(&lint_name) else {
+            return Err(UnknownLint {
+                name: name.to_string(),
+                level,
+            });
+        };
+
+        let lint_level = self.lints.entry(lint.name).or_insert(level);
+        *lint_level = (*lint_level).max(level);
+
+        Ok(())
+    }
+
+    /// Returns the level of the lint with the given name.
+    pub fn level(&self, lint: &str) -> LintLevel {
+        self.lints
+            .get(lint)
+            .copied()
+            .or(self.group)
+            .unwrap_or_else(|| find_lint(lint).map_or(LintLevel::Warn, |lint| lint.default_level))
+    }
+
+    /// Returns the flag that set the level of the lint with the given name,
+    /// like `-D warnings`, if one did.
+    pub fn flag(&self, lint: &str) -> Option<String> {
+        match (self.lints.get(lint), self.group) {
+            (Some(level), _) => Some(format!("{} {}", level.flag(), lint.replace('_', "-"))),
+            (None, Some(level)) => Some(format!("{} {WARNINGS}", level.flag())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Returns the lint with the given name, as `#[allow]` writes it, if there is
+/// one.
+pub fn find_lint(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().find(|lint| lint.name == name)
+}
+
+/// Returns the names of the lints as `#[allow]` writes them.
+pub fn lint_names() -> Vec<&'static str> {
+    LINTS.iter().map(|lint| lint.name).collect()
+}
+
+/// Returns the names of the lints as they're written in flags.
+fn lint_flag_names() -> Vec<String> {
+    LINTS
+        .iter()
+        .map(|lint| lint.name.replace('_', "-"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_levels(flags: &[(&str, LintLevel)]) -> LintLevels {
+        let mut levels = LintLevels::default();
+
+        for (name, level) in flags {
+            levels.set(name, *level).unwrap();
+        }
+
+        levels
+    }
+
+    #[test]
+    fn test_lints_are_warnings_by_default() {
+        let levels = LintLevels::default();
+
+        for lint in &LINTS {
+            assert_eq!(levels.level(lint.name), lint.default_level);
+            assert_eq!(levels.flag(lint.name), None);
+        }
+    }
+
+    #[test]
+    fn test_lint_flags_override_warnings() {
+        for flags in [
+            [
+                (WARNINGS, LintLevel::Deny),
+                ("unreachable-code", LintLevel::Allow),
+            ],
+            [
+                ("unreachable-code", LintLevel::Allow),
+                (WARNINGS, LintLevel::Deny),
+            ],
+        ] {
+            let levels = lint_levels(&flags);
+
+            assert_eq!(levels.level("unreachable_code"), LintLevel::Allow);
+            assert_eq!(levels.level("deprecated"), LintLevel::Deny);
+            assert_eq!(
+                levels.flag("unreachable_code").as_deref(),
+                Some("-A unreachable-code")
+            );
+            assert_eq!(levels.flag("deprecated").as_deref(), Some("-D warnings"));
+        }
+    }
+
+    #[test]
+    fn test_most_severe_level_wins() {
+        let levels = lint_levels(&[
+            ("deprecated", LintLevel::Deny),
+            ("deprecated", LintLevel::Allow),
+            (WARNINGS, LintLevel::Warn),
+            (WARNINGS, LintLevel::Allow),
+        ]);
+
+        assert_eq!(levels.level("deprecated"), LintLevel::Deny);
+        assert_eq!(levels.level("unused_must_use"), LintLevel::Warn);
+    }
+
+    #[test]
+    fn test_lint_names_accept_either_separator() {
+        let levels = lint_levels(&[
+            ("unused-must-use", LintLevel::Allow),
+            ("unreachable_code", LintLevel::Deny),
+        ]);
+
+        assert_eq!(levels.level("unused_must_use"), LintLevel::Allow);
+        assert_eq!(levels.level("unreachable_code"), LintLevel::Deny);
+    }
+
+    #[test]
+    fn test_unknown_lints() {
+        let mut levels = LintLevels::default();
+
+        let err = levels.set("dead-code", LintLevel::Allow).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown lint `dead-code` in `-A dead-code`. Expected `warnings` or one of: deprecated, empty-fn, non-pascal-case-types, non-snake-case, too-many-statements, unreachable-code, unused-must-use."
+        );
+        assert_eq!(levels, LintLevels::default());
+    }
+}
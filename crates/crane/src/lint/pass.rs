@@ -0,0 +1,218 @@
+//! The framework for the lints that check the AST of a package, before it's
+//! type checked.
+//!
+//! Each of these lints is a [`LintPass`], which is shown every item, statement
+//! and expression of the package in turn, and reports what it finds to the
+//! [`LintContext`]. Adding one takes a [`Lint`] in [`LINTS`], a [`Warning`]
+//! for it to report, and an entry in [`AST_LINTS`].
+//!
+//! [`Lint`]: super::Lint
+//! [`LINTS`]: super::LINTS
+
+use smol_str::SmolStr;
+
+use crate::ast::visitor::{self, Visitor};
+use crate::ast::{Expr, Item, ItemKind, Stmt, Symbol};
+use crate::lint::style::{EmptyFn, NonPascalCaseTypes, NonSnakeCase, TooManyStatements};
+use crate::typer::Warning;
+
+/// The lints that check the AST, which `crane lint` runs.
+pub const AST_LINTS: [&dyn LintPass; 4] = [
+    &EmptyFn,
+    &NonPascalCaseTypes,
+    &NonSnakeCase,
+    &TooManyStatements {
+        max: TooManyStatements::DEFAULT_MAX,
+    },
+];
+
+/// A lint that checks the AST.
+///
+/// Every hook does nothing by default, so a lint only implements those for the
+/// nodes it checks.
+pub trait LintPass: Sync {
+    /// Returns the name of the lint, as listed in [`LINTS`].
+    ///
+    /// [`LINTS`]: super::LINTS
+    fn name(&self) -> &'static str;
+
+    /// Checks an item, before the items, statements and expressions in it.
+    fn check_item(&self, _cx: &mut LintContext, _item: &Item) {}
+
+    /// Checks a statement, before the items, statements and expressions in it.
+    fn check_stmt(&self, _cx: &mut LintContext, _stmt: &Stmt) {}
+
+    /// Checks an expression, before the expressions in it.
+    fn check_expr(&self, _cx: &mut LintContext, _expr: &Expr) {}
+}
+
+/// Where the lints are being run, which they report their warnings to.
+#[derive(Default)]
+pub struct LintContext {
+    /// The path of the module being checked.
+    module: Vec<SmolStr>,
+
+    /// The lints allowed by the `#[allow]` attributes on the enclosing items.
+    allowed_lints: Vec<Symbol>,
+
+    /// The warnings reported, along with the path of the module each was
+    /// found in.
+    warnings: Vec<(Vec<SmolStr>, Warning)>,
+}
+
+impl LintContext {
+    /// Records the given warning, unless its lint is allowed where it occurred.
+    pub fn warn(&mut self, warning: Warning) {
+        if self
+            .allowed_lints
+            .iter()
+            .any(|lint| lint == warning.lint_name())
+        {
+            return;
+        }
+
+        self.warnings.push((self.module.clone(), warning));
+    }
+}
+
+/// Runs the given lints over the items of a package, returning the warnings
+/// they report along with the path of the module each was found in.
+pub fn check_items(items: &[Item], lints: &[&dyn LintPass]) -> Vec<(Vec<SmolStr>, Warning)> {
+    let mut runner = LintRunner {
+        lints,
+        cx: LintContext::default(),
+    };
+
+    for item in items {
+        runner.visit_item(item);
+    }
+
+    runner.cx.warnings
+}
+
+/// Walks the AST, running each lint on every node.
+struct LintRunner<'l> {
+    lints: &'l [&'l dyn LintPass],
+    cx: LintContext,
+}
+
+impl Visitor for LintRunner<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        let allowed_lints_len = self.cx.allowed_lints.len();
+        self.cx.allowed_lints.extend(
+            item.attrs
+                .iter()
+                .filter(|attr| attr.name.name == "allow")
+                .flat_map(|attr| attr.args.iter().map(|arg| arg.name.name)),
+        );
+
+        for lint in self.lints {
+            lint.check_item(&mut self.cx, item);
+        }
+
+        if let ItemKind::Module(_) = &item.kind {
+            self.cx.module.push(item.name.name.into());
+            visitor::walk_item(self, item);
+            self.cx.module.pop();
+        } else {
+            visitor::walk_item(self, item);
+        }
+
+        self.cx.allowed_lints.truncate(allowed_lints_len);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        for lint in self.lints {
+            lint.check_stmt(&mut self.cx, stmt);
+        }
+
+        visitor::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        for lint in self.lints {
+            lint.check_expr(&mut self.cx, expr);
+        }
+
+        visitor::walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::compiler::{CompileOptions, CompileParams, Compiler, Input};
+    use crate::lint::find_lint;
+
+    /// The directory of the fixtures of each lint, with code that it warns
+    /// about in `warn.crane`, and code that it doesn't in `pass.crane`.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/snapshot_inputs/lints");
+
+    /// Lints the given file, returning the compiler that reported the
+    /// diagnostics and what it wrote.
+    fn lint(path: &Path) -> (Compiler, String) {
+        let mut compiler = Compiler::new();
+
+        let params = CompileParams {
+            input: Input::File(path.to_path_buf()),
+            options: CompileOptions::default(),
+        };
+
+        let mut stderr = Vec::new();
+
+        assert!(compiler.lint(&mut stderr, params).is_ok());
+
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+
+        (compiler, String::from_utf8(stderr).unwrap())
+    }
+
+    #[test]
+    fn test_lints_warn() {
+        insta::glob!("../snapshot_inputs/lints", "*/warn.crane", |path| {
+            let lint_name = path
+                .parent()
+                .unwrap()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap();
+            let (compiler, stderr) = lint(path);
+
+            assert!(!compiler.diagnostics().is_empty());
+
+            // The fixture of each lint only warns about that lint.
+            for reported in compiler.diagnostics() {
+                assert!(
+                    reported.diagnostic.notes[0].contains(&format!("#[allow({lint_name})]")),
+                    "{stderr}"
+                );
+            }
+
+            // The path of the fixture differs between machines.
+            insta::assert_snapshot!(stderr.replace(FIXTURES, "lints"));
+        });
+    }
+
+    #[test]
+    fn test_lints_pass() {
+        insta::glob!("../snapshot_inputs/lints", "*/pass.crane", |path| {
+            let (_, stderr) = lint(path);
+
+            assert_eq!(stderr, "");
+        });
+    }
+
+    #[test]
+    fn test_every_ast_lint_has_fixtures() {
+        for lint in AST_LINTS {
+            let fixtures = Path::new(FIXTURES).join(lint.name());
+
+            assert!(find_lint(lint.name()).is_some(), "{}", lint.name());
+            assert!(fixtures.join("warn.crane").is_file(), "{}", lint.name());
+            assert!(fixtures.join("pass.crane").is_file(), "{}", lint.name());
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! The style lints, which check how code is written rather than what it does.
+
+use heck::{ToPascalCase, ToSnakeCase};
+
+use crate::ast::visitor::{self, Visitor};
+use crate::ast::{Ident, Item, ItemKind, Stmt, StmtKind};
+use crate::lint::pass::{LintContext, LintPass};
+use crate::typer::Warning;
+
+/// Reports functions, parameters and variables whose names aren't in
+/// snake_case.
+///
+/// Extern functions are named by the library that defines them, so they're
+/// left alone.
+pub struct NonSnakeCase;
+
+impl NonSnakeCase {
+    fn check_name(cx: &mut LintContext, kind: &str, name: &Ident) {
+        // Leading underscores mark names that are unused on purpose.
+        let trimmed = name.name.trim_start_matches('_');
+        let snake_case = trimmed.to_snake_case();
+
+        if trimmed != snake_case {
+            let underscores = &name.name[..name.name.len() - trimmed.len()];
+
+            cx.warn(Warning::NonSnakeCase {
+                kind: kind.into(),
+                name: name.name.into(),
+                suggestion: format!("{underscores}{snake_case}").into(),
+                span: name.span,
+            });
+        }
+    }
+}
+
+impl LintPass for NonSnakeCase {
+    fn name(&self) -> &'static str {
+        "non_snake_case"
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let ItemKind::Fn(fun) = &item.kind {
+            Self::check_name(cx, "Function", &item.name);
+
+            for param in &fun.decl.params {
+                Self::check_name(cx, "Parameter", &param.name);
+            }
+        }
+    }
+
+    fn check_stmt(&self, cx: &mut LintContext, stmt: &Stmt) {
+        if let StmtKind::Local(local) = &stmt.kind {
+            Self::check_name(cx, "Variable", &local.name);
+        }
+    }
+}
+
+/// Reports structs, unions and the variants of unions whose names aren't in
+/// PascalCase.
+pub struct NonPascalCaseTypes;
+
+impl NonPascalCaseTypes {
+    fn check_name(cx: &mut LintContext, kind: &str, name: &Ident) {
+        let pascal_case = name.name.to_pascal_case();
+
+        if name.name != pascal_case.as_str() {
+            cx.warn(Warning::NonPascalCaseType {
+                kind: kind.into(),
+                name: name.name.into(),
+                suggestion: pascal_case.into(),
+                span: name.span,
+            });
+        }
+    }
+}
+
+impl LintPass for NonPascalCaseTypes {
+    fn name(&self) -> &'static str {
+        "non_pascal_case_types"
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        match &item.kind {
+            ItemKind::Struct(_) => Self::check_name(cx, "Struct", &item.name),
+            ItemKind::Union(union_decl) => {
+                Self::check_name(cx, "Union", &item.name);
+
+                for variant in &union_decl.variants {
+                    Self::check_name(cx, "Variant", &variant.name);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reports functions with more than `max` statements, counting those in the
+/// blocks of their body but not those of the functions nested in it.
+pub struct TooManyStatements {
+    pub max: usize,
+}
+
+impl TooManyStatements {
+    /// The number of statements a function can have by default.
+    pub const DEFAULT_MAX: usize = 50;
+}
+
+impl LintPass for TooManyStatements {
+    fn name(&self) -> &'static str {
+        "too_many_statements"
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        let ItemKind::Fn(fun) = &item.kind else {
+            return;
+        };
+
+        let mut counter = StmtCounter { count: 0 };
+
+        for stmt in &fun.body {
+            counter.visit_stmt(stmt);
+        }
+
+        if counter.count > self.max {
+            cx.warn(Warning::TooManyStatements {
+                fn_name: item.name.name.into(),
+                count: counter.count,
+                max: self.max,
+                span: item.name.span,
+            });
+        }
+    }
+}
+
+/// Counts statements, leaving out those of nested items.
+struct StmtCounter {
+    count: usize,
+}
+
+impl Visitor for StmtCounter {
+    fn visit_item(&mut self, _item: &Item) {}
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.count += 1;
+
+        visitor::walk_stmt(self, stmt);
+    }
+}
+
+/// Reports functions whose body is empty.
+pub struct EmptyFn;
+
+impl LintPass for EmptyFn {
+    fn name(&self) -> &'static str {
+        "empty_fn"
+    }
+
+    fn check_item(&self, cx: &mut LintContext, item: &Item) {
+        if let ItemKind::Fn(fun) = &item.kind {
+            if fun.body.is_empty() {
+                cx.warn(Warning::EmptyFn {
+                    fn_name: item.name.name.into(),
+                    span: item.name.span,
+                });
+            }
+        }
+    }
+}
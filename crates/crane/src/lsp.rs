@@ -0,0 +1,742 @@
+//! A language server, speaking the [Language Server Protocol] over stdio.
+//!
+//! The server keeps the text of every open document, and analyzes it from
+//! scratch whenever it's needed:
+//!
+//! - Diagnostics are published whenever a document is opened or changed, by
+//!   type checking it like `crane check`.
+//! - Go to definition, hover, and document symbols use the parsed items of
+//!   the document, along with the modules it declares.
+//!
+//! There is no name resolution before type checking, so definitions are
+//! found by looking up the path of a reference among the items, the way the
+//! typer does: relative to the enclosing module, then to the root module, and
+//! through the `use` declarations of the enclosing module.
+//!
+//! [Language Server Protocol]: https://microsoft.github.io/language-server-protocol/
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+};
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use smol_str::SmolStr;
+use thin_vec::ThinVec;
+
+use crate::ast::visitor::{self, Visitor};
+use crate::ast::{
+    AstArena, InlineModuleDecl, Item, ItemKind, ModuleDecl, Path, SourceMap, Span, Ty, TyKind,
+    VariantData,
+};
+use crate::compiler::{CompileOptions, CompileParams, Compiler, Input};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::formatter::format_signature;
+
+/// An error that stops the language server.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Runs the language server over stdin and stdout, until the client asks it
+/// to exit.
+///
+/// Documents are checked with the given options.
+pub fn run_stdio(options: CompileOptions) -> Result<(), Error> {
+    let (connection, io_threads) = Connection::stdio();
+
+    run(connection, options)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// Runs the language server over the given connection, until the client asks
+/// it to exit.
+///
+/// Documents are checked with the given options.
+pub fn run(connection: Connection, options: CompileOptions) -> Result<(), Error> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    };
+
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    Server {
+        connection,
+        options,
+        documents: HashMap::new(),
+    }
+    .run()
+}
+
+struct Server {
+    connection: Connection,
+
+    /// The options to check documents with.
+    options: CompileOptions,
+
+    /// The text of each open document.
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn run(mut self) -> Result<(), Error> {
+        let receiver = self.connection.receiver.clone();
+
+        for message in &receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => {
+                    self.handle_notification(notification)?;
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => respond(request.params, |params| self.definition(params)),
+            HoverRequest::METHOD => respond(request.params, |params| self.hover(params)),
+            DocumentSymbolRequest::METHOD => {
+                respond(request.params, |params| self.document_symbols(params))
+            }
+            method => {
+                return Response::new_err(
+                    request.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request `{method}`."),
+                );
+            }
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(request.id, result),
+            Err(err) => {
+                Response::new_err(request.id, ErrorCode::InvalidParams as i32, err.to_string())
+            }
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), Error> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.publish_diagnostics(uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                // The document is synced in full, so the last change holds
+                // the whole text.
+                if let Some(change) = params.content_changes.pop() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+
+                self.publish_diagnostics(uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                self.documents.remove(&uri);
+                self.send_diagnostics(uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Type checks the given document and publishes its diagnostics.
+    fn publish_diagnostics(&self, uri: Url) -> Result<(), Error> {
+        let diagnostics = match self.document(&uri) {
+            Some((path, text)) => {
+                let mut compiler = Compiler::new();
+                let params = CompileParams {
+                    input: Input::Buffer {
+                        path: path.clone(),
+                        input: text.clone(),
+                    },
+                    options: self.options.clone(),
+                };
+
+                // The diagnostics are collected from the compiler instead.
+                let _ = compiler.check(&mut std::io::sink(), params);
+
+                let filepath = path.display().to_string();
+
+                compiler
+                    .diagnostics()
+                    .iter()
+                    .filter(|reported| reported.path == filepath)
+                    .map(|reported| to_lsp_diagnostic(&uri, text, &reported.diagnostic))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Error> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+
+        self.connection.sender.send(notification.into())?;
+
+        Ok(())
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let definition = self.definition_at(&params.text_document_position_params)?;
+
+        Some(GotoDefinitionResponse::Scalar(definition.location.clone()))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let definition = self.definition_at(&params.text_document_position_params)?;
+
+        let mut value = format!("```crane\n{}\n```", definition.signature);
+
+        if !definition.docs.is_empty() {
+            value.push_str("\n\n");
+            value.push_str(&definition.docs);
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let (_, text) = self.document(&params.text_document.uri)?;
+        let arena = AstArena::new();
+        let analysis = self.analyze(&params.text_document.uri, &arena)?;
+
+        Some(DocumentSymbolResponse::Nested(document_symbols(
+            text,
+            &analysis.items,
+        )))
+    }
+
+    /// Returns the definition of the item referenced, or declared, at the
+    /// given position.
+    fn definition_at(&self, params: &TextDocumentPositionParams) -> Option<Definition> {
+        let (_, text) = self.document(&params.text_document.uri)?;
+        let offset = offset(text, params.position);
+        let arena = AstArena::new();
+        let mut analysis = self.analyze(&params.text_document.uri, &arena)?;
+
+        let contains = |span: Span| span.start <= offset && offset <= span.end;
+
+        let index = match analysis
+            .references
+            .iter()
+            .find(|reference| contains(reference.span))
+        {
+            Some(reference) => analysis.resolve(reference)?,
+            None => analysis.definitions.iter().position(|definition| {
+                definition.location.uri == params.text_document.uri
+                    && contains(definition.name_span)
+            })?,
+        };
+
+        Some(analysis.definitions.swap_remove(index))
+    }
+
+    /// Returns the path and text of the given document, if it is an open
+    /// file.
+    fn document(&self, uri: &Url) -> Option<(PathBuf, &String)> {
+        let text = self.documents.get(uri)?;
+        let path = uri.to_file_path().ok()?;
+
+        Some((path, text))
+    }
+
+    /// Parses the given document into `arena`, and collects the definitions
+    /// and references in it.
+    fn analyze<'a>(&self, uri: &Url, arena: &'a AstArena<'a>) -> Option<Analysis<'a>> {
+        let (path, text) = self.document(uri)?;
+
+        let mut compiler = Compiler::new();
+        let params = CompileParams {
+            input: Input::Buffer {
+                path,
+                input: text.clone(),
+            },
+            options: self.options.clone(),
+        };
+
+        let items = compiler
+            .parse_input(&mut std::io::sink(), params, arena)
+            .ok()?;
+
+        let mut definitions = Vec::new();
+        collect_definitions(compiler.source_map(), &items, &[], &mut definitions);
+
+        let mut collector = ReferenceCollector::default();
+        for item in &items {
+            collector.visit_item(item);
+        }
+
+        Some(Analysis {
+            items,
+            definitions,
+            references: collector.references,
+            imports: collector.imports,
+        })
+    }
+}
+
+/// Deserializes the params of a request, and serializes the result of
+/// handling them.
+fn respond<P: DeserializeOwned, R: Serialize>(
+    params: serde_json::Value,
+    handle: impl FnOnce(P) -> R,
+) -> serde_json::Result<serde_json::Value> {
+    serde_json::to_value(handle(serde_json::from_value(params)?))
+}
+
+/// The items of a document, along with what they define and reference.
+struct Analysis<'a> {
+    items: ThinVec<Item<'a>>,
+    definitions: Vec<Definition>,
+    references: Vec<Reference>,
+    imports: Vec<Import>,
+}
+
+impl Analysis<'_> {
+    /// Returns the index of the definition that the given reference refers
+    /// to.
+    fn resolve(&self, reference: &Reference) -> Option<usize> {
+        let mut candidates = Vec::new();
+
+        // A name brought into scope by a `use` stands for the path it was
+        // imported from.
+        for import in &self.imports {
+            if import.module == reference.module && import.path.last() == reference.path.first() {
+                candidates.push([&import.path[..], &reference.path[1..]].concat());
+            }
+        }
+
+        candidates.push([&reference.module[..], &reference.path[..]].concat());
+        candidates.push(reference.path.clone());
+
+        candidates.iter().find_map(|candidate| {
+            self.definitions
+                .iter()
+                .position(|definition| definition.path == *candidate)
+        })
+    }
+}
+
+/// An item that can be referenced.
+struct Definition {
+    /// The path of the item from the root module.
+    path: Vec<SmolStr>,
+
+    /// The location of the item's name.
+    location: Location,
+
+    /// The span of the item's name in its file.
+    name_span: Span,
+
+    /// The declaration of the item, as shown on hover.
+    signature: String,
+
+    /// The doc comments of the item.
+    docs: String,
+}
+
+/// Collects the definitions of the given items, in the module at the given
+/// path, and the items nested in them.
+fn collect_definitions(
+    source_map: &SourceMap,
+    items: &[Item],
+    module: &[SmolStr],
+    definitions: &mut Vec<Definition>,
+) {
+    for item in items {
+        let path = [module, &[item.name.name.into()]].concat();
+
+        match &item.kind {
+            ItemKind::Use(_) => continue,
+            // The items of an `impl` block are associated with the type,
+            // which is defined by its own item.
+            ItemKind::Impl(impl_decl) => {
+                collect_definitions(source_map, &impl_decl.items, &path, definitions);
+                continue;
+            }
+            ItemKind::Module(module_decl) => {
+                if let ModuleDecl::Loaded(module, _) = &**module_decl {
+                    collect_definitions(source_map, &module.items, &path, definitions);
+                }
+            }
+            _ => {}
+        }
+
+        // The declaration of a module loaded from a file is in its parent.
+        let Some(file) = source_map.file_of(&path[..path.len() - 1]) else {
+            continue;
+        };
+        let Ok(uri) = Url::from_file_path(&file.path) else {
+            continue;
+        };
+
+        definitions.push(Definition {
+            location: Location::new(uri, range(&file.source, item.name.span)),
+            name_span: item.name.span,
+            signature: format_signature(item),
+            docs: item.doc_text(),
+            path,
+        });
+    }
+}
+
+/// A path in the source that may refer to an item.
+struct Reference {
+    /// The path of the module the reference is in.
+    module: Vec<SmolStr>,
+
+    /// The referenced path, up to and including the segment at `span`.
+    path: Vec<SmolStr>,
+
+    span: Span,
+}
+
+/// A `use` declaration.
+struct Import {
+    /// The path of the module the declaration is in.
+    module: Vec<SmolStr>,
+
+    /// The imported path.
+    path: Vec<SmolStr>,
+}
+
+/// Collects the references and imports in the items of a document.
+///
+/// Modules loaded from other files are skipped, since the spans in them
+/// aren't in the document.
+#[derive(Default)]
+struct ReferenceCollector {
+    /// The path of the module being visited.
+    module: Vec<SmolStr>,
+    references: Vec<Reference>,
+    imports: Vec<Import>,
+}
+
+impl Visitor for ReferenceCollector {
+    fn visit_item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Use(use_tree) => {
+                self.visit_path(&use_tree.prefix);
+                self.imports.push(Import {
+                    module: self.module.clone(),
+                    path: path_segments(&use_tree.prefix),
+                });
+            }
+            ItemKind::Module(module_decl) => {
+                if let ModuleDecl::Loaded(module, InlineModuleDecl::Yes) = &**module_decl {
+                    self.module.push(item.name.name.into());
+
+                    for item in &module.items {
+                        self.visit_item(item);
+                    }
+
+                    self.module.pop();
+                }
+            }
+            _ => visitor::walk_item(self, item),
+        }
+    }
+
+    fn visit_path(&mut self, path: &Path) {
+        let segments = path_segments(path);
+
+        for (index, segment) in path.segments.iter().enumerate() {
+            self.references.push(Reference {
+                module: self.module.clone(),
+                path: segments[..=index].to_vec(),
+                span: segment.ident.span,
+            });
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &Ty) {
+        match &ty.kind {
+            TyKind::Path(path) => self.visit_path(path),
+            TyKind::Fn(fn_ty) => self.visit_fn_decl(&fn_ty.decl),
+            TyKind::Array { elem, len } => {
+                self.visit_ty(elem);
+                self.visit_expr(len);
+            }
+        }
+    }
+}
+
+fn path_segments(path: &Path) -> Vec<SmolStr> {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.name.into())
+        .collect()
+}
+
+/// Returns the symbols for the given items, nested like the items are.
+fn document_symbols(source: &str, items: &[Item]) -> Vec<DocumentSymbol> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let (kind, children) = match &item.kind {
+                ItemKind::Use(_) => return None,
+                ItemKind::Fn(_) | ItemKind::ExternFn(_) => (SymbolKind::FUNCTION, Vec::new()),
+                ItemKind::Struct(struct_decl) => {
+                    (SymbolKind::STRUCT, field_symbols(source, &struct_decl.0))
+                }
+                ItemKind::Union(union_decl) => {
+                    let variants = union_decl
+                        .variants
+                        .iter()
+                        .map(|variant| {
+                            symbol(
+                                source,
+                                variant.name.name.to_string(),
+                                None,
+                                SymbolKind::ENUM_MEMBER,
+                                variant.name.span,
+                                field_symbols(source, &variant.data),
+                            )
+                        })
+                        .collect();
+
+                    (SymbolKind::ENUM, variants)
+                }
+                ItemKind::Module(module_decl) => match &**module_decl {
+                    ModuleDecl::Loaded(module, InlineModuleDecl::Yes) => {
+                        (SymbolKind::MODULE, document_symbols(source, &module.items))
+                    }
+                    _ => (SymbolKind::MODULE, Vec::new()),
+                },
+                ItemKind::Impl(impl_decl) => {
+                    return Some(symbol(
+                        source,
+                        format!("impl {}", item.name.name),
+                        None,
+                        SymbolKind::OBJECT,
+                        item.name.span,
+                        document_symbols(source, &impl_decl.items),
+                    ));
+                }
+                ItemKind::Const(_) => (SymbolKind::CONSTANT, Vec::new()),
+            };
+
+            Some(symbol(
+                source,
+                item.name.name.to_string(),
+                Some(format_signature(item)),
+                kind,
+                item.name.span,
+                children,
+            ))
+        })
+        .collect()
+}
+
+/// Returns the symbols for the named fields in the given [`VariantData`].
+fn field_symbols(source: &str, data: &VariantData) -> Vec<DocumentSymbol> {
+    data.fields()
+        .iter()
+        .filter_map(|field| {
+            let name = field.name.as_ref()?;
+
+            Some(symbol(
+                source,
+                name.name.to_string(),
+                None,
+                SymbolKind::FIELD,
+                name.span,
+                Vec::new(),
+            ))
+        })
+        .collect()
+}
+
+fn symbol(
+    source: &str,
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    span: Span,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    let range = range(source, span);
+
+    // `deprecated` is itself deprecated, in favor of `tags`.
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: range,
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// Converts a [`Diagnostic`] in the given document to an LSP diagnostic.
+///
+/// The notes and help are appended to the message, and the secondary labels
+/// become related information.
+fn to_lsp_diagnostic(uri: &Url, source: &str, diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+
+    let (span, mut message) = match diagnostic.labels.first() {
+        Some(label) => (label.span, label.message.clone()),
+        None => (Span::new(0, 0), diagnostic.message.clone()),
+    };
+
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\n{note}"));
+    }
+
+    if let Some(help) = &diagnostic.help {
+        message.push_str(&format!("\nHelp: {help}"));
+    }
+
+    let related_information = diagnostic
+        .labels
+        .iter()
+        .skip(1)
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), range(source, label.span)),
+            message: label.message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    lsp_types::Diagnostic {
+        range: range(source, span),
+        severity: Some(severity),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("crane".to_string()),
+        message,
+        related_information: (!related_information.is_empty()).then_some(related_information),
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// Returns the LSP range of the given span in the source.
+fn range(source: &str, span: Span) -> Range {
+    Range::new(position(source, span.start), position(source, span.end))
+}
+
+/// Returns the LSP position of the given offset in the source.
+///
+/// LSP positions count columns in UTF-16 code units. Offsets past the end of
+/// the source, like those of errors at the end of the file, are clamped to
+/// it.
+fn position(source: &str, offset: usize) -> Position {
+    let mut offset = offset.min(source.len());
+
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
+
+/// Returns the offset in the source of the given LSP position.
+fn offset(source: &str, position: Position) -> usize {
+    let line_start = source
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+
+    let mut column = 0;
+
+    for (index, char) in source[line_start..].char_indices() {
+        if column >= position.character as usize || char == '\n' {
+            return line_start + index;
+        }
+
+        column += char.len_utf16();
+    }
+
+    source.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let source = "fn main() {\n    let café = \"🦀\"\n}";
+
+        assert_eq!(position(source, 0), Position::new(0, 0));
+        assert_eq!(position(source, 16), Position::new(1, 4));
+        assert_eq!(position(source, 31), Position::new(1, 16));
+        assert_eq!(position(source, 33), Position::new(1, 18));
+        assert_eq!(position(source, 1000), Position::new(2, 1));
+
+        assert_eq!(offset(source, Position::new(1, 4)), 16);
+        assert_eq!(offset(source, Position::new(1, 18)), 33);
+        assert_eq!(offset(source, Position::new(1, 100)), 34);
+        assert_eq!(offset(source, Position::new(5, 0)), source.len());
+    }
+}
@@ -0,0 +1,1485 @@
+//! Expansion of the built-in macros, like `concat!`, `env!`, and
+//! `include_str!`, and of `#[derive]` attributes.
+//!
+//! Macro calls are replaced with the expressions they expand to after
+//! conditional compilation and before type checking, so the typer and the
+//! backend never see them.
+
+mod derive;
+
+use std::path::{Path, PathBuf};
+
+use thin_vec::{thin_vec, ThinVec};
+use thiserror::Error;
+
+use crate::ast::{
+    self, AstArena, Expr, ExprKind, FieldDecl, FnDecl, FnReturnTy, Ident, Item, ItemKind, Literal,
+    LiteralKind, LocalKind, MacroCall, ModuleDecl, PathSegment, SourceFile, Span, Stmt, StmtKind,
+    Ty, TyKind, VariantData,
+};
+
+/// The built-in macros.
+pub const MACROS: [&str; 11] = [
+    "compile_error",
+    "concat",
+    "env",
+    "file",
+    "format",
+    "include_bytes",
+    "include_str",
+    "line",
+    "stringify",
+    "todo",
+    "unimplemented",
+];
+
+/// An error in a macro call.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct MacroError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub type MacroResult<T> = Result<T, MacroError>;
+
+/// Expands the macro calls in a source file.
+pub struct MacroExpander<'f, 'a> {
+    /// The source file being expanded, which `file!` and `line!` refer to.
+    file: &'f SourceFile,
+
+    /// The arena that the expanded expressions are allocated in.
+    arena: &'a AstArena<'a>,
+
+    /// The directory containing the source file, which the paths of included
+    /// files are relative to.
+    ///
+    /// Source code that isn't read from a file, like that given on stdin,
+    /// doesn't have one, so it can't include files.
+    dir: Option<PathBuf>,
+
+    /// Whether this is a debug build, in which `todo!` and `unimplemented!`
+    /// abort with their message.
+    debug: bool,
+}
+
+impl<'f, 'a> MacroExpander<'f, 'a> {
+    pub fn new(file: &'f SourceFile, arena: &'a AstArena<'a>) -> Self {
+        Self {
+            file,
+            arena,
+            dir: None,
+            debug: false,
+        }
+    }
+
+    /// Sets the directory containing the source file.
+    pub fn with_dir(mut self, dir: Option<&Path>) -> Self {
+        self.dir = dir.map(Path::to_path_buf);
+        self
+    }
+
+    /// Sets whether this is a debug build.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Expands the macro calls in the given items, and adds the `impl` blocks
+    /// derived for them after the items they are derived for.
+    ///
+    /// This descends into inline modules and `impl` blocks. Modules that
+    /// haven't been loaded yet must be expanded once they are.
+    pub fn expand_items(&self, items: &mut ThinVec<Item<'a>>) -> MacroResult<()> {
+        let mut index = 0;
+
+        while index < items.len() {
+            self.expand_item(&mut items[index])?;
+
+            let derived = derive::derive_impls(&items[index], self.arena)?;
+
+            index += 1;
+
+            for item in derived {
+                items.insert(index, item);
+                index += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expand_item(&self, item: &mut Item<'a>) -> MacroResult<()> {
+        match &mut item.kind {
+            ItemKind::Use(_) => Ok(()),
+            ItemKind::Fn(fun) => {
+                self.expand_fn_decl(&mut fun.decl)?;
+                self.expand_stmts(&mut fun.body)
+            }
+            ItemKind::ExternFn(fun_decl) => self.expand_fn_decl(fun_decl),
+            ItemKind::Struct(struct_decl) => self.expand_variant_data(&mut struct_decl.0),
+            ItemKind::Union(union_decl) => {
+                for variant in union_decl.variants.iter_mut() {
+                    self.expand_variant_data(&mut variant.data)?;
+                }
+
+                Ok(())
+            }
+            ItemKind::Module(module_decl) => match module_decl.as_mut() {
+                ModuleDecl::Loaded(module, _) => self.expand_items(&mut module.items),
+                ModuleDecl::Unloaded => Ok(()),
+            },
+            ItemKind::Impl(impl_decl) => self.expand_items(&mut impl_decl.items),
+            ItemKind::Const(const_decl) => {
+                self.expand_ty(&mut const_decl.ty)?;
+                self.expand_expr(const_decl.expr)
+            }
+        }
+    }
+
+    fn expand_fn_decl(&self, fun_decl: &mut FnDecl<'a>) -> MacroResult<()> {
+        for param in fun_decl.params.iter_mut() {
+            self.expand_ty(&mut param.ty)?;
+        }
+
+        match &mut fun_decl.return_ty {
+            FnReturnTy::Ty(ty) => self.expand_ty(ty),
+            FnReturnTy::Unit => Ok(()),
+        }
+    }
+
+    fn expand_variant_data(&self, variant_data: &mut VariantData<'a>) -> MacroResult<()> {
+        let fields: &mut [FieldDecl<'a>] = match variant_data {
+            VariantData::Struct(fields) | VariantData::Tuple(fields) => fields,
+            VariantData::Unit => &mut [],
+        };
+
+        for field in fields {
+            self.expand_ty(&mut field.ty)?;
+        }
+
+        Ok(())
+    }
+
+    fn expand_ty(&self, ty: &mut Ty<'a>) -> MacroResult<()> {
+        match &mut ty.kind {
+            TyKind::Path(_) => Ok(()),
+            TyKind::Fn(fn_ty) => self.expand_fn_decl(&mut fn_ty.decl),
+            TyKind::Array { elem, len } => {
+                self.expand_ty(elem)?;
+                self.expand_expr(len)
+            }
+        }
+    }
+
+    fn expand_stmts(&self, stmts: &mut [Stmt<'a>]) -> MacroResult<()> {
+        for stmt in stmts {
+            match &mut stmt.kind {
+                StmtKind::Local(local) => {
+                    if let Some(ty) = &mut local.ty {
+                        self.expand_ty(ty)?;
+                    }
+
+                    if let LocalKind::Init(init) = &mut local.kind {
+                        self.expand_expr(init)?;
+                    }
+                }
+                StmtKind::Item(item) => self.expand_item(item)?,
+                StmtKind::Expr(expr) => self.expand_expr(expr)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expand_expr(&self, expr: &mut Expr<'a>) -> MacroResult<()> {
+        match &mut expr.kind {
+            ExprKind::Literal(_) | ExprKind::Variable(_) | ExprKind::Asm(_) => {}
+            ExprKind::Call { fun, args } => {
+                self.expand_expr(fun)?;
+
+                for arg in args.iter_mut() {
+                    self.expand_expr(arg)?;
+                }
+            }
+            ExprKind::Struct(struct_expr) => {
+                for field in struct_expr.fields.iter_mut() {
+                    self.expand_expr(field.expr)?;
+                }
+            }
+            ExprKind::Binary { op: _, lhs, rhs } => {
+                self.expand_expr(lhs)?;
+                self.expand_expr(rhs)?;
+            }
+            ExprKind::Cast { expr, ty } => {
+                self.expand_expr(expr)?;
+                self.expand_ty(ty)?;
+            }
+            ExprKind::Array(elems) => {
+                for elem in elems.iter_mut() {
+                    self.expand_expr(elem)?;
+                }
+            }
+            ExprKind::Index { expr, index } => {
+                self.expand_expr(expr)?;
+                self.expand_expr(index)?;
+            }
+            ExprKind::Assign { target, value } => {
+                self.expand_expr(target)?;
+                self.expand_expr(value)?;
+            }
+            ExprKind::While { cond, body } => {
+                self.expand_expr(cond)?;
+                self.expand_stmts(body)?;
+            }
+            ExprKind::TypeOf(expr) => self.expand_expr(expr)?,
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.expand_expr(cond)?;
+                self.expand_stmts(then_branch)?;
+
+                if let Some(else_branch) = else_branch {
+                    self.expand_stmts(else_branch)?;
+                }
+            }
+            ExprKind::For(for_expr) => {
+                self.expand_expr(for_expr.start)?;
+                self.expand_expr(for_expr.end)?;
+                self.expand_stmts(&mut for_expr.body)?;
+            }
+            ExprKind::Comptime(body) | ExprKind::Unsafe(body) => self.expand_stmts(body)?,
+            ExprKind::Match { scrutinee, arms } => {
+                self.expand_expr(scrutinee)?;
+
+                for arm in arms.iter_mut() {
+                    self.expand_stmts(&mut arm.body)?;
+                }
+            }
+            ExprKind::Field(field_expr) => self.expand_expr(field_expr.expr)?,
+            ExprKind::MethodCall(method_call) => {
+                self.expand_expr(method_call.receiver)?;
+
+                for arg in method_call.args.iter_mut() {
+                    self.expand_expr(arg)?;
+                }
+            }
+            ExprKind::Macro(macro_call) => {
+                // The arguments are expanded first, so that macro calls can be
+                // nested, like `concat!("v", concat!(1, 2))`. The argument of
+                // `stringify!` is only ever written out, so it is left as is.
+                if macro_call.name.name != "stringify" {
+                    for arg in macro_call.args.iter_mut() {
+                        self.expand_expr(arg)?;
+                    }
+                }
+
+                let kind = self.expand_macro(macro_call, expr.span.get())?;
+
+                expr.kind = kind;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the expression that the given macro call expands to.
+    fn expand_macro(
+        &self,
+        macro_call: &mut MacroCall<'a>,
+        span: Span,
+    ) -> MacroResult<ExprKind<'a>> {
+        match macro_call.name.name.as_str() {
+            "compile_error" => self.expand_compile_error(macro_call, span),
+            "concat" => self.expand_concat(macro_call, span),
+            "env" => self.expand_env(macro_call, span),
+            "file" => self.expand_file(macro_call, span),
+            "format" => self.expand_format(macro_call, span),
+            "include_bytes" => self.expand_include_bytes(macro_call, span),
+            "include_str" => self.expand_include_str(macro_call, span),
+            "line" => self.expand_line(macro_call, span),
+            "stringify" => self.expand_stringify(macro_call, span),
+            "todo" => self.expand_todo(macro_call, span, "not yet implemented"),
+            "unimplemented" => self.expand_todo(macro_call, span, "not implemented"),
+            _ => Err(MacroError {
+                message: format!(
+                    "Unknown macro `{}!`. Expected one of: {}.",
+                    macro_call.name,
+                    MACROS.map(|name| format!("{name}!")).join(", ")
+                ),
+                span: macro_call.name.span,
+            }),
+        }
+    }
+
+    /// Expands `compile_error!("message")`, which fails compilation with
+    /// `message`.
+    ///
+    /// This is meant for items disabled by `#[cfg]`, which are removed before
+    /// their macros are expanded, so the error is only reported for the
+    /// configurations that enable them.
+    fn expand_compile_error(
+        &self,
+        macro_call: &MacroCall,
+        span: Span,
+    ) -> MacroResult<ExprKind<'a>> {
+        let message = string_arg(macro_call, span, "the error message")?;
+
+        Err(MacroError {
+            message: message.to_string(),
+            span,
+        })
+    }
+
+    /// Expands `concat!(args)`, which concatenates string and integer literals
+    /// into a string literal.
+    fn expand_concat(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        if macro_call.args.is_empty() {
+            return Err(MacroError {
+                message: "`concat!` expects at least one literal to concatenate.".to_string(),
+                span,
+            });
+        }
+
+        let mut value = String::new();
+
+        for arg in &macro_call.args {
+            match &arg.kind {
+                ExprKind::Literal(Literal {
+                    kind: LiteralKind::String,
+                    value: string,
+                }) => value.push_str(string.trim_matches('"')),
+                ExprKind::Literal(Literal {
+                    kind: LiteralKind::Integer,
+                    value: integer,
+                }) => value.push_str(integer),
+                _ => {
+                    return Err(MacroError {
+                        message: "`concat!` can only concatenate string and integer literals."
+                            .to_string(),
+                        span: arg.span.get(),
+                    });
+                }
+            }
+        }
+
+        Ok(string_literal(&value))
+    }
+
+    /// Expands `env!("NAME")`, which reads the environment variable `NAME`
+    /// while compiling into a string literal.
+    fn expand_env(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        let name = string_arg(macro_call, span, "the name of an environment variable")?;
+
+        let value = std::env::var(name).map_err(|err| MacroError {
+            message: match err {
+                std::env::VarError::NotPresent => {
+                    format!("The environment variable `{name}` is not set.")
+                }
+                std::env::VarError::NotUnicode(_) => {
+                    format!("The environment variable `{name}` is not valid Unicode.")
+                }
+            },
+            span,
+        })?;
+
+        if value.contains('"') {
+            return Err(MacroError {
+                message: format!(
+                    "The environment variable `{name}` contains a `\"`, which can't be written in a string literal."
+                ),
+                span,
+            });
+        }
+
+        Ok(string_literal(&value))
+    }
+
+    /// Expands `file!()`, which is the path of the source file as shown in
+    /// diagnostics, as a string literal.
+    fn expand_file(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        no_args(macro_call, span)?;
+
+        Ok(string_literal(&self.file.path))
+    }
+
+    /// Expands `format!("template", args)`, which replaces each `{}` in the
+    /// template with the next argument, converted with `to_string`, and each
+    /// `{:?}` with the next argument converted with `debug_repr`. A literal
+    /// brace is written twice, like `{{`.
+    fn expand_format(
+        &self,
+        macro_call: &mut MacroCall<'a>,
+        span: Span,
+    ) -> MacroResult<ExprKind<'a>> {
+        // The arguments are moved into the expansion, rather than cloned.
+        let mut args = std::mem::take(&mut macro_call.args).into_iter();
+
+        let Some(template) = args.next() else {
+            return Err(MacroError {
+                message: "`format!` expects a string literal with the template.".to_string(),
+                span,
+            });
+        };
+
+        let ExprKind::Literal(Literal {
+            kind: LiteralKind::String,
+            value: template_value,
+        }) = &template.kind
+        else {
+            return Err(MacroError {
+                message: "`format!` expects a string literal with the template.".to_string(),
+                span: template.span.get(),
+            });
+        };
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template_value.trim_matches('"').chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match (char, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    literal.push(char);
+                }
+                ('{', Some('}' | ':')) => {
+                    let method = match chars.next() {
+                        Some(':') if chars.next() == Some('?') && chars.next() == Some('}') => {
+                            "debug_repr"
+                        }
+                        Some(':') => {
+                            return Err(MacroError {
+                                message: "Expected `{:?}` in the template.".to_string(),
+                                span: template.span.get(),
+                            });
+                        }
+                        _ => "to_string",
+                    };
+
+                    let Some(arg) = args.next() else {
+                        return Err(MacroError {
+                            message: "The template has more `{}` than there are arguments."
+                                .to_string(),
+                            span: template.span.get(),
+                        });
+                    };
+
+                    if !literal.is_empty() {
+                        parts.push(string_literal(&std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(method_call(arg, method));
+                }
+                ('{' | '}', _) => {
+                    return Err(MacroError {
+                        message: format!(
+                            "Unmatched `{char}` in the template. A literal brace is written twice, like `{char}{char}`."
+                        ),
+                        span: template.span.get(),
+                    });
+                }
+                _ => literal.push(char),
+            }
+        }
+
+        if let Some(arg) = args.next() {
+            return Err(MacroError {
+                message: "The template has fewer `{}` than there are arguments.".to_string(),
+                span: arg.span.get(),
+            });
+        }
+
+        if !literal.is_empty() {
+            parts.push(string_literal(&literal));
+        }
+
+        Ok(concat_strings(self.arena, parts, span))
+    }
+
+    /// Expands `line!()`, which is the line of the source file the call is on,
+    /// starting at `1`, as a `Uint64`.
+    fn expand_line(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        no_args(macro_call, span)?;
+
+        let (line, _) = self.file.line_col(span.start);
+
+        Ok(ExprKind::Cast {
+            expr: self.arena.alloc_expr(Expr {
+                kind: integer_literal(line),
+                span: span.into(),
+            }),
+            ty: Box::new(named_ty("Uint64", span)),
+        })
+    }
+
+    /// Expands `stringify!(expr)`, which is the source code of `expr`, as a
+    /// string literal. The expression isn't evaluated.
+    fn expand_stringify(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        let [arg] = macro_call.args.as_slice() else {
+            return Err(MacroError {
+                message: "`stringify!` expects a single expression.".to_string(),
+                span,
+            });
+        };
+
+        let source = self
+            .file
+            .source
+            .get(arg.span.get().start..arg.span.get().end)
+            .unwrap_or_default();
+
+        if source.contains('"') {
+            return Err(MacroError {
+                message:
+                    "The expression contains a `\"`, which can't be written in a string literal."
+                        .to_string(),
+                span: arg.span.get(),
+            });
+        }
+
+        Ok(string_literal(source))
+    }
+
+    /// Expands `todo!()` and `unimplemented!()`, which stand in for code that
+    /// hasn't been written, with an optional message.
+    ///
+    /// These call `std::process::todo` and `std::process::unimplemented`,
+    /// except that a debug build with a message aborts with it instead. Release
+    /// builds assume they are unreachable, so the message is left out.
+    fn expand_todo(
+        &self,
+        macro_call: &MacroCall,
+        span: Span,
+        prefix: &str,
+    ) -> MacroResult<ExprKind<'a>> {
+        let message = if macro_call.args.is_empty() {
+            None
+        } else {
+            Some(string_arg(macro_call, span, "the message")?)
+        };
+
+        match message {
+            Some(message) if self.debug => Ok(call(
+                self.arena,
+                "std::process::abort",
+                thin_vec![self.arena.alloc_expr(Expr {
+                    kind: string_literal(&format!("{prefix}: {message}")),
+                    span: span.into(),
+                })],
+                span,
+            )),
+            _ => Ok(call(
+                self.arena,
+                &format!("std::process::{}", macro_call.name),
+                ThinVec::new(),
+                span,
+            )),
+        }
+    }
+
+    /// Expands `include_str!("path")`, which reads the file at `path`, relative
+    /// to the directory of the source file, into a string literal.
+    fn expand_include_str(&self, macro_call: &MacroCall, span: Span) -> MacroResult<ExprKind<'a>> {
+        let full_path = self.included_path(macro_call, span)?;
+
+        let contents = std::fs::read_to_string(&full_path).map_err(|err| MacroError {
+            message: format!("Failed to read `{}`: {err}", full_path.display()),
+            span,
+        })?;
+
+        if contents.contains('"') {
+            return Err(MacroError {
+                message: format!(
+                    "`{}` contains a `\"`, which can't be written in a string literal.",
+                    full_path.display()
+                ),
+                span,
+            });
+        }
+
+        Ok(string_literal(&contents))
+    }
+
+    /// Expands `include_bytes!("path")`, which reads the file at `path`,
+    /// relative to the directory of the source file, into a `[Uint8; N]` array
+    /// literal.
+    fn expand_include_bytes(
+        &self,
+        macro_call: &MacroCall,
+        span: Span,
+    ) -> MacroResult<ExprKind<'a>> {
+        let full_path = self.included_path(macro_call, span)?;
+
+        let bytes = std::fs::read(&full_path).map_err(|err| MacroError {
+            message: format!("Failed to read `{}`: {err}", full_path.display()),
+            span,
+        })?;
+
+        if bytes.is_empty() {
+            return Err(MacroError {
+                message: format!(
+                    "`{}` is empty, and the type of an empty array can't be inferred.",
+                    full_path.display()
+                ),
+                span,
+            });
+        }
+
+        let byte = |value: u8| {
+            self.arena.alloc_expr(Expr {
+                kind: integer_literal(value),
+                span: span.into(),
+            })
+        };
+
+        // The integer literals in an array take on the type of the first
+        // element that isn't one, so casting the first byte makes them all
+        // `Uint8`.
+        let elems = bytes
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| match index {
+                0 => self.arena.alloc_expr(Expr {
+                    kind: ExprKind::Cast {
+                        expr: byte(value),
+                        ty: Box::new(named_ty("Uint8", span)),
+                    },
+                    span: span.into(),
+                }),
+                _ => byte(value),
+            })
+            .collect();
+
+        Ok(ExprKind::Array(elems))
+    }
+
+    /// Returns the path of the file included by a macro call, whose argument is
+    /// a path relative to the directory of the source file.
+    fn included_path(&self, macro_call: &MacroCall, span: Span) -> MacroResult<PathBuf> {
+        let path = string_arg(macro_call, span, "the path of a file")?;
+
+        let Some(dir) = &self.dir else {
+            return Err(MacroError {
+                message: format!(
+                    "`{}!` can only be used when compiling a file.",
+                    macro_call.name
+                ),
+                span,
+            });
+        };
+
+        Ok(dir.join(path))
+    }
+}
+
+/// Returns the contents of the only argument of a macro call, which must be a
+/// string literal holding `expected`.
+fn string_arg<'a>(macro_call: &'a MacroCall, span: Span, expected: &str) -> MacroResult<&'a str> {
+    match macro_call.args.as_slice() {
+        [arg] => match &arg.kind {
+            ExprKind::Literal(Literal {
+                kind: LiteralKind::String,
+                value,
+            }) => Ok(value.trim_matches('"')),
+            _ => Err(MacroError {
+                message: format!(
+                    "`{}!` expects a string literal with {expected}.",
+                    macro_call.name
+                ),
+                span: arg.span.get(),
+            }),
+        },
+        _ => Err(MacroError {
+            message: format!(
+                "`{}!` expects a single string literal with {expected}.",
+                macro_call.name
+            ),
+            span,
+        }),
+    }
+}
+
+/// Returns a call to the function at the given path, like `std::io::print`.
+fn call<'a>(
+    arena: &'a AstArena<'a>,
+    path: &str,
+    args: ThinVec<&'a mut Expr<'a>>,
+    span: Span,
+) -> ExprKind<'a> {
+    let segments = path
+        .split("::")
+        .map(|name| PathSegment {
+            ident: Ident {
+                name: name.into(),
+                span,
+            },
+        })
+        .collect();
+
+    ExprKind::Call {
+        fun: arena.alloc_expr(Expr {
+            kind: ExprKind::Variable(ast::Path { segments, span }),
+            span: span.into(),
+        }),
+        args,
+    }
+}
+
+/// Returns a call of the given method, which takes no arguments, on `expr`.
+fn method_call<'a>(expr: &'a mut Expr<'a>, method: &str) -> ExprKind<'a> {
+    let span = expr.span;
+
+    ExprKind::MethodCall(Box::new(ast::MethodCallExpr {
+        receiver: expr,
+        name: Ident {
+            name: method.into(),
+            span: span.get(),
+        },
+        args: ThinVec::new(),
+    }))
+}
+
+/// Returns the concatenation of the given strings, with
+/// `std::string::concat`.
+fn concat_strings<'a>(
+    arena: &'a AstArena<'a>,
+    parts: Vec<ExprKind<'a>>,
+    span: Span,
+) -> ExprKind<'a> {
+    let mut parts = parts.into_iter();
+
+    let Some(first) = parts.next() else {
+        return string_literal("");
+    };
+
+    parts.fold(first, |concatenated, part| {
+        call(
+            arena,
+            "std::string::concat",
+            thin_vec![
+                arena.alloc_expr(Expr {
+                    kind: concatenated,
+                    span: span.into(),
+                }),
+                arena.alloc_expr(Expr {
+                    kind: part,
+                    span: span.into()
+                }),
+            ],
+            span,
+        )
+    })
+}
+
+/// Checks that a macro call has no arguments.
+fn no_args(macro_call: &MacroCall, span: Span) -> MacroResult<()> {
+    if macro_call.args.is_empty() {
+        Ok(())
+    } else {
+        Err(MacroError {
+            message: format!("`{}!` does not take any arguments.", macro_call.name),
+            span,
+        })
+    }
+}
+
+/// Returns the type with the given name, like `Uint8`.
+fn named_ty<'a>(name: &str, span: Span) -> Ty<'a> {
+    Ty {
+        kind: TyKind::Path(ast::Path {
+            segments: thin_vec![PathSegment {
+                ident: Ident {
+                    name: name.into(),
+                    span,
+                },
+            }],
+            span,
+        }),
+        span,
+    }
+}
+
+/// Returns an integer literal holding `value`.
+fn integer_literal<'a>(value: impl ToString) -> ExprKind<'a> {
+    ExprKind::Literal(Literal {
+        kind: LiteralKind::Integer,
+        value: value.to_string().into(),
+    })
+}
+
+/// Returns a string literal holding `value`.
+fn string_literal<'a>(value: &str) -> ExprKind<'a> {
+    ExprKind::Literal(Literal {
+        kind: LiteralKind::String,
+        value: format!("\"{value}\"").into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ast::BinaryOp;
+    use crate::cfg::Cfg;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Expands the macros in `source`, returning the initializer of the first
+    /// `let` binding in `main`.
+    fn expand_init(source: &str) -> MacroResult<&'static Expr<'static>> {
+        expand_init_in(source, None)
+    }
+
+    /// Like [`expand_init`], but with `dir` as the directory of the source
+    /// file.
+    fn expand_init_in(source: &str, dir: Option<&Path>) -> MacroResult<&'static Expr<'static>> {
+        // The arena is leaked, so that the expression can outlive this
+        // function.
+        let arena = Box::leak(Box::new(AstArena::new()));
+        let mut items = Parser::new(Lexer::new(source), arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, arena)
+            .with_dir(dir)
+            .expand_items(&mut items)?;
+
+        let ItemKind::Fn(mut fun) = items.remove(0).kind else {
+            panic!("Expected `main` to be a function.");
+        };
+
+        let StmtKind::Local(local) = fun.body.remove(0).kind else {
+            panic!("Expected a `let` binding.");
+        };
+
+        let LocalKind::Init(init) = local.kind else {
+            panic!("Expected the `let` binding to have an initializer.");
+        };
+
+        Ok(init)
+    }
+
+    fn string_value<'e>(expr: &'e Expr) -> &'e str {
+        match &expr.kind {
+            ExprKind::Literal(Literal {
+                kind: LiteralKind::String,
+                value,
+            }) => value,
+            kind => panic!("Expected a string literal, but got {kind:?}."),
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let expr =
+            expand_init(r#"fn main() { let version = concat!("v", 1, ".", 2, concat!(".", 0)) }"#)
+                .unwrap();
+
+        assert_eq!(string_value(expr), "\"v1.2.0\"");
+    }
+
+    #[test]
+    fn test_concat_rejects_non_literals() {
+        let err = expand_init(r#"fn main() { let name = concat!("v", version) }"#).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "`concat!` can only concatenate string and integer literals."
+        );
+    }
+
+    #[test]
+    fn test_unknown_macro() {
+        let err = expand_init("fn main() { let value = frobnicate!(1) }").unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "Unknown macro `frobnicate!`. Expected one of: compile_error!, concat!, env!, file!, format!, include_bytes!, include_str!, line!, stringify!, todo!, unimplemented!."
+        );
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let err =
+            expand_init(r#"fn main() { let value = compile_error!("Only Linux is supported.") }"#)
+                .unwrap_err();
+
+        assert_eq!(err.message, "Only Linux is supported.");
+        assert_eq!(err.span, Span::new(24, 66));
+    }
+
+    #[test]
+    fn test_compile_error_in_disabled_item() {
+        let source = r#"
+            #[cfg(target_os = "windows")]
+            fn unsupported() {
+                compile_error!("Windows is not supported.")
+            }
+
+            fn main() {}
+        "#;
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let cfg = Cfg {
+            target_os: "linux".to_string(),
+            target_arch: "x86_64".to_string(),
+            debug: false,
+        };
+
+        cfg.strip_items(&mut items).unwrap();
+
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        assert!(MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_file_and_line() {
+        let expr = expand_init(r#"fn main() { let location = concat!(file!(), ":", 1) }"#).unwrap();
+
+        assert_eq!(string_value(expr), "\"main.crane:1\"");
+
+        let expr = expand_init("fn main() {\n    let line = line!()\n}").unwrap();
+
+        let ExprKind::Cast { expr, ty } = &expr.kind else {
+            panic!("Expected a cast, but got {:?}.", expr.kind);
+        };
+
+        assert!(matches!(&expr.kind, ExprKind::Literal(literal) if literal.value == "2"));
+        assert!(matches!(&ty.kind, TyKind::Path(path) if path.segments[0].ident.name == "Uint64"));
+    }
+
+    #[test]
+    fn test_line_takes_no_arguments() {
+        let err = expand_init("fn main() { let line = line!(1) }").unwrap_err();
+
+        assert_eq!(err.message, "`line!` does not take any arguments.");
+    }
+
+    #[test]
+    fn test_stringify() {
+        let expr =
+            expand_init("fn main() { let text = stringify!(add(1, 2) * env!(UNSET)) }").unwrap();
+
+        assert_eq!(string_value(expr), "\"add(1, 2) * env!(UNSET)\"");
+    }
+
+    /// Expands the `let` initializer in `main`, like [`expand_init`], in a
+    /// debug or release build, returning the path of the function it calls
+    /// and its arguments.
+    fn expand_call(source: &str, debug: bool) -> (String, Vec<&'static Expr<'static>>) {
+        let arena = Box::leak(Box::new(AstArena::new()));
+        let mut items = Parser::new(Lexer::new(source), arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, arena)
+            .with_debug(debug)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let ItemKind::Fn(mut fun) = items.remove(0).kind else {
+            panic!("Expected `main` to be a function.");
+        };
+
+        let StmtKind::Local(local) = fun.body.remove(0).kind else {
+            panic!("Expected a `let` binding.");
+        };
+
+        let LocalKind::Init(init) = local.kind else {
+            panic!("Expected the `let` binding to have an initializer.");
+        };
+
+        match &init.kind {
+            ExprKind::Call { fun, args } => match &fun.kind {
+                ExprKind::Variable(path) => (
+                    path.segments
+                        .iter()
+                        .map(|segment| segment.ident.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join("::"),
+                    args.iter().map(|arg| &**arg).collect(),
+                ),
+                kind => panic!("Expected a function path, but got {kind:?}."),
+            },
+            kind => panic!("Expected a call, but got {kind:?}."),
+        }
+    }
+
+    #[test]
+    fn test_todo() {
+        let (path, args) = expand_call("fn main() { let value = todo!() }", true);
+
+        assert_eq!(path, "std::process::todo");
+        assert!(args.is_empty());
+
+        let (path, args) = expand_call(r#"fn main() { let value = todo!("parsing") }"#, true);
+
+        assert_eq!(path, "std::process::abort");
+        assert_eq!(string_value(args[0]), "\"not yet implemented: parsing\"");
+
+        let (path, args) = expand_call(r#"fn main() { let value = todo!("parsing") }"#, false);
+
+        assert_eq!(path, "std::process::todo");
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_unimplemented() {
+        let (path, args) = expand_call(
+            r#"fn main() { let value = unimplemented!("floats") }"#,
+            true,
+        );
+
+        assert_eq!(path, "std::process::abort");
+        assert_eq!(string_value(args[0]), "\"not implemented: floats\"");
+
+        let (path, _) = expand_call("fn main() { let value = unimplemented!() }", false);
+
+        assert_eq!(path, "std::process::unimplemented");
+    }
+
+    #[test]
+    fn test_todo_expects_a_string_literal() {
+        let err = expand_init("fn main() { let value = todo!(1) }").unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "`todo!` expects a string literal with the message."
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let (path, args) = expand_call(
+            r#"fn main() { let sum = format!("{} + {} = {{}}", a, b) }"#,
+            false,
+        );
+
+        assert_eq!(path, "std::string::concat");
+        assert_eq!(string_value(args[1]), "\" = {}\"");
+
+        let ExprKind::Call { args, .. } = &args[0].kind else {
+            panic!("Expected a call of `std::string::concat`.");
+        };
+
+        let ExprKind::MethodCall(method_call) = &args[1].kind else {
+            panic!("Expected a call of `to_string`.");
+        };
+
+        assert_eq!(method_call.name.name, "to_string");
+        assert!(
+            matches!(&method_call.receiver.kind, ExprKind::Variable(path) if path.segments[0].ident.name == "b")
+        );
+    }
+
+    #[test]
+    fn test_format_debug() {
+        let (path, args) = expand_call(
+            r#"fn main() { let text = format!("name: {:?}", name) }"#,
+            false,
+        );
+
+        assert_eq!(path, "std::string::concat");
+
+        let ExprKind::MethodCall(method_call) = &args[1].kind else {
+            panic!("Expected a call of `debug_repr`.");
+        };
+
+        assert_eq!(method_call.name.name, "debug_repr");
+
+        let err = expand_init(r#"fn main() { let text = format!("{:x}", value) }"#).unwrap_err();
+
+        assert_eq!(err.message, "Expected `{:?}` in the template.");
+    }
+
+    #[test]
+    fn test_format_without_placeholders() {
+        let expr = expand_init(r#"fn main() { let text = format!("plain") }"#).unwrap();
+
+        assert_eq!(string_value(expr), "\"plain\"");
+    }
+
+    #[test]
+    fn test_format_argument_count() {
+        let err = expand_init(r#"fn main() { let text = format!("{}") }"#).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "The template has more `{}` than there are arguments."
+        );
+
+        let err = expand_init(r#"fn main() { let text = format!("{}", a, b) }"#).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "The template has fewer `{}` than there are arguments."
+        );
+    }
+
+    #[test]
+    fn test_format_unmatched_brace() {
+        let err = expand_init(r#"fn main() { let text = format!("{x}", x) }"#).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "Unmatched `{` in the template. A literal brace is written twice, like `{{`."
+        );
+    }
+
+    #[test]
+    fn test_derive_to_string() {
+        let source = "#[derive(ToString)]\nstruct Point {\n    x: Uint64,\n}\n\nfn main() {}\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let names = items
+            .iter()
+            .map(|item| item.name.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["Point", "Point", "main"]);
+
+        let ItemKind::Impl(impl_decl) = &items[1].kind else {
+            panic!("Expected an `impl` block after the struct.");
+        };
+
+        assert_eq!(impl_decl.trait_name.as_ref().unwrap().name, "ToString");
+        assert_eq!(impl_decl.items[0].name.name, "to_string");
+    }
+
+    #[test]
+    fn test_derive_debug() {
+        let source = "#[derive(Debug, ToString)]\nstruct Meters(Float64)\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let methods = items[1..]
+            .iter()
+            .map(|item| match &item.kind {
+                ItemKind::Impl(impl_decl) => impl_decl.items[0].name.to_string(),
+                kind => panic!("Expected an `impl` block, but got {kind:?}."),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(methods, ["debug_repr", "to_string"]);
+    }
+
+    #[test]
+    fn test_derive_clone() {
+        let source = "#[derive(Clone)]\nstruct User {\n    id: Uint64,\n    name: String,\n}\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let ItemKind::Impl(impl_decl) = &items[1].kind else {
+            panic!("Expected an `impl` block, but got {:?}.", items[1].kind);
+        };
+        let ItemKind::Fn(fun) = &impl_decl.items[0].kind else {
+            panic!("Expected `clone`, but got {:?}.", impl_decl.items[0].kind);
+        };
+        let Some(StmtKind::Expr(expr)) = fun.body.last().map(|stmt| &stmt.kind) else {
+            panic!("Expected `clone` to end in an expression.");
+        };
+        let ExprKind::Struct(struct_expr) = &expr.kind else {
+            panic!("Expected a struct expression, but got {expr:?}.");
+        };
+
+        // `User { id: self.id, name: self.name.clone() }`
+        let fields = struct_expr
+            .fields
+            .iter()
+            .map(|field| {
+                let is_cloned = matches!(
+                    &field.expr.kind,
+                    ExprKind::MethodCall(method_call) if method_call.name.name == "clone"
+                );
+
+                (field.name.to_string(), is_cloned)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fields,
+            [("id".to_string(), false), ("name".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_derive_hash() {
+        let source = "#[derive(Hash)]\nstruct User {\n    id: Uint64,\n    name: String,\n}\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let ItemKind::Impl(impl_decl) = &items[1].kind else {
+            panic!("Expected an `impl` block, but got {:?}.", items[1].kind);
+        };
+        let ItemKind::Fn(fun) = &impl_decl.items[0].kind else {
+            panic!("Expected `hash`, but got {:?}.", impl_decl.items[0].kind);
+        };
+        let Some(StmtKind::Expr(expr)) = fun.body.last().map(|stmt| &stmt.kind) else {
+            panic!("Expected `hash` to end in an expression.");
+        };
+
+        // `std::hash::combine(self.id.hash(), self.name.hash())`
+        let ExprKind::Call { fun: callee, args } = &expr.kind else {
+            panic!("Expected a call, but got {expr:?}.");
+        };
+        let ExprKind::Variable(path) = &callee.kind else {
+            panic!("Expected a path, but got {callee:?}.");
+        };
+
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(segments, ["std", "hash", "combine"]);
+
+        let hashed_fields = args
+            .iter()
+            .map(|arg| match &arg.kind {
+                ExprKind::MethodCall(method_call) if method_call.name.name == "hash" => {
+                    match &method_call.receiver.kind {
+                        ExprKind::Field(field) => field.name.to_string(),
+                        kind => panic!("Expected a field, but got {kind:?}."),
+                    }
+                }
+                kind => panic!("Expected a call of `hash`, but got {kind:?}."),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(hashed_fields, ["id", "name"]);
+    }
+
+    #[test]
+    fn test_derive_partial_eq() {
+        let source = "#[derive(PartialEq)]\nstruct Point {\n    x: Uint64,\n    y: Uint64,\n}\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap();
+
+        let ItemKind::Impl(impl_decl) = &items[1].kind else {
+            panic!("Expected an `impl` block, but got {:?}.", items[1].kind);
+        };
+        let ItemKind::Fn(fun) = &impl_decl.items[0].kind else {
+            panic!("Expected `eq`, but got {:?}.", impl_decl.items[0].kind);
+        };
+        let Some(StmtKind::Expr(expr)) = fun.body.last().map(|stmt| &stmt.kind) else {
+            panic!("Expected `eq` to end in an expression.");
+        };
+
+        // `self.x == other.x && self.y == other.y`
+        let ExprKind::Binary {
+            op: BinaryOp::And,
+            lhs,
+            rhs,
+        } = &expr.kind
+        else {
+            panic!("Expected the comparisons to be joined with `&&`, but got {expr:?}.");
+        };
+
+        for (comparison, field) in [(lhs, "x"), (rhs, "y")] {
+            let ExprKind::Binary {
+                op: BinaryOp::Eq,
+                lhs,
+                rhs,
+            } = &comparison.kind
+            else {
+                panic!("Expected `==`, but got {comparison:?}.");
+            };
+
+            for (operand, param) in [(lhs, "self"), (rhs, "other")] {
+                let ExprKind::Field(field_expr) = &operand.kind else {
+                    panic!("Expected a field access, but got {operand:?}.");
+                };
+
+                assert_eq!(field_expr.name.name, field);
+                assert!(
+                    matches!(&field_expr.expr.kind, ExprKind::Variable(path) if path.segments[0].ident.name == param)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_derive_unknown_trait() {
+        let source = "#[derive(ToString, Frobnicate)]\nstruct Point {\n    x: Uint64,\n}\n";
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+        let file = SourceFile::new("main.crane".to_string(), source.to_string());
+
+        let err = MacroExpander::new(&file, &arena)
+            .expand_items(&mut items)
+            .unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "`Frobnicate` can't be derived. Expected one of: `Clone`, `Debug`, `Hash`, `PartialEq`, `ToString`."
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        std::env::set_var("CRANE_TEST_ENV_MACRO", "1.2.0");
+
+        let expr =
+            expand_init(r#"fn main() { let version = env!("CRANE_TEST_ENV_MACRO") }"#).unwrap();
+
+        assert_eq!(string_value(expr), "\"1.2.0\"");
+    }
+
+    #[test]
+    fn test_env_not_set() {
+        let err = expand_init(r#"fn main() { let version = env!("CRANE_TEST_ENV_MACRO_UNSET") }"#)
+            .unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "The environment variable `CRANE_TEST_ENV_MACRO_UNSET` is not set."
+        );
+    }
+
+    #[test]
+    fn test_env_expects_a_string_literal() {
+        let err = expand_init("fn main() { let version = env!(VERSION) }").unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "`env!` expects a string literal with the name of an environment variable."
+        );
+    }
+
+    /// Returns an empty directory for the given test to include files from.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("crane-macro-tests").join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_include_str() {
+        let dir = test_dir("include_str");
+
+        std::fs::write(dir.join("greeting.txt"), "Hello, world!").unwrap();
+
+        let expr = expand_init_in(
+            r#"fn main() { let greeting = include_str!("greeting.txt") }"#,
+            Some(&dir),
+        )
+        .unwrap();
+
+        assert_eq!(string_value(expr), "\"Hello, world!\"");
+    }
+
+    #[test]
+    fn test_include_str_missing_file() {
+        let dir = test_dir("include_str_missing_file");
+
+        let err = expand_init_in(
+            r#"fn main() { let greeting = include_str!("missing.txt") }"#,
+            Some(&dir),
+        )
+        .unwrap_err();
+
+        assert!(err.message.starts_with(&format!(
+            "Failed to read `{}`",
+            dir.join("missing.txt").display()
+        )));
+    }
+
+    #[test]
+    fn test_include_str_without_a_file() {
+        let err = expand_init(r#"fn main() { let greeting = include_str!("greeting.txt") }"#)
+            .unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "`include_str!` can only be used when compiling a file."
+        );
+    }
+
+    #[test]
+    fn test_include_bytes() {
+        let dir = test_dir("include_bytes");
+
+        std::fs::write(dir.join("bytes.bin"), [0xCA, 0xFE]).unwrap();
+
+        let expr = expand_init_in(
+            r#"fn main() { let bytes = include_bytes!("bytes.bin") }"#,
+            Some(&dir),
+        )
+        .unwrap();
+
+        let ExprKind::Array(elems) = &expr.kind else {
+            panic!("Expected an array, got {:?}", expr.kind);
+        };
+
+        let ExprKind::Cast { expr: first, ty } = &elems[0].kind else {
+            panic!(
+                "Expected the first byte to be cast, got {:?}",
+                elems[0].kind
+            );
+        };
+
+        assert!(matches!(&ty.kind, TyKind::Path(path) if path.segments[0].ident.name == "Uint8"));
+        assert!(matches!(&first.kind, ExprKind::Literal(literal) if literal.value == "202"));
+        assert!(matches!(&elems[1].kind, ExprKind::Literal(literal) if literal.value == "254"));
+        assert_eq!(elems.len(), 2);
+    }
+
+    #[test]
+    fn test_include_bytes_empty_file() {
+        let dir = test_dir("include_bytes_empty_file");
+
+        std::fs::write(dir.join("empty.bin"), []).unwrap();
+
+        let err = expand_init_in(
+            r#"fn main() { let bytes = include_bytes!("empty.bin") }"#,
+            Some(&dir),
+        )
+        .unwrap_err();
+
+        assert!(err
+            .message
+            .ends_with("is empty, and the type of an empty array can't be inferred."));
+    }
+}
@@ -0,0 +1,554 @@
+//! The `impl` blocks generated by `#[derive(Trait)]` on a `struct`.
+
+use thin_vec::{thin_vec, ThinVec};
+
+use crate::ast::{
+    AstArena, Attribute, BinaryOp, Expr, ExprKind, FieldDecl, FieldExpr, Fn, FnDecl, FnParam,
+    FnReturnTy, Ident, ImplDecl, Item, ItemKind, Local, LocalKind, Path, PathSegment, Span, Stmt,
+    StmtKind, StructExpr, StructExprField, TyKind, VariantData, Visibility,
+};
+
+use super::{
+    call, concat_strings, integer_literal, method_call, named_ty, string_literal, MacroError,
+    MacroResult,
+};
+
+/// The traits that can be derived.
+pub const DERIVES: [&str; 5] = ["Clone", "Debug", "Hash", "PartialEq", "ToString"];
+
+/// The types that a derived `Clone` copies as they are, rather than cloning.
+const COPIED_TYS: [&str; 10] = [
+    "Bool", "Int8", "Int16", "Int32", "Int64", "Uint8", "Uint16", "Uint32", "Uint64", "Float64",
+];
+
+/// Returns the `impl` blocks derived for the given item by its `#[derive]`
+/// attributes, allocating their expressions in `arena`.
+pub fn derive_impls<'a>(item: &Item<'a>, arena: &'a AstArena<'a>) -> MacroResult<Vec<Item<'a>>> {
+    let mut impls = Vec::new();
+
+    for attr in item.attrs.iter().filter(|attr| attr.name.name == "derive") {
+        let ItemKind::Struct(struct_decl) = &item.kind else {
+            return Err(MacroError {
+                message: "`#[derive]` can only be applied to a struct.".to_string(),
+                span: attr.span,
+            });
+        };
+
+        for trait_name in derived_traits(attr)? {
+            let fun = match trait_name.name.as_str() {
+                "Clone" => derive_clone(arena, &item.name, &struct_decl.0),
+                "Debug" => derive_repr(arena, "debug_repr", &item.name, &struct_decl.0),
+                "Hash" => derive_hash(arena, &item.name, &struct_decl.0),
+                "PartialEq" => derive_eq(arena, &item.name, &struct_decl.0),
+                "ToString" => derive_repr(arena, "to_string", &item.name, &struct_decl.0),
+                _ => {
+                    return Err(MacroError {
+                        message: format!(
+                            "`{trait_name}` can't be derived. Expected one of: {}.",
+                            DERIVES.map(|name| format!("`{name}`")).join(", ")
+                        ),
+                        span: trait_name.span,
+                    });
+                }
+            };
+
+            impls.push(Item {
+                kind: ItemKind::Impl(Box::new(ImplDecl {
+                    trait_name: Some(trait_name.clone()),
+                    items: thin_vec![fun],
+                })),
+                name: item.name.clone(),
+                vis: item.vis,
+                attrs: ThinVec::new(),
+                docs: ThinVec::new(),
+            });
+        }
+    }
+
+    Ok(impls)
+}
+
+/// Returns the names of the traits in a `#[derive(A, B)]` attribute.
+fn derived_traits(attr: &Attribute) -> MacroResult<Vec<&Ident>> {
+    if attr.args.is_empty()
+        || attr.value.is_some()
+        || attr.args.iter().any(|arg| arg.value.is_some())
+    {
+        return Err(MacroError {
+            message: "`#[derive]` expects the traits to derive, like `#[derive(ToString)]`."
+                .to_string(),
+            span: attr.span,
+        });
+    }
+
+    Ok(attr.args.iter().map(|arg| &arg.name).collect())
+}
+
+/// Derives `ToString` or `Debug`, whose `method` writes the name of the struct
+/// and its fields, like `Point { x: 1, y: 2 }` or `Meters(1.5)`. The fields are
+/// written with the same method.
+fn derive_repr<'a>(
+    arena: &'a AstArena<'a>,
+    method: &str,
+    name: &Ident,
+    variant_data: &VariantData,
+) -> Item<'a> {
+    let span = name.span;
+
+    let self_expr = || {
+        arena.alloc_expr(Expr {
+            kind: variable(&ident("self", span)),
+            span: span.into(),
+        })
+    };
+
+    let mut body = ThinVec::new();
+    let mut parts = Vec::new();
+
+    match variant_data {
+        VariantData::Struct(fields) if fields.is_empty() => {
+            parts.push(string_literal(&format!("{name} {{}}")));
+        }
+        VariantData::Struct(fields) => {
+            for (index, field) in fields.iter().enumerate() {
+                let field_name = field.name.clone().expect("struct fields are named");
+
+                let prefix = match index {
+                    0 => format!("{name} {{ "),
+                    _ => ", ".to_string(),
+                };
+
+                parts.push(string_literal(&format!("{prefix}{field_name}: ")));
+                parts.push(method_call(
+                    arena.alloc_expr(Expr {
+                        kind: ExprKind::Field(Box::new(FieldExpr {
+                            expr: self_expr(),
+                            name: field_name,
+                        })),
+                        span: field.span.into(),
+                    }),
+                    method,
+                ));
+            }
+
+            parts.push(string_literal(" }"));
+        }
+        VariantData::Tuple(fields) => {
+            // A tuple struct has a single field, which is destructured to
+            // reach it.
+            let value = ident("value", span);
+
+            body.push(destructure(name, &value, self_expr()));
+
+            parts.push(string_literal(&format!("{name}(")));
+
+            for field in fields {
+                parts.push(method_call(
+                    arena.alloc_expr(Expr {
+                        kind: variable(&value),
+                        span: field.span.into(),
+                    }),
+                    method,
+                ));
+            }
+
+            parts.push(string_literal(")"));
+        }
+        VariantData::Unit => parts.push(string_literal(&name.name)),
+    }
+
+    body.push(Stmt {
+        kind: StmtKind::Expr(arena.alloc_expr(Expr {
+            kind: concat_strings(arena, parts, span),
+            span: span.into(),
+        })),
+        span: span.into(),
+    });
+
+    let decl = FnDecl {
+        params: thin_vec![FnParam {
+            name: ident("self", span),
+            ty: Box::new(named_ty(&name.name, span)),
+            span,
+        }],
+        return_ty: FnReturnTy::Ty(Box::new(named_ty("String", span))),
+    };
+
+    fn_item(method, decl, body, span)
+}
+
+/// Derives `Clone`, whose `clone` returns a new struct with a clone of each
+/// field. The fields of primitive, array, and function types are copied.
+fn derive_clone<'a>(arena: &'a AstArena<'a>, name: &Ident, variant_data: &VariantData) -> Item<'a> {
+    let span = name.span;
+
+    let self_param = ident("self", span);
+
+    let mut body = ThinVec::new();
+
+    let clone_field = |field: &FieldDecl, value: &'a mut Expr<'a>| {
+        let is_copied = match &field.ty.kind {
+            TyKind::Path(path) => {
+                path.segments.len() == 1
+                    && COPIED_TYS.contains(&path.segments[0].ident.name.as_str())
+            }
+            TyKind::Fn(_) | TyKind::Array { .. } => true,
+        };
+
+        if is_copied {
+            return value;
+        }
+
+        arena.alloc_expr(Expr {
+            kind: method_call(value, "clone"),
+            span: field.span.into(),
+        })
+    };
+
+    let clone = match variant_data {
+        VariantData::Tuple(fields) => {
+            // A tuple struct is destructured to reach its field, and then
+            // wrapped back up.
+            let value = ident("value", span);
+
+            body.push(destructure(
+                name,
+                &value,
+                arena.alloc_expr(Expr {
+                    kind: variable(&self_param),
+                    span: span.into(),
+                }),
+            ));
+
+            let args = fields
+                .iter()
+                .map(|field| {
+                    let value = arena.alloc_expr(Expr {
+                        kind: variable(&value),
+                        span: field.span.into(),
+                    });
+
+                    clone_field(field, value)
+                })
+                .collect();
+
+            call(arena, &name.name, args, span)
+        }
+        VariantData::Struct(fields) => ExprKind::Struct(Box::new(StructExpr {
+            path: path(name),
+            fields: fields
+                .iter()
+                .map(|field| {
+                    let field_name = field.name.clone().expect("struct fields are named");
+
+                    let value = arena.alloc_expr(Expr {
+                        kind: ExprKind::Field(Box::new(FieldExpr {
+                            expr: arena.alloc_expr(Expr {
+                                kind: variable(&self_param),
+                                span: span.into(),
+                            }),
+                            name: field_name.clone(),
+                        })),
+                        span: field.span.into(),
+                    });
+
+                    StructExprField {
+                        name: field_name,
+                        expr: clone_field(field, value),
+                        span: field.span,
+                    }
+                })
+                .collect(),
+        })),
+        VariantData::Unit => variable(name),
+    };
+
+    body.push(Stmt {
+        kind: StmtKind::Expr(arena.alloc_expr(Expr {
+            kind: clone,
+            span: span.into(),
+        })),
+        span: span.into(),
+    });
+
+    let decl = FnDecl {
+        params: thin_vec![FnParam {
+            name: self_param,
+            ty: Box::new(named_ty(&name.name, span)),
+            span,
+        }],
+        return_ty: FnReturnTy::Ty(Box::new(named_ty(&name.name, span))),
+    };
+
+    fn_item("clone", decl, body, span)
+}
+
+/// Derives `Hash`, whose `hash` mixes the hashes of the fields together in
+/// order with `std::hash::combine`.
+fn derive_hash<'a>(arena: &'a AstArena<'a>, name: &Ident, variant_data: &VariantData) -> Item<'a> {
+    let span = name.span;
+
+    let self_param = ident("self", span);
+
+    let mut body = ThinVec::new();
+
+    let hashes = match variant_data {
+        VariantData::Struct(fields) => fields
+            .iter()
+            .map(|field| {
+                let field_name = field.name.clone().expect("struct fields are named");
+
+                let value = arena.alloc_expr(Expr {
+                    kind: ExprKind::Field(Box::new(FieldExpr {
+                        expr: arena.alloc_expr(Expr {
+                            kind: variable(&self_param),
+                            span: span.into(),
+                        }),
+                        name: field_name,
+                    })),
+                    span: field.span.into(),
+                });
+
+                Expr {
+                    kind: method_call(value, "hash"),
+                    span: field.span.into(),
+                }
+            })
+            .collect(),
+        VariantData::Tuple(fields) => {
+            // A tuple struct is destructured to reach its field.
+            let value = ident("value", span);
+
+            body.push(destructure(
+                name,
+                &value,
+                arena.alloc_expr(Expr {
+                    kind: variable(&self_param),
+                    span: span.into(),
+                }),
+            ));
+
+            fields
+                .iter()
+                .map(|field| {
+                    let value = arena.alloc_expr(Expr {
+                        kind: variable(&value),
+                        span: field.span.into(),
+                    });
+
+                    Expr {
+                        kind: method_call(value, "hash"),
+                        span: field.span.into(),
+                    }
+                })
+                .collect()
+        }
+        VariantData::Unit => Vec::new(),
+    };
+
+    let hash = hashes
+        .into_iter()
+        .reduce(|hash, value| Expr {
+            kind: call(
+                arena,
+                "std::hash::combine",
+                thin_vec![arena.alloc_expr(hash), arena.alloc_expr(value)],
+                span,
+            ),
+            span: span.into(),
+        })
+        .unwrap_or(Expr {
+            kind: integer_literal(0),
+            span: span.into(),
+        });
+
+    body.push(Stmt {
+        kind: StmtKind::Expr(arena.alloc_expr(hash)),
+        span: span.into(),
+    });
+
+    let decl = FnDecl {
+        params: thin_vec![FnParam {
+            name: self_param,
+            ty: Box::new(named_ty(&name.name, span)),
+            span,
+        }],
+        return_ty: FnReturnTy::Ty(Box::new(named_ty("Uint64", span))),
+    };
+
+    fn_item("hash", decl, body, span)
+}
+
+/// Derives `PartialEq`, whose `eq` compares each field of `self` to the same
+/// field of `other` with `==`, joined with `&&`.
+fn derive_eq<'a>(arena: &'a AstArena<'a>, name: &Ident, variant_data: &VariantData) -> Item<'a> {
+    let span = name.span;
+
+    let self_param = ident("self", span);
+    let other_param = ident("other", span);
+
+    let mut body = ThinVec::new();
+
+    let comparisons = match variant_data {
+        VariantData::Struct(fields) => fields
+            .iter()
+            .map(|field| {
+                let field_name = field.name.clone().expect("struct fields are named");
+
+                let field_of = |param: &Ident| Expr {
+                    kind: ExprKind::Field(Box::new(FieldExpr {
+                        expr: arena.alloc_expr(Expr {
+                            kind: variable(param),
+                            span: span.into(),
+                        }),
+                        name: field_name.clone(),
+                    })),
+                    span: field.span.into(),
+                };
+
+                binary(
+                    arena,
+                    BinaryOp::Eq,
+                    field_of(&self_param),
+                    field_of(&other_param),
+                    field.span,
+                )
+            })
+            .collect(),
+        VariantData::Tuple(fields) => {
+            // Both tuple structs are destructured to reach their field.
+            let value = ident("value", span);
+            let other_value = ident("other_value", span);
+
+            for (binding, param) in [(&value, &self_param), (&other_value, &other_param)] {
+                let param = arena.alloc_expr(Expr {
+                    kind: variable(param),
+                    span: span.into(),
+                });
+
+                body.push(destructure(name, binding, param));
+            }
+
+            fields
+                .iter()
+                .map(|field| {
+                    let field_of = |binding: &Ident| Expr {
+                        kind: variable(binding),
+                        span: field.span.into(),
+                    };
+
+                    binary(
+                        arena,
+                        BinaryOp::Eq,
+                        field_of(&value),
+                        field_of(&other_value),
+                        field.span,
+                    )
+                })
+                .collect()
+        }
+        VariantData::Unit => Vec::new(),
+    };
+
+    let eq = comparisons
+        .into_iter()
+        .reduce(|lhs, rhs| binary(arena, BinaryOp::And, lhs, rhs, span))
+        .unwrap_or_else(|| {
+            // Structs without fields are always equal, but there are no
+            // `Bool` literals to say so with.
+            let zero = || Expr {
+                kind: integer_literal(0),
+                span: span.into(),
+            };
+
+            binary(arena, BinaryOp::Eq, zero(), zero(), span)
+        });
+
+    body.push(Stmt {
+        kind: StmtKind::Expr(arena.alloc_expr(eq)),
+        span: span.into(),
+    });
+
+    let decl = FnDecl {
+        params: [self_param, other_param]
+            .into_iter()
+            .map(|param| FnParam {
+                name: param,
+                ty: Box::new(named_ty(&name.name, span)),
+                span,
+            })
+            .collect(),
+        return_ty: FnReturnTy::Ty(Box::new(named_ty("Bool", span))),
+    };
+
+    fn_item("eq", decl, body, span)
+}
+
+/// Returns `let Name(binding) = expr`, destructuring the tuple struct `expr`.
+fn destructure<'a>(name: &Ident, binding: &Ident, expr: &'a mut Expr<'a>) -> Stmt<'a> {
+    let span = binding.span;
+
+    Stmt {
+        kind: StmtKind::Local(Box::new(Local {
+            kind: LocalKind::Init(expr),
+            name: binding.clone(),
+            ty: None,
+            destructure: Some(path(name)),
+            span,
+        })),
+        span: span.into(),
+    }
+}
+
+fn binary<'a>(
+    arena: &'a AstArena<'a>,
+    op: BinaryOp,
+    lhs: Expr<'a>,
+    rhs: Expr<'a>,
+    span: Span,
+) -> Expr<'a> {
+    Expr {
+        kind: ExprKind::Binary {
+            op,
+            lhs: arena.alloc_expr(lhs),
+            rhs: arena.alloc_expr(rhs),
+        },
+        span: span.into(),
+    }
+}
+
+/// Returns a method with the given declaration and body.
+fn fn_item<'a>(name: &str, decl: FnDecl<'a>, body: ThinVec<Stmt<'a>>, span: Span) -> Item<'a> {
+    Item {
+        kind: ItemKind::Fn(Box::new(Fn {
+            decl: Box::new(decl),
+            body,
+            is_unsafe: false,
+        })),
+        name: ident(name, span),
+        vis: Visibility::Public,
+        attrs: ThinVec::new(),
+        docs: ThinVec::new(),
+    }
+}
+
+fn ident(name: &str, span: Span) -> Ident {
+    Ident {
+        name: name.into(),
+        span,
+    }
+}
+
+/// Returns the single-segment path naming `ident`.
+fn path(ident: &Ident) -> Path {
+    Path {
+        segments: thin_vec![PathSegment {
+            ident: ident.clone(),
+        }],
+        span: ident.span,
+    }
+}
+
+fn variable<'a>(ident: &Ident) -> ExprKind<'a> {
+    ExprKind::Variable(path(ident))
+}
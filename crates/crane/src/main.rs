@@ -1,119 +1,1874 @@
-mod ast;
-mod backend;
-mod compiler;
-mod lexer;
-mod parser;
-mod typer;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Instant;
 
-use std::io::Write;
-use std::path::PathBuf;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use inkwell::targets::TargetMachine;
+use thiserror::Error;
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Layer;
 
-use clap::{Parser, Subcommand};
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
+use crane::ast::printer::{print_items, print_ty_package};
+use crane::ast::AstArena;
+use crane::backend::cache::{CompilationCache, CACHE_ENTRIES};
+use crane::build_script::{run_build_script, BUILD_SCRIPT};
+use crane::compiler::{
+    default_threads, par_map, report_parse_error, CompileOptions, CompileParams, Compiler, Failure,
+    Input, OutputKind, STDIN_FILENAME,
+};
+use crane::diagnostic::ColorChoice;
+use crane::doc::document_package;
+use crane::error::CraneError;
+use crane::error_codes::ErrorCode;
+use crane::formatter::{format_source, FormatError};
+use crane::ice::{self, PhaseLayer};
+use crane::lexer::{print_tokens, tokenize};
+use crane::lint::{LintLevel, LintLevels};
+use crane::manifest::{find_manifest, CraneManifest, DEFAULT_ENTRY, DEFAULT_OUT_DIR, MANIFEST};
+use crane::parser::ParseError;
+use crane::repl::Repl;
+use crane::scaffold::{init_package, new_package, PackageKind};
+use crane::symbols::package_symbols;
+use crane::test_harness::test_fns;
+use crane::timings::{self, Timings, TimingsLayer};
+use crane::watch::Watcher;
 
-use crate::compiler::{CompileParams, Compiler, Input};
+/// The exit codes of `crane`, which tell the ways that it can fail apart.
+const EXIT_CODES: &str = "\
+Exit codes:
+  0    Success
+  1    Any other failure, like a missing file or a failing test
+  2    Invalid arguments
+  3    Syntax errors, including invalid macros and modules
+  4    Type errors, including lints denied with `-D`
+  5    Code generation errors
+  6    Link failures
+  101  Internal compiler errors, which are bugs in the compiler
+
+`crane run` exits with the exit code of the program, once it runs.";
+
+/// The exit code for invalid arguments, which `clap` exits with too.
+const USAGE_ERROR: u8 = 2;
+
+/// The exit code for a panic in the compiler.
+const INTERNAL_COMPILER_ERROR: u8 = 101;
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, after_help = EXIT_CODES)]
 struct Args {
     #[command(subcommand)]
     pub command: Command,
+
+    /// Prints logs of what the compiler is doing. `-v` prints the main
+    /// steps, and `-vv` everything.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Prints how long each phase of the compiler took, once it is done.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "human",
+        require_equals = true
+    )]
+    pub timings: Option<TimingsFormat>,
+
+    /// When to color diagnostics.
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+}
+
+/// When to color diagnostics, set with `--color`.
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ColorWhen {
+    /// Color diagnostics when stderr is a terminal and `NO_COLOR` isn't set.
+    Auto,
+
+    /// Always color diagnostics, even when stderr isn't a terminal.
+    Always,
+
+    /// Never color diagnostics.
+    Never,
+}
+
+impl From<ColorWhen> for ColorChoice {
+    fn from(when: ColorWhen) -> Self {
+        match when {
+            ColorWhen::Auto => ColorChoice::Auto,
+            ColorWhen::Always => ColorChoice::Always,
+            ColorWhen::Never => ColorChoice::Never,
+        }
+    }
+}
+
+/// The format of the `--timings` summary.
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum TimingsFormat {
+    /// A table with one phase per line.
+    Human,
+
+    /// A JSON array of `{ "phase": "parse", "seconds": 0.0012 }` objects.
+    Json,
+}
+
+/// Prints the [`Timings`] to stderr when dropped, so that they are printed at
+/// the end of `main` however it returns.
+struct TimingsReport {
+    timings: Timings,
+    format: TimingsFormat,
+}
+
+impl Drop for TimingsReport {
+    fn drop(&mut self) {
+        match self.format {
+            TimingsFormat::Human => eprint!("{}", self.timings.summary()),
+            TimingsFormat::Json => eprintln!("{}", self.timings.to_json()),
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    /// Creates a new Crane project.
+    /// Creates a new Crane project in a new directory.
     New {
         /// The path at which to create the project.
         path: PathBuf,
+
+        /// Creates a library, instead of an executable.
+        #[arg(long)]
+        lib: bool,
+
+        /// The name of the package. Defaults to the name of the directory.
+        #[arg(long)]
+        name: Option<String>,
     },
 
-    /// Compiles the current project.
+    /// Creates a new Crane project in an existing directory, without
+    /// overwriting any of its files.
+    Init {
+        /// The directory to create the project in. Defaults to the current
+        /// directory.
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Creates a library, instead of an executable.
+        #[arg(long)]
+        lib: bool,
+
+        /// The name of the package. Defaults to the name of the directory.
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Compiles a file, or the current project.
     Build {
+        /// The files, or package directories, to compile. Defaults to the
+        /// entry point in `Crane.toml`.
+        ///
+        /// More than one file is compiled in parallel, each into artifacts
+        /// named after it.
+        files: Vec<PathBuf>,
+
         /// Builds the given example.
-        #[arg(long)]
+        #[arg(long, conflicts_with = "files")]
         example: Option<String>,
+
+        /// Reads the source code to compile from stdin, as a file named
+        /// `<stdin>`.
+        #[arg(long, conflicts_with_all = ["files", "example"])]
+        stdin: bool,
+
+        /// The artifacts to emit, separated by commas.
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [Emit::LlvmIr, Emit::Obj, Emit::Link]
+        )]
+        emit: Vec<Emit>,
+
+        /// The optimization level, from 0 to 3. `-O` on its own is `-O2`.
+        /// Defaults to the `opt-level` in `Crane.toml`, or 0.
+        #[arg(
+            short = 'O',
+            num_args = 0..=1,
+            default_missing_value = "2",
+            value_parser = clap::value_parser!(u8).range(0..=3)
+        )]
+        opt_level: Option<u8>,
+
+        /// The target triple to compile for. Defaults to the `target` in
+        /// `Crane.toml`, or the host.
+        #[arg(long)]
+        target: Option<String>,
+
+        /// The kind of artifact to build. Defaults to the `output` in
+        /// `Crane.toml`, or an executable.
+        #[arg(long, value_enum)]
+        crate_type: Option<CrateType>,
+
+        /// Prints the memory layout of every struct and union type.
+        #[arg(long)]
+        print_layout: bool,
+
+        /// Sets a code generation option, like `-C target-cpu=native`.
+        #[arg(short = 'C')]
+        codegen: Vec<CodegenOption>,
+
+        /// Builds in debug mode, enabling `#[cfg(debug)]` items.
+        #[arg(long)]
+        debug: bool,
+
+        /// The directory to write the artifacts to. Defaults to the `out-dir`
+        /// in `Crane.toml`, or `build`.
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        #[command(flatten)]
+        parallelism: CodegenArgs,
+
+        /// Recompiles every module, instead of reusing the unchanged ones from
+        /// the cache in `~/.crane/cache`.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Links against the given native library.
+        #[arg(short = 'l')]
+        libs: Vec<String>,
+
+        /// Adds a directory to search for native libraries in.
+        #[arg(short = 'L')]
+        lib_dirs: Vec<PathBuf>,
+
+        /// Rebuilds whenever a source file changes, until interrupted.
+        #[arg(long, conflicts_with = "stdin")]
+        watch: bool,
+
+        #[command(flatten)]
+        lints: LintArgs,
     },
 
-    /// Runs the current project.
+    /// Compiles and runs a file, or the current project.
+    ///
+    /// Exits with the exit code of the program.
     Run {
+        /// The file, or package directory, to run. Defaults to the entry
+        /// point in `Crane.toml`.
+        file: Option<PathBuf>,
+
         /// Runs the given example.
+        #[arg(long, conflicts_with = "file")]
+        example: Option<String>,
+
+        /// Sets a code generation option, like `-C target-cpu=native`.
+        #[arg(short = 'C')]
+        codegen: Vec<CodegenOption>,
+
+        /// Runs in debug mode, enabling `#[cfg(debug)]` items.
         #[arg(long)]
+        debug: bool,
+
+        /// Keeps the artifacts in the `out-dir` in `Crane.toml`, or `build`,
+        /// instead of building in a temporary directory that is removed once
+        /// the program exits.
+        #[arg(long)]
+        keep_artifacts: bool,
+
+        /// The directory to write the artifacts to, which are kept.
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        #[command(flatten)]
+        parallelism: CodegenArgs,
+
+        /// Recompiles every module, instead of reusing the unchanged ones from
+        /// the cache in `~/.crane/cache`.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Links against the given native library.
+        #[arg(short = 'l')]
+        libs: Vec<String>,
+
+        /// Adds a directory to search for native libraries in.
+        #[arg(short = 'L')]
+        lib_dirs: Vec<PathBuf>,
+
+        /// Rebuilds and reruns the program whenever a source file changes,
+        /// until interrupted. The previous run is killed first.
+        #[arg(long)]
+        watch: bool,
+
+        #[command(flatten)]
+        lints: LintArgs,
+
+        /// The arguments to pass to the program, after `--`.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Compiles and runs the `#[test]` functions in a file, or the current
+    /// project.
+    ///
+    /// Each test runs in a process of its own, so one that aborts doesn't stop
+    /// the others. Exits with an error if any test fails.
+    Test {
+        /// The file, or package directory, to test. Defaults to the entry
+        /// point in `Crane.toml`.
+        file: Option<PathBuf>,
+
+        /// Runs the tests in debug mode, enabling `#[cfg(debug)]` items.
+        #[arg(long)]
+        debug: bool,
+
+        #[command(flatten)]
+        lints: LintArgs,
+
+        /// Only runs the tests whose path contains one of the filters, after
+        /// `--`.
+        #[arg(last = true)]
+        filters: Vec<String>,
+    },
+
+    /// Checks a file, or the current project, for errors without generating
+    /// any code.
+    Check {
+        /// The files, or package directories, to check. Defaults to the entry
+        /// point in `Crane.toml`.
+        ///
+        /// More than one file is checked in parallel.
+        files: Vec<PathBuf>,
+
+        /// Checks the given example.
+        #[arg(long, conflicts_with = "files")]
         example: Option<String>,
+
+        /// Reads the source code to check from stdin, as a file named
+        /// `<stdin>`.
+        #[arg(long, conflicts_with_all = ["files", "example"])]
+        stdin: bool,
+
+        /// Checks in debug mode, enabling `#[cfg(debug)]` items.
+        #[arg(long)]
+        debug: bool,
+
+        #[command(flatten)]
+        threads: ThreadsArgs,
+
+        #[command(flatten)]
+        lints: LintArgs,
+    },
+
+    /// Checks the style of a file, or the current project, with the lints
+    /// that don't need type checking, like `non-snake-case`.
+    ///
+    /// Only parses the code and loads its modules, so it finds nothing that
+    /// `crane check` would.
+    Lint {
+        /// The file, or package directory, to lint. Defaults to the entry
+        /// point in `Crane.toml`.
+        file: Option<PathBuf>,
+
+        #[command(flatten)]
+        lints: LintArgs,
+    },
+
+    /// Generates HTML documentation from the doc comments in a file, or the
+    /// current project.
+    Doc {
+        /// The file, or package directory, to document. Defaults to the entry
+        /// point in `Crane.toml`.
+        file: Option<PathBuf>,
+
+        /// The directory to write the documentation to. Defaults to `doc` in
+        /// the `out-dir` in `Crane.toml`, or `build/doc`.
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Documents private items, which are left out by default.
+        #[arg(long)]
+        document_private_items: bool,
+    },
+
+    /// Prints the items that a file, or the current project, defines as JSON,
+    /// with their kinds, visibility, and locations.
+    Symbols {
+        /// The file, or package directory, to list the items of. Defaults to
+        /// the entry point in `Crane.toml`.
+        file: Option<PathBuf>,
+
+        /// Also lists the let-bindings in the body of each function.
+        #[arg(long)]
+        include_locals: bool,
+    },
+
+    /// Removes the artifacts and the incremental cache of the current
+    /// project, which are in the `out-dir` in `Crane.toml`, or `build`.
+    ///
+    /// The cache in `~/.crane/cache` is shared by every project, so it's kept
+    /// unless `--cache` is given. Nothing else outside the project is ever
+    /// removed. `-v` prints each directory that is removed.
+    Clean {
+        /// Also removes the entries in `~/.crane/cache` that the project's
+        /// builds used. Other projects that used them compile them again.
+        #[arg(long)]
+        cache: bool,
+
+        /// Prints what would be removed, without removing it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Formats Crane source files in place.
+    Fmt {
+        /// The files to format.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Checks that the files are formatted, without changing them.
+        ///
+        /// Exits with an error if any file would be changed.
+        #[arg(long)]
+        check: bool,
     },
+
+    /// Explains an error code, like `E0204`, with an example of code that
+    /// causes it and how to fix it.
+    Explain {
+        /// The error code, as shown in brackets before the error.
+        code: String,
+    },
+
+    /// Starts a language server, speaking the Language Server Protocol over
+    /// stdio.
+    Lsp,
+
+    /// Starts an interactive session, which defines the items entered and
+    /// runs the statements, printing their value.
+    Repl,
+}
+
+/// The `-A`, `-W` and `-D` flags, which set the levels of lints.
+#[derive(clap::Args, Debug)]
+struct LintArgs {
+    /// Allows the given lint, or every lint with `warnings`.
+    #[arg(short = 'A', value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Reports the given lint, or every lint with `warnings`, as a warning.
+    #[arg(short = 'W', value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Reports the given lint, or every lint with `warnings`, as an error.
+    #[arg(short = 'D', value_name = "LINT")]
+    deny: Vec<String>,
 }
 
-fn main() {
+impl LintArgs {
+    /// Returns the levels of the lints set by the flags, warning about any lint
+    /// that doesn't exist.
+    fn lint_levels(self) -> LintLevels {
+        let mut lint_levels = LintLevels::default();
+
+        for (level, names) in [
+            (LintLevel::Allow, self.allow),
+            (LintLevel::Warn, self.warn),
+            (LintLevel::Deny, self.deny),
+        ] {
+            for name in names {
+                if let Err(err) = lint_levels.set(&name, level) {
+                    eprintln!("Warning: {err}");
+                }
+            }
+        }
+
+        lint_levels
+    }
+}
+
+/// The `--jobs` and `--threads` flags of the commands that generate code.
+#[derive(clap::Args, Debug)]
+struct CodegenArgs {
+    /// The number of compilation units to generate code for in parallel, at
+    /// most `--threads`. Defaults to the number of CPUs.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    #[command(flatten)]
+    threads: ThreadsArgs,
+}
+
+impl CodegenArgs {
+    fn jobs(&self) -> usize {
+        self.jobs.unwrap_or_else(default_jobs)
+    }
+}
+
+/// The `--threads` flag.
+#[derive(clap::Args, Debug)]
+struct ThreadsArgs {
+    /// The number of threads to compile on. More than one input, the module
+    /// files they declare, and the units that code is generated for are
+    /// compiled on them in parallel. `--threads 1` compiles everything on the
+    /// current thread, in order. Defaults to the number of CPUs.
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+impl ThreadsArgs {
+    fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(default_threads)
+    }
+}
+
+/// A code generation option, set with `-C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CodegenOption {
+    /// `tailcallelim`: Runs the `tailcallelim` pass, which turns self-recursive
+    /// tail calls into loops.
+    Tailcallelim,
+
+    /// `lto`: Performs link-time optimization across compilation units.
+    Lto,
+
+    /// `target-cpu=<cpu>`: Generates code for the given CPU, or the host CPU
+    /// with `native`.
+    TargetCpu(String),
+
+    /// `target-feature=<features>`: Enables or disables target features, like
+    /// `+avx2,-sse4.1`.
+    TargetFeature(String),
+}
+
+/// A `-C` flag named a code generation option that doesn't exist.
+#[derive(Error, Debug)]
+#[error("unknown codegen option `{0}`, expected one of: tailcallelim, lto, target-cpu=<cpu>, target-feature=<features>")]
+struct UnknownCodegenOption(String);
+
+impl FromStr for CodegenOption {
+    type Err = UnknownCodegenOption;
+
+    fn from_str(option: &str) -> Result<Self, Self::Err> {
+        match option.split_once('=') {
+            None if option == "tailcallelim" => Ok(Self::Tailcallelim),
+            None if option == "lto" => Ok(Self::Lto),
+            Some(("target-cpu", cpu)) => Ok(Self::TargetCpu(cpu.to_string())),
+            Some(("target-feature", features)) => Ok(Self::TargetFeature(features.to_string())),
+            _ => Err(UnknownCodegenOption(option.to_string())),
+        }
+    }
+}
+
+/// Applies the `-C` code generation options to the given [`CompileOptions`].
+fn apply_codegen_options(
+    mut options: CompileOptions,
+    codegen: Vec<CodegenOption>,
+) -> CompileOptions {
+    let mut features = Vec::new();
+
+    for option in codegen {
+        match option {
+            CodegenOption::Tailcallelim => options.tail_call_elim = true,
+            CodegenOption::Lto => options.lto = true,
+            CodegenOption::TargetCpu(cpu) => options.target_cpu = Some(cpu),
+            CodegenOption::TargetFeature(feature) => features.push(feature),
+        }
+    }
+
+    if !features.is_empty() {
+        options.target_features = Some(features.join(","));
+    }
+
+    options
+}
+
+/// An artifact to emit, set with `--emit`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    /// The textual LLVM IR of each compilation unit (`.ll`).
+    LlvmIr,
+
+    /// The LLVM bitcode of each compilation unit (`.bc`).
+    LlvmBc,
+
+    /// The object file of each compilation unit (`.o`), which is always
+    /// written.
+    Obj,
+
+    /// The linked executable or library.
+    Link,
+
+    /// The tokens of the input, including comments and lex errors, printed
+    /// one per line, instead of any code.
+    Tokens,
+
+    /// The AST of the input, printed as a tree, instead of any code.
+    Ast,
+
+    /// The AST of the input once it has been type checked, printed as a tree
+    /// with the type of each expression, instead of any code.
+    TypedAst,
+}
+
+/// Returns the target triple of the host.
+fn host_triple() -> String {
+    TargetMachine::get_default_triple()
+        .as_str()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Returns the number of codegen jobs to run when `--jobs` isn't given.
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum CrateType {
+    /// An executable.
+    Bin,
+
+    /// A static library.
+    Lib,
+
+    /// A shared library.
+    Dylib,
+}
+
+impl From<CrateType> for OutputKind {
+    fn from(crate_type: CrateType) -> Self {
+        match crate_type {
+            CrateType::Bin => OutputKind::Executable,
+            CrateType::Lib => OutputKind::StaticLib,
+            CrateType::Dylib => OutputKind::SharedLib,
+        }
+    }
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
 
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::TRACE)
-        .finish();
+    let max_level = match args.verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        _ => LevelFilter::TRACE,
+    };
+
+    let timings_report = args.timings.map(|format| TimingsReport {
+        timings: Timings::default(),
+        format,
+    });
+
+    // The phases are timed regardless of the verbosity, without logging them.
+    let timings_layer = timings_report.as_ref().map(|report| {
+        TimingsLayer::new(report.timings.clone())
+            .with_filter(filter_fn(|metadata| metadata.target() == timings::TARGET))
+    });
+
+    let subscriber = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(max_level),
+        )
+        .with(timings_layer)
+        .with(PhaseLayer);
 
     tracing::subscriber::set_global_default(subscriber)
         .expect("failed to set default tracing subscriber");
 
-    match args.command {
-        Command::New { path } => {
-            use std::fs::{self, File};
+    ice::install_hook();
 
-            fs::create_dir(&path).expect("Failed to create directory");
+    let color = ColorChoice::from(args.color).use_color(
+        std::io::stderr().is_terminal(),
+        std::env::var_os("NO_COLOR").as_deref(),
+    );
 
-            let mut src_path = path.clone();
-            src_path.push("src");
+    // A panic is a bug in the compiler, so it's reported as one instead of
+    // with the usual panic message.
+    match std::panic::catch_unwind(|| run_command(args.command, args.verbose, color)) {
+        Ok(Ok(exit_code)) => exit_code,
+        Ok(Err(err)) => failure_exit_code(err.failure()),
+        Err(_) => {
+            if let Some(ice) = ice::take() {
+                eprintln!("{ice}");
+            }
 
-            fs::create_dir_all(&src_path).unwrap();
+            ExitCode::from(INTERNAL_COMPILER_ERROR)
+        }
+    }
+}
 
-            let mut main_path = src_path;
-            main_path.push("main.crane");
+/// Returns the exit code for the given failure, as listed in [`EXIT_CODES`].
+fn failure_exit_code(failure: Failure) -> ExitCode {
+    match failure {
+        Failure::Other => ExitCode::FAILURE,
+        Failure::Syntax => ExitCode::from(3),
+        Failure::Type => ExitCode::from(4),
+        Failure::Codegen => ExitCode::from(5),
+        Failure::Link => ExitCode::from(6),
+    }
+}
 
-            let mut main = File::create(&main_path).unwrap();
+/// Prints the given error, which isn't reported as a diagnostic, returning it.
+fn report(err: impl Into<CraneError>) -> CraneError {
+    let err = err.into();
+    eprintln!("Error: {err}");
 
-            let hello_world_program = r#"
-fn main() {
-    println("你好，世界。")
+    err
 }
-            "#
-            .trim();
 
-            main.write_all(hello_world_program.as_bytes()).unwrap();
+/// Runs the given command, returning the exit code of `crane` unless the
+/// command fails. The error it fails with has already been reported.
+///
+/// Diagnostics are colored if `color` is set.
+fn run_command(command: Command, verbose: u8, color: bool) -> Result<ExitCode, CraneError> {
+    match command {
+        Command::New { path, lib, name } => {
+            let Some(name) = name.or_else(|| package_name(&path)) else {
+                return Ok(print_usage("new"));
+            };
+
+            new_package(&path, &name, package_kind(lib)).map_err(report)?;
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Init { path, lib, name } => {
+            let Some(name) = name.or_else(|| package_name(&path)) else {
+                return Ok(print_usage("init"));
+            };
+
+            init_package(&path, &name, package_kind(lib)).map_err(report)?;
+
+            Ok(ExitCode::SUCCESS)
         }
-        Command::Build { example } => {
-            let _ = compile(example);
+        Command::Build {
+            files,
+            example,
+            stdin,
+            emit,
+            opt_level,
+            target,
+            crate_type,
+            print_layout,
+            codegen,
+            debug,
+            out_dir,
+            parallelism,
+            no_cache,
+            libs,
+            lib_dirs,
+            watch,
+            lints,
+        } => {
+            let package = read_package(color)?;
+
+            let mut inputs = if stdin {
+                let input = read_stdin()?;
+
+                vec![input]
+            } else {
+                let Some(input_files) = input_files(files, example, package.as_ref(), color) else {
+                    return Ok(print_usage("build"));
+                };
+
+                input_files.into_iter().map(Input::File).collect::<Vec<_>>()
+            };
+
+            let mut options = CompileOptions {
+                target_triple: host_triple(),
+                emit_llvm_ir: emit.contains(&Emit::LlvmIr),
+                emit_bitcode: emit.contains(&Emit::LlvmBc),
+                link: emit.contains(&Emit::Link),
+                print_layout,
+                debug,
+                jobs: parallelism.jobs(),
+                threads: parallelism.threads.threads(),
+                incremental: !no_cache,
+                cache_dir: CompilationCache::user_cache_dir(),
+                link_search_paths: lib_dirs,
+                lint_levels: lints.lint_levels(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            // The flags take precedence over the manifest.
+            if let Some(crate_type) = crate_type {
+                options.output_kind = crate_type.into();
+            }
+
+            if let Some(opt_level) = opt_level {
+                options.opt_level = opt_level;
+            }
+
+            if let Some(target) = target {
+                options.target_triple = target;
+            }
+
+            if let Some(out_dir) = out_dir {
+                options.output_dir = out_dir;
+            }
+
+            if stdin && options.output_name.is_none() {
+                options.output_name = Some("stdin".to_string());
+            }
+
+            options.link_libraries.extend(libs);
+
+            let options = apply_codegen_options(options, codegen);
+
+            if emit
+                .iter()
+                .any(|emit| matches!(emit, Emit::Tokens | Emit::Ast | Emit::TypedAst))
+            {
+                for input in inputs {
+                    print_debug_output(input, options.clone(), &emit)?;
+                }
+
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            if inputs.len() > 1 {
+                if watch {
+                    eprintln!("Error: `--watch` rebuilds a single file.");
+
+                    return Ok(ExitCode::from(USAGE_ERROR));
+                }
+
+                return build_all(inputs, options).map(|()| ExitCode::SUCCESS);
+            }
+
+            let input = inputs.pop().unwrap();
+
+            if let (true, Input::File(input_file)) = (watch, &input) {
+                let roots = watch_roots(input_file, package.as_ref());
+
+                return Ok(build_on_change(input, options, roots));
+            }
+
+            let outputs = compile(input, options)?;
+
+            println!("Compiled!");
+
+            for output in &outputs {
+                println!("  {}", output.display());
+            }
+
+            Ok(ExitCode::SUCCESS)
         }
-        Command::Run { example } => {
-            if compile(example).is_ok() {
-                run();
+        Command::Run {
+            file,
+            example,
+            codegen,
+            debug,
+            keep_artifacts,
+            out_dir,
+            parallelism,
+            no_cache,
+            libs,
+            lib_dirs,
+            watch,
+            lints,
+            args,
+        } => {
+            let package = read_package(color)?;
+
+            let Some(input_file) = input_file(file, example, package.as_ref(), color) else {
+                return Ok(print_usage("run"));
+            };
+
+            let mut options = CompileOptions {
+                target_triple: host_triple(),
+                debug,
+                jobs: parallelism.jobs(),
+                threads: parallelism.threads.threads(),
+                incremental: !no_cache,
+                cache_dir: CompilationCache::user_cache_dir(),
+                link_search_paths: lib_dirs,
+                lint_levels: lints.lint_levels(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
             }
+
+            // Only executables can be run.
+            options.output_kind = OutputKind::Executable;
+
+            let temp_dir = match out_dir {
+                Some(out_dir) => {
+                    options.output_dir = out_dir;
+
+                    None
+                }
+                None if keep_artifacts => None,
+                None => {
+                    let temp_dir =
+                        std::env::temp_dir().join(format!("crane-run-{}", std::process::id()));
+
+                    options.output_dir = temp_dir.clone();
+
+                    Some(temp_dir)
+                }
+            };
+
+            options.link_libraries.extend(libs);
+
+            let options = apply_codegen_options(options, codegen);
+
+            if watch {
+                let roots = watch_roots(&input_file, package.as_ref());
+
+                return Ok(run_on_change(input_file, options, &args, roots));
+            }
+
+            // The linked executable is the last output.
+            let result = compile(Input::File(input_file), options)
+                .and_then(|outputs| run(outputs.last().expect("the executable is linked"), &args));
+
+            if let Some(temp_dir) = temp_dir {
+                let _ = std::fs::remove_dir_all(temp_dir);
+            }
+
+            result
         }
+        Command::Test {
+            file,
+            debug,
+            lints,
+            filters,
+        } => {
+            let package = read_package(color)?;
+
+            let Some(input_file) = input_file(file, None, package.as_ref(), color) else {
+                return Ok(print_usage("test"));
+            };
+
+            let mut options = CompileOptions {
+                target_triple: host_triple(),
+                debug,
+                jobs: default_jobs(),
+                cache_dir: CompilationCache::user_cache_dir(),
+                lint_levels: lints.lint_levels(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            options.output_kind = OutputKind::Executable;
+            options.output_dir =
+                std::env::temp_dir().join(format!("crane-test-{}", std::process::id()));
+
+            let temp_dir = options.output_dir.clone();
+
+            let result = test(&input_file, options, &filters);
+
+            let _ = std::fs::remove_dir_all(temp_dir);
+
+            result.map(|()| ExitCode::SUCCESS)
+        }
+        Command::Check {
+            files,
+            example,
+            stdin,
+            debug,
+            threads,
+            lints,
+        } => {
+            let package = read_package(color)?;
+
+            let inputs = if stdin {
+                let input = read_stdin()?;
+
+                vec![input]
+            } else {
+                let Some(input_files) = input_files(files, example, package.as_ref(), color) else {
+                    return Ok(print_usage("check"));
+                };
+
+                input_files.into_iter().map(Input::File).collect()
+            };
+
+            let mut options = CompileOptions {
+                target_triple: host_triple(),
+                debug,
+                threads: threads.threads(),
+                lint_levels: lints.lint_levels(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            check_all(inputs, options).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Lint { file, lints } => {
+            let package = read_package(color)?;
+
+            let Some(input_file) = input_file(file, None, package.as_ref(), color) else {
+                return Ok(print_usage("lint"));
+            };
+
+            let mut options = CompileOptions {
+                lint_levels: lints.lint_levels(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            let params = CompileParams {
+                input: Input::File(input_file),
+                options,
+            };
+
+            Compiler::new()
+                .lint(&mut std::io::stderr(), params)
+                .map(|()| ExitCode::SUCCESS)
+        }
+        Command::Doc {
+            file,
+            out_dir,
+            document_private_items,
+        } => {
+            let package = read_package(color)?;
+
+            let Some(input_file) = input_file(file, None, package.as_ref(), color) else {
+                eprintln!("Error: No file to document, and no `{MANIFEST}` in this directory or its ancestors.");
+
+                return Ok(ExitCode::from(USAGE_ERROR));
+            };
+
+            let mut options = CompileOptions {
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            let out_dir = out_dir.unwrap_or_else(|| options.output_dir.join("doc"));
+
+            doc(&input_file, &out_dir, document_private_items, &options).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Symbols {
+            file,
+            include_locals,
+        } => {
+            let package = read_package(color)?;
+
+            let Some(input_file) = input_file(file, None, package.as_ref(), color) else {
+                return Ok(print_usage("symbols"));
+            };
+
+            let mut options = CompileOptions {
+                color,
+                ..CompileOptions::default()
+            };
+
+            if let Some(package) = &package {
+                package.manifest.apply(&package.root, &mut options);
+            }
+
+            symbols(&input_file, include_locals, &options).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Clean { cache, dry_run } => {
+            let package = read_package(color)?;
+
+            let (root, out_dir) = match &package {
+                Some(package) => (package.root.as_path(), package.manifest.out_dir()),
+                None => (Path::new(""), Path::new(DEFAULT_OUT_DIR)),
+            };
+
+            clean(root, out_dir, cache, dry_run, verbose > 0).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Fmt { files, check } => {
+            let mut failure = None;
+
+            for file in files {
+                if let Err(err) = fmt(&file, check, color) {
+                    failure.get_or_insert(err);
+                }
+            }
+
+            failure.map_or(Ok(ExitCode::SUCCESS), Err)
+        }
+        Command::Explain { code } => {
+            let Some(error_code) = ErrorCode::from_code(&code) else {
+                eprintln!("Error: `{code}` is not an error code.");
+
+                return Ok(ExitCode::from(USAGE_ERROR));
+            };
+
+            print!("{}", error_code.explanation());
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Lsp => {
+            // Any file may be opened, including modules, so none of them has
+            // to define `main`.
+            let options = CompileOptions {
+                target_triple: host_triple(),
+                output_kind: OutputKind::StaticLib,
+                ..CompileOptions::default()
+            };
+
+            crane::lsp::run_stdio(options).map_err(|err| report(CraneError::Session(err)))?;
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Repl => {
+            let options = CompileOptions {
+                target_triple: host_triple(),
+                color,
+                ..CompileOptions::default()
+            };
+
+            let result = Repl::new(options).run(
+                &mut std::io::stdin().lock(),
+                &mut std::io::stdout(),
+                &mut std::io::stderr(),
+            );
+
+            result.map_err(|err| report(CraneError::Session(err.into())))?;
+
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// A package, found by its manifest.
+struct Package {
+    /// The directory containing the manifest, relative to the current
+    /// directory if it's the current directory, or else absolute.
+    root: PathBuf,
+
+    manifest: CraneManifest,
+}
+
+/// Reads the manifest of the package that the current directory is in, if
+/// any, from the current directory or the nearest of its ancestors.
+fn read_package(color: bool) -> Result<Option<Package>, CraneError> {
+    let current_dir = std::env::current_dir().map_err(|err| report(CraneError::CurrentDir(err)))?;
+
+    let Some(path) = find_manifest(&current_dir) else {
+        return Ok(None);
+    };
+
+    let manifest = CraneManifest::load(&path).map_err(|err| {
+        err.report(&mut std::io::stderr(), color);
+
+        CraneError::from(err)
+    })?;
+
+    if !manifest.dependencies.is_empty() {
+        eprintln!("Warning: Dependencies aren't supported yet, and will be ignored.");
+    }
+
+    let root = path.parent().expect("the manifest is in a directory");
+    let root = root
+        .strip_prefix(&current_dir)
+        .unwrap_or(root)
+        .to_path_buf();
+
+    Ok(Some(Package { root, manifest }))
+}
+
+/// Prints the help for the given subcommand, returning the exit code for a
+/// usage error.
+fn print_usage(subcommand: &str) -> ExitCode {
+    let mut command = Args::command();
+    command.build();
+
+    let _ = command
+        .find_subcommand_mut(subcommand)
+        .expect("the subcommand exists")
+        .print_help();
+
+    ExitCode::from(USAGE_ERROR)
+}
+
+/// Returns the default name of the package in the given directory, which is
+/// the name of the directory.
+fn package_name(dir: &Path) -> Option<String> {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    dir.file_name()?.to_str().map(ToString::to_string)
+}
+
+/// Returns the kind of package to create for `crane new` and `crane init`.
+fn package_kind(lib: bool) -> PackageKind {
+    if lib {
+        PackageKind::Lib
+    } else {
+        PackageKind::Bin
     }
 }
 
-fn compile(example: Option<String>) -> Result<(), ()> {
-    // TODO: Don't force the usage of an example.
-    let example = example.unwrap_or("scratch".to_string());
+/// Reads the source code to compile from stdin, until EOF.
+fn read_stdin() -> Result<Input, CraneError> {
+    let input = std::io::read_to_string(std::io::stdin()).map_err(|source| {
+        report(CraneError::Io {
+            path: STDIN_FILENAME.into(),
+            source,
+        })
+    })?;
+
+    Ok(Input::String {
+        filename: STDIN_FILENAME.to_string(),
+        input,
+    })
+}
+
+/// Returns the files to compile, which are the given files, each resolved
+/// like [`input_file`], or else the one file that [`input_file`] returns.
+fn input_files(
+    files: Vec<PathBuf>,
+    example: Option<String>,
+    package: Option<&Package>,
+    color: bool,
+) -> Option<Vec<PathBuf>> {
+    if files.len() > 1 {
+        return files
+            .into_iter()
+            .map(|file| input_file(Some(file), None, package, color))
+            .collect();
+    }
 
-    let examples_path = PathBuf::from("examples");
+    input_file(files.into_iter().next(), example, package, color).map(|file| vec![file])
+}
 
-    let mut example_file = examples_path;
-    example_file.push(format!("{example}.crane"));
+/// Returns the file to compile, which is the given file or example, or else
+/// the entry point of the package.
+///
+/// A directory is compiled from the entry point of the package in it. An
+/// example is either a file in `examples`, or a directory with a `main.crane`
+/// for an example made up of multiple files.
+fn input_file(
+    file: Option<PathBuf>,
+    example: Option<String>,
+    package: Option<&Package>,
+    color: bool,
+) -> Option<PathBuf> {
+    match (file, example, package) {
+        (Some(dir), _, _) if dir.is_dir() => Some(package_entry(&dir, color)),
+        (Some(file), _, _) => Some(file),
+        (None, Some(example), _) => {
+            let example_dir = Path::new("examples").join(&example);
+
+            if example_dir.is_dir() {
+                Some(example_dir.join("main.crane"))
+            } else {
+                Some(PathBuf::from("examples").join(format!("{example}.crane")))
+            }
+        }
+        (None, None, Some(package)) => Some(package.root.join(package.manifest.entry())),
+        (None, None, None) => None,
+    }
+}
+
+/// Returns the entry point of the package in the given directory, from its
+/// `Crane.toml` if it has one, or else `src/main.crane` or `main.crane`.
+fn package_entry(dir: &Path, color: bool) -> PathBuf {
+    let manifest_path = dir.join(MANIFEST);
+
+    if manifest_path.exists() {
+        match CraneManifest::load(&manifest_path) {
+            Ok(manifest) => return dir.join(manifest.entry()),
+            Err(err) => err.report(&mut std::io::stderr(), color),
+        }
+    }
+
+    let entry = dir.join(DEFAULT_ENTRY);
+
+    if entry.exists() {
+        entry
+    } else {
+        dir.join("main.crane")
+    }
+}
+
+fn compile(input: Input, mut options: CompileOptions) -> Result<Vec<PathBuf>, CraneError> {
+    apply_build_script(&mut options)?;
 
     let mut compiler = Compiler::new();
 
-    let params = CompileParams {
-        input: Input::File(example_file),
-    };
+    let params = CompileParams { input, options };
 
     compiler.compile(&mut std::io::stderr(), params)
 }
 
-fn run() {
-    use std::process::Command;
+/// Runs the build script in the current directory, if there is one, applying
+/// its output to the options.
+fn apply_build_script(options: &mut CompileOptions) -> Result<(), CraneError> {
+    let build_script = Path::new(BUILD_SCRIPT);
+
+    if build_script.exists() {
+        run_build_script(&mut std::io::stderr(), build_script, options)
+            .map_err(report)?
+            .apply(options);
+    }
+
+    Ok(())
+}
+
+/// Compiles each of the inputs in parallel, each into artifacts named after
+/// it, and prints the diagnostics of each input in turn once they have all
+/// been compiled.
+///
+/// Returns the error of the first input that failed, if any. Inputs whose
+/// artifacts would overwrite each other are an error before any of them are
+/// compiled.
+fn build_all(inputs: Vec<Input>, mut options: CompileOptions) -> Result<(), CraneError> {
+    let mut output_names = HashMap::new();
+
+    for input in &inputs {
+        let Input::File(path) = input else {
+            continue;
+        };
+
+        let output_name = options.output_name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map_or("main".into(), |stem| stem.to_string_lossy().into_owned())
+        });
+
+        if let Some(other) = output_names.insert(output_name.clone(), path) {
+            return Err(report(CraneError::ConflictingOutputs {
+                first: other.clone(),
+                second: path.clone(),
+                name: output_name,
+                output_dir: options.output_dir.clone(),
+            }));
+        }
+    }
+
+    apply_build_script(&mut options)?;
+
+    let threads = options.threads;
+    options.threads = threads_per_input(threads, inputs.len());
+
+    let results = par_map(inputs, threads, |input| {
+        let mut stderr = Vec::new();
+        let params = CompileParams {
+            input,
+            options: options.clone(),
+        };
+
+        let result = Compiler::new().compile(&mut stderr, params);
+
+        (stderr, result)
+    });
+
+    let mut failure = None;
+
+    for (stderr, result) in results {
+        std::io::stderr().write_all(&stderr).unwrap();
+
+        match result {
+            Ok(outputs) => {
+                println!("Compiled!");
+
+                for output in &outputs {
+                    println!("  {}", output.display());
+                }
+            }
+            Err(err) => {
+                failure.get_or_insert(err);
+            }
+        }
+    }
+
+    failure.map_or(Ok(()), Err)
+}
+
+/// Checks each of the inputs in parallel, and prints the diagnostics of each
+/// input in turn once they have all been checked.
+///
+/// Returns the error of the first input that failed, if any.
+fn check_all(inputs: Vec<Input>, mut options: CompileOptions) -> Result<(), CraneError> {
+    let threads = options.threads;
+    options.threads = threads_per_input(threads, inputs.len());
+
+    let results = par_map(inputs, threads, |input| {
+        let mut stderr = Vec::new();
+        let params = CompileParams {
+            input,
+            options: options.clone(),
+        };
+
+        let result = Compiler::new().check(&mut stderr, params);
+
+        (stderr, result)
+    });
+
+    let mut failure = None;
+
+    for (stderr, result) in results {
+        std::io::stderr().write_all(&stderr).unwrap();
+
+        if let Err(err) = result {
+            failure.get_or_insert(err);
+        }
+    }
+
+    failure.map_or(Ok(()), Err)
+}
+
+/// Returns the number of threads that each of the given number of inputs is
+/// compiled on, when they're compiled in parallel on `threads` threads, so
+/// that all of them together use at most `threads`.
+fn threads_per_input(threads: usize, inputs: usize) -> usize {
+    (threads / inputs.max(1)).max(1)
+}
+
+/// Prints the tokens of the input with `--emit=tokens`, its AST with
+/// `--emit=ast`, and its typed AST with `--emit=typed-ast`.
+fn print_debug_output(
+    input: Input,
+    options: CompileOptions,
+    emit: &[Emit],
+) -> Result<(), CraneError> {
+    if emit.contains(&Emit::Tokens) {
+        let source = match &input {
+            Input::File(path) => std::fs::read_to_string(path).map_err(|source| {
+                report(CraneError::Io {
+                    path: path.clone(),
+                    source,
+                })
+            })?,
+            Input::String { input, .. } | Input::Buffer { input, .. } => input.clone(),
+        };
+
+        print!("{}", print_tokens(&source));
+
+        // The tokens are printed without parsing, so lex errors are only
+        // reported in the output.
+        if let Some(Err(err)) = tokenize(&source).into_iter().find(Result::is_err) {
+            return Err(ParseError::from(err).into());
+        }
+    }
+
+    if emit.contains(&Emit::Ast) {
+        let params = CompileParams {
+            input: input.clone(),
+            options: options.clone(),
+        };
+
+        let arena = AstArena::new();
+        let items = Compiler::new().parse_input(&mut std::io::stderr(), params, &arena)?;
+
+        print!("{}", print_items(&items));
+    }
+
+    if emit.contains(&Emit::TypedAst) {
+        let params = CompileParams { input, options };
+
+        let arena = AstArena::new();
+        let package = Compiler::new().type_check_input(&mut std::io::stderr(), params, &arena)?;
+
+        print!("{}", print_ty_package(&package));
+    }
+
+    Ok(())
+}
+
+/// Documents the package rooted at the given file in `out_dir`.
+fn doc(
+    input_file: &Path,
+    out_dir: &Path,
+    document_private_items: bool,
+    options: &CompileOptions,
+) -> Result<(), CraneError> {
+    let arena = AstArena::new();
+    let items = Compiler::new().parse_file(&mut std::io::stderr(), input_file, options, &arena)?;
+
+    let name = options.output_name.clone().unwrap_or_else(|| {
+        input_file
+            .file_stem()
+            .map_or("main".into(), |stem| stem.to_string_lossy().into_owned())
+    });
+
+    std::fs::create_dir_all(out_dir).map_err(|source| {
+        report(CraneError::Write {
+            path: out_dir.to_path_buf(),
+            source,
+        })
+    })?;
+
+    for page in document_package(&name, &items, document_private_items) {
+        let path = out_dir.join(&page.path);
+
+        std::fs::write(&path, page.html)
+            .map_err(|source| report(CraneError::Write { path, source }))?;
+    }
+
+    println!(
+        "Documented `{name}` in {}",
+        out_dir.join("index.html").display()
+    );
+
+    Ok(())
+}
+
+/// Prints the items defined by the given file, and the modules it declares,
+/// as a JSON array.
+fn symbols(
+    input_file: &Path,
+    include_locals: bool,
+    options: &CompileOptions,
+) -> Result<(), CraneError> {
+    let arena = AstArena::new();
+    let mut compiler = Compiler::new();
+    let items = compiler.parse_file(&mut std::io::stderr(), input_file, options, &arena)?;
+
+    let symbols = package_symbols(&items, compiler.source_map(), include_locals);
+
+    println!("{}", serde_json::to_string_pretty(&symbols).unwrap());
+
+    Ok(())
+}
+
+/// Removes the output directory of the project rooted at `root`, which also
+/// holds the project's incremental cache, printing it if `verbose` is set.
+/// Only prints it if `dry_run` is set. With `cache`, also removes the entries
+/// of the shared cache that the output directory lists.
+///
+/// The output directory is resolved against the root, which is the directory
+/// of the project's manifest, rather than the current directory. Refuses to
+/// remove a directory that isn't inside the project, even if its path leads
+/// into it, like `../elsewhere`, or it's a link to somewhere else.
+fn clean(
+    root: &Path,
+    out_dir: &Path,
+    cache: bool,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<(), CraneError> {
+    let canonicalize = |path: &Path| {
+        path.canonicalize().map_err(|source| {
+            report(CraneError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        })
+    };
+
+    // An empty root is the current directory.
+    let root_dir = canonicalize(&Path::new(".").join(root))?;
+    let output_dir = root_dir.join(out_dir);
+
+    // There's nothing to clean, so whatever the output directory is, there's
+    // nothing to refuse to remove either.
+    if !output_dir.exists() {
+        return Ok(());
+    }
+
+    let dir = canonicalize(&output_dir)?;
+
+    // The output directory as it's shown, relative to the current directory
+    // if the project is in it.
+    let shown_dir = root.join(out_dir);
+
+    if dir == root_dir || !dir.starts_with(&root_dir) {
+        return Err(report(CraneError::OutsideProject {
+            path: shown_dir,
+            root: root_dir,
+        }));
+    }
+
+    if let Some(cache_dir) = cache.then(CompilationCache::user_cache_dir).flatten() {
+        let files = CompilationCache::new(cache_dir.clone()).entry_files(&dir.join(CACHE_ENTRIES));
+
+        if !files.is_empty() && dry_run {
+            println!(
+                "Would remove {} file(s) from `{}`",
+                files.len(),
+                cache_dir.display()
+            );
+        } else if !files.is_empty() {
+            for file in &files {
+                std::fs::remove_file(file).map_err(|source| {
+                    report(CraneError::Remove {
+                        path: file.clone(),
+                        source,
+                    })
+                })?;
+            }
+
+            if verbose {
+                println!(
+                    "Removed {} file(s) from `{}`",
+                    files.len(),
+                    cache_dir.display()
+                );
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Would remove `{}`", shown_dir.display());
+
+        return Ok(());
+    }
+
+    std::fs::remove_dir_all(&dir).map_err(|source| {
+        report(CraneError::Remove {
+            path: shown_dir.clone(),
+            source,
+        })
+    })?;
+
+    if verbose {
+        println!("Removed `{}`", shown_dir.display());
+    }
+
+    Ok(())
+}
+
+/// Builds the test harness for the given file, and runs each of its tests
+/// that match the filters, if any.
+///
+/// Returns an error if the harness fails to build or any test fails.
+fn test(input_file: &Path, options: CompileOptions, filters: &[String]) -> Result<(), CraneError> {
+    let arena = AstArena::new();
+    let items = Compiler::new().parse_file(&mut std::io::stderr(), input_file, &options, &arena)?;
+
+    let (tests, filtered_out): (Vec<_>, Vec<_>) = test_fns(&items).into_iter().partition(|test| {
+        filters.is_empty() || filters.iter().any(|filter| test.contains(filter.as_str()))
+    });
+
+    // The linked executable is the last output.
+    let harness = compile(
+        Input::File(input_file.to_path_buf()),
+        CompileOptions {
+            test: true,
+            ..options
+        },
+    )?
+    .pop()
+    .expect("the harness is linked");
+
+    println!(
+        "\nrunning {} test{}",
+        tests.len(),
+        if tests.len() == 1 { "" } else { "s" }
+    );
+
+    let mut failures = Vec::new();
+
+    for test in &tests {
+        let start = Instant::now();
+        let output = std::process::Command::new(&harness).arg(test).output();
+        let elapsed = start.elapsed();
+
+        match output {
+            Ok(output) if output.status.success() => {
+                println!("test {test} ... ok ({elapsed:.2?})");
+            }
+            Ok(output) => {
+                println!("test {test} ... FAILED ({elapsed:.2?})");
+
+                failures.push((test, output));
+            }
+            Err(source) => {
+                return Err(report(CraneError::Run {
+                    path: harness,
+                    source,
+                }));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+
+        for (test, output) in &failures {
+            println!("\n---- {test} ({}) ----", output.status);
+
+            std::io::stdout().write_all(&output.stdout).unwrap();
+            std::io::stdout().write_all(&output.stderr).unwrap();
+        }
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} filtered out",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        tests.len() - failures.len(),
+        failures.len(),
+        filtered_out.len()
+    );
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CraneError::TestsFailed(failures.len()))
+    }
+}
+
+/// Formats the given file, or with `check`, reports whether it needs
+/// formatting.
+fn fmt(file: &Path, check: bool, color: bool) -> Result<(), CraneError> {
+    let filepath = file.display().to_string();
+
+    let source = std::fs::read_to_string(file).map_err(|source| {
+        report(CraneError::Io {
+            path: file.to_path_buf(),
+            source,
+        })
+    })?;
+
+    let formatted = match format_source(&source) {
+        Ok(formatted) => formatted,
+        Err(FormatError::Parse(err)) => {
+            report_parse_error(&mut std::io::stderr(), &filepath, &source, &err, color);
+
+            return Err(err.into());
+        }
+    };
+
+    if formatted == source {
+        return Ok(());
+    }
+
+    if check {
+        let err = CraneError::Unformatted(file.to_path_buf());
+        eprintln!("{err}");
+
+        return Err(err);
+    }
+
+    std::fs::write(file, formatted).map_err(|source| {
+        report(CraneError::Write {
+            path: file.to_path_buf(),
+            source,
+        })
+    })
+}
+
+/// Returns the files and directories to watch for changes to the given input
+/// file: the `src` directory and manifest of the package it's in, or else the
+/// directory it's in.
+fn watch_roots(input_file: &Path, package: Option<&Package>) -> Vec<PathBuf> {
+    match package {
+        Some(package) if input_file.starts_with(&package.root) => {
+            vec![package.root.join("src"), package.root.join(MANIFEST)]
+        }
+        _ => match input_file.parent() {
+            Some(dir) if dir != Path::new("") => vec![dir.to_path_buf()],
+            _ => vec![PathBuf::from(".")],
+        },
+    }
+}
+
+/// Clears the terminal before a rebuild, when the output is shown in one.
+fn clear_screen() {
+    if std::io::stdout().is_terminal() {
+        print!("\x1b[2J\x1b[H");
+    }
+}
+
+/// Builds the input, and then rebuilds it whenever a file in `roots` changes,
+/// until interrupted.
+fn build_on_change(input: Input, options: CompileOptions, roots: Vec<PathBuf>) -> ExitCode {
+    let mut watcher = Watcher::new(roots);
+
+    loop {
+        clear_screen();
+
+        let start = Instant::now();
+
+        if compile(input.clone(), options.clone()).is_ok() {
+            println!("Compiled in {:.2?}.", start.elapsed());
+        }
+
+        println!("Waiting for changes...");
 
-    let exit_status = Command::new("./build/main")
+        watcher.wait_for_change();
+    }
+}
+
+/// Builds and runs the input file, and then does so again whenever a file in
+/// `roots` changes, killing the previous run if it's still running, until
+/// interrupted.
+fn run_on_change(
+    input_file: PathBuf,
+    options: CompileOptions,
+    args: &[String],
+    roots: Vec<PathBuf>,
+) -> ExitCode {
+    let mut watcher = Watcher::new(roots);
+
+    loop {
+        clear_screen();
+
+        let start = Instant::now();
+
+        let child = match compile(Input::File(input_file.clone()), options.clone())
+            .ok()
+            .and_then(|mut outputs| outputs.pop())
+        {
+            Some(executable) => {
+                println!("Compiled in {:.2?}.", start.elapsed());
+
+                std::process::Command::new(&executable)
+                    .args(args)
+                    .spawn()
+                    .map_err(|err| {
+                        eprintln!("Error: Failed to run `{}`: {err}", executable.display())
+                    })
+                    .ok()
+            }
+            None => {
+                println!("Waiting for changes...");
+
+                None
+            }
+        };
+
+        watcher.wait_for_change();
+
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Runs the given executable with the given arguments, returning its exit
+/// code.
+fn run(executable: &Path, args: &[String]) -> Result<ExitCode, CraneError> {
+    let status = std::process::Command::new(executable)
+        .args(args)
         .status()
-        .expect("Failed to run");
+        .map_err(|source| {
+            report(CraneError::Run {
+                path: executable.to_path_buf(),
+                source,
+            })
+        })?;
 
-    println!("Exited with {}", exit_status);
+    match status.code() {
+        Some(code) => Ok(ExitCode::from(code as u8)),
+        None => Err(report(CraneError::Exited(status))),
+    }
 }
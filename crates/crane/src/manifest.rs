@@ -0,0 +1,432 @@
+//! Package manifests, which describe a Crane package.
+//!
+//! A `Crane.toml` file at the root of a package names the package and
+//! configures how it's built:
+//!
+//! ```toml
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! authors = ["Ferris"]
+//!
+//! [dependencies]
+//! greetings = "1.2.0"
+//!
+//! [build]
+//! entry = "src/main.crane"
+//! output = "bin"
+//! out-dir = "build"
+//! opt-level = 2
+//! target = "x86_64-unknown-linux-gnu"
+//! link = ["m"]
+//! ```
+//!
+//! Only the `[package]` section is required. The paths in the `[build]`
+//! section are relative to the directory containing the manifest, and the
+//! flags given to `crane` take precedence over its settings.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{de, Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::ast::Span;
+use crate::compiler::{CompileOptions, OutputKind};
+use crate::diagnostic::Diagnostic;
+use crate::error_codes::ErrorCode;
+
+/// The name of the manifest at the root of a package.
+pub const MANIFEST: &str = "Crane.toml";
+
+/// The entry point of a package that doesn't specify one.
+pub const DEFAULT_ENTRY: &str = "src/main.crane";
+
+/// The directory that a package that doesn't specify one is built into.
+pub const DEFAULT_OUT_DIR: &str = "build";
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    /// The manifest could not be read.
+    #[error("Failed to read `{}`: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The manifest isn't valid.
+    #[error("Invalid manifest `{}`: {source}", .path.display())]
+    Parse {
+        path: PathBuf,
+
+        /// The contents of the manifest, which the error points into.
+        contents: String,
+
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+}
+
+impl ManifestError {
+    /// Writes the error, pointing at the location in the manifest that isn't
+    /// valid, if any, in color if `color` is set.
+    pub fn report(&self, out: &mut impl Write, color: bool) {
+        match self {
+            Self::Parse {
+                path,
+                contents,
+                source,
+            } => {
+                let diagnostic = Diagnostic::error(
+                    ErrorCode::InvalidManifest,
+                    format!("Invalid manifest `{}`.", path.display()),
+                );
+
+                let diagnostic = match source.span() {
+                    Some(span) => diagnostic.with_label(
+                        Span {
+                            start: span.start,
+                            end: span.end,
+                        },
+                        source.message(),
+                    ),
+                    None => diagnostic.with_note(source.message()),
+                };
+
+                diagnostic.write(out, &path.display().to_string(), contents, color);
+            }
+            Self::Io { .. } => writeln!(out, "Error: {self}").unwrap(),
+        }
+    }
+}
+
+/// Returns the path of the manifest of the package that the given directory
+/// is in, which is the `Crane.toml` in the directory or the nearest of its
+/// ancestors.
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(MANIFEST))
+        .find(|path| path.is_file())
+}
+
+/// A `Crane.toml` manifest.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CraneManifest {
+    /// The `[package]` section.
+    pub package: PackageManifest,
+
+    /// The `[dependencies]` section, mapping the names of the packages this
+    /// package depends on to their versions.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+
+    /// The `[build]` section.
+    #[serde(default)]
+    pub build: BuildManifest,
+}
+
+/// The `[package]` section of a manifest.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PackageManifest {
+    /// The name of the package, which the linked artifact is named after.
+    pub name: String,
+
+    /// The version of the package.
+    pub version: String,
+
+    /// The authors of the package.
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
+
+/// The `[build]` section of a manifest.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildManifest {
+    /// The file to start compiling from.
+    ///
+    /// Defaults to [`DEFAULT_ENTRY`].
+    pub entry: Option<PathBuf>,
+
+    /// The kind of artifact to produce.
+    pub output: Option<OutputType>,
+
+    /// The directory to write the artifacts to.
+    pub out_dir: Option<PathBuf>,
+
+    /// How much to optimize the generated code, from `0` to `3`.
+    #[serde(default, deserialize_with = "deserialize_opt_level")]
+    pub opt_level: Option<u8>,
+
+    /// The target triple to compile for.
+    ///
+    /// Defaults to the host.
+    pub target: Option<String>,
+
+    /// The native libraries to link against.
+    #[serde(default)]
+    pub link: Vec<String>,
+}
+
+fn deserialize_opt_level<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let opt_level = u8::deserialize(deserializer)?;
+
+    if opt_level > 3 {
+        return Err(de::Error::custom(format!(
+            "invalid opt-level `{opt_level}`, expected 0 to 3"
+        )));
+    }
+
+    Ok(Some(opt_level))
+}
+
+/// The kind of artifact a package produces, as written in its manifest.
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputType {
+    /// An executable.
+    Bin,
+
+    /// A static library.
+    Lib,
+
+    /// A shared library.
+    Dylib,
+}
+
+impl From<OutputType> for OutputKind {
+    fn from(output: OutputType) -> Self {
+        match output {
+            OutputType::Bin => OutputKind::Executable,
+            OutputType::Lib => OutputKind::StaticLib,
+            OutputType::Dylib => OutputKind::SharedLib,
+        }
+    }
+}
+
+impl CraneManifest {
+    /// Reads the manifest at the given path.
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ManifestError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::parse(&contents).map_err(|source| ManifestError::Parse {
+            path: path.to_path_buf(),
+            contents,
+            source: Box::new(source),
+        })
+    }
+
+    /// Parses the contents of a manifest.
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Returns the path of the entry point, relative to the package root.
+    pub fn entry(&self) -> &Path {
+        self.build
+            .entry
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_ENTRY))
+    }
+
+    /// Returns the path of the output directory, relative to the package root
+    /// unless it's absolute.
+    pub fn out_dir(&self) -> &Path {
+        self.build
+            .out_dir
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_OUT_DIR))
+    }
+
+    /// Configures the given [`CompileOptions`] to build the package rooted at
+    /// `root`.
+    pub fn apply(&self, root: &Path, options: &mut CompileOptions) {
+        options.output_name = Some(self.package.name.clone());
+
+        if let Some(output) = self.build.output {
+            options.output_kind = output.into();
+        }
+
+        options.output_dir = root.join(self.out_dir());
+
+        if let Some(opt_level) = self.build.opt_level {
+            options.opt_level = opt_level;
+        }
+
+        if let Some(target) = &self.build.target {
+            options.target_triple = target.clone();
+        }
+
+        options
+            .link_libraries
+            .extend(self.build.link.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_manifest() {
+        let manifest = CraneManifest::parse(
+            r#"
+[package]
+name = "hello"
+version = "0.1.0"
+authors = ["Ferris"]
+
+[dependencies]
+greetings = "1.2.0"
+
+[build]
+entry = "src/hello.crane"
+output = "lib"
+out-dir = "out"
+opt-level = 3
+target = "x86_64-unknown-linux-gnu"
+link = ["m"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.package.name, "hello");
+        assert_eq!(manifest.package.version, "0.1.0");
+        assert_eq!(manifest.package.authors, ["Ferris"]);
+        assert_eq!(manifest.dependencies["greetings"], "1.2.0");
+        assert_eq!(manifest.entry(), Path::new("src/hello.crane"));
+
+        let mut options = CompileOptions::default();
+
+        manifest.apply(Path::new("hello"), &mut options);
+
+        assert_eq!(options.output_name.as_deref(), Some("hello"));
+        assert_eq!(options.output_kind, OutputKind::StaticLib);
+        assert_eq!(options.output_dir, Path::new("hello/out"));
+        assert_eq!(options.opt_level, 3);
+        assert_eq!(options.target_triple, "x86_64-unknown-linux-gnu");
+        assert_eq!(options.link_libraries, ["m"]);
+    }
+
+    #[test]
+    fn test_parse_minimal_manifest() {
+        let manifest = CraneManifest::parse(
+            r#"
+[package]
+name = "hello"
+version = "0.1.0"
+            "#,
+        )
+        .unwrap();
+
+        assert!(manifest.dependencies.is_empty());
+        assert_eq!(manifest.build, BuildManifest::default());
+        assert_eq!(manifest.entry(), Path::new(DEFAULT_ENTRY));
+
+        let mut options = CompileOptions::default();
+
+        manifest.apply(Path::new(""), &mut options);
+
+        let defaults = CompileOptions::default();
+
+        assert_eq!(options.output_kind, OutputKind::Executable);
+        assert_eq!(options.output_dir, Path::new("build"));
+        assert_eq!(options.opt_level, defaults.opt_level);
+        assert_eq!(options.target_triple, defaults.target_triple);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_fields() {
+        let err = CraneManifest::parse(
+            r#"
+[package]
+name = "hello"
+version = "0.1.0"
+edition = "2021"
+            "#,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("unknown field `edition`"));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_opt_level() {
+        let err = CraneManifest::parse(
+            r#"
+[package]
+name = "hello"
+version = "0.1.0"
+
+[build]
+opt-level = 4
+            "#,
+        )
+        .unwrap_err();
+
+        assert!(err
+            .message()
+            .contains("invalid opt-level `4`, expected 0 to 3"));
+    }
+
+    #[test]
+    fn test_report_points_at_the_invalid_value() {
+        let dir = std::env::temp_dir()
+            .join("crane-manifest-tests")
+            .join("report");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(MANIFEST);
+
+        std::fs::write(
+            &path,
+            "[package]\nname = \"hello\"\nversion = \"0.1.0\"\n\n[build]\noutput = \"exe\"\n",
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+
+        CraneManifest::load(&path)
+            .unwrap_err()
+            .report(&mut out, false);
+
+        let out = strip_ansi_escapes::strip(out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("Invalid manifest"), "{out}");
+        assert!(out.contains(&format!("{}:6:10", path.display())), "{out}");
+        assert!(out.contains("unknown variant `exe`"), "{out}");
+    }
+
+    #[test]
+    fn test_find_manifest_in_ancestors() {
+        let dir = std::env::temp_dir()
+            .join("crane-manifest-tests")
+            .join("find");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let nested = dir.join("hello/src/nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.join("hello").join(MANIFEST), "").unwrap();
+
+        assert_eq!(
+            find_manifest(&nested),
+            Some(dir.join("hello").join(MANIFEST))
+        );
+
+        // The search ends at the root of the filesystem.
+        assert_eq!(find_manifest(&dir), None);
+    }
+}
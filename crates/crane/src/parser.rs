@@ -1,6 +1,7 @@
 mod error;
 mod expr;
 mod item;
+mod pat;
 mod stmt;
 mod ty;
 
@@ -9,7 +10,7 @@ pub use error::*;
 use thin_vec::ThinVec;
 use tracing::trace;
 
-use crate::ast::{Ident, Item, Path, PathSegment, Span, DUMMY_SPAN};
+use crate::ast::{AstArena, DocComment, Ident, Item, Path, PathSegment, Span, DUMMY_SPAN};
 use crate::lexer::token::{Token, TokenKind};
 use crate::lexer::LexError;
 
@@ -39,10 +40,18 @@ impl std::fmt::Display for ExpectedToken {
     }
 }
 
-pub struct Parser<TokenStream: Iterator<Item = Result<Token, LexError>>> {
+pub struct Parser<'a, TokenStream: Iterator<Item = Result<Token, LexError>>> {
     /// The list of tokens.
     tokens: TokenStream,
 
+    /// The arena that the expressions are allocated in.
+    ///
+    /// It's borrowed rather than owned, since the AST borrows from it, so it
+    /// has to outlive the parser. The files of a package are parsed into one
+    /// arena, some of them in parallel, and the typer allocates the typed AST
+    /// in it too.
+    arena: &'a AstArena<'a>,
+
     /// The list of lexing errors uncovered during parsing.
     lex_errors: Vec<LexError>,
 
@@ -54,19 +63,35 @@ pub struct Parser<TokenStream: Iterator<Item = Result<Token, LexError>>> {
 
     /// The previous token.
     prev_token: Token,
+
+    /// The doc comments preceding the current token.
+    docs: ThinVec<DocComment>,
+
+    /// The doc comments read since the current token.
+    pending_docs: ThinVec<DocComment>,
+
+    /// Whether struct literals are disallowed in the expression being parsed.
+    ///
+    /// This is set while parsing the condition of a `while` loop, where the
+    /// opening brace belongs to the loop body.
+    no_struct_literal: bool,
 }
 
-impl<TokenStream> Parser<TokenStream>
+impl<'a, TokenStream> Parser<'a, TokenStream>
 where
     TokenStream: Iterator<Item = Result<Token, LexError>>,
 {
-    pub fn new(input: TokenStream) -> Self {
+    pub fn new(input: TokenStream, arena: &'a AstArena<'a>) -> Self {
         let mut parser = Self {
             tokens: input,
+            arena,
             lex_errors: Vec::new(),
             expected_tokens: Vec::new(),
             token: Token::dummy(),
             prev_token: Token::dummy(),
+            docs: ThinVec::new(),
+            pending_docs: ThinVec::new(),
+            no_struct_literal: false,
         };
 
         // Advance the parser to the first token.
@@ -75,7 +100,7 @@ where
         parser
     }
 
-    pub fn parse(mut self) -> ParseResult<ThinVec<Item>> {
+    pub fn parse(mut self) -> ParseResult<ThinVec<Item<'a>>> {
         trace!("Parsing program");
 
         let items_result = self.parse_module_items();
@@ -85,14 +110,14 @@ where
         if !self.is_at_end() {
             return Err(ParseError {
                 kind: ParseErrorKind::Error("Failed to parse.".to_string()),
-                span: self.token.span,
+                span: self.token.span.get(),
             });
         }
 
         Ok(items)
     }
 
-    fn parse_module_items(&mut self) -> ParseResult<ThinVec<Item>> {
+    fn parse_module_items(&mut self) -> ParseResult<ThinVec<Item<'a>>> {
         let mut items = ThinVec::new();
 
         while let Some(item) = self.parse_item()? {
@@ -126,12 +151,30 @@ where
             match self.tokens.next() {
                 // Ignore any comment tokens.
                 Some(Ok(Token {
-                    kind: TokenKind::Comment | TokenKind::DocComment,
+                    kind: TokenKind::Comment,
                     ..
                 })) => {
                     continue;
                 }
 
+                // Hold on to doc comments, for the item they precede.
+                Some(Ok(Token {
+                    kind: TokenKind::DocComment,
+                    lexeme,
+                    span,
+                })) => {
+                    self.pending_docs.push(DocComment {
+                        text: lexeme
+                            .strip_prefix("///")
+                            .unwrap_or(&lexeme)
+                            .trim_end()
+                            .into(),
+                        span: span.get(),
+                    });
+
+                    continue;
+                }
+
                 // Capture any lexing errors, as we come across them.
                 Some(Err(lex_error)) => {
                     next_token = None;
@@ -158,12 +201,14 @@ where
             kind: TokenKind::Eof,
             lexeme: "".into(),
             span: Span {
-                start: self.token.span.end,
-                end: self.token.span.end + 1,
-            },
+                start: self.token.span.get().end,
+                end: self.token.span.get().end + 1,
+            }
+            .into(),
         });
 
         self.prev_token = std::mem::replace(&mut self.token, next_token);
+        self.docs = std::mem::take(&mut self.pending_docs);
     }
 
     /// Returns whether the next token is of the given [`TokenKind`].
@@ -235,7 +280,7 @@ where
     pub fn parse_ident(&mut self) -> ParseResult<Ident> {
         let ident = self.token.ident().ok_or_else(|| ParseError {
             kind: ParseErrorKind::Error("Expected an identifier".to_string()),
-            span: self.token.span,
+            span: self.token.span.get(),
         })?;
 
         self.advance();
@@ -292,6 +337,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use crate::ast::{ExprKind, ItemKind, StmtKind};
     use crate::lexer::Lexer;
 
     use super::*;
@@ -302,9 +348,154 @@ mod tests {
             let source = std::fs::read_to_string(path).unwrap();
 
             let lexer = Lexer::new(&source);
-            let parser = Parser::new(lexer);
+            let arena = AstArena::new();
+            let parser = Parser::new(lexer, &arena);
 
             insta::assert_yaml_snapshot!(parser.parse());
         })
     }
+
+    #[test]
+    pub fn test_doc_comments_attach_to_the_next_item() {
+        let source = r#"
+/// Says hello.
+///
+/// Twice.
+#[inline]
+fn greet() {
+    /// Not attached to anything.
+    let greeting = "Hello"
+
+    fn nested() {}
+}
+
+// A plain comment.
+fn main() {}
+"#;
+
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let docs = |item: &Item| {
+            item.docs
+                .iter()
+                .map(|doc| doc.text.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(docs(&items[0]), [" Says hello.", "", " Twice."]);
+        assert_eq!(items[0].doc_text(), "Says hello.\n\nTwice.");
+        assert!(docs(&items[1]).is_empty());
+
+        let ItemKind::Fn(greet) = &items[0].kind else {
+            panic!("`greet` is a function");
+        };
+
+        let StmtKind::Item(nested) = &greet.body[1].kind else {
+            panic!("`nested` is an item");
+        };
+
+        assert!(docs(nested).is_empty());
+    }
+
+    #[test]
+    pub fn test_doc_comments_attach_to_fields_and_variants() {
+        let source = r#"
+struct Point {
+    /// The horizontal position.
+    #[bits(8)]
+    x: Uint64,
+    y: Uint64,
+}
+
+union Shape {
+    /// A shape without any area.
+    Empty,
+    Circle {
+        /// The distance from the center to the edge.
+        radius: Float64,
+    },
+}
+"#;
+
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let ItemKind::Struct(point) = &items[0].kind else {
+            panic!("`Point` is a struct");
+        };
+
+        let field_docs = point
+            .0
+            .fields()
+            .iter()
+            .map(|field| field.doc_text())
+            .collect::<Vec<_>>();
+
+        assert_eq!(field_docs, ["The horizontal position.", ""]);
+
+        let ItemKind::Union(shape) = &items[1].kind else {
+            panic!("`Shape` is a union");
+        };
+
+        assert_eq!(shape.variants[0].doc_text(), "A shape without any area.");
+        assert_eq!(shape.variants[1].doc_text(), "");
+        assert_eq!(
+            shape.variants[1].data.fields()[0].doc_text(),
+            "The distance from the center to the edge."
+        );
+    }
+
+    #[test]
+    pub fn test_macro_call() {
+        let source = r#"fn main() { let version = concat!("v", 1) }"#;
+
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let ItemKind::Fn(main) = &items[0].kind else {
+            panic!("`main` is a function");
+        };
+
+        let StmtKind::Local(local) = &main.body[0].kind else {
+            panic!("`version` is a `let` binding");
+        };
+
+        let init = local.kind.init().unwrap();
+
+        let ExprKind::Macro(macro_call) = &init.kind else {
+            panic!("`concat!` is a macro call");
+        };
+
+        assert_eq!(macro_call.name.name, "concat");
+        assert_eq!(macro_call.args.len(), 2);
+        assert_eq!(
+            &source[init.span.get().start..init.span.get().end],
+            r#"concat!("v", 1)"#
+        );
+    }
+
+    #[test]
+    pub fn test_macro_call_requires_parens() {
+        let arena = AstArena::new();
+        let err = Parser::new(Lexer::new("fn main() { let version = concat! }"), &arena)
+            .parse()
+            .unwrap_err();
+
+        assert!(matches!(
+            err.kind,
+            ParseErrorKind::Error(message) if message == "Expected `(` after `concat!`."
+        ));
+    }
+
+    /// Files are parsed into a shared arena on several threads at once, so
+    /// neither the parser nor the arena may hold anything thread-local.
+    #[test]
+    pub fn test_parser_is_send() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<Parser<'static, Lexer<'static>>>();
+        assert_sync::<AstArena<'static>>();
+    }
 }
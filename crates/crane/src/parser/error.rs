@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::ast::Span;
+use crate::error_codes::ErrorCode;
 use crate::lexer::{LexError, LexErrorKind};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +16,16 @@ pub enum ParseErrorKind {
     Error(String),
 }
 
+impl ParseErrorKind {
+    /// Returns the code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::LexError(LexErrorKind::Unknown) => ErrorCode::UnrecognizedCharacter,
+            Self::Error(_) => ErrorCode::SyntaxError,
+        }
+    }
+}
+
 impl std::fmt::Display for ParseErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -28,6 +39,14 @@ impl std::fmt::Display for ParseErrorKind {
     }
 }
 
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl From<LexError> for ParseError {
     fn from(value: LexError) -> Self {
         Self {
@@ -1,16 +1,174 @@
 use thin_vec::ThinVec;
 
-use crate::ast::{Expr, ExprKind, Literal, LiteralKind, Path, StructExpr, StructExprField};
+use crate::ast::{
+    keywords, BinaryOp, Expr, ExprKind, FieldExpr, ForExpr, Literal, LiteralKind, MacroCall,
+    MatchArm, MethodCallExpr, Path, Stmt, StmtKind, StructExpr, StructExprField,
+};
 use crate::lexer::token::{Token, TokenKind};
 use crate::lexer::LexError;
-use crate::parser::{ParseResult, Parser};
+use crate::parser::{ParseError, ParseErrorKind, ParseResult, Parser};
 
-impl<TokenStream> Parser<TokenStream>
+impl<'a, TokenStream> Parser<'a, TokenStream>
 where
     TokenStream: Iterator<Item = Result<Token, LexError>>,
 {
     #[tracing::instrument(skip(self))]
-    pub fn parse_expr(&mut self) -> ParseResult<Option<Expr>> {
+    pub fn parse_expr(&mut self) -> ParseResult<Option<Expr<'a>>> {
+        self.parse_binary_expr(0)
+    }
+
+    /// Parses a binary expression made up of operators that bind at least as
+    /// tightly as `min_precedence`.
+    #[tracing::instrument(skip(self))]
+    fn parse_binary_expr(&mut self, min_precedence: u8) -> ParseResult<Option<Expr<'a>>> {
+        let Some(mut lhs) = self.parse_cast_expr()? else {
+            return Ok(None);
+        };
+
+        while let Some(op) = self.binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+
+            let op_span = self.token.span;
+
+            self.advance();
+
+            let rhs = self
+                .parse_binary_expr(op.precedence() + 1)?
+                .ok_or_else(|| ParseError {
+                    kind: ParseErrorKind::Error(format!("Expected an expression after `{op}`.")),
+                    span: op_span.into(),
+                })?;
+
+            let span = lhs.span.to(rhs.span);
+
+            lhs = Expr {
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: self.arena.alloc_expr(lhs),
+                    rhs: self.arena.alloc_expr(rhs),
+                },
+                span,
+            };
+        }
+
+        Ok(Some(lhs))
+    }
+
+    /// Parses an expression followed by any number of `as` casts.
+    #[tracing::instrument(skip(self))]
+    fn parse_cast_expr(&mut self) -> ParseResult<Option<Expr<'a>>> {
+        let Some(mut expr) = self.parse_postfix_expr()? else {
+            return Ok(None);
+        };
+
+        while self.consume_keyword(keywords::AS) {
+            let ty = self.parse_ty()?;
+
+            let span = expr.span.to(ty.span);
+
+            expr = Expr {
+                kind: ExprKind::Cast {
+                    expr: self.arena.alloc_expr(expr),
+                    ty: Box::new(ty),
+                },
+                span,
+            };
+        }
+
+        Ok(Some(expr))
+    }
+
+    /// Parses an expression followed by any number of `[index]`es, `.field`
+    /// accesses, and `.method(args)` calls.
+    #[tracing::instrument(skip(self))]
+    fn parse_postfix_expr(&mut self) -> ParseResult<Option<Expr<'a>>> {
+        let Some(mut expr) = self.parse_primary_expr()? else {
+            return Ok(None);
+        };
+
+        loop {
+            if self.consume(TokenKind::Dot) {
+                let name = self.parse_ident()?;
+
+                if self.check_without_expect(TokenKind::OpenParen) {
+                    let args = self.parse_call_expr()?;
+
+                    let span = expr.span.to(self.prev_token.span);
+
+                    expr = Expr {
+                        kind: ExprKind::MethodCall(Box::new(MethodCallExpr {
+                            receiver: self.arena.alloc_expr(expr),
+                            name,
+                            args: args
+                                .into_iter()
+                                .map(|arg| self.arena.alloc_expr(arg))
+                                .collect(),
+                        })),
+                        span,
+                    };
+                } else {
+                    let span = expr.span.to(name.span);
+
+                    expr = Expr {
+                        kind: ExprKind::Field(Box::new(FieldExpr {
+                            expr: self.arena.alloc_expr(expr),
+                            name,
+                        })),
+                        span,
+                    };
+                }
+
+                continue;
+            }
+
+            if !self.consume(TokenKind::OpenBracket) {
+                break;
+            }
+
+            let index = self.parse_expr()?.ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error("Expected an index expression.".to_string()),
+                span: self.token.span.get(),
+            })?;
+
+            self.consume(TokenKind::CloseBracket);
+
+            let span = expr.span.to(self.prev_token.span);
+
+            expr = Expr {
+                kind: ExprKind::Index {
+                    expr: self.arena.alloc_expr(expr),
+                    index: self.arena.alloc_expr(index),
+                },
+                span,
+            };
+        }
+
+        Ok(Some(expr))
+    }
+
+    /// Returns the binary operator for the current token, if it is one.
+    fn binary_op(&self) -> Option<BinaryOp> {
+        Some(match self.token.kind {
+            TokenKind::Plus => BinaryOp::Add,
+            TokenKind::Minus => BinaryOp::Sub,
+            TokenKind::Star => BinaryOp::Mul,
+            TokenKind::Slash => BinaryOp::Div,
+            TokenKind::Percent => BinaryOp::Rem,
+            TokenKind::EqualEqual => BinaryOp::Eq,
+            TokenKind::BangEqual => BinaryOp::Ne,
+            TokenKind::Less => BinaryOp::Lt,
+            TokenKind::LessEqual => BinaryOp::Le,
+            TokenKind::Greater => BinaryOp::Gt,
+            TokenKind::GreaterEqual => BinaryOp::Ge,
+            TokenKind::AmpersandAmpersand => BinaryOp::And,
+            _ => return None,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn parse_primary_expr(&mut self) -> ParseResult<Option<Expr<'a>>> {
         if self.check(TokenKind::String) {
             let string_literal = Expr {
                 kind: ExprKind::Literal(Literal {
@@ -39,36 +197,93 @@ where
             return Ok(Some(int_literal));
         }
 
+        if self.check(TokenKind::Float) {
+            let float_literal = Expr {
+                kind: ExprKind::Literal(Literal {
+                    kind: LiteralKind::Float,
+                    value: self.token.lexeme.clone(),
+                }),
+                span: self.token.span,
+            };
+
+            self.advance();
+
+            return Ok(Some(float_literal));
+        }
+
+        if self.check_without_expect(TokenKind::OpenBracket) {
+            return self.parse_array_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::WHILE) {
+            return self.parse_while_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::TYPEOF) {
+            return self.parse_typeof_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::ASM) {
+            return self.parse_asm_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::IF) {
+            return self.parse_if_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::FOR) {
+            return self.parse_for_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::COMPTIME) {
+            return self.parse_comptime_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::UNSAFE) {
+            return self.parse_unsafe_expr().map(Some);
+        }
+
+        if self.token.is_keyword(keywords::MATCH) {
+            return self.parse_match_expr().map(Some);
+        }
+
         if self.check_without_expect(TokenKind::Ident) {
             let path = self.parse_path()?;
 
+            if self.check_without_expect(TokenKind::Bang) {
+                return self.parse_macro_call(path).map(Some);
+            }
+
             if self.check_without_expect(TokenKind::OpenParen) {
                 let span = path.span;
 
                 let callee = Expr {
                     kind: ExprKind::Variable(path),
-                    span,
+                    span: span.into(),
                 };
 
                 let args = self.parse_call_expr()?;
 
                 return Ok(Some(Expr {
                     kind: ExprKind::Call {
-                        fun: Box::new(callee),
-                        args: args.into_iter().map(Box::new).collect(),
+                        fun: self.arena.alloc_expr(callee),
+                        args: args
+                            .into_iter()
+                            .map(|arg| self.arena.alloc_expr(arg))
+                            .collect(),
                     },
-                    span,
+                    span: span.into(),
                 }));
             }
 
-            if self.check_without_expect(TokenKind::OpenBrace) {
+            if !self.no_struct_literal && self.check_without_expect(TokenKind::OpenBrace) {
                 let struct_expr = self.parse_struct_expr(&path)?;
 
-                let span = path.span.to(self.prev_token.span);
+                let span = path.span.to(self.prev_token.span.get());
 
                 return Ok(Some(Expr {
                     kind: ExprKind::Struct(Box::new(struct_expr)),
-                    span,
+                    span: span.into(),
                 }));
             }
 
@@ -76,15 +291,376 @@ where
 
             return Ok(Some(Expr {
                 kind: ExprKind::Variable(path),
-                span,
+                span: span.into(),
             }));
         }
 
         Ok(None)
     }
 
+    /// Parses an array literal (`[a, b, c]`).
+    #[tracing::instrument(skip(self))]
+    fn parse_array_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.consume(TokenKind::OpenBracket);
+
+        let mut elems = ThinVec::new();
+
+        while let Some(elem) = self.parse_expr()? {
+            elems.push(self.arena.alloc_expr(elem));
+
+            if !self.consume(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::CloseBracket);
+
+        Ok(Expr {
+            kind: ExprKind::Array(elems),
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a `while` loop.
+    #[tracing::instrument(skip(self))]
+    fn parse_while_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        let cond = self
+            .parse_expr_no_struct_literal()?
+            .ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected a condition after `{}`.",
+                    keywords::WHILE
+                )),
+                span: self.token.span.get(),
+            })?;
+
+        let body = self.parse_block()?;
+
+        Ok(Expr {
+            kind: ExprKind::While {
+                cond: self.arena.alloc_expr(cond),
+                body,
+            },
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses an expression in a position where a struct literal's `{` would
+    /// be mistaken for the start of a block, such as a loop condition.
+    fn parse_expr_no_struct_literal(&mut self) -> ParseResult<Option<Expr<'a>>> {
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, true);
+        let expr = self.parse_expr();
+        self.no_struct_literal = no_struct_literal;
+
+        expr
+    }
+
+    /// Parses an `if` expression, along with any `else if` and `else`
+    /// branches.
+    #[tracing::instrument(skip(self))]
+    fn parse_if_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        let cond = self
+            .parse_expr_no_struct_literal()?
+            .ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected a condition after `{}`.",
+                    keywords::IF
+                )),
+                span: self.token.span.get(),
+            })?;
+
+        let then_branch = self.parse_block()?;
+
+        let else_branch = if self.consume_keyword(keywords::ELSE) {
+            if self.token.is_keyword(keywords::IF) {
+                // An `else if` is an `else` branch holding another `if`.
+                let else_if = self.parse_if_expr()?;
+
+                let span = else_if.span;
+
+                Some(ThinVec::from([Stmt {
+                    kind: StmtKind::Expr(self.arena.alloc_expr(else_if)),
+                    span,
+                }]))
+            } else {
+                Some(self.parse_block()?)
+            }
+        } else {
+            None
+        };
+
+        Ok(Expr {
+            kind: ExprKind::If {
+                cond: self.arena.alloc_expr(cond),
+                then_branch,
+                else_branch,
+            },
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a `for` loop over a range (`for i in start..end { ... }`).
+    // TODO: Loop over anything that implements an `Iterator` trait, once
+    // generics, closures and `Option` exist for it to be written with.
+    #[tracing::instrument(skip(self))]
+    fn parse_for_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start_span = self.token.span;
+
+        self.advance();
+
+        let binding = self.parse_ident()?;
+
+        if !self.consume_keyword(keywords::IN) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected `{}` after the `{}` binding.",
+                    keywords::IN,
+                    keywords::FOR
+                )),
+                span: self.token.span.get(),
+            });
+        }
+
+        let start = self.parse_expr()?.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::Error("Expected the start of a range.".to_string()),
+            span: self.token.span.get(),
+        })?;
+
+        self.consume(TokenKind::DotDot);
+
+        let end = self
+            .parse_expr_no_struct_literal()?
+            .ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error("Expected the end of a range.".to_string()),
+                span: self.token.span.get(),
+            })?;
+
+        let body = self.parse_block()?;
+
+        Ok(Expr {
+            kind: ExprKind::For(Box::new(ForExpr {
+                binding,
+                start: self.arena.alloc_expr(start),
+                end: self.arena.alloc_expr(end),
+                body,
+            })),
+            span: start_span.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a `comptime` block.
+    #[tracing::instrument(skip(self))]
+    fn parse_comptime_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        let body = self.parse_block()?;
+
+        Ok(Expr {
+            kind: ExprKind::Comptime(body),
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses an `unsafe` block.
+    #[tracing::instrument(skip(self))]
+    fn parse_unsafe_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        let body = self.parse_block()?;
+
+        Ok(Expr {
+            kind: ExprKind::Unsafe(body),
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a `match` expression.
+    #[tracing::instrument(skip(self))]
+    fn parse_match_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        let scrutinee = self
+            .parse_expr_no_struct_literal()?
+            .ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected an expression after `{}`.",
+                    keywords::MATCH
+                )),
+                span: self.token.span.get(),
+            })?;
+
+        self.consume(TokenKind::OpenBrace);
+
+        let mut arms = ThinVec::new();
+
+        while !self.check_without_expect(TokenKind::CloseBrace) {
+            arms.push(self.parse_match_arm()?);
+
+            if !self.consume(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::CloseBrace);
+
+        Ok(Expr {
+            kind: ExprKind::Match {
+                scrutinee: self.arena.alloc_expr(scrutinee),
+                arms,
+            },
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a [`MatchArm`], whose body is either a block or a single
+    /// expression.
+    #[tracing::instrument(skip(self))]
+    fn parse_match_arm(&mut self) -> ParseResult<MatchArm<'a>> {
+        let pat = self.parse_pat()?;
+
+        self.consume(TokenKind::FatArrow);
+
+        let body = if self.check_without_expect(TokenKind::OpenBrace) {
+            self.parse_block()?
+        } else {
+            let expr = self.parse_expr()?.ok_or_else(|| ParseError {
+                kind: ParseErrorKind::Error("Expected an expression after `=>`.".to_string()),
+                span: self.token.span.get(),
+            })?;
+
+            let span = expr.span;
+
+            ThinVec::from([Stmt {
+                kind: StmtKind::Expr(self.arena.alloc_expr(expr)),
+                span,
+            }])
+        };
+
+        let span = pat.span.to(self.prev_token.span.get());
+
+        Ok(MatchArm { pat, body, span })
+    }
+
+    /// Parses a `typeof(expr)` expression.
+    #[tracing::instrument(skip(self))]
+    fn parse_typeof_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        self.consume(TokenKind::OpenParen);
+
+        let expr = self.parse_expr()?.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::Error(format!(
+                "Expected an expression in `{}`.",
+                keywords::TYPEOF
+            )),
+            span: self.token.span.get(),
+        })?;
+
+        self.consume(TokenKind::CloseParen);
+
+        Ok(Expr {
+            kind: ExprKind::TypeOf(self.arena.alloc_expr(expr)),
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses an `asm("...")` expression.
+    #[tracing::instrument(skip(self))]
+    fn parse_asm_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let start = self.token.span;
+
+        self.advance();
+
+        self.consume(TokenKind::OpenParen);
+
+        let mut lines = ThinVec::new();
+
+        while self.check_without_expect(TokenKind::String) {
+            lines.push(self.token.lexeme.trim_matches('"').into());
+
+            self.advance();
+
+            if !self.consume(TokenKind::Comma) {
+                break;
+            }
+        }
+
+        if lines.is_empty() {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected the lines of assembly in `{}`.",
+                    keywords::ASM
+                )),
+                span: self.token.span.get(),
+            });
+        }
+
+        self.consume(TokenKind::CloseParen);
+
+        Ok(Expr {
+            kind: ExprKind::Asm(lines),
+            span: start.to(self.prev_token.span),
+        })
+    }
+
+    /// Parses a macro call (`name!(args)`), whose name has already been parsed
+    /// as `path`.
+    #[tracing::instrument(skip(self))]
+    fn parse_macro_call(&mut self, path: Path) -> ParseResult<Expr<'a>> {
+        let [segment] = path.segments.as_slice() else {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(
+                    "Macros are built in, so their names can't be paths.".to_string(),
+                ),
+                span: path.span,
+            });
+        };
+
+        let name = segment.ident.clone();
+
+        self.advance();
+
+        if !self.check_without_expect(TokenKind::OpenParen) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!("Expected `(` after `{name}!`.")),
+                span: self.token.span.get(),
+            });
+        }
+
+        let args = self.parse_call_expr()?;
+
+        Ok(Expr {
+            kind: ExprKind::Macro(Box::new(MacroCall {
+                name,
+                args: args
+                    .into_iter()
+                    .map(|arg| self.arena.alloc_expr(arg))
+                    .collect(),
+            })),
+            span: path.span.to(self.prev_token.span.get()).into(),
+        })
+    }
+
     #[tracing::instrument(skip(self))]
-    fn parse_call_expr(&mut self) -> ParseResult<ThinVec<Expr>> {
+    fn parse_call_expr(&mut self) -> ParseResult<ThinVec<Expr<'a>>> {
         self.consume(TokenKind::OpenParen);
 
         let mut args = ThinVec::new();
@@ -112,7 +688,7 @@ where
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_struct_expr(&mut self, path: &Path) -> ParseResult<StructExpr> {
+    fn parse_struct_expr(&mut self, path: &Path) -> ParseResult<StructExpr<'a>> {
         self.consume(TokenKind::OpenBrace);
 
         let mut fields = ThinVec::new();
@@ -123,13 +699,18 @@ where
 
                 self.consume(TokenKind::Colon);
 
-                let expr = self.parse_expr()?.unwrap();
+                let expr = self.parse_expr()?.ok_or_else(|| ParseError {
+                    kind: ParseErrorKind::Error(format!(
+                        "Expected a value for the `{field_name}` field."
+                    )),
+                    span: self.token.span.get(),
+                })?;
 
-                let span = field_name.span.to(expr.span);
+                let span = field_name.span.to(expr.span.get());
 
                 fields.push(StructExprField {
                     name: field_name,
-                    expr: Box::new(expr),
+                    expr: self.arena.alloc_expr(expr),
                     span,
                 });
 
@@ -1,32 +1,131 @@
-use thin_vec::ThinVec;
+use smol_str::SmolStr;
+use thin_vec::{thin_vec, ThinVec};
 
 use crate::ast::{
-    keywords, FieldDecl, Fn, FnDecl, FnParam, FnReturnTy, Ident, InlineModuleDecl, Item, ItemKind,
-    Module, ModuleDecl, Path, PathSegment, StructDecl, UnionDecl, UseTree, UseTreeKind, Variant,
-    VariantData, DUMMY_SPAN,
+    keywords, Attribute, AttributeArg, ConstDecl, FieldDecl, Fn, FnDecl, FnParam, FnReturnTy,
+    Ident, ImplDecl, InlineModuleDecl, Item, ItemKind, Module, ModuleDecl, Path, PathSegment,
+    StructDecl, Symbol, UnionDecl, UseTree, UseTreeKind, Variant, VariantData, Visibility,
+    DUMMY_SPAN,
 };
 use crate::lexer::token::{Token, TokenKind};
 use crate::lexer::LexError;
-use crate::parser::{ParseResult, Parser};
+use crate::parser::{ParseError, ParseErrorKind, ParseResult, Parser};
 
-type ItemInfo = (Ident, ItemKind);
+type ItemInfo<'a> = (Ident, ItemKind<'a>);
 
-impl<TokenStream> Parser<TokenStream>
+impl<'a, TokenStream> Parser<'a, TokenStream>
 where
     TokenStream: Iterator<Item = Result<Token, LexError>>,
 {
     /// Parses an [`Item`].
     #[tracing::instrument(skip(self))]
-    pub fn parse_item(&mut self) -> ParseResult<Option<Item>> {
-        self.consume_keyword(keywords::PUB);
+    pub fn parse_item(&mut self) -> ParseResult<Option<Item<'a>>> {
+        let docs = std::mem::take(&mut self.docs);
 
-        Ok(self
-            .parse_item_kind()?
-            .map(|(name, kind)| Item { name, kind }))
+        let attrs = self.parse_attributes()?;
+
+        let vis = if self.consume_keyword(keywords::PUB) {
+            Visibility::Public
+        } else {
+            Visibility::Private
+        };
+
+        Ok(self.parse_item_kind()?.map(|(name, kind)| Item {
+            name,
+            kind,
+            vis,
+            attrs,
+            docs,
+        }))
     }
 
+    /// Parses the [`Attribute`]s preceding an item or field.
     #[tracing::instrument(skip(self))]
-    fn parse_item_kind(&mut self) -> ParseResult<Option<ItemInfo>> {
+    fn parse_attributes(&mut self) -> ParseResult<ThinVec<Attribute>> {
+        let mut attrs = ThinVec::new();
+
+        while self.check_without_expect(TokenKind::Pound) {
+            let start = self.token.span;
+
+            self.advance();
+
+            self.consume(TokenKind::OpenBracket);
+
+            let name = self.parse_ident()?;
+
+            let mut args = ThinVec::new();
+            let mut value = None;
+
+            if self.consume(TokenKind::Equal) {
+                value = Some(self.parse_attribute_value()?);
+            } else if self.consume(TokenKind::OpenParen) {
+                while !self.check(TokenKind::CloseParen) {
+                    // Integer arguments, like the width in `#[bits(3)]`, are
+                    // kept as they are written.
+                    let name = if self.check_without_expect(TokenKind::Integer) {
+                        let name = Ident {
+                            name: Symbol::intern(&self.token.lexeme),
+                            span: self.token.span.get(),
+                        };
+
+                        self.advance();
+
+                        name
+                    } else {
+                        self.parse_ident()?
+                    };
+
+                    let mut span = name.span;
+
+                    let value = if self.consume(TokenKind::Equal) {
+                        span = span.to(self.token.span.get());
+
+                        Some(self.parse_attribute_value()?)
+                    } else {
+                        None
+                    };
+
+                    args.push(AttributeArg { name, value, span });
+
+                    if !self.consume(TokenKind::Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(TokenKind::CloseParen);
+            }
+
+            self.consume(TokenKind::CloseBracket);
+
+            attrs.push(Attribute {
+                name,
+                args,
+                value,
+                span: start.to(self.prev_token.span).into(),
+            });
+        }
+
+        Ok(attrs)
+    }
+
+    /// Parses the string literal value of an attribute or attribute argument.
+    fn parse_attribute_value(&mut self) -> ParseResult<SmolStr> {
+        if !self.check(TokenKind::String) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error("Expected a string literal.".to_string()),
+                span: self.token.span.get(),
+            });
+        }
+
+        let value = self.token.lexeme.trim_matches('"').into();
+
+        self.advance();
+
+        Ok(value)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn parse_item_kind(&mut self) -> ParseResult<Option<ItemInfo<'a>>> {
         if self.consume_keyword(keywords::USE) {
             let ident = Ident {
                 name: "".into(),
@@ -44,6 +143,41 @@ where
             return Ok(Some((name, ItemKind::Fn(Box::new(fun)))));
         }
 
+        if self.consume_keyword(keywords::UNSAFE) {
+            if !self.consume_keyword(keywords::FN) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Error(format!(
+                        "Expected `{}` after `{}`.",
+                        keywords::FN,
+                        keywords::UNSAFE
+                    )),
+                    span: self.token.span.get(),
+                });
+            }
+
+            let (name, mut fun) = self.parse_fn()?;
+            fun.is_unsafe = true;
+
+            return Ok(Some((name, ItemKind::Fn(Box::new(fun)))));
+        }
+
+        if self.consume_keyword(keywords::EXTERN) {
+            if !self.consume_keyword(keywords::FN) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Error(format!(
+                        "Expected `{}` after `{}`.",
+                        keywords::FN,
+                        keywords::EXTERN
+                    )),
+                    span: self.token.span.get(),
+                });
+            }
+
+            let (name, fun_decl) = self.parse_fn_decl()?;
+
+            return Ok(Some((name, ItemKind::ExternFn(Box::new(fun_decl)))));
+        }
+
         if self.consume_keyword(keywords::STRUCT) {
             let (name, struct_decl) = self.parse_struct_decl()?;
 
@@ -62,6 +196,18 @@ where
             return Ok(Some((name, ItemKind::Module(Box::new(module_decl)))));
         }
 
+        if self.consume_keyword(keywords::IMPL) {
+            let (name, impl_decl) = self.parse_impl_decl()?;
+
+            return Ok(Some((name, ItemKind::Impl(Box::new(impl_decl)))));
+        }
+
+        if self.consume_keyword(keywords::CONST) {
+            let (name, const_decl) = self.parse_const_decl()?;
+
+            return Ok(Some((name, ItemKind::Const(const_decl))));
+        }
+
         Ok(None)
     }
 
@@ -77,6 +223,13 @@ where
             }
         }
 
+        if path_segments.is_empty() {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!("Expected a path after `{}`.", keywords::USE)),
+                span: self.token.span.get(),
+            });
+        }
+
         Ok(UseTree {
             prefix: Path {
                 segments: path_segments,
@@ -87,7 +240,24 @@ where
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_fn(&mut self) -> ParseResult<(Ident, Fn)> {
+    fn parse_fn(&mut self) -> ParseResult<(Ident, Fn<'a>)> {
+        let (ident, decl) = self.parse_fn_decl()?;
+
+        let body = self.parse_block()?;
+
+        Ok((
+            ident,
+            Fn {
+                decl: Box::new(decl),
+                body,
+                is_unsafe: false,
+            },
+        ))
+    }
+
+    /// Parses the name, parameters and return type of a function.
+    #[tracing::instrument(skip(self))]
+    fn parse_fn_decl(&mut self) -> ParseResult<(Ident, FnDecl<'a>)> {
         let ident = self.parse_ident()?;
 
         self.consume(TokenKind::OpenParen);
@@ -126,35 +296,137 @@ where
             FnReturnTy::Unit
         };
 
+        Ok((ident, FnDecl { params, return_ty }))
+    }
+
+    /// Parses an `impl` block, which may only contain functions.
+    ///
+    /// The block implements a trait if it is written as `impl Trait for Type`.
+    #[tracing::instrument(skip(self))]
+    fn parse_impl_decl(&mut self) -> ParseResult<(Ident, ImplDecl<'a>)> {
+        let mut ident = self.parse_ident()?;
+        let mut trait_name = None;
+
+        if self.consume_keyword(keywords::FOR) {
+            trait_name = Some(ident);
+            ident = self.parse_ident()?;
+        }
+
         self.consume(TokenKind::OpenBrace);
 
-        let mut body = ThinVec::new();
+        let mut items = ThinVec::new();
 
-        while let Some(stmt) = self.parse_stmt()? {
-            body.push(stmt);
+        while let Some(item) = self.parse_item()? {
+            if !matches!(item.kind, ItemKind::Fn(_)) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Error(format!(
+                        "Only functions can be declared in an `{}` block.",
+                        keywords::IMPL
+                    )),
+                    span: item.name.span,
+                });
+            }
+
+            items.push(item);
         }
 
         self.consume(TokenKind::CloseBrace);
 
+        Ok((ident, ImplDecl { trait_name, items }))
+    }
+
+    /// Parses a `const` declaration, like `const MAX_LEN: Uint64 = 16`.
+    #[tracing::instrument(skip(self))]
+    fn parse_const_decl(&mut self) -> ParseResult<(Ident, ConstDecl<'a>)> {
+        let ident = self.parse_ident()?;
+
+        if !self.consume(TokenKind::Colon) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected a type for the `{}` `{ident}`, like `{} {ident}: Uint64 = 1`.",
+                    keywords::CONST,
+                    keywords::CONST
+                )),
+                span: self.token.span.get(),
+            });
+        }
+
+        let ty = self.parse_ty()?;
+
+        let equal_span = self.token.span;
+
+        self.consume(TokenKind::Equal);
+
+        let expr = self.parse_expr()?.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::Error("Expected an expression after `=`.".to_string()),
+            span: equal_span.into(),
+        })?;
+
         Ok((
             ident,
-            Fn {
-                decl: Box::new(FnDecl { params, return_ty }),
-                body,
+            ConstDecl {
+                ty: Box::new(ty),
+                expr: self.arena.alloc_expr(expr),
             },
         ))
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_struct_decl(&mut self) -> ParseResult<(Ident, StructDecl)> {
+    fn parse_struct_decl(&mut self) -> ParseResult<(Ident, StructDecl<'a>)> {
         let ident = self.parse_ident()?;
 
+        if self.check_without_expect(TokenKind::OpenParen) {
+            let field = self.parse_tuple_field_decl()?;
+
+            return Ok((ident, StructDecl(VariantData::Tuple(thin_vec![field]))));
+        }
+
+        let fields = self.parse_field_decls()?;
+
+        Ok((ident, StructDecl(VariantData::Struct(fields))))
+    }
+
+    /// Parses the parenthesized [`FieldDecl`] of a tuple struct, like
+    /// `(Float64)` in `struct Meters(Float64)`.
+    #[tracing::instrument(skip(self))]
+    fn parse_tuple_field_decl(&mut self) -> ParseResult<FieldDecl<'a>> {
+        self.consume(TokenKind::OpenParen);
+
+        let ty = self.parse_ty()?;
+
+        if !self.consume(TokenKind::CloseParen) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(
+                    "Tuple structs have a single field, like `struct Meters(Float64)`.".to_string(),
+                ),
+                span: self.token.span.get(),
+            });
+        }
+
+        let span = ty.span;
+
+        Ok(FieldDecl {
+            name: None,
+            ty: Box::new(ty),
+            attrs: ThinVec::new(),
+            docs: ThinVec::new(),
+            span,
+        })
+    }
+
+    /// Parses the brace-delimited [`FieldDecl`]s of a `struct` or [`Variant`].
+    #[tracing::instrument(skip(self))]
+    fn parse_field_decls(&mut self) -> ParseResult<ThinVec<FieldDecl<'a>>> {
         self.consume(TokenKind::OpenBrace);
 
         let mut fields = ThinVec::new();
 
         if !self.check(TokenKind::CloseBrace) {
             loop {
+                let docs = std::mem::take(&mut self.docs);
+
+                let attrs = self.parse_attributes()?;
+
                 let field_name = self.parse_ident()?;
 
                 self.consume(TokenKind::Colon);
@@ -166,6 +438,8 @@ where
                 fields.push(FieldDecl {
                     name: Some(field_name),
                     ty: Box::new(ty),
+                    attrs,
+                    docs,
                     span,
                 });
 
@@ -179,11 +453,11 @@ where
 
         self.consume(TokenKind::CloseBrace);
 
-        Ok((ident, StructDecl(VariantData::Struct(fields))))
+        Ok(fields)
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_union_decl(&mut self) -> ParseResult<(Ident, UnionDecl)> {
+    fn parse_union_decl(&mut self) -> ParseResult<(Ident, UnionDecl<'a>)> {
         let ident = self.parse_ident()?;
 
         self.consume(TokenKind::OpenBrace);
@@ -192,13 +466,22 @@ where
 
         if !self.check(TokenKind::CloseBrace) {
             loop {
+                let docs = std::mem::take(&mut self.docs);
+
                 let variant_name = self.parse_ident()?;
 
                 let span = variant_name.span;
 
+                let data = if self.check_without_expect(TokenKind::OpenBrace) {
+                    VariantData::Struct(self.parse_field_decls()?)
+                } else {
+                    VariantData::Unit
+                };
+
                 variants.push(Variant {
                     name: variant_name,
-                    data: VariantData::Unit,
+                    data,
+                    docs,
                     span,
                 });
 
@@ -216,24 +499,28 @@ where
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_module_decl(&mut self) -> ParseResult<(Ident, ModuleDecl)> {
+    fn parse_module_decl(&mut self) -> ParseResult<(Ident, ModuleDecl<'a>)> {
         let ident = self.parse_ident()?;
 
-        self.consume(TokenKind::OpenBrace);
+        // A module without a body refers to a module in a separate file.
+        if !self.consume(TokenKind::OpenBrace) {
+            return Ok((ident, ModuleDecl::Unloaded));
+        }
 
         let mut items = ThinVec::new();
 
-        while !self.check(TokenKind::CloseBrace) {
-            loop {
-                if let Some(item) = self.parse_item()? {
-                    items.push(item);
-                } else {
-                    break;
-                }
-            }
+        while let Some(item) = self.parse_item()? {
+            items.push(item);
         }
 
-        self.consume(TokenKind::CloseBrace);
+        if !self.consume(TokenKind::CloseBrace) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error(format!(
+                    "Expected `}}` at the end of the `{ident}` module."
+                )),
+                span: self.token.span.get(),
+            });
+        }
 
         Ok((
             ident,
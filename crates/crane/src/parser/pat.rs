@@ -0,0 +1,36 @@
+use crate::ast::{Literal, LiteralKind, Pat, PatKind};
+use crate::lexer::token::{Token, TokenKind};
+use crate::lexer::LexError;
+use crate::parser::{ParseError, ParseErrorKind, ParseResult, Parser};
+
+impl<'a, TokenStream> Parser<'a, TokenStream>
+where
+    TokenStream: Iterator<Item = Result<Token, LexError>>,
+{
+    /// Parses a [`Pat`].
+    #[tracing::instrument(skip(self))]
+    pub fn parse_pat(&mut self) -> ParseResult<Pat> {
+        let kind = if self.check(TokenKind::Integer) {
+            PatKind::Literal(Literal {
+                kind: LiteralKind::Integer,
+                value: self.token.lexeme.clone(),
+            })
+        } else if self.token.kind == TokenKind::Ident && self.token.lexeme == "_" {
+            PatKind::Wild
+        } else {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error("Expected a pattern.".to_string()),
+                span: self.token.span.get(),
+            });
+        };
+
+        let span = self.token.span;
+
+        self.advance();
+
+        Ok(Pat {
+            kind,
+            span: span.get(),
+        })
+    }
+}
@@ -1,15 +1,17 @@
-use crate::ast::{keywords, Local, LocalKind, Stmt, StmtKind};
+use thin_vec::{thin_vec, ThinVec};
+
+use crate::ast::{keywords, Expr, ExprKind, Local, LocalKind, Path, PathSegment, Stmt, StmtKind};
 use crate::lexer::token::{Token, TokenKind};
 use crate::lexer::LexError;
 use crate::parser::{ParseError, ParseErrorKind, ParseResult, Parser};
 
-impl<TokenStream> Parser<TokenStream>
+impl<'a, TokenStream> Parser<'a, TokenStream>
 where
     TokenStream: Iterator<Item = Result<Token, LexError>>,
 {
     /// Parses a [`Stmt`].
     #[tracing::instrument(skip(self))]
-    pub fn parse_stmt(&mut self) -> ParseResult<Option<Stmt>> {
+    pub fn parse_stmt(&mut self) -> ParseResult<Option<Stmt<'a>>> {
         if self.consume_keyword(keywords::LET) {
             let local = self.parse_local()?;
 
@@ -17,15 +19,28 @@ where
 
             return Ok(Some(Stmt {
                 kind: StmtKind::Local(Box::new(local)),
-                span,
+                span: span.into(),
             }));
         }
 
+        if self.token.is_keyword(keywords::FN) || self.check_without_expect(TokenKind::Pound) {
+            let start = self.token.span;
+
+            if let Some(item) = self.parse_item()? {
+                return Ok(Some(Stmt {
+                    kind: StmtKind::Item(Box::new(item)),
+                    span: start.to(self.prev_token.span),
+                }));
+            }
+        }
+
         if let Some(expr) = self.parse_expr()? {
+            let expr = self.parse_assign(expr)?;
+
             let span = expr.span;
 
             return Ok(Some(Stmt {
-                kind: StmtKind::Expr(Box::new(expr)),
+                kind: StmtKind::Expr(self.arena.alloc_expr(expr)),
                 span,
             }));
         }
@@ -33,9 +48,83 @@ where
         Ok(None)
     }
 
+    /// Parses a brace-delimited block of [`Stmt`]s.
+    #[tracing::instrument(skip(self))]
+    pub fn parse_block(&mut self) -> ParseResult<ThinVec<Stmt<'a>>> {
+        self.consume(TokenKind::OpenBrace);
+
+        let mut stmts = ThinVec::new();
+
+        while let Some(stmt) = self.parse_stmt()? {
+            stmts.push(stmt);
+        }
+
+        self.consume(TokenKind::CloseBrace);
+
+        Ok(stmts)
+    }
+
+    /// Parses the remainder of an assignment, if the given expression is
+    /// followed by an `=`.
+    #[tracing::instrument(skip(self))]
+    fn parse_assign(&mut self, expr: Expr<'a>) -> ParseResult<Expr<'a>> {
+        if !self.check_without_expect(TokenKind::Equal) {
+            return Ok(expr);
+        }
+
+        if !matches!(expr.kind, ExprKind::Variable(_)) {
+            return Err(ParseError {
+                kind: ParseErrorKind::Error("Only variables can be assigned to.".to_string()),
+                span: expr.span.get(),
+            });
+        }
+
+        let equal_span = self.token.span;
+
+        self.advance();
+
+        let value = self.parse_expr()?.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::Error("Expected an expression after `=`.".to_string()),
+            span: equal_span.into(),
+        })?;
+
+        let span = expr.span.to(value.span);
+
+        Ok(Expr {
+            kind: ExprKind::Assign {
+                target: self.arena.alloc_expr(expr),
+                value: self.arena.alloc_expr(value),
+            },
+            span,
+        })
+    }
+
     #[tracing::instrument(skip(self))]
-    fn parse_local(&mut self) -> ParseResult<Local> {
-        let name = self.parse_ident()?;
+    fn parse_local(&mut self) -> ParseResult<Local<'a>> {
+        let mut name = self.parse_ident()?;
+
+        // `let Meters(x) = m` destructures the tuple struct `Meters`.
+        let destructure = if self.consume(TokenKind::OpenParen) {
+            let path = Path {
+                span: name.span,
+                segments: thin_vec![PathSegment { ident: name }],
+            };
+
+            name = self.parse_ident()?;
+
+            if !self.consume(TokenKind::CloseParen) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Error(
+                        "Expected `)` after the destructured field.".to_string(),
+                    ),
+                    span: self.token.span.get(),
+                });
+            }
+
+            Some(path)
+        } else {
+            None
+        };
 
         self.consume(TokenKind::Equal);
 
@@ -44,16 +133,17 @@ where
                 "Expected an initializer for this `{}` binding.",
                 keywords::LET
             )),
-            span: self.token.span,
+            span: self.token.span.get(),
         })?;
 
         let span = name.span;
 
         Ok(Local {
-            kind: LocalKind::Init(Box::new(init)),
+            kind: LocalKind::Init(self.arena.alloc_expr(init)),
             name,
             span,
             ty: None,
+            destructure,
         })
     }
 }
@@ -5,18 +5,18 @@ use crate::lexer::token::{Token, TokenKind};
 use crate::lexer::LexError;
 use crate::parser::{ParseError, ParseErrorKind, ParseResult, Parser};
 
-impl<TokenStream> Parser<TokenStream>
+impl<'a, TokenStream> Parser<'a, TokenStream>
 where
     TokenStream: Iterator<Item = Result<Token, LexError>>,
 {
     /// Parses a [`Ty`].
     #[tracing::instrument(skip(self))]
-    pub fn parse_ty(&mut self) -> ParseResult<Ty> {
+    pub fn parse_ty(&mut self) -> ParseResult<Ty<'a>> {
         if let Some(ident) = self.token.ident() {
             if ident.name == "Fn" {
                 let fn_ty = self.parse_fn_ty()?;
 
-                let span = ident.span.to(self.prev_token.span);
+                let span = ident.span.to(self.prev_token.span.get());
 
                 return Ok(Ty {
                     kind: TyKind::Fn(Box::new(fn_ty)),
@@ -25,6 +25,10 @@ where
             }
         }
 
+        if self.check_without_expect(TokenKind::OpenBracket) {
+            return self.parse_array_ty();
+        }
+
         if self.check_path() {
             let path = self.parse_path()?;
 
@@ -38,12 +42,39 @@ where
 
         Err(ParseError {
             kind: ParseErrorKind::Error("Expected a type.".to_string()),
-            span: self.token.span,
+            span: self.token.span.get(),
+        })
+    }
+
+    /// Parses an array type (`[T; N]`).
+    #[tracing::instrument(skip(self))]
+    fn parse_array_ty(&mut self) -> ParseResult<Ty<'a>> {
+        let start = self.token.span;
+
+        self.consume(TokenKind::OpenBracket);
+
+        let elem = self.parse_ty()?;
+
+        self.consume(TokenKind::Semicolon);
+
+        let len = self.parse_expr()?.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::Error("Expected an array length.".to_string()),
+            span: self.token.span.get(),
+        })?;
+
+        self.consume(TokenKind::CloseBracket);
+
+        Ok(Ty {
+            kind: TyKind::Array {
+                elem: Box::new(elem),
+                len: self.arena.alloc_expr(len),
+            },
+            span: start.to(self.prev_token.span).into(),
         })
     }
 
     #[tracing::instrument(skip(self))]
-    fn parse_fn_ty(&mut self) -> ParseResult<FnTy> {
+    fn parse_fn_ty(&mut self) -> ParseResult<FnTy<'a>> {
         self.parse_ident()?;
 
         self.consume(TokenKind::OpenParen);
@@ -0,0 +1,410 @@
+//! An interactive REPL, started with `crane repl`.
+//!
+//! Each input is either items, like functions and `use` declarations, which
+//! are added to the session, or statements, which are run. The statements are
+//! wrapped in a function of their own, and compiled into a program along with
+//! the items defined so far, whose `main` calls the function and prints the
+//! value it returns.
+//!
+//! Since every input is its own program, `let` bindings only last for the
+//! input they are in.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::ast::{AstArena, TyExprKind, TyItemKind, TyPackage, TyStmtKind};
+use crate::compiler::{CompileOptions, CompileParams, Compiler, Input, OutputKind};
+use crate::error::CraneError;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::typer::{ty_to_string, TyKind, TypeId};
+
+/// The name that the source code of the session is shown under in
+/// diagnostics.
+pub const REPL_FILENAME: &str = "<repl>";
+
+/// The prompt for a new input.
+const PROMPT: &str = "> ";
+
+/// The prompt for the next line of an input whose braces aren't balanced yet.
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// A REPL session.
+pub struct Repl {
+    /// The options to compile each input with.
+    options: CompileOptions,
+
+    /// The source of the items defined so far.
+    session: String,
+
+    /// The number of statement inputs so far, which numbers the function each
+    /// one is wrapped in.
+    count: usize,
+}
+
+impl Repl {
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            options,
+            session: String::new(),
+            count: 0,
+        }
+    }
+
+    /// Reads inputs from `input` until EOF, evaluating each of them.
+    ///
+    /// The prompts and the values of the inputs are written to `out`, and
+    /// diagnostics to `err`. An input that fails to compile doesn't end the
+    /// session, only failing to read `input` or write `out` or `err` does.
+    pub fn run(
+        &mut self,
+        input: &mut impl BufRead,
+        out: &mut impl Write,
+        err: &mut impl Write,
+    ) -> io::Result<()> {
+        while let Some(source) = read_input(input, out)? {
+            if !source.trim().is_empty() {
+                self.eval(&source, out, err)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates a single input.
+    ///
+    /// An input that fails is reported to `err`, so only the errors of `out`
+    /// and `err` themselves are returned.
+    fn eval(&mut self, source: &str, out: &mut impl Write, err: &mut impl Write) -> io::Result<()> {
+        let arena = AstArena::new();
+
+        let items = Parser::new(Lexer::new(source), &arena).parse();
+
+        match items {
+            Ok(items) if !items.is_empty() => self.define(source, err),
+            _ => self.execute(source, out, err),
+        }
+    }
+
+    /// Adds the items in `source` to the session, if they type check along
+    /// with the items defined so far.
+    fn define(&mut self, source: &str, err: &mut impl Write) -> io::Result<()> {
+        let session = format!("{}{source}\n", self.session);
+
+        if self.type_check(&session, err, &AstArena::new())?.is_some() {
+            self.session = session;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the statements in `source`, printing the value of the last one.
+    fn execute(
+        &mut self,
+        source: &str,
+        out: &mut impl Write,
+        err: &mut impl Write,
+    ) -> io::Result<()> {
+        self.count += 1;
+
+        let name = format!("repl_input_{}", self.count);
+
+        // The statements are first checked in a loop that never runs, whose
+        // body may have a value of any type, to find out which type the
+        // function has to return.
+        let probe = format!(
+            "{}fn {name}() {{\n    while 0 == 1 {{\n{source}\n    }}\n}}\n",
+            self.session
+        );
+
+        let arena = AstArena::new();
+        let Some(package) = self.type_check(&probe, err, &arena)? else {
+            return Ok(());
+        };
+
+        let ty = value_ty(&package, &name).expect("the probe function is type checked");
+
+        let (return_ty, print) = match &*ty {
+            TyKind::Unit | TyKind::Never => (String::new(), format!("{name}()")),
+            TyKind::Bool => (
+                " -> Bool".to_string(),
+                format!(
+                    "if {name}() {{\n        std::io::println(\"true\")\n    }} else {{\n        std::io::println(\"false\")\n    }}"
+                ),
+            ),
+            TyKind::Int(_) | TyKind::Uint(_) => (
+                format!(" -> {}", ty_to_string(ty)),
+                format!("std::io::println(std::int::int_to_string({name}() as Uint64))"),
+            ),
+            TyKind::Float(_) => (
+                " -> Float64".to_string(),
+                format!("std::io::println(std::float::float_to_string({name}()))"),
+            ),
+            TyKind::UserDefined { module, name: ty_name }
+                if module == "std::prelude" && ty_name == "String" =>
+            {
+                (" -> String".to_string(), format!("std::io::println({name}())"))
+            }
+            _ => {
+                let unprintable = CraneError::Unprintable(ty_to_string(ty));
+                writeln!(err, "Error: {unprintable}")?;
+
+                return Ok(());
+            }
+        };
+
+        let program = format!(
+            "{}fn {name}(){return_ty} {{\n{source}\n}}\n\nfn main() {{\n    {print}\n}}\n",
+            self.session
+        );
+
+        let output_dir = std::env::temp_dir().join(format!("crane-repl-{}", std::process::id()));
+
+        let result = self.compile_and_run(&program, output_dir.clone(), out, err);
+
+        let _ = std::fs::remove_dir_all(output_dir);
+
+        result
+    }
+
+    /// Type checks the given source as a library, so it doesn't need a `main`
+    /// function, allocating its AST in `arena`.
+    ///
+    /// Only the diagnostics of source that fails to type check are written,
+    /// so that the warnings for the items in the session aren't repeated for
+    /// every input. Returns `None` then.
+    fn type_check<'a>(
+        &self,
+        source: &str,
+        err: &mut impl Write,
+        arena: &'a AstArena<'a>,
+    ) -> io::Result<Option<TyPackage<'a>>> {
+        let mut diagnostics = Vec::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: REPL_FILENAME.to_string(),
+                input: source.to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::StaticLib,
+                ..self.options.clone()
+            },
+        };
+
+        match Compiler::new().type_check_input(&mut diagnostics, params, arena) {
+            Ok(package) => Ok(Some(package)),
+            Err(_) => {
+                err.write_all(&diagnostics)?;
+
+                Ok(None)
+            }
+        }
+    }
+
+    fn compile_and_run(
+        &self,
+        program: &str,
+        output_dir: PathBuf,
+        out: &mut impl Write,
+        err: &mut impl Write,
+    ) -> io::Result<()> {
+        let mut diagnostics = Vec::new();
+
+        let params = CompileParams {
+            input: Input::String {
+                filename: REPL_FILENAME.to_string(),
+                input: program.to_string(),
+            },
+            options: CompileOptions {
+                output_kind: OutputKind::Executable,
+                output_dir,
+                output_name: Some("repl".to_string()),
+                emit_llvm_ir: false,
+                print_layout: false,
+                incremental: false,
+                ..self.options.clone()
+            },
+        };
+
+        let Ok(mut outputs) = Compiler::new().compile(&mut diagnostics, params) else {
+            return err.write_all(&diagnostics);
+        };
+
+        // The linked executable is the last output.
+        let executable = outputs.pop().expect("the executable is linked");
+
+        let output = match std::process::Command::new(&executable).output() {
+            Ok(output) => output,
+            Err(source) => {
+                let run_err = CraneError::Run {
+                    path: executable,
+                    source,
+                };
+
+                return writeln!(err, "Error: {run_err}");
+            }
+        };
+
+        out.write_all(&output.stdout)?;
+        err.write_all(&output.stderr)?;
+
+        if !output.status.success() {
+            writeln!(err, "Error: {}", CraneError::Exited(output.status))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the type of the value of the statements wrapped in the function
+/// with the given name, which is the type of the last one.
+fn value_ty(package: &TyPackage, name: &str) -> Option<TypeId> {
+    let fun = package
+        .modules
+        .iter()
+        .flat_map(|module| &module.items)
+        .find_map(|item| match &item.kind {
+            TyItemKind::Fn(fun) if item.name.name == name => Some(fun),
+            _ => None,
+        })?;
+
+    let TyStmtKind::Expr(while_expr) = &fun.body.first()?.kind else {
+        return None;
+    };
+
+    let TyExprKind::While { body, .. } = &while_expr.kind else {
+        return None;
+    };
+
+    Some(match body.last().map(|stmt| &stmt.kind) {
+        Some(TyStmtKind::Expr(expr)) => expr.ty,
+        _ => TypeId::intern(TyKind::Unit),
+    })
+}
+
+/// Reads the next input, which continues onto the following lines until its
+/// braces are balanced.
+///
+/// Returns `None` at EOF.
+fn read_input(input: &mut impl BufRead, out: &mut impl Write) -> io::Result<Option<String>> {
+    let mut source = String::new();
+
+    write!(out, "{PROMPT}")?;
+    out.flush()?;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok((!source.is_empty()).then_some(source));
+        }
+
+        source.push_str(&line);
+
+        if brace_depth(&source) <= 0 {
+            return Ok(Some(source));
+        }
+
+        write!(out, "{CONTINUATION_PROMPT}")?;
+        out.flush()?;
+    }
+}
+
+/// Returns the number of braces opened in the source that haven't been
+/// closed, ignoring those in string literals and comments.
+fn brace_depth(source: &str) -> isize {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut chars = source.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&char| char == '\n');
+            }
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a session with the given input, returning what it wrote to `out`
+    /// and `err`.
+    fn transcript(input: &str) -> (String, String) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+
+        Repl::new(CompileOptions::default())
+            .run(&mut input.as_bytes(), &mut out, &mut err)
+            .unwrap();
+
+        (
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_brace_depth() {
+        assert_eq!(brace_depth("fn main() {\n"), 1);
+        assert_eq!(brace_depth("fn main() {}\n"), 0);
+        assert_eq!(brace_depth("println(\"{\") // {\n"), 0);
+    }
+
+    #[test]
+    fn test_value_ty() {
+        let repl = Repl::new(CompileOptions::default());
+        let arena = AstArena::new();
+
+        let package = repl
+            .type_check(
+                "fn repl_input_1() {\n    while 0 == 1 {\nlet a = 1\na + 2\n    }\n}\n",
+                &mut io::sink(),
+                &arena,
+            )
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            ty_to_string(value_ty(&package, "repl_input_1").unwrap()),
+            "Uint64"
+        );
+    }
+
+    #[test]
+    fn test_values_are_printed() {
+        let (out, err) = transcript("1 + 2\n");
+
+        assert_eq!(out, "> 3\n> ");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn test_items_are_kept_after_an_error() {
+        let (out, err) = transcript(
+            "fn add(a: Uint64, b: Uint64) -> Uint64 {\n    a + b\n}\nfn broken() -> Uint64 {\n    missing()\n}\nfn double(a: Uint64) -> Uint64 {\n    add(a, a)\n}\n",
+        );
+
+        assert_eq!(out, "> ... ... > ... ... > ... ... > ");
+        assert!(err.contains("Function `missing` does not exist."));
+        assert_eq!(err.matches("Error").count(), 1);
+    }
+
+    #[test]
+    fn test_statement_errors() {
+        let (out, err) =
+            transcript("fn add(a: Uint64, b: Uint64) -> Uint64 {\n    a + b\n}\nadd(1)\n");
+
+        assert_eq!(out, "> ... ... > > ");
+        assert!(err.contains("<repl>"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Creating packages, for `crane new` and `crane init`.
+//!
+//! A new package has a `Crane.toml` naming it, a `.gitignore` for its build
+//! directory, and either a `src/main.crane` that prints a greeting or, for a
+//! library, a `src/lib.crane` with a public function.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::lexer::token::TokenKind;
+use crate::lexer::Lexer;
+use crate::manifest::{DEFAULT_ENTRY, MANIFEST};
+
+/// The entry point of a new library package.
+pub const LIB_ENTRY: &str = "src/lib.crane";
+
+const MAIN_TEMPLATE: &str = r#"use std::io::println
+
+fn main() {
+    println("Hello, world!")
+}
+"#;
+
+const LIB_TEMPLATE: &str = r#"/// Returns a greeting for the given name.
+pub fn greet(name: String) -> String {
+    std::string::concat("Hello, ", name)
+}
+"#;
+
+const GITIGNORE_TEMPLATE: &str = "/build\n";
+
+#[derive(Error, Debug)]
+pub enum ScaffoldError {
+    /// The name of the package isn't a Crane identifier.
+    #[error("`{name}` is not a valid package name. Package names must be identifiers: a letter or `_`, followed by letters, digits or `_`.")]
+    InvalidName { name: String },
+
+    /// The package would overwrite an existing file or directory.
+    #[error("`{}` already exists.", .path.display())]
+    AlreadyExists { path: PathBuf },
+
+    /// A file or directory of the package could not be created.
+    #[error("Failed to create `{}`: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// The kind of package to create.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PackageKind {
+    /// An executable, with a `main` function.
+    Bin,
+
+    /// A static library.
+    Lib,
+}
+
+/// Checks that the given name is a valid package name, which is any
+/// identifier the lexer accepts.
+pub fn validate_name(name: &str) -> Result<(), ScaffoldError> {
+    let mut tokens = Lexer::new(name);
+
+    match (tokens.next(), tokens.next()) {
+        (Some(Ok(token)), None) if token.kind == TokenKind::Ident && token.lexeme == name => Ok(()),
+        _ => Err(ScaffoldError::InvalidName {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Returns the files of a new package with the given name, as paths relative
+/// to its root along with their contents.
+pub fn package_files(name: &str, kind: PackageKind) -> Vec<(&'static str, String)> {
+    let mut manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n");
+
+    let (entry, source) = match kind {
+        PackageKind::Bin => (DEFAULT_ENTRY, MAIN_TEMPLATE),
+        PackageKind::Lib => {
+            manifest.push_str(&format!(
+                "\n[build]\nentry = \"{LIB_ENTRY}\"\noutput = \"lib\"\n"
+            ));
+
+            (LIB_ENTRY, LIB_TEMPLATE)
+        }
+    };
+
+    vec![
+        (MANIFEST, manifest),
+        (".gitignore", GITIGNORE_TEMPLATE.to_string()),
+        (entry, source.to_string()),
+    ]
+}
+
+/// Creates a package with the given name in `dir`, which must already exist.
+///
+/// Nothing is written if any of the package's files already exist.
+pub fn init_package(dir: &Path, name: &str, kind: PackageKind) -> Result<(), ScaffoldError> {
+    validate_name(name)?;
+
+    let files = package_files(name, kind);
+
+    if let Some((path, _)) = files.iter().find(|(path, _)| dir.join(path).exists()) {
+        return Err(ScaffoldError::AlreadyExists {
+            path: dir.join(path),
+        });
+    }
+
+    for (path, contents) in files {
+        let path = dir.join(path);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| ScaffoldError::Io {
+                path: parent.to_path_buf(),
+                source,
+            })?;
+        }
+
+        std::fs::write(&path, contents).map_err(|source| ScaffoldError::Io { path, source })?;
+    }
+
+    Ok(())
+}
+
+/// Creates a package with the given name in a new directory at `dir`.
+pub fn new_package(dir: &Path, name: &str, kind: PackageKind) -> Result<(), ScaffoldError> {
+    validate_name(name)?;
+
+    if dir.exists() {
+        return Err(ScaffoldError::AlreadyExists {
+            path: dir.to_path_buf(),
+        });
+    }
+
+    std::fs::create_dir_all(dir).map_err(|source| ScaffoldError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+
+    init_package(dir, name, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manifest::{CraneManifest, OutputType};
+
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        for name in ["hello", "hello_world", "_private", "v2"] {
+            assert!(validate_name(name).is_ok(), "{name}");
+        }
+
+        for name in [
+            "",
+            "2fast",
+            "hello-world",
+            "hello world",
+            " hello",
+            "hello.crane",
+        ] {
+            assert!(
+                matches!(validate_name(name), Err(ScaffoldError::InvalidName { .. })),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_package_manifests() {
+        let manifest = |kind| {
+            let files = package_files("hello", kind);
+            let (_, contents) = files.iter().find(|(path, _)| *path == MANIFEST).unwrap();
+
+            CraneManifest::parse(contents).unwrap()
+        };
+
+        let bin = manifest(PackageKind::Bin);
+
+        assert_eq!(bin.package.name, "hello");
+        assert_eq!(bin.entry(), Path::new(DEFAULT_ENTRY));
+        assert_eq!(bin.build.output, None);
+
+        let lib = manifest(PackageKind::Lib);
+
+        assert_eq!(lib.entry(), Path::new(LIB_ENTRY));
+        assert_eq!(lib.build.output, Some(OutputType::Lib));
+    }
+}
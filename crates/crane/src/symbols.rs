@@ -0,0 +1,281 @@
+//! Listing the items that a package defines, for `crane symbols`.
+//!
+//! Each item is listed with its kind, path, visibility, and the location of
+//! its name, along with the parameters and return type of functions. Items in
+//! modules and `impl` blocks are listed after the module or type they're in,
+//! with the module or type as the start of their path.
+//!
+//! The let-bindings in the body of each function can be listed too, which is
+//! everything an outline needs without a language server.
+
+use serde::Serialize;
+use smol_str::SmolStr;
+
+use crate::ast::visitor::{self, Visitor};
+use crate::ast::{
+    FnDecl, FnReturnTy, Item, ItemKind, Local, ModuleDecl, SourceMap, Span, Visibility,
+};
+use crate::formatter::format_ty;
+
+/// The kind of a [`SymbolInfo`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Fn,
+    Struct,
+    Union,
+    Const,
+    Mod,
+}
+
+/// An item defined by a package.
+#[derive(Debug, Serialize)]
+pub struct SymbolInfo {
+    pub kind: SymbolKind,
+    pub name: SmolStr,
+
+    /// The path of the item from the root module, ending with its name.
+    pub path: Vec<SmolStr>,
+    pub vis: Visibility,
+
+    /// Where the name of the item is written.
+    pub location: Location,
+
+    /// The parameters of a function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Vec<ParamInfo>>,
+
+    /// The return type of a function, or `()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_ty: Option<String>,
+
+    /// The let-bindings in the body of a function, in order, if they were
+    /// asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locals: Option<Vec<LocalInfo>>,
+}
+
+/// A parameter of a function.
+#[derive(Debug, Serialize)]
+pub struct ParamInfo {
+    pub name: SmolStr,
+    pub ty: String,
+}
+
+/// A let-binding in the body of a function.
+#[derive(Debug, Serialize)]
+pub struct LocalInfo {
+    pub name: SmolStr,
+    pub location: Location,
+}
+
+/// A span in a source file, with lines and columns starting at `1`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub start: LineCol,
+    pub end: LineCol,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Returns the items defined by a package, whose files are in the given
+/// [`SourceMap`].
+///
+/// The let-bindings in each function are listed as well if `include_locals`
+/// is set.
+pub fn package_symbols(
+    items: &[Item],
+    source_map: &SourceMap,
+    include_locals: bool,
+) -> Vec<SymbolInfo> {
+    let mut symbols = Vec::new();
+
+    collect_symbols(
+        &mut Vec::new(),
+        items,
+        source_map,
+        include_locals,
+        &mut symbols,
+    );
+
+    symbols
+}
+
+fn collect_symbols(
+    module: &mut Vec<SmolStr>,
+    items: &[Item],
+    source_map: &SourceMap,
+    include_locals: bool,
+    symbols: &mut Vec<SymbolInfo>,
+) {
+    for item in items {
+        let mut path = module.clone();
+        path.push(item.name.name.into());
+
+        let Some(location) = location(source_map, &path, item.name.span) else {
+            continue;
+        };
+
+        let mut symbol = SymbolInfo {
+            kind: SymbolKind::Fn,
+            name: item.name.name.into(),
+            path,
+            vis: item.vis,
+            location,
+            params: None,
+            return_ty: None,
+            locals: None,
+        };
+
+        match &item.kind {
+            ItemKind::Fn(fun) => {
+                fn_signature(&mut symbol, &fun.decl);
+
+                if include_locals {
+                    let mut collector = LocalCollector {
+                        source_map,
+                        path: &symbol.path,
+                        locals: Vec::new(),
+                    };
+
+                    for stmt in &fun.body {
+                        collector.visit_stmt(stmt);
+                    }
+
+                    symbol.locals = Some(collector.locals);
+                }
+            }
+            ItemKind::ExternFn(fn_decl) => fn_signature(&mut symbol, fn_decl),
+            ItemKind::Struct(_) => symbol.kind = SymbolKind::Struct,
+            ItemKind::Union(_) => symbol.kind = SymbolKind::Union,
+            ItemKind::Const(_) => symbol.kind = SymbolKind::Const,
+            ItemKind::Module(module_decl) => {
+                symbol.kind = SymbolKind::Mod;
+                symbols.push(symbol);
+
+                if let ModuleDecl::Loaded(loaded, _) = &**module_decl {
+                    module.push(item.name.name.into());
+                    collect_symbols(module, &loaded.items, source_map, include_locals, symbols);
+                    module.pop();
+                }
+
+                continue;
+            }
+            // The functions of an `impl` block are listed under the type
+            // they're for, which is listed already.
+            ItemKind::Impl(impl_decl) => {
+                module.push(item.name.name.into());
+                collect_symbols(
+                    module,
+                    &impl_decl.items,
+                    source_map,
+                    include_locals,
+                    symbols,
+                );
+                module.pop();
+
+                continue;
+            }
+            ItemKind::Use(_) => continue,
+        }
+
+        symbols.push(symbol);
+    }
+}
+
+fn fn_signature(symbol: &mut SymbolInfo, fn_decl: &FnDecl) {
+    symbol.params = Some(
+        fn_decl
+            .params
+            .iter()
+            .map(|param| ParamInfo {
+                name: param.name.name.into(),
+                ty: format_ty(&param.ty),
+            })
+            .collect(),
+    );
+    symbol.return_ty = Some(match &fn_decl.return_ty {
+        FnReturnTy::Unit => "()".to_string(),
+        FnReturnTy::Ty(ty) => format_ty(ty),
+    });
+}
+
+/// Returns the location of the given span in the item at the given path.
+fn location(source_map: &SourceMap, item_path: &[SmolStr], span: Span) -> Option<Location> {
+    let file = source_map.file_of(item_path)?;
+    let line_col = |offset| {
+        let (line, column) = file.line_col(offset);
+
+        LineCol { line, column }
+    };
+
+    Some(Location {
+        file: file.path.clone(),
+        start: line_col(span.start),
+        end: line_col(span.end),
+    })
+}
+
+/// Collects the let-bindings in the body of a function, leaving out those in
+/// the functions nested inside it.
+struct LocalCollector<'s> {
+    source_map: &'s SourceMap,
+
+    /// The path of the function.
+    path: &'s [SmolStr],
+    locals: Vec<LocalInfo>,
+}
+
+impl Visitor for LocalCollector<'_> {
+    fn visit_item(&mut self, _item: &Item) {}
+
+    fn visit_local(&mut self, local: &Local) {
+        if let Some(location) = location(self.source_map, self.path, local.name.span) {
+            self.locals.push(LocalInfo {
+                name: local.name.name.into(),
+                location,
+            });
+        }
+
+        visitor::walk_local(self, local);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AstArena, SourceFile};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_locals_of_nested_fns_are_their_own() {
+        let source = "fn outer(x: Uint64) {\n    let a = x\n    fn inner() {\n        let b = 1\n    }\n    if true {\n        let c = a\n    }\n}\n";
+        let arena = AstArena::new();
+        let items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        let mut source_map = SourceMap::default();
+        source_map.add_file(
+            Vec::new(),
+            SourceFile::new("main.crane".into(), source.into()),
+        );
+
+        let symbols = package_symbols(&items, &source_map, true);
+        let locals = symbols[0].locals.as_ref().unwrap();
+
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(
+            locals
+                .iter()
+                .map(|local| local.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+        assert_eq!(locals[0].location.start, LineCol { line: 2, column: 9 });
+    }
+}
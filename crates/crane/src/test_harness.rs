@@ -0,0 +1,114 @@
+//! The test harness built by `crane test`.
+//!
+//! A test is a function marked with `#[test]`, which takes no parameters and
+//! returns `()`. When compiling with [`CompileOptions::test`], the `main`
+//! function of the package is replaced with one that runs the test named by
+//! the program's first argument, so that the runner can run each test in a
+//! process of its own. A failed assertion aborts the process, which fails
+//! the test without stopping the others.
+//!
+//! [`CompileOptions::test`]: crate::compiler::CompileOptions::test
+
+use thin_vec::ThinVec;
+
+use crate::ast::{AstArena, Item, ItemKind, ModuleDecl};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Returns the paths of the `#[test]` functions in the given items, and the
+/// modules nested in them, in the order they are declared.
+pub fn test_fns(items: &[Item]) -> Vec<String> {
+    let mut tests = Vec::new();
+
+    collect_test_fns(items, "", &mut tests);
+
+    tests
+}
+
+fn collect_test_fns(items: &[Item], module: &str, tests: &mut Vec<String>) {
+    for item in items {
+        let path = format!("{module}{}", item.name);
+
+        match &item.kind {
+            ItemKind::Fn(_) if item.attr("test").is_some() => tests.push(path),
+            ItemKind::Module(module_decl) => {
+                if let ModuleDecl::Loaded(module, _) = &**module_decl {
+                    collect_test_fns(&module.items, &format!("{path}::"), tests);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the `main` function in the given items with one that runs the
+/// test named by the first argument, which is parsed into the given arena.
+pub fn add_test_harness<'a>(items: &mut ThinVec<Item<'a>>, arena: &'a AstArena<'a>) {
+    let tests = test_fns(items);
+
+    items.retain(|item| !(matches!(item.kind, ItemKind::Fn(_)) && item.name.name == "main"));
+
+    let main = if tests.is_empty() {
+        "fn main() {}\n".to_string()
+    } else {
+        let mut main = "fn main() {\n    let name = std::env::arg(1)\n".to_string();
+
+        for test in &tests {
+            main.push_str(&format!(
+                "\n    if std::string::string_eq(name, \"{test}\") {{\n        {test}()\n    }}\n"
+            ));
+        }
+
+        main.push_str("}\n");
+        main
+    };
+
+    let harness = Parser::new(Lexer::new(&main), arena)
+        .parse()
+        .expect("the test harness is valid");
+
+    items.extend(harness);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_test_harness() {
+        let source = r#"
+            fn main() {}
+
+            #[test]
+            fn adds() {}
+
+            mod math {
+                #[test]
+                fn multiplies() {}
+
+                fn helper() {}
+            }
+        "#;
+
+        let arena = AstArena::new();
+        let mut items = Parser::new(Lexer::new(source), &arena).parse().unwrap();
+
+        assert_eq!(test_fns(&items), ["adds", "math::multiplies"]);
+
+        add_test_harness(&mut items, &arena);
+
+        let names = items
+            .iter()
+            .map(|item| item.name.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["adds", "math", "main"]);
+
+        let ItemKind::Fn(main) = &items[2].kind else {
+            panic!("Expected `main` to be a function.");
+        };
+
+        // The `let` binding of the name, then one `if` per test.
+        assert_eq!(main.body.len(), 3);
+    }
+}
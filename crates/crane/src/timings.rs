@@ -0,0 +1,182 @@
+//! Measuring how long each phase of the compiler takes, for `--timings`.
+//!
+//! Each phase runs inside a span with the [`TARGET`] target, named after the
+//! phase, which the [`TimingsLayer`] times:
+//!
+//! ```ignore
+//! let _span = tracing::info_span!(target: TARGET, "parse").entered();
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The target of the spans that the phases of the compiler run in.
+pub const TARGET: &str = "crane::timings";
+
+/// The phases of the compiler, in the order they run.
+pub const PHASES: [&str; 7] = [
+    "lex",
+    "parse",
+    "type_check",
+    "codegen",
+    "optimize",
+    "emit",
+    "link",
+];
+
+/// The time spent in each phase of the compiler.
+///
+/// A phase that runs more than once, like parsing each module or generating
+/// code for each compilation unit, is timed as the sum of its runs, even when
+/// they run in parallel.
+#[derive(Debug, Clone, Default)]
+pub struct Timings(Arc<Mutex<[Option<Duration>; PHASES.len()]>>);
+
+#[derive(Serialize)]
+struct PhaseTiming {
+    phase: &'static str,
+    seconds: f64,
+}
+
+impl Timings {
+    fn add(&self, phase: usize, elapsed: Duration) {
+        let mut durations = self.0.lock().unwrap();
+
+        *durations[phase].get_or_insert(Duration::ZERO) += elapsed;
+    }
+
+    /// Returns the phases that ran, along with the time spent in each.
+    pub fn phases(&self) -> Vec<(&'static str, Duration)> {
+        let durations = self.0.lock().unwrap();
+
+        PHASES
+            .iter()
+            .zip(durations.iter())
+            .filter_map(|(&phase, duration)| duration.map(|duration| (phase, duration)))
+            .collect()
+    }
+
+    /// Returns a table of the time spent in each phase that ran, with one
+    /// phase per line.
+    pub fn summary(&self) -> String {
+        self.phases()
+            .into_iter()
+            .map(|(phase, duration)| format!("{phase:<12}{duration:>12.2?}\n"))
+            .collect()
+    }
+
+    /// Returns the time spent in each phase that ran as a JSON array of
+    /// `{ "phase": "parse", "seconds": 0.0012 }` objects.
+    pub fn to_json(&self) -> String {
+        let phases = self
+            .phases()
+            .into_iter()
+            .map(|(phase, duration)| PhaseTiming {
+                phase,
+                seconds: duration.as_secs_f64(),
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::to_string(&phases).unwrap()
+    }
+}
+
+/// A [`Layer`] that times the spans of the phases of the compiler into
+/// [`Timings`].
+pub struct TimingsLayer {
+    timings: Timings,
+}
+
+impl TimingsLayer {
+    pub fn new(timings: Timings) -> Self {
+        Self { timings }
+    }
+}
+
+/// When the span of a phase was entered, stored in its extensions.
+struct PhaseStart {
+    phase: usize,
+    start: Instant,
+}
+
+impl<S> Layer<S> for TimingsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let metadata = attrs.metadata();
+
+        if metadata.target() != TARGET {
+            return;
+        }
+
+        let Some(phase) = PHASES.iter().position(|&phase| phase == metadata.name()) else {
+            return;
+        };
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(PhaseStart {
+                phase,
+                start: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+
+        let phase_start = span.extensions_mut().remove::<PhaseStart>();
+
+        if let Some(PhaseStart { phase, start }) = phase_start {
+            self.timings.add(phase, start.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_timings_layer() {
+        let timings = Timings::default();
+        let subscriber = tracing_subscriber::registry().with(TimingsLayer::new(timings.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..2 {
+                let _span = tracing::info_span!(target: TARGET, "parse").entered();
+            }
+
+            let _span = tracing::info_span!(target: TARGET, "lex").entered();
+
+            // Spans from elsewhere aren't phases, even if they share a name.
+            let _span = tracing::info_span!("link").entered();
+        });
+
+        let phases = timings
+            .phases()
+            .into_iter()
+            .map(|(phase, _)| phase)
+            .collect::<Vec<_>>();
+
+        assert_eq!(phases, ["lex", "parse"]);
+        assert_eq!(timings.summary().lines().count(), 2);
+
+        let json = serde_json::from_str::<serde_json::Value>(&timings.to_json()).unwrap();
+
+        assert_eq!(json[0]["phase"], "lex");
+        assert_eq!(json[1]["phase"], "parse");
+        assert!(json[1]["seconds"].is_f64());
+    }
+}
@@ -1,94 +1,430 @@
+mod comptime;
 mod error;
 mod ty;
+mod warning;
 
 pub use error::*;
 pub use ty::*;
+pub use warning::*;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
 
 use heck::{ToPascalCase, ToSnakeCase};
 use smol_str::SmolStr;
 use thin_vec::{thin_vec, ThinVec};
 
 use crate::ast::{
-    self, Expr, ExprKind, Fn, FnDecl, FnParam, FnReturnTy, Ident, InlineModuleDecl, Item, ItemKind,
-    Literal, LiteralKind, Local, LocalKind, Module, ModuleDecl, Package, PathSegment, Span, Stmt,
-    StmtKind, StructDecl, TyExpr, TyExprKind, TyFieldDecl, TyFn, TyFnParam, TyIntegerLiteral,
-    TyItem, TyItemKind, TyLiteral, TyLiteralKind, TyLocal, TyLocalKind, TyModule, TyPackage,
-    TyPath, TyPathSegment, TyStmt, TyStmtKind, TyStructDecl, TyUint, TyUnionDecl, TyVariant,
-    TyVariantData, UnionDecl, UseTree, UseTreeKind, VariantData, DUMMY_SPAN,
+    self, keywords, AstArena, Attribute, BinaryOp, ConstDecl, Expr, ExprKind, FieldDecl, Fn,
+    FnDecl, FnParam, FnReturnTy, ForExpr, Ident, InlineModuleDecl, Item, ItemKind, Literal,
+    LiteralKind, Local, LocalKind, Module, ModuleDecl, Package, PatKind, Path, PathSegment,
+    SourceMap, Span, Stmt, StmtKind, StructDecl, Symbol, TyConst, TyExpr, TyExprKind, TyFieldDecl,
+    TyFn, TyFnParam, TyForExpr, TyImplDecl, TyInt, TyIntegerLiteral, TyItem, TyItemKind, TyLiteral,
+    TyLiteralKind, TyLocal, TyLocalKind, TyMatchArm, TyModule, TyPackage, TyPat, TyPatKind, TyPath,
+    TyPathSegment, TyStmt, TyStmtKind, TyStructDecl, TyUint, TyUnionDecl, TyVariant, TyVariantData,
+    UnionDecl, UseTree, UseTreeKind, VariantData, DUMMY_SPAN,
 };
+use crate::compiler::OutputKind;
+use crate::lint::{find_lint, lint_names};
+use crate::typer::comptime::{ComptimeEvaluator, ComptimeValue};
 
-fn ty_to_string(ty: Ty) -> String {
+/// Returns the name of the given type, as it is written in source code.
+pub fn ty_to_string(ty: TypeId) -> String {
     match &*ty {
         TyKind::Unit => "()".to_string(),
-        TyKind::Uint(UintTy::U64) => "Uint64".to_string(),
+        TyKind::Never => "Never".to_string(),
+        TyKind::Bool => "Bool".to_string(),
+        TyKind::Int(int_ty) => format!("Int{}", int_ty.bit_width()),
+        TyKind::Uint(uint_ty) => format!("Uint{}", uint_ty.bit_width()),
+        TyKind::Float(FloatTy::F64) => "Float64".to_string(),
         TyKind::UserDefined { module, name } => {
             format!("{}::{}", module, name)
         }
-        TyKind::Fn { args, return_ty } => format!(
+        TyKind::Fn {
+            args,
+            return_ty,
+            calling_convention: CallingConvention::C,
+        } => format!(
             "Fn({}) -> {}",
             args.into_iter()
-                .map(|ty| ty_to_string(ty.clone()))
+                .map(|ty| ty_to_string(*ty))
+                .collect::<Vec<_>>()
+                .join(", "),
+            ty_to_string(*return_ty)
+        ),
+        TyKind::Fn {
+            args,
+            return_ty,
+            calling_convention,
+        } => format!(
+            "extern \"{}\" Fn({}) -> {}",
+            calling_convention.name(),
+            args.into_iter()
+                .map(|ty| ty_to_string(*ty))
                 .collect::<Vec<_>>()
                 .join(", "),
-            ty_to_string(return_ty.clone())
+            ty_to_string(*return_ty)
         ),
+        TyKind::Array { elem, len } => format!("[{}; {}]", ty_to_string(*elem), len),
     }
 }
 
 pub type TypeCheckResult<T> = Result<T, TypeError>;
 
-#[derive(Default)]
+/// The representations accepted by the `#[repr]` attribute on a `struct`.
+pub const STRUCT_REPRS: [&str; 2] = ["C", "packed"];
+
+/// The most bits that the fields of a `#[bitfield]` struct can occupy, since
+/// they are packed into a single integer.
+pub const MAX_BITFIELD_BITS: u32 = 64;
+
+/// The representations accepted by the `#[repr]` attribute on a `union`,
+/// which select the width of its discriminant.
+pub const UNION_REPRS: [&str; 4] = ["u8", "u16", "u32", "u64"];
+
+/// A method that the implementations of a trait must define.
+pub struct TraitMethod {
+    pub name: &'static str,
+
+    /// The types of the parameters after `self`, where `Self` stands for the
+    /// implementing type and `_` for any type the implementation chooses.
+    pub params: &'static [&'static str],
+
+    /// The return type, where `Self` stands for the implementing type and `_`
+    /// for any type the implementation chooses.
+    pub return_ty: &'static str,
+}
+
+/// The built-in traits, which are implemented with `impl Trait for Type`, and
+/// the methods their implementations define.
+pub const TRAITS: [(&str, &[TraitMethod]); 10] = [
+    (
+        "Add",
+        &[TraitMethod {
+            name: "add",
+            params: &["Self"],
+            return_ty: "Self",
+        }],
+    ),
+    (
+        "Clone",
+        &[TraitMethod {
+            name: "clone",
+            params: &[],
+            return_ty: "Self",
+        }],
+    ),
+    (
+        "Debug",
+        &[TraitMethod {
+            name: "debug_repr",
+            params: &[],
+            return_ty: "String",
+        }],
+    ),
+    (
+        "Div",
+        &[TraitMethod {
+            name: "div",
+            params: &["Self"],
+            return_ty: "Self",
+        }],
+    ),
+    (
+        "Hash",
+        &[TraitMethod {
+            name: "hash",
+            params: &[],
+            return_ty: "Uint64",
+        }],
+    ),
+    (
+        "Index",
+        &[TraitMethod {
+            name: "index",
+            params: &["_"],
+            return_ty: "_",
+        }],
+    ),
+    (
+        "Mul",
+        &[TraitMethod {
+            name: "mul",
+            params: &["Self"],
+            return_ty: "Self",
+        }],
+    ),
+    (
+        "PartialEq",
+        &[TraitMethod {
+            name: "eq",
+            params: &["Self"],
+            return_ty: "Bool",
+        }],
+    ),
+    (
+        "Sub",
+        &[TraitMethod {
+            name: "sub",
+            params: &["Self"],
+            return_ty: "Self",
+        }],
+    ),
+    (
+        "ToString",
+        &[TraitMethod {
+            name: "to_string",
+            params: &[],
+            return_ty: "String",
+        }],
+    ),
+];
+
+/// The binary operators that types can overload by implementing a trait, and
+/// the methods of those traits that they become.
+const OPERATOR_METHODS: [(BinaryOp, &str); 6] = [
+    (BinaryOp::Add, "add"),
+    (BinaryOp::Sub, "sub"),
+    (BinaryOp::Mul, "mul"),
+    (BinaryOp::Div, "div"),
+    (BinaryOp::Eq, "eq"),
+    (BinaryOp::Ne, "eq"),
+];
+
+/// The functions and methods of `std`, which are registered once and shared
+/// by every [`Typer`], even those type checking packages in parallel.
+static STD_ITEMS: LazyLock<StdItems> = LazyLock::new(|| {
+    let arena = AstArena::new();
+    let mut typer = Typer::new(&arena);
+
+    typer
+        .register_std()
+        .expect("the functions of `std` are well-formed");
+
+    StdItems {
+        modules: typer.modules,
+        methods: typer.methods,
+        unsafe_fns: typer.unsafe_fns,
+    }
+});
+
+/// The parts of a [`Typer`]'s tables that `std` fills in.
+struct StdItems {
+    modules: HashMap<TyPath, ModuleItems>,
+    methods: HashMap<(SmolStr, SmolStr), TyPath>,
+    unsafe_fns: HashSet<TyPath>,
+}
+
+/// The variants of `std::sync::MemoryOrdering`.
+pub const MEMORY_ORDERINGS: [&str; 5] = ["Relaxed", "Release", "Acquire", "AcqRel", "SeqCst"];
+
+#[derive(Default, Clone)]
 struct ModuleItems {
-    pub functions: HashMap<Ident, (ThinVec<TyFnParam>, Ty)>,
+    pub functions: HashMap<Ident, (ThinVec<TyFnParam>, TypeId)>,
     pub structs: HashMap<Ident, TyStructDecl>,
     pub unions: HashMap<Ident, TyUnionDecl>,
 }
 
-pub struct Typer {
+pub struct Typer<'a> {
+    /// The arena that the typed expressions are allocated in.
+    arena: &'a AstArena<'a>,
     modules: HashMap<TyPath, ModuleItems>,
     use_map: HashMap<TyPath, TyPath>,
-    scopes: Vec<HashMap<TyPath, Ty>>,
+    /// The `std` types brought into scope by `use`, keyed by their name.
+    imported_tys: HashMap<Symbol, TypeId>,
+    scopes: Vec<HashMap<TyPath, TypeId>>,
+    /// The values of the `const`s declared in the package, folded into literals.
+    consts: HashMap<TyPath, TyExpr<'a>>,
+    /// The methods declared in `impl` blocks, keyed by the name of their type
+    /// and their own name.
+    methods: HashMap<(SmolStr, SmolStr), TyPath>,
+    /// The functions marked `#[deprecated]`, along with their deprecation messages.
+    deprecated_fns: HashMap<TyPath, Option<SmolStr>>,
+    /// The functions marked `#[must_use]`, along with their messages.
+    must_use_fns: HashMap<TyPath, Option<SmolStr>>,
+    /// The symbols of the `extern fn`s declared in modules, keyed by their
+    /// paths within the package.
+    extern_fns: HashMap<TyPath, TyPath>,
+    /// The functions with a `#[calling_convention]` other than the default.
+    calling_conventions: HashMap<TyPath, CallingConvention>,
+    /// The functions marked `#[naked]`.
+    naked_fns: HashSet<TyPath>,
+    /// The functions that can only be called from an `unsafe` context, which
+    /// includes every `extern fn`.
+    unsafe_fns: HashSet<TyPath>,
+    /// Whether the expression being checked is in an `unsafe` block or function.
+    in_unsafe: bool,
+    /// The path of the function being type checked.
+    current_fn: Option<TyPath>,
+    /// The path of the module being type checked, which is left at the module
+    /// that failed to type check.
+    current_module: Vec<SmolStr>,
+    /// The files that the modules of the package were loaded from.
+    source_map: SourceMap,
+    /// The lints allowed by the `#[allow]` attributes on the enclosing items.
+    allowed_lints: Vec<Symbol>,
+    /// The warnings found so far, along with the path of the module each was
+    /// found in.
+    warnings: Vec<(Vec<SmolStr>, Warning)>,
+    /// The parameters of the function being type checked, which cannot be assigned to.
+    fn_params: HashSet<TyPath>,
+    output_kind: OutputKind,
 
     // Types.
-    unit_ty: Ty,
-    uint64_ty: Ty,
-    string_ty: Ty,
+    unit_ty: TypeId,
+    int32_ty: TypeId,
+    uint64_ty: TypeId,
+    float64_ty: TypeId,
+    string_ty: TypeId,
+    thread_handle_ty: TypeId,
+    mutex_ty: TypeId,
+    atomic_uint64_ty: TypeId,
+    memory_ordering_ty: TypeId,
 }
 
-impl Typer {
-    pub fn new() -> Self {
-        let unit_ty = Ty::new(TyKind::Unit);
-        let uint64_ty = Ty::new(TyKind::Uint(UintTy::U64));
+impl<'a> Typer<'a> {
+    /// Returns a new [`Typer`] for type checking a package with the given [`OutputKind`].
+    pub fn with_output_kind(arena: &'a AstArena<'a>, output_kind: OutputKind) -> Self {
+        Self {
+            output_kind,
+            ..Self::new(arena)
+        }
+    }
+
+    /// Sets the files that the modules of the package were loaded from, which
+    /// are used to find the location of a failed `assert`.
+    pub fn with_source_map(self, source_map: SourceMap) -> Self {
+        Self { source_map, ..self }
+    }
+
+    /// Returns a new [`Typer`], which allocates the typed expressions in the
+    /// given arena.
+    pub fn new(arena: &'a AstArena<'a>) -> Self {
+        let unit_ty = TypeId::intern(TyKind::Unit);
+        let int32_ty = TypeId::intern(TyKind::Int(IntTy::I32));
+        let uint64_ty = TypeId::intern(TyKind::Uint(UintTy::U64));
+        let float64_ty = TypeId::intern(TyKind::Float(FloatTy::F64));
 
-        let string_ty = Ty::new(TyKind::UserDefined {
+        let string_ty = TypeId::intern(TyKind::UserDefined {
             module: SmolStr::new_inline("std::prelude"),
             name: SmolStr::new_inline("String"),
         });
 
+        let thread_handle_ty = TypeId::intern(TyKind::UserDefined {
+            module: SmolStr::new_inline("std::thread"),
+            name: SmolStr::new_inline("ThreadHandle"),
+        });
+
+        let mutex_ty = TypeId::intern(TyKind::UserDefined {
+            module: SmolStr::new_inline("std::sync"),
+            name: SmolStr::new_inline("Mutex"),
+        });
+
+        let atomic_uint64_ty = TypeId::intern(TyKind::UserDefined {
+            module: SmolStr::new_inline("std::sync"),
+            name: SmolStr::new_inline("AtomicUint64"),
+        });
+
+        let memory_ordering_ty = TypeId::intern(TyKind::UserDefined {
+            module: SmolStr::new_inline("std::sync"),
+            name: SmolStr::new_inline("MemoryOrdering"),
+        });
+
         Self {
+            arena,
             modules: HashMap::new(),
             use_map: HashMap::new(),
+            imported_tys: HashMap::new(),
             scopes: Vec::new(),
+            consts: HashMap::new(),
+            methods: HashMap::new(),
+            deprecated_fns: HashMap::new(),
+            must_use_fns: HashMap::new(),
+            extern_fns: HashMap::new(),
+            calling_conventions: HashMap::new(),
+            naked_fns: HashSet::new(),
+            unsafe_fns: HashSet::new(),
+            in_unsafe: false,
+            current_fn: None,
+            current_module: Vec::new(),
+            source_map: SourceMap::default(),
+            allowed_lints: Vec::new(),
+            warnings: Vec::new(),
+            fn_params: HashSet::new(),
+            output_kind: OutputKind::Executable,
             unit_ty,
+            int32_ty,
             uint64_ty,
+            float64_ty,
             string_ty,
+            thread_handle_ty,
+            mutex_ty,
+            atomic_uint64_ty,
+            memory_ordering_ty,
         }
     }
 
-    pub fn type_check_package(&mut self, package: Package) -> TypeCheckResult<TyPackage> {
+    /// Returns the warnings found while type checking, along with the path of
+    /// the module each was found in.
+    pub fn warnings(&self) -> &[(Vec<SmolStr>, Warning)] {
+        &self.warnings
+    }
+
+    /// Returns the path of the module that failed to type check, which is
+    /// empty for the root module.
+    pub fn error_module(&self) -> &[SmolStr] {
+        &self.current_module
+    }
+
+    /// Returns the files that the modules of the package were loaded from.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn type_check_package(&mut self, package: &Package) -> TypeCheckResult<TyPackage<'a>> {
         // HACK: Register the functions from `std`.
-        self.register_std()?;
+        self.modules.extend(STD_ITEMS.modules.clone());
+        self.methods.extend(STD_ITEMS.methods.clone());
+        self.unsafe_fns.extend(STD_ITEMS.unsafe_fns.iter().cloned());
 
-        self.perform_item_registration_pass(&package)?;
+        self.perform_item_registration_pass(package)?;
 
         let mut typed_modules = ThinVec::new();
 
-        for module in package.modules {
+        for module in &package.modules {
             typed_modules.push(self.type_check_module(None, module)?);
         }
 
+        if self.output_kind == OutputKind::Executable {
+            let main_fn = typed_modules
+                .iter()
+                .flat_map(|module| &module.items)
+                .find_map(|item| match &item.kind {
+                    TyItemKind::Fn(fun) if item.name.name == "main" => Some((fun, item.name.span)),
+                    _ => None,
+                });
+
+            let Some((main_fn, span)) = main_fn else {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(
+                        "Executables must define a `main` function.".to_string(),
+                    ),
+                    span: DUMMY_SPAN,
+                });
+            };
+
+            // The program arguments are read through `std::env`, so `main` itself
+            // has to match the signature the backend generates a C `main` for.
+            if !main_fn.params.is_empty() || main_fn.return_ty != self.unit_ty {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(
+                        "The `main` function must be declared as `fn main()`. Use `std::env::arg_count` and `std::env::arg` to read the program arguments.".to_string(),
+                    ),
+                    span,
+                });
+            }
+        }
+
         Ok(TyPackage {
             modules: typed_modules,
         })
@@ -99,9 +435,9 @@ impl Typer {
         module_path: TyPath,
         name: Ident,
         params: ThinVec<TyFnParam>,
-        return_ty: Ty,
+        return_ty: TypeId,
     ) -> TypeCheckResult<()> {
-        if name.name != name.name.to_snake_case() {
+        if name.name.as_str() != name.name.to_snake_case() {
             return Err(TypeError {
                 kind: TypeErrorKind::InvalidFunctionName {
                     reason: "Function names must be written in snake_case.".to_string(),
@@ -117,7 +453,10 @@ impl Typer {
         Ok(())
     }
 
-    fn ensure_function_exists(&self, path: &TyPath) -> TypeCheckResult<(&ThinVec<TyFnParam>, Ty)> {
+    fn ensure_function_exists(
+        &self,
+        path: &TyPath,
+    ) -> TypeCheckResult<(&ThinVec<TyFnParam>, TypeId)> {
         let (TyPathSegment { ident: name }, module_path_segments) =
             path.segments.split_last().unwrap();
 
@@ -135,7 +474,7 @@ impl Typer {
         })?;
 
         if let Some((params, return_ty)) = module.functions.get(name) {
-            return Ok((params, return_ty.clone()));
+            return Ok((params, *return_ty));
         }
 
         Err(TypeError {
@@ -162,7 +501,7 @@ impl Typer {
         name: Ident,
         struct_decl: TyStructDecl,
     ) -> TypeCheckResult<()> {
-        if name.name != name.name.to_pascal_case() {
+        if name.name.as_str() != name.name.to_pascal_case() {
             return Err(TypeError {
                 kind: TypeErrorKind::InvalidTypeName {
                     reason: "Struct names must be written in PascalCase.".to_string(),
@@ -217,13 +556,167 @@ impl Typer {
         })
     }
 
+    /// Records the attributes on the item of the function at the given path
+    /// that affect how it is checked and called, like `#[must_use]` and
+    /// `#[calling_convention]`.
+    fn register_fn_attrs(&mut self, path: TyPath, item: &Item) {
+        if item.attr("naked").is_some() {
+            self.naked_fns.insert(path.clone());
+        }
+
+        let is_unsafe = match &item.kind {
+            ItemKind::Fn(fun) => fun.is_unsafe,
+            ItemKind::ExternFn(_) => true,
+            _ => false,
+        };
+
+        if is_unsafe {
+            self.unsafe_fns.insert(path.clone());
+        }
+
+        if let Some(attr) = item.attr("deprecated") {
+            self.deprecated_fns.insert(path.clone(), attr_message(attr));
+        }
+
+        if let Some(attr) = item.attr("must_use") {
+            self.must_use_fns.insert(path.clone(), attr_message(attr));
+        }
+
+        if let Some(calling_convention) = item
+            .attr("calling_convention")
+            .and_then(|attr| attr.value.as_deref())
+            .and_then(CallingConvention::from_name)
+        {
+            self.calling_conventions.insert(path, calling_convention);
+        }
+    }
+
+    /// Returns an error if the given operation isn't in an `unsafe` context.
+    fn require_unsafe(
+        &self,
+        operation: impl FnOnce() -> String,
+        span: Span,
+    ) -> TypeCheckResult<()> {
+        if self.in_unsafe {
+            return Ok(());
+        }
+
+        Err(TypeError {
+            kind: TypeErrorKind::RequiresUnsafe {
+                operation: operation(),
+            },
+            span,
+        })
+    }
+
+    /// Returns the calling convention of the function at the given path.
+    fn calling_convention_of(&self, path: &TyPath) -> CallingConvention {
+        self.calling_conventions
+            .get(path)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Warns about a call to the function at the given path, if it is deprecated.
+    fn warn_if_deprecated(&mut self, path: &TyPath, span: Span) {
+        if let Some(message) = self.deprecated_fns.get(path) {
+            self.warn(Warning::Deprecated {
+                fn_name: path.to_string().into(),
+                message: message.clone(),
+                span,
+            });
+        }
+    }
+
+    /// Warns about the statements in a block that follow one that diverges,
+    /// starting from the first of them.
+    fn warn_unreachable_code(&mut self, stmts: &[TyStmt]) {
+        let diverging_stmt = stmts
+            .iter()
+            .position(|stmt| matches!(&stmt.kind, TyStmtKind::Expr(expr) if expr.diverges()));
+
+        if let Some(unreachable_stmt) = diverging_stmt.and_then(|index| stmts.get(index + 1)) {
+            self.warn(Warning::UnreachableCode {
+                span: unreachable_stmt.span,
+            });
+        }
+    }
+
+    /// Warns about the expression statements in a block that call a
+    /// `#[must_use]` function, except for the last one, whose value is that
+    /// of the block.
+    fn warn_unused_results(&mut self, stmts: &[TyStmt<'a>]) {
+        let Some((_, stmts)) = stmts.split_last() else {
+            return;
+        };
+
+        for stmt in stmts {
+            if let TyStmtKind::Expr(expr) = &stmt.kind {
+                self.warn_if_unused_result(expr);
+            }
+        }
+    }
+
+    /// Warns about the given expression, whose value is discarded, if it is a
+    /// call to a `#[must_use]` function.
+    fn warn_if_unused_result(&mut self, expr: &TyExpr<'a>) {
+        let TyExprKind::Call { fun, .. } = &expr.kind else {
+            return;
+        };
+
+        let TyExprKind::Variable(path) = &fun.kind else {
+            return;
+        };
+
+        if let Some(message) = self.must_use_fns.get(path) {
+            self.warn(Warning::UnusedResult {
+                fn_name: path.to_string().into(),
+                message: message.clone(),
+                span: expr.span,
+            });
+        }
+    }
+
+    /// Records the given warning, unless its lint is allowed where it occurred.
+    fn warn(&mut self, warning: Warning) {
+        let is_allowed = self
+            .allowed_lints
+            .iter()
+            .any(|lint| lint == warning.lint_name());
+
+        // Functions are type checked more than once, so the same warning can
+        // be found again.
+        if is_allowed
+            || self
+                .warnings
+                .iter()
+                .any(|(_, found_warning)| *found_warning == warning)
+        {
+            return;
+        }
+
+        self.warnings.push((self.current_module.clone(), warning));
+    }
+
+    /// Returns the declaration of the struct with the given name, in any module.
+    fn find_struct(&self, name: &SmolStr) -> Option<&TyStructDecl> {
+        let name = Ident {
+            name: name.into(),
+            span: DUMMY_SPAN,
+        };
+
+        self.modules
+            .values()
+            .find_map(|module| module.structs.get(&name))
+    }
+
     fn register_union(
         &mut self,
         module_path: TyPath,
         name: Ident,
         union_decl: TyUnionDecl,
     ) -> TypeCheckResult<()> {
-        if name.name != name.name.to_pascal_case() {
+        if name.name.as_str() != name.name.to_pascal_case() {
             return Err(TypeError {
                 kind: TypeErrorKind::InvalidTypeName {
                     reason: "Union names must be written in PascalCase.".to_string(),
@@ -276,6 +769,162 @@ impl Typer {
             span: DUMMY_SPAN,
         };
 
+        let std_process_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "process".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+
+        let std_thread_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "thread".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+
+        let std_time_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "time".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_sync_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "sync".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_ptr_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "ptr".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_float_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "float".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_env_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "env".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_string_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "string".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+        let std_hash_path = TyPath {
+            segments: thin_vec![
+                TyPathSegment {
+                    ident: Ident {
+                        name: "std".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+                TyPathSegment {
+                    ident: Ident {
+                        name: "hash".into(),
+                        span: DUMMY_SPAN,
+                    }
+                },
+            ],
+            span: DUMMY_SPAN,
+        };
+
         self.register_function(
             std_io_path.clone(),
             Ident {
@@ -287,13 +936,13 @@ impl Typer {
                     name: "value".into(),
                     span: DUMMY_SPAN
                 },
-                ty: self.string_ty.clone(),
+                ty: self.string_ty,
                 span: DUMMY_SPAN
             }],
-            self.unit_ty.clone(),
+            self.unit_ty,
         )?;
         self.register_function(
-            std_io_path,
+            std_io_path.clone(),
             Ident {
                 name: "println".into(),
                 span: DUMMY_SPAN,
@@ -303,24 +952,117 @@ impl Typer {
                     name: "value".into(),
                     span: DUMMY_SPAN
                 },
-                ty: self.string_ty.clone(),
+                ty: self.string_ty,
                 span: DUMMY_SPAN
             }],
-            self.unit_ty.clone(),
+            self.unit_ty,
         )?;
         self.register_function(
-            std_int_path.clone(),
+            std_io_path,
             Ident {
-                name: "int_add".into(),
+                name: "read_line".into(),
                 span: DUMMY_SPAN,
             },
-            thin_vec![
+            ThinVec::new(),
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_int_path.clone(),
+            Ident {
+                name: "int_add".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "a".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "b".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_int_path,
+            Ident {
+                name: "int_to_string".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_float_path,
+            Ident {
+                name: "float_to_string".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.float64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "string_eq".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "a".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.string_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "b".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.string_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            TypeId::intern(TyKind::Bool),
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "concat".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
                 TyFnParam {
                     name: Ident {
                         name: "a".into(),
                         span: DUMMY_SPAN
                     },
-                    ty: self.uint64_ty.clone(),
+                    ty: self.string_ty,
                     span: DUMMY_SPAN
                 },
                 TyFnParam {
@@ -328,657 +1070,3541 @@ impl Typer {
                         name: "b".into(),
                         span: DUMMY_SPAN
                     },
-                    ty: self.uint64_ty.clone(),
-                    span: DUMMY_SPAN
+                    ty: self.string_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "bool_to_string".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: TypeId::intern(TyKind::Bool),
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "to_string".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.string_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "debug_repr".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.string_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        self.register_function(
+            std_string_path.clone(),
+            Ident {
+                name: "clone".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.string_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        for (name, ty) in [
+            ("hash_int", self.uint64_ty),
+            ("hash_float", self.float64_ty),
+            ("hash_bool", TypeId::intern(TyKind::Bool)),
+            ("hash_string", self.string_ty),
+        ] {
+            self.register_function(
+                std_hash_path.clone(),
+                Ident {
+                    name: name.into(),
+                    span: DUMMY_SPAN,
+                },
+                thin_vec![TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty,
+                    span: DUMMY_SPAN
+                }],
+                self.uint64_ty,
+            )?;
+        }
+        self.register_function(
+            std_hash_path,
+            Ident {
+                name: "combine".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "hash".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_process_path.clone(),
+            Ident {
+                name: "exit".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "code".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            TypeId::intern(TyKind::Never),
+        )?;
+        self.register_function(
+            std_process_path.clone(),
+            Ident {
+                name: "assert".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "cond".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: TypeId::intern(TyKind::Bool),
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "message".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.string_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.unit_ty,
+        )?;
+        self.register_function(
+            std_process_path.clone(),
+            Ident {
+                name: "abort".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "message".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.string_ty,
+                span: DUMMY_SPAN
+            }],
+            TypeId::intern(TyKind::Never),
+        )?;
+        self.register_function(
+            std_process_path.clone(),
+            Ident {
+                name: "todo".into(),
+                span: DUMMY_SPAN,
+            },
+            ThinVec::new(),
+            TypeId::intern(TyKind::Never),
+        )?;
+        self.register_function(
+            std_process_path,
+            Ident {
+                name: "unimplemented".into(),
+                span: DUMMY_SPAN,
+            },
+            ThinVec::new(),
+            TypeId::intern(TyKind::Never),
+        )?;
+        self.register_function(
+            std_thread_path.clone(),
+            Ident {
+                name: "spawn".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "fun".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: TypeId::intern(TyKind::Fn {
+                    args: ThinVec::new(),
+                    return_ty: self.unit_ty,
+                    calling_convention: CallingConvention::C,
+                }),
+                span: DUMMY_SPAN
+            }],
+            self.thread_handle_ty,
+        )?;
+        self.register_function(
+            std_thread_path.clone(),
+            Ident {
+                name: "join".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "handle".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.thread_handle_ty,
+                span: DUMMY_SPAN
+            }],
+            self.unit_ty,
+        )?;
+        self.register_function(
+            std_thread_path,
+            Ident {
+                name: "sleep".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "nanos".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.unit_ty,
+        )?;
+        self.register_function(
+            std_time_path,
+            Ident {
+                name: "now".into(),
+                span: DUMMY_SPAN,
+            },
+            ThinVec::new(),
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_env_path.clone(),
+            Ident {
+                name: "arg_count".into(),
+                span: DUMMY_SPAN,
+            },
+            ThinVec::new(),
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_env_path,
+            Ident {
+                name: "arg".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "index".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.string_ty,
+        )?;
+        // A `Mutex` guards a single `Uint64`, since there are no generic types.
+        // Without pointer types, `mutex_lock` can't hand out a reference to the
+        // guarded value either, so it returns a copy of it, which `mutex_unlock`
+        // writes back before releasing the lock.
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "mutex_new".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.mutex_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "mutex_lock".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "mutex".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.mutex_ty,
+                span: DUMMY_SPAN
+            }],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "mutex_unlock".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "mutex".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.mutex_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.unit_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "atomic_new".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "value".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.atomic_uint64_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "atomic_load".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "atomic".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.atomic_uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "ordering".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.memory_ordering_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "atomic_store".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "atomic".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.atomic_uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "ordering".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.memory_ordering_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.unit_ty,
+        )?;
+        self.register_function(
+            std_sync_path.clone(),
+            Ident {
+                name: "atomic_fetch_add".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "atomic".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.atomic_uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "ordering".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.memory_ordering_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_sync_path,
+            Ident {
+                name: "atomic_compare_exchange".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "atomic".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.atomic_uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "current".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "new".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "ordering".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.memory_ordering_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.uint64_ty,
+        )?;
+        // Memory-mapped registers are addressed by their integer address,
+        // since there are no pointer types.
+        self.register_function(
+            std_ptr_path.clone(),
+            Ident {
+                name: "volatile_read".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![TyFnParam {
+                name: Ident {
+                    name: "address".into(),
+                    span: DUMMY_SPAN
+                },
+                ty: self.uint64_ty,
+                span: DUMMY_SPAN
+            }],
+            self.uint64_ty,
+        )?;
+        self.register_function(
+            std_ptr_path.clone(),
+            Ident {
+                name: "volatile_write".into(),
+                span: DUMMY_SPAN,
+            },
+            thin_vec![
+                TyFnParam {
+                    name: Ident {
+                        name: "address".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                },
+                TyFnParam {
+                    name: Ident {
+                        name: "value".into(),
+                        span: DUMMY_SPAN
+                    },
+                    ty: self.uint64_ty,
+                    span: DUMMY_SPAN
+                }
+            ],
+            self.unit_ty,
+        )?;
+
+        // The built-in types implement `ToString`, `Debug` and `Hash`, and
+        // `String` implements `Clone`, with these functions. Integers of every
+        // width share the implementations of `Uint64`.
+        for (ty_name, method, fn_path) in [
+            ("Uint64", "to_string", "std::int::int_to_string"),
+            ("Uint64", "debug_repr", "std::int::int_to_string"),
+            ("Uint64", "hash", "std::hash::hash_int"),
+            ("Float64", "to_string", "std::float::float_to_string"),
+            ("Float64", "debug_repr", "std::float::float_to_string"),
+            ("Float64", "hash", "std::hash::hash_float"),
+            ("Bool", "to_string", "std::string::bool_to_string"),
+            ("Bool", "debug_repr", "std::string::bool_to_string"),
+            ("Bool", "hash", "std::hash::hash_bool"),
+            ("String", "to_string", "std::string::to_string"),
+            ("String", "debug_repr", "std::string::debug_repr"),
+            ("String", "clone", "std::string::clone"),
+            ("String", "hash", "std::hash::hash_string"),
+        ] {
+            let segments = fn_path
+                .split("::")
+                .map(|name| TyPathSegment {
+                    ident: Ident {
+                        name: name.into(),
+                        span: DUMMY_SPAN,
+                    },
+                })
+                .collect();
+
+            self.methods.insert(
+                (ty_name.into(), method.into()),
+                TyPath {
+                    segments,
+                    span: DUMMY_SPAN,
+                },
+            );
+        }
+
+        // Accessing an arbitrary address dereferences a raw pointer.
+        for name in ["volatile_read", "volatile_write"] {
+            let mut segments = std_ptr_path.segments.clone();
+            segments.push(TyPathSegment {
+                ident: Ident {
+                    name: name.into(),
+                    span: DUMMY_SPAN,
+                },
+            });
+
+            self.unsafe_fns.insert(TyPath {
+                segments,
+                span: DUMMY_SPAN,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Runs `f` in the module at the given path, so that the warnings and the
+    /// error it finds are attributed to the file the module was loaded from.
+    fn in_module<T>(
+        &mut self,
+        path_segments: &ThinVec<TyPathSegment>,
+        f: impl FnOnce(&mut Self) -> TypeCheckResult<T>,
+    ) -> TypeCheckResult<T> {
+        let module = path_segments
+            .iter()
+            .map(|segment| segment.ident.name.into())
+            .collect();
+
+        let outer_module = std::mem::replace(&mut self.current_module, module);
+
+        let result = f(self);
+
+        if result.is_ok() {
+            self.current_module = outer_module;
+        }
+
+        result
+    }
+
+    fn perform_item_registration_pass(&mut self, package: &Package) -> TypeCheckResult<()> {
+        for module in &package.modules {
+            self.register_items_in_module(None, module)?;
+        }
+
+        Ok(())
+    }
+
+    fn register_items_in_module(
+        &mut self,
+        prefix: Option<&ThinVec<TyPathSegment>>,
+        module: &Module,
+    ) -> TypeCheckResult<()> {
+        for item in &module.items {
+            match item.kind {
+                // The `std` types have to be in scope for the function signatures.
+                ItemKind::Use(ref use_tree) => {
+                    self.import_std_ty(use_tree);
+                }
+                ItemKind::Fn(ref fun) => {
+                    let (typed_params, return_ty) = self.infer_function_decl(&fun.decl)?;
+
+                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+
+                    let mut fn_path_segments = path_segments.clone();
+                    fn_path_segments.push(TyPathSegment {
+                        ident: item.name.clone(),
+                    });
+
+                    self.register_fn_attrs(
+                        TyPath {
+                            segments: fn_path_segments,
+                            span: item.name.span,
+                        },
+                        item,
+                    );
+
+                    let module_path = TyPath {
+                        segments: path_segments,
+                        span: DUMMY_SPAN,
+                    };
+
+                    self.register_function(
+                        module_path,
+                        item.name.clone(),
+                        typed_params,
+                        return_ty,
+                    )?;
+                }
+                ItemKind::ExternFn(ref fun_decl) => {
+                    let (typed_params, return_ty) = self.infer_function_decl(fun_decl)?;
+
+                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+
+                    let mut fn_path_segments = path_segments.clone();
+                    fn_path_segments.push(TyPathSegment {
+                        ident: item.name.clone(),
+                    });
+
+                    let fn_path = TyPath {
+                        segments: fn_path_segments,
+                        span: item.name.span,
+                    };
+
+                    self.register_fn_attrs(fn_path.clone(), item);
+
+                    // Calls to an `extern fn` in a module refer to it by its
+                    // symbol, which isn't prefixed with the module's path.
+                    if !path_segments.is_empty() {
+                        self.extern_fns
+                            .insert(fn_path, extern_fn_symbol(&item.name));
+                    }
+
+                    let module_path = TyPath {
+                        segments: path_segments,
+                        span: DUMMY_SPAN,
+                    };
+
+                    self.register_function(
+                        module_path,
+                        item.name.clone(),
+                        typed_params,
+                        return_ty,
+                    )?;
+                }
+                ItemKind::Struct(ref struct_decl) => {
+                    let typed_struct_decl =
+                        self.infer_struct_decl(struct_decl, item.attr("bitfield").is_some())?;
+
+                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+
+                    let module_path = TyPath {
+                        segments: path_segments,
+                        span: DUMMY_SPAN,
+                    };
+
+                    // Tuple structs are constructed by calling a function with
+                    // the same name, like `Meters(5.0)`.
+                    if let Some(inner) = typed_struct_decl.newtype() {
+                        let (params, return_ty) =
+                            tuple_struct_constructor_signature(&item.name, inner);
+
+                        self.modules
+                            .entry(module_path.clone())
+                            .or_default()
+                            .functions
+                            .insert(item.name.clone(), (params, return_ty));
+                    }
+
+                    self.register_struct(module_path, item.name.clone(), typed_struct_decl)?;
+                }
+                ItemKind::Union(ref union_decl) => {
+                    let typed_union_decl = self.infer_union_decl(union_decl)?;
+
+                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+
+                    let module_path = TyPath {
+                        segments: path_segments,
+                        span: DUMMY_SPAN,
+                    };
+
+                    self.register_union(module_path, item.name.clone(), typed_union_decl)?;
+                }
+                ItemKind::Module(ref module_decl) => match &**module_decl {
+                    ModuleDecl::Loaded(module, _) => {
+                        let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                        path_segments.push(TyPathSegment {
+                            ident: item.name.clone(),
+                        });
+
+                        self.in_module(&path_segments, |typer| {
+                            typer.register_items_in_module(Some(&path_segments), module)
+                        })?;
+                    }
+                    ModuleDecl::Unloaded => {}
+                },
+                ItemKind::Impl(ref impl_decl) => {
+                    let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                    path_segments.push(TyPathSegment {
+                        ident: item.name.clone(),
+                    });
+
+                    if let Some(trait_name) = &impl_decl.trait_name {
+                        self.check_trait_impl(trait_name, &item.name, &impl_decl.items)?;
+                    }
+
+                    self.register_impl_items(&item.name, path_segments, &impl_decl.items)?;
+                }
+                ItemKind::Const(ref const_decl) => {
+                    let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                    path_segments.push(TyPathSegment {
+                        ident: item.name.clone(),
+                    });
+
+                    let path = TyPath {
+                        segments: path_segments,
+                        span: item.name.span,
+                    };
+
+                    self.register_const(path, const_decl)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the value of a `const` and registers it under its path.
+    ///
+    /// A `const` can refer to those declared before it, so its value never
+    /// refers to another `const`.
+    fn register_const(&mut self, path: TyPath, const_decl: &ConstDecl) -> TypeCheckResult<()> {
+        let ty = self.infer_ty(&const_decl.ty)?;
+        let mut value = self.infer_expr(const_decl.expr)?;
+
+        self.coerce_integer_literal(&mut value, &ty)?;
+
+        if value.ty != ty {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Expected `{}` but received `{}`",
+                    ty_to_string(ty),
+                    ty_to_string(value.ty)
+                )),
+                span: value.span,
+            });
+        }
+
+        let kind = match value.kind {
+            _ if matches!(&*ty, TyKind::Int(_) | TyKind::Uint(_)) => {
+                match self.comptime_evaluator().eval_expr(&value)? {
+                    ComptimeValue::Int(int) => {
+                        integer_literal(int, &ty).map(TyLiteralKind::Integer)
+                    }
+                    _ => None,
+                }
+            }
+            TyExprKind::Literal(literal) => Some(literal.kind),
+            TyExprKind::Variable(ref other) => {
+                self.consts.get(other).and_then(|other| match &other.kind {
+                    TyExprKind::Literal(literal) => Some(literal.kind.clone()),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+
+        let Some(kind) = kind else {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "The value of the `{}` `{path}` cannot be evaluated at compile time",
+                    keywords::CONST
+                )),
+                span: value.span,
+            });
+        };
+
+        self.consts.insert(
+            path,
+            TyExpr {
+                kind: TyExprKind::Literal(TyLiteral {
+                    kind,
+                    span: value.span,
+                }),
+                span: value.span,
+                ty,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Returns a [`ComptimeEvaluator`] that knows the values of the integer
+    /// `const`s registered so far.
+    fn comptime_evaluator(&self) -> ComptimeEvaluator {
+        ComptimeEvaluator::with_consts(self.consts.iter().filter_map(|(path, value)| {
+            match &value.kind {
+                TyExprKind::Literal(TyLiteral {
+                    kind: TyLiteralKind::Integer(integer),
+                    ..
+                }) => Some((path.clone(), ComptimeValue::Int(integer.value()))),
+                _ => None,
+            }
+        }))
+    }
+
+    /// Checks that an `impl` of a trait for the type named `self_name` defines
+    /// exactly the methods of the trait, with the parameters and return types
+    /// the trait declares for them.
+    fn check_trait_impl(
+        &mut self,
+        trait_name: &Ident,
+        self_name: &Ident,
+        items: &[Item],
+    ) -> TypeCheckResult<()> {
+        let Some((_, methods)) = TRAITS.iter().find(|(name, _)| trait_name.name == *name) else {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Unknown trait `{trait_name}`. Expected one of: {}.",
+                    TRAITS.map(|(name, _)| format!("`{name}`")).join(", ")
+                )),
+                span: trait_name.span,
+            });
+        };
+
+        if let Some(method) = methods
+            .iter()
+            .find(|method| !items.iter().any(|item| item.name.name == method.name))
+        {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Missing the method `{}` of the trait `{trait_name}`.",
+                    method.name
+                )),
+                span: trait_name.span,
+            });
+        }
+
+        let ty_name = |name: &'static str| match name {
+            "Self" => self_name.name,
+            _ => name.into(),
+        };
+
+        for item in items {
+            let ItemKind::Fn(ref fun) = item.kind else {
+                continue;
+            };
+
+            let Some(method) = methods.iter().find(|method| item.name.name == method.name) else {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(format!(
+                        "`{}` is not a method of the trait `{trait_name}`.",
+                        item.name
+                    )),
+                    span: item.name.span,
+                });
+            };
+
+            let param_names = std::iter::once(self_name.name)
+                .chain(method.params.iter().map(|&param| ty_name(param)))
+                .collect::<Vec<_>>();
+
+            let (params, return_ty) = self.infer_function_decl(&fun.decl)?;
+
+            let mut matches = params.len() == param_names.len()
+                && params.first().map(|param| param.name.name.as_str()) == Some("self")
+                && (method.return_ty == "_"
+                    || return_ty == self.named_ty(ty_name(method.return_ty))?);
+
+            for (param, name) in params.iter().zip(&param_names) {
+                matches &= name == "_" || param.ty == self.named_ty(*name)?;
+            }
+
+            if !matches {
+                let params = param_names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| match index {
+                        0 => format!("self: {name}"),
+                        _ => format!("other: {name}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(format!(
+                        "`{}` must be declared as `fn {}({params}) -> {}` to implement `{trait_name}`.",
+                        item.name,
+                        item.name,
+                        ty_name(method.return_ty)
+                    )),
+                    span: item.name.span,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the type with the given name, like `String`.
+    fn named_ty(&mut self, name: Symbol) -> TypeCheckResult<TypeId> {
+        self.infer_ty(&ast::Ty {
+            kind: ast::TyKind::Path(ast::Path {
+                segments: thin_vec![PathSegment {
+                    ident: Ident {
+                        name,
+                        span: DUMMY_SPAN,
+                    },
+                }],
+                span: DUMMY_SPAN,
+            }),
+            span: DUMMY_SPAN,
+        })
+    }
+
+    /// Registers the functions in an `impl` block as items of the module named
+    /// after its type, recording those taking `self` as methods.
+    fn register_impl_items(
+        &mut self,
+        self_name: &Ident,
+        path_segments: ThinVec<TyPathSegment>,
+        items: &[Item],
+    ) -> TypeCheckResult<()> {
+        let self_ty = TypeId::intern(TyKind::UserDefined {
+            module: "std::prelude".into(),
+            name: self_name.name.into(),
+        });
+
+        for item in items {
+            let ItemKind::Fn(ref fun) = item.kind else {
+                continue;
+            };
+
+            let (typed_params, return_ty) = self.infer_function_decl(&fun.decl)?;
+
+            let mut fn_path_segments = path_segments.clone();
+            fn_path_segments.push(TyPathSegment {
+                ident: item.name.clone(),
+            });
+
+            let fn_path = TyPath {
+                segments: fn_path_segments,
+                span: item.name.span,
+            };
+
+            self.register_fn_attrs(fn_path.clone(), item);
+
+            if let Some(self_param) = typed_params
+                .first()
+                .filter(|param| param.name.name == "self")
+            {
+                if self_param.ty != self_ty {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `self` to be `{self_name}` but received `{}`",
+                            ty_to_string(self_param.ty)
+                        )),
+                        span: self_param.span,
+                    });
+                }
+
+                self.methods
+                    .insert((self_name.name.into(), item.name.name.into()), fn_path);
+            }
+
+            let module_path = TyPath {
+                segments: path_segments.clone(),
+                span: DUMMY_SPAN,
+            };
+
+            self.register_function(module_path, item.name.clone(), typed_params, return_ty)?;
+        }
+
+        Ok(())
+    }
+
+    fn type_check_module(
+        &mut self,
+        prefix: Option<&ThinVec<TyPathSegment>>,
+        module: &Module,
+    ) -> TypeCheckResult<TyModule<'a>> {
+        for item in &module.items {
+            match item.kind {
+                ItemKind::Use(ref use_tree) => {
+                    self.infer_use_tree(use_tree)?;
+                }
+                ItemKind::Fn(ref fun) => {
+                    let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                    path_segments.push(TyPathSegment {
+                        ident: item.name.clone(),
+                    });
+
+                    let path = TyPath {
+                        segments: path_segments,
+                        span: item.name.span,
+                    };
+
+                    let allowed_lints_len = self.allowed_lints.len();
+                    self.allowed_lints.extend(allowed_lints(&item.attrs));
+
+                    self.infer_function(&path, fun)?;
+
+                    self.allowed_lints.truncate(allowed_lints_len);
+                }
+                ItemKind::ExternFn(_) => {}
+                ItemKind::Struct(_) => {}
+                ItemKind::Union(_) => {}
+                ItemKind::Impl(_) => {}
+                ItemKind::Const(_) => {}
+                ItemKind::Module(ref module_decl) => match &**module_decl {
+                    ModuleDecl::Loaded(module, _) => {
+                        let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                        path_segments.push(TyPathSegment {
+                            ident: item.name.clone(),
+                        });
+
+                        self.in_module(&path_segments, |typer| {
+                            typer.type_check_module(Some(&path_segments), module)
+                        })?;
+                    }
+                    ModuleDecl::Unloaded => {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Module `{}` has not been loaded.",
+                                item.name
+                            )),
+                            span: item.name.span,
+                        });
+                    }
+                },
+            }
+        }
+
+        let mut typed_items = ThinVec::new();
+
+        for item in &module.items {
+            push_item(
+                self.arena,
+                &mut typed_items,
+                prefix,
+                self.infer_item(prefix, item)?,
+            );
+        }
+
+        Ok(TyModule { items: typed_items })
+    }
+
+    fn infer_ty(&mut self, ty: &ast::Ty) -> TypeCheckResult<TypeId> {
+        Ok(match &ty.kind {
+            ast::TyKind::Path(path) => {
+                let (PathSegment { ident }, _) = path.segments.split_last().unwrap();
+
+                match ident.name.as_str() {
+                    "Bool" => TypeId::intern(TyKind::Bool),
+                    "Int8" => TypeId::intern(TyKind::Int(IntTy::I8)),
+                    "Int16" => TypeId::intern(TyKind::Int(IntTy::I16)),
+                    "Int32" => self.int32_ty,
+                    "Int64" => TypeId::intern(TyKind::Int(IntTy::I64)),
+                    "Uint8" => TypeId::intern(TyKind::Uint(UintTy::U8)),
+                    "Uint16" => TypeId::intern(TyKind::Uint(UintTy::U16)),
+                    "Uint32" => TypeId::intern(TyKind::Uint(UintTy::U32)),
+                    "Uint64" => self.uint64_ty,
+                    "Float64" => self.float64_ty,
+                    _ => {
+                        let std_ty = if path.segments.len() == 1 {
+                            self.imported_tys.get(&ident.name).copied()
+                        } else {
+                            self.std_ty(&path.segments)
+                        };
+
+                        std_ty.unwrap_or_else(|| {
+                            TypeId::intern(TyKind::UserDefined {
+                                module: "std::prelude".into(),
+                                name: ident.to_string().into(),
+                            })
+                        })
+                    }
+                }
+            }
+            ast::TyKind::Fn(fn_ty) => {
+                let (params, return_ty) = self.infer_function_decl(&fn_ty.decl)?;
+
+                TypeId::intern(TyKind::Fn {
+                    args: params.iter().map(|param| param.ty).collect(),
+                    return_ty,
+                    calling_convention: CallingConvention::C,
+                })
+            }
+            ast::TyKind::Array { elem, len } => TypeId::intern(TyKind::Array {
+                elem: self.infer_ty(elem)?,
+                len: self.infer_array_len(len)?,
+            }),
+        })
+    }
+
+    /// Evaluates the constant expression giving the length of an array type.
+    fn infer_array_len(&mut self, len: &Expr) -> TypeCheckResult<u64> {
+        let len = self.infer_expr(len)?;
+
+        match self.comptime_evaluator().eval_expr(&len)? {
+            ComptimeValue::Int(value) if matches!(&*len.ty, TyKind::Uint(_)) => Ok(value as u64),
+            _ => Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Expected an unsigned integer array length but received `{}`",
+                    ty_to_string(len.ty)
+                )),
+                span: len.span,
+            }),
+        }
+    }
+
+    fn infer_item(
+        &mut self,
+        prefix: Option<&ThinVec<TyPathSegment>>,
+        item: &Item,
+    ) -> TypeCheckResult<TyItem<'a>> {
+        self.check_attributes(item)?;
+
+        let is_bitfield = item.attr("bitfield").is_some();
+
+        let allowed_lints_len = self.allowed_lints.len();
+        self.allowed_lints.extend(allowed_lints(&item.attrs));
+
+        let kind = match &item.kind {
+            ItemKind::Use(use_tree) => {
+                self.infer_use_tree(use_tree)?;
+
+                TyItemKind::Use
+            }
+            ItemKind::Fn(fun) => {
+                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                path_segments.push(TyPathSegment {
+                    ident: item.name.clone(),
+                });
+
+                let path = TyPath {
+                    segments: path_segments,
+                    span: item.name.span,
+                };
+
+                TyItemKind::Fn(Box::new(self.infer_function(&path, fun)?))
+            }
+            ItemKind::ExternFn(fun_decl) => {
+                let (params, return_ty) = self.infer_function_decl(fun_decl)?;
+
+                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                path_segments.push(TyPathSegment {
+                    ident: item.name.clone(),
+                });
+
+                let path = TyPath {
+                    segments: path_segments,
+                    span: item.name.span,
+                };
+
+                TyItemKind::ExternFn(Box::new(TyFn {
+                    params,
+                    return_ty,
+                    body: ThinVec::new(),
+                    calling_convention: self.calling_convention_of(&path),
+                    path: extern_fn_symbol(&item.name),
+                }))
+            }
+            ItemKind::Struct(struct_decl) => {
+                TyItemKind::Struct(self.infer_struct_decl(struct_decl, is_bitfield)?)
+            }
+            ItemKind::Union(union_decl) => TyItemKind::Union(self.infer_union_decl(union_decl)?),
+            ItemKind::Module(module_decl) => {
+                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                path_segments.push(TyPathSegment {
+                    ident: item.name.clone(),
+                });
+
+                let inline = match &**module_decl {
+                    ModuleDecl::Loaded(_, inline) => inline.clone(),
+                    ModuleDecl::Unloaded => InlineModuleDecl::No,
+                };
+
+                TyItemKind::Module(
+                    self.in_module(&path_segments, |typer| {
+                        typer.infer_module_decl(Some(&path_segments), module_decl)
+                    })?,
+                    inline,
+                )
+            }
+            ItemKind::Impl(impl_decl) => {
+                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                path_segments.push(TyPathSegment {
+                    ident: item.name.clone(),
+                });
+
+                self.ensure_struct_exists(&TyPath {
+                    segments: path_segments.clone(),
+                    span: item.name.span,
+                })?;
+
+                let items = impl_decl
+                    .items
+                    .iter()
+                    .map(|item| self.infer_item(Some(&path_segments), item))
+                    .collect::<Result<ThinVec<_>, _>>()?;
+
+                TyItemKind::Impl(TyImplDecl { items })
+            }
+            ItemKind::Const(_) => {
+                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                path_segments.push(TyPathSegment {
+                    ident: item.name.clone(),
+                });
+
+                let path = TyPath {
+                    segments: path_segments,
+                    span: item.name.span,
+                };
+
+                // The value was already evaluated when the `const` was registered.
+                let value = self.consts[&path].clone();
+
+                TyItemKind::Const(Box::new(TyConst { value, path }))
+            }
+        };
+
+        self.allowed_lints.truncate(allowed_lints_len);
+
+        Ok(TyItem {
+            kind,
+            name: item.name.clone(),
+            vis: item.vis,
+            attrs: item.attrs.clone(),
+        })
+    }
+
+    /// Checks that the attributes on the given [`Item`] are known and valid for it.
+    fn check_attributes(&self, item: &Item) -> TypeCheckResult<()> {
+        for attr in &item.attrs {
+            match attr.name.name.as_str() {
+                "repr" => {
+                    let reprs = match item.kind {
+                        ItemKind::Struct(_) => STRUCT_REPRS.as_slice(),
+                        ItemKind::Union(_) => UNION_REPRS.as_slice(),
+                        _ => {
+                            return Err(TypeError {
+                                kind: TypeErrorKind::Error(
+                                    "`#[repr]` can only be applied to a `struct` or `union`."
+                                        .to_string(),
+                                ),
+                                span: attr.span,
+                            });
+                        }
+                    };
+
+                    let [arg] = attr.args.as_slice() else {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[repr]` expects a single representation, like `#[repr(C)]`."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    };
+
+                    if arg.value.is_some() || !reprs.contains(&arg.name.name.as_str()) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Unknown representation `{}`. Expected one of: {}.",
+                                arg.name,
+                                reprs.join(", ")
+                            )),
+                            span: arg.span,
+                        });
+                    }
+
+                    if let ItemKind::Union(union_decl) = &item.kind {
+                        let tag_bits = arg.name.name[1..].parse::<u32>().unwrap();
+
+                        if (union_decl.variants.len() as u128) > 1 << tag_bits {
+                            return Err(TypeError {
+                                kind: TypeErrorKind::Error(format!(
+                                    "`{}` has {} variants, which do not fit in a `{}` discriminant.",
+                                    item.name,
+                                    union_decl.variants.len(),
+                                    arg.name
+                                )),
+                                span: arg.span,
+                            });
+                        }
+                    }
+                }
+                "bitfield" => {
+                    if !matches!(
+                        &item.kind,
+                        ItemKind::Struct(StructDecl(VariantData::Struct(_)))
+                    ) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[bitfield]` can only be applied to a `struct` with named fields."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    if !attr.args.is_empty() || item.attr("repr").is_some() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[bitfield]` takes no arguments and cannot be combined with `#[repr]`."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+                }
+                "inline" => {
+                    if !matches!(item.kind, ItemKind::Fn(_)) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[inline]` can only be applied to a function.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    match attr.args.as_slice() {
+                        [] => {}
+                        [arg]
+                            if arg.value.is_none()
+                                && matches!(arg.name.name.as_str(), "always" | "never") => {}
+                        _ => {
+                            return Err(TypeError {
+                                kind: TypeErrorKind::Error(
+                                    "`#[inline]` expects no arguments, `always` or `never`, like `#[inline(never)]`."
+                                        .to_string(),
+                                ),
+                                span: attr.span,
+                            });
+                        }
+                    }
+                }
+                "deprecated" | "must_use" => {
+                    if !matches!(item.kind, ItemKind::Fn(_)) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "`#[{}]` can only be applied to a function.",
+                                attr.name
+                            )),
+                            span: attr.span,
+                        });
+                    }
+
+                    if let Some(arg) = attr
+                        .args
+                        .iter()
+                        .find(|arg| arg.name.name != "message" || arg.value.is_none())
+                    {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "`#[{0}]` only accepts a message, like `#[{0}(message = \"...\")]`.",
+                                attr.name
+                            )),
+                            span: arg.span,
+                        });
+                    }
+                }
+                "allow" => {
+                    if attr.args.is_empty() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[allow]` expects the lints to allow, like `#[allow(deprecated)]`."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    for arg in &attr.args {
+                        if arg.value.is_some() || find_lint(arg.name.name.as_str()).is_none() {
+                            return Err(TypeError {
+                                kind: TypeErrorKind::Error(format!(
+                                    "Unknown lint `{}`. Expected one of: {}.",
+                                    arg.name,
+                                    lint_names().join(", ")
+                                )),
+                                span: arg.span,
+                            });
+                        }
+                    }
+                }
+                // Items disabled by `#[cfg]` have already been removed by
+                // `crate::cfg`, so there is nothing left to check.
+                "cfg" => {}
+                "calling_convention" => {
+                    if !matches!(item.kind, ItemKind::Fn(_) | ItemKind::ExternFn(_)) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[calling_convention]` can only be applied to a function."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    let Some(name) = attr.value.as_ref().filter(|_| attr.args.is_empty()) else {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[calling_convention]` expects a calling convention, like `#[calling_convention = \"fastcall\"]`."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    };
+
+                    if CallingConvention::from_name(name).is_none() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Unknown calling convention `{name}`. Expected one of: {}.",
+                                CallingConvention::ALL
+                                    .iter()
+                                    .map(|calling_convention| calling_convention.name())
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )),
+                            span: attr.span,
+                        });
+                    }
+                }
+                "naked" => {
+                    if !matches!(item.kind, ItemKind::Fn(_)) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[naked]` can only be applied to a function.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    if !attr.args.is_empty() || attr.value.is_some() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[naked]` does not take any arguments.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+                }
+                "test" => {
+                    let ItemKind::Fn(fun) = &item.kind else {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[test]` can only be applied to a function.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    };
+
+                    if !attr.args.is_empty() || attr.value.is_some() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[test]` does not take any arguments.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    if !fun.decl.params.is_empty()
+                        || !matches!(fun.decl.return_ty, FnReturnTy::Unit)
+                    {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "A test function must take no parameters and return `()`."
+                                    .to_string(),
+                            ),
+                            span: item.name.span,
+                        });
+                    }
+                }
+                // The traits are checked when the macro expander derives them.
+                "derive" => {}
+                "link" => {
+                    if !matches!(item.kind, ItemKind::ExternFn(_)) {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[link]` can only be applied to an `extern fn`.".to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+
+                    if !matches!(attr.args.as_slice(), [arg] if arg.name.name == "name" && arg.value.is_some())
+                    {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[link]` expects the name of a library, like `#[link(name = \"m\")]`."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+                }
+                _ => {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!("Unknown attribute `{}`.", attr.name)),
+                        span: attr.name.span,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `std` type at the given path, like `std::sync::Mutex`.
+    ///
+    /// These are only resolved by their full path, or by a name imported with
+    /// `use`, so they don't shadow the types declared in the package.
+    fn std_ty(&self, segments: &[PathSegment]) -> Option<TypeId> {
+        let names = segments
+            .iter()
+            .map(|segment| segment.ident.name.as_str())
+            .collect::<Vec<_>>();
+
+        match names.as_slice() {
+            ["std", "thread", "ThreadHandle"] => Some(self.thread_handle_ty),
+            ["std", "sync", "Mutex"] => Some(self.mutex_ty),
+            ["std", "sync", "AtomicUint64"] => Some(self.atomic_uint64_ty),
+            ["std", "sync", "MemoryOrdering"] => Some(self.memory_ordering_ty),
+            _ => None,
+        }
+    }
+
+    /// Brings the `std` type named by the `use`, if it names one, into scope.
+    fn import_std_ty(&mut self, use_tree: &UseTree) -> bool {
+        let Some(ty) = self.std_ty(&use_tree.prefix.segments) else {
+            return false;
+        };
+
+        let PathSegment { ident } = use_tree.prefix.segments.last().unwrap();
+        self.imported_tys.insert(ident.name, ty);
+
+        true
+    }
+
+    fn infer_use_tree(&mut self, use_tree: &UseTree) -> TypeCheckResult<()> {
+        if self.import_std_ty(use_tree) {
+            return Ok(());
+        }
+
+        match use_tree.kind {
+            UseTreeKind::Single => {
+                let (PathSegment { ident }, module_path_segments) =
+                    use_tree.prefix.segments.split_last().unwrap();
+
+                let module_path = TyPath {
+                    segments: module_path_segments
+                        .iter()
+                        .map(|segment| TyPathSegment {
+                            ident: segment.ident.clone(),
+                        })
+                        .collect::<ThinVec<_>>(),
+                    span: DUMMY_SPAN,
+                };
+
+                let mut fn_path_segments = module_path.segments.clone();
+                fn_path_segments.push(TyPathSegment {
+                    ident: ident.clone(),
+                });
+
+                let fn_path = TyPath {
+                    segments: fn_path_segments,
+                    span: ident.span,
+                };
+
+                self.ensure_function_exists(&fn_path)?;
+
+                let imported_path = TyPath {
+                    segments: thin_vec![TyPathSegment {
+                        ident: ident.clone()
+                    }],
+                    span: ident.span,
+                };
+
+                self.use_map.insert(imported_path, fn_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a path like `MemoryOrdering::SeqCst` to the full path of the
+    /// `std::sync::MemoryOrdering` variant it refers to.
+    fn resolve_memory_ordering(&self, path: &TyPath) -> TypeCheckResult<Option<TyPath>> {
+        let [.., enum_segment, variant_segment] = path.segments.as_slice() else {
+            return Ok(None);
+        };
+
+        if enum_segment.ident.name != "MemoryOrdering" {
+            return Ok(None);
+        }
+
+        let variant = &variant_segment.ident;
+
+        if !MEMORY_ORDERINGS.contains(&variant.name.as_str()) {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "`{}` is not a variant of `MemoryOrdering`. Expected one of: {}.",
+                    variant,
+                    MEMORY_ORDERINGS.join(", ")
+                )),
+                span: variant.span,
+            });
+        }
+
+        Ok(Some(TyPath {
+            segments: ["std", "sync", "MemoryOrdering"]
+                .into_iter()
+                .map(|name| TyPathSegment {
+                    ident: Ident {
+                        name: name.into(),
+                        span: DUMMY_SPAN,
+                    },
+                })
+                .chain([variant_segment.clone()])
+                .collect(),
+            span: path.span,
+        }))
+    }
+
+    fn infer_function(&mut self, path: &TyPath, fun: &Fn) -> TypeCheckResult<TyFn<'a>> {
+        let (_, return_ty) = self.ensure_function_exists(path)?;
+
+        self.infer_fn_body(path, return_ty, fun, None)
+    }
+
+    /// Type checks the body of the function at the given path.
+    ///
+    /// A nested function is given its own type as `self_ty`, so that its body
+    /// can call it.
+    fn infer_fn_body(
+        &mut self,
+        path: &TyPath,
+        return_ty: TypeId,
+        fun: &Fn,
+        self_ty: Option<TypeId>,
+    ) -> TypeCheckResult<TyFn<'a>> {
+        let params = self.infer_function_params(&fun.decl.params)?;
+
+        // The body of a `#[naked]` function is nothing but its assembly.
+        let is_unsafe = fun.is_unsafe || self.naked_fns.contains(path);
+        let outer_in_unsafe = std::mem::replace(&mut self.in_unsafe, is_unsafe);
+        let outer_fn = self.current_fn.replace(path.clone());
+
+        self.fn_params = params
+            .iter()
+            .map(|param| TyPath {
+                segments: thin_vec![TyPathSegment {
+                    ident: param.name.clone()
+                }],
+                span: param.span,
+            })
+            .collect();
+
+        self.scopes
+            .push(HashMap::from_iter(params.clone().into_iter().map(
+                |param| {
+                    (
+                        TyPath {
+                            segments: thin_vec![TyPathSegment { ident: param.name }],
+                            span: param.span,
+                        },
+                        param.ty,
+                    )
+                },
+            )));
+
+        if let (Some(self_ty), Some(scope)) = (self_ty, self.scopes.last_mut()) {
+            scope.insert(path.clone(), self_ty);
+        }
+
+        let body = fun
+            .body
+            .iter()
+            .map(|stmt| self.infer_stmt(stmt))
+            .collect::<Result<ThinVec<_>, _>>();
+
+        self.in_unsafe = outer_in_unsafe;
+        self.current_fn = outer_fn;
+
+        let mut body = body?;
+
+        self.warn_unused_results(&body);
+        self.warn_unreachable_code(&body);
+
+        let is_naked = self.naked_fns.contains(path);
+
+        if is_naked
+            && !matches!(body.as_slice(), [TyStmt { kind: TyStmtKind::Expr(expr), .. }] if matches!(expr.kind, TyExprKind::Asm(_)))
+        {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(
+                    "The body of a `#[naked]` function must be a single `asm` block.".to_string(),
+                ),
+                span: path.span,
+            });
+        }
+
+        // A naked function returns from its assembly, so its body has no value.
+        if let Some(last_stmt) = body.last_mut().filter(|_| !is_naked) {
+            let ty = match &mut last_stmt.kind {
+                TyStmtKind::Expr(expr) => {
+                    self.coerce_allocated(expr, |typer, expr| {
+                        typer.coerce_integer_literal(expr, &return_ty)?;
+                        typer.coerce_diverging(expr, &return_ty);
+
+                        Ok(())
+                    })?;
+
+                    &expr.ty
+                }
+                TyStmtKind::Local(_) | TyStmtKind::Item(_) => &self.unit_ty,
+            };
+
+            if *ty != return_ty {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(format!(
+                        "Expected `{path}` to return {} but got {}",
+                        ty_to_string(return_ty),
+                        ty_to_string(*ty)
+                    )),
+                    span: last_stmt.span,
+                });
+            }
+        }
+
+        let ty_fn = TyFn {
+            path: path.clone(),
+            params,
+            return_ty,
+            body,
+            calling_convention: self.calling_convention_of(path),
+        };
+
+        self.scopes.pop();
+
+        Ok(ty_fn)
+    }
+
+    fn infer_function_decl(
+        &mut self,
+        function_decl: &FnDecl,
+    ) -> TypeCheckResult<(ThinVec<TyFnParam>, TypeId)> {
+        let params = self.infer_function_params(&function_decl.params)?;
+
+        let return_ty = match function_decl.return_ty {
+            FnReturnTy::Unit => self.unit_ty,
+            FnReturnTy::Ty(ref ty) => self.infer_ty(ty)?,
+        };
+
+        Ok((params, return_ty))
+    }
+
+    fn infer_function_params(
+        &mut self,
+        params: &ThinVec<FnParam>,
+    ) -> TypeCheckResult<ThinVec<TyFnParam>> {
+        params
+            .iter()
+            .map(|param| {
+                Ok(TyFnParam {
+                    name: param.name.clone(),
+                    ty: self.infer_ty(&param.ty)?,
+                    span: param.span,
+                })
+            })
+            .collect::<Result<ThinVec<_>, _>>()
+    }
+
+    fn infer_struct_decl(
+        &mut self,
+        struct_decl: &StructDecl,
+        is_bitfield: bool,
+    ) -> TypeCheckResult<TyStructDecl> {
+        let VariantData::Struct(fields) = &struct_decl.0 else {
+            return Ok(TyStructDecl(self.infer_variant_data(&struct_decl.0)?));
+        };
+
+        let fields = self.infer_field_decls(fields, is_bitfield)?;
+
+        if is_bitfield {
+            let total_bits = fields.iter().filter_map(|field| field.bits).sum::<u32>();
+
+            if total_bits > MAX_BITFIELD_BITS {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(format!(
+                        "The fields of a `#[bitfield]` struct can occupy at most {MAX_BITFIELD_BITS} bits, but these occupy {total_bits}."
+                    )),
+                    span: fields.last().map_or(DUMMY_SPAN, |field| field.span),
+                });
+            }
+        }
+
+        Ok(TyStructDecl(TyVariantData::Struct(fields)))
+    }
+
+    fn infer_union_decl(&mut self, union_decl: &UnionDecl) -> TypeCheckResult<TyUnionDecl> {
+        let mut ty_variants = ThinVec::with_capacity(union_decl.variants.len());
+
+        for variant in &union_decl.variants {
+            ty_variants.push(TyVariant {
+                name: variant.name.clone(),
+                data: self.infer_variant_data(&variant.data)?,
+                span: variant.span,
+            });
+        }
+
+        Ok(TyUnionDecl {
+            variants: ty_variants,
+        })
+    }
+
+    fn infer_module_decl(
+        &mut self,
+        prefix: Option<&ThinVec<TyPathSegment>>,
+        module_decl: &ModuleDecl,
+    ) -> TypeCheckResult<TyModule<'a>> {
+        match &module_decl {
+            ModuleDecl::Loaded(module, _) => {
+                let mut items = ThinVec::new();
+
+                for item in &module.items {
+                    push_item(
+                        self.arena,
+                        &mut items,
+                        prefix,
+                        self.infer_item(prefix, item)?,
+                    );
+                }
+
+                Ok(TyModule { items })
+            }
+            ModuleDecl::Unloaded => Err(TypeError {
+                kind: TypeErrorKind::Error("Module has not been loaded.".into()),
+                span: DUMMY_SPAN,
+            }),
+        }
+    }
+
+    fn infer_variant_data(&mut self, variant_data: &VariantData) -> TypeCheckResult<TyVariantData> {
+        Ok(match &variant_data {
+            VariantData::Struct(fields) => {
+                TyVariantData::Struct(self.infer_field_decls(fields, false)?)
+            }
+            VariantData::Tuple(fields) => {
+                TyVariantData::Tuple(self.infer_field_decls(fields, false)?)
+            }
+            VariantData::Unit => TyVariantData::Unit,
+        })
+    }
+
+    fn infer_field_decls(
+        &mut self,
+        fields: &[FieldDecl],
+        is_bitfield: bool,
+    ) -> TypeCheckResult<ThinVec<TyFieldDecl>> {
+        let mut ty_fields = ThinVec::with_capacity(fields.len());
+
+        for field in fields {
+            let ty = self.infer_ty(&field.ty)?;
+
+            let mut bits = None;
+
+            for attr in &field.attrs {
+                match attr.name.name.as_str() {
+                    // Fields disabled by `#[cfg]` have already been removed.
+                    "cfg" => {}
+                    "bits" if is_bitfield => bits = Some(bitfield_width(attr, &ty)?),
+                    "bits" => {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "`#[bits]` can only be applied to the fields of a `#[bitfield]` struct."
+                                    .to_string(),
+                            ),
+                            span: attr.span,
+                        });
+                    }
+                    _ => {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Unknown field attribute `{}`.",
+                                attr.name
+                            )),
+                            span: attr.name.span,
+                        });
+                    }
+                }
+            }
+
+            if is_bitfield && bits.is_none() {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(
+                        "Every field of a `#[bitfield]` struct needs a width, like `#[bits(3)]`."
+                            .to_string(),
+                    ),
+                    span: field.span,
+                });
+            }
+
+            ty_fields.push(TyFieldDecl {
+                name: field.name.clone(),
+                ty,
+                bits,
+                span: field.span,
+            });
+        }
+
+        Ok(ty_fields)
+    }
+
+    fn infer_stmt(&mut self, stmt: &Stmt) -> TypeCheckResult<TyStmt<'a>> {
+        Ok(TyStmt {
+            kind: match &stmt.kind {
+                StmtKind::Local(local) => TyStmtKind::Local(Box::new(self.infer_local(local)?)),
+                StmtKind::Expr(expr) => {
+                    TyStmtKind::Expr(self.arena.alloc_ty_expr(self.infer_expr(expr)?))
+                }
+                StmtKind::Item(item) => TyStmtKind::Item(Box::new(self.infer_nested_fn(item)?)),
+            },
+            span: stmt.span.get(),
+        })
+    }
+
+    /// Type checks a function declared in the body of another function.
+    ///
+    /// A nested function can be called by the statements after it and by
+    /// itself, but it can't refer to the locals of the enclosing function.
+    fn infer_nested_fn(&mut self, item: &Item) -> TypeCheckResult<TyItem<'a>> {
+        self.check_attributes(item)?;
+
+        let ItemKind::Fn(fun) = &item.kind else {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(
+                    "Only functions can be declared inside of a function.".to_string(),
+                ),
+                span: item.name.span,
+            });
+        };
+
+        let (params, return_ty) = self.infer_function_decl(&fun.decl)?;
+
+        let fn_ty = TypeId::intern(TyKind::Fn {
+            args: params.iter().map(|param| param.ty).collect(),
+            return_ty,
+            calling_convention: CallingConvention::C,
+        });
+
+        let path = TyPath {
+            segments: thin_vec![TyPathSegment {
+                ident: item.name.clone()
+            }],
+            span: item.name.span,
+        };
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(path.clone(), fn_ty);
+        }
+
+        let outer_fn_params = std::mem::take(&mut self.fn_params);
+
+        let ty_fn = self.infer_fn_body(&path, return_ty, fun, Some(fn_ty));
+
+        self.fn_params = outer_fn_params;
+
+        Ok(TyItem {
+            kind: TyItemKind::Fn(Box::new(ty_fn?)),
+            name: item.name.clone(),
+            vis: item.vis,
+            attrs: item.attrs.clone(),
+        })
+    }
+
+    fn infer_local(&mut self, local: &Local) -> TypeCheckResult<TyLocal<'a>> {
+        let kind = match &local.kind {
+            LocalKind::Decl => TyLocalKind::Decl,
+            LocalKind::Init(init) => {
+                let init = self.infer_expr(init)?;
+
+                TyLocalKind::Init(self.arena.alloc_ty_expr(match &local.destructure {
+                    Some(path) => self.destructure_tuple_struct(path, init)?,
+                    None => init,
+                }))
+            }
+        };
+
+        let ty = match &kind {
+            TyLocalKind::Init(init) => init.ty,
+            TyLocalKind::Decl => TypeId::intern(TyKind::UserDefined {
+                module: "?".into(),
+                name: "?".into(),
+            }),
+        };
+
+        let path = TyPath {
+            segments: thin_vec![TyPathSegment {
+                ident: local.name.clone()
+            }],
+            span: local.span,
+        };
+
+        if self.consts.contains_key(&path) {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "`{}` is a `{}` and cannot be shadowed by a local",
+                    local.name,
+                    keywords::CONST
+                )),
+                span: local.name.span,
+            });
+        }
+
+        // A local that shadows a parameter can be assigned to.
+        self.fn_params.remove(&path);
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(path, ty);
+        }
+
+        Ok(TyLocal {
+            kind,
+            name: local.name.clone(),
+            ty: Some(ty),
+            span: local.span,
+        })
+    }
+
+    /// Returns the field of the tuple struct at `path` that `init` evaluates
+    /// to, for `let Meters(x) = init`.
+    fn destructure_tuple_struct(
+        &self,
+        path: &Path,
+        init: TyExpr<'a>,
+    ) -> TypeCheckResult<TyExpr<'a>> {
+        let path = TyPath {
+            segments: path
+                .segments
+                .iter()
+                .map(|segment| TyPathSegment {
+                    ident: segment.ident.clone(),
+                })
+                .collect(),
+            span: path.span,
+        };
+
+        let struct_decl = self.ensure_struct_exists(&path)?;
+
+        let Some(inner) = struct_decl.newtype() else {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!("`{path}` is not a tuple struct")),
+                span: path.span,
+            });
+        };
+
+        let (name, _) = path.segments.split_last().unwrap();
+
+        let struct_ty = TypeId::intern(TyKind::UserDefined {
+            module: "std::prelude".into(),
+            name: name.ident.name.into(),
+        });
+
+        if init.ty != struct_ty {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Expected `{}` but received `{}`",
+                    ty_to_string(struct_ty),
+                    ty_to_string(init.ty)
+                )),
+                span: init.span,
+            });
+        }
+
+        Ok(TyExpr {
+            ty: *inner,
+            span: init.span,
+            kind: TyExprKind::Field {
+                expr: self.arena.alloc_ty_expr(init),
+                index: 0,
+            },
+        })
+    }
+
+    fn infer_expr(&mut self, expr: &Expr) -> TypeCheckResult<TyExpr<'a>> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => match literal.kind {
+                LiteralKind::String => self.infer_string(literal, expr.span.get()),
+                LiteralKind::Integer => self.infer_integer(literal, expr.span.get()),
+                LiteralKind::Float => self.infer_float(literal, expr.span.get()),
+            },
+            ExprKind::Variable(path) => {
+                let path = TyPath {
+                    segments: path
+                        .segments
+                        .iter()
+                        .map(|segment| TyPathSegment {
+                            ident: segment.ident.clone(),
+                        })
+                        .collect::<ThinVec<_>>(),
+                    span: path.span,
+                };
+
+                // Check the variable's path against the items brought into scope by `use`.
+                // If we find an item that's been brought into scope we can use that as the alias.
+                let path = if let Some(use_path) = self.use_map.get(&path) {
+                    use_path.clone()
+                } else {
+                    path
+                };
+
+                if let Some(ordering_path) = self.resolve_memory_ordering(&path)? {
+                    return Ok(TyExpr {
+                        kind: TyExprKind::Variable(ordering_path),
+                        ty: self.memory_ordering_ty,
+                        span: expr.span.get(),
+                    });
+                }
+
+                if let Some(value) = self.consts.get(&path) {
+                    return Ok(TyExpr {
+                        kind: TyExprKind::Variable(path),
+                        ty: value.ty,
+                        span: expr.span.get(),
+                    });
+                }
+
+                let ty = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&path).cloned());
+
+                let ty = ty.or_else(|| {
+                    let function = self.ensure_function_exists(&path).ok();
+
+                    function.map(|(params, return_ty)| {
+                        TypeId::intern(TyKind::Fn {
+                            args: params.iter().map(|param| param.ty).collect(),
+                            return_ty,
+                            calling_convention: self.calling_convention_of(&path),
+                        })
+                    })
+                });
+
+                Ok(TyExpr {
+                    kind: TyExprKind::Variable(path),
+                    ty: ty.unwrap_or_else(|| {
+                        TypeId::intern(TyKind::UserDefined {
+                            module: "?".into(),
+                            name: "?".into(),
+                        })
+                    }),
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Call { fun, args } => {
+                let callee = self.infer_expr(fun)?;
+
+                let callee_path = match &callee.kind {
+                    TyExprKind::Variable(path) => Ok(path),
+                    _ => Err(TypeError {
+                        kind: TypeErrorKind::Error("Not a function.".to_string()),
+                        span: callee.span,
+                    }),
+                }?;
+
+                // Check the callee's path against the items brought into scope by `use`.
+                // If we find an item that's been brought into scope we can use that as the alias.
+                let callee_path = if let Some(use_path) = self.use_map.get(callee_path) {
+                    use_path.clone()
+                } else {
+                    callee_path.clone()
+                };
+
+                let callee_from_params = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&callee_path))
+                    .and_then(|ty| match &**ty {
+                        TyKind::Fn {
+                            args, return_ty, ..
+                        } => Some((
+                            args.iter()
+                                .map(|ty| TyFnParam {
+                                    name: Ident {
+                                        name: "".into(),
+                                        span: DUMMY_SPAN,
+                                    },
+                                    ty: *ty,
+                                    span: DUMMY_SPAN,
+                                })
+                                .collect::<ThinVec<_>>(),
+                            *return_ty,
+                        )),
+                        _ => None,
+                    });
+
+                let (callee_params, callee_return_ty) = if let Some(callee) = callee_from_params {
+                    callee
+                } else {
+                    let signature = self
+                        .ensure_function_exists(&callee_path)
+                        .map(|(params, return_ty)| (params.clone(), return_ty))?;
+
+                    self.warn_if_deprecated(&callee_path, callee.span);
+
+                    signature
+                };
+
+                let mut caller_args = args
+                    .iter()
+                    .map(|expr| Ok(self.arena.alloc_ty_expr(self.infer_expr(expr)?)))
+                    .collect::<TypeCheckResult<ThinVec<_>>>()?;
+
+                self.check_call_args(&callee_path, callee_params, &mut caller_args)?;
+
+                if self.unsafe_fns.contains(&callee_path) {
+                    self.require_unsafe(|| format!("Calling `{callee_path}`"), expr.span.get())?;
+                }
+
+                // A failed assertion reports where it is, so the location of
+                // the call is passed along with its arguments.
+                if callee_path.to_string() == "std::process::assert" {
+                    caller_args.push(
+                        self.arena
+                            .alloc_ty_expr(self.location_literal(expr.span.get())),
+                    );
+                }
+
+                let callee_path = self
+                    .extern_fns
+                    .get(&callee_path)
+                    .cloned()
+                    .unwrap_or(callee_path);
+
+                Ok(TyExpr {
+                    kind: TyExprKind::Call {
+                        fun: self.arena.alloc_ty_expr(TyExpr {
+                            kind: TyExprKind::Variable(callee_path.clone()),
+                            ty: callee.ty,
+                            span: callee.span,
+                        }),
+                        args: caller_args,
+                    },
+                    ty: callee_return_ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } => {
+                let lhs = self.infer_expr(lhs)?;
+                let rhs = self.infer_expr(rhs)?;
+
+                for operand in [&lhs, &rhs] {
+                    if *operand.ty != TyKind::Bool {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Expected `Bool` but received `{}`",
+                                ty_to_string(operand.ty)
+                            )),
+                            span: operand.span,
+                        });
+                    }
                 }
-            ],
-            self.uint64_ty.clone(),
-        )?;
-        self.register_function(
-            std_int_path,
-            Ident {
-                name: "int_to_string".into(),
-                span: DUMMY_SPAN,
-            },
-            thin_vec![TyFnParam {
-                name: Ident {
-                    name: "value".into(),
-                    span: DUMMY_SPAN
-                },
-                ty: self.uint64_ty.clone(),
-                span: DUMMY_SPAN
-            }],
-            self.string_ty.clone(),
-        )?;
 
-        Ok(())
-    }
+                Ok(TyExpr {
+                    kind: TyExprKind::Binary {
+                        op: BinaryOp::And,
+                        lhs: self.arena.alloc_ty_expr(lhs),
+                        rhs: self.arena.alloc_ty_expr(rhs),
+                    },
+                    ty: TypeId::intern(TyKind::Bool),
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let mut lhs = self.infer_expr(lhs)?;
+                let mut rhs = self.infer_expr(rhs)?;
 
-    fn perform_item_registration_pass(&mut self, package: &Package) -> TypeCheckResult<()> {
-        for module in &package.modules {
-            self.register_items_in_module(None, module)?;
-        }
+                // Integer literals take on the type of the other operand.
+                if matches!(lhs.kind, TyExprKind::Literal(_)) {
+                    self.coerce_integer_literal(&mut lhs, &rhs.ty.clone())?;
+                } else {
+                    self.coerce_integer_literal(&mut rhs, &lhs.ty.clone())?;
+                }
 
-        Ok(())
-    }
+                let is_eq = matches!(op, BinaryOp::Eq | BinaryOp::Ne);
 
-    fn register_items_in_module(
-        &mut self,
-        prefix: Option<&ThinVec<TyPathSegment>>,
-        module: &Module,
-    ) -> TypeCheckResult<()> {
-        for item in &module.items {
-            match item.kind {
-                ItemKind::Use(_) => {}
-                ItemKind::Fn(ref fun) => {
-                    let (typed_params, return_ty) = self.infer_function_decl(&fun.decl)?;
+                // Types implementing the trait of an operator, like `Add` or
+                // `PartialEq`, are operated on with its method, so `a + b`
+                // becomes `a.add(b)`.
+                let method = OPERATOR_METHODS
+                    .iter()
+                    .find(|(method_op, _)| method_op == op)
+                    .map(|(_, method)| *method);
 
-                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                if let (Some(method), TyKind::UserDefined { name, .. }) = (method, &*lhs.ty) {
+                    let method_path = self.methods.get(&(name.clone(), method.into())).cloned();
 
-                    let module_path = TyPath {
-                        segments: path_segments,
-                        span: DUMMY_SPAN,
-                    };
+                    if let Some(method_path) = method_path {
+                        if is_eq {
+                            return self.infer_eq_call(*op, method_path, lhs, rhs, expr.span.get());
+                        }
 
-                    self.register_function(
-                        module_path,
-                        item.name.clone(),
-                        typed_params,
-                        return_ty,
-                    )?;
+                        let lhs_span = lhs.span;
+
+                        return self.infer_method_call(
+                            method_path,
+                            lhs_span,
+                            thin_vec![self.arena.alloc_ty_expr(lhs), self.arena.alloc_ty_expr(rhs)],
+                            expr.span.get(),
+                        );
+                    }
                 }
-                ItemKind::Struct(ref struct_decl) => {
-                    let typed_struct_decl = self.infer_struct_decl(&struct_decl)?;
 
-                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                // Strings can be compared for equality, using `std::string::string_eq`.
+                let is_string_eq = is_eq && lhs.ty == self.string_ty;
+                let is_bool_eq = is_eq && *lhs.ty == TyKind::Bool;
 
-                    let module_path = TyPath {
-                        segments: path_segments,
-                        span: DUMMY_SPAN,
-                    };
+                if !lhs.ty.is_numeric() && !is_string_eq && !is_bool_eq {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Cannot apply `{op}` to `{}`",
+                            ty_to_string(lhs.ty)
+                        )),
+                        span: lhs.span,
+                    });
+                }
 
-                    self.register_struct(module_path, item.name.clone(), typed_struct_decl)?;
+                if lhs.ty != rhs.ty {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `{}` but received `{}`",
+                            ty_to_string(lhs.ty),
+                            ty_to_string(rhs.ty)
+                        )),
+                        span: rhs.span,
+                    });
                 }
-                ItemKind::Union(ref union_decl) => {
-                    let typed_union_decl = self.infer_union_decl(&union_decl)?;
 
-                    let path_segments = prefix.cloned().unwrap_or(ThinVec::new());
+                let ty = if op.is_comparison() {
+                    TypeId::intern(TyKind::Bool)
+                } else {
+                    lhs.ty
+                };
 
-                    let module_path = TyPath {
-                        segments: path_segments,
-                        span: DUMMY_SPAN,
-                    };
+                Ok(TyExpr {
+                    kind: TyExprKind::Binary {
+                        op: *op,
+                        lhs: self.arena.alloc_ty_expr(lhs),
+                        rhs: self.arena.alloc_ty_expr(rhs),
+                    },
+                    ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Cast { expr: inner, ty } => {
+                let inner = self.infer_expr(inner)?;
+                let ty = self.infer_ty(ty)?;
 
-                    self.register_union(module_path, item.name.clone(), typed_union_decl)?;
+                // Booleans can be cast to integers, becoming `0` or `1`.
+                let is_bool_to_int =
+                    *inner.ty == TyKind::Bool && matches!(&*ty, TyKind::Int(_) | TyKind::Uint(_));
+
+                if !(inner.ty.is_numeric() || is_bool_to_int) || !ty.is_numeric() {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Cannot cast `{}` to `{}`",
+                            ty_to_string(inner.ty),
+                            ty_to_string(ty)
+                        )),
+                        span: expr.span.get(),
+                    });
                 }
-                ItemKind::Module(ref module_decl) => match *module_decl.clone() {
-                    ModuleDecl::Loaded(module, InlineModuleDecl::Yes) => {
-                        let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
-                        path_segments.push(TyPathSegment {
-                            ident: item.name.clone(),
-                        });
 
-                        self.register_items_in_module(Some(&path_segments), &module)?;
-                    }
-                    ModuleDecl::Loaded(_, InlineModuleDecl::No) => {}
-                    ModuleDecl::Unloaded => {}
-                },
+                Ok(TyExpr {
+                    kind: TyExprKind::Cast(self.arena.alloc_ty_expr(inner)),
+                    ty,
+                    span: expr.span.get(),
+                })
             }
-        }
+            ExprKind::Struct(struct_expr) => {
+                let path = TyPath {
+                    segments: struct_expr
+                        .path
+                        .segments
+                        .iter()
+                        .map(|segment| TyPathSegment {
+                            ident: segment.ident.clone(),
+                        })
+                        .collect::<ThinVec<_>>(),
+                    span: struct_expr.path.span,
+                };
 
-        Ok(())
-    }
+                let struct_decl = self.ensure_struct_exists(&path)?;
+                let field_decls = struct_decl.0.fields();
 
-    fn type_check_module(
-        &mut self,
-        prefix: Option<&ThinVec<TyPathSegment>>,
-        module: Module,
-    ) -> TypeCheckResult<TyModule> {
-        for item in &module.items {
-            match item.kind {
-                ItemKind::Use(ref use_tree) => {
-                    self.infer_use_tree(use_tree)?;
-                }
-                ItemKind::Fn(ref fun) => {
-                    let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
-                    path_segments.push(TyPathSegment {
-                        ident: item.name.clone(),
+                if struct_decl.newtype().is_some() {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "`{path}` is a tuple struct, so it is constructed with `{path}(...)`"
+                        )),
+                        span: expr.span.get(),
                     });
+                }
 
-                    let path = TyPath {
-                        segments: path_segments,
-                        span: item.name.span,
-                    };
+                let mut fields: ThinVec<Option<&TyExpr<'a>>> =
+                    field_decls.iter().map(|_| None).collect();
 
-                    self.infer_function(&path, *fun.clone())?;
-                }
-                ItemKind::Struct(_) => {}
-                ItemKind::Union(_) => {}
-                ItemKind::Module(ref module_decl) => match *module_decl.clone() {
-                    ModuleDecl::Loaded(module, InlineModuleDecl::Yes) => {
-                        let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
-                        path_segments.push(TyPathSegment {
-                            ident: item.name.clone(),
+                for field in &struct_expr.fields {
+                    let Some(index) = field_decls
+                        .iter()
+                        .position(|field_decl| field_decl.name.as_ref() == Some(&field.name))
+                    else {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "`{path}` has no field named `{}`",
+                                field.name
+                            )),
+                            span: field.name.span,
                         });
+                    };
 
-                        self.type_check_module(Some(&path_segments), module.clone())?;
+                    if fields[index].is_some() {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "The field `{}` was already given",
+                                field.name
+                            )),
+                            span: field.span,
+                        });
                     }
-                    ModuleDecl::Loaded(_, InlineModuleDecl::No) => todo!(),
-                    ModuleDecl::Unloaded => todo!(),
-                },
-            }
-        }
 
-        let mut typed_items = ThinVec::new();
+                    let field_ty = &field_decls[index].ty;
 
-        for item in module.items {
-            typed_items.push(self.infer_item(prefix, item)?);
-        }
+                    let mut value = self.infer_expr(field.expr)?;
 
-        Ok(TyModule { items: typed_items })
-    }
+                    self.coerce_integer_literal(&mut value, field_ty)?;
+                    self.coerce_diverging(&mut value, field_ty);
+                    self.coerce_type_of(&mut value, field_ty);
 
-    fn infer_ty(&mut self, ty: ast::Ty) -> TypeCheckResult<Ty> {
-        Ok(match ty.kind {
-            ast::TyKind::Path(path) => {
-                let (PathSegment { ident }, _) = path.segments.split_last().unwrap();
+                    if value.ty != *field_ty {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Expected `{}` but received `{}`",
+                                ty_to_string(*field_ty),
+                                ty_to_string(value.ty)
+                            )),
+                            span: value.span,
+                        });
+                    }
 
-                match ident.name.as_str() {
-                    "Uint64" => self.uint64_ty.clone(),
-                    _ => Ty::new(TyKind::UserDefined {
-                        module: "std::prelude".into(),
-                        name: ident.to_string().into(),
-                    }),
+                    fields[index] = Some(self.arena.alloc_ty_expr(value));
                 }
-            }
-            ast::TyKind::Fn(fn_ty) => {
-                let (params, return_ty) = self.infer_function_decl(&fn_ty.decl)?;
 
-                Ty::new(TyKind::Fn {
-                    args: params.iter().map(|param| param.ty.clone()).collect(),
-                    return_ty,
+                let fields = fields
+                    .into_iter()
+                    .zip(field_decls)
+                    .map(|(field, field_decl)| {
+                        field.ok_or_else(|| TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Missing the field `{}` of `{path}`",
+                                field_decl
+                                    .name
+                                    .as_ref()
+                                    .map(|name| name.name.as_str())
+                                    .unwrap_or_default()
+                            )),
+                            span: expr.span.get(),
+                        })
+                    })
+                    .collect::<Result<ThinVec<_>, _>>()?;
+
+                let (name, _) = path.segments.split_last().unwrap();
+
+                Ok(TyExpr {
+                    kind: TyExprKind::Struct(fields),
+                    ty: TypeId::intern(TyKind::UserDefined {
+                        module: "std::prelude".into(),
+                        name: name.ident.name.into(),
+                    }),
+                    span: expr.span.get(),
                 })
             }
-        })
-    }
+            ExprKind::Field(field_expr) => {
+                let inner = self.infer_expr(field_expr.expr)?;
+
+                let field = match &*inner.ty {
+                    TyKind::UserDefined { name, .. } => {
+                        self.find_struct(name).and_then(|struct_decl| {
+                            struct_decl
+                                .0
+                                .fields()
+                                .iter()
+                                .enumerate()
+                                .find(|(_, field)| field.name.as_ref() == Some(&field_expr.name))
+                        })
+                    }
+                    _ => None,
+                };
 
-    fn infer_item(
-        &mut self,
-        prefix: Option<&ThinVec<TyPathSegment>>,
-        item: Item,
-    ) -> TypeCheckResult<TyItem> {
-        match item.kind {
-            ItemKind::Use(use_tree) => {
-                self.infer_use_tree(&use_tree)?;
+                let Some((index, field)) = field else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "`{}` has no field named `{}`",
+                            ty_to_string(inner.ty),
+                            field_expr.name
+                        )),
+                        span: field_expr.name.span,
+                    });
+                };
 
-                Ok(TyItem {
-                    kind: TyItemKind::Use,
-                    name: item.name,
+                let ty = field.ty;
+
+                Ok(TyExpr {
+                    kind: TyExprKind::Field {
+                        expr: self.arena.alloc_ty_expr(inner),
+                        index,
+                    },
+                    ty,
+                    span: expr.span.get(),
                 })
             }
-            ItemKind::Fn(fun) => {
-                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
-                path_segments.push(TyPathSegment {
-                    ident: item.name.clone(),
+            ExprKind::MethodCall(method_call) => {
+                let mut receiver = self.infer_expr(method_call.receiver)?;
+
+                // Integers of every width share the methods of `Uint64`, which
+                // they are cast to.
+                if matches!(&*receiver.ty, TyKind::Int(_) | TyKind::Uint(_))
+                    && receiver.ty != self.uint64_ty
+                {
+                    let span = receiver.span;
+
+                    receiver = TyExpr {
+                        kind: TyExprKind::Cast(self.arena.alloc_ty_expr(receiver)),
+                        ty: self.uint64_ty,
+                        span,
+                    };
+                }
+
+                let receiver_name = match &*receiver.ty {
+                    TyKind::UserDefined { name, .. } => Some(name.clone()),
+                    TyKind::Uint(_) | TyKind::Float(_) | TyKind::Bool => {
+                        Some(ty_to_string(receiver.ty).into())
+                    }
+                    _ => None,
+                };
+
+                let method_path = receiver_name.and_then(|name| {
+                    self.methods
+                        .get(&(name, method_call.name.name.into()))
+                        .cloned()
                 });
 
-                let path = TyPath {
-                    segments: path_segments,
-                    span: item.name.span,
+                let Some(method_path) = method_path else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "`{}` has no method named `{}`",
+                            ty_to_string(receiver.ty),
+                            method_call.name
+                        )),
+                        span: method_call.name.span,
+                    });
                 };
 
-                Ok(TyItem {
-                    kind: TyItemKind::Fn(Box::new(self.infer_function(&path, *fun)?)),
-                    name: item.name,
-                })
+                let mut args = thin_vec![self.arena.alloc_ty_expr(receiver)];
+
+                for arg in &method_call.args {
+                    args.push(self.arena.alloc_ty_expr(self.infer_expr(arg)?));
+                }
+
+                self.infer_method_call(method_path, method_call.name.span, args, expr.span.get())
             }
-            ItemKind::Struct(struct_decl) => Ok(TyItem {
-                kind: TyItemKind::Struct(self.infer_struct_decl(&struct_decl)?),
-                name: item.name,
-            }),
-            ItemKind::Union(union_decl) => Ok(TyItem {
-                kind: TyItemKind::Union(self.infer_union_decl(&union_decl)?),
-                name: item.name,
-            }),
-            ItemKind::Module(module_decl) => {
-                let mut path_segments = prefix.cloned().unwrap_or(ThinVec::new());
-                path_segments.push(TyPathSegment {
-                    ident: item.name.clone(),
+            ExprKind::Array(elems) => {
+                let mut elems = elems
+                    .iter()
+                    .map(|elem| self.infer_expr(elem))
+                    .collect::<Result<ThinVec<_>, _>>()?;
+
+                // Integer literals take on the type of the first element that isn't one.
+                let elem_ty = elems
+                    .iter()
+                    .find(|elem| !matches!(elem.kind, TyExprKind::Literal(_)))
+                    .or_else(|| elems.first())
+                    .map(|elem| elem.ty)
+                    .ok_or_else(|| TypeError {
+                        kind: TypeErrorKind::Error(
+                            "Cannot infer the type of an empty array.".to_string(),
+                        ),
+                        span: expr.span.get(),
+                    })?;
+
+                for elem in &mut elems {
+                    self.coerce_integer_literal(elem, &elem_ty)?;
+
+                    if elem.ty != elem_ty {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(format!(
+                                "Expected `{}` but received `{}`",
+                                ty_to_string(elem_ty),
+                                ty_to_string(elem.ty)
+                            )),
+                            span: elem.span,
+                        });
+                    }
+                }
+
+                let ty = TypeId::intern(TyKind::Array {
+                    elem: elem_ty,
+                    len: elems.len() as u64,
                 });
 
-                Ok(TyItem {
-                    kind: TyItemKind::Module(
-                        self.infer_module_decl(Some(&path_segments), &module_decl)?,
+                Ok(TyExpr {
+                    kind: TyExprKind::Array(
+                        elems
+                            .into_iter()
+                            .map(|elem| self.arena.alloc_ty_expr(elem))
+                            .collect(),
                     ),
-                    name: item.name,
+                    ty,
+                    span: expr.span.get(),
                 })
             }
-        }
-    }
+            ExprKind::Index { expr: array, index } => {
+                let array = self.infer_expr(array)?;
+                let mut index = self.infer_expr(index)?;
+
+                // Types implementing `Index` are indexed with their `index`
+                // method, so `a[i]` becomes `a.index(i)`.
+                if let TyKind::UserDefined { name, .. } = &*array.ty {
+                    let index_path = self.methods.get(&(name.clone(), "index".into())).cloned();
+
+                    if let Some(index_path) = index_path {
+                        let array_span = array.span;
+
+                        return self.infer_method_call(
+                            index_path,
+                            array_span,
+                            thin_vec![
+                                self.arena.alloc_ty_expr(array),
+                                self.arena.alloc_ty_expr(index)
+                            ],
+                            expr.span.get(),
+                        );
+                    }
+                }
 
-    fn infer_use_tree(&mut self, use_tree: &UseTree) -> TypeCheckResult<()> {
-        match use_tree.kind {
-            UseTreeKind::Single => {
-                let (PathSegment { ident }, module_path_segments) =
-                    use_tree.prefix.segments.split_last().unwrap();
+                let TyKind::Array { elem, .. } = &*array.ty else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Cannot index into `{}`",
+                            ty_to_string(array.ty)
+                        )),
+                        span: array.span,
+                    });
+                };
 
-                let module_path = TyPath {
-                    segments: module_path_segments
+                let ty = *elem;
+
+                self.coerce_integer_literal(&mut index, &self.uint64_ty.clone())?;
+
+                if !matches!(&*index.ty, TyKind::Uint(_)) {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Array indices must be unsigned integers, but received `{}`",
+                            ty_to_string(index.ty)
+                        )),
+                        span: index.span,
+                    });
+                }
+
+                Ok(TyExpr {
+                    kind: TyExprKind::Index {
+                        expr: self.arena.alloc_ty_expr(array),
+                        index: self.arena.alloc_ty_expr(index),
+                    },
+                    ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Assign { target, value } => {
+                let ExprKind::Variable(target) = &target.kind else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(
+                            "Only variables can be assigned to.".to_string(),
+                        ),
+                        span: target.span.get(),
+                    });
+                };
+
+                let target = TyPath {
+                    segments: target
+                        .segments
                         .iter()
                         .map(|segment| TyPathSegment {
                             ident: segment.ident.clone(),
                         })
                         .collect::<ThinVec<_>>(),
-                    span: DUMMY_SPAN,
+                    span: target.span,
                 };
 
-                let mut fn_path_segments = module_path.segments.clone();
-                fn_path_segments.push(TyPathSegment {
-                    ident: ident.clone(),
-                });
+                if self.fn_params.contains(&target) {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Cannot assign to the parameter `{target}`"
+                        )),
+                        span: target.span,
+                    });
+                }
 
-                let fn_path = TyPath {
-                    segments: fn_path_segments,
-                    span: ident.span,
+                let Some(target_ty) = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&target).cloned())
+                else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Cannot assign to `{target}`, as it is not a local variable"
+                        )),
+                        span: target.span,
+                    });
                 };
 
-                self.ensure_function_exists(&fn_path)?;
-
-                let imported_path = TyPath {
-                    segments: thin_vec![TyPathSegment {
-                        ident: ident.clone()
-                    }],
-                    span: ident.span,
-                };
+                let mut value = self.infer_expr(value)?;
 
-                self.use_map.insert(imported_path, fn_path);
-            }
-        }
+                self.coerce_integer_literal(&mut value, &target_ty)?;
+                self.coerce_diverging(&mut value, &target_ty);
 
-        Ok(())
-    }
+                if value.ty != target_ty {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `{}` but received `{}`",
+                            ty_to_string(target_ty),
+                            ty_to_string(value.ty)
+                        )),
+                        span: value.span,
+                    });
+                }
 
-    fn infer_function(&mut self, path: &TyPath, fun: Fn) -> TypeCheckResult<TyFn> {
-        let (_, return_ty) = self.ensure_function_exists(&path)?;
+                Ok(TyExpr {
+                    kind: TyExprKind::Assign {
+                        target,
+                        value: self.arena.alloc_ty_expr(value),
+                    },
+                    ty: self.unit_ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::While { cond, body } => {
+                let cond = self.infer_expr(cond)?;
 
-        let params = self.infer_function_params(&fun.decl.params)?;
+                if *cond.ty != TyKind::Bool {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `Bool` but received `{}`",
+                            ty_to_string(cond.ty)
+                        )),
+                        span: cond.span,
+                    });
+                }
 
-        self.scopes
-            .push(HashMap::from_iter(params.clone().into_iter().map(
-                |param| {
-                    (
-                        TyPath {
-                            segments: thin_vec![TyPathSegment { ident: param.name }],
-                            span: param.span,
-                        },
-                        param.ty,
-                    )
-                },
-            )));
+                let body = self.infer_block(body)?;
 
-        let body = fun
-            .body
-            .into_iter()
-            .map(|stmt| self.infer_stmt(stmt))
-            .collect::<Result<ThinVec<_>, _>>()?;
+                // The value of the last statement in a loop body is discarded.
+                if let Some(last_expr) = last_expr(&body) {
+                    self.warn_if_unused_result(last_expr);
+                }
 
-        if let Some(last_stmt) = body.last() {
-            let ty = match &last_stmt.kind {
-                TyStmtKind::Local(_) => todo!(),
-                TyStmtKind::Expr(expr) => &expr.ty,
-                TyStmtKind::Item(_) => todo!(),
-            };
+                Ok(TyExpr {
+                    kind: TyExprKind::While {
+                        cond: self.arena.alloc_ty_expr(cond),
+                        body,
+                    },
+                    ty: self.unit_ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::Macro(macro_call) => Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "The macro `{}!` was not expanded.",
+                    macro_call.name
+                )),
+                span: expr.span.get(),
+            }),
+            ExprKind::Asm(lines) => {
+                self.require_unsafe(|| "Inline assembly".to_string(), expr.span.get())?;
 
-            if *ty != return_ty {
-                return Err(TypeError {
-                    kind: TypeErrorKind::Error(format!(
-                        "Expected `{path}` to return {} but got {}",
-                        ty_to_string(return_ty),
-                        ty_to_string(ty.clone())
-                    )),
-                    span: last_stmt.span,
-                });
+                Ok(TyExpr {
+                    kind: TyExprKind::Asm(lines.clone()),
+                    ty: self.unit_ty,
+                    span: expr.span.get(),
+                })
             }
-        }
+            ExprKind::Unsafe(body) => {
+                let outer_in_unsafe = std::mem::replace(&mut self.in_unsafe, true);
 
-        let ty_fn = TyFn {
-            path: path.clone(),
-            params,
-            return_ty,
-            body,
-        };
+                let body = self.infer_block(body);
 
-        self.scopes.pop();
+                self.in_unsafe = outer_in_unsafe;
 
-        Ok(ty_fn)
-    }
+                let body = body?;
+                let ty = self.block_ty(&body);
 
-    fn infer_function_decl(
-        &mut self,
-        function_decl: &FnDecl,
-    ) -> TypeCheckResult<(ThinVec<TyFnParam>, Ty)> {
-        let params = self.infer_function_params(&function_decl.params)?;
+                Ok(TyExpr {
+                    kind: TyExprKind::Block(body),
+                    ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::TypeOf(inner) => {
+                let name = SmolStr::from(ty_to_string(self.infer_expr(inner)?.ty));
 
-        let return_ty = match function_decl.return_ty {
-            FnReturnTy::Unit => self.unit_ty.clone(),
-            FnReturnTy::Ty(ref ty) => self.infer_ty(*ty.clone())?,
-        };
+                Ok(TyExpr {
+                    kind: TyExprKind::TypeOf(name.clone()),
+                    ty: TypeId::intern(TyKind::UserDefined {
+                        module: "std::meta".into(),
+                        name,
+                    }),
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let cond = self.infer_expr(cond)?;
+
+                if *cond.ty != TyKind::Bool {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `Bool` but received `{}`",
+                            ty_to_string(cond.ty)
+                        )),
+                        span: cond.span,
+                    });
+                }
 
-        Ok((params, return_ty))
-    }
+                let mut then_branch = self.infer_block(then_branch)?;
 
-    fn infer_function_params(
-        &mut self,
-        params: &ThinVec<FnParam>,
-    ) -> TypeCheckResult<ThinVec<TyFnParam>> {
-        params
-            .iter()
-            .map(|param| {
-                Ok(TyFnParam {
-                    name: param.name.clone(),
-                    ty: self.infer_ty(*param.ty.clone())?,
-                    span: param.span,
-                })
-            })
-            .collect::<Result<ThinVec<_>, _>>()
-    }
+                let Some(else_branch) = else_branch else {
+                    return Ok(TyExpr {
+                        kind: TyExprKind::If {
+                            cond: self.arena.alloc_ty_expr(cond),
+                            then_branch,
+                            else_branch: None,
+                        },
+                        ty: self.unit_ty,
+                        span: expr.span.get(),
+                    });
+                };
 
-    fn infer_struct_decl(&self, struct_decl: &StructDecl) -> TypeCheckResult<TyStructDecl> {
-        Ok(TyStructDecl(self.infer_variant_data(&struct_decl.0)?))
-    }
+                let mut else_branch = self.infer_block(else_branch)?;
 
-    fn infer_union_decl(&self, union_decl: &UnionDecl) -> TypeCheckResult<TyUnionDecl> {
-        let ty_variants = union_decl
-            .variants
-            .iter()
-            .map(|variant| {
-                Ok(TyVariant {
-                    name: variant.name.clone(),
-                    data: self.infer_variant_data(&variant.data)?,
-                    span: variant.span,
-                })
-            })
-            .collect::<Result<ThinVec<_>, _>>()?;
+                // Integer literals and diverging expressions take on the type
+                // of the other branch.
+                let else_ty = self.block_ty(&else_branch);
+                if let Some(value) = last_expr_mut(&mut then_branch) {
+                    self.coerce_allocated(value, |typer, value| {
+                        typer.coerce_integer_literal(value, &else_ty)?;
+                        typer.coerce_diverging(value, &else_ty);
 
-        Ok(TyUnionDecl {
-            variants: ty_variants,
-        })
-    }
+                        Ok(())
+                    })?;
+                }
+                let then_ty = self.block_ty(&then_branch);
+                if let Some(value) = last_expr_mut(&mut else_branch) {
+                    self.coerce_allocated(value, |typer, value| {
+                        typer.coerce_integer_literal(value, &then_ty)?;
+                        typer.coerce_diverging(value, &then_ty);
+
+                        Ok(())
+                    })?;
+                }
+                let else_ty = self.block_ty(&else_branch);
 
-    fn infer_module_decl(
-        &mut self,
-        prefix: Option<&ThinVec<TyPathSegment>>,
-        module_decl: &ModuleDecl,
-    ) -> TypeCheckResult<TyModule> {
-        match &module_decl {
-            ModuleDecl::Loaded(module, InlineModuleDecl::Yes) => Ok(TyModule {
-                items: module
-                    .items
-                    .iter()
-                    .map(|item| self.infer_item(prefix, item.clone()))
-                    .collect::<Result<ThinVec<_>, _>>()?,
-            }),
-            ModuleDecl::Loaded(_, InlineModuleDecl::No) => Err(TypeError {
-                kind: TypeErrorKind::Error("Only inline modules are supported.".into()),
-                span: DUMMY_SPAN,
-            }),
-            ModuleDecl::Unloaded => Err(TypeError {
-                kind: TypeErrorKind::Error("Only inline modules are supported.".into()),
-                span: DUMMY_SPAN,
-            }),
-        }
-    }
+                if then_ty != else_ty {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected the `else` branch to produce `{}` but received `{}`",
+                            ty_to_string(then_ty),
+                            ty_to_string(else_ty)
+                        )),
+                        span: expr.span.get(),
+                    });
+                }
 
-    fn infer_variant_data(&self, variant_data: &VariantData) -> TypeCheckResult<TyVariantData> {
-        Ok(match &variant_data {
-            VariantData::Struct(fields) => TyVariantData::Struct(
-                fields
-                    .into_iter()
-                    .map(|field| TyFieldDecl {
-                        name: field.name.clone(),
-                        ty: match &field.ty.kind {
-                            ast::TyKind::Path(path) => {
-                                let (PathSegment { ident }, _) =
-                                    path.segments.split_last().unwrap();
-
-                                ident.clone()
-                            }
-                            ast::TyKind::Fn(fn_ty) => {
-                                todo!()
-                            }
-                        },
-                        span: field.span,
-                    })
-                    .collect::<ThinVec<_>>(),
-            ),
-            VariantData::Tuple(_) => todo!(),
-            VariantData::Unit => TyVariantData::Unit,
-        })
-    }
+                Ok(TyExpr {
+                    kind: TyExprKind::If {
+                        cond: self.arena.alloc_ty_expr(cond),
+                        then_branch,
+                        else_branch: Some(else_branch),
+                    },
+                    ty: then_ty,
+                    span: expr.span.get(),
+                })
+            }
+            ExprKind::For(for_expr) => {
+                let ForExpr {
+                    binding,
+                    start,
+                    end,
+                    body,
+                } = &**for_expr;
+
+                let mut start = self.infer_expr(start)?;
+                let mut end = self.infer_expr(end)?;
+
+                // Integer literals take on the type of the other bound.
+                if matches!(start.kind, TyExprKind::Literal(_)) {
+                    self.coerce_integer_literal(&mut start, &end.ty.clone())?;
+                } else {
+                    self.coerce_integer_literal(&mut end, &start.ty.clone())?;
+                }
 
-    fn infer_stmt(&mut self, stmt: Stmt) -> TypeCheckResult<TyStmt> {
-        Ok(TyStmt {
-            kind: match stmt.kind {
-                StmtKind::Local(local) => TyStmtKind::Local(Box::new(self.infer_local(*local)?)),
-                StmtKind::Expr(expr) => TyStmtKind::Expr(Box::new(self.infer_expr(*expr)?)),
-                StmtKind::Item(_) => todo!(),
-            },
-            span: stmt.span,
-        })
-    }
+                if !matches!(&*start.ty, TyKind::Int(_) | TyKind::Uint(_)) {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected an integer range but received `{}`",
+                            ty_to_string(start.ty)
+                        )),
+                        span: start.span,
+                    });
+                }
 
-    fn infer_local(&mut self, local: Local) -> TypeCheckResult<TyLocal> {
-        let ty = match local.kind.init() {
-            Some(init) => self.infer_expr(init.clone())?.ty,
-            None => Ty::new(TyKind::UserDefined {
-                module: "?".into(),
-                name: "?".into(),
-            }),
-        };
+                if start.ty != end.ty {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected `{}` but received `{}`",
+                            ty_to_string(start.ty),
+                            ty_to_string(end.ty)
+                        )),
+                        span: end.span,
+                    });
+                }
 
-        if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(
-                TyPath {
+                let binding_path = TyPath {
                     segments: thin_vec![TyPathSegment {
-                        ident: local.name.clone()
+                        ident: binding.clone()
                     }],
-                    span: local.span,
-                },
-                ty.clone(),
-            );
-        }
+                    span: binding.span,
+                };
 
-        Ok(TyLocal {
-            kind: match local.kind {
-                LocalKind::Decl => TyLocalKind::Decl,
-                LocalKind::Init(init) => TyLocalKind::Init(Box::new(self.infer_expr(*init)?)),
-            },
-            name: local.name,
-            ty: Some(ty),
-            span: local.span,
-        })
-    }
+                // The binding shadows any parameter of the same name.
+                self.fn_params.remove(&binding_path);
 
-    fn infer_expr(&self, expr: Expr) -> TypeCheckResult<TyExpr> {
-        match expr.kind {
-            ExprKind::Literal(literal) => match literal.kind {
-                LiteralKind::String => self.infer_string(literal, expr.span),
-                LiteralKind::Integer => self.infer_integer(literal, expr.span),
-            },
-            ExprKind::Variable(path) => {
-                let path = TyPath {
-                    segments: path
-                        .segments
-                        .into_iter()
-                        .map(|segment| TyPathSegment {
-                            ident: segment.ident,
-                        })
-                        .collect::<ThinVec<_>>(),
-                    span: path.span,
-                };
+                let mut scope = self.scopes.last().cloned().unwrap_or_default();
+                scope.insert(binding_path, start.ty);
+                self.scopes.push(scope);
 
-                // Check the variable's path against the items brought into scope by `use`.
-                // If we find an item that's been brought into scope we can use that as the alias.
-                let path = if let Some(use_path) = self.use_map.get(&path) {
-                    use_path.clone()
-                } else {
-                    path
-                };
+                let body = body
+                    .iter()
+                    .map(|stmt| self.infer_stmt(stmt))
+                    .collect::<Result<ThinVec<_>, _>>();
 
-                let ty = self
-                    .scopes
-                    .last()
-                    .and_then(|scope| scope.get(&path).cloned());
+                self.scopes.pop();
 
-                let ty = ty.or_else(|| {
-                    let function = self.ensure_function_exists(&path).ok();
+                let body = body?;
 
-                    function.map(|(params, return_ty)| {
-                        Ty::new(TyKind::Fn {
-                            args: params.iter().map(|param| param.ty.clone()).collect(),
-                            return_ty,
-                        })
-                    })
-                });
+                self.warn_unused_results(&body);
+                self.warn_unreachable_code(&body);
+
+                // The value of the last statement in a loop body is discarded.
+                if let Some(last_expr) = last_expr(&body) {
+                    self.warn_if_unused_result(last_expr);
+                }
 
                 Ok(TyExpr {
-                    kind: TyExprKind::Variable(path),
-                    ty: ty.unwrap_or_else(|| {
-                        Ty::new(TyKind::UserDefined {
-                            module: "?".into(),
-                            name: "?".into(),
-                        })
-                    }),
-                    span: expr.span,
+                    kind: TyExprKind::For(Box::new(TyForExpr {
+                        binding: binding.clone(),
+                        start,
+                        end,
+                        body,
+                    })),
+                    ty: self.unit_ty,
+                    span: expr.span.get(),
                 })
             }
-            ExprKind::Call { fun, args } => {
-                let callee = self.infer_expr(*fun.clone())?;
-
-                let callee_path = match &callee.kind {
-                    TyExprKind::Variable(path) => Ok(path),
-                    _ => Err(TypeError {
-                        kind: TypeErrorKind::Error("Not a function.".to_string()),
-                        span: callee.span,
-                    }),
-                }?;
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee = self.infer_expr(scrutinee)?;
 
-                // Check the callee's path against the items brought into scope by `use`.
-                // If we find an item that's been brought into scope we can use that as the alias.
-                let callee_path = if let Some(use_path) = self.use_map.get(&callee_path) {
-                    use_path
-                } else {
-                    callee_path
-                };
-
-                let callee_from_params = self
-                    .scopes
-                    .last()
-                    .and_then(|scope| scope.get(&callee_path))
-                    .and_then(|ty| match &*ty.clone() {
-                        TyKind::Fn { args, return_ty } => Some((
-                            args.iter()
-                                .map(|ty| TyFnParam {
-                                    name: Ident {
-                                        name: "".into(),
-                                        span: DUMMY_SPAN,
-                                    },
-                                    ty: ty.clone(),
-                                    span: DUMMY_SPAN,
-                                })
-                                .collect::<ThinVec<_>>(),
-                            return_ty.clone(),
+                if !matches!(&*scrutinee.ty, TyKind::Int(_) | TyKind::Uint(_)) {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected an integer to `{}` on but received `{}`",
+                            keywords::MATCH,
+                            ty_to_string(scrutinee.ty)
                         )),
-                        _ => None,
+                        span: scrutinee.span,
                     });
+                }
 
-                let (callee_params, callee_return_ty) = if let Some(callee) = callee_from_params {
-                    callee
-                } else {
-                    self.ensure_function_exists(callee_path)
-                        .map(|(params, return_ty)| (params.clone(), return_ty))?
-                };
+                let mut seen_values = HashSet::new();
+                let mut has_wild = false;
 
-                let caller_args = args
-                    .into_iter()
-                    .map(|expr| self.infer_expr(*expr))
-                    .map(|result| result.map(Box::new))
-                    .collect::<Result<ThinVec<_>, _>>()?;
+                let mut typed_arms = ThinVec::new();
+
+                for arm in arms {
+                    if has_wild {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "This arm is unreachable, as an earlier `_` arm matches everything"
+                                    .to_string(),
+                            ),
+                            span: arm.pat.span,
+                        });
+                    }
+
+                    let pat_kind = match &arm.pat.kind {
+                        PatKind::Wild => {
+                            has_wild = true;
+
+                            TyPatKind::Wild
+                        }
+                        PatKind::Literal(literal) => {
+                            let mut value = self.infer_integer(literal, arm.pat.span)?;
+                            self.coerce_integer_literal(&mut value, &scrutinee.ty)?;
+
+                            let TyExprKind::Literal(literal) = value.kind else {
+                                unreachable!("Integer patterns are always literals.");
+                            };
+
+                            let TyLiteralKind::Integer(integer) = &literal.kind else {
+                                unreachable!("Integer patterns are always integer literals.");
+                            };
+
+                            if !seen_values.insert(integer.value()) {
+                                return Err(TypeError {
+                                    kind: TypeErrorKind::Error(format!(
+                                        "This arm overlaps an earlier arm matching `{}`",
+                                        integer.value()
+                                    )),
+                                    span: arm.pat.span,
+                                });
+                            }
+
+                            TyPatKind::Literal(literal)
+                        }
+                    };
 
-                let callee_arity = callee_params.len();
-                let caller_arity = caller_args.len();
+                    typed_arms.push(TyMatchArm {
+                        pat: TyPat {
+                            kind: pat_kind,
+                            span: arm.pat.span,
+                        },
+                        body: self.infer_block(&arm.body)?,
+                        span: arm.span,
+                    });
+                }
 
-                if callee_arity != caller_arity {
+                if !has_wild {
                     return Err(TypeError {
-                        kind: TypeErrorKind::Error(format!("`{callee_path}` was called with {caller_arity} arguments when it expected {callee_arity}")),
-                        span: callee_path.span
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected a `_` arm, as a `{}` on integers must be exhaustive",
+                            keywords::MATCH
+                        )),
+                        span: expr.span.get(),
                     });
                 }
 
-                for (param, arg) in callee_params.into_iter().zip(&caller_args) {
-                    if param.ty != arg.ty {
+                // Arms that diverge don't produce a value, so the others decide
+                // the type of the `match`, preferring one that isn't an integer
+                // literal.
+                let is_diverging =
+                    |arm: &TyMatchArm<'a>| last_expr(&arm.body).is_some_and(TyExpr::diverges);
+                let ty = typed_arms
+                    .iter()
+                    .filter(|arm| !is_diverging(arm))
+                    .map(|arm| {
+                        let is_literal = matches!(
+                            last_expr(&arm.body).map(|expr| &expr.kind),
+                            Some(TyExprKind::Literal(_))
+                        );
+
+                        (is_literal, self.block_ty(&arm.body))
+                    })
+                    .min_by_key(|(is_literal, _)| *is_literal)
+                    .map_or_else(|| self.unit_ty, |(_, ty)| ty);
+
+                for arm in typed_arms.iter_mut().filter(|arm| !is_diverging(arm)) {
+                    if let Some(value) = last_expr_mut(&mut arm.body) {
+                        self.coerce_allocated(value, |typer, value| {
+                            typer.coerce_integer_literal(value, &ty)
+                        })?;
+                    }
+
+                    let arm_ty = self.block_ty(&arm.body);
+
+                    if arm_ty != ty {
                         return Err(TypeError {
                             kind: TypeErrorKind::Error(format!(
-                                "Expected `{}` but received `{}`",
-                                ty_to_string(param.ty),
-                                ty_to_string(arg.ty.clone())
+                                "Expected this arm to produce `{}` but received `{}`",
+                                ty_to_string(ty),
+                                ty_to_string(arm_ty)
                             )),
-                            span: arg.span,
+                            span: arm.span,
                         });
                     }
                 }
 
                 Ok(TyExpr {
-                    kind: TyExprKind::Call {
-                        fun: Box::new(TyExpr {
-                            kind: TyExprKind::Variable(callee_path.clone()),
-                            ty: callee.ty,
-                            span: callee.span,
-                        }),
-                        args: caller_args,
+                    kind: TyExprKind::Match {
+                        scrutinee: self.arena.alloc_ty_expr(scrutinee),
+                        arms: typed_arms,
                     },
-                    ty: callee_return_ty.clone(),
-                    span: expr.span,
+                    ty,
+                    span: expr.span.get(),
                 })
             }
-            ExprKind::Struct(struct_expr) => {
-                let path = TyPath {
-                    segments: struct_expr
-                        .path
-                        .segments
-                        .into_iter()
-                        .map(|segment| TyPathSegment {
-                            ident: segment.ident,
-                        })
-                        .collect::<ThinVec<_>>(),
-                    span: struct_expr.path.span,
+            ExprKind::Comptime(body) => {
+                let body = self.infer_block(body)?;
+                let ty = self.block_ty(&body);
+
+                let literal = match self.comptime_evaluator().eval_block(&body)? {
+                    ComptimeValue::Int(value) => integer_literal(value, &ty),
+                    _ => None,
                 };
 
-                let _struct_decl = self.ensure_struct_exists(&path)?;
+                let Some(literal) = literal else {
+                    return Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Expected a `{}` block to produce an integer but received `{}`",
+                            keywords::COMPTIME,
+                            ty_to_string(ty)
+                        )),
+                        span: expr.span.get(),
+                    });
+                };
 
-                Err(TypeError {
-                    kind: TypeErrorKind::Error(
-                        "Struct expressions are not yet supported.".to_string(),
-                    ),
-                    span: expr.span,
+                Ok(TyExpr {
+                    kind: TyExprKind::Literal(TyLiteral {
+                        kind: TyLiteralKind::Integer(literal),
+                        span: expr.span.get(),
+                    }),
+                    ty,
+                    span: expr.span.get(),
                 })
             }
         }
     }
 
-    fn infer_string(&self, literal: Literal, span: Span) -> TypeCheckResult<TyExpr> {
+    /// Checks the arguments of a call against the parameters of the callee.
+    fn check_call_args(
+        &self,
+        callee_path: &TyPath,
+        callee_params: ThinVec<TyFnParam>,
+        caller_args: &mut [&'a TyExpr<'a>],
+    ) -> TypeCheckResult<()> {
+        let callee_arity = callee_params.len();
+        let caller_arity = caller_args.len();
+
+        if callee_arity != caller_arity {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!("`{callee_path}` was called with {caller_arity} arguments when it expected {callee_arity}")),
+                span: callee_path.span
+            });
+        }
+
+        for (param, arg) in callee_params.into_iter().zip(caller_args) {
+            self.coerce_allocated(arg, |typer, arg| {
+                typer.coerce_integer_literal(arg, &param.ty)?;
+                typer.coerce_type_of(arg, &param.ty);
+                typer.coerce_diverging(arg, &param.ty);
+
+                Ok(())
+            })?;
+
+            if param.ty != arg.ty {
+                return Err(TypeError {
+                    kind: TypeErrorKind::Error(format!(
+                        "Expected `{}` but received `{}`",
+                        ty_to_string(param.ty),
+                        ty_to_string(arg.ty)
+                    )),
+                    span: arg.span,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Type checks a block, which can see the enclosing locals but whose own
+    /// bindings go out of scope at its end.
+    fn infer_block(&mut self, stmts: &[Stmt]) -> TypeCheckResult<ThinVec<TyStmt<'a>>> {
+        let scope = self.scopes.last().cloned().unwrap_or_default();
+        self.scopes.push(scope);
+
+        let stmts = stmts
+            .iter()
+            .map(|stmt| self.infer_stmt(stmt))
+            .collect::<Result<ThinVec<_>, _>>();
+
+        self.scopes.pop();
+
+        let stmts = stmts?;
+
+        self.warn_unused_results(&stmts);
+        self.warn_unreachable_code(&stmts);
+
+        Ok(stmts)
+    }
+
+    /// Returns the type of the value produced by a block, which is that of its
+    /// trailing expression.
+    fn block_ty(&self, stmts: &[TyStmt]) -> TypeId {
+        match stmts.last().map(|stmt| &stmt.kind) {
+            Some(TyStmtKind::Expr(expr)) => expr.ty,
+            _ => self.unit_ty,
+        }
+    }
+
+    /// Applies the given coercions to an expression that has already been
+    /// allocated in the arena, by allocating the coerced expression in its
+    /// place.
+    ///
+    /// Only the expressions that a coercion can change are copied.
+    fn coerce_allocated(
+        &self,
+        expr: &mut &'a TyExpr<'a>,
+        coerce: impl FnOnce(&Self, &mut TyExpr<'a>) -> TypeCheckResult<()>,
+    ) -> TypeCheckResult<()> {
+        let is_coercible = expr.diverges()
+            || matches!(
+                expr.kind,
+                TyExprKind::Literal(TyLiteral {
+                    kind: TyLiteralKind::Integer(_),
+                    ..
+                }) | TyExprKind::Array(_)
+                    | TyExprKind::TypeOf(_)
+            );
+
+        if !is_coercible {
+            return Ok(());
+        }
+
+        let mut coerced = (*expr).clone();
+        coerce(self, &mut coerced)?;
+        *expr = self.arena.alloc_ty_expr(coerced);
+
+        Ok(())
+    }
+
+    /// Coerces a `typeof` expression to the `String` holding the type's name,
+    /// where a `String` is expected.
+    fn coerce_type_of(&self, expr: &mut TyExpr<'a>, expected_ty: &TypeId) {
+        if matches!(expr.kind, TyExprKind::TypeOf(_)) && *expected_ty == self.string_ty {
+            expr.ty = self.string_ty;
+        }
+    }
+
+    /// Coerces a diverging expression to the expected type.
+    ///
+    /// A diverging expression never produces a value, so it may be used
+    /// anywhere a value of any type is expected.
+    fn coerce_diverging(&self, expr: &mut TyExpr<'a>, expected_ty: &TypeId) {
+        if expr.diverges() {
+            expr.ty = *expected_ty;
+        }
+    }
+
+    /// Coerces an integer literal to the expected integer type.
+    ///
+    /// Integer literals are typed as `Uint64` by default, but may be used
+    /// anywhere another integer type is expected.
+    fn coerce_integer_literal(
+        &self,
+        expr: &mut TyExpr<'a>,
+        expected_ty: &TypeId,
+    ) -> TypeCheckResult<()> {
+        // The integer literals in an array literal are coerced to the expected element type.
+        if let (TyExprKind::Array(elems), TyKind::Array { elem: elem_ty, len }) =
+            (&mut expr.kind, &**expected_ty)
+        {
+            if elems.len() as u64 == *len {
+                for elem in elems.iter_mut() {
+                    self.coerce_allocated(elem, |typer, elem| {
+                        typer.coerce_integer_literal(elem, elem_ty)
+                    })?;
+                }
+
+                if elems.iter().all(|elem| elem.ty == *elem_ty) {
+                    expr.ty = *expected_ty;
+                }
+            }
+
+            return Ok(());
+        }
+
+        let TyExprKind::Literal(TyLiteral {
+            kind: TyLiteralKind::Integer(literal),
+            ..
+        }) = &mut expr.kind
+        else {
+            return Ok(());
+        };
+
+        let value = match literal {
+            TyIntegerLiteral::Signed(value, _) => *value,
+            TyIntegerLiteral::Unsigned(value, _) => *value as i128,
+        };
+
+        let Some(coerced_literal) = integer_literal(value, expected_ty) else {
+            return Ok(());
+        };
+
+        let fits = match &**expected_ty {
+            TyKind::Int(int_ty) => {
+                let bits = int_ty.bit_width();
+                (-(1i128 << (bits - 1))..(1i128 << (bits - 1))).contains(&value)
+            }
+            TyKind::Uint(uint_ty) => (0..(1i128 << uint_ty.bit_width())).contains(&value),
+            _ => unreachable!(),
+        };
+
+        if !fits {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Integer literal `{value}` does not fit in {}",
+                    ty_to_string(*expected_ty)
+                )),
+                span: expr.span,
+            });
+        }
+
+        *literal = coerced_literal;
+
+        expr.ty = *expected_ty;
+
+        Ok(())
+    }
+
+    /// Returns a call to the method at `method_path`, with the receiver as the
+    /// first of the already type checked `args`.
+    fn infer_method_call(
+        &mut self,
+        method_path: TyPath,
+        name_span: Span,
+        mut args: ThinVec<&'a TyExpr<'a>>,
+        span: Span,
+    ) -> TypeCheckResult<TyExpr<'a>> {
+        let (params, return_ty) = self
+            .ensure_function_exists(&method_path)
+            .map(|(params, return_ty)| (params.clone(), return_ty))?;
+
+        let fn_ty = TypeId::intern(TyKind::Fn {
+            args: params.iter().map(|param| param.ty).collect(),
+            return_ty,
+            calling_convention: self.calling_convention_of(&method_path),
+        });
+
+        self.check_call_args(&method_path, params, &mut args)?;
+
+        self.warn_if_deprecated(&method_path, name_span);
+
+        Ok(TyExpr {
+            kind: TyExprKind::Call {
+                fun: self.arena.alloc_ty_expr(TyExpr {
+                    kind: TyExprKind::Variable(method_path),
+                    ty: fn_ty,
+                    span: name_span,
+                }),
+                args,
+            },
+            ty: return_ty,
+            span,
+        })
+    }
+
+    /// Desugars `lhs == rhs`, or `lhs != rhs`, on a type implementing
+    /// `PartialEq` into a call to its `eq` method at `eq_path`.
+    fn infer_eq_call(
+        &mut self,
+        op: BinaryOp,
+        eq_path: TyPath,
+        lhs: TyExpr<'a>,
+        rhs: TyExpr<'a>,
+        span: Span,
+    ) -> TypeCheckResult<TyExpr<'a>> {
+        let lhs_span = lhs.span;
+        let eq = self.infer_method_call(
+            eq_path,
+            lhs_span,
+            thin_vec![self.arena.alloc_ty_expr(lhs), self.arena.alloc_ty_expr(rhs)],
+            span,
+        )?;
+
+        if op == BinaryOp::Eq {
+            return Ok(eq);
+        }
+
+        // There is no `!` operator to negate the result with, so `a != b`
+        // becomes `a.eq(b) == false`, with `false` as a `Bool`-typed `0`.
+        let bool_ty = TypeId::intern(TyKind::Bool);
+
+        Ok(TyExpr {
+            kind: TyExprKind::Binary {
+                op: BinaryOp::Eq,
+                lhs: self.arena.alloc_ty_expr(eq),
+                rhs: self.arena.alloc_ty_expr(TyExpr {
+                    kind: TyExprKind::Literal(TyLiteral {
+                        kind: TyLiteralKind::Integer(TyIntegerLiteral::Unsigned(0, TyUint::Uint8)),
+                        span,
+                    }),
+                    ty: bool_ty,
+                    span,
+                }),
+            },
+            ty: bool_ty,
+            span,
+        })
+    }
+
+    /// Returns a string literal holding the location of the given span in the
+    /// function being type checked, as `path:line:column`.
+    fn location_literal(&self, span: Span) -> TyExpr<'a> {
+        let fn_path = self
+            .current_fn
+            .iter()
+            .flat_map(|path| &path.segments)
+            .map(|segment| segment.ident.name.into())
+            .collect::<Vec<SmolStr>>();
+
+        let location = self
+            .source_map
+            .location(&fn_path, span)
+            .unwrap_or_else(|| "<unknown>".to_string());
+
+        TyExpr {
+            kind: TyExprKind::Literal(TyLiteral {
+                kind: TyLiteralKind::String(format!("\"{location}\"").into()),
+                span,
+            }),
+            span,
+            ty: self.string_ty,
+        }
+    }
+
+    fn infer_string(&self, literal: &Literal, span: Span) -> TypeCheckResult<TyExpr<'a>> {
+        Ok(TyExpr {
+            kind: TyExprKind::Literal(TyLiteral {
+                kind: TyLiteralKind::String(literal.value.clone()),
+                span,
+            }),
+            span,
+            ty: self.string_ty,
+        })
+    }
+
+    fn infer_float(&self, literal: &Literal, span: Span) -> TypeCheckResult<TyExpr<'a>> {
+        let value: f64 = literal.value.parse().expect("Failed to parse float.");
+
         Ok(TyExpr {
             kind: TyExprKind::Literal(TyLiteral {
-                kind: TyLiteralKind::String(literal.value),
+                kind: TyLiteralKind::Float(value),
                 span,
             }),
             span,
-            ty: self.string_ty.clone(),
+            ty: self.float64_ty,
         })
     }
 
-    fn infer_integer(&self, literal: Literal, span: Span) -> TypeCheckResult<TyExpr> {
-        let value: u128 = literal.value.parse().expect("Failed to parse integer.");
+    fn infer_integer(&self, literal: &Literal, span: Span) -> TypeCheckResult<TyExpr<'a>> {
+        let value: u128 = literal.value.parse().map_err(|_| TypeError {
+            kind: TypeErrorKind::Error(format!(
+                "Integer literal `{}` is too large.",
+                literal.value
+            )),
+            span,
+        })?;
 
         Ok(TyExpr {
             kind: TyExprKind::Literal(TyLiteral {
@@ -986,11 +4612,217 @@ impl Typer {
                 span,
             }),
             span,
-            ty: self.uint64_ty.clone(),
+            ty: self.uint64_ty,
         })
     }
 }
 
+/// Returns the integer literal of the given type holding the value, if the
+/// type is an integer type.
+fn integer_literal(value: i128, ty: &TypeId) -> Option<TyIntegerLiteral> {
+    match &**ty {
+        TyKind::Int(int_ty) => {
+            let int_ty = match int_ty {
+                IntTy::I8 => TyInt::Int8,
+                IntTy::I16 => TyInt::Int16,
+                IntTy::I32 => TyInt::Int32,
+                IntTy::I64 => TyInt::Int64,
+            };
+
+            Some(TyIntegerLiteral::Signed(value, int_ty))
+        }
+        TyKind::Uint(uint_ty) => {
+            let uint_ty = match uint_ty {
+                UintTy::U8 => TyUint::Uint8,
+                UintTy::U16 => TyUint::Uint16,
+                UintTy::U32 => TyUint::Uint32,
+                UintTy::U64 => TyUint::Uint64,
+            };
+
+            Some(TyIntegerLiteral::Unsigned(value as u128, uint_ty))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the value of the `message` argument of the given attribute, if it has one.
+/// Returns the parameters and return type of the function that constructs the
+/// tuple struct with the given name and wrapped type.
+fn tuple_struct_constructor_signature(
+    name: &Ident,
+    inner: &TypeId,
+) -> (ThinVec<TyFnParam>, TypeId) {
+    let params = thin_vec![TyFnParam {
+        name: Ident {
+            name: "value".into(),
+            span: DUMMY_SPAN,
+        },
+        ty: *inner,
+        span: name.span,
+    }];
+
+    let return_ty = TypeId::intern(TyKind::UserDefined {
+        module: "std::prelude".into(),
+        name: name.name.into(),
+    });
+
+    (params, return_ty)
+}
+
+/// Pushes the typed item onto `items`, followed by its constructor if it is a
+/// tuple struct.
+///
+/// The constructor wraps its argument in the tuple struct, which the backend
+/// represents as the wrapped type itself.
+fn push_item<'a>(
+    arena: &'a AstArena<'a>,
+    items: &mut ThinVec<TyItem<'a>>,
+    prefix: Option<&ThinVec<TyPathSegment>>,
+    item: TyItem<'a>,
+) {
+    let constructor = match &item.kind {
+        TyItemKind::Struct(struct_decl) => struct_decl.newtype().map(|inner| {
+            let (params, return_ty) = tuple_struct_constructor_signature(&item.name, inner);
+
+            let mut segments = prefix.cloned().unwrap_or_default();
+            segments.push(TyPathSegment {
+                ident: item.name.clone(),
+            });
+
+            let body = thin_vec![TyStmt {
+                kind: TyStmtKind::Expr(arena.alloc_ty_expr(TyExpr {
+                    kind: TyExprKind::Struct(thin_vec![arena.alloc_ty_expr(TyExpr {
+                        kind: TyExprKind::Variable(TyPath {
+                            segments: thin_vec![TyPathSegment {
+                                ident: params[0].name.clone(),
+                            }],
+                            span: item.name.span,
+                        }),
+                        ty: *inner,
+                        span: item.name.span,
+                    })]),
+                    ty: return_ty,
+                    span: item.name.span,
+                })),
+                span: item.name.span,
+            }];
+
+            TyItem {
+                kind: TyItemKind::Fn(Box::new(TyFn {
+                    params,
+                    return_ty,
+                    body,
+                    calling_convention: CallingConvention::C,
+                    path: TyPath {
+                        segments,
+                        span: item.name.span,
+                    },
+                })),
+                name: item.name.clone(),
+                vis: item.vis,
+                attrs: thin_vec![Attribute {
+                    name: Ident {
+                        name: "inline".into(),
+                        span: DUMMY_SPAN,
+                    },
+                    args: ThinVec::new(),
+                    value: None,
+                    span: DUMMY_SPAN,
+                }],
+            }
+        }),
+        _ => None,
+    };
+
+    items.push(item);
+    items.extend(constructor);
+}
+
+/// Returns the path of the symbol that an `extern fn` with the given name is
+/// linked against.
+fn extern_fn_symbol(name: &Ident) -> TyPath {
+    TyPath {
+        segments: thin_vec![TyPathSegment {
+            ident: name.clone()
+        }],
+        span: name.span,
+    }
+}
+
+/// Returns the width given by a `#[bits(N)]` attribute on a field of the
+/// given type.
+fn bitfield_width(attr: &Attribute, ty: &TypeId) -> TypeCheckResult<u32> {
+    let max_width = match &**ty {
+        TyKind::Bool => 1,
+        TyKind::Int(int_ty) => int_ty.bit_width(),
+        TyKind::Uint(uint_ty) => uint_ty.bit_width(),
+        _ => {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Bitfields must be integers or `Bool`, but this is `{}`.",
+                    ty_to_string(*ty)
+                )),
+                span: attr.span,
+            });
+        }
+    };
+
+    let width = match attr.args.as_slice() {
+        [arg] if arg.value.is_none() => arg.name.name.parse::<u32>().ok(),
+        _ => None,
+    };
+
+    let Some(width) = width else {
+        return Err(TypeError {
+            kind: TypeErrorKind::Error("`#[bits]` expects a width, like `#[bits(3)]`.".to_string()),
+            span: attr.span,
+        });
+    };
+
+    if width == 0 || width > max_width {
+        return Err(TypeError {
+            kind: TypeErrorKind::Error(format!(
+                "A `{}` bitfield must be between 1 and {max_width} bits wide.",
+                ty_to_string(*ty)
+            )),
+            span: attr.span,
+        });
+    }
+
+    Ok(width)
+}
+
+fn attr_message(attr: &Attribute) -> Option<SmolStr> {
+    attr.args
+        .iter()
+        .find(|arg| arg.name.name == "message")
+        .and_then(|arg| arg.value.clone())
+}
+
+/// Returns the lints allowed by the `#[allow]` attributes among the given ones.
+fn allowed_lints(attrs: &[Attribute]) -> impl Iterator<Item = Symbol> + '_ {
+    attrs
+        .iter()
+        .filter(|attr| attr.name.name == "allow")
+        .flat_map(|attr| attr.args.iter().map(|arg| arg.name.name))
+}
+
+/// Returns the trailing expression of a block, if it has one.
+fn last_expr<'a>(stmts: &[TyStmt<'a>]) -> Option<&'a TyExpr<'a>> {
+    match stmts.last().map(|stmt| &stmt.kind) {
+        Some(TyStmtKind::Expr(expr)) => Some(*expr),
+        _ => None,
+    }
+}
+
+/// Returns the trailing expression of a block, if it has one.
+fn last_expr_mut<'s, 'a>(stmts: &'s mut [TyStmt<'a>]) -> Option<&'s mut &'a TyExpr<'a>> {
+    match stmts.last_mut().map(|stmt| &mut stmt.kind) {
+        Some(TyStmtKind::Expr(expr)) => Some(expr),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::lexer::Lexer;
@@ -1003,8 +4835,9 @@ mod tests {
         insta::glob!("snapshot_inputs/*.crane", |path| {
             let source = std::fs::read_to_string(path).unwrap();
 
+            let arena = AstArena::new();
             let lexer = Lexer::new(&source);
-            let parser = Parser::new(lexer);
+            let parser = Parser::new(lexer, &arena);
 
             let items = parser.parse().unwrap();
 
@@ -1014,9 +4847,10 @@ mod tests {
                 modules: thin_vec![module],
             };
 
-            let mut typer = Typer::new();
+            // Some of the inputs only declare types, so don't require a `main` function.
+            let mut typer = Typer::with_output_kind(&arena, OutputKind::StaticLib);
 
-            insta::assert_yaml_snapshot!(typer.type_check_package(package));
+            insta::assert_yaml_snapshot!(typer.type_check_package(&package));
         })
     }
 }
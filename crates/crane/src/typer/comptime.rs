@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use thin_vec::thin_vec;
+
+use crate::ast::{
+    BinaryOp, Span, TyExpr, TyExprKind, TyLiteralKind, TyLocalKind, TyPatKind, TyPath,
+    TyPathSegment, TyStmt, TyStmtKind,
+};
+use crate::typer::{TyKind, TypeCheckResult, TypeError, TypeErrorKind, TypeId};
+
+/// The maximum number of loop iterations a `comptime` block may run, so that
+/// an infinite loop is reported instead of hanging the compiler.
+pub const MAX_COMPTIME_ITERATIONS: u64 = 1_000_000;
+
+/// A value computed at compile time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComptimeValue {
+    Unit,
+    Bool(bool),
+    Int(i128),
+}
+
+/// Evaluates type checked expressions at compile time.
+///
+/// Only the subset of the language that doesn't touch memory or call
+/// functions can be evaluated: integer arithmetic, comparisons, casts,
+/// `let` bindings, assignments, `if`/`else`, `while`, and `for` loops.
+#[derive(Default)]
+pub struct ComptimeEvaluator {
+    scopes: Vec<HashMap<TyPath, ComptimeValue>>,
+    iterations: u64,
+}
+
+impl ComptimeEvaluator {
+    /// Returns a new [`ComptimeEvaluator`] in which the given values, like
+    /// those of the `const`s in the package, are known.
+    pub fn with_consts(consts: impl IntoIterator<Item = (TyPath, ComptimeValue)>) -> Self {
+        Self {
+            scopes: vec![consts.into_iter().collect()],
+            ..Self::default()
+        }
+    }
+
+    /// Evaluates a block, returning the value of its last expression.
+    pub fn eval_block(&mut self, stmts: &[TyStmt]) -> TypeCheckResult<ComptimeValue> {
+        self.scopes.push(HashMap::new());
+
+        let value = self.eval_stmts(stmts);
+
+        self.scopes.pop();
+
+        value
+    }
+
+    fn eval_stmts(&mut self, stmts: &[TyStmt]) -> TypeCheckResult<ComptimeValue> {
+        let mut value = ComptimeValue::Unit;
+
+        for stmt in stmts {
+            value = match &stmt.kind {
+                TyStmtKind::Local(local) => {
+                    let TyLocalKind::Init(init) = &local.kind else {
+                        return Err(not_constant(stmt.span));
+                    };
+
+                    let init = self.eval_expr(init)?;
+
+                    let path = TyPath {
+                        segments: thin_vec![TyPathSegment {
+                            ident: local.name.clone()
+                        }],
+                        span: local.span,
+                    };
+
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert(path, init);
+                    }
+
+                    ComptimeValue::Unit
+                }
+                TyStmtKind::Expr(expr) => self.eval_expr(expr)?,
+                TyStmtKind::Item(_) => return Err(not_constant(stmt.span)),
+            };
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluates an expression.
+    pub fn eval_expr(&mut self, expr: &TyExpr) -> TypeCheckResult<ComptimeValue> {
+        match &expr.kind {
+            TyExprKind::Literal(literal) => match &literal.kind {
+                TyLiteralKind::Integer(integer) => Ok(ComptimeValue::Int(integer.value())),
+                TyLiteralKind::String(_) | TyLiteralKind::Float(_) => Err(not_constant(expr.span)),
+            },
+            TyExprKind::Variable(path) => self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(path).copied())
+                .ok_or_else(|| TypeError {
+                    kind: TypeErrorKind::Error(format!("`{path}` is not known at compile time")),
+                    span: expr.span,
+                }),
+            TyExprKind::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } => Ok(ComptimeValue::Bool(
+                self.eval_bool(lhs)? && self.eval_bool(rhs)?,
+            )),
+            TyExprKind::Binary { op, lhs, rhs } => {
+                let lhs_value = self.eval_int(lhs)?;
+                let rhs_value = self.eval_int(rhs)?;
+
+                let value = match op {
+                    BinaryOp::Add => lhs_value.checked_add(rhs_value),
+                    BinaryOp::Sub => lhs_value.checked_sub(rhs_value),
+                    BinaryOp::Mul => lhs_value.checked_mul(rhs_value),
+                    BinaryOp::Div | BinaryOp::Rem if rhs_value == 0 => {
+                        return Err(TypeError {
+                            kind: TypeErrorKind::Error(
+                                "Division by zero at compile time".to_string(),
+                            ),
+                            span: rhs.span,
+                        });
+                    }
+                    BinaryOp::Div => lhs_value.checked_div(rhs_value),
+                    BinaryOp::Rem => lhs_value.checked_rem(rhs_value),
+                    BinaryOp::Eq => return Ok(ComptimeValue::Bool(lhs_value == rhs_value)),
+                    BinaryOp::Ne => return Ok(ComptimeValue::Bool(lhs_value != rhs_value)),
+                    BinaryOp::Lt => return Ok(ComptimeValue::Bool(lhs_value < rhs_value)),
+                    BinaryOp::Le => return Ok(ComptimeValue::Bool(lhs_value <= rhs_value)),
+                    BinaryOp::Gt => return Ok(ComptimeValue::Bool(lhs_value > rhs_value)),
+                    BinaryOp::Ge => return Ok(ComptimeValue::Bool(lhs_value >= rhs_value)),
+                    BinaryOp::And => unreachable!("`&&` is evaluated on `Bool`s."),
+                };
+
+                match value {
+                    Some(value) if fits(value, &expr.ty) => Ok(ComptimeValue::Int(value)),
+                    _ => Err(TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "Overflow evaluating `{op}` at compile time"
+                        )),
+                        span: expr.span,
+                    }),
+                }
+            }
+            TyExprKind::Cast(inner) => {
+                let value = match self.eval_expr(inner)? {
+                    ComptimeValue::Int(value) => value,
+                    ComptimeValue::Bool(value) => value as i128,
+                    ComptimeValue::Unit => return Err(not_constant(inner.span)),
+                };
+
+                Ok(ComptimeValue::Int(wrap(value, &expr.ty)))
+            }
+            TyExprKind::Assign { target, value } => {
+                let value = self.eval_expr(value)?;
+
+                let slot = self
+                    .scopes
+                    .iter_mut()
+                    .rev()
+                    .find_map(|scope| scope.get_mut(target))
+                    .ok_or_else(|| TypeError {
+                        kind: TypeErrorKind::Error(format!(
+                            "`{target}` is not known at compile time"
+                        )),
+                        span: target.span,
+                    })?;
+
+                *slot = value;
+
+                Ok(ComptimeValue::Unit)
+            }
+            TyExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                match (self.eval_bool(cond)?, else_branch) {
+                    (true, Some(_)) => self.eval_block(then_branch),
+                    (false, Some(else_branch)) => self.eval_block(else_branch),
+                    // An `if` without an `else` doesn't produce a value.
+                    (true, None) => self.eval_block(then_branch).map(|_| ComptimeValue::Unit),
+                    (false, None) => Ok(ComptimeValue::Unit),
+                }
+            }
+            TyExprKind::While { cond, body } => {
+                while self.eval_bool(cond)? {
+                    self.count_iteration(expr.span)?;
+                    self.eval_block(body)?;
+                }
+
+                Ok(ComptimeValue::Unit)
+            }
+            TyExprKind::For(for_expr) => {
+                let start = self.eval_int(&for_expr.start)?;
+                let end = self.eval_int(&for_expr.end)?;
+
+                let binding = TyPath {
+                    segments: thin_vec![TyPathSegment {
+                        ident: for_expr.binding.clone()
+                    }],
+                    span: for_expr.binding.span,
+                };
+
+                for value in start..end {
+                    self.count_iteration(expr.span)?;
+
+                    self.scopes.push(HashMap::from([(
+                        binding.clone(),
+                        ComptimeValue::Int(value),
+                    )]));
+
+                    let result = self.eval_block(&for_expr.body);
+
+                    self.scopes.pop();
+
+                    result?;
+                }
+
+                Ok(ComptimeValue::Unit)
+            }
+            TyExprKind::Match { scrutinee, arms } => {
+                let value = self.eval_int(scrutinee)?;
+
+                let arm = arms.iter().find(|arm| match &arm.pat.kind {
+                    TyPatKind::Wild => true,
+                    TyPatKind::Literal(literal) => {
+                        matches!(&literal.kind, TyLiteralKind::Integer(integer) if integer.value() == value)
+                    }
+                });
+
+                match arm {
+                    Some(arm) => self.eval_block(&arm.body),
+                    None => Ok(ComptimeValue::Unit),
+                }
+            }
+            TyExprKind::Call { .. }
+            | TyExprKind::Array(_)
+            | TyExprKind::Index { .. }
+            | TyExprKind::Struct(_)
+            | TyExprKind::Field { .. }
+            | TyExprKind::TypeOf(_)
+            | TyExprKind::Asm(_)
+            | TyExprKind::Block(_) => Err(not_constant(expr.span)),
+        }
+    }
+
+    fn eval_int(&mut self, expr: &TyExpr) -> TypeCheckResult<i128> {
+        match self.eval_expr(expr)? {
+            ComptimeValue::Int(value) => Ok(value),
+            _ => Err(not_constant(expr.span)),
+        }
+    }
+
+    fn eval_bool(&mut self, expr: &TyExpr) -> TypeCheckResult<bool> {
+        match self.eval_expr(expr)? {
+            ComptimeValue::Bool(value) => Ok(value),
+            _ => Err(not_constant(expr.span)),
+        }
+    }
+
+    fn count_iteration(&mut self, span: Span) -> TypeCheckResult<()> {
+        self.iterations += 1;
+
+        if self.iterations > MAX_COMPTIME_ITERATIONS {
+            return Err(TypeError {
+                kind: TypeErrorKind::Error(format!(
+                    "Exceeded {MAX_COMPTIME_ITERATIONS} loop iterations at compile time"
+                )),
+                span,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn not_constant(span: Span) -> TypeError {
+    TypeError {
+        kind: TypeErrorKind::Error("This cannot be evaluated at compile time".to_string()),
+        span,
+    }
+}
+
+/// Returns the range of values representable by the given integer type.
+fn int_range(ty: &TypeId) -> Option<(i128, i128)> {
+    match &**ty {
+        TyKind::Int(int_ty) => {
+            let bits = int_ty.bit_width();
+            Some((-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1))
+        }
+        TyKind::Uint(uint_ty) => Some((0, (1i128 << uint_ty.bit_width()) - 1)),
+        _ => None,
+    }
+}
+
+/// Returns whether the value fits in the given integer type.
+fn fits(value: i128, ty: &TypeId) -> bool {
+    int_range(ty).is_none_or(|(min, max)| (min..=max).contains(&value))
+}
+
+/// Wraps the value into the given integer type, as a cast does at runtime.
+fn wrap(value: i128, ty: &TypeId) -> i128 {
+    let Some((min, max)) = int_range(ty) else {
+        return value;
+    };
+
+    (value - min).rem_euclid(max - min + 1) + min
+}
@@ -3,6 +3,7 @@ use smol_str::SmolStr;
 use thin_vec::ThinVec;
 
 use crate::ast::{Span, TyPath};
+use crate::error_codes::ErrorCode;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TypeError {
@@ -32,5 +33,50 @@ pub enum TypeErrorKind {
         path: TyPath,
         options: ThinVec<TyPath>,
     },
+    /// An operation that requires `unsafe` was used outside of an `unsafe`
+    /// block or function.
+    RequiresUnsafe {
+        operation: String,
+    },
     Error(String),
 }
+
+impl TypeErrorKind {
+    /// Returns the code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidFunctionName { .. } => ErrorCode::InvalidFunctionName,
+            Self::InvalidTypeName { .. } => ErrorCode::InvalidTypeName,
+            Self::UnknownModule { .. } => ErrorCode::UnknownModule,
+            Self::UnknownFunction { .. } => ErrorCode::UnknownFunction,
+            Self::UnknownType { .. } => ErrorCode::UnknownType,
+            Self::RequiresUnsafe { .. } => ErrorCode::RequiresUnsafe,
+            Self::Error(_) => ErrorCode::TypeError,
+        }
+    }
+}
+
+impl std::fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFunctionName { reason, .. } | Self::InvalidTypeName { reason, .. } => {
+                write!(f, "{reason}")
+            }
+            Self::UnknownModule { path, .. } => write!(f, "Module `{path}` does not exist."),
+            Self::UnknownFunction { path, .. } => write!(f, "Function `{path}` does not exist."),
+            Self::UnknownType { path, .. } => write!(f, "Type `{path}` does not exist."),
+            Self::RequiresUnsafe { operation } => {
+                write!(f, "{operation} requires an `unsafe` block or function.")
+            }
+            Self::Error(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for TypeError {}
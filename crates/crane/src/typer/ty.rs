@@ -1,39 +1,138 @@
+use std::collections::HashMap;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{LazyLock, RwLock};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use smol_str::SmolStr;
 use thin_vec::ThinVec;
 
-/// A type in the type system.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct Ty(Arc<TyKind>);
+/// The types interned so far, which are shared by every thread.
+static INTERNER: LazyLock<RwLock<TypeInterner>> = LazyLock::new(|| {
+    RwLock::new(TypeInterner {
+        kinds: Vec::new(),
+        ids: HashMap::new(),
+    })
+});
 
-impl Deref for Ty {
+/// A pool of interned types, which the typer and the typed AST refer to by
+/// [`TypeId`], rather than each holding a type of their own.
+///
+/// Equal types are interned once, so they share a [`TypeId`], and comparing
+/// two types doesn't compare their kinds, however deeply they're nested.
+///
+/// Interned types live for the rest of the process, like interned symbols, and
+/// are only interned once, so the language server and the REPL only grow by
+/// the types they haven't seen before.
+pub struct TypeInterner {
+    kinds: Vec<&'static TyKind>,
+    ids: HashMap<&'static TyKind, TypeId>,
+}
+
+impl TypeInterner {
+    /// Interns the given type, returning its [`TypeId`].
+    pub fn intern(kind: TyKind) -> TypeId {
+        if let Some(id) = INTERNER.read().unwrap().ids.get(&kind) {
+            return *id;
+        }
+
+        INTERNER.write().unwrap().insert(kind)
+    }
+
+    /// Returns the type with the given [`TypeId`].
+    pub fn get(id: TypeId) -> &'static TyKind {
+        INTERNER.read().unwrap().kinds[id.0 as usize]
+    }
+
+    fn insert(&mut self, kind: TyKind) -> TypeId {
+        if let Some(id) = self.ids.get(&kind) {
+            return *id;
+        }
+
+        let id = TypeId(u32::try_from(self.kinds.len()).expect("too many types to intern"));
+        let kind: &'static TyKind = Box::leak(Box::new(kind));
+
+        self.kinds.push(kind);
+        self.ids.insert(kind, id);
+
+        id
+    }
+}
+
+/// A type in the type system, interned in the [`TypeInterner`].
+///
+/// It is shown and serialized as the [`TyKind`] it refers to.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct TypeId(u32);
+
+impl Deref for TypeId {
     type Target = TyKind;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        TypeInterner::get(*self)
+    }
+}
+
+impl TypeId {
+    /// Interns the given type, returning its [`TypeId`].
+    pub fn intern(kind: TyKind) -> Self {
+        TypeInterner::intern(kind)
+    }
+
+    /// Returns whether this is an integer or floating-point type.
+    pub fn is_numeric(self) -> bool {
+        matches!(&*self, TyKind::Int(_) | TyKind::Uint(_) | TyKind::Float(_))
+    }
+}
+
+impl std::fmt::Debug for TypeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Serialize for TypeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
     }
 }
 
-impl Ty {
-    pub fn new(kind: TyKind) -> Self {
-        Self(Arc::new(kind))
+impl<'de> Deserialize<'de> for TypeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TyKind::deserialize(deserializer).map(Self::intern)
     }
 }
 
-/// The kind of a [`Ty`].
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+/// The kind of a [`TypeId`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum TyKind {
     /// The unit type (`()`).
     Unit,
 
+    /// The type of expressions that never produce a value, like a call to
+    /// `std::process::exit`.
+    Never,
+
+    /// The boolean type.
+    Bool,
+
+    /// A signed integer type.
+    Int(IntTy),
+
     /// An unsigned integer type.
     Uint(UintTy),
 
+    /// A floating-point type.
+    Float(FloatTy),
+
     /// A function type.
-    Fn { args: ThinVec<Ty>, return_ty: Ty },
+    Fn {
+        args: ThinVec<TypeId>,
+        return_ty: TypeId,
+        calling_convention: CallingConvention,
+    },
+
+    /// A fixed-size array type.
+    Array { elem: TypeId, len: u64 },
 
     /// A user-defined type.
     UserDefined {
@@ -45,18 +144,142 @@ pub enum TyKind {
     },
 }
 
+/// A signed integer type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub enum IntTy {
+    /// An 8-bit signed integer.
+    I8,
+
+    /// A 16-bit signed integer.
+    I16,
+
+    /// A 32-bit signed integer.
+    I32,
+
+    /// A 64-bit signed integer.
+    I64,
+}
+
+impl IntTy {
+    /// Returns the width of this type, in bits.
+    pub fn bit_width(&self) -> u32 {
+        match self {
+            Self::I8 => 8,
+            Self::I16 => 16,
+            Self::I32 => 32,
+            Self::I64 => 64,
+        }
+    }
+}
+
 /// An unsigned integer type.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum UintTy {
+    /// An 8-bit unsigned integer.
+    U8,
+
+    /// A 16-bit unsigned integer.
+    U16,
+
+    /// A 32-bit unsigned integer.
+    U32,
+
     /// A 64-bit unsigned integer.
     U64,
 }
 
+impl UintTy {
+    /// Returns the width of this type, in bits.
+    pub fn bit_width(&self) -> u32 {
+        match self {
+            Self::U8 => 8,
+            Self::U16 => 16,
+            Self::U32 => 32,
+            Self::U64 => 64,
+        }
+    }
+}
+
+/// A floating-point type.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub enum FloatTy {
+    /// A 64-bit floating-point number.
+    F64,
+}
+
+/// The calling convention of a function, set with `#[calling_convention]`.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum CallingConvention {
+    /// The C calling convention of the target.
+    #[default]
+    C,
+
+    /// A fast calling convention, for calls within the program.
+    Fast,
+
+    /// A calling convention that keeps the caller fast, for rarely called functions.
+    Cold,
+
+    /// The Win32 `stdcall` calling convention.
+    X86Stdcall,
+
+    /// The Win32 `fastcall` calling convention.
+    X86Fastcall,
+
+    /// The ARM Procedure Call Standard.
+    ArmAapcs,
+}
+
+impl CallingConvention {
+    /// All of the calling conventions.
+    pub const ALL: [Self; 6] = [
+        Self::C,
+        Self::Fast,
+        Self::Cold,
+        Self::X86Stdcall,
+        Self::X86Fastcall,
+        Self::ArmAapcs,
+    ];
+
+    /// Returns the name of this calling convention in `#[calling_convention]`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::C => "C",
+            Self::Fast => "fast",
+            Self::Cold => "cold",
+            Self::X86Stdcall => "stdcall",
+            Self::X86Fastcall => "fastcall",
+            Self::ArmAapcs => "aapcs",
+        }
+    }
+
+    /// Returns the calling convention with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|calling_convention| calling_convention.name() == name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Tests the size of [`Type`] to ensure it doesn't unintentionally get bigger.
+    #[test]
+    fn test_equal_types_share_an_id() {
+        let array = |elem| {
+            TypeId::intern(TyKind::Array {
+                elem: TypeId::intern(TyKind::Uint(elem)),
+                len: 4,
+            })
+        };
+
+        assert_eq!(array(UintTy::U8), array(UintTy::U8));
+        assert_ne!(array(UintTy::U8), array(UintTy::U16));
+        assert!(matches!(&*array(UintTy::U8), TyKind::Array { len: 4, .. }));
+    }
+
+    /// Tests the size of [`TyKind`] to ensure it doesn't unintentionally get bigger.
     #[test]
     fn test_type_size() {
         use std::mem::size_of;
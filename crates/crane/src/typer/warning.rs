@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use crate::ast::Span;
+
+/// A problem found by a lint, while type checking or by `crane lint`, that
+/// doesn't stop compilation.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum Warning {
+    /// A call to a function marked `#[deprecated]`.
+    Deprecated {
+        fn_name: SmolStr,
+        message: Option<SmolStr>,
+        span: Span,
+    },
+
+    /// A call to a function marked `#[must_use]` whose result is discarded.
+    UnusedResult {
+        fn_name: SmolStr,
+        message: Option<SmolStr>,
+        span: Span,
+    },
+
+    /// Code that is never run, as it follows an expression that diverges.
+    UnreachableCode { span: Span },
+
+    /// A function, parameter or variable whose name isn't in snake_case.
+    NonSnakeCase {
+        /// What is named, like `Function`.
+        kind: SmolStr,
+        name: SmolStr,
+        suggestion: SmolStr,
+        span: Span,
+    },
+
+    /// A struct, union or variant whose name isn't in PascalCase.
+    NonPascalCaseType {
+        /// What is named, like `Struct`.
+        kind: SmolStr,
+        name: SmolStr,
+        suggestion: SmolStr,
+        span: Span,
+    },
+
+    /// A function with more statements than it should have.
+    TooManyStatements {
+        fn_name: SmolStr,
+        count: usize,
+        max: usize,
+        span: Span,
+    },
+
+    /// A function whose body is empty.
+    EmptyFn { fn_name: SmolStr, span: Span },
+}
+
+impl Warning {
+    /// Returns the name of the lint that `#[allow]` uses to suppress this warning.
+    pub fn lint_name(&self) -> &'static str {
+        match self {
+            Warning::Deprecated { .. } => "deprecated",
+            Warning::UnusedResult { .. } => "unused_must_use",
+            Warning::UnreachableCode { .. } => "unreachable_code",
+            Warning::NonSnakeCase { .. } => "non_snake_case",
+            Warning::NonPascalCaseType { .. } => "non_pascal_case_types",
+            Warning::TooManyStatements { .. } => "too_many_statements",
+            Warning::EmptyFn { .. } => "empty_fn",
+        }
+    }
+}
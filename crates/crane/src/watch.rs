@@ -0,0 +1,175 @@
+//! Watching source files for changes, for `crane build --watch` and
+//! `crane run --watch`.
+//!
+//! The watched files are polled for their modification time and length by
+//! path, rather than through their inode, so that a file replaced by renaming
+//! another over it, like editors do when saving atomically, is seen to change.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::manifest::MANIFEST;
+
+/// How often the watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the files must go without changing before a change is reported,
+/// so that a burst of writes, like saving several files at once, only
+/// triggers a single rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// The state of a watched file, which changes when it is written to.
+type FileState = (SystemTime, u64);
+
+/// Watches the Crane source files in a set of directories, and the files
+/// directly given to it.
+#[derive(Debug)]
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl Watcher {
+    /// Starts watching the given files and directories. Directories are
+    /// watched recursively, for `.crane` files and manifests.
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let mut watcher = Self {
+            roots,
+            files: HashMap::new(),
+        };
+
+        watcher.files = watcher.scan();
+        watcher
+    }
+
+    /// Blocks until a watched file is created, changed, or removed, and has
+    /// then stopped changing.
+    pub fn wait_for_change(&mut self) {
+        while !self.poll() {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        loop {
+            std::thread::sleep(DEBOUNCE);
+
+            if !self.poll() {
+                return;
+            }
+        }
+    }
+
+    /// Returns whether any watched file has changed since the last poll.
+    pub fn poll(&mut self) -> bool {
+        let files = self.scan();
+        let changed = files != self.files;
+
+        self.files = files;
+
+        changed
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, FileState> {
+        let mut files = HashMap::new();
+
+        for root in &self.roots {
+            if root.is_dir() {
+                scan_dir(root, &mut files);
+            } else {
+                add_file(root, &mut files);
+            }
+        }
+
+        files
+    }
+}
+
+/// Adds the source files in the given directory, and those nested in it, to
+/// `files`. Hidden directories, and the `build` directory that artifacts are
+/// written to by default, are skipped.
+fn scan_dir(dir: &Path, files: &mut HashMap<PathBuf, FileState>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "build" {
+                scan_dir(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "crane") || name == MANIFEST {
+            add_file(&path, files);
+        }
+    }
+}
+
+fn add_file(path: &Path, files: &mut HashMap<PathBuf, FileState>) {
+    if let Ok(metadata) = std::fs::metadata(path) {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+        files.insert(path.to_path_buf(), (modified, metadata.len()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("crane-watch-tests").join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_poll() {
+        let dir = test_dir("poll");
+        let main = dir.join("main.crane");
+
+        std::fs::write(&main, "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let mut watcher = Watcher::new(vec![dir.clone()]);
+
+        assert!(!watcher.poll());
+
+        // Other files aren't watched.
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert!(!watcher.poll());
+
+        std::fs::write(&main, "fn main() {\n}\n").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+
+        // Modules added in nested directories are watched.
+        std::fs::create_dir(dir.join("math")).unwrap();
+        std::fs::write(dir.join("math/mod.crane"), "").unwrap();
+        assert!(watcher.poll());
+    }
+
+    #[test]
+    fn test_poll_sees_files_replaced_by_renaming() {
+        let dir = test_dir("rename");
+        let main = dir.join("main.crane");
+
+        std::fs::write(&main, "fn main() {}\n").unwrap();
+
+        let mut watcher = Watcher::new(vec![main.clone()]);
+
+        // Saved the way editors save atomically, by writing a new file and
+        // renaming it over the old one.
+        let saved = dir.join(".main.crane.swp");
+
+        std::fs::write(&saved, "fn main() {\n}\n").unwrap();
+        std::fs::rename(&saved, &main).unwrap();
+
+        assert!(watcher.poll());
+    }
+}
@@ -0,0 +1,34 @@
+mod common;
+
+#[test]
+fn test_sum_array_in_loop() {
+    let output = common::compile_and_run(
+        "arrays",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+fn sum(values: [Uint64; 4]) -> Uint64 {
+    let total = 0
+    let index = 0
+
+    while index < 4 {
+        total = total + values[index]
+        index = index + 1
+    }
+
+    total
+}
+
+fn main() {
+    let values = [1, 2, 3, 4]
+
+    println(int_to_string(sum(values)))
+    println(int_to_string(values[3] * 10))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n40\n");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+#[test]
+fn test_passing_assert() {
+    let output = common::compile_and_run(
+        "assert_passing",
+        r#"
+use std::io::println
+use std::process::assert
+
+fn main() {
+    assert(1 + 1 == 2, "Math is broken.")
+    println("Passed.")
+}
+"#,
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Passed.\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn test_failing_assert() {
+    let output = common::compile_and_run(
+        "assert_failing",
+        r#"
+use std::io::println
+use std::process::assert
+
+fn main() {
+    println("Checking...")
+    assert(2 + 2 == 5, "Expected 2 + 2 to be 5.")
+    println("Unreachable.")
+}
+"#,
+    );
+
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Checking...\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Assertion failed at assert_failing.crane:7:5: Expected 2 + 2 to be 5.\n"
+    );
+}
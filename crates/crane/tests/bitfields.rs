@@ -0,0 +1,47 @@
+mod common;
+
+#[test]
+fn test_bitfields() {
+    let output = common::compile_and_run(
+        "bitfields",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+#[bitfield]
+struct ControlRegister {
+    #[bits(1)]
+    enabled: Bool,
+    #[bits(3)]
+    mode: Uint8,
+    #[bits(4)]
+    offset: Int8,
+}
+
+fn mode_of(register: ControlRegister) -> Uint8 {
+    register.mode
+}
+
+fn main() {
+    let one = 1 as Int8
+    let register = ControlRegister {
+        enabled: 1 == 1,
+        mode: 5 as Uint8,
+        offset: one - 4 as Int8,
+    }
+
+    if register.enabled {
+        println(int_to_string(mode_of(register) as Uint64))
+    }
+
+    let offset = register.offset
+    if offset < 0 as Int8 {
+        println(int_to_string(0 - offset as Int64 as Uint64))
+    }
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n3\n");
+}
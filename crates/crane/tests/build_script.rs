@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crane::build_script::{run_build_script, BuildScriptError, BuildScriptOutput, BUILD_SCRIPT};
+use crane::compiler::{CompileParams, Compiler, Input};
+
+mod common;
+
+/// Writes the given build script into a fresh package directory, returning
+/// its path.
+fn write_build_script(package_dir: &Path, source: &str) -> PathBuf {
+    let _ = std::fs::remove_dir_all(package_dir);
+    std::fs::create_dir_all(package_dir).unwrap();
+
+    let script = package_dir.join(BUILD_SCRIPT);
+    std::fs::write(&script, source).unwrap();
+
+    script
+}
+
+#[test]
+fn test_build_script_configures_the_compilation() {
+    let package_dir = std::env::temp_dir()
+        .join("crane-tests")
+        .join("build_script");
+
+    let script = write_build_script(
+        &package_dir,
+        r#"
+use std::io::println
+
+fn main() {
+    println("Configuring...")
+    println("crane:link-lib=m")
+    println("crane:link-search=/opt/crane/lib")
+    println("crane:env=GREETING=Hello=World")
+    println("crane:warning=Using the system math library.")
+}
+"#,
+    );
+
+    let mut options = common::host_options(&package_dir.join("build"));
+
+    let mut stderr = Vec::new();
+
+    let output = run_build_script(&mut stderr, &script, &options)
+        .unwrap_or_else(|_| panic!("{}", String::from_utf8_lossy(&stderr)));
+
+    assert_eq!(
+        output,
+        BuildScriptOutput {
+            link_libraries: vec!["m".to_string()],
+            link_search_paths: vec![PathBuf::from("/opt/crane/lib")],
+            env: vec![("GREETING".to_string(), "Hello=World".to_string())],
+            warnings: vec!["Using the system math library.".to_string()],
+        }
+    );
+    assert!(String::from_utf8_lossy(&stderr).contains("Warning: Using the system math library."));
+
+    output.apply(&mut options);
+
+    let params = CompileParams {
+        input: Input::String {
+            filename: "build_script.crane".into(),
+            input: r#"
+use std::float::float_to_string
+use std::io::println
+
+extern fn sqrt(x: Float64) -> Float64
+
+fn main() {
+    println(float_to_string(unsafe { sqrt(6.25) }))
+}
+"#
+            .into(),
+        },
+        options,
+    };
+
+    Compiler::new()
+        .compile(&mut stderr, params)
+        .unwrap_or_else(|_| panic!("{}", String::from_utf8_lossy(&stderr)));
+
+    let output = Command::new(package_dir.join("build").join("build_script"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2.5\n");
+}
+
+#[test]
+fn test_failing_build_script() {
+    let package_dir = std::env::temp_dir()
+        .join("crane-tests")
+        .join("failing_build_script");
+
+    let script = write_build_script(
+        &package_dir,
+        r#"
+use std::process::exit
+
+fn main() {
+    exit(3)
+}
+"#,
+    );
+
+    let err = run_build_script(
+        &mut Vec::new(),
+        &script,
+        &common::host_options(&package_dir),
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, BuildScriptError::Failed { status, .. } if status.code() == Some(3)),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_unknown_build_script_directive() {
+    assert!(
+        BuildScriptOutput::parse("crane:link-lib=m\ncrane:rerun-if-changed=build.crane").is_err()
+    );
+    assert_eq!(
+        BuildScriptOutput::parse("cargo:link-lib=m").unwrap(),
+        BuildScriptOutput::default()
+    );
+}
@@ -0,0 +1,1576 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Returns an empty directory for the given test to run the CLI in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("crane-tests").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// Runs `crane` with the given arguments in the given directory.
+fn crane(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_crane"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("Failed to run `crane`")
+}
+
+fn hello_world() -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../examples/hello_world.crane")
+        .display()
+        .to_string()
+}
+
+#[test]
+fn test_build_hello_world() {
+    let dir = test_dir("cli_build_hello_world");
+
+    let output = crane(
+        &dir,
+        &["build", &hello_world(), "--out-dir", "out", "--no-cache"],
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    for artifact in ["hello_world.ll", "hello_world.o", "hello_world"] {
+        let path = Path::new("out").join(artifact);
+
+        assert!(
+            dir.join(&path).exists(),
+            "`{}` was not written",
+            path.display()
+        );
+        assert!(stdout.contains(&path.display().to_string()));
+    }
+
+    let run = Command::new(dir.join("out/hello_world")).output().unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "Hello, world!\n");
+}
+
+#[test]
+fn test_build_emit_without_linking() {
+    let dir = test_dir("cli_build_emit");
+
+    let output = crane(
+        &dir,
+        &[
+            "build",
+            &hello_world(),
+            "--out-dir",
+            "out",
+            "--no-cache",
+            "--emit",
+            "llvm-ir,llvm-bc",
+            "-O2",
+        ],
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(dir.join("out/hello_world.ll").exists());
+    assert!(dir.join("out/hello_world.bc").exists());
+    assert!(!dir.join("out/hello_world").exists());
+}
+
+#[test]
+fn test_build_emit_ast() {
+    let dir = test_dir("cli_build_emit_ast");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "fn main() {}\n\nfn add() -> Uint64 {\n    let sum = 1 + 2\n    sum\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["build", "main.crane", "--emit", "ast,typed-ast"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("Fn add @ 17..20"));
+    assert!(stdout.contains("      Binary + @ 49..54"));
+    assert!(stdout.contains("      Binary + : Uint64 @ 49..54"));
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_build_emit_tokens() {
+    let dir = test_dir("cli_build_emit_tokens");
+
+    let output = crane_with_stdin(
+        &dir,
+        &["build", "--stdin", "--emit", "tokens"],
+        "let x = $1 // One.\n",
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Ident let @ 0..3\nIdent x @ 4..5\nEqual = @ 6..7\nError $ @ 8..9: Unrecognized character.\nInteger 1 @ 9..10\nComment // One. @ 11..18\nEof @ 19..19\n"
+    );
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_build_without_a_file_prints_usage() {
+    let dir = test_dir("cli_build_usage");
+
+    let output = crane(&dir, &["build"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Usage: crane build"));
+}
+
+#[test]
+fn test_build_exits_with_an_error_when_compilation_fails() {
+    let dir = test_dir("cli_build_error");
+
+    std::fs::write(
+        dir.join("broken.crane"),
+        "fn main() {\n    let value: Uint64 = \"not a number\"\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["build", "broken.crane", "--no-cache"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!output.stderr.is_empty());
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = test_dir("cli_exit_codes");
+
+    std::fs::write(dir.join("syntax.crane"), "fn main() {\n    let x = $\n}\n").unwrap();
+    std::fs::write(dir.join("type.crane"), "fn main() {\n    missing()\n}\n").unwrap();
+
+    for (file, code) in [("syntax.crane", 3), ("type.crane", 4)] {
+        let output = crane(&dir, &["check", file]);
+
+        assert_eq!(output.status.code(), Some(code), "{file}");
+    }
+
+    let output = crane(
+        &dir,
+        &[
+            "build",
+            &hello_world(),
+            "--target",
+            "crane-unknown-nowhere",
+            "--no-cache",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = crane(
+        &dir,
+        &[
+            "build",
+            &hello_world(),
+            "-l",
+            "crane_missing_library",
+            "--no-cache",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn test_build_missing_file() {
+    let dir = test_dir("cli_build_missing_file");
+
+    let output = crane(&dir, &["build", "missing.crane"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to read `missing.crane`"));
+}
+
+#[test]
+fn test_fmt() {
+    let dir = test_dir("cli_fmt");
+
+    let unformatted = "fn main() {\n  // Add them up.\n  let sum = 1+2\n}\n";
+
+    std::fs::write(dir.join("main.crane"), unformatted).unwrap();
+
+    let output = crane(&dir, &["fmt", "--check", "main.crane"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("main.crane")).unwrap(),
+        unformatted
+    );
+
+    let output = crane(&dir, &["fmt", "main.crane"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("main.crane")).unwrap(),
+        "fn main() {\n    // Add them up.\n    let sum = 1 + 2\n}\n"
+    );
+
+    let output = crane(&dir, &["fmt", "--check", "main.crane"]);
+
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_doc() {
+    let dir = test_dir("cli_doc");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "/// Adds two numbers.\npub fn add(a: Uint64, b: Uint64) -> Uint64 {\n    a + b\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["doc", "main.crane", "--out-dir", "doc"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(dir.join("doc/index.html").exists());
+
+    let page = std::fs::read_to_string(dir.join("doc/main.html")).unwrap();
+
+    assert!(page.contains("<p>Adds two numbers.</p>"));
+    assert!(page.contains("<span class=\"keyword\">fn</span> add("));
+}
+
+#[test]
+fn test_doc_private_items() {
+    let dir = test_dir("cli_doc_private_items");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "/// Subtracts two numbers.\nfn sub(a: Uint64, b: Uint64) -> Uint64 {\n    a - b\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["doc", "main.crane", "--out-dir", "doc"]);
+
+    assert!(output.status.success());
+
+    let page = std::fs::read_to_string(dir.join("doc/main.html")).unwrap();
+
+    assert!(!page.contains("<p>Subtracts two numbers.</p>"));
+
+    let output = crane(
+        &dir,
+        &[
+            "doc",
+            "main.crane",
+            "--out-dir",
+            "doc",
+            "--document-private-items",
+        ],
+    );
+
+    assert!(output.status.success());
+
+    let page = std::fs::read_to_string(dir.join("doc/main.html")).unwrap();
+
+    assert!(page.contains("<p>Subtracts two numbers.</p>"));
+}
+
+#[test]
+fn test_run_hello_world() {
+    let dir = test_dir("cli_run_hello_world");
+
+    let output = crane(&dir, &["run", &hello_world(), "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, world!\n");
+
+    // The artifacts are built in a temporary directory.
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_run_propagates_the_exit_code() {
+    let dir = test_dir("cli_run_exit_code");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "use std::process::exit\n\nfn main() {\n    exit(3)\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(
+        &dir,
+        &["run", "main.crane", "--no-cache", "--keep-artifacts"],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(dir.join("build/main").exists());
+}
+
+#[test]
+fn test_run_to_string() {
+    let dir = test_dir("cli_run_to_string");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+#[derive(ToString)]
+struct Point {
+    x: Uint64,
+    y: Int32,
+}
+
+#[derive(ToString)]
+struct Meters(Float64)
+
+struct Celsius {
+    degrees: Float64,
+}
+
+impl ToString for Celsius {
+    fn to_string(self: Celsius) -> String {
+        format!("{} degrees", self.degrees)
+    }
+}
+
+fn main() {
+    println(Point { x: 1, y: 2 }.to_string())
+    println(format!("{} and {{}} {}", Meters(1.5), Celsius { degrees: 20.5 }))
+    println(format!("{}", 1 == 2))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Point { x: 1, y: 2 }\nMeters(1.5) and {} 20.5 degrees\nfalse\n"
+    );
+}
+
+#[test]
+fn test_run_debug_repr() {
+    let dir = test_dir("cli_run_debug_repr");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+#[derive(Debug)]
+struct Foo {
+    x: Uint64,
+    y: String,
+}
+
+#[derive(Debug, ToString)]
+struct Name(String)
+
+fn main() {
+    println(Foo { x: 42, y: "hello" }.debug_repr())
+    println(format!("{:?} {}", Name("a"), Name("b")))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Foo { x: 42, y: \"hello\" }\nName(\"a\") Name(b)\n"
+    );
+}
+
+#[test]
+fn test_run_clone() {
+    let dir = test_dir("cli_run_clone");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+#[derive(Clone, Debug)]
+struct Name(String)
+
+#[derive(Clone)]
+struct Account {
+    id: Uint64,
+    owner: Name,
+}
+
+fn main() {
+    let account = Account { id: 7, owner: Name("ada") }
+    let copy = account.clone()
+
+    println(format!("{} {:?}", copy.id, copy.owner))
+    println("hello".clone())
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "7 Name(\"ada\")\nhello\n"
+    );
+}
+
+#[test]
+fn test_run_hash() {
+    let dir = test_dir("cli_run_hash");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+#[derive(Hash)]
+struct Name(String)
+
+#[derive(Hash)]
+struct Account {
+    id: Uint64,
+    owner: Name,
+}
+
+fn main() {
+    let account = Account { id: 7, owner: Name("ada") }
+    let same = Account { id: 7, owner: Name("ada") }
+    let other = Account { id: 8, owner: Name("ada") }
+
+    println(format!("{}", account.hash() == same.hash()))
+    println(format!("{}", account.hash() == other.hash()))
+    println(format!("{}", "ada".hash() == Name("ada").hash()))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "true\nfalse\ntrue\n"
+    );
+}
+
+#[test]
+fn test_run_operator_overloading() {
+    let dir = test_dir("cli_run_operator_overloading");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+struct Vector {
+    x: Int64,
+    y: Int64,
+}
+
+impl Add for Vector {
+    fn add(self: Vector, other: Vector) -> Vector {
+        Vector { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Vector {
+    fn sub(self: Vector, other: Vector) -> Vector {
+        Vector { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul for Vector {
+    fn mul(self: Vector, other: Vector) -> Vector {
+        Vector { x: self.x * other.x, y: self.y * other.y }
+    }
+}
+
+impl Div for Vector {
+    fn div(self: Vector, other: Vector) -> Vector {
+        Vector { x: self.x / other.x, y: self.y / other.y }
+    }
+}
+
+fn main() {
+    let a = Vector { x: 6, y: 8 }
+    let b = Vector { x: 2, y: 4 }
+    let c = a + b * b - a / b
+
+    println(format!("{} {}", c.x, c.y))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7 22\n");
+}
+
+#[test]
+fn test_run_index() {
+    let dir = test_dir("cli_run_index");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+struct Day(Uint64)
+
+struct Forecast {
+    highs: [Int64; 3],
+}
+
+impl Index for Forecast {
+    fn index(self: Forecast, day: Day) -> Int64 {
+        let Day(day) = day
+        self.highs[day]
+    }
+}
+
+fn main() {
+    let forecast = Forecast { highs: [18, 21, 16] }
+
+    println(format!("{} {}", forecast[Day(0)], forecast[Day(1)] - forecast[Day(2)]))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "18 5\n");
+}
+
+#[test]
+fn test_run_partial_eq() {
+    let dir = test_dir("cli_run_partial_eq");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::io::println
+
+#[derive(PartialEq)]
+struct Account {
+    id: Uint64,
+    owner: String,
+    active: Bool,
+}
+
+#[derive(PartialEq)]
+struct Meters(Float64)
+
+#[derive(PartialEq)]
+struct Marker {}
+
+fn main() {
+    let account = Account { id: 1, owner: "ada", active: 1 == 1 }
+    let renamed = Account { id: 1, owner: "grace", active: 1 == 1 }
+
+    println(format!("{} {}", account == account, account == renamed))
+    println(format!("{} {}", Meters(1.5) != Meters(2.5), Marker {} == Marker {}))
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "main.crane", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "true false\ntrue true\n"
+    );
+}
+
+#[test]
+fn test_run_does_not_run_when_compilation_fails() {
+    let dir = test_dir("cli_run_error");
+
+    std::fs::write(
+        dir.join("broken.crane"),
+        "fn main() {\n    let value: Uint64 = \"not a number\"\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["run", "broken.crane", "--no-cache"]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_check() {
+    let dir = test_dir("cli_check");
+
+    let output = crane(&dir, &["check", &hello_world()]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stderr.is_empty());
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_check_package_directory() {
+    let dir = test_dir("cli_check_directory");
+
+    std::fs::create_dir_all(dir.join("greeter/src")).unwrap();
+    std::fs::write(
+        dir.join("greeter/src/main.crane"),
+        "mod util\n\nfn main() {\n    util::greet(\"x\")\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("greeter/src/util.crane"),
+        "use std::io::println\n\npub fn greet(name: String) {\n    println(name)\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["check", "greeter"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Writes a package named `hello` to `dir`, with the given `[build]` section
+/// in its manifest.
+fn write_package(dir: &Path, build: &str) {
+    std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+    std::fs::write(
+        dir.join("Crane.toml"),
+        format!("[package]\nname = \"hello\"\nversion = \"0.1.0\"\n\n[build]\n{build}"),
+    )
+    .unwrap();
+    std::fs::write(dir.join("src/main.crane"), "fn main() {}\n").unwrap();
+}
+
+#[test]
+fn test_check_finds_the_manifest_in_an_ancestor() {
+    let dir = test_dir("cli_check_ancestor_manifest");
+
+    write_package(&dir, "");
+
+    let output = crane(&dir.join("src/nested"), &["check"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_check_reports_invalid_manifests() {
+    let dir = test_dir("cli_check_invalid_manifest");
+
+    write_package(&dir, "opt-level = 4\n");
+
+    let output = crane(&dir, &["check"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("Crane.toml:6:13"), "{stderr}");
+    assert!(
+        stderr.contains("invalid opt-level `4`, expected 0 to 3"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_clean() {
+    let dir = test_dir("cli_clean");
+
+    write_package(&dir, "out-dir = \"out\"\n");
+    std::fs::create_dir_all(dir.join("out/cache")).unwrap();
+    std::fs::write(dir.join("out/hello"), "").unwrap();
+    std::fs::write(dir.join("out/cache/main.o"), "").unwrap();
+
+    let output = crane(&dir, &["clean", "--verbose"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Removed `out`\n");
+    assert!(!dir.join("out").exists());
+    assert!(dir.join("src/main.crane").exists());
+
+    // There's nothing left to clean.
+    let output = crane(&dir.join("src/nested"), &["clean"]);
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_clean_dry_run() {
+    let dir = test_dir("cli_clean_dry_run");
+
+    std::fs::create_dir_all(dir.join("build")).unwrap();
+
+    let output = crane(&dir, &["clean", "--dry-run"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Would remove `build`\n"
+    );
+    assert!(dir.join("build").exists());
+}
+
+#[test]
+fn test_clean_cache() {
+    let dir = test_dir("cli_clean_cache");
+    let project = dir.join("project");
+    let cache_dir = dir.join("home/.crane/cache");
+    let used = "ab".repeat(32);
+    let unused = "cd".repeat(32);
+
+    write_package(&project, "out-dir = \"out\"\n");
+    std::fs::create_dir_all(project.join("out")).unwrap();
+    std::fs::write(project.join("out/cache-entries"), format!("{used}\n")).unwrap();
+    std::fs::create_dir_all(&cache_dir).unwrap();
+
+    for key in [&used, &unused] {
+        std::fs::write(cache_dir.join(format!("{key}.o")), "").unwrap();
+        std::fs::write(cache_dir.join(format!("{key}.ll")), "").unwrap();
+    }
+
+    // The output directory is resolved against the project root, rather than
+    // the current directory.
+    let output = Command::new(env!("CARGO_BIN_EXE_crane"))
+        .current_dir(project.join("src/nested"))
+        .env("HOME", dir.join("home"))
+        .args(["clean", "--cache"])
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!project.join("out").exists());
+    assert!(!cache_dir.join(format!("{used}.o")).exists());
+    assert!(!cache_dir.join(format!("{used}.ll")).exists());
+    assert!(cache_dir.join(format!("{unused}.o")).exists());
+}
+
+#[test]
+fn test_clean_refuses_to_remove_outside_the_project() {
+    let dir = test_dir("cli_clean_outside");
+    let project = dir.join("project");
+
+    std::fs::create_dir_all(dir.join("outside")).unwrap();
+    std::fs::write(dir.join("outside/keep"), "").unwrap();
+
+    for out_dir in [
+        "../outside",
+        "src/../../outside",
+        ".",
+        dir.to_str().unwrap(),
+    ] {
+        write_package(&project, &format!("out-dir = {out_dir:?}\n"));
+
+        for args in [&["clean"][..], &["clean", "--dry-run"]] {
+            let output = crane(&project, args);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            assert_eq!(output.status.code(), Some(1), "{out_dir}");
+            assert!(stderr.contains("Refusing to remove"), "{stderr}");
+            assert!(output.stdout.is_empty());
+        }
+    }
+
+    assert!(dir.join("outside/keep").exists());
+    assert!(project.join("Crane.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_clean_refuses_to_follow_a_link_outside_the_project() {
+    let dir = test_dir("cli_clean_link_outside");
+    let project = dir.join("project");
+
+    write_package(&project, "");
+    std::fs::create_dir_all(dir.join("outside")).unwrap();
+    std::os::unix::fs::symlink(dir.join("outside"), project.join("build")).unwrap();
+
+    let output = crane(&project, &["clean"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(dir.join("outside").exists());
+}
+
+#[test]
+fn test_new_builds_and_runs() {
+    let dir = test_dir("cli_new");
+
+    let output = crane(&dir, &["new", "hello"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(std::fs::read_to_string(dir.join("hello/Crane.toml"))
+        .unwrap()
+        .contains("name = \"hello\""));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("hello/.gitignore")).unwrap(),
+        "/build\n"
+    );
+
+    let output = crane(&dir.join("hello"), &["run", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, world!\n");
+}
+
+#[test]
+fn test_new_lib_builds() {
+    let dir = test_dir("cli_new_lib");
+
+    let output = crane(&dir, &["new", "greetings", "--lib"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.join("greetings/src/lib.crane").exists());
+    assert!(!dir.join("greetings/src/main.crane").exists());
+
+    let output = crane(&dir.join("greetings"), &["build", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.join("greetings/build/libgreetings.a").exists());
+}
+
+#[test]
+fn test_new_rejects_invalid_names() {
+    let dir = test_dir("cli_new_invalid_name");
+
+    let output = crane(&dir, &["new", "hello-world"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`hello-world` is not a valid package name"));
+    assert!(!dir.join("hello-world").exists());
+
+    let output = crane(&dir, &["new", "hello-world", "--name", "hello_world"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(std::fs::read_to_string(dir.join("hello-world/Crane.toml"))
+        .unwrap()
+        .contains("name = \"hello_world\""));
+}
+
+#[test]
+fn test_init_builds() {
+    let dir = test_dir("cli_init").join("hello");
+
+    std::fs::create_dir(&dir).unwrap();
+
+    let output = crane(&dir, &["init"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = crane(&dir, &["build", "--no-cache"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.join("build/hello").exists());
+}
+
+#[test]
+fn test_init_does_not_overwrite_files() {
+    let dir = test_dir("cli_init_existing");
+
+    std::fs::write(dir.join(".gitignore"), "*.o\n").unwrap();
+
+    let output = crane(&dir, &["init", "--name", "hello"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(".gitignore` already exists"));
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "*.o\n"
+    );
+    assert!(!dir.join("Crane.toml").exists());
+}
+
+#[test]
+fn test_build_flags_take_precedence_over_the_manifest() {
+    let dir = test_dir("cli_build_manifest_precedence");
+
+    let build = |args: &[&str]| {
+        let output = crane(&dir, &[&["build", "--emit", "llvm-ir"], args].concat());
+
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        std::fs::read_to_string(dir.join("build/hello.ll")).unwrap()
+    };
+
+    write_package(&dir, "target = \"aarch64-unknown-linux-gnu\"\n");
+
+    assert!(build(&[]).contains("target triple = \"aarch64-unknown-linux-gnu\""));
+    assert!(build(&["--target", "x86_64-unknown-linux-gnu"])
+        .contains("target triple = \"x86_64-unknown-linux-gnu\""));
+}
+
+#[test]
+fn test_build_watch() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dir = test_dir("cli_build_watch");
+    let main = dir.join("main.crane");
+
+    // The build fails, which still waits for changes to rebuild.
+    std::fs::write(&main, "fn main() {\n    missing()\n}\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_crane"))
+        .current_dir(&dir)
+        .args(["build", "main.crane", "--watch", "--no-cache"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to run `crane`");
+
+    let (lines, received) = mpsc::channel();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+
+    std::thread::spawn(move || {
+        for line in stdout.lines() {
+            let _ = lines.send(line.unwrap());
+        }
+    });
+
+    let wait_for_build = || loop {
+        let line = received
+            .recv_timeout(Duration::from_secs(30))
+            .expect("Timed out waiting for a build");
+
+        if line == "Waiting for changes..." {
+            break;
+        }
+    };
+
+    wait_for_build();
+
+    // Saved by renaming over the file, like editors do.
+    let saved = dir.join(".main.crane.tmp");
+
+    std::fs::write(&saved, "fn main() {\n    missing_too()\n}\n").unwrap();
+    std::fs::rename(&saved, &main).unwrap();
+
+    wait_for_build();
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_check_reports_errors() {
+    let dir = test_dir("cli_check_error");
+
+    std::fs::write(
+        dir.join("broken.crane"),
+        "fn main() {\n    let value = missing()\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["check", "broken.crane"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Function `missing` does not exist."));
+    assert!(!dir.join("build").exists());
+}
+
+#[test]
+fn test_check_lint_levels() {
+    let dir = test_dir("cli_check_lint_levels");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "#[deprecated]\nfn old() -> Uint64 {\n    1\n}\n\nfn area() -> Uint64 {\n    old()\n}\n\nfn main() {}\n",
+    )
+    .unwrap();
+
+    let check = |args: &[&str]| {
+        let output = crane(&dir, &[&["check", "main.crane"], args].concat());
+        let stderr = strip_ansi_escapes::strip(&output.stderr).unwrap();
+
+        (output.status.code(), String::from_utf8(stderr).unwrap())
+    };
+
+    let (code, stderr) = check(&[]);
+
+    assert_eq!(code, Some(0));
+    assert!(
+        stderr.contains("Warning: Use of deprecated function `old`."),
+        "{stderr}"
+    );
+
+    let (code, stderr) = check(&["-D", "warnings"]);
+
+    assert_eq!(code, Some(4));
+    assert!(
+        stderr.contains("Error: Use of deprecated function `old`."),
+        "{stderr}"
+    );
+    assert!(stderr.contains("as `-D warnings` denies it"), "{stderr}");
+
+    let (code, stderr) = check(&["-D", "warnings", "-A", "deprecated"]);
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "");
+
+    let (code, stderr) = check(&["-A", "dead-code"]);
+
+    assert_eq!(code, Some(0));
+    assert!(
+        stderr.contains("Warning: Unknown lint `dead-code` in `-A dead-code`."),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_lint() {
+    let dir = test_dir("cli_lint");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        "fn main() {\n    let exitCode = missing()\n}\n\nfn cleanUp() {}\n",
+    )
+    .unwrap();
+
+    let lint = |args: &[&str]| {
+        let output = crane(&dir, &[&["lint", "main.crane"], args].concat());
+        let stderr = strip_ansi_escapes::strip(&output.stderr).unwrap();
+
+        (output.status.code(), String::from_utf8(stderr).unwrap())
+    };
+
+    // Nothing is type checked, so the missing function isn't an error.
+    let (code, stderr) = lint(&[]);
+
+    assert_eq!(code, Some(0));
+    assert!(
+        stderr.contains("Warning: Variable `exitCode` should have a snake_case name."),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Warning: Function `cleanUp` has an empty body."),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("does not exist"), "{stderr}");
+
+    let (code, stderr) = lint(&["-D", "non-snake-case"]);
+
+    assert_eq!(code, Some(4));
+    assert!(
+        stderr.contains("Error: Function `cleanUp` should have a snake_case name."),
+        "{stderr}"
+    );
+
+    let (code, stderr) = lint(&["-A", "warnings"]);
+
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn test_check_timings() {
+    let dir = test_dir("cli_check_timings");
+
+    std::fs::copy(hello_world(), dir.join("main.crane")).unwrap();
+
+    let output = crane(&dir, &["check", "main.crane", "--timings"]);
+
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let phases = stderr
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(phases, ["lex", "parse", "type_check"]);
+}
+
+#[test]
+fn test_build_timings_as_json() {
+    let dir = test_dir("cli_build_timings");
+
+    let output = crane(
+        &dir,
+        &["build", &hello_world(), "--no-cache", "--timings=json"],
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let timings = stderr.lines().last().unwrap();
+    let timings = serde_json::from_str::<Vec<serde_json::Value>>(timings).unwrap();
+
+    let phases = timings
+        .iter()
+        .map(|timing| timing["phase"].as_str().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        phases,
+        [
+            "lex",
+            "parse",
+            "type_check",
+            "codegen",
+            "optimize",
+            "emit",
+            "link"
+        ]
+    );
+}
+
+/// Runs `crane` with the given arguments in the given directory, writing
+/// `stdin` to its standard input.
+fn crane_with_stdin(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_crane"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run `crane`");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_check_stdin() {
+    let dir = test_dir("cli_check_stdin");
+
+    let source = std::fs::read_to_string(hello_world()).unwrap();
+
+    let output = crane_with_stdin(&dir, &["check", "--stdin"], &source);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = crane_with_stdin(
+        &dir,
+        &["check", "--stdin"],
+        "fn main() {\n    missing()\n}\n",
+    );
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("<stdin>"));
+}
+
+#[test]
+fn test_build_stdin() {
+    let dir = test_dir("cli_build_stdin");
+
+    let source = std::fs::read_to_string(hello_world()).unwrap();
+
+    let output = crane_with_stdin(&dir, &["build", "--stdin", "--no-cache"], &source);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.join("build/stdin").exists());
+}
+
+#[test]
+fn test_repl() {
+    let dir = test_dir("cli_repl");
+
+    let output = crane_with_stdin(
+        &dir,
+        &["repl"],
+        "fn add(a: Uint64, b: Uint64) -> Uint64 {\n    a + b\n}\nadd(1, 2)\nadd(1)\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "> ... ... > 3\n> > "
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("<repl>"));
+}
+
+#[test]
+fn test_test() {
+    let dir = test_dir("cli_test");
+
+    std::fs::write(
+        dir.join("main.crane"),
+        r#"use std::process::assert
+
+fn add(a: Uint64, b: Uint64) -> Uint64 {
+    a + b
+}
+
+fn main() {}
+
+#[test]
+fn adds() {
+    assert(add(1, 2) == 3, "1 + 2 is 3")
+}
+
+#[test]
+fn adds_wrong() {
+    assert(add(1, 2) == 4, "1 + 2 is 4")
+}
+"#,
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["test", "main.crane"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("test adds ... ok"), "{stdout}");
+    assert!(stdout.contains("test adds_wrong ... FAILED"), "{stdout}");
+    assert!(stdout.contains("1 + 2 is 4"), "{stdout}");
+    assert!(
+        stdout.ends_with("test result: FAILED. 1 passed; 1 failed; 0 filtered out\n"),
+        "{stdout}"
+    );
+
+    let output = crane(&dir, &["test", "main.crane", "--", "adds_wrong"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout.ends_with("test result: FAILED. 0 passed; 1 failed; 1 filtered out\n"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn test_check_color() {
+    let dir = test_dir("cli_check_color");
+
+    std::fs::write(dir.join("main.crane"), "fn main() {\n    greet()\n}\n").unwrap();
+
+    let check = |args: &[&str], no_color: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_crane"));
+
+        command
+            .current_dir(&dir)
+            .args([&["check", "main.crane"], args].concat())
+            .env_remove("NO_COLOR");
+
+        if let Some(no_color) = no_color {
+            command.env("NO_COLOR", no_color);
+        }
+
+        let output = command.output().expect("Failed to run `crane`");
+
+        assert_eq!(output.status.code(), Some(4));
+
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    // The output is piped, so it isn't colored unless that's forced.
+    for (args, no_color, colored) in [
+        (&[][..], None, false),
+        (&["--color=auto"][..], None, false),
+        (&["--color=never"][..], None, false),
+        (&["--color=always"][..], None, true),
+        (&["--color=always"][..], Some("1"), true),
+    ] {
+        let stderr = check(args, no_color);
+
+        assert_eq!(stderr.contains('\x1b'), colored, "{args:?}: {stderr}");
+        assert!(
+            stderr.contains("Function `greet` does not exist."),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_explain() {
+    let dir = test_dir("cli_explain");
+
+    let output = crane(&dir, &["explain", "E0204"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("# E0204: "));
+
+    let output = crane(&dir, &["explain", "E9999"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`E9999` is not an error code."));
+}
+
+#[test]
+fn test_symbols() {
+    let dir = test_dir("cli_symbols");
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/symbols/main.crane");
+
+    let output = crane(
+        &dir,
+        &[
+            "symbols",
+            &fixture.display().to_string(),
+            "--include-locals",
+        ],
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let symbols = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    let symbols = symbols.as_array().unwrap();
+
+    let summary = symbols
+        .iter()
+        .map(|symbol| {
+            let path = symbol["path"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|segment| segment.as_str().unwrap())
+                .collect::<Vec<_>>()
+                .join("::");
+
+            (symbol["kind"].as_str().unwrap().to_string(), path)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        [
+            ("mod", "shapes"),
+            ("fn", "shapes::side"),
+            ("const", "SIDES"),
+            ("struct", "Point"),
+            ("union", "Shape"),
+            ("mod", "geometry"),
+            ("fn", "geometry::area"),
+            ("fn", "main"),
+        ]
+        .map(|(kind, path)| (kind.to_string(), path.to_string()))
+    );
+
+    let area = &symbols[6];
+
+    assert_eq!(area["vis"], "Public");
+    assert_eq!(area["location"]["start"]["line"], 16);
+    assert_eq!(area["params"][1]["name"], "height");
+    assert_eq!(area["params"][1]["ty"], "Uint64");
+    assert_eq!(area["return_ty"], "Uint64");
+    assert_eq!(area["locals"][0]["name"], "area");
+
+    let side = &symbols[1];
+
+    assert!(side["location"]["file"]
+        .as_str()
+        .unwrap()
+        .ends_with("shapes.crane"));
+    assert_eq!(side["location"]["start"]["line"], 1);
+}
+
+#[test]
+fn test_check_multiple_files() {
+    let dir = test_dir("cli_check_multiple");
+
+    std::fs::copy(hello_world(), dir.join("good.crane")).unwrap();
+    std::fs::write(
+        dir.join("bad.crane"),
+        "fn main() {\n    let value = missing()\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(&dir, &["check", "good.crane", "bad.crane"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Function `missing` does not exist."));
+}
+
+#[test]
+fn test_build_multiple_files() {
+    let dir = test_dir("cli_build_multiple");
+
+    std::fs::copy(hello_world(), dir.join("good.crane")).unwrap();
+    std::fs::write(
+        dir.join("bad.crane"),
+        "fn main() {\n    let value = missing()\n}\n",
+    )
+    .unwrap();
+
+    let output = crane(
+        &dir,
+        &[
+            "build",
+            "good.crane",
+            "bad.crane",
+            "--out-dir",
+            "out",
+            "--no-cache",
+        ],
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(4), "{stderr}");
+    assert!(stderr.contains("Function `missing` does not exist."));
+    assert!(dir.join("out/good").exists(), "`out/good` was not written");
+    assert!(!dir.join("out/bad").exists());
+}
+
+#[test]
+fn test_build_multiple_files_with_the_same_name() {
+    let dir = test_dir("cli_build_same_name");
+
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::create_dir_all(dir.join("b")).unwrap();
+    std::fs::copy(hello_world(), dir.join("a/hello.crane")).unwrap();
+    std::fs::copy(hello_world(), dir.join("b/hello.crane")).unwrap();
+
+    let output = crane(
+        &dir,
+        &[
+            "build",
+            "a/hello.crane",
+            "b/hello.crane",
+            "--out-dir",
+            "out",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("`a/hello.crane` and `b/hello.crane` would both be built as `hello` in `out`."));
+    assert!(!dir.join("out").exists());
+}
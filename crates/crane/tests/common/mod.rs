@@ -0,0 +1,71 @@
+//! Helpers for the integration tests that compile programs for the host.
+
+// Each test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+use inkwell::targets::TargetMachine;
+
+/// Returns the options to compile an executable for the host into the given
+/// directory.
+pub fn host_options(output_dir: &Path) -> CompileOptions {
+    CompileOptions {
+        target_triple: TargetMachine::get_default_triple()
+            .as_str()
+            .to_string_lossy()
+            .into_owned(),
+        output_dir: output_dir.to_path_buf(),
+        ..CompileOptions::default()
+    }
+}
+
+/// Returns the directory for the given test to write its outputs to, with the
+/// outputs of any previous run removed.
+pub fn output_dir(name: &str) -> PathBuf {
+    let output_dir = std::env::temp_dir().join("crane-tests").join(name);
+    let _ = std::fs::remove_dir_all(&output_dir);
+
+    output_dir
+}
+
+/// Compiles the given input, returning the paths of the outputs and the
+/// diagnostics written to stderr.
+///
+/// Panics with the diagnostics if the input fails to compile.
+pub fn compile_with_options(input: Input, options: CompileOptions) -> (Vec<PathBuf>, String) {
+    let params = CompileParams { input, options };
+
+    let mut stderr = Vec::new();
+
+    let outputs = Compiler::new()
+        .compile(&mut stderr, params)
+        .unwrap_or_else(|_| panic!("{}", String::from_utf8_lossy(&stderr)));
+
+    (outputs, String::from_utf8_lossy(&stderr).into_owned())
+}
+
+/// Compiles the given program into an executable for the host, returning the
+/// path of the executable and the diagnostics written to stderr.
+pub fn compile(name: &str, source: &str) -> (PathBuf, String) {
+    let output_dir = output_dir(name);
+
+    let (_, stderr) = compile_with_options(
+        Input::String {
+            filename: format!("{name}.crane"),
+            input: source.to_string(),
+        },
+        host_options(&output_dir),
+    );
+
+    (output_dir.join(name), stderr)
+}
+
+/// Compiles the given program into an executable for the host and runs it.
+pub fn compile_and_run(name: &str, source: &str) -> Output {
+    let (executable, _) = compile(name, source);
+
+    Command::new(executable).output().unwrap()
+}
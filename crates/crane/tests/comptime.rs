@@ -0,0 +1,58 @@
+mod common;
+
+#[test]
+fn test_comptime_blocks_and_control_flow() {
+    let output = common::compile_and_run(
+        "comptime",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+fn fib(n: Uint64) -> Uint64 {
+    let a = 0
+    let b = 1
+
+    for i in 0..n {
+        let next = a + b
+        a = b
+        b = next
+    }
+
+    a
+}
+
+fn first(values: [Uint64; comptime { 1 + 2 }]) -> Uint64 {
+    values[0]
+}
+
+fn main() {
+    let limit = comptime {
+        let total = 0
+
+        for i in 1..5 {
+            if i % 2 == 0 {
+                total = total + i * 10
+            } else {
+                total = total + i
+            }
+        }
+
+        total
+    }
+
+    println(int_to_string(limit))
+    println(int_to_string(fib(10)))
+    println(int_to_string(first([7, 8, 9])))
+
+    let parity = if fib(10) % 2 == 0 { "even" } else if fib(10) < limit { "small and odd" } else { "odd" }
+    println(parity)
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "64\n55\n7\nsmall and odd\n"
+    );
+}
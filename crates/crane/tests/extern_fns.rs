@@ -0,0 +1,71 @@
+use std::process::Command;
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+
+mod common;
+
+#[test]
+fn test_extern_fn_from_a_linked_library() {
+    let output_dir = common::output_dir("extern_fns");
+
+    common::compile_with_options(
+        Input::String {
+            filename: "extern_fns.crane".into(),
+            input: r#"
+use std::float::float_to_string
+use std::io::println
+
+extern fn sqrt(x: Float64) -> Float64
+
+fn main() {
+    println(float_to_string(unsafe { sqrt(2.25) }))
+}
+"#
+            .into(),
+        },
+        CompileOptions {
+            link_libraries: vec!["m".to_string()],
+            ..common::host_options(&output_dir)
+        },
+    );
+
+    let output = Command::new(output_dir.join("extern_fns"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1.5\n");
+}
+
+#[test]
+fn test_missing_library_fails_to_link() {
+    let mut compiler = Compiler::new();
+
+    let output_dir = common::output_dir("missing_library");
+
+    let params = CompileParams {
+        input: Input::String {
+            filename: "missing_library.crane".into(),
+            input: r#"
+use std::int::int_to_string
+use std::io::println
+
+#[link(name = "crane_missing")]
+extern fn crane_missing_fn(x: Uint64) -> Uint64
+
+fn main() {
+    println(int_to_string(unsafe { crane_missing_fn(1) }))
+}
+"#
+            .into(),
+        },
+        options: common::host_options(&output_dir),
+    };
+
+    let mut stderr = Vec::new();
+
+    assert!(compiler.compile(&mut stderr, params).is_err());
+
+    let stderr = String::from_utf8_lossy(&stderr);
+    assert!(stderr.contains("failed to link"), "{stderr}");
+    assert!(stderr.contains("crane_missing"), "{stderr}");
+}
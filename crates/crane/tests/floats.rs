@@ -0,0 +1,20 @@
+mod common;
+
+#[test]
+fn test_float_arithmetic() {
+    let output = common::compile_and_run(
+        "floats",
+        r#"
+use std::float::float_to_string
+use std::io::println
+
+fn main() {
+    println(float_to_string(1.5 + 2.25))
+    println(float_to_string(7 as Float64 / 2.0))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3.75\n3.5\n");
+}
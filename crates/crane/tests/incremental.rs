@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crane::compiler::{CompileOptions, Input};
+
+mod common;
+
+const MAIN: &str = r#"
+use std::int::int_to_string
+use std::io::println
+
+mod net
+mod util
+
+fn main() {
+    println(int_to_string(util::value()))
+    net::ping()
+}
+"#;
+
+const UTIL: &str = r#"
+pub fn value() -> Uint64 {
+    1
+}
+"#;
+
+const NET: &str = r#"
+use std::io::println
+
+pub fn ping() {
+    println("pong")
+}
+"#;
+
+fn compile(input: &Path, output_dir: &Path, incremental: bool) -> Vec<PathBuf> {
+    compile_with_options(
+        input,
+        CompileOptions {
+            incremental,
+            ..common::host_options(output_dir)
+        },
+    )
+}
+
+fn compile_with_options(input: &Path, options: CompileOptions) -> Vec<PathBuf> {
+    let (outputs, _) = common::compile_with_options(Input::File(input.to_path_buf()), options);
+
+    outputs
+}
+
+/// Returns the IR of every unit in the cache.
+fn cached_units(output_dir: &Path) -> HashSet<String> {
+    std::fs::read_dir(output_dir.join("cache"))
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ll"))
+                .map(|path| std::fs::read_to_string(path).unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Builds the program and returns the units that had to be recompiled, by
+/// the name of their LLVM module, along with the output of the program.
+fn rebuild(input: &Path, output_dir: &Path) -> (Vec<String>, String) {
+    let before = cached_units(output_dir);
+
+    let outputs = compile(input, output_dir, true);
+
+    let mut recompiled = cached_units(output_dir)
+        .difference(&before)
+        .map(|ir| {
+            let module_id = ir.lines().next().unwrap();
+            module_id
+                .trim_start_matches("; ModuleID = '")
+                .trim_end_matches('\'')
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    recompiled.sort();
+
+    let output = Command::new(outputs.last().unwrap()).output().unwrap();
+    assert!(output.status.success());
+
+    (
+        recompiled,
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_incremental_rebuilds() {
+    let dir = common::output_dir("incremental");
+
+    let src = dir.join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(src.join("main.crane"), MAIN).unwrap();
+    std::fs::write(src.join("util.crane"), UTIL).unwrap();
+    std::fs::write(src.join("net.crane"), NET).unwrap();
+
+    let input = src.join("main.crane");
+    let output_dir = dir.join("build");
+
+    assert_eq!(
+        rebuild(&input, &output_dir),
+        (
+            vec!["main".into(), "net".into(), "util".into()],
+            "1\npong\n".into()
+        )
+    );
+
+    // Nothing changed, so every unit is reused.
+    assert_eq!(rebuild(&input, &output_dir), (vec![], "1\npong\n".into()));
+
+    // Editing the body of a function only invalidates its own module.
+    std::fs::write(src.join("util.crane"), UTIL.replace("1", "2")).unwrap();
+
+    assert_eq!(
+        rebuild(&input, &output_dir),
+        (vec!["util".into()], "2\npong\n".into())
+    );
+
+    // Editing the signature of a `pub` function also invalidates the modules
+    // that call it, but not the others.
+    std::fs::write(
+        src.join("util.crane"),
+        "pub fn value(offset: Uint64) -> Uint64 {\n    offset + 2\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("main.crane"),
+        MAIN.replace("util::value()", "util::value(1)"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        rebuild(&input, &output_dir),
+        (vec!["main".into(), "util".into()], "3\npong\n".into())
+    );
+}
+
+#[test]
+fn test_debug_builds_dont_reuse_release_units() {
+    let dir = common::output_dir("incremental_debug");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.crane"), MAIN).unwrap();
+    std::fs::write(dir.join("util.crane"), UTIL).unwrap();
+    std::fs::write(dir.join("net.crane"), NET).unwrap();
+
+    let input = dir.join("main.crane");
+    let output_dir = dir.join("build");
+
+    let cached_objects = || {
+        std::fs::read_dir(output_dir.join("cache"))
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().is_some_and(|extension| extension == "o")
+            })
+            .count()
+    };
+
+    compile(&input, &output_dir, true);
+
+    assert_eq!(cached_objects(), 3);
+
+    // `todo()` aborts in debug builds, but is unreachable in release builds,
+    // so none of the units of one can be reused by the other.
+    compile_with_options(
+        &input,
+        CompileOptions {
+            debug: true,
+            ..common::host_options(&output_dir)
+        },
+    );
+
+    assert_eq!(cached_objects(), 6);
+}
+
+#[test]
+fn test_no_cache_recompiles_everything() {
+    let dir = common::output_dir("incremental_no_cache");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.crane"), MAIN).unwrap();
+    std::fs::write(dir.join("util.crane"), UTIL).unwrap();
+    std::fs::write(dir.join("net.crane"), NET).unwrap();
+
+    let output_dir = dir.join("build");
+
+    compile(&dir.join("main.crane"), &output_dir, false);
+
+    assert!(!output_dir.join("cache").exists());
+}
+
+#[test]
+fn test_shared_cache_dir() {
+    let dir = common::output_dir("incremental_shared_cache");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.crane"), MAIN).unwrap();
+    std::fs::write(dir.join("util.crane"), UTIL).unwrap();
+    std::fs::write(dir.join("net.crane"), NET).unwrap();
+
+    let cache_dir = dir.join("shared-cache");
+
+    let build = |output_dir: &str| {
+        compile_with_options(
+            &dir.join("main.crane"),
+            CompileOptions {
+                cache_dir: Some(cache_dir.clone()),
+                ..common::host_options(&dir.join(output_dir))
+            },
+        )
+    };
+
+    build("first");
+
+    assert!(!dir.join("first").join("cache").exists());
+
+    // Reused units are copied from the cache, so marking the cached IR shows
+    // which units the second build reused.
+    for entry in std::fs::read_dir(&cache_dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.extension().is_some_and(|extension| extension == "ll") {
+            let ir = std::fs::read_to_string(&path).unwrap();
+            std::fs::write(&path, format!("; Cached\n{ir}")).unwrap();
+        }
+    }
+
+    let outputs = build("second");
+
+    let reused = outputs
+        .iter()
+        .filter(|output| {
+            output
+                .extension()
+                .is_some_and(|extension| extension == "ll")
+        })
+        .filter(|output| {
+            std::fs::read_to_string(output)
+                .unwrap()
+                .starts_with("; Cached")
+        })
+        .count();
+    assert_eq!(reused, 3);
+}
@@ -0,0 +1,268 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+/// Returns an empty directory for the given test to open files in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("crane-lsp-tests").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// A `crane lsp` process, spoken to over JSON-RPC through its stdin and
+/// stdout.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    /// Starts the server and initializes it.
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_crane"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run `crane lsp`");
+
+        let mut client = Self {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            next_id: 0,
+        };
+
+        let capabilities = client.request("initialize", json!({ "capabilities": {} }));
+
+        assert_eq!(capabilities["capabilities"]["definitionProvider"], true);
+
+        client.notify("initialized", json!({}));
+
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+
+            let header = header.trim_end();
+
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Sends a request, returning the result of its response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+
+        let id = self.next_id;
+
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+
+            if message["id"] == id {
+                return message["result"].clone();
+            }
+        }
+    }
+
+    /// Waits for the diagnostics of the next document they're published for.
+    fn diagnostics(&mut self) -> Value {
+        loop {
+            let message = self.receive();
+
+            if message["method"] == "textDocument/publishDiagnostics" {
+                return message["params"].clone();
+            }
+        }
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn file_uri(path: PathBuf) -> String {
+    format!("file://{}", path.display())
+}
+
+#[test]
+fn test_diagnostics() {
+    let dir = test_dir("diagnostics");
+    let uri = file_uri(dir.join("main.crane"));
+
+    let mut client = Client::start();
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "crane",
+                "version": 1,
+                "text": "fn main() {\n    missing()\n}\n",
+            }
+        }),
+    );
+
+    let params = client.diagnostics();
+
+    assert_eq!(params["uri"], uri);
+    assert_eq!(
+        params["diagnostics"],
+        json!([{
+            "range": {
+                "start": { "line": 1, "character": 4 },
+                "end": { "line": 1, "character": 11 },
+            },
+            "severity": 1,
+            "code": "E0204",
+            "source": "crane",
+            "message": "Function `missing` does not exist.",
+            "relatedInformation": [{
+                "location": {
+                    "uri": uri,
+                    "range": {
+                        "start": { "line": 0, "character": 3 },
+                        "end": { "line": 0, "character": 7 },
+                    },
+                },
+                "message": "There is a function with a similar name: `main`.",
+            }],
+        }])
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "fn main() {}\n" }],
+        }),
+    );
+
+    assert_eq!(client.diagnostics()["diagnostics"], json!([]));
+
+    client.shutdown();
+}
+
+#[test]
+fn test_definition_and_symbols() {
+    let dir = test_dir("definition");
+    let uri = file_uri(dir.join("main.crane"));
+
+    std::fs::write(
+        dir.join("math.crane"),
+        "/// Adds two numbers.\npub fn add(a: Uint64, b: Uint64) -> Uint64 {\n    a + b\n}\n",
+    )
+    .unwrap();
+
+    let mut client = Client::start();
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "crane",
+                "version": 1,
+                "text": "mod math\n\nstruct Point {\n    x: Uint64,\n}\n\nfn origin() -> Point {\n    Point { x: math::add(1, 2) }\n}\n\nfn main() {}\n",
+            }
+        }),
+    );
+
+    assert_eq!(client.diagnostics()["diagnostics"], json!([]));
+
+    // The return type of `origin`.
+    let definition = client.request(
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": uri }, "position": { "line": 6, "character": 16 } }),
+    );
+
+    assert_eq!(
+        definition,
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": 2, "character": 7 },
+                "end": { "line": 2, "character": 12 },
+            },
+        })
+    );
+
+    // The call to `math::add`, in another file.
+    let definition = client.request(
+        "textDocument/definition",
+        json!({ "textDocument": { "uri": uri }, "position": { "line": 7, "character": 22 } }),
+    );
+
+    assert_eq!(definition["uri"], file_uri(dir.join("math.crane")));
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 1, "character": 7 })
+    );
+
+    let hover = client.request(
+        "textDocument/hover",
+        json!({ "textDocument": { "uri": uri }, "position": { "line": 7, "character": 22 } }),
+    );
+
+    assert_eq!(
+        hover["contents"]["value"],
+        "```crane\npub fn add(a: Uint64, b: Uint64) -> Uint64\n```\n\nAdds two numbers."
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+
+    let names = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["math", "Point", "origin", "main"]);
+    assert_eq!(symbols[1]["children"][0]["name"], "x");
+
+    client.shutdown();
+}
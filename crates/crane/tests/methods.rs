@@ -0,0 +1,37 @@
+mod common;
+
+#[test]
+fn test_methods() {
+    let output = common::compile_and_run(
+        "methods",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+struct Point {
+    x: Uint64,
+    y: Uint64,
+}
+
+impl Point {
+    fn sum(self: Point) -> Uint64 {
+        self.x + self.y
+    }
+
+    fn scaled(self: Point, factor: Uint64) -> Point {
+        Point { x: self.x * factor, y: self.y * factor }
+    }
+}
+
+fn main() {
+    let p = Point { x: 3, y: 4 }
+    println(int_to_string(p.sum()))
+    println(int_to_string(p.scaled(10).sum()))
+    println(int_to_string(Point::sum(p)))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7\n70\n7\n");
+}
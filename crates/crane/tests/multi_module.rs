@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+
+mod common;
+
+#[test]
+fn test_link_multiple_modules() {
+    let output_dir = common::output_dir("multi_module");
+
+    let (outputs, _) = common::compile_with_options(
+        Input::File("tests/multi_module/main.crane".into()),
+        common::host_options(&output_dir),
+    );
+
+    assert_eq!(
+        outputs,
+        ["main.ll", "main.o", "util.ll", "util.o", "main"]
+            .map(|filename| output_dir.join(filename))
+    );
+
+    let main_ir = std::fs::read_to_string(output_dir.join("main.ll")).unwrap();
+    assert!(main_ir.contains(r#"declare void @"util::greet"()"#));
+
+    let util_ir = std::fs::read_to_string(output_dir.join("util.ll")).unwrap();
+    assert!(util_ir.contains(r#"define void @"util::greet"()"#));
+
+    let output = Command::new(output_dir.join("main")).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello from util!\n"
+    );
+}
+
+/// Returns an empty directory for the given test to write a package in.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("crane-multi-module-tests")
+        .join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// Writes the given files to `dir`, creating their directories.
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        let path = dir.join(path);
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+/// Type checks the given file, returning the diagnostics it reports.
+fn check(file: PathBuf) -> Result<(), String> {
+    let params = CompileParams {
+        input: Input::File(file),
+        options: CompileOptions::default(),
+    };
+
+    let mut stderr = Vec::new();
+
+    Compiler::new().check(&mut stderr, params).map_err(|_| {
+        let stderr = strip_ansi_escapes::strip(stderr).unwrap();
+
+        String::from_utf8(stderr).unwrap()
+    })
+}
+
+#[test]
+fn test_nested_module_files() {
+    let dir = test_dir("nested");
+
+    write_files(
+        &dir,
+        &[
+            (
+                "src/main.crane",
+                "mod text\n\nfn main() {\n    text::shout::greet(\"x\")\n}\n",
+            ),
+            ("src/text/mod.crane", "pub mod shout\n"),
+            (
+                "src/text/shout.crane",
+                "use std::io::println\n\npub fn greet(name: String) {\n    println(name)\n}\n",
+            ),
+        ],
+    );
+
+    check(dir.join("src/main.crane")).unwrap();
+}
+
+#[test]
+fn test_errors_are_reported_in_the_module_file() {
+    let dir = test_dir("errors");
+
+    write_files(
+        &dir,
+        &[
+            (
+                "main.crane",
+                "mod util\n\nfn main() {\n    util::greet(\"x\")\n}\n",
+            ),
+            (
+                "util.crane",
+                "pub fn greet(name: String) {\n    missing(name)\n}\n",
+            ),
+        ],
+    );
+
+    let stderr = check(dir.join("main.crane")).unwrap_err();
+
+    assert!(
+        stderr.contains(&format!("{}:2:5", dir.join("util.crane").display())),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_sibling_module_files_are_parsed_together() {
+    let dir = test_dir("siblings");
+
+    write_files(
+        &dir,
+        &[
+            (
+                "main.crane",
+                "mod first\nmod second\nmod third\n\nfn main() {\n    first::run()\n}\n",
+            ),
+            ("first.crane", "pub fn run() {}\n"),
+            ("second.crane", "pub fn run( {}\n"),
+            ("third.crane", "pub fn run() {}\n"),
+        ],
+    );
+
+    let stderr = check(dir.join("main.crane")).unwrap_err();
+
+    assert!(
+        stderr.contains(&format!("{}:1:", dir.join("second.crane").display())),
+        "{stderr}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cyclic_modules() {
+    let dir = test_dir("cycle");
+
+    write_files(
+        &dir,
+        &[
+            ("main.crane", "mod a\n\nfn main() {}\n"),
+            ("a.crane", "mod b\n"),
+        ],
+    );
+
+    std::fs::create_dir(dir.join("a")).unwrap();
+    std::os::unix::fs::symlink(dir.join("a.crane"), dir.join("a/b.crane")).unwrap();
+
+    let stderr = check(dir.join("main.crane")).unwrap_err();
+
+    let a = dir.join("a.crane").canonicalize().unwrap();
+
+    assert!(stderr.contains("Cyclic module declaration."), "{stderr}");
+    assert!(
+        stderr.contains(&format!(
+            "Module `b` is loaded from a file that declares it: `{}` -> `{}`.",
+            a.display(),
+            a.display()
+        )),
+        "{stderr}"
+    );
+}
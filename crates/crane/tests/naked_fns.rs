@@ -0,0 +1,26 @@
+#![cfg(target_arch = "x86_64")]
+
+mod common;
+
+#[test]
+fn test_naked_fn_returns_from_its_assembly() {
+    let output = common::compile_and_run(
+        "naked_fns",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+#[naked]
+fn answer() -> Uint64 {
+    asm("movq $$42, %rax", "retq")
+}
+
+fn main() {
+    println(int_to_string(answer()))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+}
@@ -0,0 +1,32 @@
+mod common;
+
+#[test]
+fn test_nested_fns_can_be_called_from_their_parent() {
+    let output = common::compile_and_run(
+        "nested_fns",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+fn main() {
+    fn shout(msg: String) {
+        println(msg)
+    }
+
+    fn countdown(n: Uint64) -> Uint64 {
+        if n == 0 {
+            0
+        } else {
+            countdown(n - 1)
+        }
+    }
+
+    shout("Hello")
+    shout(int_to_string(countdown(3)))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello\n0\n");
+}
@@ -0,0 +1,110 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crane::compiler::{CompileOptions, Input};
+
+mod common;
+
+/// Writes a program with the given number of modules, each in its own file,
+/// to `dir`, returning the path of the root module.
+fn write_program(dir: &Path, modules: usize, fns_per_module: usize) -> PathBuf {
+    std::fs::create_dir_all(dir).unwrap();
+
+    let mut main = String::from("use std::int::int_to_string\nuse std::io::println\n\n");
+
+    for module in 0..modules {
+        writeln!(main, "mod m{module}").unwrap();
+
+        let mut source = String::new();
+
+        for index in 0..fns_per_module {
+            writeln!(
+                source,
+                "pub fn f{index}(x: Uint64) -> Uint64 {{\n    let y = x * 3 + {}\n    y % 1000\n}}\n",
+                module + index
+            )
+            .unwrap();
+        }
+
+        source.push_str("pub fn chain(x: Uint64) -> Uint64 {\n    let x0 = x\n");
+        for index in 0..fns_per_module {
+            writeln!(
+                source,
+                "    let x{} = m{module}::f{index}(x{index})",
+                index + 1
+            )
+            .unwrap();
+        }
+        writeln!(source, "    x{fns_per_module}\n}}").unwrap();
+
+        std::fs::write(dir.join(format!("m{module}.crane")), source).unwrap();
+    }
+
+    main.push_str("\nfn main() {\n");
+    for module in 0..modules {
+        writeln!(main, "    println(int_to_string(m{module}::chain(1)))").unwrap();
+    }
+    main.push_str("}\n");
+
+    let main_path = dir.join("main.crane");
+    std::fs::write(&main_path, main).unwrap();
+
+    main_path
+}
+
+fn compile(input: &Path, output_dir: &Path, jobs: usize) -> Vec<PathBuf> {
+    let (outputs, _) = common::compile_with_options(
+        Input::File(input.to_path_buf()),
+        CompileOptions {
+            jobs,
+            ..common::host_options(output_dir)
+        },
+    );
+
+    outputs
+}
+
+#[test]
+fn test_parallel_codegen_is_deterministic() {
+    let dir = common::output_dir("parallel_codegen");
+
+    let input = write_program(&dir.join("src"), 8, 20);
+
+    let serial_outputs = compile(&input, &dir.join("serial"), 1);
+    let parallel_outputs = compile(&input, &dir.join("parallel"), 4);
+
+    let filenames = |outputs: &[PathBuf]| {
+        outputs
+            .iter()
+            .map(|output| output.file_name().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(filenames(&serial_outputs), filenames(&parallel_outputs));
+
+    for (serial, parallel) in serial_outputs.iter().zip(&parallel_outputs) {
+        if serial
+            .extension()
+            .is_some_and(|extension| extension == "ll")
+        {
+            assert_eq!(
+                std::fs::read_to_string(serial).unwrap(),
+                std::fs::read_to_string(parallel).unwrap(),
+                "{} differs between serial and parallel codegen",
+                serial.display()
+            );
+        }
+    }
+
+    let serial = Command::new(serial_outputs.last().unwrap())
+        .output()
+        .unwrap();
+    let parallel = Command::new(parallel_outputs.last().unwrap())
+        .output()
+        .unwrap();
+
+    assert!(parallel.status.success());
+    assert_eq!(serial.stdout, parallel.stdout);
+    assert_eq!(String::from_utf8_lossy(&parallel.stdout).lines().count(), 8);
+}
@@ -0,0 +1,69 @@
+use std::process::Command;
+
+mod common;
+
+#[test]
+fn test_exit_code() {
+    let (executable, stderr) = common::compile(
+        "exit_code",
+        r#"
+use std::io::println
+use std::process::exit
+
+fn checked_half(value: Uint64) -> Uint64 {
+    if value % 2 == 1 {
+        exit(value)
+    } else {
+        value / 2
+    }
+}
+
+fn main() {
+    println("Exiting...")
+    exit(checked_half(14))
+    println("Unreachable.")
+}
+"#,
+    );
+
+    assert!(stderr.contains("Unreachable code."), "{stderr}");
+
+    let output = Command::new(executable).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Exiting...\n");
+}
+
+#[test]
+fn test_abort_with_a_message() {
+    let (executable, stderr) = common::compile(
+        "abort_message",
+        r#"
+use std::int::int_to_string
+use std::io::println
+use std::process::abort
+
+fn checked_div(a: Uint64, b: Uint64) -> Uint64 {
+    if b == 0 {
+        abort("Division by zero.")
+    } else {
+        a / b
+    }
+}
+
+fn main() {
+    println(int_to_string(checked_div(6, 3)))
+    println(int_to_string(checked_div(1, 0)))
+}
+"#,
+    );
+
+    assert!(!stderr.contains("Unreachable code."), "{stderr}");
+
+    let output = Command::new(executable).output().unwrap();
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Division by zero.\n"
+    );
+}
@@ -0,0 +1,26 @@
+use std::process::Command;
+
+mod common;
+
+#[test]
+fn test_program_args() {
+    let (executable, _) = common::compile(
+        "program_args",
+        r#"
+use std::env::arg
+use std::io::println
+
+fn main() {
+    println(arg(1))
+    println(arg(2))
+}
+"#,
+    );
+
+    let output = Command::new(executable)
+        .args(["hello", "world"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello\nworld\n");
+}
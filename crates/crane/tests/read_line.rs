@@ -0,0 +1,59 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crane::compiler::Input;
+
+mod common;
+
+/// Builds the `greet` example into the given directory.
+fn build_greet_example(output_dir: &Path) {
+    common::compile_with_options(
+        Input::File("../../examples/greet.crane".into()),
+        common::host_options(output_dir),
+    );
+}
+
+/// Runs the built executable with the given input on stdin, returning its
+/// stdout.
+fn run_with_stdin(executable: &Path, input: &str) -> String {
+    let mut child = Command::new(executable)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_read_line_from_stdin() {
+    let output_dir = common::output_dir("read_line");
+
+    build_greet_example(&output_dir);
+
+    let executable = output_dir.join("greet");
+
+    assert_eq!(
+        run_with_stdin(&executable, "Crane\nignored\n"),
+        "What's your name? Hello, Crane\n"
+    );
+    assert_eq!(
+        run_with_stdin(&executable, "Crane"),
+        "What's your name? Hello, Crane\n"
+    );
+    assert_eq!(
+        run_with_stdin(&executable, ""),
+        "What's your name? Goodbye!\n"
+    );
+}
@@ -0,0 +1,64 @@
+use std::process::Command;
+
+use crane::compiler::{CompileOptions, Input, OutputKind};
+
+mod common;
+
+#[test]
+fn test_link_static_lib_from_c() {
+    let output_dir = common::output_dir("static_lib");
+
+    common::compile_with_options(
+        Input::String {
+            filename: "crane_add.crane".into(),
+            input: r#"
+use std::int::int_add
+
+pub fn crane_add(a: Uint64, b: Uint64) -> Uint64 {
+    int_add(a, b)
+}
+
+fn not_exported() {}
+            "#
+            .trim()
+            .to_string(),
+        },
+        CompileOptions {
+            output_kind: OutputKind::StaticLib,
+            ..common::host_options(&output_dir)
+        },
+    );
+
+    let ir = std::fs::read_to_string(output_dir.join("crane_add.ll")).unwrap();
+    assert!(ir.contains("define i64 @crane_add("));
+    assert!(ir.contains("define internal void @not_exported()"));
+
+    std::fs::write(
+        output_dir.join("c_main.c"),
+        r#"
+#include <stdint.h>
+#include <stdio.h>
+
+uint64_t crane_add(uint64_t a, uint64_t b);
+
+int main(void) {
+    printf("%llu\n", (unsigned long long)crane_add(2, 3));
+    return 0;
+}
+        "#,
+    )
+    .unwrap();
+
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(output_dir.join("c_main"))
+        .arg(output_dir.join("c_main.c"))
+        .arg(output_dir.join("libcrane_add.a"))
+        .status()
+        .expect("Failed to run cc");
+    assert!(status.success());
+
+    let output = Command::new(output_dir.join("c_main")).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n");
+}
@@ -0,0 +1,24 @@
+mod common;
+
+#[test]
+fn test_string_equality() {
+    let output = common::compile_and_run(
+        "strings",
+        r#"
+use std::int::int_to_string
+use std::io::println
+use std::string::string_eq
+
+fn main() {
+    println(int_to_string(string_eq("crane", "crane") as Uint64))
+    println(int_to_string(string_eq("crane", "heron") as Uint64))
+    println(int_to_string(string_eq("42", int_to_string(42)) as Uint64))
+    let is_ne = "crane" != "heron"
+    println(int_to_string(is_ne as Uint64))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n0\n1\n1\n");
+}
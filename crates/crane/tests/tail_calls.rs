@@ -0,0 +1,27 @@
+mod common;
+
+#[test]
+fn test_deep_tail_recursion() {
+    let output = common::compile_and_run(
+        "tail_calls",
+        r#"
+use std::int::int_to_string
+use std::io::println
+
+fn countdown(n: Uint64, steps: Uint64) -> Uint64 {
+    if n == 0 {
+        steps
+    } else {
+        countdown(n - 1, steps + 1)
+    }
+}
+
+fn main() {
+    println(int_to_string(countdown(1000000, 0)))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1000000\n");
+}
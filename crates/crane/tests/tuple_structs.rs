@@ -0,0 +1,42 @@
+mod common;
+
+#[test]
+fn test_tuple_structs() {
+    let output = common::compile_and_run(
+        "tuple_structs",
+        r#"
+use std::float::float_to_string
+use std::int::int_to_string
+use std::io::println
+
+struct Meters(Float64)
+
+struct UserId(Uint64)
+
+impl UserId {
+    fn next(self: UserId) -> UserId {
+        let UserId(id) = self
+        UserId(id + 1)
+    }
+}
+
+fn total(a: Meters, b: Meters) -> Meters {
+    let Meters(x) = a
+    let Meters(y) = b
+    Meters(x + y)
+}
+
+fn main() {
+    let id = UserId(41)
+    let UserId(next) = id.next()
+    println(int_to_string(next))
+
+    let Meters(distance) = total(Meters(1.5), Meters(2.25))
+    println(float_to_string(distance))
+}
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n3.75\n");
+}
@@ -0,0 +1,406 @@
+//! The ui test suite, which compiles every `.crane` file under `tests/ui` and
+//! compares what it writes against the expected output stored next to it.
+//!
+//! The comments on the first lines of a fixture may hold a directive:
+//!
+//! - `// check-pass`: the fixture type checks, although it may have warnings.
+//! - `// run-pass`: the fixture compiles, and running it succeeds.
+//!
+//! Without one, the fixture is expected to fail to type check.
+//!
+//! The diagnostics are compared against `<name>.stderr`, and what a `run-pass`
+//! fixture prints against `<name>.stdout`, where a missing file expects no
+//! output at all. Paths in the output are replaced with `$DIR`, for the
+//! directory of the fixture, and `$TMP`, for the directory it's built in.
+//!
+//! Running the suite with `BLESS=1` writes the actual output of every fixture
+//! as its expected output.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crane::compiler::{CompileOptions, CompileParams, Compiler, Input};
+use inkwell::targets::TargetMachine;
+
+/// How long a `run-pass` fixture may run before it's considered hung.
+const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a fixture is expected to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    CheckFail,
+    CheckPass,
+    RunPass,
+}
+
+impl Mode {
+    /// Reads the mode from the directives in the leading comments of a fixture.
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut mode = None;
+
+        for line in source.lines() {
+            let Some(comment) = line.strip_prefix("//") else {
+                break;
+            };
+
+            // Doc comments document the code that follows them.
+            if comment.starts_with('/') {
+                break;
+            }
+
+            let directive = match comment.trim() {
+                "check-pass" => Self::CheckPass,
+                "run-pass" => Self::RunPass,
+                directive => return Err(format!("unknown directive `{directive}`")),
+            };
+
+            if mode.replace(directive).is_some() {
+                return Err("more than one mode directive".to_string());
+            }
+        }
+
+        Ok(mode.unwrap_or(Self::CheckFail))
+    }
+}
+
+/// The output of a fixture, once normalized.
+struct Output {
+    stderr: String,
+    stdout: Option<String>,
+}
+
+#[test]
+fn test_ui() {
+    let ui_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("ui");
+    let bless = std::env::var_os("BLESS").is_some_and(|bless| bless == "1");
+
+    let mut fixtures = Vec::new();
+    let mut expectations = Vec::new();
+    collect_files(&ui_dir, &mut fixtures, &mut expectations);
+
+    assert!(
+        !fixtures.is_empty(),
+        "no fixtures in `{}`",
+        ui_dir.display()
+    );
+
+    let mut failures = Vec::new();
+
+    for fixture in &fixtures {
+        let name = fixture.strip_prefix(&ui_dir).unwrap().display().to_string();
+
+        if let Err(message) = run_fixture(fixture, bless) {
+            failures.push(format!("{name}: {message}"));
+        }
+    }
+
+    // Expected output left behind by a fixture that was renamed or removed
+    // would otherwise never be checked.
+    for expectation in expectations {
+        if !expectation.with_extension("crane").is_file() {
+            if bless {
+                std::fs::remove_file(&expectation).unwrap();
+            } else {
+                failures.push(format!(
+                    "{}: no fixture for this expected output",
+                    expectation.strip_prefix(&ui_dir).unwrap().display()
+                ));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} ui tests failed:\n\n{}\n\nRun with `BLESS=1` to update the expected output.",
+        failures.len(),
+        fixtures.len(),
+        failures.join("\n\n")
+    );
+}
+
+/// Collects the fixtures and expected output files in the given directory and
+/// those nested in it, in a stable order.
+fn collect_files(dir: &Path, fixtures: &mut Vec<PathBuf>, expectations: &mut Vec<PathBuf>) {
+    let mut entries = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, fixtures, expectations);
+            continue;
+        }
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("crane") => fixtures.push(path),
+            Some("stderr" | "stdout") => expectations.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Runs the given fixture in the mode it asks for, and compares its output
+/// against the expected output.
+fn run_fixture(fixture: &Path, bless: bool) -> Result<(), String> {
+    let source = std::fs::read_to_string(fixture).unwrap();
+    let mode = Mode::parse(&source)?;
+
+    let output = match mode {
+        Mode::CheckFail | Mode::CheckPass => check(fixture, mode)?,
+        Mode::RunPass => compile_and_run(fixture)?,
+    };
+
+    let mut mismatches = Vec::new();
+
+    mismatches.extend(compare(
+        &fixture.with_extension("stderr"),
+        &output.stderr,
+        bless,
+    ));
+
+    if let Some(stdout) = &output.stdout {
+        mismatches.extend(compare(&fixture.with_extension("stdout"), stdout, bless));
+    } else if fixture.with_extension("stdout").is_file() {
+        mismatches.push("only `run-pass` fixtures can have a `.stdout` file".to_string());
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches.join("\n"))
+    }
+}
+
+/// Type checks the given fixture, which is expected to fail for
+/// [`Mode::CheckFail`] and succeed for [`Mode::CheckPass`].
+fn check(fixture: &Path, mode: Mode) -> Result<Output, String> {
+    let mut compiler = Compiler::new();
+
+    let params = CompileParams {
+        input: Input::File(fixture.to_path_buf()),
+        options: CompileOptions::default(),
+    };
+
+    let mut stderr = Vec::new();
+
+    let result = compiler.check(&mut stderr, params);
+
+    let stderr = normalize(&stderr, fixture, None);
+
+    match (mode, result) {
+        (Mode::CheckFail, Ok(())) => Err(format!(
+            "expected type checking to fail, but it succeeded:\n{stderr}"
+        )),
+        (Mode::CheckPass, Err(failure)) => Err(format!(
+            "expected type checking to succeed, but it failed with {failure:?}:\n{stderr}"
+        )),
+        _ => Ok(Output {
+            stderr,
+            stdout: None,
+        }),
+    }
+}
+
+/// Compiles the given fixture to an executable for the host, and runs it.
+fn compile_and_run(fixture: &Path) -> Result<Output, String> {
+    let name = fixture.file_stem().unwrap().to_string_lossy().into_owned();
+
+    // Fixtures in different directories may share a name.
+    let topic = fixture
+        .parent()
+        .and_then(Path::file_name)
+        .unwrap_or_default();
+
+    let output_dir = std::env::temp_dir()
+        .join("crane-ui-tests")
+        .join(topic)
+        .join(&name);
+    let _ = std::fs::remove_dir_all(&output_dir);
+
+    let mut compiler = Compiler::new();
+
+    let params = CompileParams {
+        input: Input::File(fixture.to_path_buf()),
+        options: CompileOptions {
+            target_triple: TargetMachine::get_default_triple()
+                .as_str()
+                .to_string_lossy()
+                .into_owned(),
+            output_dir: output_dir.clone(),
+            ..CompileOptions::default()
+        },
+    };
+
+    let mut stderr = Vec::new();
+
+    let result = compiler.compile(&mut stderr, params);
+
+    let stderr = normalize(&stderr, fixture, Some(&output_dir));
+
+    if let Err(failure) = result {
+        return Err(format!(
+            "expected compiling to succeed, but it failed with {failure:?}:\n{stderr}"
+        ));
+    }
+
+    let executable = output_dir.join(&name);
+
+    let mut child = Command::new(&executable)
+        .current_dir(&output_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run `{}`: {err}", executable.display()))?;
+
+    // The pipes are drained as the program runs, so that it can't block on a
+    // full pipe while we wait for it to exit.
+    let mut child_stdout = child.stdout.take().unwrap();
+    let mut child_stderr = child.stderr.take().unwrap();
+
+    let stdout_reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        std::io::copy(&mut child_stdout, &mut output).map(|_| output)
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        std::io::copy(&mut child_stderr, &mut output).map(|_| output)
+    });
+
+    let started = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+
+        if started.elapsed() > RUN_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return Err(format!(
+                "the program didn't exit within {} seconds",
+                RUN_TIMEOUT.as_secs()
+            ));
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let stdout = stdout_reader.join().unwrap().unwrap();
+    let program_stderr = stderr_reader.join().unwrap().unwrap();
+
+    if !status.success() {
+        return Err(format!(
+            "expected the program to succeed, but it exited with {status}:\n{}",
+            normalize(&program_stderr, fixture, Some(&output_dir))
+        ));
+    }
+
+    Ok(Output {
+        stderr,
+        stdout: Some(normalize(&stdout, fixture, Some(&output_dir))),
+    })
+}
+
+/// Makes the given output independent of the machine it was produced on, by
+/// removing colors and replacing the paths that differ between machines.
+fn normalize(output: &[u8], fixture: &Path, output_dir: Option<&Path>) -> String {
+    let output = strip_ansi_escapes::strip(output).unwrap();
+    let mut output = String::from_utf8_lossy(&output).replace("\r\n", "\n");
+
+    // The output directory is replaced first, in case it's inside the
+    // directory of the fixture.
+    let replacements = output_dir
+        .map(|output_dir| (output_dir, "$TMP"))
+        .into_iter()
+        .chain(fixture.parent().map(|dir| (dir, "$DIR")));
+
+    for (path, placeholder) in replacements {
+        output = output.replace(&*path.to_string_lossy(), placeholder);
+    }
+
+    output.replace("$DIR\\", "$DIR/").replace("$TMP\\", "$TMP/")
+}
+
+/// Compares the actual output against the expected output in the given file,
+/// returning a description of how they differ, if they do.
+///
+/// When blessing, the actual output is written to the file instead, and an
+/// empty output removes it.
+fn compare(expected_path: &Path, actual: &str, bless: bool) -> Option<String> {
+    let extension = expected_path.extension().unwrap().to_string_lossy();
+
+    if bless {
+        if actual.is_empty() {
+            if expected_path.is_file() {
+                std::fs::remove_file(expected_path).unwrap();
+            }
+        } else {
+            std::fs::write(expected_path, actual).unwrap();
+        }
+
+        return None;
+    }
+
+    let expected = std::fs::read_to_string(expected_path)
+        .unwrap_or_default()
+        .replace("\r\n", "\n");
+
+    if expected == actual {
+        return None;
+    }
+
+    Some(format!(
+        "the {extension} differs from the expected output:\n{}",
+        diff_lines(&expected, actual)
+    ))
+}
+
+/// Returns a line diff of the expected and actual output, where lines only in
+/// the expected output are prefixed with `-` and those only in the actual
+/// output with `+`.
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // The length of the longest common subsequence of the lines from each
+    // index onwards.
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            diff.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || common[i][j + 1] >= common[i + 1][j])
+        {
+            diff.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        } else {
+            diff.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        }
+    }
+
+    diff
+}